@@ -4,11 +4,124 @@ use ark_ec::{
     AffineRepr, CurveGroup,
 };
 
+use ark_ff::PrimeField;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::{ops::Mul, UniformRand};
 use rand::{CryptoRng, RngCore};
 use sha3::digest::{ExtendableOutput, Update, XofReader};
-use sha3::Shake256;
+use sha3::{Digest, Shake256};
+use std::io;
+
+use crate::sign::CHALLENGE_SIZE;
+
+/// The hash function used for signature challenges by the curves in this workspace.
+pub type DefaultHash = sha2::Sha512;
+
+/// append_to_hash. This function absorbs a labelled value into `hasher`. Both
+/// the label and the value are length-prefixed, so that distinct sequences of
+/// calls never produce the same input to the hash function.
+/// # Arguments
+/// * `hasher` - the hash state.
+/// * `label` - the label of the value.
+/// * `bytes` - the value.
+pub fn append_to_hash<D: Digest>(hasher: &mut D, label: &[u8], bytes: &[u8]) {
+    Digest::update(hasher, (label.len() as u64).to_le_bytes());
+    Digest::update(hasher, label);
+    Digest::update(hasher, (bytes.len() as u64).to_le_bytes());
+    Digest::update(hasher, bytes);
+}
+
+/// expand_hash. This function produces `len` bytes from the hash state by
+/// hashing it with a little-endian 32-bit counter appended, for as many
+/// counter values as needed.
+/// # Arguments
+/// * `hasher` - the hash state.
+/// * `len` - the number of bytes to produce.
+pub fn expand_hash<D: Digest + Clone>(hasher: &D, len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(len + <D as Digest>::output_size());
+    let mut counter = 0u32;
+    while out.len() < len {
+        let mut block = hasher.clone();
+        Digest::update(&mut block, counter.to_le_bytes());
+        out.extend_from_slice(&block.finalize());
+        counter += 1;
+    }
+
+    out.truncate(len);
+    out
+}
+
+/// AuxHasher. This struct hashes auxiliary data that is too large to pass to the signature as
+/// a single slice, e.g. a policy document or megabyte-sized metadata. The data is fed in chunks
+/// with `update`, or from a reader with `std::io::copy`, and is never buffered. The resulting
+/// `AuxDigest` is bound into the signature by `SigChall::challenge_with_aux`,
+/// `SigSign::sign_with_aux` and `SigVerify::verify_with_aux`, so each party only reads the data
+/// once, however it is chunked.
+pub struct AuxHasher<A: ACLConfig> {
+    hasher: A::Hash,
+    len: u64,
+}
+
+impl<A: ACLConfig> Default for AuxHasher<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: ACLConfig> AuxHasher<A> {
+    /// new. This function creates a hasher that has not been fed any data.
+    pub fn new() -> Self {
+        let mut hasher = A::Hash::new();
+        append_to_hash(&mut hasher, b"dom-sep", b"acl-aux");
+        Self { hasher, len: 0 }
+    }
+
+    /// update. This function feeds the next chunk of the auxiliary data.
+    /// # Arguments
+    /// * `chunk` - the next bytes of the data.
+    pub fn update(&mut self, chunk: &[u8]) {
+        Digest::update(&mut self.hasher, chunk);
+        self.len += chunk.len() as u64;
+    }
+
+    /// finalize. This function returns the digest of all the data fed so far. The length of
+    /// the data is absorbed last, since it is not known up front.
+    pub fn finalize(mut self) -> AuxDigest {
+        Digest::update(&mut self.hasher, self.len.to_le_bytes());
+        AuxDigest(self.hasher.finalize().to_vec())
+    }
+}
+
+impl<A: ACLConfig> io::Write for AuxHasher<A> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// AuxDigest. The digest of auxiliary data, as produced by `AuxHasher::finalize`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuxDigest(Vec<u8>);
+
+impl AuxDigest {
+    /// as_bytes. This function returns the bytes of the digest.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// append_aux. This function absorbs the digest of the auxiliary data, if any, into a
+/// signature challenge `hasher`, after the message. Without auxiliary data, the challenge is
+/// the same as before auxiliary data was supported.
+pub(crate) fn append_aux<D: Digest>(hasher: &mut D, aux: Option<&AuxDigest>) {
+    if let Some(aux) = aux {
+        append_to_hash(hasher, b"aux", aux.as_bytes());
+    }
+}
 
 pub trait ACLConfig: SWCurveConfig {
     /// The curve type that maps to this Config.
@@ -24,6 +137,42 @@ pub trait ACLConfig: SWCurveConfig {
 
     /// Second generator that's used in Pedersen commitments. Corresponds to H.
     const GENERATOR2: sw::Affine<Self>;
+
+    /// The hash function used to derive signature challenges. This is
+    /// `DefaultHash` (SHA-512) for all curves in this workspace, but any
+    /// `Digest` (e.g. SHA3-512 or BLAKE2b) can be used instead.
+    type Hash: Digest + Clone;
+
+    /// This function accepts a challenge slice (produced by a transcript)
+    /// and converts it into an element of Self::ScalarField. Only as many bytes
+    /// as are needed to encode a scalar are used, and the result is reduced
+    /// modulo the group order, so curves whose scalars are wider than the
+    /// challenge buffer are also supported.
+    ///
+    /// * `chal_buf` - a slice of bytes to be used to make an element of the ScalarField.
+    ///
+    /// Returns a scalar field element.
+    fn make_challenge_from_buffer(chal_buf: &[u8]) -> <Self as CurveConfig>::ScalarField {
+        let size = (<Self as CurveConfig>::ScalarField::MODULUS_BIT_SIZE as usize).div_ceil(8);
+        <Self as CurveConfig>::ScalarField::from_le_bytes_mod_order(
+            &chal_buf[..size.min(chal_buf.len())],
+        )
+    }
+
+    /// This function finalises a signature challenge hash into an element of
+    /// Self::ScalarField. The hash output is expanded to the size of a scalar
+    /// plus SECPARAM bits (and to at least CHALLENGE_SIZE bytes), and the whole
+    /// expansion is reduced modulo the group order, so that the challenge is
+    /// statistically close to uniform on every curve.
+    ///
+    /// * `hasher` - the hash state holding the challenge input.
+    ///
+    /// Returns a scalar field element.
+    fn make_challenge_from_hash(hasher: &Self::Hash) -> <Self as CurveConfig>::ScalarField {
+        let size = (<Self as CurveConfig>::ScalarField::MODULUS_BIT_SIZE as usize).div_ceil(8);
+        let len = (size + Self::SECPARAM.div_ceil(8)).max(CHALLENGE_SIZE);
+        <Self as CurveConfig>::ScalarField::from_le_bytes_mod_order(&expand_hash(hasher, len))
+    }
 }
 
 /// ACL keypair.
@@ -0,0 +1,312 @@
+//!
+//! Module containing an escrow mode for the proof of signature.
+//!
+//! An `AuditableSigProof` is a `SigProof` that additionally ElGamal-encrypts the issuance tag of the
+//! signature to a designated auditor. The issuance tag is z1 = rand * G + comms, i.e the commitment
+//! that the signer saw (and can log) during issuance. Since zeta1 = gamma * z1 and b_gamma = gamma * G,
+//! the escrow proof shows that the ciphertext holds zeta1 / gamma, without revealing gamma.
+//!
+//! Only the holder of the auditor's secret key can decrypt the issuance tag, and hence link a
+//! spend to the run of the issuance protocol that produced it. The signer alone cannot.
+//!
+//! N.B The escrow relies on zeta1 = gamma * z1, which holds for every signature that is built
+//! with `SigChall::challenge` and `SigSign::sign`.
+//!
+
+use ark_ec::{
+    models::CurveConfig,
+    short_weierstrass::{self as sw, SWCurveConfig},
+    CurveGroup,
+};
+use ark_ff::Field;
+use rand::{CryptoRng, RngCore};
+
+use crate::config::ACLConfig;
+use crate::sign::{SigProof, SigSign};
+use crate::transcript::{append_message, challenge_bytes};
+use crate::verify::SigVerifProof;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{ops::Mul, UniformRand};
+use merlin::Transcript;
+
+/// AuditorKeyPair. This struct acts as a container for the ElGamal key pair of an auditor.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct AuditorKeyPair<A: ACLConfig> {
+    /// public_key: the key that issuance tags are encrypted to.
+    pub public_key: sw::Affine<A>,
+
+    /// Private component x
+    x: <A as CurveConfig>::ScalarField,
+}
+
+impl<A: ACLConfig> Clone for AuditorKeyPair<A> {
+    fn clone(&self) -> Self {
+        Self {
+            public_key: self.public_key,
+            x: self.x,
+        }
+    }
+}
+
+impl<A: ACLConfig> AuditorKeyPair<A> {
+    /// Generate a new auditor keypair
+    pub fn generate<T: RngCore + CryptoRng>(rng: &mut T) -> Self {
+        let x = <A as CurveConfig>::ScalarField::rand(rng);
+        Self {
+            public_key: (<A as SWCurveConfig>::GENERATOR.mul(x)).into_affine(),
+            x,
+        }
+    }
+
+    /// Auditor public key
+    pub const fn public_key(&self) -> &sw::Affine<A> {
+        &self.public_key
+    }
+
+    /// open. This function decrypts the issuance tag held by `escrow`. The caller should only
+    /// trust the result if the proof that carries `escrow` verifies.
+    /// # Arguments
+    /// * `escrow` - the escrowed issuance tag.
+    pub fn open(&self, escrow: &TagEscrow<A>) -> sw::Affine<A> {
+        (escrow.e1.mul(-self.x) + escrow.e2).into_affine()
+    }
+}
+
+/// TagEscrow. This struct acts as a container for the encrypted issuance tag, along with the
+/// proof that it was encrypted correctly.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct TagEscrow<A: ACLConfig> {
+    /// e1: the first part of the ciphertext, i.e k * G.
+    pub e1: sw::Affine<A>,
+    /// e2: the second part of the ciphertext, i.e z1 + k * Y.
+    pub e2: sw::Affine<A>,
+    /// t1: the commitment for e1.
+    pub t1: sw::Affine<A>,
+    /// t2: the commitment for e2.
+    pub t2: sw::Affine<A>,
+    /// t3: the commitment for the inverse of gamma.
+    pub t3: sw::Affine<A>,
+    /// a1: the response for k.
+    pub a1: <A as CurveConfig>::ScalarField,
+    /// a2: the response for the inverse of gamma.
+    pub a2: <A as CurveConfig>::ScalarField,
+}
+
+impl<A: ACLConfig> TagEscrow<A> {
+    /// make_transcript. This function adds the statement and the commitments of the escrow proof
+    /// to the `transcript`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn make_transcript(
+        transcript: &mut Transcript,
+        auditor_key: &sw::Affine<A>,
+        zeta1: &sw::Affine<A>,
+        b_gamma: &sw::Affine<A>,
+        e1: &sw::Affine<A>,
+        e2: &sw::Affine<A>,
+        t1: &sw::Affine<A>,
+        t2: &sw::Affine<A>,
+        t3: &sw::Affine<A>,
+    ) {
+        append_message(transcript, b"dom-sep", b"acl-escrow");
+
+        let mut compressed_bytes = Vec::new();
+        for (label, point) in [
+            (b"Y" as &'static [u8], auditor_key),
+            (b"zeta1", zeta1),
+            (b"b_gamma", b_gamma),
+            (b"e1", e1),
+            (b"e2", e2),
+            (b"t1", t1),
+            (b"t2", t2),
+            (b"t3", t3),
+        ] {
+            compressed_bytes.clear();
+            point.serialize_compressed(&mut compressed_bytes).unwrap();
+            append_message(transcript, label, &compressed_bytes[..]);
+        }
+    }
+
+    /// challenge. This function returns the challenge of the escrow proof.
+    #[allow(clippy::too_many_arguments)]
+    fn challenge(
+        auditor_key: &sw::Affine<A>,
+        zeta1: &sw::Affine<A>,
+        b_gamma: &sw::Affine<A>,
+        e1: &sw::Affine<A>,
+        e2: &sw::Affine<A>,
+        t1: &sw::Affine<A>,
+        t2: &sw::Affine<A>,
+        t3: &sw::Affine<A>,
+        context: Option<&[u8]>,
+    ) -> <A as CurveConfig>::ScalarField {
+        let label = b"Chall ACLEscrow";
+        let mut transcript = Transcript::new(label);
+        SigProof::<A>::make_context(&mut transcript, context);
+        Self::make_transcript(
+            &mut transcript,
+            auditor_key,
+            zeta1,
+            b_gamma,
+            e1,
+            e2,
+            t1,
+            t2,
+            t3,
+        );
+
+        let mut buf = [0u8; 64];
+        challenge_bytes(&mut transcript, b"challescrow", &mut buf);
+        A::make_challenge_from_buffer(&buf)
+    }
+
+    /// create. This function encrypts the issuance tag of `sig_m` to `auditor_key`, and proves
+    /// that the ciphertext holds zeta1 / gamma, where b_gamma = gamma * G.
+    /// # Arguments
+    /// * `rng` - the RNG that is used to produce the random values. Must be cryptographically secure.
+    /// * `auditor_key` - the public key of the auditor.
+    /// * `sig_m` - the signature, along with its opening.
+    /// * `b_gamma` - the value gamma * G.
+    /// * `context` - an optional domain label.
+    pub fn create<T: RngCore + CryptoRng>(
+        rng: &mut T,
+        auditor_key: &sw::Affine<A>,
+        sig_m: &SigSign<A>,
+        b_gamma: &sw::Affine<A>,
+        context: Option<&[u8]>,
+    ) -> Self {
+        let delta = sig_m
+            .opening
+            .gamma
+            .inverse()
+            .expect("ACL escrow: gamma must not be zero");
+        let zeta1 = sig_m.sigma.zeta1;
+        let k = <A as CurveConfig>::ScalarField::rand(rng);
+
+        // The ciphertext of zeta1 / gamma.
+        let e1 = (A::GENERATOR.mul(k)).into_affine();
+        let e2 = (zeta1.mul(delta) + auditor_key.mul(k)).into_affine();
+
+        let r1 = <A as CurveConfig>::ScalarField::rand(rng);
+        let r2 = <A as CurveConfig>::ScalarField::rand(rng);
+        let t1 = (A::GENERATOR.mul(r1)).into_affine();
+        let t2 = (zeta1.mul(r2) + auditor_key.mul(r1)).into_affine();
+        let t3 = (b_gamma.mul(r2)).into_affine();
+
+        let ch = Self::challenge(
+            auditor_key,
+            &zeta1,
+            b_gamma,
+            &e1,
+            &e2,
+            &t1,
+            &t2,
+            &t3,
+            context,
+        );
+
+        Self {
+            e1,
+            e2,
+            t1,
+            t2,
+            t3,
+            a1: r1 + k * ch,
+            a2: r2 + delta * ch,
+        }
+    }
+
+    /// verify. This function returns true if `self` holds an encryption of zeta1 / gamma to
+    /// `auditor_key`, where b_gamma = gamma * G, and false otherwise.
+    /// # Arguments
+    /// * `auditor_key` - the public key of the auditor.
+    /// * `zeta1` - the second part of the blinded commitment of the signature.
+    /// * `b_gamma` - the value gamma * G.
+    /// * `context` - an optional domain label.
+    pub fn verify(
+        &self,
+        auditor_key: &sw::Affine<A>,
+        zeta1: &sw::Affine<A>,
+        b_gamma: &sw::Affine<A>,
+        context: Option<&[u8]>,
+    ) -> bool {
+        let ch = Self::challenge(
+            auditor_key,
+            zeta1,
+            b_gamma,
+            &self.e1,
+            &self.e2,
+            &self.t1,
+            &self.t2,
+            &self.t3,
+            context,
+        );
+
+        A::GENERATOR.mul(self.a1) == self.t1 + self.e1.mul(ch)
+            && zeta1.mul(self.a2) + auditor_key.mul(self.a1) == self.t2 + self.e2.mul(ch)
+            && b_gamma.mul(self.a2) == self.t3 + A::GENERATOR.mul(ch)
+    }
+}
+
+/// AuditableSigProof. This struct acts as a container for a proof of signature that also escrows
+/// the issuance tag of the signature to an auditor.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AuditableSigProof<A: ACLConfig> {
+    /// proof: the proof of signature.
+    pub proof: SigProof<A>,
+    /// escrow: the encrypted issuance tag.
+    pub escrow: TagEscrow<A>,
+}
+
+impl<A: ACLConfig> AuditableSigProof<A> {
+    /// prove. This function produces a `SigProof` over `sig_m`, along with an escrow of its
+    /// issuance tag to `auditor_key`.
+    /// # Arguments
+    /// * `rng` - the RNG that is used to produce the random values. Must be cryptographically secure.
+    /// * `tag_key` - the tag key of the signer.
+    /// * `sig_m` - the signature, along with its opening.
+    /// * `vals` - the committed values.
+    /// * `gens` - the generators of the commitment.
+    /// * `comm_r` - the randomness of the commitment.
+    /// * `auditor_key` - the public key of the auditor.
+    /// * `context` - an optional domain label.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prove<T: RngCore + CryptoRng>(
+        rng: &mut T,
+        tag_key: sw::Affine<A>,
+        sig_m: &SigSign<A>,
+        vals: &[<A as CurveConfig>::ScalarField],
+        gens: &[sw::Affine<A>],
+        comm_r: <A as CurveConfig>::ScalarField,
+        auditor_key: &sw::Affine<A>,
+        context: Option<&[u8]>,
+    ) -> Self {
+        let proof = SigProof::prove(rng, tag_key, sig_m, vals, gens, comm_r, context);
+        let escrow = TagEscrow::create(rng, auditor_key, sig_m, &proof.b_gamma, context);
+        Self { proof, escrow }
+    }
+
+    /// verify. This function returns true if both the proof of signature and the escrow
+    /// proof held by `self` are valid, and false otherwise.
+    /// # Arguments
+    /// * `tag_key` - the tag key of the signer.
+    /// * `sig_m` - the signature.
+    /// * `gens` - the generators of the commitment.
+    /// * `auditor_key` - the public key of the auditor.
+    /// * `context` - an optional domain label.
+    pub fn verify(
+        &self,
+        tag_key: sw::Affine<A>,
+        sig_m: &SigSign<A>,
+        gens: &[sw::Affine<A>],
+        auditor_key: &sw::Affine<A>,
+        context: Option<&[u8]>,
+    ) -> bool {
+        SigVerifProof::verify(&self.proof, tag_key, sig_m, gens, context)
+            && self.escrow.verify(
+                auditor_key,
+                &sig_m.sigma.zeta1,
+                &self.proof.b_gamma,
+                context,
+            )
+    }
+}
@@ -1,4 +1,7 @@
 #![forbid(unsafe_code)]
 pub mod config;
+pub mod escrow;
+pub mod protocol;
 pub mod sign;
+mod transcript;
 pub mod verify;
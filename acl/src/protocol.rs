@@ -0,0 +1,257 @@
+//!
+//! Module containing the three-move signing protocol as explicit steps.
+//!
+//! The steps are the ones that `SigComm`, `SigChall`, `SigResp` and `SigSign` run, split between
+//! what each side keeps and what it sends, so that other protocols (e.g. anonymous surveys) can
+//! drive ACL signing directly, rather than only through the issuance flow of Boomerang:
+//!
+//! 1. The signer calls `commit` on the user's commitment, keeps the `SignerState` and sends the
+//!    `Commitment`.
+//! 2. The user calls `challenge` (or `challenge_with_aux`), keeps the `UserState` and sends the
+//!    `Challenge`.
+//! 3. The signer calls `respond`, which consumes the `SignerState`, and sends the `Response`.
+//! 4. The user calls `unblind`, which consumes the `UserState`, and gets a `SigSign` that
+//!    `SigVerify::verify` accepts.
+//!
+//! Unlike the lower-level types, the steps that take messages from the other side return a
+//! `ProtocolError` rather than panicking on a malformed message.
+//!
+//! N.B The states hold the secret nonces of their side, and must never be sent. Answering two
+//! challenges with the same nonces reveals the signing key, which is why `respond` consumes the
+//! `SignerState`, and why it cannot be cloned.
+//!
+
+use ark_ec::{models::CurveConfig, short_weierstrass as sw};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::{CryptoRng, RngCore};
+use std::fmt;
+
+use crate::config::{ACLConfig, AuxDigest, KeyPair};
+use crate::sign::{SigChall, SigSign};
+use crate::verify::{SigComm, SigResp};
+
+/// SignerState. This struct holds what the signer keeps between `commit` and `respond`.
+///
+/// N.B This holds the signer's nonces: it may be stored (e.g. between two requests), but it
+/// must never be sent to the user.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct SignerState<A: ACLConfig> {
+    comm: SigComm<A>,
+}
+
+/// Commitment. This struct is the first message of the protocol, which the signer sends.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct Commitment<A: ACLConfig> {
+    /// comms: the multi-commitment to the user's chosen values.
+    pub comms: sw::Affine<A>,
+    /// rand: the randomness that the signer adds to `comms`.
+    pub rand: <A as CurveConfig>::ScalarField,
+    /// a: the commitment to the signer's nonce for the signing key.
+    pub a: sw::Affine<A>,
+    /// a1: the first commitment to the signer's nonces for the tag.
+    pub a1: sw::Affine<A>,
+    /// a2: the second commitment to the signer's nonces for the tag.
+    pub a2: sw::Affine<A>,
+}
+
+// We need to implement these manually for generic structs.
+impl<A: ACLConfig> Copy for Commitment<A> {}
+impl<A: ACLConfig> Clone for Commitment<A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+/// Challenge. This struct is the second message of the protocol, which the user sends.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct Challenge<A: ACLConfig> {
+    /// e: the blinded challenge.
+    pub e: <A as CurveConfig>::ScalarField,
+}
+
+impl<A: ACLConfig> Copy for Challenge<A> {}
+impl<A: ACLConfig> Clone for Challenge<A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+/// Response. The third message of the protocol, which the signer sends.
+pub type Response<A> = SigResp<A>;
+
+/// UserState. This struct holds what the user keeps between `challenge` and `unblind`: the
+/// blinding values, and what the signature is bound to.
+///
+/// N.B This holds the user's blinding values, which link the signature to this run of the
+/// protocol: it must never be sent to the signer.
+pub struct UserState<A: ACLConfig> {
+    chall: SigChall<A>,
+    pub_key: sw::Affine<A>,
+    tag_key: sw::Affine<A>,
+    message: Vec<u8>,
+    context: Option<Vec<u8>>,
+    aux: Option<AuxDigest>,
+}
+
+/// ProtocolError. This enum describes why a message of the other side was rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProtocolError {
+    /// InvalidCommitment: the commitment has a zero `rand`, or points that are not on the curve.
+    InvalidCommitment,
+    /// InvalidResponse: the response does not answer the challenge under the signer's key.
+    InvalidResponse,
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::InvalidCommitment => write!(f, "ACL protocol: invalid commitment"),
+            ProtocolError::InvalidResponse => write!(f, "ACL protocol: invalid response"),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+/// commit. This function runs the first step of the signer, on the user's commitment `comm`.
+/// # Arguments
+/// * `keys` - the signer's keypair.
+/// * `rng` - the source of the signer's nonces.
+/// * `comm` - the multi-commitment to the values that the user wants signed.
+pub fn commit<A: ACLConfig, T: RngCore + CryptoRng>(
+    keys: &KeyPair<A>,
+    rng: &mut T,
+    comm: sw::Affine<A>,
+) -> (SignerState<A>, Commitment<A>) {
+    let comm = SigComm::commit(keys, rng, comm);
+    let commitment = Commitment {
+        comms: comm.comms,
+        rand: comm.rand,
+        a: comm.a,
+        a1: comm.a1,
+        a2: comm.a2,
+    };
+
+    (SignerState { comm }, commitment)
+}
+
+/// challenge. This function runs the step of the user, on the signer's `commitment`. This
+/// function fails if the commitment is malformed.
+/// # Arguments
+/// * `pub_key` - the signer's public key.
+/// * `tag_key` - the signer's tag key.
+/// * `rng` - the source of the user's blinding values.
+/// * `commitment` - the first message of the protocol.
+/// * `message` - the message being signed.
+/// * `context` - an optional domain label absorbed alongside the message.
+pub fn challenge<A: ACLConfig, T: RngCore + CryptoRng>(
+    pub_key: sw::Affine<A>,
+    tag_key: sw::Affine<A>,
+    rng: &mut T,
+    commitment: &Commitment<A>,
+    message: &[u8],
+    context: Option<&[u8]>,
+) -> Result<(UserState<A>, Challenge<A>), ProtocolError> {
+    challenge_with(pub_key, tag_key, rng, commitment, message, context, None)
+}
+
+/// challenge_with_aux. This function runs the step of the user as `challenge` does, and also
+/// binds the auxiliary data hashed into `aux` into the signature.
+/// # Arguments
+/// * `pub_key` - the signer's public key.
+/// * `tag_key` - the signer's tag key.
+/// * `rng` - the source of the user's blinding values.
+/// * `commitment` - the first message of the protocol.
+/// * `message` - the message being signed.
+/// * `context` - an optional domain label absorbed alongside the message.
+/// * `aux` - the digest of the auxiliary data (see `AuxHasher`).
+pub fn challenge_with_aux<A: ACLConfig, T: RngCore + CryptoRng>(
+    pub_key: sw::Affine<A>,
+    tag_key: sw::Affine<A>,
+    rng: &mut T,
+    commitment: &Commitment<A>,
+    message: &[u8],
+    context: Option<&[u8]>,
+    aux: &AuxDigest,
+) -> Result<(UserState<A>, Challenge<A>), ProtocolError> {
+    challenge_with(
+        pub_key,
+        tag_key,
+        rng,
+        commitment,
+        message,
+        context,
+        Some(aux),
+    )
+}
+
+fn challenge_with<A: ACLConfig, T: RngCore + CryptoRng>(
+    pub_key: sw::Affine<A>,
+    tag_key: sw::Affine<A>,
+    rng: &mut T,
+    commitment: &Commitment<A>,
+    message: &[u8],
+    context: Option<&[u8]>,
+    aux: Option<&AuxDigest>,
+) -> Result<(UserState<A>, Challenge<A>), ProtocolError> {
+    let mut comm_m = SigComm::default();
+    comm_m.comms = commitment.comms;
+    comm_m.rand = commitment.rand;
+    comm_m.a = commitment.a;
+    comm_m.a1 = commitment.a1;
+    comm_m.a2 = commitment.a2;
+
+    if !SigChall::is_valid_commitment(&comm_m) {
+        return Err(ProtocolError::InvalidCommitment);
+    }
+
+    let chall = SigChall::challenge_with(tag_key, pub_key, rng, comm_m, message, context, aux);
+    let e = chall.e;
+    let state = UserState {
+        chall,
+        pub_key,
+        tag_key,
+        message: message.to_vec(),
+        context: context.map(|context| context.to_vec()),
+        aux: aux.cloned(),
+    };
+
+    Ok((state, Challenge { e }))
+}
+
+/// respond. This function runs the second step of the signer, on the user's `challenge`. The
+/// `state` is consumed, so that its nonces cannot answer a second challenge.
+/// # Arguments
+/// * `keys` - the signer's keypair.
+/// * `state` - the state that `commit` returned.
+/// * `challenge` - the second message of the protocol.
+pub fn respond<A: ACLConfig>(
+    keys: &KeyPair<A>,
+    state: SignerState<A>,
+    challenge: &Challenge<A>,
+) -> Response<A> {
+    let mut chall_m = SigChall::default();
+    chall_m.e = challenge.e;
+    SigResp::respond(keys, &state.comm, &chall_m)
+}
+
+/// unblind. This function runs the last step of the user, on the signer's `response`, and
+/// returns the signature. This function fails if the response does not answer the challenge.
+/// # Arguments
+/// * `state` - the state that `challenge` returned.
+/// * `response` - the third message of the protocol.
+pub fn unblind<A: ACLConfig>(
+    state: UserState<A>,
+    response: &Response<A>,
+) -> Result<SigSign<A>, ProtocolError> {
+    SigSign::try_sign_with(
+        state.pub_key,
+        state.tag_key,
+        &state.chall,
+        response,
+        &state.message,
+        state.context.as_deref(),
+        state.aux.as_ref(),
+    )
+    .ok_or(ProtocolError::InvalidResponse)
+}
@@ -9,11 +9,13 @@ use ark_ec::{
 };
 use rand::{CryptoRng, RngCore};
 
-use crate::config::ACLConfig;
+use crate::config::{append_aux, append_to_hash, ACLConfig, AuxDigest};
+use crate::transcript::{append_message, challenge_bytes};
 use crate::verify::{SigComm, SigResp};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::{ops::Mul, UniformRand, Zero};
 use merlin::Transcript;
+use sha3::Digest;
 use std::default::Default;
 
 pub const CHALLENGE_SIZE: usize = 64;
@@ -78,54 +80,96 @@ impl<P: ACLConfig> Default for SigChall<P> {
 impl<A: ACLConfig> SigChall<A> {
     #[allow(clippy::too_many_arguments)]
     pub fn make_transcript(
-        transcript: &mut Transcript,
+        hasher: &mut A::Hash,
         c1: &sw::Affine<A>,
         c2: &sw::Affine<A>,
         c3: &sw::Affine<A>,
         c4: &sw::Affine<A>,
         c5: &sw::Affine<A>,
         c6: &sw::Affine<A>,
-        message: &str,
+        message: &[u8],
+        context: Option<&[u8]>,
     ) {
-        transcript.append_message(b"dom-sep", b"acl-challenge");
+        append_to_hash(hasher, b"dom-sep", b"acl-challenge");
 
         let mut compressed_bytes = Vec::new();
         c1.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c1", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c1", &compressed_bytes[..]);
 
         c2.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c2", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c2", &compressed_bytes[..]);
 
         c3.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c3", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c3", &compressed_bytes[..]);
 
         c4.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c4", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c4", &compressed_bytes[..]);
 
         c5.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c5", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c5", &compressed_bytes[..]);
 
         c6.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c6", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c6", &compressed_bytes[..]);
 
-        transcript.append_message(b"message", message.as_bytes());
+        if let Some(context) = context {
+            append_to_hash(hasher, b"context", context);
+        }
+        append_to_hash(hasher, b"message", message);
     }
 
     /// challenge. This function creates the second signature message.
     /// # Arguments
     /// * `inter` - the intermediate values to use.
+    /// * `message` - the message being signed.
+    /// * `context` - an optional domain label absorbed alongside the message.
     pub fn challenge<T: RngCore + CryptoRng>(
         tag_key: sw::Affine<A>,
         pub_key: sw::Affine<A>,
         rng: &mut T,
         comm_m: SigComm<A>,
-        message: &str,
+        message: &[u8],
+        context: Option<&[u8]>,
+    ) -> SigChall<A> {
+        Self::challenge_with(tag_key, pub_key, rng, comm_m, message, context, None)
+    }
+
+    /// challenge_with_aux. This function creates the second signature message as `challenge`
+    /// does, and also binds the auxiliary data hashed into `aux` into the signature.
+    /// # Arguments
+    /// * `message` - the message being signed.
+    /// * `context` - an optional domain label absorbed alongside the message.
+    /// * `aux` - the digest of the auxiliary data (see `AuxHasher`).
+    pub fn challenge_with_aux<T: RngCore + CryptoRng>(
+        tag_key: sw::Affine<A>,
+        pub_key: sw::Affine<A>,
+        rng: &mut T,
+        comm_m: SigComm<A>,
+        message: &[u8],
+        context: Option<&[u8]>,
+        aux: &AuxDigest,
+    ) -> SigChall<A> {
+        Self::challenge_with(tag_key, pub_key, rng, comm_m, message, context, Some(aux))
+    }
+
+    /// is_valid_commitment. This function checks that the first signature message can be
+    /// challenged, i.e that its `rand` is not zero and that its points are on the curve.
+    pub(crate) fn is_valid_commitment(comm_m: &SigComm<A>) -> bool {
+        !comm_m.rand.is_zero()
+            && comm_m.a.is_on_curve()
+            && comm_m.a1.is_on_curve()
+            && comm_m.a2.is_on_curve()
+    }
+
+    pub(crate) fn challenge_with<T: RngCore + CryptoRng>(
+        tag_key: sw::Affine<A>,
+        pub_key: sw::Affine<A>,
+        rng: &mut T,
+        comm_m: SigComm<A>,
+        message: &[u8],
+        context: Option<&[u8]>,
+        aux: Option<&AuxDigest>,
     ) -> SigChall<A> {
-        if comm_m.rand.is_zero()
-            || !comm_m.a.is_on_curve()
-            || !comm_m.a1.is_on_curve()
-            || !comm_m.a2.is_on_curve()
-        {
+        if !Self::is_valid_commitment(&comm_m) {
             panic!("Failed to create signature challenge: params are incorrect");
         } else {
             let z1 = (A::GENERATOR.mul(comm_m.rand) + comm_m.comms).into_affine();
@@ -150,10 +194,9 @@ impl<A: ACLConfig> SigChall<A> {
             let alpha2 =
                 (comm_m.a2.mul(gamma) + A::GENERATOR2.mul(t5) + zeta2.mul(t4)).into_affine();
 
-            let label = b"Chall ACL";
-            let mut transcript_v = Transcript::new(label);
+            let mut hasher = A::Hash::new();
             Self::make_transcript(
-                &mut transcript_v,
+                &mut hasher,
                 &zeta,
                 &zeta1,
                 &alpha,
@@ -161,13 +204,11 @@ impl<A: ACLConfig> SigChall<A> {
                 &alpha2,
                 &mu,
                 message,
+                context,
             );
+            append_aux(&mut hasher, aux);
 
-            let mut buf = [0u8; CHALLENGE_SIZE];
-            let _ = &transcript_v.challenge_bytes(b"chall", &mut buf);
-
-            let epsilon: <A as CurveConfig>::ScalarField =
-                <A as CurveConfig>::ScalarField::deserialize_compressed(&buf[..]).unwrap();
+            let epsilon: <A as CurveConfig>::ScalarField = A::make_challenge_from_hash(&hasher);
             let e = epsilon - t2 - t4;
 
             Self {
@@ -243,12 +284,17 @@ impl<A: ACLConfig> Clone for Opening<A> {
 }
 
 /// SigSign. This struct acts as a container for the fourth message (the signature) of the Signature.
+///
+/// N.B ACL signatures are one-show, and cannot be re-randomized. Scaling `zeta` and `zeta1` by
+/// a new blinding factor would change the challenge hash, and the `rho`/`omega` responses can
+/// only be recomputed with the signer's secret key. Repeated presentations of the same signature
+/// are therefore linkable, which is why Boomerang replaces the token on every interaction.
 #[derive(CanonicalSerialize, CanonicalDeserialize)]
 pub struct SigSign<A: ACLConfig> {
     /// sigma: the signature itself.
     pub sigma: Signature<A>,
     /// opening: the opening values.
-    opening: Opening<A>,
+    pub(crate) opening: Opening<A>,
 }
 
 impl<A: ACLConfig> Clone for SigSign<A> {
@@ -263,46 +309,102 @@ impl<A: ACLConfig> Clone for SigSign<A> {
 impl<A: ACLConfig> SigSign<A> {
     #[allow(clippy::too_many_arguments)]
     pub fn make_transcript(
-        transcript: &mut Transcript,
+        hasher: &mut A::Hash,
         c1: &sw::Affine<A>,
         c2: &sw::Affine<A>,
         c3: &sw::Affine<A>,
         c4: &sw::Affine<A>,
         c5: &sw::Affine<A>,
         c6: &sw::Affine<A>,
-        message: &str,
+        message: &[u8],
+        context: Option<&[u8]>,
     ) {
-        transcript.append_message(b"dom-sep", b"acl-challenge");
+        append_to_hash(hasher, b"dom-sep", b"acl-challenge");
 
         let mut compressed_bytes = Vec::new();
         c1.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c1", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c1", &compressed_bytes[..]);
 
         c2.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c2", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c2", &compressed_bytes[..]);
 
         c3.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c3", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c3", &compressed_bytes[..]);
 
         c4.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c4", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c4", &compressed_bytes[..]);
 
         c5.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c5", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c5", &compressed_bytes[..]);
 
         c6.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c6", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c6", &compressed_bytes[..]);
 
-        transcript.append_message(b"message", message.as_bytes());
+        if let Some(context) = context {
+            append_to_hash(hasher, b"context", context);
+        }
+        append_to_hash(hasher, b"message", message);
     }
 
+    /// sign. This function creates the signature from the challenge and the response.
+    /// The `message` and `context` must match the ones used in `SigChall::challenge`.
     pub fn sign(
         pub_key: sw::Affine<A>,
         tag_key: sw::Affine<A>,
         chall_m: &SigChall<A>,
         resp_m: &SigResp<A>,
-        message: &str,
+        message: &[u8],
+        context: Option<&[u8]>,
+    ) -> SigSign<A> {
+        Self::sign_with(pub_key, tag_key, chall_m, resp_m, message, context, None)
+    }
+
+    /// sign_with_aux. This function creates the signature as `sign` does, for a challenge that
+    /// was created by `SigChall::challenge_with_aux` with the same `aux`.
+    pub fn sign_with_aux(
+        pub_key: sw::Affine<A>,
+        tag_key: sw::Affine<A>,
+        chall_m: &SigChall<A>,
+        resp_m: &SigResp<A>,
+        message: &[u8],
+        context: Option<&[u8]>,
+        aux: &AuxDigest,
+    ) -> SigSign<A> {
+        Self::sign_with(
+            pub_key,
+            tag_key,
+            chall_m,
+            resp_m,
+            message,
+            context,
+            Some(aux),
+        )
+    }
+
+    fn sign_with(
+        pub_key: sw::Affine<A>,
+        tag_key: sw::Affine<A>,
+        chall_m: &SigChall<A>,
+        resp_m: &SigResp<A>,
+        message: &[u8],
+        context: Option<&[u8]>,
+        aux: Option<&AuxDigest>,
     ) -> SigSign<A> {
+        Self::try_sign_with(pub_key, tag_key, chall_m, resp_m, message, context, aux)
+            .expect("Failed to create a signature")
+    }
+
+    /// try_sign_with. This function creates the signature as `sign_with` does, but returns
+    /// `None` rather than panicking if the response does not match the challenge.
+    pub(crate) fn try_sign_with(
+        pub_key: sw::Affine<A>,
+        tag_key: sw::Affine<A>,
+        chall_m: &SigChall<A>,
+        resp_m: &SigResp<A>,
+        message: &[u8],
+        context: Option<&[u8]>,
+        aux: Option<&AuxDigest>,
+    ) -> Option<SigSign<A>> {
         let rho = resp_m.r + chall_m.t1;
         let omega = resp_m.c + chall_m.t2;
         let rho1 = chall_m.gamma * resp_m.r1 + chall_m.t3;
@@ -315,10 +417,9 @@ impl<A: ACLConfig> SigSign<A> {
         let tmp3 = (A::GENERATOR2.mul(rho2) + chall_m.zeta2.mul(omega1)).into_affine();
         let tmp4 = (tag_key.mul(v) + chall_m.zeta.mul(omega1)).into_affine();
 
-        let label = b"Chall ACL";
-        let mut transcript_v = Transcript::new(label);
+        let mut hasher = A::Hash::new();
         Self::make_transcript(
-            &mut transcript_v,
+            &mut hasher,
             &chall_m.zeta,
             &chall_m.zeta1,
             &tmp1,
@@ -326,18 +427,16 @@ impl<A: ACLConfig> SigSign<A> {
             &tmp3,
             &tmp4,
             message,
+            context,
         );
+        append_aux(&mut hasher, aux);
 
-        let mut buf = [0u8; CHALLENGE_SIZE];
-        let _ = &transcript_v.challenge_bytes(b"chall", &mut buf);
-
-        let epsilon: <A as CurveConfig>::ScalarField =
-            <A as CurveConfig>::ScalarField::deserialize_compressed(&buf[..]).unwrap();
+        let epsilon: <A as CurveConfig>::ScalarField = A::make_challenge_from_hash(&hasher);
 
         let e = omega + omega1;
 
         if e != epsilon {
-            panic!("Failed to create a signature");
+            None
         } else {
             let sigma = Signature {
                 zeta: chall_m.zeta,
@@ -355,7 +454,7 @@ impl<A: ACLConfig> SigSign<A> {
                 rand: chall_m.rand,
             };
 
-            Self { sigma, opening }
+            Some(Self { sigma, opening })
         }
     }
 }
@@ -365,25 +464,26 @@ pub struct SubVals<A: ACLConfig> {
     pub pos: Vec<usize>,
 }
 
-/// DLogProof. This struct acts as a container for the opening proof.
+/// DLogProof. This struct acts as a container for the proof of equality of discrete logarithms.
+/// The proof is compressed: it holds the challenge rather than the commitments `t1` and `t2`,
+/// which the verifier recomputes from the challenge and the response.
 #[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct SigProofD<A: ACLConfig> {
-    /// t2: the second proof.
-    pub t1: sw::Affine<A>,
-    /// t2: the second proof.
-    pub t2: sw::Affine<A>,
-    /// b_gamma: the first message value.
+    /// ch: the challenge.
+    pub ch: <A as CurveConfig>::ScalarField,
+    /// a1: the response.
     pub a1: <A as CurveConfig>::ScalarField,
 }
 
-/// OpeningProof. This struct acts as a container for the opening proof.
+/// OpeningProof. This struct acts as a container for the opening proof. Like `SigProofD`, it
+/// holds the challenge rather than the commitment `t3`.
 #[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct SigProofO<A: ACLConfig> {
-    /// t2: the second proof.
-    pub t3: sw::Affine<A>,
-    /// t2: the second proof.
+    /// ch: the challenge.
+    pub ch: <A as CurveConfig>::ScalarField,
+    /// a3: the response for the randomness of the signature.
     pub a3: <A as CurveConfig>::ScalarField,
-    /// b_gamma: the first message value.
+    /// a4: the response for the randomness of the commitment.
     pub a4: <A as CurveConfig>::ScalarField,
 }
 
@@ -406,22 +506,30 @@ pub struct SigProof<A: ACLConfig> {
 
 impl<A: ACLConfig> SigProof<A> {
     pub fn make_transcript(transcript: &mut Transcript, c1: &sw::Affine<A>, c2: &sw::Affine<A>) {
-        transcript.append_message(b"dom-sep", b"acl-challenge-zk");
+        append_message(transcript, b"dom-sep", b"acl-challenge-zk");
 
         let mut compressed_bytes = Vec::new();
         c1.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c1", &compressed_bytes[..]);
+        append_message(transcript, b"c1", &compressed_bytes[..]);
 
         c2.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c2", &compressed_bytes[..]);
+        append_message(transcript, b"c2", &compressed_bytes[..]);
     }
 
     pub fn make_transcript_one(transcript: &mut Transcript, c1: &sw::Affine<A>) {
-        transcript.append_message(b"dom-sep", b"acl-challenge-zk2");
+        append_message(transcript, b"dom-sep", b"acl-challenge-zk2");
 
         let mut compressed_bytes = Vec::new();
         c1.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c1", &compressed_bytes[..]);
+        append_message(transcript, b"c1", &compressed_bytes[..]);
+    }
+
+    /// make_context. This function binds `transcript` to the optional domain label `context`,
+    /// e.g. the identifier of the session that the proof is produced in.
+    pub fn make_context(transcript: &mut Transcript, context: Option<&[u8]>) {
+        if let Some(context) = context {
+            append_message(transcript, b"context", context);
+        }
     }
 
     pub fn prove<T: RngCore + CryptoRng>(
@@ -431,6 +539,7 @@ impl<A: ACLConfig> SigProof<A> {
         vals: &[<A as CurveConfig>::ScalarField],
         gens: &[sw::Affine<A>],
         comm_r: <A as CurveConfig>::ScalarField,
+        context: Option<&[u8]>,
     ) -> SigProof<A> {
         let b_gamma = (A::GENERATOR.mul(sig_m.opening.gamma)).into_affine();
 
@@ -447,17 +556,17 @@ impl<A: ACLConfig> SigProof<A> {
 
         let label = b"Chall ACLZK";
         let mut transcript_v = Transcript::new(label);
+        Self::make_context(&mut transcript_v, context);
         Self::make_transcript(&mut transcript_v, &t1, &t2);
 
         let mut buf = [0u8; 64];
-        let _ = &transcript_v.challenge_bytes(b"challzk", &mut buf);
+        challenge_bytes(&mut transcript_v, b"challzk", &mut buf);
 
-        let ch: <A as CurveConfig>::ScalarField =
-            <A as CurveConfig>::ScalarField::deserialize_compressed(&buf[..]).unwrap();
+        let ch: <A as CurveConfig>::ScalarField = A::make_challenge_from_buffer(&buf);
 
         let a1 = r + sig_m.opening.gamma * ch;
 
-        let pi1 = SigProofD { t1, t2, a1 };
+        let pi1 = SigProofD { ch, a1 };
 
         // Equality proofs of zeta = h_vec
         let pi_hvec: Vec<SigProofD<A>> = gens
@@ -471,18 +580,18 @@ impl<A: ACLConfig> SigProof<A> {
 
                 let label3 = b"Chall ACLZK3";
                 let mut transcript_v = Transcript::new(label3);
+                Self::make_context(&mut transcript_v, context);
                 Self::make_transcript(&mut transcript_v, &t1, &t2);
 
                 let mut buf3 = [0u8; 64];
-                let _ = &transcript_v.challenge_bytes(b"challzk3", &mut buf3);
+                challenge_bytes(&mut transcript_v, b"challzk3", &mut buf3);
 
-                let ch: <A as CurveConfig>::ScalarField =
-                    <A as CurveConfig>::ScalarField::deserialize_compressed(&buf3[..]).unwrap();
+                let ch: <A as CurveConfig>::ScalarField = A::make_challenge_from_buffer(&buf3);
 
                 let a1 = r + sig_m.opening.gamma * ch;
 
                 // Opening proof `pi`
-                SigProofD { t1, t2, a1 }
+                SigProofD { ch, a1 }
             })
             .collect();
 
@@ -506,18 +615,18 @@ impl<A: ACLConfig> SigProof<A> {
 
         let label2 = b"Chall ACLZK2";
         let mut transcript_v = Transcript::new(label2);
+        Self::make_context(&mut transcript_v, context);
         Self::make_transcript_one(&mut transcript_v, &t3);
 
         let mut buf2 = [0u8; 64];
-        let _ = &transcript_v.challenge_bytes(b"challzk2", &mut buf2);
+        challenge_bytes(&mut transcript_v, b"challzk2", &mut buf2);
 
-        let ch2: <A as CurveConfig>::ScalarField =
-            <A as CurveConfig>::ScalarField::deserialize_compressed(&buf2[..]).unwrap();
+        let ch2: <A as CurveConfig>::ScalarField = A::make_challenge_from_buffer(&buf2);
 
         let a3 = alpha1 + sig_m.opening.rand * ch2; // proof g^rand
         let a4 = alpha2 + comm_r * ch2; // proof h^r
 
-        let pi2 = SigProofO { t3, a3, a4 };
+        let pi2 = SigProofO { ch: ch2, a3, a4 };
 
         let val = (A::GENERATOR.mul(sig_m.opening.rand) + A::GENERATOR2.mul(comm_r)).into_affine();
 
@@ -0,0 +1,21 @@
+//! Helpers for the operations on Merlin transcripts. With the `transcript_dump` feature, they
+//! are recorded by the `transcript_dump` module of the pedersen crate.
+
+use merlin::Transcript;
+
+/// append_message. This function appends `message` to `transcript` under `label`.
+pub(crate) fn append_message(transcript: &mut Transcript, label: &'static [u8], message: &[u8]) {
+    #[cfg(feature = "transcript_dump")]
+    pedersen::transcript::append_message(transcript, label, message);
+    #[cfg(not(feature = "transcript_dump"))]
+    transcript.append_message(label, message);
+}
+
+/// challenge_bytes. This function fills `dest` with challenge bytes from `transcript` under
+/// `label`.
+pub(crate) fn challenge_bytes(transcript: &mut Transcript, label: &'static [u8], dest: &mut [u8]) {
+    #[cfg(feature = "transcript_dump")]
+    pedersen::transcript::challenge_bytes(transcript, label, dest);
+    #[cfg(not(feature = "transcript_dump"))]
+    transcript.challenge_bytes(label, dest);
+}
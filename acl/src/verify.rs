@@ -10,11 +10,16 @@ use ark_ec::{
 use rand::{CryptoRng, RngCore};
 
 use crate::sign::{SigChall, SigProof, SigSign};
-use crate::{config::ACLConfig, config::KeyPair};
+use crate::transcript::{append_message, challenge_bytes};
+use crate::{
+    config::append_aux, config::append_to_hash, config::ACLConfig, config::AuxDigest,
+    config::KeyPair,
+};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::Zero;
 use ark_std::{ops::Mul, UniformRand};
 use merlin::Transcript;
+use sha3::Digest;
 use std::default::Default;
 use std::marker::PhantomData;
 
@@ -146,44 +151,75 @@ pub struct SigVerify<A: ACLConfig> {
 impl<A: ACLConfig> SigVerify<A> {
     #[allow(clippy::too_many_arguments)]
     pub fn make_transcript(
-        transcript: &mut Transcript,
+        hasher: &mut A::Hash,
         c1: &sw::Affine<A>,
         c2: &sw::Affine<A>,
         c3: &sw::Affine<A>,
         c4: &sw::Affine<A>,
         c5: &sw::Affine<A>,
         c6: &sw::Affine<A>,
-        message: &str,
+        message: &[u8],
+        context: Option<&[u8]>,
     ) {
-        transcript.append_message(b"dom-sep", b"acl-challenge");
+        append_to_hash(hasher, b"dom-sep", b"acl-challenge");
 
         let mut compressed_bytes = Vec::new();
         c1.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c1", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c1", &compressed_bytes[..]);
 
         c2.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c2", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c2", &compressed_bytes[..]);
 
         c3.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c3", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c3", &compressed_bytes[..]);
 
         c4.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c4", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c4", &compressed_bytes[..]);
 
         c5.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c5", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c5", &compressed_bytes[..]);
 
         c6.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c6", &compressed_bytes[..]);
+        append_to_hash(hasher, b"c6", &compressed_bytes[..]);
 
-        transcript.append_message(b"message", message.as_bytes());
+        if let Some(context) = context {
+            append_to_hash(hasher, b"context", context);
+        }
+        append_to_hash(hasher, b"message", message);
     }
 
+    /// verify. This function checks the signature `sig_m` on `message` under the
+    /// optional domain label `context`.
     pub fn verify(
         pub_key: sw::Affine<A>,
         tag_key: sw::Affine<A>,
         sig_m: &SigSign<A>,
-        message: &str,
+        message: &[u8],
+        context: Option<&[u8]>,
+    ) -> bool {
+        Self::verify_with(pub_key, tag_key, sig_m, message, context, None)
+    }
+
+    /// verify_with_aux. This function checks the signature `sig_m` as `verify` does, for a
+    /// signature that also binds the auxiliary data hashed into `aux`.
+    pub fn verify_with_aux(
+        pub_key: sw::Affine<A>,
+        tag_key: sw::Affine<A>,
+        sig_m: &SigSign<A>,
+        message: &[u8],
+        context: Option<&[u8]>,
+        aux: &AuxDigest,
+    ) -> bool {
+        Self::verify_with(pub_key, tag_key, sig_m, message, context, Some(aux))
+    }
+
+    fn verify_with(
+        pub_key: sw::Affine<A>,
+        tag_key: sw::Affine<A>,
+        sig_m: &SigSign<A>,
+        message: &[u8],
+        context: Option<&[u8]>,
+        aux: Option<&AuxDigest>,
     ) -> bool {
         let z2 = sig_m.sigma.zeta - sig_m.sigma.zeta1;
         let tmp1 =
@@ -194,10 +230,9 @@ impl<A: ACLConfig> SigVerify<A> {
         let tmp4 =
             (tag_key.mul(sig_m.sigma.v) + sig_m.sigma.zeta.mul(sig_m.sigma.omega1)).into_affine();
 
-        let label = b"Chall ACL";
-        let mut transcript_v = Transcript::new(label);
+        let mut hasher = A::Hash::new();
         Self::make_transcript(
-            &mut transcript_v,
+            &mut hasher,
             &sig_m.sigma.zeta,
             &sig_m.sigma.zeta1,
             &tmp1,
@@ -205,13 +240,11 @@ impl<A: ACLConfig> SigVerify<A> {
             &tmp3,
             &tmp4,
             message,
+            context,
         );
+        append_aux(&mut hasher, aux);
 
-        let mut buf = [0u8; 64];
-        let _ = &transcript_v.challenge_bytes(b"chall", &mut buf);
-
-        let epsilon: <A as CurveConfig>::ScalarField =
-            <A as CurveConfig>::ScalarField::deserialize_compressed(&buf[..]).unwrap();
+        let epsilon: <A as CurveConfig>::ScalarField = A::make_challenge_from_hash(&hasher);
 
         let e = sig_m.sigma.omega + sig_m.sigma.omega1;
 
@@ -226,50 +259,79 @@ pub struct SigVerifProof<A: ACLConfig> {
 
 impl<A: ACLConfig> SigVerifProof<A> {
     pub fn make_transcript(transcript: &mut Transcript, c1: &sw::Affine<A>, c2: &sw::Affine<A>) {
-        transcript.append_message(b"dom-sep", b"acl-challenge-zk");
+        append_message(transcript, b"dom-sep", b"acl-challenge-zk");
 
         let mut compressed_bytes = Vec::new();
         c1.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c1", &compressed_bytes[..]);
+        append_message(transcript, b"c1", &compressed_bytes[..]);
 
         c2.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c2", &compressed_bytes[..]);
+        append_message(transcript, b"c2", &compressed_bytes[..]);
     }
 
     pub fn make_transcript_one(transcript: &mut Transcript, c1: &sw::Affine<A>) {
-        transcript.append_message(b"dom-sep", b"acl-challenge-zk2");
+        append_message(transcript, b"dom-sep", b"acl-challenge-zk2");
 
         let mut compressed_bytes = Vec::new();
         c1.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_message(b"c1", &compressed_bytes[..]);
+        append_message(transcript, b"c1", &compressed_bytes[..]);
+    }
+
+    /// make_context. This function binds `transcript` to the optional domain label `context`,
+    /// e.g. the identifier of the session that the proof is produced in.
+    pub fn make_context(transcript: &mut Transcript, context: Option<&[u8]>) {
+        if let Some(context) = context {
+            append_message(transcript, b"context", context);
+        }
+    }
+
+    /// challenge. This function returns the challenge of one of the sub-proofs, i.e the
+    /// challenge `chall_label` of a transcript that starts with `label`, is bound to
+    /// `context` and then absorbs the commitments of the sub-proof with `append`.
+    fn challenge(
+        label: &'static [u8],
+        chall_label: &'static [u8],
+        context: Option<&[u8]>,
+        append: impl FnOnce(&mut Transcript),
+    ) -> <A as CurveConfig>::ScalarField {
+        let mut transcript_v = Transcript::new(label);
+        Self::make_context(&mut transcript_v, context);
+        append(&mut transcript_v);
+
+        let mut buf = [0u8; 64];
+        challenge_bytes(&mut transcript_v, chall_label, &mut buf);
+        A::make_challenge_from_buffer(&buf)
     }
 
+    /// verify. This function checks the proof of signature `proof` on `sig_m`. As the proof
+    /// only carries the challenges and the responses of its sub-proofs, the commitments of each
+    /// sub-proof are recomputed from them, and the challenge that they produce must match the
+    /// one of the proof.
+    /// # Arguments
+    /// * `proof` - the proof of signature.
+    /// * `tag_key` - the tag key of the signer.
+    /// * `sig_m` - the signature.
+    /// * `gens` - the generators of the commitment.
+    /// * `context` - an optional domain label.
     pub fn verify(
         proof: &SigProof<A>,
         tag_key: sw::Affine<A>,
         sig_m: &SigSign<A>,
         gens: &[sw::Affine<A>],
+        context: Option<&[u8]>,
     ) -> bool {
-        // Equality proof of zeta = b_gamma
-        let rhs1 = (tag_key.mul(proof.pi1.a1)).into_affine();
-        let rhs2 = (A::GENERATOR.mul(proof.pi1.a1)).into_affine();
-
-        let label = b"Chall ACLZK";
-        let mut transcript_v = Transcript::new(label);
-        Self::make_transcript(&mut transcript_v, &proof.pi1.t1, &proof.pi1.t2);
-
-        let mut buf = [0u8; 64];
-        let _ = &transcript_v.challenge_bytes(b"challzk", &mut buf);
-
-        let ch: <A as CurveConfig>::ScalarField =
-            <A as CurveConfig>::ScalarField::deserialize_compressed(&buf[..]).unwrap();
-
-        let lhs1 = proof.pi1.t1 + (sig_m.sigma.zeta.mul(ch));
-        let lhs2 = proof.pi1.t2 + (proof.b_gamma.mul(ch));
+        // Each sub-value is committed to under its own generator, skipping the first.
+        if proof.h_vec.len() != proof.pi3.len() || gens.len() <= proof.pi3.len() {
+            return false;
+        }
 
-        if !(rhs1 == lhs1 && rhs2 == lhs2) {
-            // Only continue if above proof verifies to true, otherwise we can
-            // return early
+        // Equality proof of zeta = b_gamma
+        let t1 = (tag_key.mul(proof.pi1.a1) - sig_m.sigma.zeta.mul(proof.pi1.ch)).into_affine();
+        let t2 = (A::GENERATOR.mul(proof.pi1.a1) - proof.b_gamma.mul(proof.pi1.ch)).into_affine();
+        let ch = Self::challenge(b"Chall ACLZK", b"challzk", context, |t| {
+            Self::make_transcript(t, &t1, &t2)
+        });
+        if ch != proof.pi1.ch {
             return false;
         }
 
@@ -278,26 +340,14 @@ impl<A: ACLConfig> SigVerifProof<A> {
             .pi3
             .iter()
             .zip(proof.h_vec.iter())
-            .zip(gens.iter().take(proof.pi3.len()).skip(1))
+            .zip(gens.iter().skip(1))
         {
-            let rhs4 = (tag_key.mul(pi.a1)).into_affine();
-            let rhs5 = (gen.mul(pi.a1)).into_affine();
-
-            let label3 = b"Chall ACLZK3";
-            let mut transcript_v = Transcript::new(label3);
-            Self::make_transcript(&mut transcript_v, &pi.t1, &pi.t2);
-
-            let mut buf3 = [0u8; 64];
-            let _ = &transcript_v.challenge_bytes(b"challzk3", &mut buf3);
-
-            let ch3: <A as CurveConfig>::ScalarField =
-                <A as CurveConfig>::ScalarField::deserialize_compressed(&buf3[..]).unwrap();
-
-            let lhs4 = pi.t1 + (sig_m.sigma.zeta.mul(ch3));
-            let lhs5 = pi.t2 + (h.mul(ch3));
-
-            if !(rhs4 == lhs4 && rhs5 == lhs5) {
-                // if any of the proof is false, return immediately
+            let t1 = (tag_key.mul(pi.a1) - sig_m.sigma.zeta.mul(pi.ch)).into_affine();
+            let t2 = (gen.mul(pi.a1) - h.mul(pi.ch)).into_affine();
+            let ch3 = Self::challenge(b"Chall ACLZK3", b"challzk3", context, |t| {
+                Self::make_transcript(t, &t1, &t2)
+            });
+            if ch3 != pi.ch {
                 return false;
             }
         }
@@ -305,21 +355,13 @@ impl<A: ACLConfig> SigVerifProof<A> {
         // For our cases, we will always prove knowledge of all signed committed values,
         // but this is not for all cases.
         // Hence, we only need to prove knowledge of g^rand and h^r
-
-        let label2 = b"Chall ACLZK2";
-        let mut transcript_v = Transcript::new(label2);
-        Self::make_transcript_one(&mut transcript_v, &proof.pi2.t3);
-
-        let mut buf2 = [0u8; 64];
-        let _ = &transcript_v.challenge_bytes(b"challzk2", &mut buf2);
-
-        let ch2: <A as CurveConfig>::ScalarField =
-            <A as CurveConfig>::ScalarField::deserialize_compressed(&buf2[..]).unwrap();
-
-        let rhs3 = proof.val.mul(ch2) + proof.pi2.t3;
-        let lhs3 = (A::GENERATOR2.mul(proof.pi2.a4) + A::GENERATOR.mul(proof.pi2.a3)).into_affine();
-
-        // Proof succeeds if these final sides also agree.
-        rhs3 == lhs3
+        let t3 = (A::GENERATOR.mul(proof.pi2.a3) + A::GENERATOR2.mul(proof.pi2.a4)
+            - proof.val.mul(proof.pi2.ch))
+        .into_affine();
+        let ch2 = Self::challenge(b"Chall ACLZK2", b"challzk2", context, |t| {
+            Self::make_transcript_one(t, &t3)
+        });
+
+        ch2 == proof.pi2.ch
     }
 }
@@ -0,0 +1,7 @@
+use boomerang_macros::bench_all_curves;
+bench_all_curves!(
+    10;
+    (t256, t256::Config, "t256"),
+    (t384, t384::Config, "t384"),
+    (tsecq256k1, tsecq256k1::Config, "tsecq256k1"),
+);
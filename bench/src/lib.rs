@@ -0,0 +1,2 @@
+//! This crate only holds the benches that compare the boomerang protocols across curves.
+//! Run them with `cargo bench -p boomerang-bench`.
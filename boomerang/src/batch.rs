@@ -0,0 +1,84 @@
+//! Defines a verifier that checks many linear verification equations with a single MSM.
+//!
+//! Each of the sigma protocols that the server checks ends in one or more equations of the
+//! form `sum_i s_i * P_i = 0`. Instead of checking them one by one, a `BatchVerifier` scales
+//! every equation by a fresh random weight and adds it to a running sum. If any equation does
+//! not hold, the sum is non-zero except with probability 1/|ScalarField|.
+//!
+//! The verifier merges the terms of equal points as they are added, so its memory only grows
+//! with the number of distinct points, rather than with the number of equations. This matters
+//! because most equations share the generators, the server's keys and the signature values.
+//! The points are indexed by a hash map, so that adding a term does not scan the batch.
+
+use ark_bulletproofs::msm;
+use ark_ec::{
+    models::CurveConfig,
+    short_weierstrass::{self as sw, SWCurveConfig},
+};
+use ark_std::{UniformRand, Zero};
+use std::collections::HashMap;
+
+use crate::rng::BoomerangRng;
+
+/// BatchVerifier. This struct accumulates random linear combinations of verification equations.
+pub struct BatchVerifier<C: SWCurveConfig> {
+    /// terms: the distinct points of every equation so far, with their combined scalars.
+    terms: Vec<(sw::Affine<C>, <C as CurveConfig>::ScalarField)>,
+    /// index: the position of every point in `terms`.
+    index: HashMap<sw::Affine<C>, usize>,
+}
+
+impl<C: SWCurveConfig> Default for BatchVerifier<C> {
+    fn default() -> Self {
+        Self {
+            terms: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl<C: SWCurveConfig> BatchVerifier<C> {
+    /// new. This function returns a new, empty, batch verifier.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add. This function adds `equation`, scaled by a fresh random weight, to the batch.
+    /// # Arguments
+    /// * `rng` - the RNG that is used to draw the weight. Must be cryptographically secure.
+    /// * `equation` - the (point, scalar) terms whose sum must be the identity.
+    pub fn add<T: BoomerangRng>(
+        &mut self,
+        rng: &mut T,
+        equation: impl IntoIterator<Item = (sw::Affine<C>, <C as CurveConfig>::ScalarField)>,
+    ) {
+        let weight = <C as CurveConfig>::ScalarField::rand(rng);
+        for (point, scalar) in equation {
+            let scalar = weight * scalar;
+            match self.index.get(&point) {
+                Some(&i) => self.terms[i].1 += scalar,
+                None => {
+                    self.index.insert(point, self.terms.len());
+                    self.terms.push((point, scalar));
+                }
+            }
+        }
+    }
+
+    /// len. Returns the number of distinct points in the batch.
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    /// is_empty. Returns true if no equation has been added.
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// verify. This function returns true if every equation in the batch holds, except with
+    /// negligible probability, and false otherwise.
+    pub fn verify(&self) -> bool {
+        let (bases, scalars): (Vec<_>, Vec<_>) = self.terms.iter().cloned().unzip();
+        msm::msm(&bases, &scalars).unwrap().is_zero()
+    }
+}
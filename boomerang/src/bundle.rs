@@ -0,0 +1,116 @@
+//! Defines spend bundles, which allow a spend to be verified after the fact.
+//!
+//! During the Spend/Verify protocol, only the server that takes part in the run checks the
+//! client's proofs and the rewards proof. A `SpendBundle` packs the client's second message
+//! (the commitments, the ACL signature and its proof, the tag and the sub proof) together with
+//! the server's rewards proof, so that auditors and other servers can check the spend with
+//! `verify_spend_bundle`, the server's public keys and the id that the server published for the
+//! policy that it applies.
+//!
+//! N.B A bundle shows that the spend was valid, but it cannot show that the spend was fresh:
+//! the verifier of a bundle must still check the tag against the set of spent tags, and the
+//! session identifier against the sessions it expects.
+
+use ark_ec::{
+    models::CurveConfig,
+    short_weierstrass::{self as sw},
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use crate::client::SpendVerifyM2;
+use crate::config::BoomerangConfig;
+use crate::rng::BoomerangRng;
+use crate::server::{SpendVerifyM3, SpendVerifyStateS};
+use crate::utils::rewards::{BRewardsProof, PolicyId, RewardsGenerators};
+
+/// SpendBundle. This struct acts as a container for every public value of a spend, along with
+/// the proofs that a third party needs in order to check it.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct SpendBundle<B: BoomerangConfig> {
+    /// m2: the client's spend message, which carries the commitments, the ACL proof and the tag.
+    pub m2: SpendVerifyM2<B>,
+    /// pi_reward: the server's rewards proof.
+    pub pi_reward: BRewardsProof<B>,
+    /// policy_id: the id of the policy that `pi_reward` is bound to.
+    pub policy_id: PolicyId,
+}
+
+impl<B: BoomerangConfig> SpendBundle<B> {
+    /// new. This function builds a bundle from the second and third messages of a run of the
+    /// Spend/Verify protocol. This function fails if the messages belong to different sessions.
+    /// # Arguments
+    /// * `m2` - the client's second message.
+    /// * `m3` - the server's third message.
+    pub fn new(m2: SpendVerifyM2<B>, m3: &SpendVerifyM3<B>) -> Result<Self, &'static str> {
+        if m2.session_id != m3.session_id {
+            return Err("Boomerang spend bundle: mismatched sessions");
+        }
+
+        Ok(Self {
+            m2,
+            pi_reward: m3.pi_reward.clone(),
+            policy_id: m3.policy_id,
+        })
+    }
+
+    /// tag. Returns the double-spending tag of the spend.
+    pub fn tag(&self) -> &<B as CurveConfig>::ScalarField {
+        &self.m2.tag
+    }
+}
+
+/// verify_spend_bundle. This is a convenience wrapper around `verify_spend_bundle_with_rng`
+/// that uses the thread RNG.
+/// # Arguments
+/// * `bundle` - the bundle to check.
+/// * `verifying_key` - the verifying key of the server that the spend was made to.
+/// * `tag_key` - the tag key of the server that the spend was made to.
+/// * `policy_id` - the id that the server published for the policy that it applies.
+pub fn verify_spend_bundle<B: BoomerangConfig>(
+    bundle: &SpendBundle<B>,
+    verifying_key: &sw::Affine<B>,
+    tag_key: &sw::Affine<B>,
+    policy_id: &PolicyId,
+) -> Result<(), &'static str> {
+    verify_spend_bundle_with_rng(
+        bundle,
+        verifying_key,
+        tag_key,
+        policy_id,
+        &mut rand::thread_rng(),
+    )
+}
+
+/// verify_spend_bundle_with_rng. This function checks every proof in `bundle`, i.e the checks
+/// that the server makes on the client's second message, and the rewards proof against
+/// `policy_id`. This function returns the reason for the first failed check, if any.
+/// # Arguments
+/// * `bundle` - the bundle to check.
+/// * `verifying_key` - the verifying key of the server that the spend was made to.
+/// * `tag_key` - the tag key of the server that the spend was made to.
+/// * `policy_id` - the id that the server published for the policy that it applies.
+/// * `rng` - the RNG that is used to batch the range proof checks.
+pub fn verify_spend_bundle_with_rng<B: BoomerangConfig, T: BoomerangRng>(
+    bundle: &SpendBundle<B>,
+    verifying_key: &sw::Affine<B>,
+    tag_key: &sw::Affine<B>,
+    policy_id: &PolicyId,
+    rng: &mut T,
+) -> Result<(), &'static str> {
+    if bundle.policy_id != *policy_id {
+        return Err("Boomerang spend bundle: rewards proof is for another policy");
+    }
+
+    SpendVerifyStateS::verify_spendverify_m2_with_keys(&bundle.m2, verifying_key, tag_key, rng)?;
+
+    bundle
+        .pi_reward
+        .verify_with_policy_id(
+            &RewardsGenerators::shared(),
+            &bundle.m2.spend_state,
+            &bundle.m2.session_id,
+            policy_id,
+            rng,
+        )
+        .map_err(|_| "Boomerang spend bundle: invalid rewards proof")
+}
@@ -0,0 +1,269 @@
+//! Defines the compact CBOR representation of the protocol messages, for constrained clients.
+//!
+//! Every message is encoded as a CBOR map (RFC 8949) with the same members as its JSON
+//! representation (see the `json` module): `version` is an unsigned integer, `type` is a text
+//! string, and every field of the message struct is a byte string that holds the compressed
+//! arkworks encoding of the field. Proofs, such as the rewards proof of a `SpendVerifyM2`, are
+//! therefore a single byte string, rather than base64url text.
+//!
+//! The encoding is deterministic, as in section 4.2.1 of RFC 8949: every length is definite
+//! and in its shortest form, and the keys are sorted by their encodings. The decoder only
+//! accepts deterministic encodings, so every message has exactly one CBOR representation. This
+//! makes the representation suitable as the payload of a COSE_Sign1 envelope (RFC 9052), whose
+//! signed bytes are returned by `sig_structure`.
+//!
+//! As in the JSON representation, members with unknown keys are ignored, as long as their
+//! values are unsigned integers, text strings or byte strings.
+
+use crate::json::{MessageFields, JSON_VERSION};
+
+/// MAJOR_UINT. The major type of an unsigned integer.
+const MAJOR_UINT: u8 = 0;
+/// MAJOR_BYTES. The major type of a byte string.
+const MAJOR_BYTES: u8 = 2;
+/// MAJOR_TEXT. The major type of a text string.
+const MAJOR_TEXT: u8 = 3;
+/// MAJOR_ARRAY. The major type of an array.
+const MAJOR_ARRAY: u8 = 4;
+/// MAJOR_MAP. The major type of a map.
+const MAJOR_MAP: u8 = 5;
+/// MAJOR_TAG. The major type of a tag.
+const MAJOR_TAG: u8 = 6;
+
+/// COSE_SIGN1_TAG. The CBOR tag of a COSE_Sign1 structure.
+pub const COSE_SIGN1_TAG: u64 = 18;
+
+/// CborMessage. A protocol message with a CBOR representation.
+pub trait CborMessage: Sized {
+    /// to_cbor. Returns the deterministic CBOR representation of the message.
+    fn to_cbor(&self) -> Vec<u8>;
+
+    /// from_cbor. This function parses a message from its CBOR representation. This function
+    /// fails if `bytes` is not the deterministic representation of a message of this type and
+    /// version.
+    /// # Arguments
+    /// * `bytes` - the CBOR representation of the message.
+    fn from_cbor(bytes: &[u8]) -> Result<Self, String>;
+}
+
+/// Item. A decoded CBOR data item, among the ones that appear in a message.
+enum Item<'a> {
+    /// Uint: an unsigned integer.
+    Uint(u64),
+    /// Bytes: a byte string.
+    Bytes(&'a [u8]),
+    /// Text: a text string.
+    Text(&'a str),
+}
+
+/// write_head. Writes the head of a data item of major type `major` with argument `arg`, in its
+/// shortest form.
+fn write_head(out: &mut Vec<u8>, major: u8, arg: u64) {
+    let major = major << 5;
+    match arg {
+        0..=23 => out.push(major | arg as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, arg as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(arg as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(arg as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&arg.to_be_bytes());
+        }
+    }
+}
+
+/// write_bytes. Writes a data item of major type `major` with the content `bytes`.
+fn write_bytes(out: &mut Vec<u8>, major: u8, bytes: &[u8]) {
+    write_head(out, major, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// read_head. Reads the head of a data item from `input`, and returns its major type and
+/// argument. This function fails on indefinite lengths and on arguments that are not in their
+/// shortest form.
+fn read_head(input: &mut &[u8]) -> Result<(u8, u64), String> {
+    let (&initial, rest) = input
+        .split_first()
+        .ok_or_else(|| "Truncated CBOR item".to_string())?;
+    let (major, info) = (initial >> 5, initial & 31);
+    let len = match info {
+        0..=23 => {
+            *input = rest;
+            return Ok((major, info as u64));
+        }
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        _ => return Err("Unsupported CBOR length".to_string()),
+    };
+    if rest.len() < len {
+        return Err("Truncated CBOR item".to_string());
+    }
+
+    let mut buf = [0u8; 8];
+    buf[8 - len..].copy_from_slice(&rest[..len]);
+    let arg = u64::from_be_bytes(buf);
+    let min = match len {
+        1 => 24,
+        _ => 1 << (4 * len),
+    };
+    if arg < min {
+        return Err("Non-deterministic CBOR length".to_string());
+    }
+    *input = &rest[len..];
+    Ok((major, arg))
+}
+
+/// read_item. Reads an unsigned integer, a byte string or a text string from `input`.
+fn read_item<'a>(input: &mut &'a [u8]) -> Result<Item<'a>, String> {
+    let (major, arg) = read_head(input)?;
+    if major == MAJOR_UINT {
+        return Ok(Item::Uint(arg));
+    }
+
+    let len = usize::try_from(arg)
+        .ok()
+        .filter(|&len| len <= input.len())
+        .ok_or_else(|| "Truncated CBOR item".to_string())?;
+    let (content, rest) = input.split_at(len);
+    *input = rest;
+    match major {
+        MAJOR_BYTES => Ok(Item::Bytes(content)),
+        MAJOR_TEXT => core::str::from_utf8(content)
+            .map(Item::Text)
+            .map_err(|_| "Invalid CBOR text string".to_string()),
+        _ => Err("Unexpected CBOR item".to_string()),
+    }
+}
+
+impl<M: MessageFields> CborMessage for M {
+    fn to_cbor(&self) -> Vec<u8> {
+        let encode = |major: u8, bytes: &[u8]| {
+            let mut item = Vec::new();
+            write_bytes(&mut item, major, bytes);
+            item
+        };
+
+        let mut version = Vec::new();
+        write_head(&mut version, MAJOR_UINT, JSON_VERSION);
+        let mut entries = vec![
+            (encode(MAJOR_TEXT, b"version"), version),
+            (
+                encode(MAJOR_TEXT, b"type"),
+                encode(MAJOR_TEXT, M::MESSAGE_TYPE.as_bytes()),
+            ),
+        ];
+        for (name, bytes) in self.to_fields() {
+            entries.push((
+                encode(MAJOR_TEXT, name.as_bytes()),
+                encode(MAJOR_BYTES, &bytes),
+            ));
+        }
+
+        // Deterministic encoding: the keys are sorted by their encodings.
+        entries.sort();
+        let mut out = Vec::new();
+        write_head(&mut out, MAJOR_MAP, entries.len() as u64);
+        for (k, v) in entries {
+            out.extend_from_slice(&k);
+            out.extend_from_slice(&v);
+        }
+        out
+    }
+
+    fn from_cbor(bytes: &[u8]) -> Result<Self, String> {
+        let mut input = bytes;
+        let (major, len) = read_head(&mut input)?;
+        if major != MAJOR_MAP {
+            return Err("Invalid message: not a map".to_string());
+        }
+
+        let (mut version, mut message_type) = (None, None);
+        let mut payloads = Vec::new();
+        let mut previous: Option<&[u8]> = None;
+        for _ in 0..len {
+            let start = input;
+            let Item::Text(key) = read_item(&mut input)? else {
+                return Err("Invalid message: key is not a text string".to_string());
+            };
+            let encoded_key = &start[..start.len() - input.len()];
+            if previous.is_some_and(|p| p >= encoded_key) {
+                return Err("Invalid message: keys are not in deterministic order".to_string());
+            }
+            previous = Some(encoded_key);
+
+            match (key, read_item(&mut input)?) {
+                ("version", Item::Uint(v)) => version = Some(v),
+                ("type", Item::Text(t)) => message_type = Some(t),
+                ("version" | "type", _) => {
+                    return Err(format!("Invalid message: malformed {}", key));
+                }
+                (_, Item::Bytes(payload)) => payloads.push((key, payload)),
+                _ => {}
+            }
+        }
+        if !input.is_empty() {
+            return Err("Invalid message: trailing bytes".to_string());
+        }
+
+        match version {
+            Some(JSON_VERSION) => {}
+            Some(version) => return Err(format!("Unsupported message version {}", version)),
+            None => return Err("Missing message version".to_string()),
+        }
+        match message_type {
+            Some(t) if t == M::MESSAGE_TYPE => {}
+            Some(t) => return Err(format!("Expected a {} message, got {}", M::MESSAGE_TYPE, t)),
+            None => return Err("Missing message type".to_string()),
+        }
+
+        M::from_fields(|name| {
+            payloads
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, payload)| payload.to_vec())
+                .ok_or_else(|| format!("Missing field {}", name))
+        })
+    }
+}
+
+/// sig_structure. Returns the bytes that are signed in a COSE_Sign1 envelope (the
+/// `Sig_structure` of RFC 9052, section 4.4), e.g. with the CBOR representation of a message as
+/// the payload.
+/// # Arguments
+/// * `protected` - the encoded protected header of the envelope.
+/// * `external_aad` - the externally supplied data, or an empty slice.
+/// * `payload` - the payload of the envelope.
+pub fn sig_structure(protected: &[u8], external_aad: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_head(&mut out, MAJOR_ARRAY, 4);
+    write_bytes(&mut out, MAJOR_TEXT, b"Signature1");
+    write_bytes(&mut out, MAJOR_BYTES, protected);
+    write_bytes(&mut out, MAJOR_BYTES, external_aad);
+    write_bytes(&mut out, MAJOR_BYTES, payload);
+    out
+}
+
+/// cose_sign1. Returns a tagged COSE_Sign1 envelope (RFC 9052, section 4.2) with an empty
+/// unprotected header.
+/// # Arguments
+/// * `protected` - the encoded protected header of the envelope.
+/// * `payload` - the payload of the envelope.
+/// * `signature` - the signature on the `sig_structure` of the envelope.
+pub fn cose_sign1(protected: &[u8], payload: &[u8], signature: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_head(&mut out, MAJOR_TAG, COSE_SIGN1_TAG);
+    write_head(&mut out, MAJOR_ARRAY, 4);
+    write_bytes(&mut out, MAJOR_BYTES, protected);
+    write_head(&mut out, MAJOR_MAP, 0);
+    write_bytes(&mut out, MAJOR_BYTES, payload);
+    write_bytes(&mut out, MAJOR_BYTES, signature);
+    out
+}
@@ -0,0 +1,306 @@
+//! Defines checkpoints of the client state, so that a client can resume a protocol run after
+//! its process dies, e.g. a mobile client that is killed between two messages.
+//!
+//! A `Checkpoint` is taken right after the client generates one of its messages, and holds the
+//! client state of the run alongside that message. The client must persist the checkpoint
+//! before it sends the message. After a restart, the client loads the checkpoint and either:
+//!
+//! * re-sends the very same message, with `sent`, if it does not know whether the server
+//!   received it, or
+//! * resumes the run with `resume`, once the server's reply arrives. The reply must be the next
+//!   message of the same protocol run, i.e. it must have the step that follows the checkpoint
+//!   and the session id of the checkpointed state.
+//!
+//! N.B A client must never generate a message again for a step that it has checkpointed. The
+//! messages of the collection, update and spend/verify protocols reveal a double-spending tag of
+//! the token that is spent, which is bound to the server's `r2`: generating a new message in a
+//! new session would reveal a second tag of the same token, and with it the client's secret key.
+//! Likewise, the checkpointed state holds the blinding factors of the signature challenge, which
+//! must only be used in the run that they were generated for. A checkpoint should thus be
+//! resumed once, and replaced by the checkpoint of the next message before that message is sent.
+//!
+//! Checkpoints hold secret values (the client's blinding factors and token openings). A client
+//! that persists them in storage that may leak should seal them with a device-local key, with
+//! `seal` and `open`.
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use crate::client::{
+    CollectionM2, CollectionM4, CollectionStateC, IssuanceM1, IssuanceM3, IssuanceStateC,
+    SpendVerifyM2, SpendVerifyM2Multi, SpendVerifyM4, SpendVerifyStateC, TransferM4,
+    TransferRequest, TransferStateC, UpdateM2, UpdateM4, UpdateStateC,
+};
+use crate::config::{BoomerangConfig, SessionId};
+use crate::rng::BoomerangRng;
+use crate::server::{
+    CollectionM1, CollectionM3, CollectionM5, IssuanceM2, IssuanceM4, SpendVerifyAck,
+    SpendVerifyM1, SpendVerifyM3, SpendVerifyM5, TransferM1, TransferM3, TransferM5, UpdateM1,
+    UpdateM3, UpdateM5,
+};
+use crate::ticket::{SessionTicket, TicketKey};
+
+/// CHECKPOINT_VERSION. The version of the encoding of the checkpoints.
+pub const CHECKPOINT_VERSION: u8 = 1;
+
+/// ClientState. The client state of a protocol run, which can be checkpointed.
+pub trait ClientState: CanonicalSerialize + CanonicalDeserialize {
+    /// PROTOCOL: the name of the protocol.
+    const PROTOCOL: &'static str;
+
+    /// CLIENT_STEPS: the steps of the messages that the client generates.
+    const CLIENT_STEPS: &'static [u8];
+
+    /// session_id. Returns the identifier of the session of this state, once it is known.
+    fn session_id(&self) -> &SessionId;
+}
+
+/// ProtocolMessage. A message of a protocol run, in either direction.
+pub trait ProtocolMessage {
+    /// State: the client state of the protocol.
+    type State: ClientState;
+
+    /// STEP: the position of the message in the protocol, starting at 1.
+    const STEP: u8;
+
+    /// session_id. Returns the identifier of the session of this message, if the message
+    /// carries one.
+    fn session_id(&self) -> Option<&SessionId>;
+}
+
+/// Checkpoint. This struct holds the client state of a protocol run, taken right after the
+/// client generated the message of step `step`, alongside that message.
+#[derive(Clone)]
+pub struct Checkpoint<S: ClientState> {
+    /// step: the step of the message that the client generated.
+    step: u8,
+    /// state: the client state after the message was generated.
+    state: S,
+    /// sent: the compressed encoding of the message that the client generated.
+    sent: Vec<u8>,
+}
+
+impl<S: ClientState> Checkpoint<S> {
+    /// new. This function takes a checkpoint of `state`, right after the client generated `sent`.
+    /// # Arguments
+    /// * `state` - the tmp local client state.
+    /// * `sent` - the message that the client generated, and is about to send.
+    pub fn new<M>(state: &S, sent: &M) -> Self
+    where
+        S: Clone,
+        M: ProtocolMessage<State = S> + CanonicalSerialize,
+    {
+        let mut bytes = Vec::new();
+        sent.serialize_compressed(&mut bytes)
+            .expect("Failed to serialize the checkpointed message");
+        Self {
+            step: M::STEP,
+            state: state.clone(),
+            sent: bytes,
+        }
+    }
+
+    /// step. Returns the step of the message that was generated when the checkpoint was taken.
+    pub fn step(&self) -> u8 {
+        self.step
+    }
+
+    /// session_id. Returns the identifier of the session of the checkpointed state.
+    pub fn session_id(&self) -> &SessionId {
+        self.state.session_id()
+    }
+
+    /// sent. This function returns the message that was generated when the checkpoint was
+    /// taken, so that it can be sent again. This function fails if the checkpoint was taken for
+    /// another step.
+    pub fn sent<M>(&self) -> Result<M, &'static str>
+    where
+        M: ProtocolMessage<State = S> + CanonicalDeserialize,
+    {
+        if M::STEP != self.step {
+            return Err("Boomerang checkpoint: the message is of another step");
+        }
+        M::deserialize_compressed(&self.sent[..])
+            .map_err(|_| "Boomerang checkpoint: malformed message")
+    }
+
+    /// resume. This function returns the checkpointed state, so that the client can handle
+    /// `reply`. This function fails if `reply` is not the next message of the checkpointed run.
+    /// # Arguments
+    /// * `reply` - the server message that was received after the checkpoint was taken.
+    pub fn resume<M: ProtocolMessage<State = S>>(self, reply: &M) -> Result<S, &'static str> {
+        if M::STEP != self.step + 1 {
+            return Err("Boomerang checkpoint: the reply is not the next message");
+        }
+        if reply
+            .session_id()
+            .is_some_and(|id| id != self.state.session_id())
+        {
+            return Err("Boomerang checkpoint: session id mismatch");
+        }
+        Ok(self.state)
+    }
+
+    /// to_bytes. Returns the encoding of the checkpoint, which can be persisted.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![CHECKPOINT_VERSION];
+        S::PROTOCOL
+            .as_bytes()
+            .to_vec()
+            .serialize_compressed(&mut bytes)
+            .expect("Failed to serialize the checkpoint");
+        bytes.push(self.step);
+        self.state
+            .serialize_compressed(&mut bytes)
+            .expect("Failed to serialize the checkpoint");
+        self.sent
+            .serialize_compressed(&mut bytes)
+            .expect("Failed to serialize the checkpoint");
+        bytes
+    }
+
+    /// from_bytes. This function decodes a checkpoint from `bytes`. This function fails if
+    /// `bytes` is not the encoding of a checkpoint of this protocol and version, if the step is
+    /// not one of the client's, or if there are trailing bytes.
+    /// # Arguments
+    /// * `bytes` - the encoding of the checkpoint.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, &'static str> {
+        let (&version, mut reader) = bytes
+            .split_first()
+            .ok_or("Boomerang checkpoint: malformed checkpoint")?;
+        if version != CHECKPOINT_VERSION {
+            return Err("Boomerang checkpoint: unsupported version");
+        }
+
+        let protocol = Vec::<u8>::deserialize_compressed(&mut reader)
+            .map_err(|_| "Boomerang checkpoint: malformed checkpoint")?;
+        if protocol != S::PROTOCOL.as_bytes() {
+            return Err("Boomerang checkpoint: checkpoint of another protocol");
+        }
+
+        let step = u8::deserialize_compressed(&mut reader)
+            .map_err(|_| "Boomerang checkpoint: malformed checkpoint")?;
+        if !S::CLIENT_STEPS.contains(&step) {
+            return Err("Boomerang checkpoint: invalid step");
+        }
+
+        let state = S::deserialize_compressed(&mut reader)
+            .map_err(|_| "Boomerang checkpoint: malformed state")?;
+        let sent = Vec::<u8>::deserialize_compressed(&mut reader)
+            .map_err(|_| "Boomerang checkpoint: malformed checkpoint")?;
+        if !reader.is_empty() {
+            return Err("Boomerang checkpoint: trailing bytes");
+        }
+
+        Ok(Self { step, state, sent })
+    }
+
+    /// seal. This function seals the checkpoint with `key`, so that it can be persisted in
+    /// storage that may leak.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `key` - the device-local key that checkpoints are sealed with.
+    /// * `expiry` - the time after which the checkpoint is no longer accepted, e.g. the expiry
+    ///   of the server's session.
+    pub fn seal<T: BoomerangRng>(
+        &self,
+        rng: &mut T,
+        key: &TicketKey,
+        expiry: u64,
+    ) -> SessionTicket {
+        key.seal(rng, &Self::label(), expiry, &self.to_bytes())
+    }
+
+    /// open. This function returns the checkpoint that is sealed in `sealed`. This function
+    /// fails if `sealed` was not sealed with `key` for this protocol, if it has expired, or if
+    /// it does not hold a valid checkpoint.
+    /// # Arguments
+    /// * `key` - the device-local key that checkpoints are sealed with.
+    /// * `now` - the current time, in the same clock as the expiry time.
+    /// * `sealed` - the sealed checkpoint.
+    pub fn open(key: &TicketKey, now: u64, sealed: &SessionTicket) -> Result<Self, &'static str> {
+        let bytes: Vec<u8> = key.open(&Self::label(), now, sealed)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// label. Returns the label that the checkpoints of this protocol are sealed with.
+    fn label() -> Vec<u8> {
+        [b"BoomerangCheckpoint", S::PROTOCOL.as_bytes()].concat()
+    }
+}
+
+/// client_states. Implements `ClientState` for client state structs.
+macro_rules! client_states {
+    ($($state:ident: $protocol:literal, $steps:expr;)*) => {
+        $(
+            impl<B: BoomerangConfig> ClientState for $state<B> {
+                const PROTOCOL: &'static str = $protocol;
+                const CLIENT_STEPS: &'static [u8] = $steps;
+
+                fn session_id(&self) -> &SessionId {
+                    &self.session_id
+                }
+            }
+        )*
+    };
+}
+
+client_states! {
+    IssuanceStateC: "Issuance", &[1, 3];
+    CollectionStateC: "Collection", &[2, 4];
+    UpdateStateC: "Update", &[2, 4];
+    SpendVerifyStateC: "SpendVerify", &[2, 4];
+    TransferStateC: "Transfer", &[2, 4];
+}
+
+/// protocol_messages. Implements `ProtocolMessage` for message structs that carry a session
+/// id, from their protocol and step.
+macro_rules! protocol_messages {
+    ($($state:ident { $($message:ident = $step:literal),* $(,)? })*) => {
+        $($(
+            impl<B: BoomerangConfig> ProtocolMessage for $message<B> {
+                type State = $state<B>;
+                const STEP: u8 = $step;
+
+                fn session_id(&self) -> Option<&SessionId> {
+                    Some(&self.session_id)
+                }
+            }
+        )*)*
+    };
+}
+
+protocol_messages! {
+    IssuanceStateC { IssuanceM3 = 3, IssuanceM4 = 4 }
+    CollectionStateC {
+        CollectionM1 = 1, CollectionM2 = 2, CollectionM3 = 3, CollectionM4 = 4, CollectionM5 = 5,
+    }
+    UpdateStateC { UpdateM1 = 1, UpdateM2 = 2, UpdateM3 = 3, UpdateM4 = 4, UpdateM5 = 5 }
+    SpendVerifyStateC {
+        SpendVerifyM1 = 1, SpendVerifyM2 = 2, SpendVerifyM2Multi = 2, SpendVerifyM3 = 3,
+        SpendVerifyAck = 3, SpendVerifyM4 = 4, SpendVerifyM5 = 5,
+    }
+    // The recipient of a transfer generates the request, which the sender forwards alongside the
+    // second message.
+    TransferStateC {
+        TransferM1 = 1, TransferRequest = 2, TransferM3 = 3, TransferM4 = 4, TransferM5 = 5,
+    }
+}
+
+// The first two messages of the issuance protocol precede its session id.
+impl<B: BoomerangConfig> ProtocolMessage for IssuanceM1<B> {
+    type State = IssuanceStateC<B>;
+    const STEP: u8 = 1;
+
+    fn session_id(&self) -> Option<&SessionId> {
+        None
+    }
+}
+
+impl<B: BoomerangConfig> ProtocolMessage for IssuanceM2<B> {
+    type State = IssuanceStateC<B>;
+    const STEP: u8 = 2;
+
+    fn session_id(&self) -> Option<&SessionId> {
+        None
+    }
+}
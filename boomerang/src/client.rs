@@ -2,32 +2,65 @@
 //! Module containing the definition of the client side of the algorithm
 //!
 
+use ark_bulletproofs::msm;
 use ark_ec::{
     models::CurveConfig,
     short_weierstrass::{self as sw, SWCurveConfig},
     CurveGroup,
 };
-use rand::{CryptoRng, RngCore};
 
-use crate::config::{BoomerangConfig, State};
+use crate::commitment::StateCommitment;
+use crate::config::{
+    issuance_balance_comm, issuance_session_id, new_nonce, session_transcript,
+    split_value_generators, token_message, BoomerangConfig, Nonce, SessionId, State,
+    BASE_ATTRIBUTES, TOKEN_CONTEXT, VALUE_INDEX,
+};
+use crate::encoding::{append_point, encode_scalar, Endianness};
+use crate::progress::{ProgressCallback, ProofPhase, Reporter};
+use crate::rng::BoomerangRng;
 use crate::server::{
-    CollectionM1, CollectionM3, CollectionM5, IssuanceM2, IssuanceM4, ServerKeyPair, SpendVerifyM1,
-    SpendVerifyM3, SpendVerifyM5,
+    CollectionM1, CollectionM3, CollectionM5, IssuanceM2, IssuanceM4, ServerKeyPair,
+    SpendVerifyAck, SpendVerifyM1, SpendVerifyM3, SpendVerifyM5, TransferM1, TransferM3,
+    TransferM5, UpdateM1, UpdateM3, UpdateM5,
 };
+use crate::sizes::spend_verify_sizes;
+use crate::spendlimit::{counter_value, SpendLimit, SpendLimitProof, SpendLimitStatement};
 
-use acl::{sign::SigChall, sign::SigProof, sign::SigSign};
-use merlin::Transcript;
+use acl::{config::ACLConfig, sign::SigChall, sign::SigProof, sign::SigSign};
+use pedersen::transcript::{append_message, append_u64, challenge_bytes};
 use pedersen::{
     add_mul_protocol::AddMulProof, issuance_protocol::IssuanceProofMulti,
     opening_protocol::OpeningProofMulti, pedersen_config::Generators,
-    pedersen_config::PedersenComm,
+    pedersen_config::PedersenComm, pedersen_config::PedersenConfig,
 };
 
+use ark_ff::{BigInteger, PrimeField};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::{ops::Mul, UniformRand, Zero};
 
+use crate::trace::SpanTimer;
 use crate::utils::rewards::*;
 
+/// COLLECTION_M2_PHASES. The phases of `generate_collection_m2`, in order.
+pub const COLLECTION_M2_PHASES: &[ProofPhase] = &[
+    ProofPhase::Commitments,
+    ProofPhase::OpeningProofs,
+    ProofPhase::TagProof,
+    ProofPhase::SignatureProof,
+];
+
+/// SPEND_M2_PHASES. The phases of `generate_spendverify_m2`, in order.
+pub const SPEND_M2_PHASES: &[ProofPhase] = &[
+    ProofPhase::Commitments,
+    ProofPhase::OpeningProofs,
+    ProofPhase::TagProof,
+    ProofPhase::SubProof,
+    ProofPhase::SignatureProof,
+];
+
+/// SPEND_M4_PHASES. The phases of `generate_spendverify_m4`, in order.
+pub const SPEND_M4_PHASES: &[ProofPhase] = &[ProofPhase::RewardsCheck, ProofPhase::Challenge];
+
 /// The token representation.
 #[derive(Clone)]
 #[must_use]
@@ -40,13 +73,60 @@ pub struct Token<B: BoomerangConfig> {
     sk: <B as CurveConfig>::ScalarField,
     /// Random value
     r: <B as CurveConfig>::ScalarField,
+    /// attrs: the extra attributes, which follow the four base attributes.
+    attrs: Vec<<B as CurveConfig>::ScalarField>,
     /// gens: the generators of the committed values.
     pub gens: Generators<B>,
 }
 
+impl<B: BoomerangConfig> Token<B> {
+    /// from_vals. This function returns the token that holds the committed values `vals`.
+    /// # Arguments
+    /// * `vals` - the committed values, in the order of the generators.
+    /// * `gens` - the generators of the committed values.
+    fn from_vals(vals: &[<B as CurveConfig>::ScalarField], gens: Generators<B>) -> Self {
+        Self {
+            id: vals[0],
+            v: vals[1],
+            sk: vals[2],
+            r: vals[3],
+            attrs: vals[BASE_ATTRIBUTES..].to_vec(),
+            gens,
+        }
+    }
+
+    /// vals. Returns every committed value of the token, in the order of the generators.
+    fn vals(&self) -> Vec<<B as CurveConfig>::ScalarField> {
+        let mut vals = vec![self.id, self.v, self.sk, self.r];
+        vals.extend_from_slice(&self.attrs);
+        vals
+    }
+
+    /// opens. Returns true if `comm` is a commitment to the values of the token, under its
+    /// generators and the blinding factor of `comm`.
+    /// # Arguments
+    /// * `comm` - the commitment of the token.
+    pub(crate) fn opens(&self, comm: &PedersenComm<B>) -> bool {
+        comm.opens(&self.vals(), &self.gens)
+    }
+
+    /// value. Returns the balance of the token, e.g to pick the tokens that cover an amount
+    /// with `SpendVerifyStateC::generate_spendverify_m2_multi`.
+    pub fn value(&self) -> <B as CurveConfig>::ScalarField {
+        self.v
+    }
+
+    /// attrs. Returns the extra attributes of the token, i.e the attributes that follow the
+    /// serial number, the value, the secret key and the random value. The attribute at index `i`
+    /// is updated by running the Update Protocol with `attr = BASE_ATTRIBUTES + i`.
+    pub fn attrs(&self) -> &[<B as CurveConfig>::ScalarField] {
+        &self.attrs
+    }
+}
+
 /// Client keypair.
 ///
-#[derive(Clone, PartialEq)]
+#[derive(PartialEq)]
 #[must_use]
 pub struct UKeyPair<B: BoomerangConfig> {
     /// Public key
@@ -56,10 +136,19 @@ pub struct UKeyPair<B: BoomerangConfig> {
     x: <B as CurveConfig>::ScalarField,
 }
 
+impl<B: BoomerangConfig> Clone for UKeyPair<B> {
+    fn clone(&self) -> Self {
+        Self {
+            public_key: self.public_key,
+            x: self.x,
+        }
+    }
+}
+
 impl<B: BoomerangConfig> UKeyPair<B> {
     /// Generate a new user keypair
     #[inline]
-    pub fn generate<T: RngCore + CryptoRng>(rng: &mut T) -> Self {
+    pub fn generate<T: BoomerangRng>(rng: &mut T) -> Self {
         let x = <B as CurveConfig>::ScalarField::rand(rng);
 
         Self {
@@ -67,6 +156,36 @@ impl<B: BoomerangConfig> UKeyPair<B> {
             x,
         }
     }
+
+    /// from_seed. This function derives the user keypair from `seed`, so that it can be derived
+    /// again, e.g. to recover the user's tokens with a `BackupKeyPair` of the same seed.
+    /// # Arguments
+    /// * `seed` - the secret seed.
+    pub fn from_seed(seed: &KeySeed) -> Self {
+        let x = key_from_seed::<B>(b"BoomerangUserKey", seed);
+
+        Self {
+            public_key: (<B as SWCurveConfig>::GENERATOR.mul(x)).into_affine(),
+            x,
+        }
+    }
+}
+
+/// KeySeed. The secret seed that the keys of a user are derived from, e.g. from a backup
+/// phrase. It should be kept offline, apart from the keypair that it derives.
+pub type KeySeed = [u8; 32];
+
+/// key_from_seed. Returns the secret key that is derived from `seed` for `label`.
+fn key_from_seed<B: BoomerangConfig>(
+    label: &'static [u8],
+    seed: &KeySeed,
+) -> <B as CurveConfig>::ScalarField {
+    let mut transcript = B::CONTEXT.transcript(label);
+    append_message(&mut transcript, b"seed", seed);
+
+    let mut buf = [0u8; 64];
+    challenge_bytes(&mut transcript, b"key", &mut buf);
+    <B as ACLConfig>::make_challenge_from_buffer(&buf)
 }
 
 /// Issuance Protocol
@@ -84,6 +203,8 @@ pub struct IssuanceM1<B: BoomerangConfig> {
     pub len: usize,
     /// gens: the generators of the committed values.
     pub gens: Generators<B>,
+    /// nonce: the client's nonce for this session.
+    pub nonce: Nonce,
 }
 
 /// IssuanceM3. This struct acts as a container for the thrid message of
@@ -92,10 +213,12 @@ pub struct IssuanceM1<B: BoomerangConfig> {
 pub struct IssuanceM3<B: BoomerangConfig> {
     /// e: the signature challenge value.
     pub e: SigChall<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
 }
 
 /// IssuanceStateC. This struct represents the issuance protocol for the client.
-#[derive(Clone)]
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct IssuanceStateC<B: BoomerangConfig> {
     /// Serial Number
     id_0: <B as CurveConfig>::ScalarField,
@@ -109,8 +232,14 @@ pub struct IssuanceStateC<B: BoomerangConfig> {
     c: PedersenComm<B>,
     /// id: the serial number value.
     id: <B as CurveConfig>::ScalarField,
+    /// v: the initial balance of the token.
+    v: <B as CurveConfig>::ScalarField,
     /// e: the signature challenge value.
     e: SigChall<B>,
+    /// nonce: the client's nonce for this session.
+    nonce: Nonce,
+    /// session_id: the identifier of this session.
+    pub(crate) session_id: SessionId,
 }
 
 impl<B: BoomerangConfig> IssuanceStateC<B> {
@@ -124,7 +253,10 @@ impl<B: BoomerangConfig> IssuanceStateC<B> {
             gens: Generators::default(),
             c: PedersenComm::default(),
             id: <B as CurveConfig>::ScalarField::zero(),
+            v: <B as CurveConfig>::ScalarField::zero(),
             e: SigChall::default(),
+            nonce: Nonce::default(),
+            session_id: SessionId::default(),
         }
     }
 
@@ -132,11 +264,17 @@ impl<B: BoomerangConfig> IssuanceStateC<B> {
     /// # Arguments
     /// * `key_pair` - the client's keypair.
     /// * `rng` - the source of randomness.
-    pub fn generate_issuance_m1<T: RngCore + CryptoRng>(
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "issuance_m1", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_issuance_m1<T: BoomerangRng>(
         key_pair: &UKeyPair<B>,
         state: &mut IssuanceStateC<B>,
         rng: &mut T,
     ) -> IssuanceM1<B> {
+        let timer = SpanTimer::start();
+        let nonce = new_nonce(rng);
         let id_0 = <B as CurveConfig>::ScalarField::rand(rng);
         let v = <B as CurveConfig>::ScalarField::zero(); // the token starts with 0
         let r_0 = <B as CurveConfig>::ScalarField::rand(rng);
@@ -146,33 +284,97 @@ impl<B: BoomerangConfig> IssuanceStateC<B> {
         let (c1, gens) = PedersenComm::new_multi(&vals, rng);
 
         let label = b"BoomerangM1";
-        let mut transcript = Transcript::new(label);
+        let mut transcript = B::CONTEXT.transcript(label);
+        append_message(&mut transcript, b"client_nonce", &nonce);
         let proof = IssuanceProofMulti::create(&mut transcript, rng, &vals, &c1, &gens);
 
         state.id_0 = id_0;
+        state.nonce = nonce;
         state.r = r_0;
         state.gens = gens.clone();
         state.comm = c1;
 
-        IssuanceM1 {
+        timer.finish(IssuanceM1 {
             comm: c1,
             pi_issuance: proof,
             u_pk: key_pair.public_key,
             len: vals.len(),
             gens,
+            nonce,
+        })
+    }
+
+    /// verify_issuance_m2. This function checks the second message of the Issuance Protocol,
+    /// i.e that the server signs under its published keys, and the proof that the server's share
+    /// of the commitment holds the initial balance that the server claims, and nothing else.
+    /// A token under an unpublished key, e.g. a per-user tag key, could be recognised when it is
+    /// spent.
+    /// # Arguments
+    /// * `s_m` - the received server message.
+    /// * `state` - the client state.
+    /// * `s_key_pair` - the server's published keypair.
+    pub fn verify_issuance_m2(
+        s_m: &IssuanceM2<B>,
+        state: &IssuanceStateC<B>,
+        s_key_pair: &ServerKeyPair<B>,
+    ) -> Result<(), &'static str> {
+        if s_m.verifying_key != s_key_pair.s_key_pair.verifying_key
+            || s_m.tag_key != s_key_pair.s_key_pair.tag_key
+        {
+            return Err(
+                "Boomerang issuance: the server advertised another key than its published one",
+            );
         }
+
+        Self::verify_issuance_balance(s_m, state)
     }
 
-    /// generate_issuance_m2. This function generates the second message of the Issuance Protocol.
+    /// verify_issuance_balance. This function checks the proof of the second message of the
+    /// Issuance Protocol that the server's share of the commitment holds the initial balance
+    /// that the server claims, and nothing else.
+    /// # Arguments
+    /// * `s_m` - the received server message.
+    /// * `state` - the client state.
+    fn verify_issuance_balance(
+        s_m: &IssuanceM2<B>,
+        state: &IssuanceStateC<B>,
+    ) -> Result<(), &'static str> {
+        let session_id = issuance_session_id(&state.nonce, &s_m.nonce);
+        let expected = issuance_balance_comm(s_m.id_1, s_m.balance, &state.gens);
+
+        let label = b"BoomerangIssuanceM2Balance";
+        let mut transcript = session_transcript::<B>(label, &session_id);
+        if !s_m
+            .pi_balance
+            .verify(&mut transcript, &s_m.comm.comm, &expected.comm)
+        {
+            return Err("Boomerang issuance: invalid proof of balance");
+        }
+
+        Ok(())
+    }
+
+    /// generate_issuance_m3. This function generates the third message of the Issuance Protocol,
+    /// once it has checked the second with `verify_issuance_m2`.
     /// # Arguments
-    /// * `c_m` - the client message.
     /// * `s_m` - the received server message.
+    /// * `state` - the client state.
     /// * `rng` - the source of randomness.
-    pub fn generate_issuance_m3<T: RngCore + CryptoRng>(
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "issuance_m3", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_issuance_m3<T: BoomerangRng>(
         s_m: &IssuanceM2<B>,
         state: &mut IssuanceStateC<B>,
         rng: &mut T,
     ) -> IssuanceM3<B> {
+        let timer = SpanTimer::start();
+        if let Err(e) = Self::verify_issuance_balance(s_m, state) {
+            panic!("{}", timer.reject(e));
+        }
+
+        let session_id = issuance_session_id(&state.nonce, &s_m.nonce);
         let c = s_m.comm + state.comm;
         let id = s_m.id_1 + state.id_0;
 
@@ -181,18 +383,22 @@ impl<B: BoomerangConfig> IssuanceStateC<B> {
             s_m.verifying_key,
             rng,
             s_m.sig_commit,
-            "message",
+            &token_message::<B>(&s_m.verifying_key, &s_m.tag_key),
+            Some(TOKEN_CONTEXT),
         );
 
         let m3 = IssuanceM3 {
             e: sig_chall.clone(),
+            session_id,
         };
 
+        state.session_id = session_id;
         state.c = c;
         state.id = id;
+        state.v = s_m.balance;
         state.e = sig_chall.clone();
 
-        m3
+        timer.finish(m3)
     }
 
     /// populate_state. This function populates the local state for the client.
@@ -205,14 +411,19 @@ impl<B: BoomerangConfig> IssuanceStateC<B> {
         s_m: &IssuanceM4<B>,
         state: &mut IssuanceStateC<B>,
         s_key_pair: &ServerKeyPair<B>,
-        c_key_pair: UKeyPair<B>,
+        c_key_pair: &UKeyPair<B>,
     ) -> State<B> {
+        if s_m.session_id != state.session_id {
+            panic!("Boomerang issuance: session id mismatch");
+        }
+
         let sig = SigSign::sign(
             s_key_pair.s_key_pair.verifying_key,
             s_key_pair.s_key_pair.tag_key,
             &state.e,
             &s_m.s,
-            "message",
+            &s_key_pair.token_message(),
+            Some(TOKEN_CONTEXT),
         );
 
         // The comm_state
@@ -222,9 +433,10 @@ impl<B: BoomerangConfig> IssuanceStateC<B> {
         // The state: which contains the tokens
         let token = Token {
             id: state.id,
-            v: <B as CurveConfig>::ScalarField::zero(),
+            v: state.v,
             sk: c_key_pair.x,
             r: state.r,
+            attrs: Vec::new(),
             gens: state.gens.clone(),
         };
         let tokens: Vec<Token<B>> = vec![token];
@@ -233,7 +445,7 @@ impl<B: BoomerangConfig> IssuanceStateC<B> {
             comm_state: commits,
             sig_state: sigs,
             token_state: tokens,
-            c_key_pair,
+            c_key_pair: c_key_pair.clone(),
         }
     }
 }
@@ -267,6 +479,8 @@ pub struct CollectionM2<B: BoomerangConfig> {
     pub s_proof: SigProof<B>,
     /// tag_commits: the commits for the tag proof
     pub tag_commits: Vec<PedersenComm<B>>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
 }
 
 /// CollectionM4. This struct acts as a container for the fourth message of
@@ -275,10 +489,12 @@ pub struct CollectionM2<B: BoomerangConfig> {
 pub struct CollectionM4<B: BoomerangConfig> {
     /// e: the signature challenge value.
     pub e: SigChall<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
 }
 
 /// CollectionStateC. This struct represents the collection protocol for the client.
-#[derive(Clone)]
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct CollectionStateC<B: BoomerangConfig> {
     /// c: the final commit value.
     c: PedersenComm<B>,
@@ -296,8 +512,12 @@ pub struct CollectionStateC<B: BoomerangConfig> {
     r: <B as CurveConfig>::ScalarField,
     /// val: the underlying value of the token.
     val_0: <B as CurveConfig>::ScalarField,
+    /// attrs: the extra attributes of the token.
+    attrs: Vec<<B as CurveConfig>::ScalarField>,
     /// e: the signature challenge value.
     e: SigChall<B>,
+    /// session_id: the identifier of this session.
+    pub(crate) session_id: SessionId,
 }
 
 impl<B: BoomerangConfig> CollectionStateC<B> {
@@ -312,7 +532,9 @@ impl<B: BoomerangConfig> CollectionStateC<B> {
             id_0: <B as CurveConfig>::ScalarField::zero(),
             r: <B as CurveConfig>::ScalarField::zero(),
             val_0: <B as CurveConfig>::ScalarField::zero(),
+            attrs: Vec::new(),
             e: SigChall::default(),
+            session_id: SessionId::default(),
         }
     }
 
@@ -324,34 +546,60 @@ impl<B: BoomerangConfig> CollectionStateC<B> {
     /// * `s_m` - the received server message.
     /// * `col_state` - the tmp local client state.
     /// * `s_key_pair` - the server's keypair.
-    pub fn generate_collection_m2<T: RngCore + CryptoRng>(
+    pub fn generate_collection_m2<T: BoomerangRng>(
         rng: &mut T,
-        state: State<B>,
+        state: &State<B>,
         s_m: &CollectionM1<B>,
         col_state: &mut CollectionStateC<B>,
         s_key_pair: &ServerKeyPair<B>,
     ) -> CollectionM2<B> {
+        Self::generate_collection_m2_with_progress(rng, state, s_m, col_state, s_key_pair, None)
+            .expect("Boomerang collection: generation without a callback cannot be aborted")
+    }
+
+    /// generate_collection_m2_with_progress. This function generates the second message of
+    /// the Collection Protocol, and reports its progress to `progress`. This function fails,
+    /// without changing `col_state`, if `progress` aborts the generation.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `state` - the local client state.
+    /// * `s_m` - the received server message.
+    /// * `col_state` - the tmp local client state.
+    /// * `s_key_pair` - the server's keypair.
+    /// * `progress` - the optional progress callback.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "collection_m2", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_collection_m2_with_progress<T: BoomerangRng>(
+        rng: &mut T,
+        state: &State<B>,
+        s_m: &CollectionM1<B>,
+        col_state: &mut CollectionStateC<B>,
+        s_key_pair: &ServerKeyPair<B>,
+        progress: Option<ProgressCallback>,
+    ) -> Result<CollectionM2<B>, &'static str> {
+        let timer = SpanTimer::start();
+        let mut reporter = Reporter::new(progress, COLLECTION_M2_PHASES);
+        reporter.phase(ProofPhase::Commitments)?;
         let r1 = <B as CurveConfig>::ScalarField::rand(rng);
         let id1 = <B as CurveConfig>::ScalarField::rand(rng);
 
-        let vals: Vec<<B as CurveConfig>::ScalarField> =
+        let mut vals: Vec<<B as CurveConfig>::ScalarField> =
             vec![id1, state.token_state[0].v, state.c_key_pair.x, r1];
+        vals.extend_from_slice(state.token_state[0].attrs());
 
-        let prev_vals: Vec<<B as CurveConfig>::ScalarField> = vec![
-            state.token_state[0].id,
-            state.token_state[0].v,
-            state.token_state[0].sk,
-            state.token_state[0].r,
-        ];
+        let prev_vals: Vec<<B as CurveConfig>::ScalarField> = state.token_state[0].vals();
 
         let (c1, gens) = PedersenComm::new_multi(&vals, rng);
 
+        reporter.phase(ProofPhase::OpeningProofs)?;
         let label = b"BoomerangCollectionM2O1";
-        let mut transcript = Transcript::new(label);
+        let mut transcript = session_transcript::<B>(label, &s_m.session_id);
         let proof_1 = OpeningProofMulti::create(&mut transcript, rng, &vals, &c1, &gens);
 
         let label1 = b"BoomerangCollectionM2O2";
-        let mut transcript1 = Transcript::new(label1);
+        let mut transcript1 = session_transcript::<B>(label1, &s_m.session_id);
         let proof_2 = OpeningProofMulti::create(
             &mut transcript1,
             rng,
@@ -360,6 +608,7 @@ impl<B: BoomerangConfig> CollectionStateC<B> {
             &state.token_state[0].gens,
         );
 
+        reporter.phase(ProofPhase::TagProof)?;
         let t_tag = state.c_key_pair.x * state.token_state[0].id;
         let tag = t_tag + s_m.r2;
 
@@ -370,7 +619,7 @@ impl<B: BoomerangConfig> CollectionStateC<B> {
         let e: PedersenComm<B> = d + c;
 
         let label2 = b"BoomerangCollectionM2AM2";
-        let mut transcript2 = Transcript::new(label2);
+        let mut transcript2 = session_transcript::<B>(label2, &s_m.session_id);
         let proof_3 = AddMulProof::create(
             &mut transcript2,
             rng,
@@ -387,6 +636,7 @@ impl<B: BoomerangConfig> CollectionStateC<B> {
         let tag_commits: Vec<PedersenComm<B>> = vec![a, b, c, d, e];
         // TODO: add membership proof
 
+        reporter.phase(ProofPhase::SignatureProof)?;
         let sig_proof = SigProof::prove(
             rng,
             s_key_pair.s_key_pair.tag_key,
@@ -394,15 +644,19 @@ impl<B: BoomerangConfig> CollectionStateC<B> {
             &prev_vals,
             &state.token_state[0].gens.generators,
             state.comm_state[0].r,
+            Some(&s_m.session_id),
         );
 
         col_state.id_0 = id1;
         col_state.val_0 = state.token_state[0].v;
+        col_state.attrs.clone_from(&state.token_state[0].attrs);
         col_state.comm = c1;
         col_state.r = r1;
         col_state.gens = gens.clone();
+        col_state.session_id = s_m.session_id;
 
-        CollectionM2 {
+        reporter.done();
+        Ok(timer.finish(CollectionM2 {
             comm: c1,
             gens: gens.clone(),
             prev_comm: state.comm_state[0],
@@ -415,7 +669,8 @@ impl<B: BoomerangConfig> CollectionStateC<B> {
             sig: state.sig_state[0].clone(),
             s_proof: sig_proof,
             tag_commits,
-        }
+            session_id: s_m.session_id,
+        }))
     }
 
     /// generate_collection_m4. This function generates the fourth message of
@@ -424,11 +679,20 @@ impl<B: BoomerangConfig> CollectionStateC<B> {
     /// * `rng` - the source of randomness.
     /// * `c_m` - the client message.
     /// * `s_m` - the received server message.
-    pub fn generate_collection_m4<T: RngCore + CryptoRng>(
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "collection_m4", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_collection_m4<T: BoomerangRng>(
         rng: &mut T,
         col_state: &mut CollectionStateC<B>,
         s_m: &CollectionM3<B>,
     ) -> CollectionM4<B> {
+        let timer = SpanTimer::start();
+        if s_m.session_id != col_state.session_id {
+            panic!("Boomerang collection: session id mismatch");
+        }
+
         let c = s_m.comm + col_state.comm;
         let id = s_m.id_1 + col_state.id_0;
         let val = s_m.val + col_state.val_0;
@@ -438,7 +702,8 @@ impl<B: BoomerangConfig> CollectionStateC<B> {
             s_m.verifying_key,
             rng,
             s_m.sig_commit,
-            "message",
+            &token_message::<B>(&s_m.verifying_key, &s_m.tag_key),
+            Some(TOKEN_CONTEXT),
         );
 
         col_state.id = id;
@@ -446,9 +711,10 @@ impl<B: BoomerangConfig> CollectionStateC<B> {
         col_state.c = c;
         col_state.e = sig_chall.clone();
 
-        CollectionM4 {
+        timer.finish(CollectionM4 {
             e: sig_chall.clone(),
-        }
+            session_id: col_state.session_id,
+        })
     }
 
     /// populate_state. This function re-populates the local state for the client.
@@ -461,14 +727,19 @@ impl<B: BoomerangConfig> CollectionStateC<B> {
         col_state: &mut CollectionStateC<B>,
         s_m: &CollectionM5<B>,
         s_key_pair: &ServerKeyPair<B>,
-        c_key_pair: UKeyPair<B>,
+        c_key_pair: &UKeyPair<B>,
     ) -> State<B> {
+        if s_m.session_id != col_state.session_id {
+            panic!("Boomerang collection: session id mismatch");
+        }
+
         let sig = SigSign::sign(
             s_key_pair.s_key_pair.verifying_key,
             s_key_pair.s_key_pair.tag_key,
             &col_state.e,
             &s_m.s,
-            "message",
+            &s_key_pair.token_message(),
+            Some(TOKEN_CONTEXT),
         );
 
         let commits: Vec<PedersenComm<B>> = vec![col_state.c];
@@ -478,6 +749,7 @@ impl<B: BoomerangConfig> CollectionStateC<B> {
             v: col_state.val,
             sk: c_key_pair.x,
             r: col_state.r,
+            attrs: col_state.attrs.clone(),
             gens: col_state.gens.clone(),
         };
 
@@ -487,17 +759,16 @@ impl<B: BoomerangConfig> CollectionStateC<B> {
             comm_state: commits,
             sig_state: sigs,
             token_state: tokens,
-            c_key_pair,
+            c_key_pair: c_key_pair.clone(),
         }
     }
 }
 
-/// Spending/Verification Protocol
-
-/// SpendVerifyM2. This struct acts as a container for the second message of
-/// the spendverify protocol.
+/// Update Protocol
+/// UpdateM2. This struct acts as a container for the second message of
+/// the update protocol.
 #[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
-pub struct SpendVerifyM2<B: BoomerangConfig> {
+pub struct UpdateM2<B: BoomerangConfig> {
     /// comm: the commitment value.
     pub comm: PedersenComm<B>,
     /// gens: the generators of the commitment value.
@@ -512,9 +783,6 @@ pub struct SpendVerifyM2<B: BoomerangConfig> {
     pub pi_2: OpeningProofMulti<B>,
     /// pi_3: the proof of the tag.
     pub pi_3: AddMulProof<B>,
-    /// pi_4: the sub proof.
-    pub pi_4: SubProof<B>,
-    /// pi_5: the membership proof.
     /// tag: the tag value.
     pub tag: <B as CurveConfig>::ScalarField,
     /// id: the serial number value.
@@ -525,98 +793,100 @@ pub struct SpendVerifyM2<B: BoomerangConfig> {
     pub s_proof: SigProof<B>,
     /// tag_commits: the commits for the tag proof
     pub tag_commits: Vec<PedersenComm<B>>,
-    /// spend_state: the values to spend
-    pub spend_state: Vec<<B as CurveConfig>::ScalarField>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
 }
 
-/// SpendVerifyM4. This struct acts as a container for the fourth message of
-/// the spendverify protocol.
+/// UpdateM4. This struct acts as a container for the fourth message of
+/// the update protocol.
 #[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
-pub struct SpendVerifyM4<B: BoomerangConfig> {
+pub struct UpdateM4<B: BoomerangConfig> {
     /// e: the signature challenge value.
     pub e: SigChall<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
 }
 
-/// SpendVerifyC. This struct represents the spendverify protocol for the client.
+/// UpdateStateC. This struct represents the update protocol for the client.
 #[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
-pub struct SpendVerifyStateC<B: BoomerangConfig> {
-    /// c: the commit value.
+pub struct UpdateStateC<B: BoomerangConfig> {
+    /// c: the final commit value.
     c: PedersenComm<B>,
-    /// id: the serial number value.
-    id: <B as CurveConfig>::ScalarField,
-    /// val: the underlying value.
-    val: <B as CurveConfig>::ScalarField,
+    /// vals: the committed values of the updated token.
+    vals: Vec<<B as CurveConfig>::ScalarField>,
     /// comm: the initial commitment value.
     comm: PedersenComm<B>,
-    /// gens: the generators of the committed values.
+    /// gens: the generators of the commitment value.
     gens: Generators<B>,
-    /// id_0: the current serial number value.
-    id_0: <B as CurveConfig>::ScalarField,
-    /// r: the random double-spending tag value.
-    r: <B as CurveConfig>::ScalarField,
-    /// val_0: the underlying current value of the token.
-    val_0: <B as CurveConfig>::ScalarField,
+    /// attr: the index of the attribute that is updated.
+    attr: usize,
     /// e: the signature challenge value.
     e: SigChall<B>,
-    /// spend_state: the spent values.
-    spend_state: Vec<<B as CurveConfig>::ScalarField>,
+    /// session_id: the identifier of this session.
+    pub(crate) session_id: SessionId,
 }
 
-impl<B: BoomerangConfig> SpendVerifyStateC<B> {
+impl<B: BoomerangConfig> UpdateStateC<B> {
     #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         Self {
             c: PedersenComm::default(),
-            id: <B as CurveConfig>::ScalarField::zero(),
-            val: <B as CurveConfig>::ScalarField::zero(),
+            vals: Vec::new(),
             comm: PedersenComm::default(),
             gens: Generators::default(),
-            id_0: <B as CurveConfig>::ScalarField::zero(),
-            r: <B as CurveConfig>::ScalarField::zero(),
-            val_0: <B as CurveConfig>::ScalarField::zero(),
+            attr: 0,
             e: SigChall::default(),
-            spend_state: Vec::default(),
+            session_id: SessionId::default(),
         }
     }
 
-    /// generate_spendverify_m2. This function generates the second message of
-    /// the Spend/Verify Protocol.
+    /// generate_update_m2. This function generates the second message of
+    /// the Update Protocol. This function panics if the attribute that the server
+    /// asks to update is neither an attribute of the token, nor the next new attribute.
     /// # Arguments
     /// * `rng` - the source of randomness.
     /// * `state` - the local client state.
-    /// * `s_state` - the tmp client state.
     /// * `s_m` - the received server message.
+    /// * `up_state` - the tmp local client state.
     /// * `s_key_pair` - the server's keypair.
-    /// * `spend_state` - the values to spend passed as a vector.
-    pub fn generate_spendverify_m2<T: RngCore + CryptoRng>(
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "update_m2", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_update_m2<T: BoomerangRng>(
         rng: &mut T,
-        state: State<B>,
-        s_state: &mut SpendVerifyStateC<B>,
-        s_m: &SpendVerifyM1<B>,
+        state: &State<B>,
+        s_m: &UpdateM1<B>,
+        up_state: &mut UpdateStateC<B>,
         s_key_pair: &ServerKeyPair<B>,
-        spend_state: Vec<<B as CurveConfig>::ScalarField>,
-    ) -> SpendVerifyM2<B> {
+    ) -> UpdateM2<B> {
+        let timer = SpanTimer::start();
         let r1 = <B as CurveConfig>::ScalarField::rand(rng);
         let id1 = <B as CurveConfig>::ScalarField::rand(rng);
 
-        let vals: Vec<<B as CurveConfig>::ScalarField> =
+        let mut vals: Vec<<B as CurveConfig>::ScalarField> =
             vec![id1, state.token_state[0].v, state.c_key_pair.x, r1];
+        vals.extend_from_slice(state.token_state[0].attrs());
+
+        // A new attribute starts at zero.
+        let attr = s_m.attr as usize;
+        if attr > vals.len() {
+            panic!("Boomerang update: attribute out of range");
+        }
+        if attr == vals.len() {
+            vals.push(<B as CurveConfig>::ScalarField::zero());
+        }
 
-        let prev_vals: Vec<<B as CurveConfig>::ScalarField> = vec![
-            state.token_state[0].id,
-            state.token_state[0].v,
-            state.token_state[0].sk,
-            state.token_state[0].r,
-        ];
+        let prev_vals: Vec<<B as CurveConfig>::ScalarField> = state.token_state[0].vals();
 
         let (c1, gens) = PedersenComm::new_multi(&vals, rng);
 
-        let label = b"BoomerangSpendVerifyM2O1";
-        let mut transcript = Transcript::new(label);
+        let label = b"BoomerangUpdateM2O1";
+        let mut transcript = session_transcript::<B>(label, &s_m.session_id);
         let proof_1 = OpeningProofMulti::create(&mut transcript, rng, &vals, &c1, &gens);
 
-        let label1 = b"BoomerangSpendVerifyM2O2";
-        let mut transcript1 = Transcript::new(label1);
+        let label1 = b"BoomerangUpdateM2O2";
+        let mut transcript1 = session_transcript::<B>(label1, &s_m.session_id);
         let proof_2 = OpeningProofMulti::create(
             &mut transcript1,
             rng,
@@ -634,8 +904,8 @@ impl<B: BoomerangConfig> SpendVerifyStateC<B> {
         let d: PedersenComm<B> = PedersenComm::new(t_tag, rng);
         let e: PedersenComm<B> = d + c;
 
-        let label2 = b"BoomerangSpendVerifyM2AM2";
-        let mut transcript2 = Transcript::new(label2);
+        let label2 = b"BoomerangUpdateM2AM2";
+        let mut transcript2 = session_transcript::<B>(label2, &s_m.session_id);
         let proof_3 = AddMulProof::create(
             &mut transcript2,
             rng,
@@ -649,23 +919,7 @@ impl<B: BoomerangConfig> SpendVerifyStateC<B> {
             &e,
         );
 
-        // Calculate the sub_proof
-        let spend = state.token_state[0].v - spend_state[0];
-
-        let mut compressed_bytes = Vec::new();
-        spend.serialize_compressed(&mut compressed_bytes).unwrap();
-
-        let spend_u64 = match extract_u64_from_compressed_data(&compressed_bytes) {
-            Ok(value) => value,
-            Err(_e) => {
-                panic!("Boomerang verification: failed to serialise")
-            }
-        };
-
-        let sub_proof = SubProof::prove(spend_u64, rng);
-
         let tag_commits: Vec<PedersenComm<B>> = vec![a, b, c, d, e];
-        // TODO: add membership proof
 
         let sig_proof = SigProof::prove(
             rng,
@@ -674,16 +928,16 @@ impl<B: BoomerangConfig> SpendVerifyStateC<B> {
             &prev_vals,
             &state.token_state[0].gens.generators,
             state.comm_state[0].r,
+            Some(&s_m.session_id),
         );
 
-        s_state.r = r1;
-        s_state.val_0 = state.token_state[0].v;
-        s_state.spend_state.clone_from(&spend_state);
-        s_state.comm = c1;
-        s_state.id_0 = id1;
-        s_state.gens = gens.clone();
+        up_state.vals = vals;
+        up_state.comm = c1;
+        up_state.gens = gens.clone();
+        up_state.attr = attr;
+        up_state.session_id = s_m.session_id;
 
-        SpendVerifyM2 {
+        timer.finish(UpdateM2 {
             comm: c1,
             gens,
             prev_comm: state.comm_state[0],
@@ -691,79 +945,1878 @@ impl<B: BoomerangConfig> SpendVerifyStateC<B> {
             pi_1: proof_1,
             pi_2: proof_2,
             pi_3: proof_3,
-            pi_4: sub_proof,
             tag,
             id: id1,
             sig: state.sig_state[0].clone(),
             s_proof: sig_proof,
             tag_commits,
-            spend_state: spend_state.clone(),
-        }
+            session_id: s_m.session_id,
+        })
     }
 
-    pub fn generate_spendverify_m4<T: RngCore + CryptoRng>(
+    /// generate_update_m4. This function generates the fourth message of
+    /// the Update Protocol.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `up_state` - the tmp local client state.
+    /// * `s_m` - the received server message.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "update_m4", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_update_m4<T: BoomerangRng>(
         rng: &mut T,
-        s_state: &mut SpendVerifyStateC<B>,
-        s_m: &SpendVerifyM3<B>,
-    ) -> SpendVerifyM4<B> {
-        // Verify rewards proof
-        let reward_proof = &s_m.pi_reward;
-        let check = reward_proof.verify(&s_state.spend_state);
-        if check.is_err() {
-            panic!("Boomerang verification: reward proof verification failed")
+        up_state: &mut UpdateStateC<B>,
+        s_m: &UpdateM3<B>,
+    ) -> UpdateM4<B> {
+        let timer = SpanTimer::start();
+        if s_m.session_id != up_state.session_id {
+            panic!("Boomerang update: session id mismatch");
         }
 
-        // The other way around to handle the negative
-        let c = s_state.comm - s_m.comm;
-        let id = s_state.id_0 - s_m.id_1;
-        let val = s_state.val_0 - s_m.val;
+        let c = s_m.comm + up_state.comm;
+        up_state.vals[0] += s_m.id_1;
+        up_state.vals[up_state.attr] += s_m.delta;
 
         let sig_chall = SigChall::challenge(
             s_m.tag_key,
             s_m.verifying_key,
             rng,
             s_m.sig_commit,
-            "message",
+            &token_message::<B>(&s_m.verifying_key, &s_m.tag_key),
+            Some(TOKEN_CONTEXT),
         );
 
-        s_state.id = id;
-        s_state.val = val;
-        s_state.c = c;
-        s_state.e = sig_chall.clone();
+        up_state.c = c;
+        up_state.e = sig_chall.clone();
 
-        SpendVerifyM4 { e: sig_chall }
+        timer.finish(UpdateM4 {
+            e: sig_chall,
+            session_id: up_state.session_id,
+        })
     }
 
+    /// populate_state. This function re-populates the local state for the client.
+    /// # Arguments
+    /// * `up_state` - the tmp local client state.
+    /// * `s_m` - the received server message.
+    /// * `s_key_pair` - the server's keypair.
+    /// * `c_key_pair` - the client's keypair.
     pub fn populate_state(
-        s_state: &mut SpendVerifyStateC<B>,
-        s_m: &SpendVerifyM5<B>,
+        up_state: &mut UpdateStateC<B>,
+        s_m: &UpdateM5<B>,
         s_key_pair: &ServerKeyPair<B>,
-        c_key_pair: UKeyPair<B>,
+        c_key_pair: &UKeyPair<B>,
     ) -> State<B> {
+        if s_m.session_id != up_state.session_id {
+            panic!("Boomerang update: session id mismatch");
+        }
+
         let sig = SigSign::sign(
             s_key_pair.s_key_pair.verifying_key,
             s_key_pair.s_key_pair.tag_key,
-            &s_state.e,
+            &up_state.e,
             &s_m.s,
-            "message",
+            &s_key_pair.token_message(),
+            Some(TOKEN_CONTEXT),
         );
 
-        let commits: Vec<PedersenComm<B>> = vec![s_state.c];
-        let sigs: Vec<SigSign<B>> = vec![sig];
-        let token = Token {
-            id: s_state.id,
-            v: s_state.val,
-            sk: c_key_pair.x,
-            r: s_state.r,
-            gens: s_state.gens.clone(),
-        };
-        let tokens: Vec<Token<B>> = vec![token];
-
         State {
-            comm_state: commits,
-            sig_state: sigs,
-            token_state: tokens,
-            c_key_pair,
+            comm_state: vec![up_state.c],
+            sig_state: vec![sig],
+            token_state: vec![Token::from_vals(&up_state.vals, up_state.gens.clone())],
+            c_key_pair: c_key_pair.clone(),
         }
     }
 }
+
+/// Spending/Verification Protocol
+
+/// SpendVerifyM2. This struct acts as a container for the second message of
+/// the spendverify protocol.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SpendVerifyM2<B: BoomerangConfig> {
+    /// comm: the commitment value.
+    pub comm: PedersenComm<B>,
+    /// gens: the generators of the commitment value.
+    pub gens: Generators<B>,
+    /// prev_comm: the commitment value.
+    pub prev_comm: PedersenComm<B>,
+    /// prev_gens: the generators of the commitment value.
+    pub prev_gens: Generators<B>,
+    /// pi_1: the proof value of the generated commitment.
+    pub pi_1: OpeningProofMulti<B>,
+    /// pi_2: the proof value of the previous commitment.
+    pub pi_2: OpeningProofMulti<B>,
+    /// pi_3: the proof of the tag.
+    pub pi_3: AddMulProof<B>,
+    /// pi_4: the sub proof.
+    pub pi_4: SubProof<B>,
+    /// pi_5: the membership proof.
+    /// tag: the tag value.
+    pub tag: <B as CurveConfig>::ScalarField,
+    /// id: the serial number value.
+    pub id: <B as CurveConfig>::ScalarField,
+    /// sig: the signature
+    pub sig: SigSign<B>,
+    /// s_proof: the proof of the commitments under the signature
+    pub s_proof: SigProof<B>,
+    /// tag_commits: the commits for the tag proof
+    pub tag_commits: Vec<PedersenComm<B>>,
+    /// spend_state: the values to spend
+    pub spend_state: Vec<<B as CurveConfig>::ScalarField>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
+}
+
+/// SpendInput. This struct holds the part of a `SpendVerifyM2Multi` that presents one of the
+/// spent tokens.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SpendInput<B: BoomerangConfig> {
+    /// prev_comm: the signed commitment of the token.
+    pub prev_comm: PedersenComm<B>,
+    /// prev_gens: the generators of the signed commitment.
+    pub prev_gens: Generators<B>,
+    /// pi_2: the proof of opening of the signed commitment.
+    pub pi_2: OpeningProofMulti<B>,
+    /// pi_3: the proof of the tag.
+    pub pi_3: AddMulProof<B>,
+    /// tag: the tag value.
+    pub tag: <B as CurveConfig>::ScalarField,
+    /// id: the serial number of the token.
+    pub id: <B as CurveConfig>::ScalarField,
+    /// sig: the signature on the token.
+    pub sig: SigSign<B>,
+    /// s_proof: the proof of the commitment under the signature.
+    pub s_proof: SigProof<B>,
+    /// tag_commits: the commits for the tag proof.
+    pub tag_commits: Vec<PedersenComm<B>>,
+    /// balance_comm: the commitment to the balance of the token, under the `pc_gens` of
+    /// `RewardsGenerators::shared`.
+    pub balance_comm: sw::Affine<B>,
+    /// pi_balance: the proof that `balance_comm` commits to the balance of the signed
+    /// commitment. It is made alongside `pi_2`.
+    pub pi_balance: ValueLinkProof<B>,
+}
+
+/// SpendVerifyM2Multi. This struct acts as a container for the second message of the
+/// spendverify protocol, when the client spends several tokens at once. The new token holds the
+/// sum of the balances of the spent tokens, and `pi_4` shows that this sum covers the amount.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SpendVerifyM2Multi<B: BoomerangConfig> {
+    /// comm: the commitment to the new token.
+    pub comm: PedersenComm<B>,
+    /// gens: the generators of the commitment to the new token.
+    pub gens: Generators<B>,
+    /// pi_1: the proof of opening of the commitment to the new token.
+    pub pi_1: OpeningProofMulti<B>,
+    /// pi_balance: the proof that the new token holds the sum of the balance commitments of
+    /// the inputs. It is made alongside `pi_1`.
+    pub pi_balance: ValueLinkProof<B>,
+    /// inputs: the spent tokens.
+    pub inputs: Vec<SpendInput<B>>,
+    /// pi_4: the range proof on the sum of the balances minus the amount. Its commitment is the
+    /// sum of the balance commitments minus the amount.
+    pub pi_4: SubProof<B>,
+    /// spend_state: the values to spend
+    pub spend_state: Vec<<B as CurveConfig>::ScalarField>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
+}
+
+/// SpendPrecomputation. This struct holds the parts of the second message of the spendverify
+/// protocol that can be computed before the server's first message is received (see
+/// `SpendVerifyStateC::precompute`). It holds secret values, and must not be shared.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct SpendPrecomputation<B: BoomerangConfig> {
+    /// prev_id: the serial number of the token that the bundle is computed for.
+    prev_id: <B as CurveConfig>::ScalarField,
+    /// vals: the committed values of the new token.
+    vals: Vec<<B as CurveConfig>::ScalarField>,
+    /// comm: the commitment to the new token.
+    comm: PedersenComm<B>,
+    /// gens: the generators of the commitment to the new token.
+    gens: Generators<B>,
+    /// tag_sk: the commitment to the secret key for the tag proof.
+    tag_sk: PedersenComm<B>,
+    /// tag_id: the commitment to the serial number for the tag proof.
+    tag_id: PedersenComm<B>,
+    /// tag_prod: the commitment to their product for the tag proof.
+    tag_prod: PedersenComm<B>,
+    /// sub_blind: the blinding factor of the sub proof.
+    sub_blind: <B as CurveConfig>::ScalarField,
+}
+
+/// SpendVerifyM4. This struct acts as a container for the fourth message of
+/// the spendverify protocol.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SpendVerifyM4<B: BoomerangConfig> {
+    /// e: the signature challenge value.
+    pub e: SigChall<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
+}
+
+/// SpendVerifyC. This struct represents the spendverify protocol for the client.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SpendVerifyStateC<B: BoomerangConfig> {
+    /// c: the commit value.
+    c: PedersenComm<B>,
+    /// id: the serial number value.
+    id: <B as CurveConfig>::ScalarField,
+    /// val: the underlying value.
+    val: <B as CurveConfig>::ScalarField,
+    /// comm: the initial commitment value.
+    comm: PedersenComm<B>,
+    /// gens: the generators of the committed values.
+    gens: Generators<B>,
+    /// id_0: the current serial number value.
+    id_0: <B as CurveConfig>::ScalarField,
+    /// r: the random double-spending tag value.
+    r: <B as CurveConfig>::ScalarField,
+    /// val_0: the underlying current value of the token.
+    val_0: <B as CurveConfig>::ScalarField,
+    /// attrs: the extra attributes of the token.
+    attrs: Vec<<B as CurveConfig>::ScalarField>,
+    /// e: the signature challenge value.
+    e: SigChall<B>,
+    /// spend_state: the spent values.
+    spend_state: Vec<<B as CurveConfig>::ScalarField>,
+    /// session_id: the identifier of this session.
+    pub(crate) session_id: SessionId,
+}
+
+impl<B: BoomerangConfig> SpendVerifyStateC<B> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        Self {
+            c: PedersenComm::default(),
+            id: <B as CurveConfig>::ScalarField::zero(),
+            val: <B as CurveConfig>::ScalarField::zero(),
+            comm: PedersenComm::default(),
+            gens: Generators::default(),
+            id_0: <B as CurveConfig>::ScalarField::zero(),
+            r: <B as CurveConfig>::ScalarField::zero(),
+            val_0: <B as CurveConfig>::ScalarField::zero(),
+            attrs: Vec::new(),
+            e: SigChall::default(),
+            spend_state: Vec::default(),
+            session_id: SessionId::default(),
+        }
+    }
+
+    /// generate_spendverify_m2. This function generates the second message of
+    /// the Spend/Verify Protocol.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `state` - the local client state.
+    /// * `s_state` - the tmp client state.
+    /// * `s_m` - the received server message.
+    /// * `s_key_pair` - the server's keypair.
+    /// * `spend_state` - the values to spend passed as a slice.
+    pub fn generate_spendverify_m2<T: BoomerangRng>(
+        rng: &mut T,
+        state: &State<B>,
+        s_state: &mut SpendVerifyStateC<B>,
+        s_m: &SpendVerifyM1<B>,
+        s_key_pair: &ServerKeyPair<B>,
+        spend_state: &[<B as CurveConfig>::ScalarField],
+    ) -> SpendVerifyM2<B> {
+        Self::generate_spendverify_m2_with_progress(
+            rng,
+            state,
+            s_state,
+            s_m,
+            s_key_pair,
+            spend_state,
+            None,
+        )
+        .expect("Boomerang spend-verify: generation without a callback cannot be aborted")
+    }
+
+    /// generate_spendverify_m2_with_progress. This function generates the second message of
+    /// the Spend/Verify Protocol, and reports its progress to `progress`. This function fails,
+    /// without changing `s_state`, if `progress` aborts the generation.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `state` - the local client state.
+    /// * `s_state` - the tmp client state.
+    /// * `s_m` - the received server message.
+    /// * `s_key_pair` - the server's keypair.
+    /// * `spend_state` - the values to spend passed as a slice.
+    /// * `progress` - the optional progress callback.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "spend_m2", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_spendverify_m2_with_progress<T: BoomerangRng>(
+        rng: &mut T,
+        state: &State<B>,
+        s_state: &mut SpendVerifyStateC<B>,
+        s_m: &SpendVerifyM1<B>,
+        s_key_pair: &ServerKeyPair<B>,
+        spend_state: &[<B as CurveConfig>::ScalarField],
+        progress: Option<ProgressCallback>,
+    ) -> Result<SpendVerifyM2<B>, &'static str> {
+        let timer = SpanTimer::start();
+        let mut reporter = Reporter::new(progress, SPEND_M2_PHASES);
+        reporter.phase(ProofPhase::Commitments)?;
+        let pre = Self::precompute(rng, state);
+        Self::finish_spendverify_m2(
+            rng,
+            state,
+            s_state,
+            s_m,
+            s_key_pair,
+            spend_state,
+            pre,
+            &mut reporter,
+        )
+        .map(|m2| timer.finish(m2))
+    }
+
+    /// precompute. This function computes the parts of the second message of the Spend/Verify
+    /// Protocol that do not depend on the server's first message, i.e the commitment to the new
+    /// token, the commitments of the tag proof and the blinding factor of the sub proof, so that
+    /// they can be computed ahead of time, e.g while the client is idle.
+    /// The bundle must be used at most once: it is consumed by
+    /// `generate_spendverify_m2_precomputed`, and it only holds for the token in `state`.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `state` - the local client state, which holds the token that will be spent.
+    pub fn precompute<T: BoomerangRng>(rng: &mut T, state: &State<B>) -> SpendPrecomputation<B> {
+        Self::precompute_with_attrs(rng, state, state.token_state[0].attrs())
+    }
+
+    /// precompute_with_attrs. This function computes the bundle of `precompute`, for a new
+    /// token that holds `attrs` rather than the attributes of the spent token.
+    fn precompute_with_attrs<T: BoomerangRng>(
+        rng: &mut T,
+        state: &State<B>,
+        attrs: &[<B as CurveConfig>::ScalarField],
+    ) -> SpendPrecomputation<B> {
+        let token = &state.token_state[0];
+        let r1 = <B as CurveConfig>::ScalarField::rand(rng);
+        let id1 = <B as CurveConfig>::ScalarField::rand(rng);
+
+        let mut vals: Vec<<B as CurveConfig>::ScalarField> =
+            vec![id1, token.v, state.c_key_pair.x, r1];
+        vals.extend_from_slice(attrs);
+
+        let (comm, gens) = PedersenComm::new_multi(&vals, rng);
+
+        let t_tag = state.c_key_pair.x * token.id;
+        SpendPrecomputation {
+            prev_id: token.id,
+            vals,
+            comm,
+            gens,
+            tag_sk: PedersenComm::new(state.c_key_pair.x, rng),
+            tag_id: PedersenComm::new(token.id, rng),
+            tag_prod: PedersenComm::new(t_tag, rng),
+            sub_blind: <B as CurveConfig>::ScalarField::rand(rng),
+        }
+    }
+
+    /// generate_spendverify_m2_precomputed. This function generates the second message of the
+    /// Spend/Verify Protocol, as in `generate_spendverify_m2`, from the bundle that was returned
+    /// by `precompute`, so that only the parts that depend on `s_m` are computed online.
+    /// This function fails, without changing `s_state`, if `pre` was computed for another token.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `state` - the local client state.
+    /// * `s_state` - the tmp client state.
+    /// * `s_m` - the received server message.
+    /// * `s_key_pair` - the server's keypair.
+    /// * `spend_state` - the values to spend passed as a slice.
+    /// * `pre` - the precomputed bundle.
+    pub fn generate_spendverify_m2_precomputed<T: BoomerangRng>(
+        rng: &mut T,
+        state: &State<B>,
+        s_state: &mut SpendVerifyStateC<B>,
+        s_m: &SpendVerifyM1<B>,
+        s_key_pair: &ServerKeyPair<B>,
+        spend_state: &[<B as CurveConfig>::ScalarField],
+        pre: SpendPrecomputation<B>,
+    ) -> Result<SpendVerifyM2<B>, &'static str> {
+        let token = &state.token_state[0];
+        if pre.prev_id != token.id || pre.vals[1] != token.v || pre.vals[2] != state.c_key_pair.x {
+            return Err("Boomerang spend-verify: precomputation is for another token");
+        }
+
+        let mut reporter = Reporter::new(None, SPEND_M2_PHASES);
+        reporter.phase(ProofPhase::Commitments)?;
+        Self::finish_spendverify_m2(
+            rng,
+            state,
+            s_state,
+            s_m,
+            s_key_pair,
+            spend_state,
+            pre,
+            &mut reporter,
+        )
+    }
+
+    /// generate_spendverify_m2_with_spend_limit. This function generates the second message of
+    /// the Spend/Verify Protocol, as in `generate_spendverify_m2`, for a token that counts its
+    /// spends in the attribute of `limit`. The new token moves the counter into the window of
+    /// `epoch`, and the returned proof shows that it does, so that the server can check that
+    /// the token was spent fewer than `limit.limit()` times in `epoch` (see
+    /// `SpendVerifyStateS::verify_spend_limit`).
+    /// This function fails, without changing `s_state`, if the token does not hold the counter,
+    /// or if the limit of the epoch is reached.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `state` - the local client state.
+    /// * `s_state` - the tmp client state.
+    /// * `s_m` - the received server message.
+    /// * `s_key_pair` - the server's keypair.
+    /// * `spend_state` - the values to spend passed as a slice.
+    /// * `limit` - the spend limit.
+    /// * `epoch` - the current epoch.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_spendverify_m2_with_spend_limit<T: BoomerangRng>(
+        rng: &mut T,
+        state: &State<B>,
+        s_state: &mut SpendVerifyStateC<B>,
+        s_m: &SpendVerifyM1<B>,
+        s_key_pair: &ServerKeyPair<B>,
+        spend_state: &[<B as CurveConfig>::ScalarField],
+        limit: &SpendLimit,
+        epoch: u64,
+    ) -> Result<(SpendVerifyM2<B>, SpendLimitProof<B>), &'static str> {
+        let token = &state.token_state[0];
+        let index = limit
+            .attr()
+            .checked_sub(BASE_ATTRIBUTES)
+            .filter(|index| *index < token.attrs.len())
+            .ok_or("Boomerang spend limit: the token has no counter")?;
+        let counter = counter_value::<B>(&token.attrs[index])
+            .ok_or("Boomerang spend limit: the counter does not fit in 64 bits")?;
+        let next = limit
+            .next_counter(counter, epoch)
+            .ok_or("Boomerang spend limit: the limit of the epoch is reached")?;
+
+        let mut attrs = token.attrs.clone();
+        attrs[index] = <B as CurveConfig>::ScalarField::from(next);
+        let prev_vals = token.vals();
+        let pre = Self::precompute_with_attrs(rng, state, &attrs);
+        let (comm, vals) = (pre.comm, pre.vals.clone());
+
+        let mut reporter = Reporter::new(None, SPEND_M2_PHASES);
+        reporter.phase(ProofPhase::Commitments)?;
+        let m2 = Self::finish_spendverify_m2(
+            rng,
+            state,
+            s_state,
+            s_m,
+            s_key_pair,
+            spend_state,
+            pre,
+            &mut reporter,
+        )?;
+
+        let statement = SpendLimitStatement {
+            session_id: &s_m.session_id,
+            limit,
+            epoch,
+            prev_comm: &m2.prev_comm.comm,
+            prev_gens: &m2.prev_gens,
+            comm: &m2.comm.comm,
+            gens: &m2.gens,
+        };
+        let pi = SpendLimitProof::create(
+            rng,
+            &statement,
+            &state.comm_state[0],
+            &prev_vals,
+            &comm,
+            &vals,
+        )?;
+        Ok((m2, pi))
+    }
+
+    /// finish_spendverify_m2. This function generates the parts of the second message of the
+    /// Spend/Verify Protocol that depend on `s_m`, and assembles the message.
+    #[allow(clippy::too_many_arguments)]
+    fn finish_spendverify_m2<T: BoomerangRng>(
+        rng: &mut T,
+        state: &State<B>,
+        s_state: &mut SpendVerifyStateC<B>,
+        s_m: &SpendVerifyM1<B>,
+        s_key_pair: &ServerKeyPair<B>,
+        spend_state: &[<B as CurveConfig>::ScalarField],
+        pre: SpendPrecomputation<B>,
+        reporter: &mut Reporter,
+    ) -> Result<SpendVerifyM2<B>, &'static str> {
+        let SpendPrecomputation {
+            vals,
+            comm: c1,
+            gens,
+            tag_sk: a,
+            tag_id: b,
+            tag_prod: d,
+            sub_blind,
+            ..
+        } = pre;
+        let (id1, r1) = (vals[0], vals[3]);
+        let prev_vals: Vec<<B as CurveConfig>::ScalarField> = state.token_state[0].vals();
+
+        reporter.phase(ProofPhase::OpeningProofs)?;
+        let label = b"BoomerangSpendVerifyM2O1";
+        let mut transcript = session_transcript::<B>(label, &s_m.session_id);
+        let proof_1 = OpeningProofMulti::create(&mut transcript, rng, &vals, &c1, &gens);
+
+        let label1 = b"BoomerangSpendVerifyM2O2";
+        let mut transcript1 = session_transcript::<B>(label1, &s_m.session_id);
+        let proof_2 = OpeningProofMulti::create(
+            &mut transcript1,
+            rng,
+            &prev_vals,
+            &state.comm_state[0],
+            &state.token_state[0].gens,
+        );
+
+        reporter.phase(ProofPhase::TagProof)?;
+        let t_tag = state.c_key_pair.x * state.token_state[0].id;
+        let tag = t_tag + s_m.r2;
+
+        let c: PedersenComm<B> = PedersenComm::new(s_m.r2, rng);
+        let e: PedersenComm<B> = d + c;
+
+        let label2 = b"BoomerangSpendVerifyM2AM2";
+        let mut transcript2 = session_transcript::<B>(label2, &s_m.session_id);
+        let proof_3 = AddMulProof::create(
+            &mut transcript2,
+            rng,
+            &state.c_key_pair.x,
+            &state.token_state[0].id,
+            &s_m.r2,
+            &a,
+            &b,
+            &c,
+            &d,
+            &e,
+        );
+
+        // Calculate the sub_proof
+        reporter.phase(ProofPhase::SubProof)?;
+        let spend = state.token_state[0].v - spend_state[0];
+
+        let spend_bytes = encode_scalar(&spend, Endianness::Little);
+
+        let spend_u64 = match extract_u64_from_compressed_data(&spend_bytes) {
+            Ok(value) => value,
+            Err(_e) => {
+                panic!("Boomerang verification: failed to serialise")
+            }
+        };
+
+        let sub_proof = SubProof::prove_with_blind(spend_u64, sub_blind, &s_m.session_id, rng);
+
+        let tag_commits: Vec<PedersenComm<B>> = vec![a, b, c, d, e];
+        // TODO: add membership proof
+
+        reporter.phase(ProofPhase::SignatureProof)?;
+        let sig_proof = SigProof::prove(
+            rng,
+            s_key_pair.s_key_pair.tag_key,
+            &state.sig_state[0],
+            &prev_vals,
+            &state.token_state[0].gens.generators,
+            state.comm_state[0].r,
+            Some(&s_m.session_id),
+        );
+
+        s_state.r = r1;
+        s_state.val_0 = state.token_state[0].v;
+        s_state.attrs = vals[BASE_ATTRIBUTES..].to_vec();
+        s_state.spend_state = spend_state.to_vec();
+        s_state.comm = c1;
+        s_state.id_0 = id1;
+        s_state.gens = gens.clone();
+        s_state.session_id = s_m.session_id;
+
+        reporter.done();
+        Ok(SpendVerifyM2 {
+            comm: c1,
+            gens,
+            prev_comm: state.comm_state[0],
+            prev_gens: state.token_state[0].gens.clone(),
+            pi_1: proof_1,
+            pi_2: proof_2,
+            pi_3: proof_3,
+            pi_4: sub_proof,
+            tag,
+            id: id1,
+            sig: state.sig_state[0].clone(),
+            s_proof: sig_proof,
+            tag_commits,
+            spend_state: spend_state.to_vec(),
+            session_id: s_m.session_id,
+        })
+    }
+
+    /// generate_spendverify_m2_multi. This function generates the second message of the
+    /// Spend/Verify Protocol for every token in `state`, so that the client can spend an amount
+    /// that exceeds the balance of any single token. The tokens are replaced by a single new
+    /// token, which holds the sum of their balances and the attributes of the first token.
+    /// The rest of the protocol is the same as for a single token.
+    /// This function fails if `state` holds no token, if the same token is presented twice, or
+    /// if the balances do not cover the amount.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `state` - the local client state, which holds the tokens to spend.
+    /// * `s_state` - the tmp client state.
+    /// * `s_m` - the received server message.
+    /// * `s_key_pair` - the server's keypair.
+    /// * `spend_state` - the values to spend passed as a slice.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "spend_m2_multi", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_spendverify_m2_multi<T: BoomerangRng>(
+        rng: &mut T,
+        state: &State<B>,
+        s_state: &mut SpendVerifyStateC<B>,
+        s_m: &SpendVerifyM1<B>,
+        s_key_pair: &ServerKeyPair<B>,
+        spend_state: &[<B as CurveConfig>::ScalarField],
+    ) -> Result<SpendVerifyM2Multi<B>, &'static str> {
+        let timer = SpanTimer::start();
+        let tokens = &state.token_state;
+        if tokens.is_empty() {
+            return Err("Boomerang spend-verify: no token to spend");
+        }
+        if spend_state.is_empty() {
+            return Err("Boomerang spend-verify: empty spend state");
+        }
+        if tokens.len() != state.sig_state.len() || tokens.len() != state.comm_state.len() {
+            return Err("Boomerang spend-verify: inconsistent client state");
+        }
+        for (i, token) in tokens.iter().enumerate() {
+            if tokens[..i].iter().any(|t| t.id == token.id) {
+                return Err("Boomerang spend-verify: token presented twice");
+            }
+            if token.attrs.len() != tokens[0].attrs.len() {
+                return Err("Boomerang spend-verify: tokens have different attributes");
+            }
+        }
+
+        // The remainder must fit in the range of the sub proof, or the server rejects it.
+        let balance: <B as CurveConfig>::ScalarField = tokens.iter().map(|t| t.v).sum();
+        let remainder = encode_scalar(&(balance - spend_state[0]), Endianness::Little);
+        if remainder[8..].iter().any(|&byte| byte != 0) {
+            return Err("Boomerang spend-verify: balance does not cover the amount");
+        }
+        let spend_u64 = extract_u64_from_compressed_data(&remainder)?;
+
+        let r1 = <B as CurveConfig>::ScalarField::rand(rng);
+        let id1 = <B as CurveConfig>::ScalarField::rand(rng);
+
+        let mut vals: Vec<<B as CurveConfig>::ScalarField> =
+            vec![id1, balance, state.c_key_pair.x, r1];
+        vals.extend_from_slice(tokens[0].attrs());
+
+        let (c1, gens) = PedersenComm::new_multi(&vals, rng);
+
+        let rewards_gens = RewardsGenerators::<B>::shared();
+        let mut blind = <B as CurveConfig>::ScalarField::zero();
+        let mut inputs = Vec::with_capacity(tokens.len());
+        for (i, ((token, comm), sig)) in tokens
+            .iter()
+            .zip(state.comm_state.iter())
+            .zip(state.sig_state.iter())
+            .enumerate()
+        {
+            let prev_vals = token.vals();
+            let balance_blind = <B as CurveConfig>::ScalarField::rand(rng);
+            blind += balance_blind;
+
+            // The opening of the signed commitment also shows that `balance_comm` commits to
+            // its balance.
+            let label1 = b"BoomerangSpendVerifyM2MultiO2";
+            let mut transcript1 = session_transcript::<B>(label1, &s_m.session_id);
+            append_u64(&mut transcript1, b"input", i as u64);
+            let (proof_2, balance_proof) = ValueLinkProof::create(
+                &mut transcript1,
+                rng,
+                &prev_vals,
+                comm,
+                &token.gens,
+                balance_blind,
+            );
+
+            let t_tag = state.c_key_pair.x * token.id;
+            let tag = t_tag + s_m.r2;
+
+            let a: PedersenComm<B> = PedersenComm::new(state.c_key_pair.x, rng);
+            let b: PedersenComm<B> = PedersenComm::new(token.id, rng);
+            let c: PedersenComm<B> = PedersenComm::new(s_m.r2, rng);
+            let d: PedersenComm<B> = PedersenComm::new(t_tag, rng);
+            let e: PedersenComm<B> = d + c;
+
+            let label2 = b"BoomerangSpendVerifyM2MultiAM2";
+            let mut transcript2 = session_transcript::<B>(label2, &s_m.session_id);
+            append_u64(&mut transcript2, b"input", i as u64);
+            let proof_3 = AddMulProof::create(
+                &mut transcript2,
+                rng,
+                &state.c_key_pair.x,
+                &token.id,
+                &s_m.r2,
+                &a,
+                &b,
+                &c,
+                &d,
+                &e,
+            );
+
+            let sig_proof = SigProof::prove(
+                rng,
+                s_key_pair.s_key_pair.tag_key,
+                sig,
+                &prev_vals,
+                &token.gens.generators,
+                comm.r,
+                Some(&s_m.session_id),
+            );
+
+            inputs.push(SpendInput {
+                prev_comm: *comm,
+                prev_gens: token.gens.clone(),
+                pi_2: proof_2,
+                pi_3: proof_3,
+                tag,
+                id: token.id,
+                sig: sig.clone(),
+                s_proof: sig_proof,
+                tag_commits: vec![a, b, c, d, e],
+                balance_comm: rewards_gens.pc_gens.commit(token.v, balance_blind),
+                pi_balance: balance_proof,
+            });
+        }
+
+        // The sum of the balance commitments commits to `balance` under the sum of their
+        // blinding factors, which the opening of the new commitment shows it holds.
+        let label = b"BoomerangSpendVerifyM2MultiO1";
+        let mut transcript = session_transcript::<B>(label, &s_m.session_id);
+        let (proof_1, sum_proof) =
+            ValueLinkProof::create(&mut transcript, rng, &vals, &c1, &gens, blind);
+
+        // The commitment of the sub proof is the sum of the balance commitments minus the
+        // amount, which the server checks.
+        let sub_proof = SubProof::prove_with_blind(spend_u64, blind, &s_m.session_id, rng);
+
+        s_state.r = r1;
+        s_state.val_0 = balance;
+        s_state.attrs.clone_from(&tokens[0].attrs);
+        s_state.spend_state = spend_state.to_vec();
+        s_state.comm = c1;
+        s_state.id_0 = id1;
+        s_state.gens = gens.clone();
+        s_state.session_id = s_m.session_id;
+
+        Ok(timer.finish(SpendVerifyM2Multi {
+            comm: c1,
+            gens,
+            pi_1: proof_1,
+            pi_balance: sum_proof,
+            inputs,
+            pi_4: sub_proof,
+            spend_state: spend_state.to_vec(),
+            session_id: s_m.session_id,
+        }))
+    }
+
+    /// generate_spendverify_m4. This function checks the rewards proof of the third message of
+    /// the Spend/Verify Protocol against `policy_id`, and generates the fourth message.
+    /// This function fails, without changing `s_state`, if the rewards proof is bound to
+    /// another policy id.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `s_state` - the tmp client state.
+    /// * `s_m` - the received server message.
+    /// * `policy_id` - the id that the server published for the policy that it is expected to
+    ///   apply (see `ServerKeyPair::policy_id`).
+    pub fn generate_spendverify_m4<T: BoomerangRng>(
+        rng: &mut T,
+        s_state: &mut SpendVerifyStateC<B>,
+        s_m: &SpendVerifyM3<B>,
+        policy_id: &PolicyId,
+    ) -> Result<SpendVerifyM4<B>, &'static str> {
+        Self::generate_spendverify_m4_with_progress(rng, s_state, s_m, policy_id, None)
+    }
+
+    /// generate_spendverify_m4_with_policy. This function generates the fourth message of the
+    /// Spend/Verify Protocol, as in `generate_spendverify_m4`, once it has recomputed the reward
+    /// from `policy_state` and checked that the server's rewards proof commits to it. The policy
+    /// can e.g. be a `SignedPolicy` that was checked against the server's policy key.
+    /// This function fails, without changing `s_state`, if the server committed to another
+    /// reward or bound its proof to another policy id, so that a server cannot under-pay the
+    /// client without being noticed.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `s_state` - the tmp client state.
+    /// * `s_m` - the received server message.
+    /// * `policy_state` - the policy vector that the server is expected to apply.
+    /// * `policy_id` - the id that the server published for `policy_state`.
+    pub fn generate_spendverify_m4_with_policy<T: BoomerangRng>(
+        rng: &mut T,
+        s_state: &mut SpendVerifyStateC<B>,
+        s_m: &SpendVerifyM3<B>,
+        policy_state: &[<B as CurveConfig>::ScalarField],
+        policy_id: &PolicyId,
+    ) -> Result<SpendVerifyM4<B>, &'static str> {
+        s_m.pi_reward
+            .check_reward(
+                &RewardsGenerators::shared(),
+                &s_m.reward_opening,
+                &s_state.spend_state,
+                policy_state,
+            )
+            .map_err(|_| "Boomerang spend-verify: reward does not match the policy")?;
+
+        Self::generate_spendverify_m4_with_progress(rng, s_state, s_m, policy_id, None)
+    }
+
+    /// generate_spendverify_m4_with_progress. This function checks the rewards proof of the
+    /// third message of the Spend/Verify Protocol against `policy_id`, generates the fourth
+    /// message, and reports its progress to `progress`. This function fails, without changing
+    /// `s_state`, if the rewards proof is bound to another policy id, or if `progress` aborts
+    /// the generation.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `s_state` - the tmp client state.
+    /// * `s_m` - the received server message.
+    /// * `policy_id` - the id that the server published for the policy that it is expected to
+    ///   apply.
+    /// * `progress` - the optional progress callback.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "spend_m4", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_spendverify_m4_with_progress<T: BoomerangRng>(
+        rng: &mut T,
+        s_state: &mut SpendVerifyStateC<B>,
+        s_m: &SpendVerifyM3<B>,
+        policy_id: &PolicyId,
+        progress: Option<ProgressCallback>,
+    ) -> Result<SpendVerifyM4<B>, &'static str> {
+        let timer = SpanTimer::start();
+        if s_m.session_id != s_state.session_id {
+            panic!("Boomerang spend-verify: session id mismatch");
+        }
+        if s_m.policy_id != *policy_id {
+            return Err(timer.reject("Boomerang spend-verify: rewards proof is for another policy"));
+        }
+
+        // Verify rewards proof
+        let mut reporter = Reporter::new(progress, SPEND_M4_PHASES);
+        reporter.phase(ProofPhase::RewardsCheck)?;
+        let reward_proof = &s_m.pi_reward;
+        let check = reward_proof.verify_with_policy_id(
+            &RewardsGenerators::shared(),
+            &s_state.spend_state,
+            &s_state.session_id,
+            policy_id,
+            rng,
+        );
+        if check.is_err() {
+            panic!("Boomerang verification: reward proof verification failed")
+        }
+
+        reporter.phase(ProofPhase::Challenge)?;
+        let m4 = Self::challenge_new_token(rng, s_state, &s_m.ack());
+        reporter.done();
+        Ok(timer.finish(m4))
+    }
+
+    /// generate_spendverify_m4_fast. This function generates the fourth message of the fast path
+    /// of the Spend/Verify Protocol, i.e in reply to a `SpendVerifyAck`, once it has checked
+    /// that `policy_state` yields no reward for the spent values.
+    /// This function fails, without changing `s_state`, if a reward is due, so that a server
+    /// cannot skip a reward by taking the fast path, or if the session ids do not match.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `s_state` - the tmp client state.
+    /// * `s_m` - the received server acknowledgement.
+    /// * `policy_state` - the policy vector that the server is expected to apply.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "spend_m4_fast", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_spendverify_m4_fast<T: BoomerangRng>(
+        rng: &mut T,
+        s_state: &mut SpendVerifyStateC<B>,
+        s_m: &SpendVerifyAck<B>,
+        policy_state: &[<B as CurveConfig>::ScalarField],
+    ) -> Result<SpendVerifyM4<B>, &'static str> {
+        let timer = SpanTimer::start();
+        if s_m.session_id != s_state.session_id {
+            return Err("Boomerang spend-verify: session id mismatch");
+        }
+        if policy_state.len() != s_state.spend_state.len()
+            || reward_due::<B>(&s_state.spend_state, policy_state)
+        {
+            return Err("Boomerang spend-verify: a reward is due, the fast path is not allowed");
+        }
+
+        Ok(timer.finish(Self::challenge_new_token(rng, s_state, s_m)))
+    }
+
+    /// challenge_new_token. This function computes the client's share of the new token from the
+    /// server's reply, and the challenge of the blind signature on it.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `s_state` - the tmp client state.
+    /// * `s_m` - the server's reply, without its rewards proof.
+    fn challenge_new_token<T: BoomerangRng>(
+        rng: &mut T,
+        s_state: &mut SpendVerifyStateC<B>,
+        s_m: &SpendVerifyAck<B>,
+    ) -> SpendVerifyM4<B> {
+        // The other way around to handle the negative
+        let c = s_state.comm - s_m.comm;
+        let id = s_state.id_0 - s_m.id_1;
+        let val = s_state.val_0 - s_m.val;
+
+        let sig_chall = SigChall::challenge(
+            s_m.tag_key,
+            s_m.verifying_key,
+            rng,
+            s_m.sig_commit,
+            &token_message::<B>(&s_m.verifying_key, &s_m.tag_key),
+            Some(TOKEN_CONTEXT),
+        );
+
+        s_state.id = id;
+        s_state.val = val;
+        s_state.c = c;
+        s_state.e = sig_chall.clone();
+
+        SpendVerifyM4 {
+            e: sig_chall,
+            session_id: s_state.session_id,
+        }
+    }
+
+    pub fn populate_state(
+        s_state: &mut SpendVerifyStateC<B>,
+        s_m: &SpendVerifyM5<B>,
+        s_key_pair: &ServerKeyPair<B>,
+        c_key_pair: &UKeyPair<B>,
+    ) -> State<B> {
+        if s_m.session_id != s_state.session_id {
+            panic!("Boomerang spend-verify: session id mismatch");
+        }
+
+        let sig = SigSign::sign(
+            s_key_pair.s_key_pair.verifying_key,
+            s_key_pair.s_key_pair.tag_key,
+            &s_state.e,
+            &s_m.s,
+            &s_key_pair.token_message(),
+            Some(TOKEN_CONTEXT),
+        );
+
+        let commits: Vec<PedersenComm<B>> = vec![s_state.c];
+        let sigs: Vec<SigSign<B>> = vec![sig];
+        let token = Token {
+            id: s_state.id,
+            v: s_state.val,
+            sk: c_key_pair.x,
+            r: s_state.r,
+            attrs: s_state.attrs.clone(),
+            gens: s_state.gens.clone(),
+        };
+        let tokens: Vec<Token<B>> = vec![token];
+
+        State {
+            comm_state: commits,
+            sig_state: sigs,
+            token_state: tokens,
+            c_key_pair: c_key_pair.clone(),
+        }
+    }
+}
+
+/// SpendSizes. This struct holds the number of bytes that a run of the Spend/Verify Protocol
+/// sends and receives, in the compressed encoding of its messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpendSizes {
+    /// sent: the bytes of the client messages, i.e m2 and m4.
+    pub sent: usize,
+    /// received: the bytes of the server messages, i.e m1, m3 (or the `SpendVerifyAck` of the
+    /// fast path) and m5.
+    pub received: usize,
+    /// fast_path: whether no reward is due, so that the server answers m2 with a
+    /// `SpendVerifyAck` rather than with a rewards proof.
+    pub fast_path: bool,
+}
+
+impl SpendSizes {
+    /// total. Returns the bytes that the spend sends and receives.
+    pub fn total(&self) -> usize {
+        self.sent + self.received
+    }
+}
+
+/// estimate_reward. This function returns the reward that `policy_state` gives for
+/// `spend_state`, i.e their inner product, without running the protocol, e.g. to show it to
+/// the user before a spend. This function fails if the vectors have different lengths, or if
+/// the reward does not fit in the 64 bits of the range proof, as the server then cannot prove
+/// it.
+/// # Arguments
+/// * `spend_state` - the values that the client would spend.
+/// * `policy_state` - the policy vector that the server applies.
+pub fn estimate_reward<B: BoomerangConfig>(
+    spend_state: &[<B as CurveConfig>::ScalarField],
+    policy_state: &[<B as CurveConfig>::ScalarField],
+) -> Result<u64, &'static str> {
+    if spend_state.len() != policy_state.len() {
+        return Err("Boomerang estimate: spend and policy vectors have different lengths");
+    }
+
+    let reward = ark_bulletproofs::inner_product(spend_state, policy_state).into_bigint();
+    if reward.num_bits() > 64 {
+        return Err("Boomerang estimate: reward does not fit in 64 bits");
+    }
+    Ok(reward.as_ref()[0])
+}
+
+/// estimate_spend_sizes. This function returns the number of bytes that a spend of
+/// `spend_state` under `policy_state` sends and receives, without running the protocol, and
+/// whether it takes the fast path. The sizes are those of `spend_verify_sizes`, which counts
+/// them from the layout of the messages, for a token without attributes. This function fails
+/// if the vectors have different lengths, or if their length is not a power of two.
+/// # Arguments
+/// * `spend_state` - the values that the client would spend.
+/// * `policy_state` - the policy vector that the server applies.
+pub fn estimate_spend_sizes<B: BoomerangConfig>(
+    spend_state: &[<B as CurveConfig>::ScalarField],
+    policy_state: &[<B as CurveConfig>::ScalarField],
+) -> Result<SpendSizes, &'static str> {
+    if spend_state.len() != policy_state.len() {
+        return Err("Boomerang estimate: spend and policy vectors have different lengths");
+    }
+    if !spend_state.len().is_power_of_two() {
+        return Err("Boomerang estimate: spend state length is not a power of two");
+    }
+
+    let ([m1, m2, m3, m4, m5], ack) = spend_verify_sizes::<B>(spend_state.len());
+    let fast_path = !reward_due::<B>(spend_state, policy_state);
+    let reply = if fast_path { ack } else { m3 };
+
+    Ok(SpendSizes {
+        sent: m2 + m4,
+        received: m1 + reply + m5,
+        fast_path,
+    })
+}
+
+// Transfer Protocol
+
+/// KeyBindingProof. This struct holds the proof that the recipient of a transfer knows the
+/// opening of its part of the new token, and that the secret key in it is the secret key of its
+/// public key, so that the transferred token can only be used by the holder of that key.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct KeyBindingProof<B: BoomerangConfig> {
+    /// alpha: the commitment of the proof to the token.
+    alpha: sw::Affine<B>,
+    /// beta: the commitment of the proof to the public key.
+    beta: sw::Affine<B>,
+    /// z: the responses for the serial number, the secret key, the random value and the
+    /// blinding factor, in that order.
+    z: Vec<<B as CurveConfig>::ScalarField>,
+}
+
+impl<B: BoomerangConfig> Clone for KeyBindingProof<B> {
+    fn clone(&self) -> Self {
+        Self {
+            alpha: self.alpha,
+            beta: self.beta,
+            z: self.z.clone(),
+        }
+    }
+}
+
+impl<B: BoomerangConfig> KeyBindingProof<B> {
+    /// challenge. Returns the challenge of a proof with commitments `alpha` and `beta`.
+    fn challenge(
+        session_id: &SessionId,
+        comm: &sw::Affine<B>,
+        public_key: &sw::Affine<B>,
+        alpha: &sw::Affine<B>,
+        beta: &sw::Affine<B>,
+    ) -> <B as CurveConfig>::ScalarField {
+        let mut transcript = session_transcript::<B>(b"BoomerangTransferKeyBinding", session_id);
+        append_point(&mut transcript, b"comm", comm);
+        append_point(&mut transcript, b"public_key", public_key);
+        append_point(&mut transcript, b"alpha", alpha);
+        append_point(&mut transcript, b"beta", beta);
+
+        let mut buf = [0u8; 64];
+        challenge_bytes(&mut transcript, b"challenge", &mut buf);
+        <B as ACLConfig>::make_challenge_from_buffer(&buf)
+    }
+
+    /// create. This function proves that `comm` commits to `vals`, i.e the serial number, the
+    /// secret key and the random value of a token with a zero value, under `gens`, and that the
+    /// secret key is the one of `key_pair`.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `session_id` - the session that the proof is bound to.
+    /// * `comm` - the commitment.
+    /// * `gens` - the generators of every committed value but the value.
+    /// * `vals` - the serial number, the secret key and the random value.
+    /// * `key_pair` - the recipient's keypair.
+    fn create<T: BoomerangRng>(
+        rng: &mut T,
+        session_id: &SessionId,
+        comm: &PedersenComm<B>,
+        gens: &Generators<B>,
+        vals: &[<B as CurveConfig>::ScalarField; 3],
+        key_pair: &UKeyPair<B>,
+    ) -> Self {
+        let t: Vec<<B as CurveConfig>::ScalarField> = (0..4)
+            .map(|_| <B as CurveConfig>::ScalarField::rand(rng))
+            .collect();
+
+        let alpha = (msm::msm(&gens.generators[..3], &t[..3]).unwrap()
+            + <B as PedersenConfig>::GENERATOR2.mul(t[3]))
+        .into_affine();
+        let beta = <B as SWCurveConfig>::GENERATOR.mul(t[1]).into_affine();
+        let c = Self::challenge(session_id, &comm.comm, &key_pair.public_key, &alpha, &beta);
+
+        let z = vals
+            .iter()
+            .chain([&comm.r])
+            .zip(t.iter())
+            .map(|(w, t)| *t + c * w)
+            .collect();
+        Self { alpha, beta, z }
+    }
+
+    /// verify. This function returns true if the proof shows that `comm` commits to a token
+    /// with a zero value under `gens`, whose secret key is the one of `public_key`, and false
+    /// otherwise.
+    /// # Arguments
+    /// * `session_id` - the session that the proof is bound to.
+    /// * `comm` - the commitment.
+    /// * `gens` - the generators of every committed value but the value.
+    /// * `public_key` - the recipient's public key.
+    pub fn verify(
+        &self,
+        session_id: &SessionId,
+        comm: &sw::Affine<B>,
+        gens: &Generators<B>,
+        public_key: &sw::Affine<B>,
+    ) -> bool {
+        if self.z.len() != 4 || gens.generators.len() < 3 {
+            return false;
+        }
+
+        let c = Self::challenge(session_id, comm, public_key, &self.alpha, &self.beta);
+        let lhs = msm::msm(&gens.generators[..3], &self.z[..3]).unwrap()
+            + <B as PedersenConfig>::GENERATOR2.mul(self.z[3]);
+        let check = lhs == self.alpha + comm.mul(c);
+        let check2 =
+            <B as SWCurveConfig>::GENERATOR.mul(self.z[1]) == self.beta + public_key.mul(c);
+        check && check2
+    }
+}
+
+/// TransferRequest. This struct acts as a container for the message that the recipient of a
+/// transfer sends to the sender: the recipient's part of the new token, i.e a commitment to a
+/// fresh serial number, its secret key and a random value.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct TransferRequest<B: BoomerangConfig> {
+    /// comm: the commitment to the recipient's part of the new token.
+    pub comm: sw::Affine<B>,
+    /// gens: the generators of the new token.
+    pub gens: Generators<B>,
+    /// public_key: the recipient's public key.
+    pub public_key: sw::Affine<B>,
+    /// pi: the proof that `comm` is bound to `public_key`.
+    pub pi: KeyBindingProof<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
+}
+
+impl<B: BoomerangConfig> Clone for TransferRequest<B> {
+    fn clone(&self) -> Self {
+        Self {
+            comm: self.comm,
+            gens: self.gens.clone(),
+            public_key: self.public_key,
+            pi: self.pi.clone(),
+            session_id: self.session_id,
+        }
+    }
+}
+
+/// TransferM2. This struct acts as a container for the second message of the transfer
+/// protocol, which the sender sends to the server. The message splits the commitment to the
+/// sender's token into a commitment to its value and a commitment to the rest, so that the value
+/// can be carried over to the recipient without being revealed.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct TransferM2<B: BoomerangConfig> {
+    /// prev_comm: the signed commitment to the sender's token.
+    pub prev_comm: PedersenComm<B>,
+    /// prev_gens: the generators of the signed commitment.
+    pub prev_gens: Generators<B>,
+    /// value_comm: the commitment to the value of the sender's token.
+    pub value_comm: sw::Affine<B>,
+    /// pi_value: the proof of opening of `value_comm` under the generator of the value.
+    pub pi_value: OpeningProofMulti<B>,
+    /// pi_rest: the proof of opening of `prev_comm - value_comm` under the other generators.
+    pub pi_rest: OpeningProofMulti<B>,
+    /// pi_3: the proof of the tag.
+    pub pi_3: AddMulProof<B>,
+    /// tag: the tag value.
+    pub tag: <B as CurveConfig>::ScalarField,
+    /// id: the serial number of the sender's token.
+    pub id: <B as CurveConfig>::ScalarField,
+    /// sig: the signature on the sender's token.
+    pub sig: SigSign<B>,
+    /// s_proof: the proof of the commitment under the signature.
+    pub s_proof: SigProof<B>,
+    /// tag_commits: the commits for the tag proof.
+    pub tag_commits: Vec<PedersenComm<B>>,
+    /// request: the recipient's request.
+    pub request: TransferRequest<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
+}
+
+/// TransferOpening. This struct holds the opening of the `value_comm` of a `TransferM2`. The
+/// sender hands it to the recipient over a private channel, and never to the server.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct TransferOpening<B: BoomerangConfig> {
+    /// value: the transferred value.
+    pub value: <B as CurveConfig>::ScalarField,
+    /// blind: the blinding factor of the commitment to the value.
+    pub blind: <B as CurveConfig>::ScalarField,
+}
+
+impl<B: BoomerangConfig> Copy for TransferOpening<B> {}
+impl<B: BoomerangConfig> Clone for TransferOpening<B> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+/// TransferM4. This struct acts as a container for the fourth message of the transfer protocol,
+/// which the recipient sends to the server.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct TransferM4<B: BoomerangConfig> {
+    /// e: the signature challenge value.
+    pub e: SigChall<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
+}
+
+/// TransferStateC. This struct represents the transfer protocol for the recipient.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct TransferStateC<B: BoomerangConfig> {
+    /// comm: the commitment to the recipient's part of the new token.
+    comm: PedersenComm<B>,
+    /// gens: the generators of the new token.
+    gens: Generators<B>,
+    /// id: the serial number of the new token.
+    id: <B as CurveConfig>::ScalarField,
+    /// r: the random double-spending tag value.
+    r: <B as CurveConfig>::ScalarField,
+    /// c: the commitment to the new token.
+    c: PedersenComm<B>,
+    /// val: the transferred value.
+    val: <B as CurveConfig>::ScalarField,
+    /// e: the signature challenge value.
+    e: SigChall<B>,
+    /// session_id: the identifier of this session.
+    pub(crate) session_id: SessionId,
+}
+
+impl<B: BoomerangConfig> TransferStateC<B> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        Self {
+            comm: PedersenComm::default(),
+            gens: Generators::default(),
+            id: <B as CurveConfig>::ScalarField::zero(),
+            r: <B as CurveConfig>::ScalarField::zero(),
+            c: PedersenComm::default(),
+            val: <B as CurveConfig>::ScalarField::zero(),
+            e: SigChall::default(),
+            session_id: SessionId::default(),
+        }
+    }
+
+    /// generate_transfer_request. This function generates the recipient's request of the
+    /// Transfer Protocol, which the recipient hands to the sender.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `c_key_pair` - the recipient's keypair.
+    /// * `t_state` - the tmp recipient state.
+    /// * `s_m` - the received server message.
+    pub fn generate_transfer_request<T: BoomerangRng>(
+        rng: &mut T,
+        c_key_pair: &UKeyPair<B>,
+        t_state: &mut TransferStateC<B>,
+        s_m: &TransferM1<B>,
+    ) -> TransferRequest<B> {
+        let id = <B as CurveConfig>::ScalarField::rand(rng);
+        let r = <B as CurveConfig>::ScalarField::rand(rng);
+        let zero = <B as CurveConfig>::ScalarField::zero();
+        let (comm, gens) = PedersenComm::new_multi(&[id, zero, c_key_pair.x, r], rng);
+
+        let (_, rest) = split_value_generators(&gens);
+        let pi = KeyBindingProof::create(
+            rng,
+            &s_m.session_id,
+            &comm,
+            &rest,
+            &[id, c_key_pair.x, r],
+            c_key_pair,
+        );
+
+        t_state.comm = comm;
+        t_state.gens = gens.clone();
+        t_state.id = id;
+        t_state.r = r;
+        t_state.session_id = s_m.session_id;
+
+        TransferRequest {
+            comm: comm.comm,
+            gens,
+            public_key: c_key_pair.public_key,
+            pi,
+            session_id: s_m.session_id,
+        }
+    }
+
+    /// generate_transfer_m2. This function generates the second message of the Transfer
+    /// Protocol, which gives the value of the sender's token in `state` to the recipient that
+    /// made `request`. The returned opening must be handed to the recipient over a private
+    /// channel. The attributes of the sender's token are not transferred.
+    /// This function fails if `request` does not belong to this session, or if it is not bound
+    /// to the recipient's public key. The caller should check that this key is the expected one.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `state` - the sender's local state.
+    /// * `s_m` - the received server message.
+    /// * `request` - the recipient's request.
+    /// * `s_key_pair` - the server's keypair.
+    pub fn generate_transfer_m2<T: BoomerangRng>(
+        rng: &mut T,
+        state: &State<B>,
+        s_m: &TransferM1<B>,
+        request: &TransferRequest<B>,
+        s_key_pair: &ServerKeyPair<B>,
+    ) -> Result<(TransferM2<B>, TransferOpening<B>), &'static str> {
+        if request.session_id != s_m.session_id {
+            return Err("Boomerang transfer: session id mismatch");
+        }
+        let (_, rest) = split_value_generators(&request.gens);
+        if !request
+            .pi
+            .verify(&s_m.session_id, &request.comm, &rest, &request.public_key)
+        {
+            return Err("Boomerang transfer: request is not bound to the recipient's key");
+        }
+
+        let token = &state.token_state[0];
+        let prev_comm = state.comm_state[0];
+        let prev_vals = token.vals();
+
+        // Split the signed commitment into a commitment to the value, and one to the rest.
+        let (value_gens, rest_gens) = split_value_generators(&token.gens);
+        let value_comm = PedersenComm::new_multi_with_all_generators(&[token.v], rng, &value_gens);
+        let rest_comm = prev_comm - value_comm;
+        let mut rest_vals = prev_vals.clone();
+        rest_vals.remove(VALUE_INDEX);
+
+        let label = b"BoomerangTransferM2Value";
+        let mut transcript = session_transcript::<B>(label, &s_m.session_id);
+        let pi_value =
+            OpeningProofMulti::create(&mut transcript, rng, &[token.v], &value_comm, &value_gens);
+
+        let label1 = b"BoomerangTransferM2Rest";
+        let mut transcript1 = session_transcript::<B>(label1, &s_m.session_id);
+        let pi_rest =
+            OpeningProofMulti::create(&mut transcript1, rng, &rest_vals, &rest_comm, &rest_gens);
+
+        let t_tag = state.c_key_pair.x * token.id;
+        let tag = t_tag + s_m.r2;
+
+        let a: PedersenComm<B> = PedersenComm::new(state.c_key_pair.x, rng);
+        let b: PedersenComm<B> = PedersenComm::new(token.id, rng);
+        let c: PedersenComm<B> = PedersenComm::new(s_m.r2, rng);
+        let d: PedersenComm<B> = PedersenComm::new(t_tag, rng);
+        let e: PedersenComm<B> = d + c;
+
+        let label2 = b"BoomerangTransferM2AM2";
+        let mut transcript2 = session_transcript::<B>(label2, &s_m.session_id);
+        let proof_3 = AddMulProof::create(
+            &mut transcript2,
+            rng,
+            &state.c_key_pair.x,
+            &token.id,
+            &s_m.r2,
+            &a,
+            &b,
+            &c,
+            &d,
+            &e,
+        );
+
+        let sig_proof = SigProof::prove(
+            rng,
+            s_key_pair.s_key_pair.tag_key,
+            &state.sig_state[0],
+            &prev_vals,
+            &token.gens.generators,
+            prev_comm.r,
+            Some(&s_m.session_id),
+        );
+
+        let opening = TransferOpening {
+            value: token.v,
+            blind: value_comm.r,
+        };
+        let m2 = TransferM2 {
+            prev_comm,
+            prev_gens: token.gens.clone(),
+            value_comm: value_comm.comm,
+            pi_value,
+            pi_rest,
+            pi_3: proof_3,
+            tag,
+            id: token.id,
+            sig: state.sig_state[0].clone(),
+            s_proof: sig_proof,
+            tag_commits: vec![a, b, c, d, e],
+            request: request.clone(),
+            session_id: s_m.session_id,
+        };
+        Ok((m2, opening))
+    }
+
+    /// generate_transfer_m4. This function generates the fourth message of the Transfer
+    /// Protocol, once it has checked that the commitment to the value that the server signs
+    /// opens to the value in `opening`.
+    /// This function fails, without changing `t_state`, if it does not.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `t_state` - the tmp recipient state.
+    /// * `s_m` - the received server message.
+    /// * `opening` - the opening that was received from the sender.
+    pub fn generate_transfer_m4<T: BoomerangRng>(
+        rng: &mut T,
+        t_state: &mut TransferStateC<B>,
+        s_m: &TransferM3<B>,
+        opening: &TransferOpening<B>,
+    ) -> Result<TransferM4<B>, &'static str> {
+        if s_m.session_id != t_state.session_id {
+            return Err("Boomerang transfer: session id mismatch");
+        }
+
+        let (value_gens, _) = split_value_generators(&t_state.gens);
+        let value_comm = PedersenComm {
+            comm: s_m.value_comm,
+            r: opening.blind,
+        };
+        if !value_comm.open_multi_with_all_generators(&[opening.value], opening.blind, &value_gens)
+        {
+            return Err("Boomerang transfer: value does not match the opening");
+        }
+
+        let sig_chall = SigChall::challenge(
+            s_m.tag_key,
+            s_m.verifying_key,
+            rng,
+            s_m.sig_commit,
+            &token_message::<B>(&s_m.verifying_key, &s_m.tag_key),
+            Some(TOKEN_CONTEXT),
+        );
+
+        t_state.c = t_state.comm + value_comm;
+        t_state.val = opening.value;
+        t_state.e = sig_chall.clone();
+
+        Ok(TransferM4 {
+            e: sig_chall,
+            session_id: t_state.session_id,
+        })
+    }
+
+    /// populate_state. This function returns the recipient's state, which holds the
+    /// transferred token.
+    /// # Arguments
+    /// * `t_state` - the tmp recipient state.
+    /// * `s_m` - the received server message.
+    /// * `s_key_pair` - the server's keypair.
+    /// * `c_key_pair` - the recipient's keypair.
+    pub fn populate_state(
+        t_state: &mut TransferStateC<B>,
+        s_m: &TransferM5<B>,
+        s_key_pair: &ServerKeyPair<B>,
+        c_key_pair: &UKeyPair<B>,
+    ) -> State<B> {
+        if s_m.session_id != t_state.session_id {
+            panic!("Boomerang transfer: session id mismatch");
+        }
+
+        let sig = SigSign::sign(
+            s_key_pair.s_key_pair.verifying_key,
+            s_key_pair.s_key_pair.tag_key,
+            &t_state.e,
+            &s_m.s,
+            &s_key_pair.token_message(),
+            Some(TOKEN_CONTEXT),
+        );
+
+        let token = Token {
+            id: t_state.id,
+            v: t_state.val,
+            sk: c_key_pair.x,
+            r: t_state.r,
+            attrs: Vec::new(),
+            gens: t_state.gens.clone(),
+        };
+
+        State {
+            comm_state: vec![t_state.c],
+            sig_state: vec![sig],
+            token_state: vec![token],
+            c_key_pair: c_key_pair.clone(),
+        }
+    }
+}
+
+// Recovery Protocol
+
+/// BackupKeyPair. This struct holds the backup key of a user, which is derived from the same
+/// seed as its `UKeyPair`. Once it is registered with the server, it allows the user to move
+/// the value of its tokens to a fresh keypair, e.g. after the device that held the keypair was
+/// lost or compromised.
+#[derive(PartialEq)]
+#[must_use]
+pub struct BackupKeyPair<B: BoomerangConfig> {
+    /// Public key
+    pub public_key: sw::Affine<B>,
+
+    /// Private component x
+    x: <B as CurveConfig>::ScalarField,
+}
+
+impl<B: BoomerangConfig> Clone for BackupKeyPair<B> {
+    fn clone(&self) -> Self {
+        Self {
+            public_key: self.public_key,
+            x: self.x,
+        }
+    }
+}
+
+impl<B: BoomerangConfig> BackupKeyPair<B> {
+    /// from_seed. This function derives the backup keypair from `seed`.
+    /// # Arguments
+    /// * `seed` - the secret seed.
+    pub fn from_seed(seed: &KeySeed) -> Self {
+        let x = key_from_seed::<B>(b"BoomerangBackupKey", seed);
+
+        Self {
+            public_key: (<B as SWCurveConfig>::GENERATOR.mul(x)).into_affine(),
+            x,
+        }
+    }
+
+    /// register. This function generates the message that registers this backup key for the
+    /// user keypair `key_pair` with the server. It proves that the user knows both secret keys.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `key_pair` - the user's keypair.
+    pub fn register<T: BoomerangRng>(
+        &self,
+        rng: &mut T,
+        key_pair: &UKeyPair<B>,
+    ) -> BackupRegistration<B> {
+        let t_u = <B as CurveConfig>::ScalarField::rand(rng);
+        let t_b = <B as CurveConfig>::ScalarField::rand(rng);
+        let alpha_u = <B as SWCurveConfig>::GENERATOR.mul(t_u).into_affine();
+        let alpha_b = <B as SWCurveConfig>::GENERATOR.mul(t_b).into_affine();
+        let c = BackupRegistration::challenge(
+            &key_pair.public_key,
+            &self.public_key,
+            &alpha_u,
+            &alpha_b,
+        );
+
+        BackupRegistration {
+            user_pk: key_pair.public_key,
+            backup_pk: self.public_key,
+            alpha_u,
+            alpha_b,
+            z_u: t_u + c * key_pair.x,
+            z_b: t_b + c * self.x,
+        }
+    }
+}
+
+/// BackupRegistration. This struct acts as a container for the message that registers the
+/// backup key of a user with the server, alongside the proof that the user knows the secret
+/// keys of both its public key and its backup key.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct BackupRegistration<B: BoomerangConfig> {
+    /// user_pk: the user's public key.
+    pub user_pk: sw::Affine<B>,
+    /// backup_pk: the public backup key.
+    pub backup_pk: sw::Affine<B>,
+    /// alpha_u: the commitment of the proof to the user's secret key.
+    pub alpha_u: sw::Affine<B>,
+    /// alpha_b: the commitment of the proof to the secret backup key.
+    pub alpha_b: sw::Affine<B>,
+    /// z_u: the response for the user's secret key.
+    pub z_u: <B as CurveConfig>::ScalarField,
+    /// z_b: the response for the secret backup key.
+    pub z_b: <B as CurveConfig>::ScalarField,
+}
+
+impl<B: BoomerangConfig> BackupRegistration<B> {
+    /// challenge. Returns the challenge of a registration with commitments `alpha_u` and
+    /// `alpha_b`.
+    fn challenge(
+        user_pk: &sw::Affine<B>,
+        backup_pk: &sw::Affine<B>,
+        alpha_u: &sw::Affine<B>,
+        alpha_b: &sw::Affine<B>,
+    ) -> <B as CurveConfig>::ScalarField {
+        let mut transcript = B::CONTEXT.transcript(b"BoomerangBackupRegistration");
+        append_point(&mut transcript, b"user_pk", user_pk);
+        append_point(&mut transcript, b"backup_pk", backup_pk);
+        append_point(&mut transcript, b"alpha_u", alpha_u);
+        append_point(&mut transcript, b"alpha_b", alpha_b);
+
+        let mut buf = [0u8; 64];
+        challenge_bytes(&mut transcript, b"challenge", &mut buf);
+        <B as ACLConfig>::make_challenge_from_buffer(&buf)
+    }
+
+    /// verify. This function returns true if the registration proves knowledge of the secret
+    /// keys of `user_pk` and `backup_pk`, and false otherwise.
+    pub fn verify(&self) -> bool {
+        let c = Self::challenge(&self.user_pk, &self.backup_pk, &self.alpha_u, &self.alpha_b);
+        let g = <B as SWCurveConfig>::GENERATOR;
+        let check = g.mul(self.z_u) == self.alpha_u + self.user_pk.mul(c);
+        let check2 = g.mul(self.z_b) == self.alpha_b + self.backup_pk.mul(c);
+        check && check2
+    }
+}
+
+/// RecoveryProof. This struct holds the proof that the sender of a recovery knows the secret
+/// key that the tag of its token is computed with, that this key is the secret key of the
+/// user's public key, and that it knows the secret backup key that was registered for it.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct RecoveryProof<B: BoomerangConfig> {
+    /// alpha: the commitment of the proof to the commitment to the secret key.
+    alpha: sw::Affine<B>,
+    /// beta: the commitment of the proof to the public key.
+    beta: sw::Affine<B>,
+    /// gamma: the commitment of the proof to the backup key.
+    gamma: sw::Affine<B>,
+    /// z: the responses for the secret key, the blinding factor of its commitment and the
+    /// secret backup key, in that order.
+    z: Vec<<B as CurveConfig>::ScalarField>,
+}
+
+impl<B: BoomerangConfig> Clone for RecoveryProof<B> {
+    fn clone(&self) -> Self {
+        Self {
+            alpha: self.alpha,
+            beta: self.beta,
+            gamma: self.gamma,
+            z: self.z.clone(),
+        }
+    }
+}
+
+impl<B: BoomerangConfig> RecoveryProof<B> {
+    /// challenge. Returns the challenge of a proof with commitments `alpha`, `beta` and
+    /// `gamma`. It is bound to the public key of the fresh keypair, so that the proof cannot
+    /// be replayed to recover to another key.
+    #[allow(clippy::too_many_arguments)]
+    fn challenge(
+        session_id: &SessionId,
+        comm: &sw::Affine<B>,
+        user_pk: &sw::Affine<B>,
+        backup_pk: &sw::Affine<B>,
+        recipient_pk: &sw::Affine<B>,
+        alpha: &sw::Affine<B>,
+        beta: &sw::Affine<B>,
+        gamma: &sw::Affine<B>,
+    ) -> <B as CurveConfig>::ScalarField {
+        let mut transcript = session_transcript::<B>(b"BoomerangRecovery", session_id);
+        append_point(&mut transcript, b"comm", comm);
+        append_point(&mut transcript, b"user_pk", user_pk);
+        append_point(&mut transcript, b"backup_pk", backup_pk);
+        append_point(&mut transcript, b"recipient_pk", recipient_pk);
+        append_point(&mut transcript, b"alpha", alpha);
+        append_point(&mut transcript, b"beta", beta);
+        append_point(&mut transcript, b"gamma", gamma);
+
+        let mut buf = [0u8; 64];
+        challenge_bytes(&mut transcript, b"challenge", &mut buf);
+        <B as ACLConfig>::make_challenge_from_buffer(&buf)
+    }
+
+    /// create. This function proves that `comm` commits to the secret key of `key_pair`, and
+    /// that the secret key of `backup` is known.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `session_id` - the session that the proof is bound to.
+    /// * `comm` - the commitment to the secret key.
+    /// * `key_pair` - the user's keypair.
+    /// * `backup` - the user's backup keypair.
+    /// * `recipient_pk` - the public key of the fresh keypair.
+    fn create<T: BoomerangRng>(
+        rng: &mut T,
+        session_id: &SessionId,
+        comm: &PedersenComm<B>,
+        key_pair: &UKeyPair<B>,
+        backup: &BackupKeyPair<B>,
+        recipient_pk: &sw::Affine<B>,
+    ) -> Self {
+        let t: Vec<<B as CurveConfig>::ScalarField> = (0..3)
+            .map(|_| <B as CurveConfig>::ScalarField::rand(rng))
+            .collect();
+
+        let g = <B as SWCurveConfig>::GENERATOR;
+        let alpha = (g.mul(t[0]) + <B as PedersenConfig>::GENERATOR2.mul(t[1])).into_affine();
+        let beta = g.mul(t[0]).into_affine();
+        let gamma = g.mul(t[2]).into_affine();
+        let c = Self::challenge(
+            session_id,
+            &comm.comm,
+            &key_pair.public_key,
+            &backup.public_key,
+            recipient_pk,
+            &alpha,
+            &beta,
+            &gamma,
+        );
+
+        let z = [key_pair.x, comm.r, backup.x]
+            .iter()
+            .zip(t.iter())
+            .map(|(w, t)| *t + c * w)
+            .collect();
+        Self {
+            alpha,
+            beta,
+            gamma,
+            z,
+        }
+    }
+
+    /// verify. This function returns true if the proof shows that `comm` commits to the secret
+    /// key of `user_pk`, and that the secret key of `backup_pk` is known, and false otherwise.
+    /// # Arguments
+    /// * `session_id` - the session that the proof is bound to.
+    /// * `comm` - the commitment to the secret key.
+    /// * `user_pk` - the user's public key.
+    /// * `backup_pk` - the registered backup key.
+    /// * `recipient_pk` - the public key of the fresh keypair.
+    pub fn verify(
+        &self,
+        session_id: &SessionId,
+        comm: &sw::Affine<B>,
+        user_pk: &sw::Affine<B>,
+        backup_pk: &sw::Affine<B>,
+        recipient_pk: &sw::Affine<B>,
+    ) -> bool {
+        if self.z.len() != 3 {
+            return false;
+        }
+
+        let c = Self::challenge(
+            session_id,
+            comm,
+            user_pk,
+            backup_pk,
+            recipient_pk,
+            &self.alpha,
+            &self.beta,
+            &self.gamma,
+        );
+        let g = <B as SWCurveConfig>::GENERATOR;
+        let lhs = g.mul(self.z[0]) + <B as PedersenConfig>::GENERATOR2.mul(self.z[1]);
+        let check = lhs == self.alpha + comm.mul(c);
+        let check2 = g.mul(self.z[0]) == self.beta + user_pk.mul(c);
+        let check3 = g.mul(self.z[2]) == self.gamma + backup_pk.mul(c);
+        check && check2 && check3
+    }
+}
+
+/// RecoveryM2. This struct acts as a container for the second message of the recovery
+/// protocol, which takes the place of the `TransferM2` of a transfer from the user's old
+/// keypair to a fresh one. The message reveals the user's public key, so that the server can
+/// look up its backup key.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct RecoveryM2<B: BoomerangConfig> {
+    /// transfer: the transfer of the old token to the fresh keypair.
+    pub transfer: TransferM2<B>,
+    /// user_pk: the user's old public key.
+    pub user_pk: sw::Affine<B>,
+    /// pi: the proof that the old token belongs to `user_pk`, and of the backup key.
+    pub pi: RecoveryProof<B>,
+}
+
+impl<B: BoomerangConfig> TransferStateC<B> {
+    /// generate_recovery_m2. This function generates the second message of the Recovery
+    /// Protocol, which moves the value of the token in `state` to the fresh keypair that made
+    /// `request`. The protocol is otherwise the Transfer Protocol, in which the user is both
+    /// the sender and the recipient, so the value is opened with the returned opening in
+    /// `generate_transfer_m4`.
+    /// This function fails if `request` does not pass `generate_transfer_m2`, or if it is made
+    /// by the old keypair.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `state` - the user's old state, e.g. as restored from a backup.
+    /// * `s_m` - the received server message.
+    /// * `request` - the request of the fresh keypair.
+    /// * `backup` - the user's backup keypair.
+    /// * `s_key_pair` - the server's keypair.
+    pub fn generate_recovery_m2<T: BoomerangRng>(
+        rng: &mut T,
+        state: &State<B>,
+        s_m: &TransferM1<B>,
+        request: &TransferRequest<B>,
+        backup: &BackupKeyPair<B>,
+        s_key_pair: &ServerKeyPair<B>,
+    ) -> Result<(RecoveryM2<B>, TransferOpening<B>), &'static str> {
+        if request.public_key == state.c_key_pair.public_key {
+            return Err("Boomerang recovery: the fresh key is the old key");
+        }
+        let (transfer, opening) = Self::generate_transfer_m2(rng, state, s_m, request, s_key_pair)?;
+
+        // The first tag commitment is the commitment to the secret key of the old token.
+        let pi = RecoveryProof::create(
+            rng,
+            &s_m.session_id,
+            &transfer.tag_commits[0],
+            &state.c_key_pair,
+            backup,
+            &request.public_key,
+        );
+
+        let m2 = RecoveryM2 {
+            transfer,
+            user_pk: state.c_key_pair.public_key,
+            pi,
+        };
+        Ok((m2, opening))
+    }
+}
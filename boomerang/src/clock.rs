@@ -0,0 +1,150 @@
+//! Defines epochs, and the clocks that they are read from.
+//!
+//! The time-dependent parts of the protocols, e.g the expiry of tokens, the epoch of a
+//! `RevocationList` and the window of a `SpendLimit`, take the current time or epoch as an
+//! argument. A `Clock` is the single place where that time comes from, so that a server or a
+//! client can read it from the system with `SystemClock`, and tests can set it with a
+//! `TestClock` instead of waiting for the wall clock.
+//!
+//! Times are counted in seconds since the Unix epoch, and an epoch is a window of a fixed
+//! number of seconds: epoch `e` of length `l` holds the times `e * l` to `(e + 1) * l - 1`.
+//!
+//! N.B Only `SystemClock` depends on `std`, so that the rest of this module is available
+//! without it.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Epoch. The index of a window of time of a fixed length.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Epoch(u64);
+
+impl Epoch {
+    /// new. This function returns the epoch with index `index`.
+    /// # Arguments
+    /// * `index` - the index of the epoch.
+    pub const fn new(index: u64) -> Self {
+        Self(index)
+    }
+
+    /// at. This function returns the epoch of length `length` that holds `now`. This function
+    /// panics if `length` is zero.
+    /// # Arguments
+    /// * `now` - the time, in seconds since the Unix epoch.
+    /// * `length` - the length of an epoch, in seconds.
+    pub fn at(now: u64, length: u64) -> Self {
+        assert!(length > 0, "Boomerang epoch: the length must not be zero");
+        Self(now / length)
+    }
+
+    /// value. Returns the index of the epoch.
+    pub const fn value(&self) -> u64 {
+        self.0
+    }
+
+    /// start. This function returns the first time of the epoch, if the epoch has length
+    /// `length`, or None if it does not fit in 64 bits.
+    /// # Arguments
+    /// * `length` - the length of an epoch, in seconds.
+    pub fn start(&self, length: u64) -> Option<u64> {
+        self.0.checked_mul(length)
+    }
+
+    /// next. Returns the epoch after this one, or None if there is none.
+    pub fn next(&self) -> Option<Self> {
+        self.0.checked_add(1).map(Self)
+    }
+}
+
+impl From<u64> for Epoch {
+    fn from(index: u64) -> Self {
+        Self(index)
+    }
+}
+
+impl From<Epoch> for u64 {
+    fn from(epoch: Epoch) -> Self {
+        epoch.0
+    }
+}
+
+/// Clock. This trait is the source of the current time of the time-dependent protocol logic.
+pub trait Clock {
+    /// now. Returns the current time, in seconds since the Unix epoch.
+    fn now(&self) -> u64;
+
+    /// epoch. This function returns the current epoch, for epochs of length `length`. This
+    /// function panics if `length` is zero.
+    /// # Arguments
+    /// * `length` - the length of an epoch, in seconds.
+    fn epoch(&self, length: u64) -> Epoch {
+        Epoch::at(self.now(), length)
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> u64 {
+        (**self).now()
+    }
+}
+
+/// SystemClock. A clock that reads the system time. A system time before the Unix epoch is
+/// read as the Unix epoch.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+/// TestClock. A clock that only moves when it is told to, so that tests can step through
+/// expiries and epochs. The clock can be moved through a shared reference, e.g while a
+/// server holds it.
+#[derive(Debug, Default)]
+pub struct TestClock {
+    /// now: the current time, in seconds since the Unix epoch.
+    now: AtomicU64,
+}
+
+impl TestClock {
+    /// new. This function returns a clock that is stopped at `now`.
+    /// # Arguments
+    /// * `now` - the time, in seconds since the Unix epoch.
+    pub fn new(now: u64) -> Self {
+        Self {
+            now: AtomicU64::new(now),
+        }
+    }
+
+    /// set. This function moves the clock to `now`, which can be before its current time.
+    /// # Arguments
+    /// * `now` - the time, in seconds since the Unix epoch.
+    pub fn set(&self, now: u64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    /// advance. This function moves the clock forward by `secs` seconds, saturating at the
+    /// largest time.
+    /// # Arguments
+    /// * `secs` - the number of seconds to move forward by.
+    pub fn advance(&self, secs: u64) {
+        // fetch_update only fails if the closure returns None, which it never does.
+        let _ = self
+            .now
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |now| {
+                Some(now.saturating_add(secs))
+            });
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
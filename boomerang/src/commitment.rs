@@ -0,0 +1,139 @@
+//!
+//! Module containing the commitment backends of the state of a token.
+//!
+//! The values of a token are held by the client, and the server only ever sees (and signs) a
+//! commitment to them. `StateCommitment` abstracts what the state needs from that commitment:
+//! committing to the values, checking an opening, and adding the shares that the client and the
+//! server commit to, as in the Collection Protocol. This allows other backends, e.g. vector
+//! commitments with constant-size openings, to be prototyped against the state.
+//!
+//! Two backends are provided:
+//!
+//! * `PedersenComm`: a single vector commitment to every value, under the generators of the
+//!   token. This is the commitment that the protocols run with.
+//! * `SplitComm`: a Pedersen commitment over the two generators of the curve for every value,
+//!   which is larger, but lets every value be opened on its own.
+//!
+//! N.B The proofs of the protocols are specific to `PedersenComm`. A new backend has to provide
+//! its own proofs before the protocols can run with it.
+//!
+
+use ark_ec::{models::CurveConfig, short_weierstrass::SWCurveConfig, CurveGroup};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::ops::Mul;
+
+use crate::config::{committed_len, BoomerangConfig};
+use crate::rng::BoomerangRng;
+use pedersen::pedersen_config::{Generators, PedersenComm, PedersenConfig};
+
+/// StateCommitment. This trait describes a commitment to the values of a token, which holds
+/// the randomness that opens it.
+pub trait StateCommitment<B: BoomerangConfig>: Clone + Sized {
+    /// Key. The public parameters that the values are committed under.
+    type Key: Clone;
+
+    /// commit. This function returns a new commitment to `vals` under `key`, with fresh
+    /// randomness.
+    /// # Arguments
+    /// * `vals` - the values to commit to.
+    /// * `key` - the public parameters of the commitment.
+    /// * `rng` - the source of randomness.
+    fn commit<T: BoomerangRng>(
+        vals: &[<B as CurveConfig>::ScalarField],
+        key: &Self::Key,
+        rng: &mut T,
+    ) -> Self;
+
+    /// opens. This function returns true if the commitment, with its randomness, opens to
+    /// `vals` under `key`.
+    /// # Arguments
+    /// * `vals` - the committed values.
+    /// * `key` - the public parameters of the commitment.
+    fn opens(&self, vals: &[<B as CurveConfig>::ScalarField], key: &Self::Key) -> bool;
+
+    /// combine. This function returns the commitment to the sum of the values of `self` and
+    /// `other`, value by value, e.g. to add the server's share of a token to the client's.
+    /// # Arguments
+    /// * `other` - a commitment under the same key.
+    fn combine(&self, other: &Self) -> Self;
+}
+
+impl<B: BoomerangConfig> StateCommitment<B> for PedersenComm<B> {
+    type Key = Generators<B>;
+
+    fn commit<T: BoomerangRng>(
+        vals: &[<B as CurveConfig>::ScalarField],
+        key: &Self::Key,
+        rng: &mut T,
+    ) -> Self {
+        PedersenComm::new_multi_with_all_generators(vals, rng, key)
+    }
+
+    fn opens(&self, vals: &[<B as CurveConfig>::ScalarField], key: &Self::Key) -> bool {
+        committed_len(key) == vals.len() && self.open_multi_with_all_generators(vals, self.r, key)
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        self + other
+    }
+}
+
+/// SplitComm. This struct holds a Pedersen commitment over the two generators of the curve for
+/// every committed value.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct SplitComm<B: BoomerangConfig> {
+    /// comms: the commitments, in the order of the values.
+    pub comms: Vec<PedersenComm<B>>,
+}
+
+impl<B: BoomerangConfig> Clone for SplitComm<B> {
+    fn clone(&self) -> Self {
+        Self {
+            comms: self.comms.clone(),
+        }
+    }
+}
+
+impl<B: BoomerangConfig> StateCommitment<B> for SplitComm<B> {
+    /// The two generators are fixed by the curve.
+    type Key = ();
+
+    fn commit<T: BoomerangRng>(
+        vals: &[<B as CurveConfig>::ScalarField],
+        _key: &Self::Key,
+        rng: &mut T,
+    ) -> Self {
+        Self {
+            comms: vals
+                .iter()
+                .map(|val| PedersenComm::new(*val, rng))
+                .collect(),
+        }
+    }
+
+    fn opens(&self, vals: &[<B as CurveConfig>::ScalarField], _key: &Self::Key) -> bool {
+        self.comms.len() == vals.len()
+            && self.comms.iter().zip(vals).all(|(comm, val)| {
+                let point = <B as SWCurveConfig>::GENERATOR.mul(val)
+                    + <B as PedersenConfig>::GENERATOR2.mul(comm.r);
+                point.into_affine() == comm.comm
+            })
+    }
+
+    /// combine. This function panics if the commitments are not to the same number of values.
+    fn combine(&self, other: &Self) -> Self {
+        assert_eq!(
+            self.comms.len(),
+            other.comms.len(),
+            "Boomerang commitment: mismatched lengths"
+        );
+        Self {
+            comms: self
+                .comms
+                .iter()
+                .zip(&other.comms)
+                .map(|(a, b)| a + b)
+                .collect(),
+        }
+    }
+}
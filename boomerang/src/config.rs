@@ -1,13 +1,340 @@
-use ark_ec::{models::CurveConfig, short_weierstrass::SWCurveConfig};
+use ark_ec::{
+    models::CurveConfig,
+    short_weierstrass::{self as sw, SWCurveConfig},
+    AffineRepr,
+};
+use ark_ff::{Field, PrimeField};
+use ark_serialize::CanonicalSerialize;
+use ark_std::{One, Zero};
+use merlin::Transcript;
+use num_bigint::BigUint;
+use std::sync::Arc;
 
 use crate::client::{Token, UKeyPair};
+use crate::encoding::append_point;
+use crate::rng::BoomerangRng;
 use acl::{config::ACLConfig, sign::SigSign};
-use pedersen::{pedersen_config::PedersenComm, pedersen_config::PedersenConfig};
+use pedersen::transcript::{append_message, challenge_bytes};
+use pedersen::{
+    pedersen_config::Generators, pedersen_config::PedersenComm, pedersen_config::PedersenConfig,
+};
 
 pub trait BoomerangConfig:
     ACLConfig<OCurve = Self::Curve> + PedersenConfig<OCurve = Self::Curve>
 {
     type Curve: CurveConfig + SWCurveConfig;
+
+    /// CONTEXT. The context of the application that uses this config. Every transcript of
+    /// the Boomerang protocols, and the message of every token signature, is bound to it.
+    const CONTEXT: ProtocolContext = ProtocolContext::DEFAULT;
+
+    /// validate. This function checks that the constants of this config are sound. It is run
+    /// in debug builds whenever a key pair is generated, and should be called by deployments
+    /// as a smoke test. In particular, this function checks that:
+    /// * every generator of the curve and of the OCurve is on its curve, is not the
+    ///   identity, and is in the prime order subgroup.
+    /// * the second generator is the same for Pedersen commitments and for signatures, and
+    ///   is not a small multiple of the first generator.
+    /// * the conversions between the fields of the two curves preserve the integers that
+    ///   they are applied to.
+    ///
+    /// N.B The second generators in this workspace are fixed points (e.g. the point with the
+    /// smallest x co-ordinate after the first generator) rather than the output of a
+    /// hash-to-curve, so there is no seed to re-derive them from. The independence check can
+    /// therefore only rule out a trivial relation between the two generators.
+    fn validate() -> Result<(), &'static str> {
+        check_generator(&<Self as SWCurveConfig>::GENERATOR)?;
+        check_generator(&<Self as PedersenConfig>::GENERATOR2)?;
+        check_generator(&<Self::Curve as SWCurveConfig>::GENERATOR)?;
+        check_generator(&<Self as PedersenConfig>::OGENERATOR2)?;
+
+        if <Self as PedersenConfig>::GENERATOR2 != <Self as ACLConfig>::GENERATOR2 {
+            return Err("Boomerang config: mismatched second generators");
+        }
+
+        // Rule out GENERATOR2 = ±k * GENERATOR for small k.
+        let g = <Self as SWCurveConfig>::GENERATOR.into_group();
+        let h = <Self as PedersenConfig>::GENERATOR2.into_group();
+        let mut multiple = g;
+        for _ in 0..VALIDATE_MULTIPLES {
+            if multiple == h || multiple == -h {
+                return Err("Boomerang config: dependent generators");
+            }
+            multiple += g;
+        }
+
+        check_conversions::<Self>()
+    }
+}
+
+/// VALIDATE_MULTIPLES. The number of multiples of the first generator that `validate`
+/// compares the second generator against.
+const VALIDATE_MULTIPLES: usize = 1 << 10;
+
+/// check_generator. This function returns an error if `point` is not a generator of the
+/// prime order subgroup of its curve.
+/// # Arguments
+/// * `point` - the point to check.
+fn check_generator<C: SWCurveConfig>(point: &sw::Affine<C>) -> Result<(), &'static str> {
+    if point.infinity {
+        return Err("Boomerang config: generator is the identity");
+    }
+
+    if !point.is_on_curve() {
+        return Err("Boomerang config: generator is not on the curve");
+    }
+
+    // The subgroup check also multiplies by the group order, so this shows that the point has
+    // prime order.
+    if !point.is_in_correct_subgroup_assuming_on_curve()
+        || !point.mul_bigint(C::ScalarField::MODULUS).is_zero()
+    {
+        return Err("Boomerang config: generator is not of prime order");
+    }
+
+    Ok(())
+}
+
+/// check_conversions. This function returns an error if the field conversions of `B` do not
+/// preserve the integers that they are applied to.
+fn check_conversions<B: BoomerangConfig>() -> Result<(), &'static str> {
+    type Sf<B> = <B as CurveConfig>::ScalarField;
+    type Bf<B> = <B as CurveConfig>::BaseField;
+    type Osf<B> = <<B as BoomerangConfig>::Curve as CurveConfig>::ScalarField;
+    type Obf<B> = <<B as BoomerangConfig>::Curve as CurveConfig>::BaseField;
+
+    const ERR: &str = "Boomerang config: field conversions do not round-trip";
+
+    if <B as PedersenConfig>::CP1 != Sf::<B>::one() || <B as PedersenConfig>::CM1 != -Sf::<B>::one()
+    {
+        return Err(ERR);
+    }
+
+    for x in [0u64, 1, 2, 255, u32::MAX as u64, u64::MAX] {
+        let expected = Sf::<B>::from(x);
+        if B::from_u64_to_sf(x) != expected
+            || B::from_bf_to_sf(Bf::<B>::from(x)) != expected
+            || B::from_ob_to_sf(Obf::<B>::from(x)) != expected
+            || B::from_os_to_sf(Osf::<B>::from(x)) != expected
+            || B::from_oc(Osf::<B>::from(x)) != expected
+            || B::from_ob_to_os(Obf::<B>::from(x)) != Osf::<B>::from(x)
+        {
+            return Err(ERR);
+        }
+    }
+
+    // The co-ordinates of points on the OCurve are converted into the ScalarField, which must
+    // leave them unchanged. This only holds if the ScalarField is at least as large.
+    let sf_modulus: BigUint = Sf::<B>::MODULUS.into();
+    let obf_modulus = BigUint::from_bytes_le(
+        &Obf::<B>::characteristic()
+            .iter()
+            .flat_map(|limb| limb.to_le_bytes())
+            .collect::<Vec<u8>>(),
+    );
+    if obf_modulus <= sf_modulus {
+        for point in [
+            <B::Curve as SWCurveConfig>::GENERATOR,
+            <B as PedersenConfig>::OGENERATOR2,
+        ] {
+            for coord in [point.x, point.y] {
+                let mut bytes = Vec::new();
+                coord.serialize_uncompressed(&mut bytes).unwrap();
+                let before = BigUint::from_bytes_le(&bytes);
+                let after: BigUint = B::from_ob_to_sf(coord).into();
+                if before != after {
+                    return Err(ERR);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The number of base attributes of every token, i.e the serial number, the value, the secret
+/// key and the random value. Any further attributes are added with the Update Protocol.
+pub const BASE_ATTRIBUTES: usize = 4;
+
+/// committed_len. Returns the number of values that are committed to with `gens`. The
+/// generators of a multi-commitment always hold one more generator than there are values.
+/// # Arguments
+/// * `gens` - the generators of the commitment.
+pub fn committed_len<B: BoomerangConfig>(gens: &Generators<B>) -> usize {
+    gens.generators.len().saturating_sub(1)
+}
+
+/// VALUE_INDEX. The index of the value among the committed values of a token.
+pub const VALUE_INDEX: usize = 1;
+
+/// split_value_generators. Returns the generator of the value of a token, and the generators of
+/// every other committed value, each as a set of generators of its own. This allows a commitment
+/// to a token to be split into a commitment to its value and a commitment to the rest.
+/// This function panics if `gens` does not hold a generator for the value.
+/// # Arguments
+/// * `gens` - the generators of the token.
+pub fn split_value_generators<B: BoomerangConfig>(
+    gens: &Generators<B>,
+) -> (Generators<B>, Generators<B>) {
+    let mut rest = gens.generators.to_vec();
+    let value = rest.remove(VALUE_INDEX);
+    (
+        Generators {
+            generators: Arc::new(vec![value]),
+        },
+        Generators {
+            generators: Arc::new(rest),
+        },
+    )
+}
+
+/// The domain label absorbed into the challenge of every token signature.
+pub const TOKEN_CONTEXT: &[u8] = b"boomerang-token";
+
+/// token_message. This function returns the message that is signed on every
+/// token: a hash of the token metadata, which binds the signature to the
+/// issuing server's keys and to the context of `B`.
+/// # Arguments
+/// * `pub_key` - the server's verifying key.
+/// * `tag_key` - the server's tag key.
+pub fn token_message<B: BoomerangConfig>(
+    pub_key: &sw::Affine<B>,
+    tag_key: &sw::Affine<B>,
+) -> [u8; 64] {
+    let mut transcript = B::CONTEXT.transcript(b"Boomerang token metadata");
+
+    append_point(&mut transcript, b"pub_key", pub_key);
+    append_point(&mut transcript, b"tag_key", tag_key);
+
+    let mut buf = [0u8; 64];
+    challenge_bytes(&mut transcript, b"message", &mut buf);
+    buf
+}
+
+/// SessionId. The identifier that binds together every message of a single
+/// collection or spend/verify run. It is chosen by the server in the first
+/// message, echoed in every later message, and absorbed into every proof of the run.
+/// The session id of an issuance run is instead derived from a nonce of each party,
+/// see `issuance_session_id`.
+pub type SessionId = [u8; 32];
+
+/// Nonce. A fresh value that a party contributes to the session id of an issuance run.
+pub type Nonce = [u8; 32];
+
+/// new_nonce. This function generates a new, uniformly random, nonce.
+/// # Arguments
+/// * `rng` - the source of randomness.
+pub fn new_nonce<T: BoomerangRng>(rng: &mut T) -> Nonce {
+    let mut nonce = [0u8; 32];
+    rng.fill_bytes(&mut nonce);
+    nonce
+}
+
+/// issuance_session_id. This function derives the session id of an issuance run from the
+/// nonce that the client sends in the first message and the nonce that the server sends
+/// in the second message. Since both parties contribute a fresh nonce, neither message can be
+/// replayed into another run.
+/// # Arguments
+/// * `client_nonce` - the client's nonce.
+/// * `server_nonce` - the server's nonce.
+pub fn issuance_session_id(client_nonce: &Nonce, server_nonce: &Nonce) -> SessionId {
+    let mut transcript = Transcript::new(b"Boomerang issuance session");
+    append_message(&mut transcript, b"client_nonce", client_nonce);
+    append_message(&mut transcript, b"server_nonce", server_nonce);
+
+    let mut session_id = [0u8; 32];
+    challenge_bytes(&mut transcript, b"session_id", &mut session_id);
+    session_id
+}
+
+/// issuance_balance_comm. This function returns the commitment, without randomness, to the
+/// server's share of the values of a new token: the serial number `id_1` and the initial
+/// `balance`, the other values being zero. The server's share of the commitment must differ from
+/// it only in its randomness.
+/// # Arguments
+/// * `id_1` - the server's share of the serial number.
+/// * `balance` - the initial balance of the token.
+/// * `gens` - the generators of the token.
+pub fn issuance_balance_comm<B: BoomerangConfig>(
+    id_1: <B as CurveConfig>::ScalarField,
+    balance: <B as CurveConfig>::ScalarField,
+    gens: &Generators<B>,
+) -> PedersenComm<B> {
+    let comm = gens.generators[0].into_group() * id_1 + gens.generators[VALUE_INDEX] * balance;
+    PedersenComm {
+        comm: comm.into(),
+        r: <B as CurveConfig>::ScalarField::zero(),
+    }
+}
+
+/// new_session_id. This function generates a new, uniformly random, session id.
+/// # Arguments
+/// * `rng` - the source of randomness.
+pub fn new_session_id<T: BoomerangRng>(rng: &mut T) -> SessionId {
+    let mut session_id = [0u8; 32];
+    rng.fill_bytes(&mut session_id);
+    session_id
+}
+
+/// ProtocolContext. This struct acts as a namespace for the transcripts of a deployment.
+/// The labels of the transcripts (e.g. the domain separators of every sub-proof) are shared by
+/// every user of this crate, so two applications that use the same curve would otherwise accept
+/// each other's proofs. Binding every transcript to an application label rules this out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProtocolContext {
+    /// app_label: the label of the application.
+    pub app_label: &'static [u8],
+}
+
+impl ProtocolContext {
+    /// DEFAULT. The context of applications that do not set their own.
+    pub const DEFAULT: Self = Self::new(b"boomerang");
+
+    /// new. This function returns the context of the application labelled `app_label`.
+    /// # Arguments
+    /// * `app_label` - the label of the application.
+    pub const fn new(app_label: &'static [u8]) -> Self {
+        Self { app_label }
+    }
+
+    /// transcript. This function returns a new transcript with the label `label` that is
+    /// bound to this context.
+    /// # Arguments
+    /// * `label` - the label of the transcript.
+    pub fn transcript(&self, label: &'static [u8]) -> Transcript {
+        let mut transcript = Transcript::new(label);
+        append_message(&mut transcript, b"app_label", self.app_label);
+        transcript
+    }
+
+    /// session_transcript. This function returns a new transcript with the label
+    /// `label` that is bound to this context and to the session `session_id`.
+    /// # Arguments
+    /// * `label` - the label of the transcript.
+    /// * `session_id` - the session id.
+    pub fn session_transcript(&self, label: &'static [u8], session_id: &SessionId) -> Transcript {
+        let mut transcript = self.transcript(label);
+        append_message(&mut transcript, b"session_id", session_id);
+        transcript
+    }
+}
+
+impl Default for ProtocolContext {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// session_transcript. This function returns a new transcript with the label
+/// `label` that is bound to the context of `B` and to the session `session_id`.
+/// # Arguments
+/// * `label` - the label of the transcript.
+/// * `session_id` - the session id.
+pub fn session_transcript<B: BoomerangConfig>(
+    label: &'static [u8],
+    session_id: &SessionId,
+) -> Transcript {
+    B::CONTEXT.session_transcript(label, session_id)
 }
 
 /// Boomerang state.
@@ -23,3 +350,47 @@ pub struct State<B: BoomerangConfig> {
     /// The user keypair
     pub c_key_pair: UKeyPair<B>,
 }
+
+/// RevocationKey. The identifier under which a token can be revoked. It is derived from the
+/// token's signature, which the server sees every time the token is presented.
+pub type RevocationKey = [u8; 32];
+
+/// revocation_key. This function returns the key under which the token signed by `sig` can be revoked.
+/// # Arguments
+/// * `sig` - the token signature.
+pub fn revocation_key<B: BoomerangConfig>(sig: &SigSign<B>) -> RevocationKey {
+    let mut transcript = Transcript::new(b"Boomerang revocation key");
+
+    append_point(&mut transcript, b"zeta", &sig.sigma.zeta);
+    append_point(&mut transcript, b"zeta1", &sig.sigma.zeta1);
+
+    let mut key = [0u8; 32];
+    challenge_bytes(&mut transcript, b"key", &mut key);
+    key
+}
+
+impl<B: BoomerangConfig> State<B> {
+    /// revocation_key. Returns the key under which the current token can be revoked. The client
+    /// uses this key to fetch a non-revocation witness before spending the token.
+    pub fn revocation_key(&self) -> RevocationKey {
+        revocation_key::<B>(&self.sig_state[0])
+    }
+
+    /// balance. Returns the sum of the balances of the tokens of the state.
+    pub fn balance(&self) -> <B as CurveConfig>::ScalarField {
+        self.token_state.iter().map(|token| token.value()).sum()
+    }
+
+    /// is_consistent. Returns true if every token of the state opens its signed commitment,
+    /// i.e if the balance that the client holds is the balance that the server signed. A state
+    /// whose values were tampered with fails this check, and its next spend is rejected.
+    pub fn is_consistent(&self) -> bool {
+        self.token_state.len() == self.comm_state.len()
+            && self.token_state.len() == self.sig_state.len()
+            && self
+                .token_state
+                .iter()
+                .zip(&self.comm_state)
+                .all(|(token, comm)| token.opens(comm))
+    }
+}
@@ -0,0 +1,224 @@
+//! Defines R1CS gadgets for the verification equations of the Boomerang statements.
+//!
+//! The gadgets work over the BaseField of the current curve, so that the group operations are
+//! native to the circuit. The points are `ProjectiveVar`s over `FpVar`s, which is exactly the `GVar`
+//! type that the `constraints` module of each T-curve crate exports. Scalars are allocated as
+//! little-endian bit decompositions, since the ScalarField is not native to the circuit.
+//!
+//! N.B The ACL gadget checks every equation of `SigVerify::verify` except for the hash that derives
+//! the challenge, as there is no gadget for the signature hash (e.g SHA-512) yet. Instead, the
+//! gadget takes the challenge as an input and returns the values that are hashed, so that a
+//! circuit can either bind them to a hash gadget or expose them as public inputs.
+
+use ark_ec::{models::CurveConfig, short_weierstrass::SWCurveConfig};
+use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+    groups::{curves::short_weierstrass::ProjectiveVar, CurveVar},
+};
+use ark_relations::r1cs::{Namespace, SynthesisError};
+use ark_std::{borrow::Borrow, marker::PhantomData};
+
+use crate::config::BoomerangConfig;
+use acl::{config::ACLConfig, sign::Signature};
+use pedersen::pedersen_config::PedersenConfig;
+
+/// ConstraintF. The field that the circuits are defined over, i.e the BaseField of the curve.
+pub type ConstraintF<B> = <B as CurveConfig>::BaseField;
+
+/// GVar. The R1CS equivalent of a point on the curve.
+pub type GVar<B> = ProjectiveVar<B, FpVar<ConstraintF<B>>>;
+
+/// ScalarVar. This struct acts as the R1CS equivalent of an element of the ScalarField, held as
+/// its little-endian bits.
+pub struct ScalarVar<B: BoomerangConfig>
+where
+    ConstraintF<B>: PrimeField,
+{
+    /// bits: the little-endian bits of the scalar.
+    pub bits: Vec<Boolean<ConstraintF<B>>>,
+}
+
+impl<B: BoomerangConfig> AllocVar<<B as CurveConfig>::ScalarField, ConstraintF<B>> for ScalarVar<B>
+where
+    ConstraintF<B>: PrimeField,
+{
+    fn new_variable<T: Borrow<<B as CurveConfig>::ScalarField>>(
+        cs: impl Into<Namespace<ConstraintF<B>>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+
+        let size = <<B as CurveConfig>::ScalarField as PrimeField>::MODULUS_BIT_SIZE as usize;
+        let value = f().map(|x| x.borrow().into_bigint().to_bits_le());
+        let bits = (0..size)
+            .map(|i| {
+                Boolean::new_variable(
+                    cs.clone(),
+                    || value.as_ref().map(|bits| bits[i]).map_err(|e| *e),
+                    mode,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { bits })
+    }
+}
+
+/// mul. Returns `scalar * point` in the circuit.
+/// # Arguments
+/// * `point` - the point.
+/// * `scalar` - the scalar.
+fn mul<B: BoomerangConfig>(
+    point: &GVar<B>,
+    scalar: &ScalarVar<B>,
+) -> Result<GVar<B>, SynthesisError>
+where
+    ConstraintF<B>: PrimeField,
+{
+    point.scalar_mul_le(scalar.bits.iter())
+}
+
+/// SignatureVar. This struct acts as the R1CS equivalent of an ACL `Signature`.
+pub struct SignatureVar<B: BoomerangConfig>
+where
+    ConstraintF<B>: PrimeField,
+{
+    /// zeta: the first part of the blinded commitment.
+    pub zeta: GVar<B>,
+    /// zeta1: the second part of the blinded commitment.
+    pub zeta1: GVar<B>,
+    /// rho: the first response.
+    pub rho: ScalarVar<B>,
+    /// omega: the first part of the challenge.
+    pub omega: ScalarVar<B>,
+    /// rho1: the second response.
+    pub rho1: ScalarVar<B>,
+    /// rho2: the third response.
+    pub rho2: ScalarVar<B>,
+    /// v: the fourth response.
+    pub v: ScalarVar<B>,
+    /// omega1: the second part of the challenge.
+    pub omega1: ScalarVar<B>,
+}
+
+impl<B: BoomerangConfig> AllocVar<Signature<B>, ConstraintF<B>> for SignatureVar<B>
+where
+    ConstraintF<B>: PrimeField,
+{
+    fn new_variable<T: Borrow<Signature<B>>>(
+        cs: impl Into<Namespace<ConstraintF<B>>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+
+        let sig = f().map(|sig| sig.borrow().clone());
+        let get = |g: fn(&Signature<B>) -> <B as CurveConfig>::ScalarField| {
+            sig.as_ref().map(g).map_err(|e| *e)
+        };
+
+        Ok(Self {
+            zeta: GVar::<B>::new_variable(
+                cs.clone(),
+                || sig.as_ref().map(|sig| sig.zeta).map_err(|e| *e),
+                mode,
+            )?,
+            zeta1: GVar::<B>::new_variable(
+                cs.clone(),
+                || sig.as_ref().map(|sig| sig.zeta1).map_err(|e| *e),
+                mode,
+            )?,
+            rho: ScalarVar::new_variable(cs.clone(), || get(|sig| sig.rho), mode)?,
+            omega: ScalarVar::new_variable(cs.clone(), || get(|sig| sig.omega), mode)?,
+            rho1: ScalarVar::new_variable(cs.clone(), || get(|sig| sig.rho1), mode)?,
+            rho2: ScalarVar::new_variable(cs.clone(), || get(|sig| sig.rho2), mode)?,
+            v: ScalarVar::new_variable(cs.clone(), || get(|sig| sig.v), mode)?,
+            omega1: ScalarVar::new_variable(cs, || get(|sig| sig.omega1), mode)?,
+        })
+    }
+}
+
+/// ACLVerifyGadget. This struct provides the R1CS equivalent of `SigVerify::verify`.
+pub struct ACLVerifyGadget<B: BoomerangConfig> {
+    _marker: PhantomData<B>,
+}
+
+impl<B: BoomerangConfig> ACLVerifyGadget<B>
+where
+    ConstraintF<B>: PrimeField,
+{
+    /// challenge_inputs. This function returns the four commitments that `SigVerify::verify`
+    /// recomputes from the signature, and that are hashed (along with `zeta`, `zeta1` and the
+    /// message) into the challenge.
+    /// # Arguments
+    /// * `pub_key` - the signer's verifying key.
+    /// * `tag_key` - the signer's tag key.
+    /// * `sig` - the signature.
+    pub fn challenge_inputs(
+        pub_key: &GVar<B>,
+        tag_key: &GVar<B>,
+        sig: &SignatureVar<B>,
+    ) -> Result<[GVar<B>; 4], SynthesisError> {
+        let g = GVar::<B>::constant(<B as SWCurveConfig>::GENERATOR.into());
+        let h = GVar::<B>::constant(<B as ACLConfig>::GENERATOR2.into());
+        let z2 = &sig.zeta - &sig.zeta1;
+
+        Ok([
+            mul(&g, &sig.rho)? + mul(pub_key, &sig.omega)?,
+            mul(&g, &sig.rho1)? + mul(&sig.zeta1, &sig.omega1)?,
+            mul(&h, &sig.rho2)? + mul(&z2, &sig.omega1)?,
+            mul(tag_key, &sig.v)? + mul(&sig.zeta, &sig.omega1)?,
+        ])
+    }
+
+    /// verify. This function returns a Boolean that is true if `omega + omega1` is the
+    /// challenge `epsilon`. Together with a check that `epsilon` is the hash of the values
+    /// returned by `challenge_inputs`, this shows that `sig` is a valid signature.
+    /// Since the generator has prime order, the sum is checked in the exponent, which avoids
+    /// arithmetic in the ScalarField.
+    /// # Arguments
+    /// * `sig` - the signature.
+    /// * `epsilon` - the challenge.
+    pub fn verify(
+        sig: &SignatureVar<B>,
+        epsilon: &ScalarVar<B>,
+    ) -> Result<Boolean<ConstraintF<B>>, SynthesisError> {
+        let g = GVar::<B>::constant(<B as SWCurveConfig>::GENERATOR.into());
+        let lhs = mul(&g, &sig.omega)? + mul(&g, &sig.omega1)?;
+        lhs.is_eq(&mul(&g, epsilon)?)
+    }
+}
+
+/// PedersenOpeningGadget. This struct provides a gadget that checks the opening of a Pedersen
+/// commitment, i.e that `comm = x * g + r * h`.
+pub struct PedersenOpeningGadget<B: BoomerangConfig> {
+    _marker: PhantomData<B>,
+}
+
+impl<B: BoomerangConfig> PedersenOpeningGadget<B>
+where
+    ConstraintF<B>: PrimeField,
+{
+    /// verify. This function returns a Boolean that is true if (`x`, `r`) is an opening of `comm`.
+    /// # Arguments
+    /// * `comm` - the commitment.
+    /// * `x` - the committed value.
+    /// * `r` - the randomness of the commitment.
+    pub fn verify(
+        comm: &GVar<B>,
+        x: &ScalarVar<B>,
+        r: &ScalarVar<B>,
+    ) -> Result<Boolean<ConstraintF<B>>, SynthesisError> {
+        let g = GVar::<B>::constant(<B as SWCurveConfig>::GENERATOR.into());
+        let h = GVar::<B>::constant(<B as PedersenConfig>::GENERATOR2.into());
+        let expected = mul(&g, x)? + mul(&h, r)?;
+        expected.is_eq(comm)
+    }
+}
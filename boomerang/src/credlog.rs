@@ -0,0 +1,172 @@
+//! Defines the log of the credentials that a server issues.
+//!
+//! A server that rotates its keys needs to know how many tokens are live under each key, and a
+//! fraud analysis needs to know when tokens were issued. Rather than keeping the messages of the
+//! Issuance Protocol, a server can plug a `CredentialLog` into
+//! `IssuanceStateS::generate_issuance_m4_with_log`, which records one `CredentialRecord` per
+//! issued token: the id of the signing key, the epoch, the time of issuance, and the commitment
+//! that the server signed.
+//!
+//! N.B A record holds nothing that the server did not already see during the issuance, and
+//! nothing that identifies the user: neither the user's public key, nor the nonces or the id of
+//! the session. Storage backends should not add them either.
+
+use ark_ec::short_weierstrass::{self as sw};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use core::{fmt, ops::Range};
+
+use crate::config::BoomerangConfig;
+use crate::encoding::append_point;
+use crate::server::ServerKeyPair;
+use pedersen::transcript::challenge_bytes;
+
+/// KeyId. The identifier of a server keypair, derived from its public keys.
+pub type KeyId = [u8; 32];
+
+/// key_id. This function returns the identifier of `key_pair`, which only depends on its
+/// public keys, so that it can be computed again from the published keys.
+/// # Arguments
+/// * `key_pair` - the server's keypair.
+pub fn key_id<B: BoomerangConfig>(key_pair: &ServerKeyPair<B>) -> KeyId {
+    let mut transcript = B::CONTEXT.transcript(b"Boomerang key id");
+    append_point(&mut transcript, b"public_key", key_pair.public_key());
+    append_point(&mut transcript, b"tag_key", key_pair.tag_key());
+
+    let mut id = [0u8; 32];
+    challenge_bytes(&mut transcript, b"id", &mut id);
+    id
+}
+
+/// CredentialRecord. This struct holds the metadata of an issued token.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct CredentialRecord<B: BoomerangConfig> {
+    /// key_id: the id of the key that signed the token.
+    pub key_id: KeyId,
+    /// epoch: the epoch of the issuance.
+    pub epoch: u64,
+    /// issued_at: the time of the issuance.
+    pub issued_at: u64,
+    /// commitment: the commitment that the server signed.
+    pub commitment: sw::Affine<B>,
+}
+
+impl<B: BoomerangConfig> Clone for CredentialRecord<B> {
+    fn clone(&self) -> Self {
+        Self {
+            key_id: self.key_id,
+            epoch: self.epoch,
+            issued_at: self.issued_at,
+            commitment: self.commitment,
+        }
+    }
+}
+
+impl<B: BoomerangConfig> PartialEq for CredentialRecord<B> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key_id == other.key_id
+            && self.epoch == other.epoch
+            && self.issued_at == other.issued_at
+            && self.commitment == other.commitment
+    }
+}
+
+impl<B: BoomerangConfig> Eq for CredentialRecord<B> {}
+
+impl<B: BoomerangConfig> fmt::Debug for CredentialRecord<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CredentialRecord")
+            .field("key_id", &self.key_id)
+            .field("epoch", &self.epoch)
+            .field("issued_at", &self.issued_at)
+            .field("commitment", &self.commitment)
+            .finish()
+    }
+}
+
+/// CredentialLog. A storage for the records of issued tokens.
+pub trait CredentialLog<B: BoomerangConfig> {
+    /// record. This function stores `record`. This function fails if the record could not be
+    /// stored, in which case the server must not release the signature.
+    /// # Arguments
+    /// * `record` - the record of the issued token.
+    fn record(&mut self, record: CredentialRecord<B>) -> Result<(), &'static str>;
+
+    /// query. Returns the records of the tokens that `key_id` signed in `epochs`, in the
+    /// order they were stored.
+    /// # Arguments
+    /// * `key_id` - the id of the signing key.
+    /// * `epochs` - the epochs.
+    fn query(&self, key_id: &KeyId, epochs: Range<u64>) -> Vec<CredentialRecord<B>>;
+
+    /// count. Returns the number of tokens that `key_id` signed in `epochs`.
+    /// # Arguments
+    /// * `key_id` - the id of the signing key.
+    /// * `epochs` - the epochs.
+    fn count(&self, key_id: &KeyId, epochs: Range<u64>) -> usize {
+        self.query(key_id, epochs).len()
+    }
+}
+
+/// MemoryCredentialLog. A log that keeps its records in memory, e.g. for tests, or in front
+/// of a storage that is written in batches.
+pub struct MemoryCredentialLog<B: BoomerangConfig> {
+    /// records: the records, in the order they were stored.
+    records: Vec<CredentialRecord<B>>,
+}
+
+impl<B: BoomerangConfig> Clone for MemoryCredentialLog<B> {
+    fn clone(&self) -> Self {
+        Self {
+            records: self.records.clone(),
+        }
+    }
+}
+
+impl<B: BoomerangConfig> fmt::Debug for MemoryCredentialLog<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryCredentialLog")
+            .field("records", &self.records)
+            .finish()
+    }
+}
+
+impl<B: BoomerangConfig> Default for MemoryCredentialLog<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: BoomerangConfig> MemoryCredentialLog<B> {
+    /// new. Returns an empty log.
+    pub fn new() -> Self {
+        Self {
+            records: Vec::new(),
+        }
+    }
+
+    /// records. Returns every record of the log, in the order they were stored.
+    pub fn records(&self) -> &[CredentialRecord<B>] {
+        &self.records
+    }
+
+    /// drain. This function removes every record of the log, and returns them, e.g. to write
+    /// them to a storage in one batch.
+    pub fn drain(&mut self) -> Vec<CredentialRecord<B>> {
+        core::mem::take(&mut self.records)
+    }
+}
+
+impl<B: BoomerangConfig> CredentialLog<B> for MemoryCredentialLog<B> {
+    fn record(&mut self, record: CredentialRecord<B>) -> Result<(), &'static str> {
+        self.records.push(record);
+        Ok(())
+    }
+
+    fn query(&self, key_id: &KeyId, epochs: Range<u64>) -> Vec<CredentialRecord<B>> {
+        self.records
+            .iter()
+            .filter(|record| record.key_id == *key_id && epochs.contains(&record.epoch))
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,232 @@
+//!
+//! Module containing the identifiers of the curves that Boomerang runs on.
+//!
+//! A `CurveId` names one of the T-curves of this workspace, and carries the parameters that
+//! peers need to agree on before they exchange messages: the name that the negotiation uses,
+//! the byte that tags an envelope, the sizes of the compressed points and scalars, and the
+//! security level. The T-curve crates implement `RegisteredCurve` on their config, which ties
+//! the `BoomerangConfig` to its id.
+//!
+//! This crate cannot depend on the T-curve crates, which depend on it. A server that runs on
+//! several curves therefore registers the configs that it was built with in a `CurveRegistry`,
+//! which dispatches a `CurveHandler` to the config of a given id, e.g. the id that a client
+//! negotiated, or that tags a message.
+//!
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::BoomerangConfig;
+
+/// CurveId. The identifier of a curve that Boomerang runs on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CurveId {
+    /// T256: the T-curve that is paired with NIST P-256.
+    T256,
+    /// T384: the T-curve that is paired with NIST P-384.
+    T384,
+    /// T521: the T-curve that is paired with NIST P-521.
+    T521,
+    /// TSecp256k1: the T-curve that is paired with secp256k1.
+    TSecp256k1,
+    /// TSecq256k1: the T-curve that is paired with secq256k1.
+    TSecq256k1,
+}
+
+/// CurveInfo. This struct holds the parameters of a curve.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CurveInfo {
+    /// name: the name of the curve, as in the `ServerConfig` and the negotiation.
+    pub name: &'static str,
+    /// wire_id: the byte that identifies the curve in an envelope.
+    pub wire_id: u8,
+    /// point_size: the size of a compressed point, in bytes.
+    pub point_size: usize,
+    /// scalar_size: the size of a compressed scalar, in bytes.
+    pub scalar_size: usize,
+    /// security_bits: the security level of the curve, in bits.
+    pub security_bits: usize,
+}
+
+impl CurveId {
+    /// ALL. Every curve, in order of preference.
+    pub const ALL: [CurveId; 5] = [
+        CurveId::T256,
+        CurveId::T384,
+        CurveId::T521,
+        CurveId::TSecp256k1,
+        CurveId::TSecq256k1,
+    ];
+
+    /// info. Returns the parameters of the curve.
+    pub const fn info(&self) -> CurveInfo {
+        match self {
+            CurveId::T256 => CurveInfo {
+                name: "t256",
+                wire_id: 1,
+                point_size: 33,
+                scalar_size: 32,
+                security_bits: 128,
+            },
+            CurveId::T384 => CurveInfo {
+                name: "t384",
+                wire_id: 2,
+                point_size: 49,
+                scalar_size: 48,
+                security_bits: 192,
+            },
+            CurveId::T521 => CurveInfo {
+                name: "t521",
+                wire_id: 3,
+                point_size: 66,
+                scalar_size: 66,
+                security_bits: 256,
+            },
+            CurveId::TSecp256k1 => CurveInfo {
+                name: "tsecp256k1",
+                wire_id: 4,
+                point_size: 33,
+                scalar_size: 32,
+                security_bits: 128,
+            },
+            CurveId::TSecq256k1 => CurveInfo {
+                name: "tsecq256k1",
+                wire_id: 5,
+                point_size: 33,
+                scalar_size: 32,
+                security_bits: 128,
+            },
+        }
+    }
+
+    /// name. Returns the name of the curve.
+    pub const fn name(&self) -> &'static str {
+        self.info().name
+    }
+
+    /// wire_id. Returns the byte that identifies the curve in an envelope.
+    pub const fn wire_id(&self) -> u8 {
+        self.info().wire_id
+    }
+
+    /// from_wire_id. This function returns the curve that `wire_id` identifies, if any.
+    /// # Arguments
+    /// * `wire_id` - the byte that identifies the curve.
+    pub fn from_wire_id(wire_id: u8) -> Option<CurveId> {
+        Self::ALL.into_iter().find(|id| id.wire_id() == wire_id)
+    }
+}
+
+impl fmt::Display for CurveId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for CurveId {
+    type Err = UnknownCurve;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|id| id.name() == name)
+            .ok_or_else(|| UnknownCurve(name.to_string()))
+    }
+}
+
+/// UnknownCurve. The error of a name that is not the name of a curve.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownCurve(pub String);
+
+impl fmt::Display for UnknownCurve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Boomerang curves: unknown curve {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCurve {}
+
+/// RegisteredCurve. This trait ties a `BoomerangConfig` to the id of its curve.
+pub trait RegisteredCurve: BoomerangConfig {
+    /// ID. The id of the curve of this config.
+    const ID: CurveId;
+}
+
+/// CurveHandler. This trait describes a computation that is generic over the config, e.g. the
+/// handling of a request, which a `CurveRegistry` runs on the config of a given curve.
+pub trait CurveHandler {
+    /// Output. The result of the computation.
+    type Output;
+
+    /// handle. This function runs the computation on the config `B`.
+    fn handle<B: RegisteredCurve>(self) -> Self::Output;
+}
+
+/// Handle. The handler `H`, run on the config of one curve.
+type Handle<H> = fn(H) -> <H as CurveHandler>::Output;
+
+/// CurveRegistry. This struct maps the ids of the curves that an application was built with
+/// to their configs, for the handler `H`.
+pub struct CurveRegistry<H: CurveHandler> {
+    /// entries: the id of every registered curve, and the handler on its config.
+    entries: Vec<(CurveId, Handle<H>)>,
+}
+
+impl<H: CurveHandler> Default for CurveRegistry<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: CurveHandler> CurveRegistry<H> {
+    /// new. This function returns an empty registry.
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// register. This function registers the config `B` under its id, and returns the
+    /// registry, so that calls can be chained. This function panics if another config was
+    /// registered under the same id.
+    pub fn register<B: RegisteredCurve>(mut self) -> Self {
+        assert!(
+            !self.contains(B::ID),
+            "Boomerang curves: {} is already registered",
+            B::ID
+        );
+        self.entries.push((B::ID, H::handle::<B>));
+        self
+    }
+
+    /// contains. Returns true if a config was registered under `id`.
+    /// # Arguments
+    /// * `id` - the id of the curve.
+    pub fn contains(&self, id: CurveId) -> bool {
+        self.entries.iter().any(|(entry, _)| *entry == id)
+    }
+
+    /// ids. Returns the ids of the registered curves, in the order that they were registered.
+    pub fn ids(&self) -> Vec<CurveId> {
+        self.entries.iter().map(|(id, _)| *id).collect()
+    }
+
+    /// names. Returns the names of the registered curves, in the order that they were
+    /// registered, e.g. to advertise them in the `ServerParams`.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.entries.iter().map(|(id, _)| id.name()).collect()
+    }
+
+    /// dispatch. This function runs `handler` on the config that was registered under `id`.
+    /// This function fails if no config was registered under `id`.
+    /// # Arguments
+    /// * `id` - the id of the curve.
+    /// * `handler` - the computation to run.
+    pub fn dispatch(&self, id: CurveId, handler: H) -> Result<H::Output, UnknownCurve> {
+        self.entries
+            .iter()
+            .find(|(entry, _)| *entry == id)
+            .map(|(_, handle)| handle(handler))
+            .ok_or_else(|| UnknownCurve(id.name().to_string()))
+    }
+}
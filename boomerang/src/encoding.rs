@@ -0,0 +1,163 @@
+//! Defines the wire encodings of points, scalars and byte strings.
+//!
+//! Every encoding in this module has a fixed length for a given curve, except for byte strings,
+//! which carry a length prefix. Points are encoded in compressed form, i.e. the canonical
+//! compressed encoding of arkworks: the little-endian x co-ordinate, with the sign of y and the
+//! identity flag held in the top bits. Scalars are encoded as their canonical integer value, in
+//! the byte order that the caller asks for.
+//!
+//! The decode functions are strict: they reject trailing bytes, non-canonical scalars, and
+//! points that are not on the curve or not in the prime order subgroup. They should be used on
+//! every value that is received from the other party.
+//!
+//! N.B The `append_point` and `append_scalar` functions absorb exactly the bytes that were
+//! previously absorbed by hand, so the transcripts that use them are unchanged.
+
+use ark_ec::short_weierstrass::{self as sw, SWCurveConfig};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use merlin::Transcript;
+use pedersen::transcript::append_message;
+
+/// LEN_PREFIX_BYTES. The number of bytes in the length prefix of a byte string.
+pub const LEN_PREFIX_BYTES: usize = 4;
+
+/// Endianness. The byte order of an encoded scalar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    /// Little: the least significant byte comes first. This is the order that is used in
+    /// every message and transcript of this workspace.
+    Little,
+    /// Big: the most significant byte comes first, e.g. for interoperability with SEC1
+    /// or RFC 6979 encodings.
+    Big,
+}
+
+/// point_len. Returns the length of the encoding of a point on `C`.
+pub fn point_len<C: SWCurveConfig>() -> usize {
+    <C as SWCurveConfig>::GENERATOR.compressed_size()
+}
+
+/// scalar_len. Returns the length of the encoding of an element of `F`.
+pub fn scalar_len<F: PrimeField>() -> usize {
+    F::zero().compressed_size()
+}
+
+/// encode_point. Returns the compressed encoding of `point`.
+/// # Arguments
+/// * `point` - the point to encode.
+pub fn encode_point<C: SWCurveConfig>(point: &sw::Affine<C>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(point_len::<C>());
+    point.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+/// decode_point. This function returns the point that is encoded in `bytes`. This function
+/// fails if `bytes` is not exactly the encoding of a point, or if the point is not on the curve
+/// or not in the prime order subgroup.
+/// # Arguments
+/// * `bytes` - the encoded point.
+pub fn decode_point<C: SWCurveConfig>(bytes: &[u8]) -> Result<sw::Affine<C>, &'static str> {
+    if bytes.len() != point_len::<C>() {
+        return Err("Boomerang encoding: point has the wrong length");
+    }
+
+    let point = sw::Affine::<C>::deserialize_compressed_unchecked(bytes)
+        .map_err(|_| "Boomerang encoding: malformed point")?;
+
+    if !point.is_on_curve() {
+        return Err("Boomerang encoding: point is not on the curve");
+    }
+
+    if !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err("Boomerang encoding: point is not in the prime order subgroup");
+    }
+
+    Ok(point)
+}
+
+/// encode_scalar. Returns the encoding of `scalar` in the byte order `order`.
+/// # Arguments
+/// * `scalar` - the scalar to encode.
+/// * `order` - the byte order of the encoding.
+pub fn encode_scalar<F: PrimeField>(scalar: &F, order: Endianness) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(scalar_len::<F>());
+    scalar.serialize_compressed(&mut bytes).unwrap();
+    if order == Endianness::Big {
+        bytes.reverse();
+    }
+    bytes
+}
+
+/// decode_scalar. This function returns the scalar that is encoded in `bytes` in the byte order
+/// `order`. This function fails if `bytes` does not have the length of an encoded scalar, or if
+/// the encoded integer is not smaller than the modulus.
+/// # Arguments
+/// * `bytes` - the encoded scalar.
+/// * `order` - the byte order of the encoding.
+pub fn decode_scalar<F: PrimeField>(bytes: &[u8], order: Endianness) -> Result<F, &'static str> {
+    if bytes.len() != scalar_len::<F>() {
+        return Err("Boomerang encoding: scalar has the wrong length");
+    }
+
+    let mut le_bytes = bytes.to_vec();
+    if order == Endianness::Big {
+        le_bytes.reverse();
+    }
+
+    F::deserialize_compressed(&le_bytes[..]).map_err(|_| "Boomerang encoding: non-canonical scalar")
+}
+
+/// encode_bytes. This function appends `bytes` to `out`, prefixed with its length as a
+/// little-endian u32.
+/// # Arguments
+/// * `out` - the buffer to write to.
+/// * `bytes` - the byte string to encode.
+pub fn encode_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    let len = u32::try_from(bytes.len()).expect("Boomerang encoding: byte string is too long");
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+/// decode_bytes. This function reads a length-prefixed byte string from the front of `input`,
+/// and advances `input` past it. This function fails if `input` is shorter than the prefix says.
+/// # Arguments
+/// * `input` - the buffer to read from.
+pub fn decode_bytes<'a>(input: &mut &'a [u8]) -> Result<&'a [u8], &'static str> {
+    const ERR: &str = "Boomerang encoding: truncated byte string";
+
+    let prefix = input.get(..LEN_PREFIX_BYTES).ok_or(ERR)?;
+    let len = u32::from_le_bytes(prefix.try_into().unwrap()) as usize;
+    let end = LEN_PREFIX_BYTES.checked_add(len).ok_or(ERR)?;
+    let bytes = input.get(LEN_PREFIX_BYTES..end).ok_or(ERR)?;
+
+    *input = &input[end..];
+    Ok(bytes)
+}
+
+/// append_point. This function adds the encoding of `point` to `transcript` under `label`.
+/// # Arguments
+/// * `transcript` - the transcript to add to.
+/// * `label` - the label of the point.
+/// * `point` - the point.
+pub fn append_point<C: SWCurveConfig>(
+    transcript: &mut Transcript,
+    label: &'static [u8],
+    point: &sw::Affine<C>,
+) {
+    append_message(transcript, label, &encode_point(point));
+}
+
+/// append_scalar. This function adds the little-endian encoding of `scalar` to `transcript`
+/// under `label`.
+/// # Arguments
+/// * `transcript` - the transcript to add to.
+/// * `label` - the label of the scalar.
+/// * `scalar` - the scalar.
+pub fn append_scalar<F: PrimeField>(transcript: &mut Transcript, label: &'static [u8], scalar: &F) {
+    append_message(
+        transcript,
+        label,
+        &encode_scalar(scalar, Endianness::Little),
+    );
+}
@@ -0,0 +1,291 @@
+//! Defines the JSON representation of the protocol messages.
+//!
+//! Every message is encoded as a JSON object with the following members:
+//!
+//! * `version`: the version of the representation, i.e `JSON_VERSION`.
+//! * `type`: the name of the message struct, e.g. `"SpendVerifyM2"`.
+//! * `curve`: optionally, the name of the curve of the message, e.g. `"t256"`, so that a
+//!   server that runs on several curves can dispatch the message before it decodes it (see
+//!   `message_curve`).
+//! * one member per field of the message struct, named after the field, e.g. `"session_id"`.
+//!   Its value is the unpadded base64url encoding (RFC 4648, section 5) of the compressed
+//!   arkworks encoding of the field, i.e. the bytes that the field contributes to the compressed
+//!   encoding of the whole message.
+//!
+//! The decoder is strict about the members that it knows: it rejects a message of another
+//! version or type, a missing field, a malformed payload, and a payload with trailing bytes.
+//! It ignores members that it does not know, so that later versions can add members without
+//! breaking older decoders.
+//!
+//! N.B Fields that hold other structs, e.g. the inputs of a `SpendVerifyM2Multi`, are a single
+//! payload, rather than a nested object.
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use serde_json::{Map, Value};
+
+use crate::client::{
+    BackupRegistration, CollectionM2, CollectionM4, IssuanceM1, IssuanceM3, RecoveryM2,
+    SpendVerifyM2, SpendVerifyM2Multi, SpendVerifyM4, TransferM2, TransferM4, UpdateM2, UpdateM4,
+};
+use crate::config::BoomerangConfig;
+use crate::curves::CurveId;
+use crate::server::{
+    CollectionM1, CollectionM3, CollectionM5, IssuanceM2, IssuanceM4, SpendVerifyAck,
+    SpendVerifyM1, SpendVerifyM3, SpendVerifyM5, TransferM1, TransferM3, TransferM5, UpdateM1,
+    UpdateM3, UpdateM5,
+};
+
+/// JSON_VERSION. The version of the JSON representation of the messages.
+pub const JSON_VERSION: u64 = 1;
+
+/// BASE64URL. The alphabet of the base64url encoding.
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// MessageFields. A protocol message, seen as a list of named fields. The representations of
+/// the messages in this module, and in the `cbor` module, are built from these fields.
+pub trait MessageFields: Sized {
+    /// MESSAGE_TYPE: the type of the message, i.e the name of the message struct.
+    const MESSAGE_TYPE: &'static str;
+
+    /// to_fields. Returns the name and the compressed encoding of every field of the message,
+    /// in the order of the struct.
+    fn to_fields(&self) -> Vec<(&'static str, Vec<u8>)>;
+
+    /// from_fields. This function builds a message from the encodings of its fields. This
+    /// function fails if a field is missing or cannot be decoded.
+    /// # Arguments
+    /// * `field` - returns the encoding of the field with the given name, or fails if there is
+    ///   none.
+    fn from_fields(field: impl Fn(&'static str) -> Result<Vec<u8>, String>)
+        -> Result<Self, String>;
+}
+
+/// JsonMessage. A protocol message with a JSON representation.
+pub trait JsonMessage: Sized {
+    /// to_json. Returns the JSON representation of the message.
+    fn to_json(&self) -> String;
+
+    /// to_json_with_curve. Returns the JSON representation of the message, tagged with the
+    /// name of `curve`.
+    /// # Arguments
+    /// * `curve` - the curve of the message.
+    fn to_json_with_curve(&self, curve: CurveId) -> String;
+
+    /// from_json. This function parses a message from its JSON representation. This function
+    /// fails if `json` is not the representation of a message of this type and version.
+    /// # Arguments
+    /// * `json` - the JSON representation of the message.
+    fn from_json(json: &str) -> Result<Self, String>;
+}
+
+impl<M: MessageFields> JsonMessage for M {
+    fn to_json(&self) -> String {
+        Value::Object(to_object(self)).to_string()
+    }
+
+    fn to_json_with_curve(&self, curve: CurveId) -> String {
+        let mut object = to_object(self);
+        object.insert("curve".to_string(), curve.name().into());
+        Value::Object(object).to_string()
+    }
+
+    fn from_json(json: &str) -> Result<Self, String> {
+        let object = parse_envelope(json, M::MESSAGE_TYPE)?;
+        M::from_fields(|name| {
+            let payload = object
+                .get(name)
+                .and_then(Value::as_str)
+                .ok_or_else(|| format!("Missing field {}", name))?;
+            from_base64url(payload).map_err(|e| format!("{}: {}", name, e))
+        })
+    }
+}
+
+/// to_object. Returns the JSON object of the message, without a curve.
+fn to_object<M: MessageFields>(message: &M) -> Map<String, Value> {
+    let mut object = Map::new();
+    object.insert("version".to_string(), JSON_VERSION.into());
+    object.insert("type".to_string(), M::MESSAGE_TYPE.into());
+    for (name, bytes) in message.to_fields() {
+        object.insert(name.to_string(), Value::String(to_base64url(&bytes)));
+    }
+    object
+}
+
+/// message_curve. This function returns the curve that the JSON representation of a message
+/// is tagged with, if any, without decoding the message. This function fails if `json` is not
+/// an object, or if its curve is not the name of a curve.
+/// # Arguments
+/// * `json` - the JSON representation of the message.
+pub fn message_curve(json: &str) -> Result<Option<CurveId>, String> {
+    let Value::Object(object) =
+        serde_json::from_str(json).map_err(|e| format!("Invalid message: {}", e))?
+    else {
+        return Err("Invalid message: not an object".to_string());
+    };
+
+    match object.get("curve") {
+        None => Ok(None),
+        Some(Value::String(name)) => name.parse().map(Some).map_err(|e| format!("{}", e)),
+        Some(_) => Err("Invalid message curve".to_string()),
+    }
+}
+
+/// to_base64url. Returns the unpadded base64url encoding of `bytes`.
+fn to_base64url(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity((bytes.len() * 4).div_ceil(3));
+    for chunk in bytes.chunks(3) {
+        let mut block = [0u8; 3];
+        block[..chunk.len()].copy_from_slice(chunk);
+        let n = u32::from_be_bytes([0, block[0], block[1], block[2]]);
+        for i in 0..=chunk.len() {
+            out.push(BASE64URL[((n >> (18 - 6 * i)) & 63) as usize] as char);
+        }
+    }
+    out
+}
+
+/// from_base64url. Returns the bytes whose unpadded base64url encoding is `s`. This function
+/// fails on padding, on characters outside of the alphabet, and on non-canonical encodings.
+fn from_base64url(s: &str) -> Result<Vec<u8>, String> {
+    if s.len() % 4 == 1 {
+        return Err("Invalid base64url length".to_string());
+    }
+
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    for chunk in s.as_bytes().chunks(4) {
+        let mut n = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let v = BASE64URL
+                .iter()
+                .position(|a| a == c)
+                .ok_or_else(|| format!("Invalid base64url character {:?}", *c as char))?;
+            n |= (v as u32) << (18 - 6 * i);
+        }
+
+        let bytes = n.to_be_bytes();
+        let len = chunk.len() - 1;
+        if bytes[1 + len..].iter().any(|&b| b != 0) {
+            return Err("Non-canonical base64url encoding".to_string());
+        }
+        out.extend_from_slice(&bytes[1..1 + len]);
+    }
+    Ok(out)
+}
+
+/// encode_field. Returns the compressed encoding of a field.
+fn encode_field<T: CanonicalSerialize>(field: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    field
+        .serialize_compressed(&mut bytes)
+        .expect("Failed to serialize message field");
+    bytes
+}
+
+/// decode_field. Returns the field `name`, decoded from `bytes`. This function fails if `bytes`
+/// is malformed, or has trailing bytes.
+fn decode_field<T: CanonicalDeserialize>(bytes: &[u8], name: &str) -> Result<T, String> {
+    let mut reader = bytes;
+    let field = T::deserialize_compressed(&mut reader).map_err(|e| format!("{}: {}", name, e))?;
+    if !reader.is_empty() {
+        return Err(format!("{}: trailing bytes", name));
+    }
+    Ok(field)
+}
+
+/// parse_envelope. Returns the members of the JSON object `json`, after checking its version
+/// and type.
+fn parse_envelope(json: &str, message_type: &str) -> Result<Map<String, Value>, String> {
+    let Value::Object(object) =
+        serde_json::from_str(json).map_err(|e| format!("Invalid message: {}", e))?
+    else {
+        return Err("Invalid message: not an object".to_string());
+    };
+
+    match object.get("version").and_then(Value::as_u64) {
+        Some(JSON_VERSION) => {}
+        Some(version) => return Err(format!("Unsupported message version {}", version)),
+        None => return Err("Missing message version".to_string()),
+    }
+
+    match object.get("type").and_then(Value::as_str) {
+        Some(t) if t == message_type => Ok(object),
+        Some(t) => Err(format!("Expected a {} message, got {}", message_type, t)),
+        None => Err("Missing message type".to_string()),
+    }
+}
+
+/// message_fields. Implements `MessageFields` for message structs, from the list of their
+/// fields. Every field must be listed, as the struct is built from the decoded fields.
+macro_rules! message_fields {
+    ($($name:ident { $($field:ident),* $(,)? })*) => {
+        $(
+            impl<B: BoomerangConfig> MessageFields for $name<B> {
+                const MESSAGE_TYPE: &'static str = stringify!($name);
+
+                fn to_fields(&self) -> Vec<(&'static str, Vec<u8>)> {
+                    vec![$((stringify!($field), encode_field(&self.$field)),)*]
+                }
+
+                fn from_fields(
+                    field: impl Fn(&'static str) -> Result<Vec<u8>, String>,
+                ) -> Result<Self, String> {
+                    Ok(Self {
+                        $($field: decode_field(&field(stringify!($field))?, stringify!($field))?,)*
+                    })
+                }
+            }
+        )*
+    };
+}
+
+message_fields! {
+    IssuanceM1 { comm, pi_issuance, u_pk, len, gens, nonce }
+    IssuanceM2 { comm, sig_commit, id_1, verifying_key, tag_key, nonce, balance, pi_balance }
+    IssuanceM3 { e, session_id }
+    IssuanceM4 { s, session_id }
+
+    CollectionM1 { r2, session_id }
+    CollectionM2 {
+        comm, gens, prev_comm, prev_gens, pi_1, pi_2, pi_3, tag, id, sig, s_proof, tag_commits,
+        session_id,
+    }
+    CollectionM3 { comm, sig_commit, id_1, val, verifying_key, tag_key, session_id }
+    CollectionM4 { e, session_id }
+    CollectionM5 { s, session_id }
+
+    UpdateM1 { r2, attr, session_id }
+    UpdateM2 {
+        comm, gens, prev_comm, prev_gens, pi_1, pi_2, pi_3, tag, id, sig, s_proof, tag_commits,
+        session_id,
+    }
+    UpdateM3 { comm, sig_commit, id_1, delta, verifying_key, tag_key, session_id }
+    UpdateM4 { e, session_id }
+    UpdateM5 { s, session_id }
+
+    SpendVerifyM1 { r2, session_id }
+    SpendVerifyM2 {
+        comm, gens, prev_comm, prev_gens, pi_1, pi_2, pi_3, pi_4, tag, id, sig, s_proof,
+        tag_commits, spend_state, session_id,
+    }
+    SpendVerifyM2Multi { comm, gens, pi_1, pi_balance, inputs, pi_4, spend_state, session_id }
+    SpendVerifyM3 {
+        comm, sig_commit, id_1, val, verifying_key, tag_key, pi_reward, reward_opening,
+        policy_id, session_id,
+    }
+    SpendVerifyAck { comm, sig_commit, id_1, val, verifying_key, tag_key, session_id }
+    SpendVerifyM4 { e, session_id }
+    SpendVerifyM5 { s, session_id }
+
+    TransferM1 { r2, session_id }
+    TransferM2 {
+        prev_comm, prev_gens, value_comm, pi_value, pi_rest, pi_3, tag, id, sig, s_proof,
+        tag_commits, request, session_id,
+    }
+    TransferM3 { sig_commit, value_comm, verifying_key, tag_key, session_id }
+    TransferM4 { e, session_id }
+    TransferM5 { s, session_id }
+
+    BackupRegistration { user_pk, backup_pk, alpha_u, alpha_b, z_u, z_b }
+    RecoveryM2 { transfer, user_pk, pi }
+}
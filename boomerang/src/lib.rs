@@ -1,5 +1,37 @@
 #![forbid(unsafe_code)]
+pub mod batch;
+pub mod bundle;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+pub mod checkpoint;
 pub mod client;
+pub mod clock;
+pub mod commitment;
 pub mod config;
+#[cfg(feature = "r1cs")]
+pub mod constraints;
+pub mod credlog;
+pub mod curves;
+pub mod encoding;
+pub mod json;
+pub mod merkle;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod params;
+pub mod policy;
+pub mod progress;
+pub mod rng;
+pub mod routes;
 pub mod server;
+pub mod sizes;
+pub mod spendlimit;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod testvectors;
+pub mod ticket;
+mod trace;
 pub mod utils;
+pub mod wallet;
+
+pub use bundle::{verify_spend_bundle, verify_spend_bundle_with_rng, SpendBundle};
+pub use sizes::{sizes, MessageSizes};
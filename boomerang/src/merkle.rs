@@ -0,0 +1,286 @@
+//! Defines a sorted set of 32-byte keys that is committed to with a Merkle tree.
+//!
+//! A `MerkleSet` is used wherever the server publishes a set that third parties need to check
+//! against without holding a copy of it, e.g. the revocation list and the checkpoints of the
+//! tag store. The set is summarised by a `MerkleRoot`, and membership of a key is shown with a
+//! `MerklePath`. Since the keys are kept sorted, absence of a key is shown with an
+//! `ExclusionProof`, i.e. the paths to the two keys that are adjacent to it.
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use merlin::Transcript;
+use pedersen::transcript::{append_message, challenge_bytes};
+
+/// MerkleDigest. The type of every key and every node of a Merkle tree.
+pub type MerkleDigest = [u8; 32];
+
+/// merkle_hash. This function hashes `parts` under the domain `label` into a node of a Merkle tree.
+/// # Arguments
+/// * `label` - the domain of the node.
+/// * `parts` - the parts of the node.
+pub(crate) fn merkle_hash(label: &'static [u8], parts: &[&[u8]]) -> MerkleDigest {
+    let mut transcript = Transcript::new(b"Boomerang Merkle tree");
+    append_message(&mut transcript, b"dom-sep", label);
+    for part in parts {
+        append_message(&mut transcript, b"part", part);
+    }
+
+    let mut digest = [0u8; 32];
+    challenge_bytes(&mut transcript, b"digest", &mut digest);
+    digest
+}
+
+/// MerkleRoot. This struct acts as a compact commitment to a `MerkleSet`. It is all that a
+/// verifier needs in order to check a `MerklePath` or an `ExclusionProof`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MerkleRoot {
+    /// epoch: the epoch of the set.
+    pub epoch: u64,
+    /// len: the number of keys in the set.
+    pub len: u64,
+    /// digest: the digest of the Merkle tree over the keys, bound to `epoch` and `len`.
+    pub digest: MerkleDigest,
+}
+
+/// MerklePath. This struct acts as a container for a key and the path from its leaf to the
+/// root of the tree.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MerklePath {
+    /// key: the key.
+    pub key: MerkleDigest,
+    /// index: the position of `key` in the sorted set.
+    pub index: u64,
+    /// siblings: the sibling of every node on the path, from the leaf upwards.
+    pub siblings: Vec<MerkleDigest>,
+}
+
+impl MerklePath {
+    /// tree_root. Returns the root of the tree that this path leads to.
+    /// # Arguments
+    /// * `epoch` - the epoch of the set.
+    fn tree_root(&self, epoch: u64) -> MerkleDigest {
+        let mut node = MerkleSet::leaf(epoch, &self.key);
+        let mut index = self.index;
+        for sibling in self.siblings.iter() {
+            node = if index & 1 == 0 {
+                merkle_hash(b"node", &[&node, sibling])
+            } else {
+                merkle_hash(b"node", &[sibling, &node])
+            };
+            index >>= 1;
+        }
+        node
+    }
+
+    /// verify. This function returns true if this path shows that `self.key` is at `self.index`
+    /// in the set that is committed to by `root`, and false otherwise.
+    /// # Arguments
+    /// * `root` - the root of the set.
+    pub fn verify(&self, root: &MerkleRoot) -> bool {
+        self.index < root.len
+            && MerkleSet::root_digest(root.epoch, root.len, &self.tree_root(root.epoch))
+                == root.digest
+    }
+}
+
+/// ExclusionProof. This struct acts as a proof that a key is not in a `MerkleSet`.
+/// `lower` is None if the key is smaller than every key in the set, and `upper` is None if the
+/// key is larger than every key in the set.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ExclusionProof {
+    /// lower: the path to the largest key in the set that is smaller than the key.
+    pub lower: Option<MerklePath>,
+    /// upper: the path to the smallest key in the set that is larger than the key.
+    pub upper: Option<MerklePath>,
+}
+
+impl ExclusionProof {
+    /// verify. This function returns true if this proof shows that `key` is not in the set
+    /// that is committed to by `root`, and false otherwise.
+    /// # Arguments
+    /// * `root` - the root of the set.
+    /// * `key` - the key that is claimed to be absent.
+    pub fn verify(&self, root: &MerkleRoot, key: &MerkleDigest) -> bool {
+        match (&self.lower, &self.upper) {
+            (None, None) => root.len == 0 && root.digest == MerkleSet::empty_root(root.epoch),
+            (Some(lower), None) => {
+                lower.verify(root) && lower.index + 1 == root.len && lower.key < *key
+            }
+            (None, Some(upper)) => upper.verify(root) && upper.index == 0 && *key < upper.key,
+            (Some(lower), Some(upper)) => {
+                lower.verify(root)
+                    && upper.verify(root)
+                    && lower.index + 1 == upper.index
+                    && lower.key < *key
+                    && *key < upper.key
+            }
+        }
+    }
+}
+
+/// MerkleSet. This struct holds a sorted set of keys that belongs to an epoch, and commits to
+/// it with a Merkle tree.
+#[derive(Clone, Debug, Default)]
+pub struct MerkleSet {
+    /// epoch: the epoch of the set.
+    epoch: u64,
+    /// keys: the keys, in ascending order.
+    keys: Vec<MerkleDigest>,
+}
+
+impl MerkleSet {
+    /// new. This function returns a new, empty, set for `epoch`.
+    /// # Arguments
+    /// * `epoch` - the epoch of the set.
+    pub fn new(epoch: u64) -> Self {
+        Self {
+            epoch,
+            keys: Vec::new(),
+        }
+    }
+
+    /// epoch. Returns the epoch of the set.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// len. Returns the number of keys in the set.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// is_empty. Returns true if the set holds no key.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// insert. This function adds `key` to the set. Returns false if `key` was already in the set.
+    /// # Arguments
+    /// * `key` - the key to add.
+    pub fn insert(&mut self, key: MerkleDigest) -> bool {
+        match self.keys.binary_search(&key) {
+            Ok(_) => false,
+            Err(pos) => {
+                self.keys.insert(pos, key);
+                true
+            }
+        }
+    }
+
+    /// contains. Returns true if `key` is in the set.
+    /// # Arguments
+    /// * `key` - the key to look up.
+    pub fn contains(&self, key: &MerkleDigest) -> bool {
+        self.keys.binary_search(key).is_ok()
+    }
+
+    /// leaf. Returns the leaf of `key` in the tree of `epoch`.
+    fn leaf(epoch: u64, key: &MerkleDigest) -> MerkleDigest {
+        merkle_hash(b"leaf", &[&epoch.to_le_bytes(), key])
+    }
+
+    /// root_digest. Returns the digest that binds `tree_root` to `epoch` and `len`.
+    fn root_digest(epoch: u64, len: u64, tree_root: &MerkleDigest) -> MerkleDigest {
+        merkle_hash(
+            b"root",
+            &[&epoch.to_le_bytes(), &len.to_le_bytes(), tree_root],
+        )
+    }
+
+    /// empty_root. Returns the digest of an empty set in `epoch`.
+    fn empty_root(epoch: u64) -> MerkleDigest {
+        Self::root_digest(epoch, 0, &merkle_hash(b"empty", &[]))
+    }
+
+    /// levels. Returns every level of the tree, from the leaves upwards. The leaves are padded
+    /// to a power of two.
+    fn levels(&self) -> Vec<Vec<MerkleDigest>> {
+        let width = self.keys.len().next_power_of_two();
+        let mut level: Vec<MerkleDigest> = self
+            .keys
+            .iter()
+            .map(|key| Self::leaf(self.epoch, key))
+            .collect();
+        level.resize(width, merkle_hash(b"empty", &[]));
+
+        let mut levels = vec![level];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| merkle_hash(b"node", &[&pair[0], &pair[1]]))
+                .collect();
+            levels.push(next);
+        }
+        levels
+    }
+
+    /// path. Returns the path to the key at `index`.
+    fn path(&self, levels: &[Vec<MerkleDigest>], index: usize) -> MerklePath {
+        let siblings = levels[..levels.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(depth, level)| level[(index >> depth) ^ 1])
+            .collect();
+        MerklePath {
+            key: self.keys[index],
+            index: index as u64,
+            siblings,
+        }
+    }
+
+    /// root. Returns the root of the set.
+    pub fn root(&self) -> MerkleRoot {
+        if self.keys.is_empty() {
+            return MerkleRoot {
+                epoch: self.epoch,
+                len: 0,
+                digest: Self::empty_root(self.epoch),
+            };
+        }
+
+        let levels = self.levels();
+        MerkleRoot {
+            epoch: self.epoch,
+            len: self.keys.len() as u64,
+            digest: Self::root_digest(
+                self.epoch,
+                self.keys.len() as u64,
+                &levels.last().unwrap()[0],
+            ),
+        }
+    }
+
+    /// inclusion_proof. This function returns a path that shows that `key` is in the set,
+    /// or None if it is not.
+    /// # Arguments
+    /// * `key` - the key to look up.
+    pub fn inclusion_proof(&self, key: &MerkleDigest) -> Option<MerklePath> {
+        let pos = self.keys.binary_search(key).ok()?;
+        Some(self.path(&self.levels(), pos))
+    }
+
+    /// exclusion_proof. This function returns a proof that `key` is not in the set,
+    /// or None if it is.
+    /// # Arguments
+    /// * `key` - the key to look up.
+    pub fn exclusion_proof(&self, key: &MerkleDigest) -> Option<ExclusionProof> {
+        let pos = match self.keys.binary_search(key) {
+            Ok(_) => return None,
+            Err(pos) => pos,
+        };
+
+        if self.keys.is_empty() {
+            return Some(ExclusionProof {
+                lower: None,
+                upper: None,
+            });
+        }
+
+        let levels = self.levels();
+        Some(ExclusionProof {
+            lower: pos.checked_sub(1).map(|i| self.path(&levels, i)),
+            upper: (pos < self.keys.len()).then(|| self.path(&levels, pos)),
+        })
+    }
+}
@@ -0,0 +1,239 @@
+//! Defines the optional `metrics` of a Boomerang server.
+//!
+//! With the `metrics` feature, the server functions keep a process-wide registry of:
+//!
+//! * `boomerang_messages_total` and `boomerang_message_bytes_total`: the number of messages, and
+//!   of compressed bytes, that were received (`direction="in"`) or produced (`direction="out"`),
+//!   by message type.
+//! * `boomerang_verify_duration_seconds`: a histogram of the time spent checking each type of
+//!   message or proof, whether the check succeeded or not.
+//! * `boomerang_rejects_total`: the number of rejected messages, by reason. The reasons are the
+//!   errors that the server functions return (or panic with).
+//! * `boomerang_sessions_active`: the number of sessions, by protocol, whose first server message
+//!   has been produced, but whose last one has not. A session that a client abandons is never
+//!   closed, so it stays counted until the process restarts.
+//!
+//! `render` returns the registry in the Prometheus text exposition format, so that it can be
+//! served as is, e.g. on a `/metrics` route.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// BUCKETS. The upper bounds of the buckets of the duration histograms, in seconds.
+const BUCKETS: [f64; 12] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+];
+
+/// Direction. Whether a message was received or produced.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Direction {
+    /// In: the message was received.
+    In,
+    /// Out: the message was produced.
+    Out,
+}
+
+impl Direction {
+    /// label. Returns the label value of the direction.
+    fn label(self) -> &'static str {
+        match self {
+            Direction::In => "in",
+            Direction::Out => "out",
+        }
+    }
+}
+
+/// Histogram. The observations of one duration histogram.
+#[derive(Default)]
+struct Histogram {
+    /// buckets: the number of observations in each bucket, i.e not cumulative.
+    buckets: [u64; BUCKETS.len()],
+    /// count: the number of observations.
+    count: u64,
+    /// sum: the sum of the observations, in seconds.
+    sum: f64,
+}
+
+/// Registry. The values of every metric.
+struct Registry {
+    /// messages: the number of messages and of bytes, by message type and direction.
+    messages: BTreeMap<(&'static str, Direction), (u64, u64)>,
+    /// verify: the verification times, by message type.
+    verify: BTreeMap<&'static str, Histogram>,
+    /// rejects: the number of rejected messages, by reason.
+    rejects: BTreeMap<&'static str, u64>,
+    /// sessions: the number of active sessions, by protocol.
+    sessions: BTreeMap<&'static str, i64>,
+}
+
+/// REGISTRY. The process-wide registry.
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    messages: BTreeMap::new(),
+    verify: BTreeMap::new(),
+    rejects: BTreeMap::new(),
+    sessions: BTreeMap::new(),
+});
+
+/// with_registry. Runs `f` on the registry. A poisoned registry is still used, as every update
+/// leaves it consistent.
+fn with_registry<R>(f: impl FnOnce(&mut Registry) -> R) -> R {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut registry)
+}
+
+/// message_type. Returns the name of `T` without its path or generic arguments, e.g.
+/// `SpendVerifyM2` for `boomerang::server::SpendVerifyM2<Config>`.
+pub(crate) fn message_type<T: ?Sized>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    let name = &name[..name.find('<').unwrap_or(name.len())];
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// message. Counts a message of `size` bytes.
+/// # Arguments
+/// * `message` - the type of the message.
+/// * `direction` - whether the message was received or produced.
+/// * `size` - the compressed size of the message.
+pub(crate) fn message(message: &'static str, direction: Direction, size: usize) {
+    with_registry(|r| {
+        let (count, bytes) = r.messages.entry((message, direction)).or_default();
+        *count += 1;
+        *bytes += size as u64;
+    });
+}
+
+/// verified. Records the time spent checking a message.
+/// # Arguments
+/// * `message` - the type of the message.
+/// * `elapsed` - the time spent checking the message.
+pub(crate) fn verified(message: &'static str, elapsed: Duration) {
+    let secs = elapsed.as_secs_f64();
+    with_registry(|r| {
+        let histogram = r.verify.entry(message).or_default();
+        if let Some(i) = BUCKETS.iter().position(|&le| secs <= le) {
+            histogram.buckets[i] += 1;
+        }
+        histogram.count += 1;
+        histogram.sum += secs;
+    });
+}
+
+/// rejected. Counts a rejected message.
+/// # Arguments
+/// * `reason` - the reason for the rejection.
+pub(crate) fn rejected(reason: &'static str) {
+    with_registry(|r| *r.rejects.entry(reason).or_default() += 1);
+}
+
+/// session. Adds `delta` to the number of active sessions of `protocol`.
+/// # Arguments
+/// * `protocol` - the protocol of the session.
+/// * `delta` - 1 when a session is opened, -1 when it is closed.
+pub(crate) fn session(protocol: &'static str, delta: i64) {
+    with_registry(|r| *r.sessions.entry(protocol).or_default() += delta);
+}
+
+/// escape. Escapes a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// render. This function returns every metric in the Prometheus text exposition format
+/// (version 0.0.4).
+pub fn render() -> String {
+    with_registry(|r| {
+        let mut out = String::new();
+
+        out.push_str(
+            "# HELP boomerang_messages_total Boomerang messages, by type and direction.\n",
+        );
+        out.push_str("# TYPE boomerang_messages_total counter\n");
+        for ((message, direction), (count, _)) in &r.messages {
+            let _ = writeln!(
+                out,
+                "boomerang_messages_total{{message=\"{}\",direction=\"{}\"}} {}",
+                message,
+                direction.label(),
+                count
+            );
+        }
+
+        out.push_str(
+            "# HELP boomerang_message_bytes_total Compressed size of the Boomerang messages, by \
+             type and direction.\n",
+        );
+        out.push_str("# TYPE boomerang_message_bytes_total counter\n");
+        for ((message, direction), (_, bytes)) in &r.messages {
+            let _ = writeln!(
+                out,
+                "boomerang_message_bytes_total{{message=\"{}\",direction=\"{}\"}} {}",
+                message,
+                direction.label(),
+                bytes
+            );
+        }
+
+        out.push_str(
+            "# HELP boomerang_verify_duration_seconds Time spent checking a Boomerang message \
+             or proof.\n",
+        );
+        out.push_str("# TYPE boomerang_verify_duration_seconds histogram\n");
+        for (message, histogram) in &r.verify {
+            let mut cumulative = 0;
+            for (le, n) in BUCKETS.iter().zip(&histogram.buckets) {
+                cumulative += n;
+                let _ = writeln!(
+                    out,
+                    "boomerang_verify_duration_seconds_bucket{{message=\"{}\",le=\"{}\"}} {}",
+                    message, le, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "boomerang_verify_duration_seconds_bucket{{message=\"{}\",le=\"+Inf\"}} {}",
+                message, histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "boomerang_verify_duration_seconds_sum{{message=\"{}\"}} {}",
+                message, histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "boomerang_verify_duration_seconds_count{{message=\"{}\"}} {}",
+                message, histogram.count
+            );
+        }
+
+        out.push_str("# HELP boomerang_rejects_total Rejected Boomerang messages, by reason.\n");
+        out.push_str("# TYPE boomerang_rejects_total counter\n");
+        for (reason, count) in &r.rejects {
+            let _ = writeln!(
+                out,
+                "boomerang_rejects_total{{reason=\"{}\"}} {}",
+                escape(reason),
+                count
+            );
+        }
+
+        out.push_str(
+            "# HELP boomerang_sessions_active Boomerang sessions that are in progress, by \
+             protocol.\n",
+        );
+        out.push_str("# TYPE boomerang_sessions_active gauge\n");
+        for (protocol, count) in &r.sessions {
+            let _ = writeln!(
+                out,
+                "boomerang_sessions_active{{protocol=\"{}\"}} {}",
+                protocol, count
+            );
+        }
+
+        out
+    })
+}
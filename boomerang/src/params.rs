@@ -0,0 +1,216 @@
+//!
+//! Module containing the parameter negotiation that comes before issuance.
+//!
+//! The server advertises what it supports in a `ServerParams` message, which is fetched from
+//! the `PARAMS` route. The client library compares it with what the client supports, in
+//! `ClientParams`, and picks the parameters that both sides run the protocols with, before it
+//! starts the Issuance Protocol. A client and a server that were built with different curves or
+//! versions then fail up front with a `ParamsError`, rather than on the first message that
+//! does not decode.
+//!
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use std::fmt;
+
+use crate::curves::{CurveId, UnknownCurve};
+use crate::server::{ServerConfig, SUPPORTED_CURVES};
+
+/// PROTOCOL_VERSION. The version of the protocol messages that this library speaks.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// SUPPORTED_VERSIONS. The versions of the protocol messages that this library can speak.
+pub const SUPPORTED_VERSIONS: &[u32] = &[PROTOCOL_VERSION];
+
+/// SUPPORTED_BITSIZES. The bit sizes of the range proofs that this library can make and check,
+/// i.e the sizes of the rewards and of the spent values.
+pub const SUPPORTED_BITSIZES: &[u32] = &[64];
+
+/// DEFAULT_MAX_CATALOG_SIZE. The largest catalog that a client accepts by default, i.e the
+/// capacity of the shared rewards generators.
+pub const DEFAULT_MAX_CATALOG_SIZE: usize = 64;
+
+/// ServerParams. This struct is the message that a server advertises its parameters with.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ServerParams {
+    /// curves: the names of the curves that the server runs on, in order of preference.
+    pub curves: Vec<String>,
+    /// catalog_size: the number of actions of the policy, i.e the length of the spend state.
+    pub catalog_size: u64,
+    /// bitsizes: the bit sizes of the range proofs that the server accepts.
+    pub bitsizes: Vec<u32>,
+    /// versions: the versions of the protocol messages that the server speaks.
+    pub versions: Vec<u32>,
+}
+
+impl ServerParams {
+    /// new. This function returns the parameters of a server that runs on `curves`, with a
+    /// policy of `catalog_size` actions, and that supports every bit size and version of this
+    /// library.
+    /// # Arguments
+    /// * `curves` - the names of the curves, in order of preference.
+    /// * `catalog_size` - the number of actions of the policy.
+    pub fn new(curves: &[&str], catalog_size: usize) -> Self {
+        Self {
+            curves: curves.iter().map(|curve| curve.to_string()).collect(),
+            catalog_size: catalog_size as u64,
+            bitsizes: SUPPORTED_BITSIZES.to_vec(),
+            versions: SUPPORTED_VERSIONS.to_vec(),
+        }
+    }
+}
+
+impl From<&ServerConfig> for ServerParams {
+    fn from(config: &ServerConfig) -> Self {
+        Self::new(&[config.curve.as_str()], config.policy.len())
+    }
+}
+
+/// ClientParams. This struct holds the parameters that a client supports.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientParams {
+    /// curves: the names of the curves that the client was built with, in order of preference.
+    pub curves: Vec<String>,
+    /// max_catalog_size: the largest catalog that the client accepts.
+    pub max_catalog_size: usize,
+    /// bitsizes: the bit sizes of the range proofs that the client can make.
+    pub bitsizes: Vec<u32>,
+    /// versions: the versions of the protocol messages that the client speaks.
+    pub versions: Vec<u32>,
+}
+
+impl Default for ClientParams {
+    fn default() -> Self {
+        Self::new(SUPPORTED_CURVES)
+    }
+}
+
+impl ClientParams {
+    /// new. This function returns the parameters of a client that was built with `curves`, and
+    /// that supports the default catalog size, and every bit size and version of this library.
+    /// # Arguments
+    /// * `curves` - the names of the curves, in order of preference.
+    pub fn new(curves: &[&str]) -> Self {
+        Self {
+            curves: curves.iter().map(|curve| curve.to_string()).collect(),
+            max_catalog_size: DEFAULT_MAX_CATALOG_SIZE,
+            bitsizes: SUPPORTED_BITSIZES.to_vec(),
+            versions: SUPPORTED_VERSIONS.to_vec(),
+        }
+    }
+
+    /// from_ids. This function returns the parameters of a client that was built with the
+    /// curves `ids`, as `new` does for their names.
+    /// # Arguments
+    /// * `ids` - the ids of the curves, in order of preference.
+    pub fn from_ids(ids: &[CurveId]) -> Self {
+        let names: Vec<_> = ids.iter().map(CurveId::name).collect();
+        Self::new(&names)
+    }
+
+    /// negotiate. This function picks the parameters to run the protocols with: the first
+    /// curve of the client that the server also runs on, and the largest bit size and the
+    /// highest version that both support. This function fails if there is no such curve, bit
+    /// size or version, or if the catalog of the server is empty or too large.
+    /// # Arguments
+    /// * `server` - the parameters that the server advertised.
+    pub fn negotiate(&self, server: &ServerParams) -> Result<Params, ParamsError> {
+        let curve = self
+            .curves
+            .iter()
+            .find(|curve| server.curves.contains(curve))
+            .ok_or(ParamsError::NoCommonCurve)?;
+
+        let catalog_size = usize::try_from(server.catalog_size).unwrap_or(usize::MAX);
+        if catalog_size == 0 {
+            return Err(ParamsError::EmptyCatalog);
+        }
+        if catalog_size > self.max_catalog_size {
+            return Err(ParamsError::CatalogTooLarge {
+                len: server.catalog_size,
+                max: self.max_catalog_size,
+            });
+        }
+
+        let bitsize = self
+            .bitsizes
+            .iter()
+            .filter(|bitsize| server.bitsizes.contains(bitsize))
+            .max()
+            .ok_or(ParamsError::NoCommonBitsize)?;
+        let version = self
+            .versions
+            .iter()
+            .filter(|version| server.versions.contains(version))
+            .max()
+            .ok_or(ParamsError::NoCommonVersion)?;
+
+        Ok(Params {
+            curve: curve.clone(),
+            catalog_size,
+            bitsize: *bitsize,
+            version: *version,
+        })
+    }
+}
+
+/// Params. This struct holds the parameters that a client and a server agreed on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Params {
+    /// curve: the name of the curve.
+    pub curve: String,
+    /// catalog_size: the length of the spend state.
+    pub catalog_size: usize,
+    /// bitsize: the bit size of the range proofs.
+    pub bitsize: u32,
+    /// version: the version of the protocol messages.
+    pub version: u32,
+}
+
+impl Params {
+    /// curve_id. This function returns the id of the agreed curve, e.g. to dispatch the
+    /// protocols to its config with a `CurveRegistry`. This function fails if the curve is not
+    /// one of `CurveId::ALL`.
+    pub fn curve_id(&self) -> Result<CurveId, UnknownCurve> {
+        self.curve.parse()
+    }
+}
+
+/// ParamsError. This enum describes why the parameters of a server were rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParamsError {
+    /// NoCommonCurve: the server does not run on any curve of the client.
+    NoCommonCurve,
+    /// NoCommonBitsize: the server does not accept any bit size of the client.
+    NoCommonBitsize,
+    /// NoCommonVersion: the server does not speak any version of the client.
+    NoCommonVersion,
+    /// EmptyCatalog: the policy of the server has no actions.
+    EmptyCatalog,
+    /// CatalogTooLarge: the policy of the server has more actions than the client accepts.
+    CatalogTooLarge {
+        /// len: the number of actions of the policy.
+        len: u64,
+        /// max: the largest catalog that the client accepts.
+        max: usize,
+    },
+}
+
+impl fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamsError::NoCommonCurve => write!(f, "Boomerang params: no common curve"),
+            ParamsError::NoCommonBitsize => write!(f, "Boomerang params: no common bit size"),
+            ParamsError::NoCommonVersion => {
+                write!(f, "Boomerang params: no common protocol version")
+            }
+            ParamsError::EmptyCatalog => write!(f, "Boomerang params: empty catalog"),
+            ParamsError::CatalogTooLarge { len, max } => write!(
+                f,
+                "Boomerang params: catalog has {} actions, but at most {} are accepted",
+                len, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParamsError {}
@@ -0,0 +1,310 @@
+//! Defines where the policy vector of the Spend/Verify Protocol comes from.
+//!
+//! The policy vector gives the reward of each action that a token counts. Rather than passing a
+//! fixed vector to every spend, a server can plug its business logic in as a `PolicyProvider`,
+//! which is asked for the policy of a campaign in a given epoch, so that policies can change from
+//! one epoch to the next (see `SpendVerifyStateS::generate_spendverify_m3_with_policy`).
+//!
+//! A `PolicyCache` sits in front of a provider: it asks the provider once per (campaign, epoch),
+//! and signs the result with a `PolicyKey`, so that the server can publish the policy that it
+//! applies and clients can check it against the server's policy key.
+
+use ark_ec::{
+    models::CurveConfig,
+    short_weierstrass::{self as sw, SWCurveConfig},
+    AffineRepr, CurveGroup,
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::UniformRand;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::config::BoomerangConfig;
+use crate::encoding::{append_point, append_scalar};
+use crate::rng::BoomerangRng;
+use acl::config::ACLConfig;
+use pedersen::transcript::{append_u64, challenge_bytes};
+
+/// PolicyError. This enum describes why a provider did not return a policy, so that servers
+/// can report the reason for a refused spend.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyError {
+    /// NoPolicy: the provider has no policy for the campaign in the epoch.
+    NoPolicy,
+    /// Unavailable: the provider could not look the policy up, e.g. because its backend is
+    /// down. The reason is given by the provider.
+    Unavailable(&'static str),
+}
+
+impl PolicyError {
+    /// as_str. Returns the reason for the error.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyError::NoPolicy => "Boomerang policy: no policy for the campaign and epoch",
+            PolicyError::Unavailable(reason) => reason,
+        }
+    }
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+/// PolicyProvider. A source of the policy vector of the Spend/Verify Protocol.
+pub trait PolicyProvider<B: BoomerangConfig> {
+    /// policy_for. Returns the policy of `campaign_id` in `epoch`. This function fails with
+    /// `PolicyError::NoPolicy` if there is none.
+    /// # Arguments
+    /// * `campaign_id` - the campaign.
+    /// * `epoch` - the epoch.
+    /// * `rng` - the source of randomness, e.g. for a provider that signs its policies.
+    fn policy_for<T: BoomerangRng>(
+        &self,
+        campaign_id: u64,
+        epoch: u64,
+        rng: &mut T,
+    ) -> Result<Vec<<B as CurveConfig>::ScalarField>, PolicyError>;
+}
+
+/// StaticPolicy. A provider that returns the same policy for every campaign and epoch.
+#[derive(Clone)]
+pub struct StaticPolicy<B: BoomerangConfig>(pub Vec<<B as CurveConfig>::ScalarField>);
+
+impl<B: BoomerangConfig> PolicyProvider<B> for StaticPolicy<B> {
+    fn policy_for<T: BoomerangRng>(
+        &self,
+        _campaign_id: u64,
+        _epoch: u64,
+        _rng: &mut T,
+    ) -> Result<Vec<<B as CurveConfig>::ScalarField>, PolicyError> {
+        if self.0.is_empty() {
+            return Err(PolicyError::NoPolicy);
+        }
+        Ok(self.0.clone())
+    }
+}
+
+impl<B, F> PolicyProvider<B> for F
+where
+    B: BoomerangConfig,
+    F: Fn(u64, u64) -> Result<Vec<<B as CurveConfig>::ScalarField>, PolicyError>,
+{
+    fn policy_for<T: BoomerangRng>(
+        &self,
+        campaign_id: u64,
+        epoch: u64,
+        _rng: &mut T,
+    ) -> Result<Vec<<B as CurveConfig>::ScalarField>, PolicyError> {
+        self(campaign_id, epoch)
+    }
+}
+
+/// PolicyKey. The key that a server signs its policies with. This key is independent of the
+/// server's ACL keys.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PolicyKey<B: BoomerangConfig> {
+    /// signing_key: the private key.
+    signing_key: <B as CurveConfig>::ScalarField,
+    /// public_key: the public key.
+    pub public_key: sw::Affine<B>,
+}
+
+impl<B: BoomerangConfig> PolicyKey<B> {
+    /// generate. This function generates a new policy key.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    pub fn generate<T: BoomerangRng>(rng: &mut T) -> Self {
+        let signing_key = <B as CurveConfig>::ScalarField::rand(rng);
+        Self {
+            signing_key,
+            public_key: (<B as SWCurveConfig>::GENERATOR * signing_key).into_affine(),
+        }
+    }
+}
+
+/// SignedPolicy. This struct holds the policy of a campaign in an epoch, along with a Schnorr
+/// signature on it under a `PolicyKey`.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SignedPolicy<B: BoomerangConfig> {
+    /// campaign_id: the campaign.
+    pub campaign_id: u64,
+    /// epoch: the epoch.
+    pub epoch: u64,
+    /// policy: the policy vector.
+    pub policy: Vec<<B as CurveConfig>::ScalarField>,
+    /// commit: the commitment of the signature.
+    commit: sw::Affine<B>,
+    /// response: the response of the signature.
+    response: <B as CurveConfig>::ScalarField,
+}
+
+impl<B: BoomerangConfig> SignedPolicy<B> {
+    /// challenge. Returns the challenge of a signature on (`campaign_id`, `epoch`, `policy`).
+    fn challenge(
+        public_key: &sw::Affine<B>,
+        commit: &sw::Affine<B>,
+        campaign_id: u64,
+        epoch: u64,
+        policy: &[<B as CurveConfig>::ScalarField],
+    ) -> <B as CurveConfig>::ScalarField {
+        let mut transcript = B::CONTEXT.transcript(b"Boomerang signed policy");
+        append_point(&mut transcript, b"public_key", public_key);
+        append_point(&mut transcript, b"commit", commit);
+        append_u64(&mut transcript, b"campaign_id", campaign_id);
+        append_u64(&mut transcript, b"epoch", epoch);
+        append_u64(&mut transcript, b"len", policy.len() as u64);
+        for action in policy {
+            append_scalar(&mut transcript, b"action", action);
+        }
+
+        let mut buf = [0u8; 64];
+        challenge_bytes(&mut transcript, b"challenge", &mut buf);
+        <B as ACLConfig>::make_challenge_from_buffer(&buf)
+    }
+
+    /// sign. This function signs the policy of `campaign_id` in `epoch`.
+    /// # Arguments
+    /// * `key` - the policy key.
+    /// * `campaign_id` - the campaign.
+    /// * `epoch` - the epoch.
+    /// * `policy` - the policy vector.
+    /// * `rng` - the source of randomness.
+    pub fn sign<T: BoomerangRng>(
+        key: &PolicyKey<B>,
+        campaign_id: u64,
+        epoch: u64,
+        policy: Vec<<B as CurveConfig>::ScalarField>,
+        rng: &mut T,
+    ) -> Self {
+        let nonce = <B as CurveConfig>::ScalarField::rand(rng);
+        let commit = (<B as SWCurveConfig>::GENERATOR * nonce).into_affine();
+        let c = Self::challenge(&key.public_key, &commit, campaign_id, epoch, &policy);
+
+        Self {
+            campaign_id,
+            epoch,
+            policy,
+            commit,
+            response: nonce + c * key.signing_key,
+        }
+    }
+
+    /// verify. This function returns true if this policy was signed under `public_key`, and
+    /// false otherwise.
+    /// # Arguments
+    /// * `public_key` - the public key of the policy key.
+    pub fn verify(&self, public_key: &sw::Affine<B>) -> bool {
+        let c = Self::challenge(
+            public_key,
+            &self.commit,
+            self.campaign_id,
+            self.epoch,
+            &self.policy,
+        );
+        (<B as SWCurveConfig>::GENERATOR * self.response - *public_key * c - self.commit)
+            .into_affine()
+            .is_zero()
+    }
+}
+
+/// PolicyEntries. The signed policies of a cache, by campaign and epoch.
+type PolicyEntries<B> = HashMap<(u64, u64), Arc<SignedPolicy<B>>>;
+
+/// PolicyCache. This struct caches the signed policies of a provider, so that the provider is
+/// asked at most once for the policy of a campaign in an epoch.
+pub struct PolicyCache<B: BoomerangConfig, P: PolicyProvider<B>> {
+    /// provider: the source of the policies.
+    provider: P,
+    /// key: the key that the policies are signed with.
+    key: PolicyKey<B>,
+    /// entries: the signed policies, by campaign and epoch.
+    entries: Mutex<PolicyEntries<B>>,
+}
+
+impl<B: BoomerangConfig, P: PolicyProvider<B>> PolicyCache<B, P> {
+    /// new. This function returns a new, empty, cache in front of `provider`.
+    /// # Arguments
+    /// * `provider` - the source of the policies.
+    /// * `key` - the key that the policies are signed with.
+    pub fn new(provider: P, key: PolicyKey<B>) -> Self {
+        Self {
+            provider,
+            key,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// public_key. Returns the public key that the cached policies are signed under.
+    pub fn public_key(&self) -> &sw::Affine<B> {
+        &self.key.public_key
+    }
+
+    /// get. This function returns the signed policy of `campaign_id` in `epoch`, and asks the
+    /// provider for it if it is not cached yet. This function fails with the error of the
+    /// provider if it has no policy for `campaign_id` in `epoch`; the failure is not cached.
+    /// # Arguments
+    /// * `campaign_id` - the campaign.
+    /// * `epoch` - the epoch.
+    /// * `rng` - the source of randomness for the provider and the signature.
+    pub fn get<T: BoomerangRng>(
+        &self,
+        campaign_id: u64,
+        epoch: u64,
+        rng: &mut T,
+    ) -> Result<Arc<SignedPolicy<B>>, PolicyError> {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(policy) = entries.get(&(campaign_id, epoch)) {
+            return Ok(Arc::clone(policy));
+        }
+
+        let policy = self.provider.policy_for(campaign_id, epoch, rng)?;
+        if policy.is_empty() {
+            return Err(PolicyError::NoPolicy);
+        }
+
+        let signed = Arc::new(SignedPolicy::sign(
+            &self.key,
+            campaign_id,
+            epoch,
+            policy,
+            rng,
+        ));
+        entries.insert((campaign_id, epoch), Arc::clone(&signed));
+        Ok(signed)
+    }
+
+    /// evict_before. This function drops the cached policies of every epoch before `epoch`.
+    /// # Arguments
+    /// * `epoch` - the first epoch to keep.
+    pub fn evict_before(&self, epoch: u64) {
+        self.entries.lock().unwrap().retain(|&(_, e), _| e >= epoch);
+    }
+
+    /// len. Returns the number of cached policies.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// is_empty. Returns true if no policy is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<B: BoomerangConfig, P: PolicyProvider<B>> PolicyProvider<B> for PolicyCache<B, P> {
+    fn policy_for<T: BoomerangRng>(
+        &self,
+        campaign_id: u64,
+        epoch: u64,
+        rng: &mut T,
+    ) -> Result<Vec<<B as CurveConfig>::ScalarField>, PolicyError> {
+        self.get(campaign_id, epoch, rng)
+            .map(|signed| signed.policy.clone())
+    }
+}
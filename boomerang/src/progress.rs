@@ -0,0 +1,102 @@
+//! Defines the progress reports of the heavy client-side generators.
+//!
+//! The `_with_progress` variants of the client generators take an optional callback, which is
+//! called before every phase of the generation, and once more when the message is ready. The
+//! callback can return `ControlFlow::Break` to abort the generation, in which case the generator
+//! returns an error and leaves the client state untouched.
+//!
+//! The callback is never given any secret value, and it does not change the output: a generator
+//! that is given no callback draws the same randomness, and returns the same message, as one
+//! whose callback always continues.
+
+use std::ops::ControlFlow;
+
+/// ProofPhase. The phases of the client-side generators.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofPhase {
+    /// Commitments: the commitment to the new token.
+    Commitments,
+    /// OpeningProofs: the proofs of opening of the new and of the previous commitment.
+    OpeningProofs,
+    /// TagProof: the proof that the double-spending tag is well formed.
+    TagProof,
+    /// SubProof: the range proof on the spent value.
+    SubProof,
+    /// SignatureProof: the proof of the signature on the previous token.
+    SignatureProof,
+    /// RewardsCheck: the check of the server's rewards proof.
+    RewardsCheck,
+    /// Challenge: the blinded challenge for the signature on the new token.
+    Challenge,
+    /// Done: the message is ready.
+    Done,
+}
+
+/// Progress. This struct is the report that is passed to a progress callback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// phase: the phase that is about to start.
+    pub phase: ProofPhase,
+    /// step: the number of phases that have been completed.
+    pub step: usize,
+    /// steps: the number of phases of the generator.
+    pub steps: usize,
+}
+
+/// ProgressCallback. A callback that is told about the progress of a generator, and that can
+/// abort it by returning `ControlFlow::Break`.
+pub type ProgressCallback<'a> = &'a mut dyn FnMut(Progress) -> ControlFlow<()>;
+
+/// Reporter. This struct passes the phases of a generator to an optional callback.
+pub(crate) struct Reporter<'a> {
+    callback: Option<ProgressCallback<'a>>,
+    phases: &'static [ProofPhase],
+    step: usize,
+}
+
+impl<'a> Reporter<'a> {
+    /// new. This function returns a reporter for a generator that goes through `phases`.
+    /// # Arguments
+    /// * `callback` - the optional progress callback.
+    /// * `phases` - the phases of the generator, in order.
+    pub(crate) fn new(
+        callback: Option<ProgressCallback<'a>>,
+        phases: &'static [ProofPhase],
+    ) -> Self {
+        Self {
+            callback,
+            phases,
+            step: 0,
+        }
+    }
+
+    /// phase. This function reports that the next phase is about to start. This function fails
+    /// if the callback asks to abort.
+    /// # Arguments
+    /// * `phase` - the phase that is about to start.
+    pub(crate) fn phase(&mut self, phase: ProofPhase) -> Result<(), &'static str> {
+        debug_assert_eq!(self.phases.get(self.step), Some(&phase));
+        let progress = Progress {
+            phase,
+            step: self.step,
+            steps: self.phases.len(),
+        };
+        self.step += 1;
+
+        match self.callback.as_mut().map(|callback| callback(progress)) {
+            Some(ControlFlow::Break(())) => Err("Boomerang: generation aborted by the caller"),
+            _ => Ok(()),
+        }
+    }
+
+    /// done. This function reports that the message is ready.
+    pub(crate) fn done(&mut self) {
+        if let Some(callback) = self.callback.as_mut() {
+            let _ = callback(Progress {
+                phase: ProofPhase::Done,
+                step: self.phases.len(),
+                steps: self.phases.len(),
+            });
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Defines the source of randomness of the protocol functions.
+//!
+//! Every function that draws randomness takes a `BoomerangRng`. Any cryptographically secure
+//! generator is one, so production code passes `OsRng`, as before. Tests and fuzzers can pass a
+//! `SeededRng` instead, so that a run that fails can be replayed from its seed. As with the
+//! `testing` module, `SeededRng` is only built for tests or with the `testing` feature.
+//!
+//! With the `unsafe_deterministic_provers` feature, this module also provides an `AuditRng`,
+//! which derives all of the randomness of a prover from a seed and the witness, so that an
+//! auditor can reproduce a proof bit for bit.
+
+use rand::{CryptoRng, RngCore};
+
+#[cfg(any(test, feature = "testing", feature = "unsafe_deterministic_provers"))]
+use rand::SeedableRng;
+#[cfg(any(test, feature = "testing", feature = "unsafe_deterministic_provers"))]
+use rand_chacha::ChaCha20Rng;
+
+#[cfg(feature = "unsafe_deterministic_provers")]
+use merlin::{Transcript, TranscriptRng};
+
+/// SEED_VAR. The environment variable that `SeededRng::from_env` reads its seed from.
+#[cfg(any(test, feature = "testing"))]
+pub const SEED_VAR: &str = "BOOMERANG_SEED";
+
+/// BoomerangRng. A cryptographically secure source of randomness, as taken by the protocol
+/// functions.
+pub trait BoomerangRng: RngCore + CryptoRng {}
+
+impl<T: RngCore + CryptoRng + ?Sized> BoomerangRng for T {}
+
+/// SeededRng. This struct is a deterministic `BoomerangRng` (ChaCha20, seeded with a `u64`),
+/// for tests, benches and fuzzing.
+///
+/// N.B The seed is far too short to be secret: anyone who knows it knows every value that the
+/// generator produces, e.g. every blinding factor. It must never be used in production, which
+/// is why it sits behind the `testing` feature.
+#[cfg(any(test, feature = "testing"))]
+pub struct SeededRng {
+    /// seed: the seed of the generator.
+    seed: u64,
+    /// rng: the underlying generator.
+    rng: ChaCha20Rng,
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl SeededRng {
+    /// new. This function returns a new generator, seeded with `seed`.
+    /// # Arguments
+    /// * `seed` - the seed of the generator.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: ChaCha20Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// from_env. This function returns a new generator, seeded with the value of the
+    /// `BOOMERANG_SEED` environment variable, or with a random seed if it is not set. The seed
+    /// can be read back with `seed`, so that a failing test can report it and be replayed by
+    /// setting the variable to it. This function panics if the variable is set but is not a
+    /// `u64`.
+    pub fn from_env() -> Self {
+        let seed = match std::env::var(SEED_VAR) {
+            Ok(seed) => seed
+                .trim()
+                .parse()
+                .unwrap_or_else(|_| panic!("{} must be a u64, got {:?}", SEED_VAR, seed)),
+            Err(_) => rand::rngs::OsRng.next_u64(),
+        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!(seed, "seeded generator from {}", SEED_VAR);
+        Self::new(seed)
+    }
+
+    /// seed. Returns the seed of the generator.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl CryptoRng for SeededRng {}
+
+/// AUDIT_RNG_DOMAIN. The label of the transcript that `AuditRng` derives its randomness from.
+#[cfg(feature = "unsafe_deterministic_provers")]
+pub const AUDIT_RNG_DOMAIN: &[u8] = b"boomerang-audit-rng-v1";
+
+/// AuditRng. This struct is a deterministic `BoomerangRng`, which derives its output from a
+/// transcript over a caller-supplied seed, a label and the witness. Any prover of the
+/// pedersen, acl or bulletproofs crates that is given an `AuditRng` built from the same
+/// inputs draws the same randomness, and so makes the same proof, bit for bit.
+///
+/// N.B This is for audits and debugging only. Anyone who knows the seed and the witness knows
+/// every nonce and blinding factor of the proof, which breaks its zero-knowledge (and, for a
+/// signature, can reveal the signing key if the same seed is reused with another challenge).
+/// It must never be used in production, which is why it sits behind the
+/// `unsafe_deterministic_provers` feature.
+#[cfg(feature = "unsafe_deterministic_provers")]
+pub struct AuditRng {
+    /// rng: the PRF that the transcript was finalised into.
+    rng: TranscriptRng,
+}
+
+#[cfg(feature = "unsafe_deterministic_provers")]
+impl AuditRng {
+    /// new. This function returns a new generator, keyed with `seed`, `label` and every part
+    /// of `witness`, in order.
+    /// # Arguments
+    /// * `seed` - the seed that the proof is reproduced from.
+    /// * `label` - a label for the proof, e.g the name of the protocol.
+    /// * `witness` - the encoding of the secret inputs of the prover.
+    pub fn new(seed: &[u8; 32], label: &[u8], witness: &[&[u8]]) -> Self {
+        let mut transcript = Transcript::new(AUDIT_RNG_DOMAIN);
+        transcript.append_message(b"seed", seed);
+        transcript.append_message(b"label", label);
+
+        let builder = witness
+            .iter()
+            .fold(transcript.build_rng(), |builder, part| {
+                builder.rekey_with_witness_bytes(b"witness", part)
+            });
+
+        // The transcript mixes in 32 bytes from this generator as well, which we derive from
+        // the seed, so that the output only depends on the inputs above.
+        Self {
+            rng: builder.finalize(&mut ChaCha20Rng::from_seed(*seed)),
+        }
+    }
+}
+
+#[cfg(feature = "unsafe_deterministic_provers")]
+impl RngCore for AuditRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(feature = "unsafe_deterministic_provers")]
+impl CryptoRng for AuditRng {}
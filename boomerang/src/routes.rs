@@ -0,0 +1,158 @@
+//!
+//! Module containing the canonical HTTP routes of the protocol messages.
+//!
+//! Every route is named after the server message that it answers with, and accepts the client
+//! message that comes before it, e.g. a POST of an `IssuanceM1` to `ISSUANCE_M2` is answered
+//! with an `IssuanceM2`. The first message of a protocol that the server starts is fetched
+//! with a GET. The bodies are the compressed arkworks encoding of the messages.
+//!
+//! Servers and clients should both take their paths and methods from here, so that they cannot
+//! drift apart.
+//!
+
+use std::fmt;
+
+/// Method. The HTTP method that a route is requested with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Method {
+    /// Get: the route takes no body.
+    Get,
+    /// Post: the route takes the previous client message as its body.
+    Post,
+}
+
+impl Method {
+    /// as_str. Returns the name of the method, as in an HTTP request line.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Get => "GET",
+            Self::Post => "POST",
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Route. The path of an endpoint, and the method that it expects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Route {
+    /// path: the absolute path of the endpoint.
+    pub path: &'static str,
+    /// method: the method that the endpoint is requested with.
+    pub method: Method,
+}
+
+impl Route {
+    /// url. This function returns the URL of the route on the server at `base`.
+    /// # Arguments
+    /// * `base` - the scheme and authority of the server, e.g `https://127.0.0.1:3000`.
+    pub fn url(&self, base: &str) -> String {
+        format!("{}{}", base.trim_end_matches('/'), self.path)
+    }
+
+    /// find. This function returns the route with the given `method` and `path`, if any.
+    /// # Arguments
+    /// * `method` - the method of the request.
+    /// * `path` - the path of the request.
+    pub fn find(method: Method, path: &str) -> Option<Route> {
+        ALL.iter()
+            .copied()
+            .find(|r| r.method == method && r.path == path)
+    }
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.method, self.path)
+    }
+}
+
+const fn get(path: &'static str) -> Route {
+    Route {
+        path,
+        method: Method::Get,
+    }
+}
+
+const fn post(path: &'static str) -> Route {
+    Route {
+        path,
+        method: Method::Post,
+    }
+}
+
+/// PARAMS: answers with the `ServerParams` of the server, which the client negotiates the
+/// parameters of the protocols from before issuance.
+pub const PARAMS: Route = get("/params");
+
+/// ISSUANCE_M2: accepts an `IssuanceM1`, answers with an `IssuanceM2`.
+pub const ISSUANCE_M2: Route = post("/issuance/m2");
+/// ISSUANCE_M4: accepts an `IssuanceM3`, answers with an `IssuanceM4`.
+pub const ISSUANCE_M4: Route = post("/issuance/m4");
+
+/// COLLECTION_M1: answers with a `CollectionM1`.
+pub const COLLECTION_M1: Route = get("/collection/m1");
+/// COLLECTION_M3: accepts a `CollectionM2`, answers with a `CollectionM3`.
+pub const COLLECTION_M3: Route = post("/collection/m3");
+/// COLLECTION_M5: accepts a `CollectionM4`, answers with a `CollectionM5`.
+pub const COLLECTION_M5: Route = post("/collection/m5");
+
+/// UPDATE_M1: answers with an `UpdateM1`.
+pub const UPDATE_M1: Route = get("/update/m1");
+/// UPDATE_M3: accepts an `UpdateM2`, answers with an `UpdateM3`.
+pub const UPDATE_M3: Route = post("/update/m3");
+/// UPDATE_M5: accepts an `UpdateM4`, answers with an `UpdateM5`.
+pub const UPDATE_M5: Route = post("/update/m5");
+
+/// SPEND_VERIFY_M1: answers with a `SpendVerifyM1`.
+pub const SPEND_VERIFY_M1: Route = get("/spend-verify/m1");
+/// SPEND_VERIFY_M3: accepts a `SpendVerifyM2`, answers with a `SpendVerifyM3`.
+pub const SPEND_VERIFY_M3: Route = post("/spend-verify/m3");
+/// SPEND_VERIFY_M5: accepts a `SpendVerifyM4`, answers with a `SpendVerifyM5`.
+pub const SPEND_VERIFY_M5: Route = post("/spend-verify/m5");
+
+/// TRANSFER_M1: answers with a `TransferM1`.
+pub const TRANSFER_M1: Route = get("/transfer/m1");
+/// TRANSFER_M3: accepts a `TransferM2`, answers with a `TransferM3`.
+pub const TRANSFER_M3: Route = post("/transfer/m3");
+/// TRANSFER_M5: accepts a `TransferM4`, answers with a `TransferM5`.
+pub const TRANSFER_M5: Route = post("/transfer/m5");
+
+/// RECOVERY_REGISTER: accepts a `BackupRegistration`, answers with an empty body.
+pub const RECOVERY_REGISTER: Route = post("/recovery/register");
+/// RECOVERY_M3: accepts a `RecoveryM2`, answers with a `TransferM3`. The recovery otherwise
+/// takes the transfer routes.
+pub const RECOVERY_M3: Route = post("/recovery/m3");
+
+/// METRICS: answers with the metrics of the server, in the Prometheus text format.
+pub const METRICS: Route = get("/metrics");
+
+/// ROOT: answers with a greeting, e.g. as a health check.
+pub const ROOT: Route = get("/");
+
+/// ALL: every route of the protocol.
+pub const ALL: [Route; 19] = [
+    PARAMS,
+    ISSUANCE_M2,
+    ISSUANCE_M4,
+    COLLECTION_M1,
+    COLLECTION_M3,
+    COLLECTION_M5,
+    UPDATE_M1,
+    UPDATE_M3,
+    UPDATE_M5,
+    SPEND_VERIFY_M1,
+    SPEND_VERIFY_M3,
+    SPEND_VERIFY_M5,
+    TRANSFER_M1,
+    TRANSFER_M3,
+    TRANSFER_M5,
+    RECOVERY_REGISTER,
+    RECOVERY_M3,
+    METRICS,
+    ROOT,
+];
@@ -5,27 +5,61 @@
 use ark_ec::{
     models::CurveConfig,
     short_weierstrass::{self as sw},
+    AffineRepr, CurveGroup,
 };
-use rand::{CryptoRng, RngCore};
 
+use crate::batch::BatchVerifier;
 use crate::client::{
-    CollectionM2, CollectionM4, IssuanceM1, IssuanceM3, SpendVerifyM2, SpendVerifyM4,
+    BackupRegistration, CollectionM2, CollectionM4, IssuanceM1, IssuanceM3, RecoveryM2, SpendInput,
+    SpendVerifyM2, SpendVerifyM2Multi, SpendVerifyM4, TransferM2, TransferM4, UpdateM2, UpdateM4,
+};
+use crate::clock::{Clock, Epoch};
+use crate::config::{
+    committed_len, issuance_balance_comm, issuance_session_id, new_nonce, new_session_id,
+    revocation_key, session_transcript, split_value_generators, token_message, BoomerangConfig,
+    Nonce, RevocationKey, SessionId, BASE_ATTRIBUTES, TOKEN_CONTEXT,
 };
-use crate::config::BoomerangConfig;
 
 use acl::{
     config::KeyPair, verify::SigComm, verify::SigResp, verify::SigVerifProof, verify::SigVerify,
 };
-use merlin::Transcript;
-use pedersen::pedersen_config::PedersenComm;
-
+use pedersen::equality_protocol::EqualityProof;
+use pedersen::pedersen_config::{Generators, PedersenComm};
+use pedersen::transcript::{append_message, append_u64};
+
+use crate::credlog::{key_id, CredentialLog, CredentialRecord, KeyId};
+use crate::curves::CurveId;
+use crate::encoding::{encode_scalar, Endianness};
+use crate::merkle::{merkle_hash, ExclusionProof, MerkleDigest, MerklePath, MerkleRoot, MerkleSet};
+use crate::policy::{PolicyError, PolicyProvider};
+use crate::rng::BoomerangRng;
+use crate::spendlimit::{SpendLimit, SpendLimitProof, SpendLimitStatement};
+use crate::ticket::{ReplayGuard, SessionTicket, TicketKey};
+use crate::trace::{close_session, open_session, SpanTimer};
 use crate::utils::rewards::*;
 
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::{UniformRand, Zero};
+use serde::{Deserialize, Serialize};
 
 use std::default::Default;
 use std::fmt;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{
+    mpsc::{self, Receiver, SyncSender, TrySendError},
+    Arc, Condvar, Mutex,
+};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+
+/// The labels that bind each session ticket to the step it is issued for.
+const COLLECTION_M1_TICKET: &[u8] = b"BoomerangCollectionM1Ticket";
+const COLLECTION_M3_TICKET: &[u8] = b"BoomerangCollectionM3Ticket";
+const SPENDVERIFY_M1_TICKET: &[u8] = b"BoomerangSpendVerifyM1Ticket";
+const SPENDVERIFY_M3_TICKET: &[u8] = b"BoomerangSpendVerifyM3Ticket";
 
 /// Server keypair.
 ///
@@ -33,6 +67,8 @@ use std::fmt;
 pub struct ServerKeyPair<B: BoomerangConfig> {
     /// Public key
     pub s_key_pair: KeyPair<B>,
+    /// policy_salt: the secret salt of the ids of the policies that this keypair applies.
+    policy_salt: PolicySalt,
 }
 
 /// Server tag.
@@ -40,7 +76,6 @@ pub struct ServerKeyPair<B: BoomerangConfig> {
 #[derive(Clone)]
 #[allow(unused_variables)]
 struct ServerTag<B: BoomerangConfig> {
-    #[allow(dead_code)]
     tag: <B as CurveConfig>::ScalarField,
     #[allow(dead_code)]
     id_0: <B as CurveConfig>::ScalarField,
@@ -51,10 +86,16 @@ struct ServerTag<B: BoomerangConfig> {
 impl<B: BoomerangConfig> ServerKeyPair<B> {
     /// Generate a new server keypair
     #[inline]
-    pub fn generate<T: RngCore + CryptoRng>(rng: &mut T) -> Self {
+    pub fn generate<T: BoomerangRng>(rng: &mut T) -> Self {
+        debug_assert_eq!(B::validate(), Ok(()));
         let keys = KeyPair::generate(rng);
+        let mut policy_salt = [0u8; 32];
+        rng.fill_bytes(&mut policy_salt);
 
-        Self { s_key_pair: keys }
+        Self {
+            s_key_pair: keys,
+            policy_salt,
+        }
     }
 
     /// Server public key
@@ -66,6 +107,22 @@ impl<B: BoomerangConfig> ServerKeyPair<B> {
     pub const fn tag_key(&self) -> &sw::Affine<B> {
         &self.s_key_pair.tag_key
     }
+
+    /// The message signed on every token issued under this keypair.
+    pub fn token_message(&self) -> [u8; 64] {
+        token_message::<B>(self.public_key(), self.tag_key())
+    }
+
+    /// The id of this keypair, under which its tokens are logged.
+    pub fn key_id(&self) -> KeyId {
+        key_id(self)
+    }
+
+    /// The id of `policy_state` under this keypair, which the server publishes so that clients
+    /// and auditors can check which policy its rewards proofs are bound to (see `policy_id`).
+    pub fn policy_id(&self, policy_state: &[<B as CurveConfig>::ScalarField]) -> PolicyId {
+        policy_id::<B>(policy_state, &self.policy_salt)
+    }
 }
 
 impl<B: BoomerangConfig> fmt::Debug for ServerKeyPair<B> {
@@ -92,6 +149,12 @@ pub struct IssuanceM2<B: BoomerangConfig> {
     pub verifying_key: sw::Affine<B>,
     /// Tag public key
     pub tag_key: sw::Affine<B>,
+    /// nonce: the server's nonce for this session.
+    pub nonce: Nonce,
+    /// balance: the initial balance of the token.
+    pub balance: <B as CurveConfig>::ScalarField,
+    /// pi_balance: the proof that `comm` commits to `id_1` and `balance`.
+    pub pi_balance: EqualityProof<B>,
 }
 
 /// IssuanceM4. This struct acts as a container for the fourth message of
@@ -100,36 +163,73 @@ pub struct IssuanceM2<B: BoomerangConfig> {
 pub struct IssuanceM4<B: BoomerangConfig> {
     /// s: the signature response value.
     pub s: SigResp<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
 }
 
 #[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct IssuanceStateS<B: BoomerangConfig> {
     /// sig_commit: the first signature value.
     pub sig_commit: SigComm<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
 }
 
 impl<B: BoomerangConfig> Default for IssuanceStateS<B> {
     fn default() -> Self {
         Self {
             sig_commit: SigComm::<B>::default(), // Default value for `sig_commit`
+            session_id: SessionId::default(),
         }
     }
 }
 
 impl<B: BoomerangConfig> IssuanceStateS<B> {
-    /// generate_issuance_m2. This function generates the second message of the Issuance Protocol.
+    /// generate_issuance_m2. This function generates the second message of the Issuance Protocol,
+    /// for a token that starts with a balance of zero.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `key_pair` - the server keypair.
+    /// * `rng` - the source of randomness.
+    pub fn generate_issuance_m2<T: BoomerangRng>(
+        c_m: &IssuanceM1<B>,
+        key_pair: &ServerKeyPair<B>,
+        state: &mut IssuanceStateS<B>,
+        rng: &mut T,
+    ) -> IssuanceM2<B> {
+        Self::generate_issuance_m2_with_balance(
+            c_m,
+            key_pair,
+            state,
+            <B as CurveConfig>::ScalarField::zero(),
+            rng,
+        )
+    }
+
+    /// generate_issuance_m2_with_balance. This function generates the second message of the
+    /// Issuance Protocol, for a token that starts with `balance`, e.g. a promotional balance.
+    /// The message carries a proof that the server's share of the commitment holds exactly
+    /// `balance`, so that the client can check the balance of the token before it is signed.
     /// # Arguments
     /// * `c_m` - the received client message.
     /// * `key_pair` - the server keypair.
+    /// * `balance` - the initial balance of the token.
     /// * `rng` - the source of randomness.
-    pub fn generate_issuance_m2<T: RngCore + CryptoRng>(
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "issuance_m2", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_issuance_m2_with_balance<T: BoomerangRng>(
         c_m: &IssuanceM1<B>,
         key_pair: &ServerKeyPair<B>,
         state: &mut IssuanceStateS<B>,
+        balance: <B as CurveConfig>::ScalarField,
         rng: &mut T,
     ) -> IssuanceM2<B> {
+        let timer = SpanTimer::serve(c_m);
         let label = b"BoomerangM1";
-        let mut transcript = Transcript::new(label);
+        let mut transcript = B::CONTEXT.transcript(label);
+        append_message(&mut transcript, b"client_nonce", &c_m.nonce);
         let check = c_m.pi_issuance.verify(
             &mut transcript,
             &c_m.comm.comm,
@@ -139,18 +239,28 @@ impl<B: BoomerangConfig> IssuanceStateS<B> {
         );
 
         if !check {
-            panic!("Boomerang issuance: invalid proof");
+            panic!("{}", timer.reject("Boomerang issuance: invalid proof"));
         }
 
+        let nonce = new_nonce(rng);
         let id_1 = <B as CurveConfig>::ScalarField::rand(rng);
 
-        let v1 = <B as CurveConfig>::ScalarField::zero();
         let v2 = <B as CurveConfig>::ScalarField::zero();
         let v3 = <B as CurveConfig>::ScalarField::zero();
-        let vals: Vec<<B as CurveConfig>::ScalarField> = vec![id_1, v1, v2, v3];
+        let vals: Vec<<B as CurveConfig>::ScalarField> = vec![id_1, balance, v2, v3];
         let c1 = PedersenComm::new_multi_with_all_generators(&vals, rng, &c_m.gens);
         let c = c1 + c_m.comm;
 
+        let session_id = issuance_session_id(&c_m.nonce, &nonce);
+        let label1 = b"BoomerangIssuanceM2Balance";
+        let mut transcript1 = session_transcript::<B>(label1, &session_id);
+        let pi_balance = EqualityProof::create(
+            &mut transcript1,
+            rng,
+            &c1,
+            &issuance_balance_comm(id_1, balance, &c_m.gens),
+        );
+
         let sig_comm = SigComm::commit(&key_pair.s_key_pair, rng, c.comm);
         let m2 = IssuanceM2 {
             id_1,
@@ -158,11 +268,16 @@ impl<B: BoomerangConfig> IssuanceStateS<B> {
             sig_commit: sig_comm,
             verifying_key: key_pair.s_key_pair.verifying_key,
             tag_key: key_pair.s_key_pair.tag_key,
+            nonce,
+            balance,
+            pi_balance,
         };
 
         state.sig_commit = sig_comm;
+        state.session_id = session_id;
 
-        m2
+        open_session("issuance");
+        timer.finish(m2)
     }
 
     /// generate_issuance_m4. This function generates the fourth message of the Issuance Protocol.
@@ -170,14 +285,62 @@ impl<B: BoomerangConfig> IssuanceStateS<B> {
     /// * `c_m` - the client message.
     /// * `s_m` - the received server message.
     /// * `key_pair` - the server's keypair.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "issuance_m4", skip_all, fields(size, elapsed_us))
+    )]
     pub fn generate_issuance_m4(
         c_m: &IssuanceM3<B>,
         state: &mut IssuanceStateS<B>,
         key_pair: &ServerKeyPair<B>,
     ) -> IssuanceM4<B> {
+        let timer = SpanTimer::serve(c_m);
+        if c_m.session_id != state.session_id {
+            panic!(
+                "{}",
+                timer.reject("Boomerang issuance: session id mismatch")
+            );
+        }
+
         let sig_resp = SigResp::respond(&key_pair.s_key_pair, &state.sig_commit, &c_m.e);
 
-        IssuanceM4 { s: sig_resp }
+        close_session("issuance");
+        timer.finish(IssuanceM4 {
+            s: sig_resp,
+            session_id: state.session_id,
+        })
+    }
+
+    /// generate_issuance_m4_with_log. This function generates the fourth message of the
+    /// Issuance Protocol, as in `generate_issuance_m4`, once it has recorded the issued token in
+    /// `log`. This function fails, without signing, if the session ids do not match or if the
+    /// log could not store the record.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `state` - the server state.
+    /// * `key_pair` - the server's keypair.
+    /// * `log` - the log of the issued tokens.
+    /// * `epoch` - the current epoch.
+    /// * `issued_at` - the current time.
+    pub fn generate_issuance_m4_with_log(
+        c_m: &IssuanceM3<B>,
+        state: &mut IssuanceStateS<B>,
+        key_pair: &ServerKeyPair<B>,
+        log: &mut impl CredentialLog<B>,
+        epoch: u64,
+        issued_at: u64,
+    ) -> Result<IssuanceM4<B>, &'static str> {
+        if c_m.session_id != state.session_id {
+            return Err("Boomerang issuance: session id mismatch");
+        }
+
+        log.record(CredentialRecord {
+            key_id: key_pair.key_id(),
+            epoch,
+            issued_at,
+            commitment: state.sig_commit.comms,
+        })?;
+        Ok(Self::generate_issuance_m4(c_m, state, key_pair))
     }
 }
 
@@ -188,6 +351,8 @@ impl<B: BoomerangConfig> IssuanceStateS<B> {
 pub struct CollectionM1<B: BoomerangConfig> {
     /// r2: the random double-spending tag value.
     pub r2: <B as CurveConfig>::ScalarField,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
 }
 
 /// CollectionM3. This struct acts as a container for the thrid message of
@@ -206,6 +371,8 @@ pub struct CollectionM3<B: BoomerangConfig> {
     pub verifying_key: sw::Affine<B>,
     /// tag_key: the tag public key
     pub tag_key: sw::Affine<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
 }
 
 impl<B: BoomerangConfig> Clone for CollectionM3<B> {
@@ -217,6 +384,7 @@ impl<B: BoomerangConfig> Clone for CollectionM3<B> {
             val: self.val,
             verifying_key: self.verifying_key,
             tag_key: self.tag_key,
+            session_id: self.session_id,
         }
     }
 }
@@ -227,6 +395,8 @@ impl<B: BoomerangConfig> Clone for CollectionM3<B> {
 pub struct CollectionM5<B: BoomerangConfig> {
     /// s: the signature response value.
     pub s: SigResp<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
 }
 
 /// CollectionS. This struct represents the collection protocol for the server.
@@ -236,6 +406,8 @@ pub struct CollectionStateS<B: BoomerangConfig> {
     r2: <B as CurveConfig>::ScalarField,
     /// sig_commit: the first signature value.
     sig_commit: SigComm<B>,
+    /// session_id: the identifier of this session.
+    session_id: SessionId,
     /// db: the db of tags.
     db: Vec<ServerTag<B>>,
 }
@@ -245,6 +417,7 @@ impl<B: BoomerangConfig> Default for CollectionStateS<B> {
         Self {
             r2: <B as CurveConfig>::ScalarField::zero(),
             sig_commit: SigComm::<B>::default(),
+            session_id: SessionId::default(),
             db: Vec::new(),
         }
     }
@@ -253,86 +426,73 @@ impl<B: BoomerangConfig> Default for CollectionStateS<B> {
 impl<B: BoomerangConfig> CollectionStateS<B> {
     /// generate_collection_m1. This function generates the first message of
     /// the Collection Protocol.
-    pub fn generate_collection_m1<T: RngCore + CryptoRng>(
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "collection_m1", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_collection_m1<T: BoomerangRng>(
         rng: &mut T,
         col_state: &mut CollectionStateS<B>,
     ) -> CollectionM1<B> {
+        let timer = SpanTimer::start();
         let r2 = <B as CurveConfig>::ScalarField::rand(rng);
 
+        let session_id = new_session_id(rng);
+
         col_state.r2 = r2;
+        col_state.session_id = session_id;
 
-        CollectionM1 { r2 }
+        open_session("collection");
+        timer.finish(CollectionM1 { r2, session_id })
     }
 
     /// generate_collection_m3. This function generates the thrid message of
-    /// the Collection Protocol.
+    /// the Collection Protocol. This function fails if the client message is not part of this
+    /// session, or if it does not verify.
     /// # Arguments
     /// * `rng` - the source of randomness.
     /// * `c_m` - the received client message.
     /// * `s_m` - the server message.
     /// * `key_pair` - the server's keypair.
     /// * `v` - the value to add.
-    pub fn generate_collection_m3<T: RngCore + CryptoRng>(
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "collection_m3", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_collection_m3<T: BoomerangRng>(
         rng: &mut T,
         c_m: &CollectionM2<B>,
         col_state: &mut CollectionStateS<B>,
         key_pair: &ServerKeyPair<B>,
         v: <B as CurveConfig>::ScalarField,
-    ) -> CollectionM3<B> {
-        let check = SigVerify::verify(
-            key_pair.s_key_pair.verifying_key,
-            key_pair.s_key_pair.tag_key,
-            &c_m.sig,
-            "message",
-        );
-        if !check {
-            panic!("Boomerang collection: invalid signature");
-        }
-
-        let check2 = SigVerifProof::verify(
-            &c_m.s_proof,
-            key_pair.s_key_pair.tag_key,
-            &c_m.sig,
-            &c_m.prev_gens.generators,
-        );
-
-        if !check2 {
-            panic!("Boomerang collection: invalid proof sig");
-        }
-
-        let label = b"BoomerangCollectionM2O1";
-        let mut transcript = Transcript::new(label);
-        let check3 = c_m
-            .pi_1
-            .verify(&mut transcript, &c_m.comm.comm, 4, &c_m.gens);
-
-        if !check3 {
-            panic!("Boomerang collection: invalid proof opening 1");
+    ) -> Result<CollectionM3<B>, &'static str> {
+        let timer = SpanTimer::start();
+        if c_m.session_id != col_state.session_id {
+            return Err(timer.reject("Boomerang collection: session id mismatch"));
         }
 
-        let label1 = b"BoomerangCollectionM2O2";
-        let mut transcript1 = Transcript::new(label1);
-        let check4 = c_m
-            .pi_2
-            .verify(&mut transcript1, &c_m.prev_comm.comm, 4, &c_m.prev_gens);
-        if !check4 {
-            panic!("Boomerang collection: invalid proof opening 2");
-        }
+        Self::verify_collection_m2_with_rng(c_m, key_pair, rng)?;
 
-        let label2 = b"BoomerangCollectionM2AM2";
-        let mut transcript2 = Transcript::new(label2);
-        let check5 = c_m.pi_3.verify(
-            &mut transcript2,
-            &c_m.tag_commits[0].comm,
-            &c_m.tag_commits[1].comm,
-            &c_m.tag_commits[2].comm,
-            &c_m.tag_commits[3].comm,
-            &c_m.tag_commits[4].comm,
-        );
-        if !check5 {
-            panic!("Boomerang collection: invalid proof of tag");
-        }
+        let m3 = Self::respond_collection_m2(rng, c_m, col_state, key_pair, v);
+        Ok(timer.finish(m3))
+    }
 
+    /// respond_collection_m2. This function generates the third message of the Collection
+    /// Protocol once the second message has been checked, i.e it adds `v` to the client's
+    /// commitment and commits to the signature on the new token.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `c_m` - the received client message.
+    /// * `col_state` - the server state.
+    /// * `key_pair` - the server's keypair.
+    /// * `v` - the value to add.
+    fn respond_collection_m2<T: BoomerangRng>(
+        rng: &mut T,
+        c_m: &CollectionM2<B>,
+        col_state: &mut CollectionStateS<B>,
+        key_pair: &ServerKeyPair<B>,
+        v: <B as CurveConfig>::ScalarField,
+    ) -> CollectionM3<B> {
         // TODO: verify the membership proof
         let id_1 = <B as CurveConfig>::ScalarField::rand(rng);
         let v2 = <B as CurveConfig>::ScalarField::zero();
@@ -360,6 +520,7 @@ impl<B: BoomerangConfig> CollectionStateS<B> {
             sig_commit: sig_comm,
             verifying_key: key_pair.s_key_pair.verifying_key,
             tag_key: key_pair.s_key_pair.tag_key,
+            session_id: col_state.session_id,
         }
     }
 
@@ -369,134 +530,392 @@ impl<B: BoomerangConfig> CollectionStateS<B> {
     /// * `c_m` - the received client message.
     /// * `s_m` - the server message.
     /// * `key_pair` - the server's keypair.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "collection_m5", skip_all, fields(size, elapsed_us))
+    )]
     pub fn generate_collection_m5(
         c_m: &CollectionM4<B>,
         col_state: &mut CollectionStateS<B>,
         key_pair: &ServerKeyPair<B>,
     ) -> CollectionM5<B> {
+        let timer = SpanTimer::serve(c_m);
+        if c_m.session_id != col_state.session_id {
+            panic!(
+                "{}",
+                timer.reject("Boomerang collection: session id mismatch")
+            );
+        }
+
         let sig_resp = SigResp::respond(&key_pair.s_key_pair, &col_state.sig_commit, &c_m.e);
 
-        CollectionM5 { s: sig_resp }
+        close_session("collection");
+        timer.finish(CollectionM5 {
+            s: sig_resp,
+            session_id: col_state.session_id,
+        })
+    }
+
+    /// generate_collection_m1_stateless. This function generates the first message of
+    /// the Collection Protocol, alongside a ticket that holds the server's state for this session.
+    /// The ticket must be returned by the client alongside the second message.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `ticket_key` - the key that tickets are sealed with.
+    /// * `expiry` - the time after which the ticket is no longer accepted.
+    pub fn generate_collection_m1_stateless<T: BoomerangRng>(
+        rng: &mut T,
+        ticket_key: &TicketKey,
+        expiry: u64,
+    ) -> (CollectionM1<B>, SessionTicket) {
+        let mut col_state = Self::default();
+        let m1 = Self::generate_collection_m1(rng, &mut col_state);
+        let ticket = ticket_key.seal(
+            rng,
+            COLLECTION_M1_TICKET,
+            expiry,
+            &(col_state.r2, col_state.session_id),
+        );
+        (m1, ticket)
+    }
+
+    /// verify_collection_m2. This is a convenience wrapper around `verify_collection_m2_with_rng`
+    /// that uses the thread RNG.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `key_pair` - the server's keypair.
+    pub fn verify_collection_m2(
+        c_m: &CollectionM2<B>,
+        key_pair: &ServerKeyPair<B>,
+    ) -> Result<(), &'static str> {
+        Self::verify_collection_m2_with_rng(c_m, key_pair, &mut rand::thread_rng())
+    }
+
+    /// verify_collection_m2_with_rng. This function checks the second message of the Collection
+    /// Protocol, i.e the client's signature, the proof of the signed commitment, the openings
+    /// of both commitments and the proof of the tag. The openings are checked together, with a
+    /// single MSM, so a failure of either is reported as one error. The proof of the signed
+    /// commitment is checked on its own: it only holds its challenges and responses, so its
+    /// commitments must be recomputed and hashed, which cannot be batched. This function returns
+    /// the reason for the first failed check, if any.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `key_pair` - the server's keypair.
+    /// * `rng` - the RNG that is used to weight the batched checks.
+    pub fn verify_collection_m2_with_rng<T: BoomerangRng>(
+        c_m: &CollectionM2<B>,
+        key_pair: &ServerKeyPair<B>,
+        rng: &mut T,
+    ) -> Result<(), &'static str> {
+        let timer = SpanTimer::serve(c_m);
+        let check = SigVerify::verify(
+            key_pair.s_key_pair.verifying_key,
+            key_pair.s_key_pair.tag_key,
+            &c_m.sig,
+            &key_pair.token_message(),
+            Some(TOKEN_CONTEXT),
+        );
+        if !check {
+            return Err(timer.reject("Boomerang collection: invalid signature"));
+        }
+
+        // The new commitment must keep the layout of the signed one.
+        let len = committed_len(&c_m.prev_gens);
+        if len < BASE_ATTRIBUTES || committed_len(&c_m.gens) != len {
+            return Err(timer.reject("Boomerang collection: invalid proof opening 1"));
+        }
+
+        let check = SigVerifProof::verify(
+            &c_m.s_proof,
+            key_pair.s_key_pair.tag_key,
+            &c_m.sig,
+            &c_m.prev_gens.generators,
+            Some(&c_m.session_id),
+        );
+        if !check {
+            return Err(timer.reject("Boomerang collection: invalid proof sig"));
+        }
+
+        let mut batch = BatchVerifier::<B>::new();
+
+        let label = b"BoomerangCollectionM2O1";
+        let mut transcript = session_transcript::<B>(label, &c_m.session_id);
+        let equation = c_m
+            .pi_1
+            .verification_equation(&mut transcript, &c_m.comm.comm, len, &c_m.gens)
+            .ok_or_else(|| timer.reject("Boomerang collection: invalid proof opening 1"))?;
+        batch.add(rng, equation);
+
+        let label1 = b"BoomerangCollectionM2O2";
+        let mut transcript1 = session_transcript::<B>(label1, &c_m.session_id);
+        let equation = c_m
+            .pi_2
+            .verification_equation(&mut transcript1, &c_m.prev_comm.comm, len, &c_m.prev_gens)
+            .ok_or_else(|| timer.reject("Boomerang collection: invalid proof opening 2"))?;
+        batch.add(rng, equation);
+
+        if !batch.verify() {
+            return Err(timer.reject("Boomerang collection: invalid proof opening"));
+        }
+
+        let label2 = b"BoomerangCollectionM2AM2";
+        let mut transcript2 = session_transcript::<B>(label2, &c_m.session_id);
+        let check5 = c_m.pi_3.verify(
+            &mut transcript2,
+            &c_m.tag_commits[0].comm,
+            &c_m.tag_commits[1].comm,
+            &c_m.tag_commits[2].comm,
+            &c_m.tag_commits[3].comm,
+            &c_m.tag_commits[4].comm,
+        );
+        if !check5 {
+            return Err(timer.reject("Boomerang collection: invalid proof of tag"));
+        }
+
+        timer.record(c_m);
+        Ok(())
+    }
+
+    /// generate_collection_m3_stateless. This function generates the third message of the
+    /// Collection Protocol from the ticket that was issued with the first message, alongside a
+    /// new ticket that must be returned by the client alongside the fourth message.
+    /// This function fails if the second message is invalid, if `ticket` cannot be opened, if
+    /// `guard` has already accepted it, or if it was issued for another session.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `c_m` - the received client message.
+    /// * `ticket` - the ticket that was returned by the client.
+    /// * `ticket_key` - the key that tickets are sealed with.
+    /// * `guard` - the ids of the tickets that were already accepted.
+    /// * `now` - the current time.
+    /// * `expiry` - the time after which the new ticket is no longer accepted.
+    /// * `key_pair` - the server's keypair.
+    /// * `v` - the value to add.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_collection_m3_stateless<T: BoomerangRng, G: ReplayGuard>(
+        rng: &mut T,
+        c_m: &CollectionM2<B>,
+        ticket: &SessionTicket,
+        ticket_key: &TicketKey,
+        guard: &mut G,
+        now: u64,
+        expiry: u64,
+        key_pair: &ServerKeyPair<B>,
+        v: <B as CurveConfig>::ScalarField,
+    ) -> Result<(CollectionM3<B>, SessionTicket), &'static str> {
+        // The message is checked before the ticket is spent, so that an invalid message does
+        // not use up the client's ticket.
+        Self::verify_collection_m2_with_rng(c_m, key_pair, rng)?;
+
+        let (r2, session_id) = ticket_key.open_once(COLLECTION_M1_TICKET, now, ticket, guard)?;
+        if c_m.session_id != session_id {
+            return Err("Boomerang collection: session id mismatch");
+        }
+
+        let mut col_state = Self {
+            r2,
+            session_id,
+            ..Self::default()
+        };
+        let m3 = Self::respond_collection_m2(rng, c_m, &mut col_state, key_pair, v);
+        let ticket = ticket_key.seal(
+            rng,
+            COLLECTION_M3_TICKET,
+            expiry,
+            &(col_state.sig_commit, col_state.session_id),
+        );
+        Ok((m3, ticket))
+    }
+
+    /// generate_collection_m5_stateless. This function generates the fifth message of the
+    /// Collection Protocol from the ticket that was issued with the third message.
+    /// This function fails if `ticket` cannot be opened, or if `guard` has already accepted it:
+    /// answering the same signature commitment twice leaks the server's signing key.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `ticket` - the ticket that was returned by the client.
+    /// * `ticket_key` - the key that tickets are sealed with.
+    /// * `guard` - the ids of the tickets that were already accepted.
+    /// * `now` - the current time.
+    /// * `key_pair` - the server's keypair.
+    pub fn generate_collection_m5_stateless<G: ReplayGuard>(
+        c_m: &CollectionM4<B>,
+        ticket: &SessionTicket,
+        ticket_key: &TicketKey,
+        guard: &mut G,
+        now: u64,
+        key_pair: &ServerKeyPair<B>,
+    ) -> Result<CollectionM5<B>, &'static str> {
+        let (sig_commit, session_id) =
+            ticket_key.open_once(COLLECTION_M3_TICKET, now, ticket, guard)?;
+        let mut col_state = Self {
+            sig_commit,
+            session_id,
+            ..Self::default()
+        };
+        Ok(Self::generate_collection_m5(c_m, &mut col_state, key_pair))
     }
 }
 
-/// Spending/Verification protocol
-/// SpendVerifyM1. This struct acts as a container for the first message of
-/// the spendverify protocol.
-#[derive(CanonicalSerialize, CanonicalDeserialize)]
-pub struct SpendVerifyM1<B: BoomerangConfig> {
+/// Update protocol
+/// UpdateM1. This struct acts as a container for the first message of
+/// the update protocol.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct UpdateM1<B: BoomerangConfig> {
     /// r2: the random double-spending tag value.
     pub r2: <B as CurveConfig>::ScalarField,
+    /// attr: the index of the attribute that is updated.
+    pub attr: u64,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
 }
 
-impl<B: BoomerangConfig> Clone for SpendVerifyM1<B> {
-    fn clone(&self) -> Self {
-        Self { r2: self.r2 }
-    }
-}
-
-/// SpendVerifyM3. This struct acts as a container for the third message of
-/// the spendverify protocol.
+/// UpdateM3. This struct acts as a container for the third message of
+/// the update protocol.
 #[derive(CanonicalSerialize, CanonicalDeserialize)]
-pub struct SpendVerifyM3<B: BoomerangConfig> {
+pub struct UpdateM3<B: BoomerangConfig> {
     /// comm: the commitment value.
     pub comm: PedersenComm<B>,
     /// sig_commit: the first signature value.
     pub sig_commit: SigComm<B>,
-    /// Serial Number
+    /// id_1: serial Number
     pub id_1: <B as CurveConfig>::ScalarField,
-    /// Val: the value to be added
-    pub val: <B as CurveConfig>::ScalarField,
-    /// Public key
+    /// delta: the value that is added to the updated attribute.
+    pub delta: <B as CurveConfig>::ScalarField,
+    /// verifying_key: the public verifying key
     pub verifying_key: sw::Affine<B>,
-    /// Tag public key
+    /// tag_key: the tag public key
     pub tag_key: sw::Affine<B>,
-    /// Rewards proof
-    pub pi_reward: BRewardsProof<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
 }
 
-impl<B: BoomerangConfig> Clone for SpendVerifyM3<B> {
+impl<B: BoomerangConfig> Clone for UpdateM3<B> {
     fn clone(&self) -> Self {
-        Self {
+        UpdateM3 {
             comm: self.comm,
             sig_commit: self.sig_commit,
             id_1: self.id_1,
-            val: self.val,
+            delta: self.delta,
             verifying_key: self.verifying_key,
             tag_key: self.tag_key,
-            pi_reward: self.pi_reward.clone(),
+            session_id: self.session_id,
         }
     }
 }
 
-/// SpendVerifyM5. This struct acts as a container for the fifth message of
-/// the spendverify protocol.
-#[derive(CanonicalSerialize, CanonicalDeserialize)]
-pub struct SpendVerifyM5<B: BoomerangConfig> {
+/// UpdateM5. This struct acts as a container for the fifth message of
+/// the update protocol.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct UpdateM5<B: BoomerangConfig> {
     /// s: the signature response value.
     pub s: SigResp<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
 }
 
-impl<B: BoomerangConfig> Clone for SpendVerifyM5<B> {
-    fn clone(&self) -> Self {
-        Self { s: self.s }
-    }
-}
-
-/// SpendVerifyS. This struct represents the spendverify protocol for the server.
-#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
-pub struct SpendVerifyStateS<B: BoomerangConfig> {
+/// UpdateStateS. This struct represents the update protocol for the server.
+/// The update protocol adds a public `delta` to a single attribute of an existing token, and
+/// signs the updated token. The attribute is either the value of the token, or one of its extra
+/// attributes (e.g. a tier level). Updating the attribute just past the last one adds a new
+/// attribute to the token, which starts at zero.
+#[derive(Clone)]
+pub struct UpdateStateS<B: BoomerangConfig> {
     /// r2: the random double-spending tag value.
     r2: <B as CurveConfig>::ScalarField,
+    /// attr: the index of the attribute that is updated.
+    attr: usize,
     /// sig_commit: the first signature value.
     sig_commit: SigComm<B>,
+    /// session_id: the identifier of this session.
+    session_id: SessionId,
+    /// db: the db of tags.
+    db: Vec<ServerTag<B>>,
 }
 
-impl<B: BoomerangConfig> Default for SpendVerifyStateS<B> {
+impl<B: BoomerangConfig> Default for UpdateStateS<B> {
     fn default() -> Self {
         Self {
             r2: <B as CurveConfig>::ScalarField::zero(),
+            attr: 0,
             sig_commit: SigComm::<B>::default(),
+            session_id: SessionId::default(),
+            db: Vec::new(),
         }
     }
 }
 
-impl<B: BoomerangConfig> SpendVerifyStateS<B> {
-    /// generate_spendverify_m1. This function generates the first message of
-    /// the SpendVerify Protocol.
-    pub fn generate_spendverify_m1<T: RngCore + CryptoRng>(
+impl<B: BoomerangConfig> UpdateStateS<B> {
+    /// generate_update_m1. This function generates the first message of
+    /// the Update Protocol. This function panics if `attr` is the serial number, the secret key
+    /// or the random value of the token, as these attributes cannot be updated.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `up_state` - the server state.
+    /// * `attr` - the index of the attribute to update.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "update_m1", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_update_m1<T: BoomerangRng>(
         rng: &mut T,
-        s_state: &mut SpendVerifyStateS<B>,
-    ) -> SpendVerifyM1<B> {
-        let r2 = <B as CurveConfig>::ScalarField::rand(rng);
+        up_state: &mut UpdateStateS<B>,
+        attr: usize,
+    ) -> UpdateM1<B> {
+        let timer = SpanTimer::start();
+        if attr != 1 && attr < BASE_ATTRIBUTES {
+            panic!("Boomerang update: attribute cannot be updated");
+        }
 
-        s_state.r2 = r2;
-        SpendVerifyM1 { r2 }
+        let r2 = <B as CurveConfig>::ScalarField::rand(rng);
+        let session_id = new_session_id(rng);
+
+        up_state.r2 = r2;
+        up_state.attr = attr;
+        up_state.session_id = session_id;
+
+        open_session("update");
+        timer.finish(UpdateM1 {
+            r2,
+            attr: attr as u64,
+            session_id,
+        })
     }
 
-    /// generate_spendverify_m3. This function generates the thrid message of
-    /// the Spend/Verify Protocol.
+    /// generate_update_m3. This function generates the third message of
+    /// the Update Protocol. This function fails if the client message is not part of this
+    /// session, or if it does not verify.
     /// # Arguments
     /// * `rng` - the source of randomness.
     /// * `c_m` - the received client message.
-    /// * `s_m` - the server message.
+    /// * `up_state` - the server state.
     /// * `key_pair` - the server's keypair.
-    /// * `v` - the value to be spent.
-    pub fn generate_spendverify_m3<T: RngCore + CryptoRng>(
+    /// * `delta` - the value to add to the attribute.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "update_m3", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_update_m3<T: BoomerangRng>(
         rng: &mut T,
-        c_m: &SpendVerifyM2<B>,
-        s_state: &mut SpendVerifyStateS<B>,
+        c_m: &UpdateM2<B>,
+        up_state: &mut UpdateStateS<B>,
         key_pair: &ServerKeyPair<B>,
-        policy_state: Vec<<B as CurveConfig>::ScalarField>,
-    ) -> SpendVerifyM3<B> {
+        delta: <B as CurveConfig>::ScalarField,
+    ) -> Result<UpdateM3<B>, &'static str> {
+        let timer = SpanTimer::serve(c_m);
+        if c_m.session_id != up_state.session_id {
+            return Err(timer.reject("Boomerang update: session id mismatch"));
+        }
+
         let check = SigVerify::verify(
             key_pair.s_key_pair.verifying_key,
             key_pair.s_key_pair.tag_key,
             &c_m.sig,
-            "message",
+            &key_pair.token_message(),
+            Some(TOKEN_CONTEXT),
         );
         if !check {
-            panic!("Boomerang spend-verify: invalid signature");
+            return Err(timer.reject("Boomerang update: invalid signature"));
         }
 
         let check2 = SigVerifProof::verify(
@@ -504,33 +923,47 @@ impl<B: BoomerangConfig> SpendVerifyStateS<B> {
             key_pair.s_key_pair.tag_key,
             &c_m.sig,
             &c_m.prev_gens.generators,
+            Some(&c_m.session_id),
         );
         if !check2 {
-            panic!("Boomerang spend-verify: invalid proof sig");
+            return Err(timer.reject("Boomerang update: invalid proof sig"));
         }
 
-        let label = b"BoomerangSpendVerifyM2O1";
-        let mut transcript = Transcript::new(label);
-        let check3 = c_m
-            .pi_1
-            .verify(&mut transcript, &c_m.comm.comm, 4, &c_m.gens);
-
+        // The new commitment keeps the layout of the signed one, and gains a new
+        // attribute if the updated attribute is just past the last one.
+        let prev_len = committed_len(&c_m.prev_gens);
+        let len = committed_len(&c_m.gens);
+        let check3 = prev_len >= BASE_ATTRIBUTES
+            && len == prev_len.max(up_state.attr + 1)
+            && len <= prev_len + 1;
         if !check3 {
-            panic!("Boomerang spend-verify: invalid proof opening 1");
+            return Err(timer.reject("Boomerang update: invalid attributes"));
         }
 
-        let label1 = b"BoomerangSpendVerifyM2O2";
-        let mut transcript1 = Transcript::new(label1);
+        let label = b"BoomerangUpdateM2O1";
+        let mut transcript = session_transcript::<B>(label, &c_m.session_id);
         let check4 = c_m
-            .pi_2
-            .verify(&mut transcript1, &c_m.prev_comm.comm, 4, &c_m.prev_gens);
+            .pi_1
+            .verify(&mut transcript, &c_m.comm.comm, len, &c_m.gens);
         if !check4 {
-            panic!("Boomerang spend-verify: invalid proof opening 2");
+            return Err(timer.reject("Boomerang update: invalid proof opening 1"));
         }
 
-        let label2 = b"BoomerangSpendVerifyM2AM2";
-        let mut transcript2 = Transcript::new(label2);
-        let check5 = c_m.pi_3.verify(
+        let label1 = b"BoomerangUpdateM2O2";
+        let mut transcript1 = session_transcript::<B>(label1, &c_m.session_id);
+        let check5 = c_m.pi_2.verify(
+            &mut transcript1,
+            &c_m.prev_comm.comm,
+            prev_len,
+            &c_m.prev_gens,
+        );
+        if !check5 {
+            return Err(timer.reject("Boomerang update: invalid proof opening 2"));
+        }
+
+        let label2 = b"BoomerangUpdateM2AM2";
+        let mut transcript2 = session_transcript::<B>(label2, &c_m.session_id);
+        let check6 = c_m.pi_3.verify(
             &mut transcript2,
             &c_m.tag_commits[0].comm,
             &c_m.tag_commits[1].comm,
@@ -538,72 +971,2184 @@ impl<B: BoomerangConfig> SpendVerifyStateS<B> {
             &c_m.tag_commits[3].comm,
             &c_m.tag_commits[4].comm,
         );
-        if !check5 {
-            panic!("Boomerang spend-verify: invalid proof of tag");
+        if !check6 {
+            return Err(timer.reject("Boomerang update: invalid proof of tag"));
+        }
+
+        let id_1 = <B as CurveConfig>::ScalarField::rand(rng);
+        let mut vals = vec![<B as CurveConfig>::ScalarField::zero(); len];
+        vals[0] = id_1;
+        vals[up_state.attr] = delta;
+
+        let c1 = PedersenComm::new_multi_with_all_generators(&vals, rng, &c_m.gens);
+        let c = c1 + c_m.comm;
+
+        let sig_comm = SigComm::commit(&key_pair.s_key_pair, rng, c.comm);
+
+        let dtag: ServerTag<B> = ServerTag {
+            tag: c_m.tag,
+            id_0: c_m.id,
+            r2: up_state.r2,
+        };
+
+        up_state.sig_commit = sig_comm;
+        up_state.db.push(dtag);
+
+        Ok(timer.finish(UpdateM3 {
+            id_1,
+            delta,
+            comm: c1,
+            sig_commit: sig_comm,
+            verifying_key: key_pair.s_key_pair.verifying_key,
+            tag_key: key_pair.s_key_pair.tag_key,
+            session_id: up_state.session_id,
+        }))
+    }
+
+    /// generate_update_m5. This function generates the fifth message of
+    /// the Update Protocol.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `up_state` - the server state.
+    /// * `key_pair` - the server's keypair.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "update_m5", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_update_m5(
+        c_m: &UpdateM4<B>,
+        up_state: &mut UpdateStateS<B>,
+        key_pair: &ServerKeyPair<B>,
+    ) -> UpdateM5<B> {
+        let timer = SpanTimer::serve(c_m);
+        if c_m.session_id != up_state.session_id {
+            panic!("{}", timer.reject("Boomerang update: session id mismatch"));
+        }
+
+        let sig_resp = SigResp::respond(&key_pair.s_key_pair, &up_state.sig_commit, &c_m.e);
+
+        close_session("update");
+        timer.finish(UpdateM5 {
+            s: sig_resp,
+            session_id: up_state.session_id,
+        })
+    }
+}
+
+/// Spending/Verification protocol
+/// SpendVerifyM1. This struct acts as a container for the first message of
+/// the spendverify protocol.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct SpendVerifyM1<B: BoomerangConfig> {
+    /// r2: the random double-spending tag value.
+    pub r2: <B as CurveConfig>::ScalarField,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
+}
+
+impl<B: BoomerangConfig> Clone for SpendVerifyM1<B> {
+    fn clone(&self) -> Self {
+        Self {
+            r2: self.r2,
+            session_id: self.session_id,
+        }
+    }
+}
+
+/// SpendVerifyM3. This struct acts as a container for the third message of
+/// the spendverify protocol.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct SpendVerifyM3<B: BoomerangConfig> {
+    /// comm: the commitment value.
+    pub comm: PedersenComm<B>,
+    /// sig_commit: the first signature value.
+    pub sig_commit: SigComm<B>,
+    /// Serial Number
+    pub id_1: <B as CurveConfig>::ScalarField,
+    /// Val: the value to be added
+    pub val: <B as CurveConfig>::ScalarField,
+    /// Public key
+    pub verifying_key: sw::Affine<B>,
+    /// Tag public key
+    pub tag_key: sw::Affine<B>,
+    /// Rewards proof
+    pub pi_reward: BRewardsProof<B>,
+    /// reward_opening: the opening of the reward commitments of `pi_reward`, so that a client
+    /// that knows the policy can check the reward (see
+    /// `SpendVerifyStateC::generate_spendverify_m4_with_policy`).
+    pub reward_opening: RewardOpening<B>,
+    /// policy_id: the id of the policy that `pi_reward` is bound to (see
+    /// `ServerKeyPair::policy_id`), which the client checks against the id that the server
+    /// published, and which can be logged to record which policy was applied.
+    pub policy_id: PolicyId,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
+}
+
+impl<B: BoomerangConfig> Clone for SpendVerifyM3<B> {
+    fn clone(&self) -> Self {
+        Self {
+            comm: self.comm,
+            sig_commit: self.sig_commit,
+            id_1: self.id_1,
+            val: self.val,
+            verifying_key: self.verifying_key,
+            tag_key: self.tag_key,
+            pi_reward: self.pi_reward.clone(),
+            reward_opening: self.reward_opening,
+            policy_id: self.policy_id,
+            session_id: self.session_id,
+        }
+    }
+}
+
+impl<B: BoomerangConfig> SpendVerifyM3<B> {
+    /// ack. Returns the parts of the message that do not concern the reward, i.e the
+    /// acknowledgement that the server would have sent on the fast path.
+    pub fn ack(&self) -> SpendVerifyAck<B> {
+        SpendVerifyAck {
+            comm: self.comm,
+            sig_commit: self.sig_commit,
+            id_1: self.id_1,
+            val: self.val,
+            verifying_key: self.verifying_key,
+            tag_key: self.tag_key,
+            session_id: self.session_id,
+        }
+    }
+}
+
+/// SpendVerifyAck. This struct acts as a container for the third message of the fast path of
+/// the spendverify protocol, which the server sends instead of a `SpendVerifyM3` when no reward
+/// is due. It commits to the signature on the new token, as a `SpendVerifyM3` does, but
+/// carries no rewards proof.
+///
+/// N.B The new token is blindly signed, so the fast path still takes the fourth and fifth
+/// messages: only the rewards proof is skipped.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct SpendVerifyAck<B: BoomerangConfig> {
+    /// comm: the commitment value.
+    pub comm: PedersenComm<B>,
+    /// sig_commit: the first signature value.
+    pub sig_commit: SigComm<B>,
+    /// Serial Number
+    pub id_1: <B as CurveConfig>::ScalarField,
+    /// Val: the value to be added
+    pub val: <B as CurveConfig>::ScalarField,
+    /// Public key
+    pub verifying_key: sw::Affine<B>,
+    /// Tag public key
+    pub tag_key: sw::Affine<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
+}
+
+impl<B: BoomerangConfig> Clone for SpendVerifyAck<B> {
+    fn clone(&self) -> Self {
+        Self {
+            comm: self.comm,
+            sig_commit: self.sig_commit,
+            id_1: self.id_1,
+            val: self.val,
+            verifying_key: self.verifying_key,
+            tag_key: self.tag_key,
+            session_id: self.session_id,
+        }
+    }
+}
+
+/// SpendVerifyReply. The third message of the spendverify protocol, as selected by
+/// `SpendVerifyStateS::generate_spendverify_reply`.
+pub enum SpendVerifyReply<B: BoomerangConfig> {
+    /// Full: a reward is due, and is proven.
+    Full(SpendVerifyM3<B>),
+    /// Fast: no reward is due, so the rewards proof is skipped.
+    Fast(SpendVerifyAck<B>),
+}
+
+/// SpendVerifyM5. This struct acts as a container for the fifth message of
+/// the spendverify protocol.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct SpendVerifyM5<B: BoomerangConfig> {
+    /// s: the signature response value.
+    pub s: SigResp<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
+}
+
+impl<B: BoomerangConfig> Clone for SpendVerifyM5<B> {
+    fn clone(&self) -> Self {
+        Self {
+            s: self.s,
+            session_id: self.session_id,
+        }
+    }
+}
+
+/// SpendVerifyS. This struct represents the spendverify protocol for the server.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SpendVerifyStateS<B: BoomerangConfig> {
+    /// r2: the random double-spending tag value.
+    r2: <B as CurveConfig>::ScalarField,
+    /// sig_commit: the first signature value.
+    sig_commit: SigComm<B>,
+    /// session_id: the identifier of this session.
+    session_id: SessionId,
+}
+
+impl<B: BoomerangConfig> Default for SpendVerifyStateS<B> {
+    fn default() -> Self {
+        Self {
+            r2: <B as CurveConfig>::ScalarField::zero(),
+            sig_commit: SigComm::<B>::default(),
+            session_id: SessionId::default(),
+        }
+    }
+}
+
+impl<B: BoomerangConfig> SpendVerifyStateS<B> {
+    /// generate_spendverify_m1. This function generates the first message of
+    /// the SpendVerify Protocol.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "spend_m1", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_spendverify_m1<T: BoomerangRng>(
+        rng: &mut T,
+        s_state: &mut SpendVerifyStateS<B>,
+    ) -> SpendVerifyM1<B> {
+        let timer = SpanTimer::start();
+        let r2 = <B as CurveConfig>::ScalarField::rand(rng);
+
+        let session_id = new_session_id(rng);
+
+        s_state.r2 = r2;
+        s_state.session_id = session_id;
+        open_session("spend_verify");
+        timer.finish(SpendVerifyM1 { r2, session_id })
+    }
+
+    /// verify_spendverify_m2. This is a convenience wrapper around `verify_spendverify_m2_with_rng`
+    /// that uses the thread RNG.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `key_pair` - the server's keypair.
+    pub fn verify_spendverify_m2(
+        c_m: &SpendVerifyM2<B>,
+        key_pair: &ServerKeyPair<B>,
+    ) -> Result<(), &'static str> {
+        Self::verify_spendverify_m2_with_rng(c_m, key_pair, &mut rand::thread_rng())
+    }
+
+    /// verify_spendverify_m2_with_rng. This function checks the second message of the Spend/Verify
+    /// Protocol, i.e the client's signature, the proof of the signed commitment, the openings
+    /// of both commitments, the proof of the tag and the sub proof.
+    /// This function returns the reason for the first failed check, if any.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `key_pair` - the server's keypair.
+    /// * `rng` - the RNG that is used to batch the range proof checks.
+    pub fn verify_spendverify_m2_with_rng<T: BoomerangRng>(
+        c_m: &SpendVerifyM2<B>,
+        key_pair: &ServerKeyPair<B>,
+        rng: &mut T,
+    ) -> Result<(), &'static str> {
+        Self::verify_spendverify_m2_with_keys(c_m, key_pair.public_key(), key_pair.tag_key(), rng)
+    }
+
+    /// verify_spendverify_m2_with_keys. This function performs the same checks as
+    /// `verify_spendverify_m2_with_rng`, but only needs the server's public keys. This allows
+    /// parties other than the issuing server to check the message, e.g. as part of a `SpendBundle`.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `verifying_key` - the server's verifying key.
+    /// * `tag_key` - the server's tag key.
+    /// * `rng` - the RNG that is used to batch the range proof checks.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "spend_m2_verify", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn verify_spendverify_m2_with_keys<T: BoomerangRng>(
+        c_m: &SpendVerifyM2<B>,
+        verifying_key: &sw::Affine<B>,
+        tag_key: &sw::Affine<B>,
+        rng: &mut T,
+    ) -> Result<(), &'static str> {
+        let timer = SpanTimer::serve(c_m);
+        let check = SigVerify::verify(
+            *verifying_key,
+            *tag_key,
+            &c_m.sig,
+            &token_message::<B>(verifying_key, tag_key),
+            Some(TOKEN_CONTEXT),
+        );
+        if !check {
+            return Err(timer.reject("Boomerang spend-verify: invalid signature"));
+        }
+
+        let check2 = SigVerifProof::verify(
+            &c_m.s_proof,
+            *tag_key,
+            &c_m.sig,
+            &c_m.prev_gens.generators,
+            Some(&c_m.session_id),
+        );
+        if !check2 {
+            return Err(timer.reject("Boomerang spend-verify: invalid proof sig"));
+        }
+
+        // The new commitment must keep the layout of the signed one.
+        let len = committed_len(&c_m.prev_gens);
+        let label = b"BoomerangSpendVerifyM2O1";
+        let mut transcript = session_transcript::<B>(label, &c_m.session_id);
+        let check3 = len >= BASE_ATTRIBUTES
+            && committed_len(&c_m.gens) == len
+            && c_m
+                .pi_1
+                .verify(&mut transcript, &c_m.comm.comm, len, &c_m.gens);
+
+        if !check3 {
+            return Err(timer.reject("Boomerang spend-verify: invalid proof opening 1"));
+        }
+
+        let label1 = b"BoomerangSpendVerifyM2O2";
+        let mut transcript1 = session_transcript::<B>(label1, &c_m.session_id);
+        let check4 = c_m
+            .pi_2
+            .verify(&mut transcript1, &c_m.prev_comm.comm, len, &c_m.prev_gens);
+        if !check4 {
+            return Err(timer.reject("Boomerang spend-verify: invalid proof opening 2"));
+        }
+
+        let label2 = b"BoomerangSpendVerifyM2AM2";
+        let mut transcript2 = session_transcript::<B>(label2, &c_m.session_id);
+        let check5 = c_m.pi_3.verify(
+            &mut transcript2,
+            &c_m.tag_commits[0].comm,
+            &c_m.tag_commits[1].comm,
+            &c_m.tag_commits[2].comm,
+            &c_m.tag_commits[3].comm,
+            &c_m.tag_commits[4].comm,
+        );
+        if !check5 {
+            return Err(timer.reject("Boomerang spend-verify: invalid proof of tag"));
         }
 
         // Verify the sub proof
         let sub_proof = &c_m.pi_4;
-        let check6 = sub_proof.verify();
+        let check6 = sub_proof.verify_with_rng(&c_m.session_id, rng);
         if check6.is_err() {
-            panic!("Boomerang verification: sub proof verification failed")
+            return Err(timer.reject("Boomerang verification: sub proof verification failed"));
+        }
+
+        timer.record(c_m);
+        Ok(())
+    }
+
+    /// verify_spend_limit. This is a convenience wrapper around `verify_spend_limit_with_rng`
+    /// that uses the thread RNG.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `pi` - the received spend limit proof.
+    /// * `limit` - the spend limit.
+    /// * `epoch` - the current epoch.
+    pub fn verify_spend_limit(
+        c_m: &SpendVerifyM2<B>,
+        pi: &SpendLimitProof<B>,
+        limit: &SpendLimit,
+        epoch: u64,
+    ) -> Result<(), &'static str> {
+        Self::verify_spend_limit_with_rng(c_m, pi, limit, epoch, &mut rand::thread_rng())
+    }
+
+    /// verify_spend_limit_with_rng. This function checks that the token that is spent in `c_m`
+    /// was spent fewer than `limit.limit()` times in `epoch`, and that the new token counts this
+    /// spend, with the proof that came with the message. The server should check the message
+    /// itself with `verify_spendverify_m2`, and reject the spend if either check fails.
+    /// This function returns the reason for the first failed check, if any.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `pi` - the received spend limit proof.
+    /// * `limit` - the spend limit.
+    /// * `epoch` - the current epoch.
+    /// * `rng` - the source of randomness for the batched checks.
+    pub fn verify_spend_limit_with_rng<T: BoomerangRng>(
+        c_m: &SpendVerifyM2<B>,
+        pi: &SpendLimitProof<B>,
+        limit: &SpendLimit,
+        epoch: u64,
+        rng: &mut T,
+    ) -> Result<(), &'static str> {
+        let statement = SpendLimitStatement {
+            session_id: &c_m.session_id,
+            limit,
+            epoch,
+            prev_comm: &c_m.prev_comm.comm,
+            prev_gens: &c_m.prev_gens,
+            comm: &c_m.comm.comm,
+            gens: &c_m.gens,
+        };
+        pi.verify(&statement, rng)
+    }
+
+    /// generate_spendverify_m3. This function generates the thrid message of
+    /// the Spend/Verify Protocol. This function fails if the client message is not part of this
+    /// session, if it does not verify, or if the reward cannot be computed.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `c_m` - the received client message.
+    /// * `s_m` - the server message.
+    /// * `key_pair` - the server's keypair.
+    /// * `v` - the value to be spent.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "spend_m3", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_spendverify_m3<T: BoomerangRng>(
+        rng: &mut T,
+        c_m: &SpendVerifyM2<B>,
+        s_state: &mut SpendVerifyStateS<B>,
+        key_pair: &ServerKeyPair<B>,
+        policy_state: Vec<<B as CurveConfig>::ScalarField>,
+    ) -> Result<SpendVerifyM3<B>, &'static str> {
+        let timer = SpanTimer::start();
+        if c_m.session_id != s_state.session_id {
+            return Err(timer.reject("Boomerang spend-verify: session id mismatch"));
+        }
+
+        Self::verify_spendverify_m2_with_rng(c_m, key_pair, rng)?;
+
+        // TODO: verify the membership proof
+        #[allow(unused_variables)]
+        let dtag: ServerTag<B> = ServerTag {
+            tag: c_m.tag,
+            id_0: c_m.id,
+            r2: s_state.r2,
+        }; // N.B the server should record this with `TagStore::record_spend` once m2 is accepted
+
+        let m3 = Self::respond_spendverify_m2(
+            rng,
+            &c_m.comm,
+            &c_m.gens,
+            &c_m.spend_state,
+            s_state,
+            key_pair,
+            policy_state,
+        )
+        .map_err(|e| timer.reject(e))?;
+        Ok(timer.finish(m3))
+    }
+
+    /// respond_spendverify_m2. This function generates the third message of the Spend/Verify
+    /// Protocol once the second message has been checked, i.e it proves the reward and commits
+    /// to the signature on the new token. This function fails if the reward does not fit in 64
+    /// bits, e.g if the client spends a value that is out of range.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `comm` - the client's commitment to the new token.
+    /// * `gens` - the generators of `comm`.
+    /// * `spend_state` - the values that the client spends.
+    /// * `s_state` - the server state.
+    /// * `key_pair` - the server's keypair.
+    /// * `policy_state` - the policy vector.
+    fn respond_spendverify_m2<T: BoomerangRng>(
+        rng: &mut T,
+        comm: &PedersenComm<B>,
+        gens: &Generators<B>,
+        spend_state: &[<B as CurveConfig>::ScalarField],
+        s_state: &mut SpendVerifyStateS<B>,
+        key_pair: &ServerKeyPair<B>,
+        policy_state: Vec<<B as CurveConfig>::ScalarField>,
+    ) -> Result<SpendVerifyM3<B>, &'static str> {
+        let (id_1, c1) = Self::commit_spent_value(rng, gens, spend_state);
+
+        // Compute rewards
+        let (reward_u64, reward) = inner_product_to_u64::<B>(spend_state, &policy_state)
+            .map_err(|_| "Boomerang verification: failed to compute reward")?;
+
+        let policy_id = key_pair.policy_id(&policy_state);
+        let (re_proof, reward_opening) = BRewardsProof::prove_with_policy_id(
+            &RewardsGenerators::shared(),
+            spend_state,
+            &policy_state,
+            &policy_id,
+            reward_u64,
+            reward,
+            &s_state.session_id,
+            rng,
+        )
+        .map_err(|_| "Boomerang verification: failed to create rewards proof")?;
+
+        // Only if the rewards proof was successfully done
+        let c = *comm - c1; // The other way around to handle the negative
+        let sig_comm = SigComm::commit(&key_pair.s_key_pair, rng, c.comm);
+        s_state.sig_commit = sig_comm;
+
+        Ok(SpendVerifyM3 {
+            id_1,
+            val: spend_state[0],
+            comm: c1,
+            sig_commit: sig_comm,
+            verifying_key: key_pair.s_key_pair.verifying_key,
+            tag_key: key_pair.s_key_pair.tag_key,
+            pi_reward: re_proof,
+            reward_opening,
+            policy_id,
+            session_id: s_state.session_id,
+        })
+    }
+
+    /// commit_spent_value. This function returns a new serial number share and the server's
+    /// commitment to it and to the spent value, which is subtracted from the client's
+    /// commitment to the new token.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `gens` - the generators of the client's commitment.
+    /// * `spend_state` - the values that the client spends.
+    fn commit_spent_value<T: BoomerangRng>(
+        rng: &mut T,
+        gens: &Generators<B>,
+        spend_state: &[<B as CurveConfig>::ScalarField],
+    ) -> (<B as CurveConfig>::ScalarField, PedersenComm<B>) {
+        let id_1 = <B as CurveConfig>::ScalarField::rand(rng);
+        let v2 = <B as CurveConfig>::ScalarField::zero();
+        let v3 = <B as CurveConfig>::ScalarField::zero();
+        let vals: Vec<<B as CurveConfig>::ScalarField> = vec![id_1, spend_state[0], v2, v3];
+
+        (
+            id_1,
+            PedersenComm::new_multi_with_all_generators(&vals, rng, gens),
+        )
+    }
+
+    /// generate_spendverify_reply. This function checks the second message of the
+    /// Spend/Verify Protocol, and generates its reply: a `SpendVerifyM3` if `policy_state`
+    /// yields a reward for the spent values, and a `SpendVerifyAck`, which skips the rewards
+    /// proof, otherwise. The client must answer the latter with
+    /// `SpendVerifyStateC::generate_spendverify_m4_fast`.
+    /// This function fails if the policy does not have the length of the client's spend state.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `c_m` - the received client message.
+    /// * `s_state` - the server state.
+    /// * `key_pair` - the server's keypair.
+    /// * `policy_state` - the policy vector.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "spend_reply", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_spendverify_reply<T: BoomerangRng>(
+        rng: &mut T,
+        c_m: &SpendVerifyM2<B>,
+        s_state: &mut SpendVerifyStateS<B>,
+        key_pair: &ServerKeyPair<B>,
+        policy_state: Vec<<B as CurveConfig>::ScalarField>,
+    ) -> Result<SpendVerifyReply<B>, &'static str> {
+        let timer = SpanTimer::start();
+        if policy_state.len() != c_m.spend_state.len() {
+            return Err("Boomerang spend-verify: policy does not match the spend state");
+        }
+        if c_m.session_id != s_state.session_id {
+            return Err(timer.reject("Boomerang spend-verify: session id mismatch"));
+        }
+        Self::verify_spendverify_m2_with_rng(c_m, key_pair, rng).map_err(|e| timer.reject(e))?;
+
+        if reward_due::<B>(&c_m.spend_state, &policy_state) {
+            let m3 = Self::respond_spendverify_m2(
+                rng,
+                &c_m.comm,
+                &c_m.gens,
+                &c_m.spend_state,
+                s_state,
+                key_pair,
+                policy_state,
+            )
+            .map_err(|e| timer.reject(e))?;
+            return Ok(SpendVerifyReply::Full(timer.finish(m3)));
+        }
+
+        let (id_1, c1) = Self::commit_spent_value(rng, &c_m.gens, &c_m.spend_state);
+        let c = c_m.comm - c1;
+        let sig_comm = SigComm::commit(&key_pair.s_key_pair, rng, c.comm);
+        s_state.sig_commit = sig_comm;
+
+        Ok(SpendVerifyReply::Fast(timer.finish(SpendVerifyAck {
+            comm: c1,
+            sig_commit: sig_comm,
+            id_1,
+            val: c_m.spend_state[0],
+            verifying_key: key_pair.s_key_pair.verifying_key,
+            tag_key: key_pair.s_key_pair.tag_key,
+            session_id: s_state.session_id,
+        })))
+    }
+
+    /// verify_not_revoked. This function checks that the token that is presented in the second
+    /// message of the Spend/Verify Protocol is not in the revocation list committed to by `root`.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `root` - the current root of the revocation list.
+    /// * `witness` - the non-revocation witness that was sent by the client alongside `c_m`.
+    pub fn verify_not_revoked(
+        c_m: &SpendVerifyM2<B>,
+        root: &RevocationRoot,
+        witness: &NonRevocationWitness,
+    ) -> Result<(), &'static str> {
+        if !witness.verify(root, &revocation_key::<B>(&c_m.sig)) {
+            return Err("Boomerang spend-verify: token is revoked");
+        }
+        Ok(())
+    }
+
+    /// generate_spendverify_m3_with_revocation. This function generates the third message of the
+    /// Spend/Verify Protocol, as in `generate_spendverify_m3`, once it has checked that the
+    /// presented token is not revoked.
+    /// This function fails if `witness` does not show that the token is absent from the list.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `c_m` - the received client message.
+    /// * `s_state` - the server state.
+    /// * `key_pair` - the server's keypair.
+    /// * `policy_state` - the policy vector.
+    /// * `root` - the current root of the revocation list.
+    /// * `witness` - the non-revocation witness that was sent by the client alongside `c_m`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_spendverify_m3_with_revocation<T: BoomerangRng>(
+        rng: &mut T,
+        c_m: &SpendVerifyM2<B>,
+        s_state: &mut SpendVerifyStateS<B>,
+        key_pair: &ServerKeyPair<B>,
+        policy_state: Vec<<B as CurveConfig>::ScalarField>,
+        root: &RevocationRoot,
+        witness: &NonRevocationWitness,
+    ) -> Result<SpendVerifyM3<B>, &'static str> {
+        Self::verify_not_revoked(c_m, root, witness)?;
+        Self::generate_spendverify_m3(rng, c_m, s_state, key_pair, policy_state)
+    }
+
+    /// generate_spendverify_m3_with_policy. This function generates the third message of the
+    /// Spend/Verify Protocol, as in `generate_spendverify_m3`, with the policy that `provider`
+    /// returns for `campaign_id` in `epoch`.
+    /// This function fails if the provider has no policy for the campaign in that epoch, with
+    /// the reason that the provider gives, or if the policy does not have the length of the
+    /// client's spend state.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `c_m` - the received client message.
+    /// * `s_state` - the server state.
+    /// * `key_pair` - the server's keypair.
+    /// * `provider` - the source of the policy vector.
+    /// * `campaign_id` - the campaign that the spend belongs to.
+    /// * `epoch` - the current epoch.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_spendverify_m3_with_policy<T: BoomerangRng>(
+        rng: &mut T,
+        c_m: &SpendVerifyM2<B>,
+        s_state: &mut SpendVerifyStateS<B>,
+        key_pair: &ServerKeyPair<B>,
+        provider: &impl PolicyProvider<B>,
+        campaign_id: u64,
+        epoch: u64,
+    ) -> Result<SpendVerifyM3<B>, &'static str> {
+        let policy_state = match provider.policy_for(campaign_id, epoch, rng) {
+            Ok(policy_state) if !policy_state.is_empty() => policy_state,
+            Ok(_) | Err(PolicyError::NoPolicy) => {
+                return Err("Boomerang spend-verify: no policy for the campaign and epoch")
+            }
+            Err(PolicyError::Unavailable(reason)) => return Err(reason),
+        };
+        if policy_state.len() != c_m.spend_state.len() {
+            return Err("Boomerang spend-verify: policy does not match the spend state");
+        }
+
+        Self::generate_spendverify_m3(rng, c_m, s_state, key_pair, policy_state)
+    }
+
+    /// verify_spendverify_m2_multi. This function checks the second message of the
+    /// Spend/Verify Protocol when the client spends several tokens at once, i.e for every spent
+    /// token the signature, the proof of the signed commitment, its opening and the proof of the
+    /// tag, and then the opening of the new commitment and the proof that the balances of the
+    /// spent tokens cover the amount.
+    /// This function returns the reason for the first failed check, if any.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `key_pair` - the server's keypair.
+    /// * `rng` - the RNG that is used to batch the range proof checks.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "spend_m2_multi_verify", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn verify_spendverify_m2_multi<T: BoomerangRng>(
+        c_m: &SpendVerifyM2Multi<B>,
+        key_pair: &ServerKeyPair<B>,
+        rng: &mut T,
+    ) -> Result<(), &'static str> {
+        let timer = SpanTimer::serve(c_m);
+        if c_m.inputs.is_empty() {
+            return Err(timer.reject("Boomerang spend-verify: no token to spend"));
+        }
+        if c_m.spend_state.is_empty() {
+            return Err(timer.reject("Boomerang spend-verify: empty spend state"));
+        }
+
+        // The new commitment must keep the layout of the signed ones.
+        let len = committed_len(&c_m.gens);
+        if len < BASE_ATTRIBUTES {
+            return Err(timer.reject("Boomerang spend-verify: invalid proof opening 1"));
+        }
+
+        for (i, input) in c_m.inputs.iter().enumerate() {
+            if c_m.inputs[..i].iter().any(|other| other.id == input.id) {
+                return Err(timer.reject("Boomerang spend-verify: token presented twice"));
+            }
+            Self::verify_spend_input(input, i, len, &c_m.session_id, key_pair)
+                .map_err(|e| timer.reject(e))?;
+        }
+
+        // The new token must hold the sum of the balances.
+        let balance: sw::Projective<B> = c_m
+            .inputs
+            .iter()
+            .map(|input| input.balance_comm.into_group())
+            .sum();
+        let label = b"BoomerangSpendVerifyM2MultiO1";
+        let mut transcript = session_transcript::<B>(label, &c_m.session_id);
+        let check = c_m.pi_balance.verify(
+            &c_m.pi_1,
+            &mut transcript,
+            &c_m.comm.comm,
+            len,
+            &c_m.gens,
+            &balance.into_affine(),
+        );
+        if !check {
+            return Err(timer.reject("Boomerang spend-verify: invalid proof opening 1"));
+        }
+
+        // The balance commitments, minus the amount, must be the commitment of the sub proof.
+        let gens = RewardsGenerators::<B>::shared();
+        let amount = gens
+            .pc_gens
+            .commit(c_m.spend_state[0], <B as CurveConfig>::ScalarField::zero());
+        if (balance - amount).into_affine() != c_m.pi_4.r_comms {
+            return Err(timer.reject("Boomerang spend-verify: balances do not match the sub proof"));
+        }
+
+        if c_m.pi_4.verify_with_rng(&c_m.session_id, rng).is_err() {
+            return Err(timer.reject("Boomerang verification: sub proof verification failed"));
+        }
+
+        timer.record(c_m);
+        Ok(())
+    }
+
+    /// verify_spend_input. This function checks the presentation of the spent token with index
+    /// `index` in a `SpendVerifyM2Multi`.
+    /// # Arguments
+    /// * `input` - the presentation of the token.
+    /// * `index` - the index of the token in the message.
+    /// * `len` - the number of committed values of the new token.
+    /// * `session_id` - the identifier of the session.
+    /// * `key_pair` - the server's keypair.
+    fn verify_spend_input(
+        input: &SpendInput<B>,
+        index: usize,
+        len: usize,
+        session_id: &SessionId,
+        key_pair: &ServerKeyPair<B>,
+    ) -> Result<(), &'static str> {
+        let check = SigVerify::verify(
+            key_pair.s_key_pair.verifying_key,
+            key_pair.s_key_pair.tag_key,
+            &input.sig,
+            &key_pair.token_message(),
+            Some(TOKEN_CONTEXT),
+        );
+        if !check {
+            return Err("Boomerang spend-verify: invalid signature");
+        }
+
+        let check2 = SigVerifProof::verify(
+            &input.s_proof,
+            key_pair.s_key_pair.tag_key,
+            &input.sig,
+            &input.prev_gens.generators,
+            Some(session_id),
+        );
+        if !check2 {
+            return Err("Boomerang spend-verify: invalid proof sig");
+        }
+
+        // The opening of the signed commitment also shows that `balance_comm` commits to its
+        // balance.
+        let label = b"BoomerangSpendVerifyM2MultiO2";
+        let mut transcript = session_transcript::<B>(label, session_id);
+        append_u64(&mut transcript, b"input", index as u64);
+        let check3 = committed_len(&input.prev_gens) == len
+            && input.pi_balance.verify(
+                &input.pi_2,
+                &mut transcript,
+                &input.prev_comm.comm,
+                len,
+                &input.prev_gens,
+                &input.balance_comm,
+            );
+        if !check3 {
+            return Err("Boomerang spend-verify: invalid proof opening 2");
+        }
+
+        let label2 = b"BoomerangSpendVerifyM2MultiAM2";
+        let mut transcript2 = session_transcript::<B>(label2, session_id);
+        append_u64(&mut transcript2, b"input", index as u64);
+        let check4 = input.tag_commits.len() == 5
+            && input.pi_3.verify(
+                &mut transcript2,
+                &input.tag_commits[0].comm,
+                &input.tag_commits[1].comm,
+                &input.tag_commits[2].comm,
+                &input.tag_commits[3].comm,
+                &input.tag_commits[4].comm,
+            );
+        if !check4 {
+            return Err("Boomerang spend-verify: invalid proof of tag");
+        }
+
+        Ok(())
+    }
+
+    /// generate_spendverify_m3_multi. This function generates the third message of the
+    /// Spend/Verify Protocol in answer to a `SpendVerifyM2Multi`. The rest of the protocol is the
+    /// same as for a single token. The server should record the tags of every spent token with
+    /// `TagStore::record_spend_multi` once the message is accepted.
+    /// This function fails if the message does not pass `verify_spendverify_m2_multi`.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `c_m` - the received client message.
+    /// * `s_state` - the server state.
+    /// * `key_pair` - the server's keypair.
+    /// * `policy_state` - the policy vector.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "spend_m3_multi", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_spendverify_m3_multi<T: BoomerangRng>(
+        rng: &mut T,
+        c_m: &SpendVerifyM2Multi<B>,
+        s_state: &mut SpendVerifyStateS<B>,
+        key_pair: &ServerKeyPair<B>,
+        policy_state: Vec<<B as CurveConfig>::ScalarField>,
+    ) -> Result<SpendVerifyM3<B>, &'static str> {
+        let timer = SpanTimer::start();
+        if c_m.session_id != s_state.session_id {
+            return Err(timer.reject("Boomerang spend-verify: session id mismatch"));
+        }
+
+        Self::verify_spendverify_m2_multi(c_m, key_pair, rng)?;
+
+        let m3 = Self::respond_spendverify_m2(
+            rng,
+            &c_m.comm,
+            &c_m.gens,
+            &c_m.spend_state,
+            s_state,
+            key_pair,
+            policy_state,
+        )
+        .map_err(|e| timer.reject(e))?;
+        Ok(timer.finish(m3))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "spend_m5", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_spendverify_m5(
+        c_m: &SpendVerifyM4<B>,
+        s_state: &mut SpendVerifyStateS<B>,
+        key_pair: &ServerKeyPair<B>,
+    ) -> SpendVerifyM5<B> {
+        let timer = SpanTimer::serve(c_m);
+        if c_m.session_id != s_state.session_id {
+            panic!(
+                "{}",
+                timer.reject("Boomerang spend-verify: session id mismatch")
+            );
+        }
+
+        let sig_resp = SigResp::respond(&key_pair.s_key_pair, &s_state.sig_commit, &c_m.e);
+
+        close_session("spend_verify");
+        timer.finish(SpendVerifyM5 {
+            s: sig_resp,
+            session_id: s_state.session_id,
+        })
+    }
+
+    /// generate_spendverify_m1_stateless. This function generates the first message of
+    /// the Spend/Verify Protocol, alongside a ticket that holds the server's state for this session.
+    /// The ticket must be returned by the client alongside the second message.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `ticket_key` - the key that tickets are sealed with.
+    /// * `expiry` - the time after which the ticket is no longer accepted.
+    pub fn generate_spendverify_m1_stateless<T: BoomerangRng>(
+        rng: &mut T,
+        ticket_key: &TicketKey,
+        expiry: u64,
+    ) -> (SpendVerifyM1<B>, SessionTicket) {
+        let mut s_state = Self::default();
+        let m1 = Self::generate_spendverify_m1(rng, &mut s_state);
+        let ticket = ticket_key.seal(
+            rng,
+            SPENDVERIFY_M1_TICKET,
+            expiry,
+            &(s_state.r2, s_state.session_id),
+        );
+        (m1, ticket)
+    }
+
+    /// generate_spendverify_m3_stateless. This function generates the third message of the
+    /// Spend/Verify Protocol from the ticket that was issued with the first message, alongside a
+    /// new ticket that must be returned by the client alongside the fourth message.
+    /// This function fails if the second message is invalid, if `ticket` cannot be opened, if
+    /// `guard` has already accepted it, or if it was issued for another session.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `c_m` - the received client message.
+    /// * `ticket` - the ticket that was returned by the client.
+    /// * `ticket_key` - the key that tickets are sealed with.
+    /// * `guard` - the ids of the tickets that were already accepted.
+    /// * `now` - the current time.
+    /// * `expiry` - the time after which the new ticket is no longer accepted.
+    /// * `key_pair` - the server's keypair.
+    /// * `policy_state` - the policy vector.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_spendverify_m3_stateless<T: BoomerangRng, G: ReplayGuard>(
+        rng: &mut T,
+        c_m: &SpendVerifyM2<B>,
+        ticket: &SessionTicket,
+        ticket_key: &TicketKey,
+        guard: &mut G,
+        now: u64,
+        expiry: u64,
+        key_pair: &ServerKeyPair<B>,
+        policy_state: Vec<<B as CurveConfig>::ScalarField>,
+    ) -> Result<(SpendVerifyM3<B>, SessionTicket), &'static str> {
+        // As for collection, the message is checked before the ticket is spent.
+        Self::verify_spendverify_m2_with_rng(c_m, key_pair, rng)?;
+
+        let (r2, session_id) = ticket_key.open_once(SPENDVERIFY_M1_TICKET, now, ticket, guard)?;
+        if c_m.session_id != session_id {
+            return Err("Boomerang spend-verify: session id mismatch");
+        }
+
+        let mut s_state = Self {
+            r2,
+            session_id,
+            ..Self::default()
+        };
+        let m3 = Self::respond_spendverify_m2(
+            rng,
+            &c_m.comm,
+            &c_m.gens,
+            &c_m.spend_state,
+            &mut s_state,
+            key_pair,
+            policy_state,
+        )?;
+        let ticket = ticket_key.seal(
+            rng,
+            SPENDVERIFY_M3_TICKET,
+            expiry,
+            &(s_state.sig_commit, s_state.session_id),
+        );
+        Ok((m3, ticket))
+    }
+
+    /// generate_spendverify_m5_stateless. This function generates the fifth message of the
+    /// Spend/Verify Protocol from the ticket that was issued with the third message.
+    /// This function fails if `ticket` cannot be opened, or if `guard` has already accepted it:
+    /// answering the same signature commitment twice leaks the server's signing key.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `ticket` - the ticket that was returned by the client.
+    /// * `ticket_key` - the key that tickets are sealed with.
+    /// * `guard` - the ids of the tickets that were already accepted.
+    /// * `now` - the current time.
+    /// * `key_pair` - the server's keypair.
+    pub fn generate_spendverify_m5_stateless<G: ReplayGuard>(
+        c_m: &SpendVerifyM4<B>,
+        ticket: &SessionTicket,
+        ticket_key: &TicketKey,
+        guard: &mut G,
+        now: u64,
+        key_pair: &ServerKeyPair<B>,
+    ) -> Result<SpendVerifyM5<B>, &'static str> {
+        let (sig_commit, session_id) =
+            ticket_key.open_once(SPENDVERIFY_M3_TICKET, now, ticket, guard)?;
+        let mut s_state = Self {
+            sig_commit,
+            session_id,
+            ..Self::default()
+        };
+        Ok(Self::generate_spendverify_m5(c_m, &mut s_state, key_pair))
+    }
+}
+
+/// Transfer protocol
+/// TransferM1. This struct acts as a container for the first message of the transfer
+/// protocol, which the server sends to both the sender and the recipient.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct TransferM1<B: BoomerangConfig> {
+    /// r2: the random double-spending tag value.
+    pub r2: <B as CurveConfig>::ScalarField,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
+}
+
+impl<B: BoomerangConfig> Clone for TransferM1<B> {
+    fn clone(&self) -> Self {
+        Self {
+            r2: self.r2,
+            session_id: self.session_id,
+        }
+    }
+}
+
+/// TransferM3. This struct acts as a container for the third message of the transfer
+/// protocol, which the server sends to the recipient.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct TransferM3<B: BoomerangConfig> {
+    /// sig_commit: the first signature value.
+    pub sig_commit: SigComm<B>,
+    /// value_comm: the commitment to the transferred value.
+    pub value_comm: sw::Affine<B>,
+    /// Public key
+    pub verifying_key: sw::Affine<B>,
+    /// Tag public key
+    pub tag_key: sw::Affine<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
+}
+
+impl<B: BoomerangConfig> Clone for TransferM3<B> {
+    fn clone(&self) -> Self {
+        Self {
+            sig_commit: self.sig_commit,
+            value_comm: self.value_comm,
+            verifying_key: self.verifying_key,
+            tag_key: self.tag_key,
+            session_id: self.session_id,
+        }
+    }
+}
+
+/// TransferM5. This struct acts as a container for the fifth message of the transfer protocol.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct TransferM5<B: BoomerangConfig> {
+    /// s: the signature response value.
+    pub s: SigResp<B>,
+    /// session_id: the identifier of this session.
+    pub session_id: SessionId,
+}
+
+impl<B: BoomerangConfig> Clone for TransferM5<B> {
+    fn clone(&self) -> Self {
+        Self {
+            s: self.s,
+            session_id: self.session_id,
+        }
+    }
+}
+
+/// TransferStateS. This struct represents the transfer protocol for the server.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct TransferStateS<B: BoomerangConfig> {
+    /// r2: the random double-spending tag value.
+    r2: <B as CurveConfig>::ScalarField,
+    /// sig_commit: the first signature value.
+    sig_commit: SigComm<B>,
+    /// session_id: the identifier of this session.
+    session_id: SessionId,
+}
+
+impl<B: BoomerangConfig> Default for TransferStateS<B> {
+    fn default() -> Self {
+        Self {
+            r2: <B as CurveConfig>::ScalarField::zero(),
+            sig_commit: SigComm::<B>::default(),
+            session_id: SessionId::default(),
+        }
+    }
+}
+
+impl<B: BoomerangConfig> TransferStateS<B> {
+    /// generate_transfer_m1. This function generates the first message of the Transfer
+    /// Protocol, in which the sender's token is re-issued to the recipient's key.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `s_state` - the server state.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "transfer_m1", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_transfer_m1<T: BoomerangRng>(
+        rng: &mut T,
+        s_state: &mut TransferStateS<B>,
+    ) -> TransferM1<B> {
+        let timer = SpanTimer::start();
+        let r2 = <B as CurveConfig>::ScalarField::rand(rng);
+        let session_id = new_session_id(rng);
+
+        s_state.r2 = r2;
+        s_state.session_id = session_id;
+        open_session("transfer");
+        timer.finish(TransferM1 { r2, session_id })
+    }
+
+    /// verify_transfer_m2. This function checks the second message of the Transfer Protocol,
+    /// i.e the sender's signature, the proof of the signed commitment, the proofs that split it
+    /// into a commitment to the value and a commitment to the rest, the proof of the tag, and the
+    /// recipient's request.
+    /// This function returns the reason for the first failed check, if any.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `key_pair` - the server's keypair.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "transfer_m2_verify", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn verify_transfer_m2(
+        c_m: &TransferM2<B>,
+        key_pair: &ServerKeyPair<B>,
+    ) -> Result<(), &'static str> {
+        let timer = SpanTimer::serve(c_m);
+        if c_m.request.session_id != c_m.session_id {
+            return Err(timer.reject("Boomerang transfer: session id mismatch"));
+        }
+
+        let check = SigVerify::verify(
+            key_pair.s_key_pair.verifying_key,
+            key_pair.s_key_pair.tag_key,
+            &c_m.sig,
+            &key_pair.token_message(),
+            Some(TOKEN_CONTEXT),
+        );
+        if !check {
+            return Err(timer.reject("Boomerang transfer: invalid signature"));
+        }
+
+        let check2 = SigVerifProof::verify(
+            &c_m.s_proof,
+            key_pair.s_key_pair.tag_key,
+            &c_m.sig,
+            &c_m.prev_gens.generators,
+            Some(&c_m.session_id),
+        );
+        if !check2 {
+            return Err(timer.reject("Boomerang transfer: invalid proof sig"));
+        }
+
+        let len = committed_len(&c_m.prev_gens);
+        if len < BASE_ATTRIBUTES {
+            return Err(timer.reject("Boomerang transfer: invalid layout"));
+        }
+        let (value_gens, rest_gens) = split_value_generators(&c_m.prev_gens);
+
+        let label = b"BoomerangTransferM2Value";
+        let mut transcript = session_transcript::<B>(label, &c_m.session_id);
+        let check3 = c_m
+            .pi_value
+            .verify(&mut transcript, &c_m.value_comm, 1, &value_gens);
+
+        let label1 = b"BoomerangTransferM2Rest";
+        let mut transcript1 = session_transcript::<B>(label1, &c_m.session_id);
+        let rest_comm = (c_m.prev_comm.comm.into_group() - c_m.value_comm).into_affine();
+        let check4 = c_m
+            .pi_rest
+            .verify(&mut transcript1, &rest_comm, len - 1, &rest_gens);
+        if !check3 || !check4 {
+            return Err(timer.reject("Boomerang transfer: invalid proof of the value"));
+        }
+
+        let label2 = b"BoomerangTransferM2AM2";
+        let mut transcript2 = session_transcript::<B>(label2, &c_m.session_id);
+        let check5 = c_m.tag_commits.len() == 5
+            && c_m.pi_3.verify(
+                &mut transcript2,
+                &c_m.tag_commits[0].comm,
+                &c_m.tag_commits[1].comm,
+                &c_m.tag_commits[2].comm,
+                &c_m.tag_commits[3].comm,
+                &c_m.tag_commits[4].comm,
+            );
+        if !check5 {
+            return Err(timer.reject("Boomerang transfer: invalid proof of tag"));
+        }
+
+        // The new token must use the generators of the sender's token, so that the value
+        // commitment carries over.
+        let request = &c_m.request;
+        let check6 = committed_len(&request.gens) == BASE_ATTRIBUTES
+            && request.gens.generators[..BASE_ATTRIBUTES]
+                == c_m.prev_gens.generators[..BASE_ATTRIBUTES];
+        if !check6 {
+            return Err(timer.reject("Boomerang transfer: invalid layout"));
+        }
+
+        let (_, request_gens) = split_value_generators(&request.gens);
+        if !request.pi.verify(
+            &c_m.session_id,
+            &request.comm,
+            &request_gens,
+            &request.public_key,
+        ) {
+            return Err(
+                timer.reject("Boomerang transfer: request is not bound to the recipient's key")
+            );
+        }
+
+        timer.record(c_m);
+        Ok(())
+    }
+
+    /// generate_transfer_m3. This function generates the third message of the Transfer
+    /// Protocol, i.e the commitment to the signature on the recipient's new token, which holds
+    /// the value of the sender's token. The server should record the sender's tag with
+    /// `TagStore::record_transfer` once the message is accepted.
+    /// This function fails if the message does not pass `verify_transfer_m2`.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `c_m` - the received client message.
+    /// * `s_state` - the server state.
+    /// * `key_pair` - the server's keypair.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "transfer_m3", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_transfer_m3<T: BoomerangRng>(
+        rng: &mut T,
+        c_m: &TransferM2<B>,
+        s_state: &mut TransferStateS<B>,
+        key_pair: &ServerKeyPair<B>,
+    ) -> Result<TransferM3<B>, &'static str> {
+        let timer = SpanTimer::start();
+        if c_m.session_id != s_state.session_id {
+            return Err(timer.reject("Boomerang transfer: session id mismatch"));
+        }
+        Self::verify_transfer_m2(c_m, key_pair)?;
+
+        let c = (c_m.request.comm + c_m.value_comm).into_affine();
+        let sig_comm = SigComm::commit(&key_pair.s_key_pair, rng, c);
+        s_state.sig_commit = sig_comm;
+
+        Ok(timer.finish(TransferM3 {
+            sig_commit: sig_comm,
+            value_comm: c_m.value_comm,
+            verifying_key: key_pair.s_key_pair.verifying_key,
+            tag_key: key_pair.s_key_pair.tag_key,
+            session_id: s_state.session_id,
+        }))
+    }
+
+    /// generate_transfer_m5. This function generates the fifth message of the Transfer Protocol.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `s_state` - the server state.
+    /// * `key_pair` - the server's keypair.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "transfer_m5", skip_all, fields(size, elapsed_us))
+    )]
+    pub fn generate_transfer_m5(
+        c_m: &TransferM4<B>,
+        s_state: &mut TransferStateS<B>,
+        key_pair: &ServerKeyPair<B>,
+    ) -> TransferM5<B> {
+        let timer = SpanTimer::serve(c_m);
+        if c_m.session_id != s_state.session_id {
+            panic!(
+                "{}",
+                timer.reject("Boomerang transfer: session id mismatch")
+            );
         }
 
-        // TODO: verify the membership proof
-        #[allow(unused_variables)]
-        let dtag: ServerTag<B> = ServerTag {
+        let sig_resp = SigResp::respond(&key_pair.s_key_pair, &s_state.sig_commit, &c_m.e);
+        close_session("transfer");
+        timer.finish(TransferM5 {
+            s: sig_resp,
+            session_id: s_state.session_id,
+        })
+    }
+}
+
+impl<B: BoomerangConfig> TransferStateS<B> {
+    /// verify_recovery_m2. This function checks the second message of the Recovery Protocol,
+    /// i.e that a backup key is registered for the user's public key, that the fresh key is
+    /// not the old one, the proof of the old key and of the backup key, and the transfer as in
+    /// `verify_transfer_m2`.
+    /// This function returns the reason for the first failed check, if any.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    /// * `key_pair` - the server's keypair.
+    /// * `registry` - the registered backup keys.
+    pub fn verify_recovery_m2(
+        c_m: &RecoveryM2<B>,
+        key_pair: &ServerKeyPair<B>,
+        registry: &RecoveryRegistry<B>,
+    ) -> Result<(), &'static str> {
+        let backup_pk = registry
+            .backup_key(&c_m.user_pk)
+            .ok_or("Boomerang recovery: no backup key is registered")?;
+
+        let transfer = &c_m.transfer;
+        if transfer.request.public_key == c_m.user_pk {
+            return Err("Boomerang recovery: the fresh key is the old key");
+        }
+        if transfer.tag_commits.is_empty()
+            || !c_m.pi.verify(
+                &transfer.session_id,
+                &transfer.tag_commits[0].comm,
+                &c_m.user_pk,
+                &backup_pk,
+                &transfer.request.public_key,
+            )
+        {
+            return Err("Boomerang recovery: invalid proof of the backup key");
+        }
+
+        Self::verify_transfer_m2(transfer, key_pair)
+    }
+
+    /// generate_recovery_m3. This function generates the third message of the Recovery
+    /// Protocol, which is the `TransferM3` of the transfer in `c_m`. The rest of the protocol
+    /// is the Transfer Protocol. The server should record the tag of the old token with
+    /// `TagStore::record_transfer` on `c_m.transfer` once the message is accepted, so that the
+    /// old token cannot be spent anymore.
+    /// This function fails if the message does not pass `verify_recovery_m2`.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `c_m` - the received client message.
+    /// * `s_state` - the server state.
+    /// * `key_pair` - the server's keypair.
+    /// * `registry` - the registered backup keys.
+    pub fn generate_recovery_m3<T: BoomerangRng>(
+        rng: &mut T,
+        c_m: &RecoveryM2<B>,
+        s_state: &mut TransferStateS<B>,
+        key_pair: &ServerKeyPair<B>,
+        registry: &RecoveryRegistry<B>,
+    ) -> Result<TransferM3<B>, &'static str> {
+        if c_m.transfer.session_id != s_state.session_id {
+            return Err("Boomerang recovery: session id mismatch");
+        }
+        Self::verify_recovery_m2(c_m, key_pair, registry)?;
+        Self::generate_transfer_m3(rng, &c_m.transfer, s_state, key_pair)
+    }
+}
+
+/// RecoveryRegistry. This struct holds the backup keys that users registered with the server,
+/// keyed by their public keys. A backup key cannot be replaced once it is registered, so that
+/// a stolen keypair cannot be used to take over the recovery of its tokens.
+pub struct RecoveryRegistry<B: BoomerangConfig> {
+    /// keys: the registered public keys, alongside their backup keys.
+    keys: Vec<(sw::Affine<B>, sw::Affine<B>)>,
+}
+
+impl<B: BoomerangConfig> Default for RecoveryRegistry<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: BoomerangConfig> RecoveryRegistry<B> {
+    /// new. This function returns a new, empty, registry.
+    pub fn new() -> Self {
+        Self { keys: Vec::new() }
+    }
+
+    /// register. This function records the backup key in `reg` for its user public key.
+    /// Registering the same backup key again is a no-op.
+    /// This function fails if the proof in `reg` does not verify, or if another backup key is
+    /// already registered for the public key.
+    /// # Arguments
+    /// * `reg` - the received registration.
+    pub fn register(&mut self, reg: &BackupRegistration<B>) -> Result<(), &'static str> {
+        if !reg.verify() {
+            return Err("Boomerang recovery: invalid proof of the keys");
+        }
+        match self.backup_key(&reg.user_pk) {
+            Some(backup_pk) if backup_pk == reg.backup_pk => Ok(()),
+            Some(_) => Err("Boomerang recovery: a backup key is already registered"),
+            None => {
+                self.keys.push((reg.user_pk, reg.backup_pk));
+                Ok(())
+            }
+        }
+    }
+
+    /// backup_key. This function returns the backup key that is registered for `user_pk`, if
+    /// any.
+    /// # Arguments
+    /// * `user_pk` - the user's public key.
+    pub fn backup_key(&self, user_pk: &sw::Affine<B>) -> Option<sw::Affine<B>> {
+        self.keys
+            .iter()
+            .find(|(pk, _)| pk == user_pk)
+            .map(|(_, backup_pk)| *backup_pk)
+    }
+
+    /// len. Returns the number of registered backup keys.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// is_empty. Returns true if no backup key is registered.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+/// RevocationRoot. The published root of a `RevocationList`.
+pub type RevocationRoot = MerkleRoot;
+
+/// NonRevocationWitness. A proof that a key is not in a `RevocationList`. The list is kept
+/// sorted, so it suffices to show the two revoked keys that are adjacent to the key.
+pub type NonRevocationWitness = ExclusionProof;
+
+/// RevocationList. This struct holds the keys of the tokens that have been revoked in an epoch,
+/// so that compromised tokens can be rejected without rotating the server's keys.
+/// The keys are committed to with a Merkle tree, whose root can be published so that clients
+/// can show that their token is not revoked with a `NonRevocationWitness`.
+#[derive(Clone, Debug, Default)]
+pub struct RevocationList {
+    /// revoked: the revoked keys.
+    revoked: MerkleSet,
+}
+
+impl RevocationList {
+    /// new. This function returns a new, empty, revocation list for `epoch`.
+    /// # Arguments
+    /// * `epoch` - the epoch of the list.
+    pub fn new(epoch: u64) -> Self {
+        Self {
+            revoked: MerkleSet::new(epoch),
+        }
+    }
+
+    /// epoch. Returns the epoch of the list.
+    pub fn epoch(&self) -> u64 {
+        self.revoked.epoch()
+    }
+
+    /// is_current. This function returns true if the list belongs to the current epoch of
+    /// `clock`, i.e if it should not yet be replaced by the list of a later epoch.
+    /// # Arguments
+    /// * `clock` - the source of the current time.
+    /// * `length` - the length of an epoch, in seconds.
+    pub fn is_current(&self, clock: &impl Clock, length: u64) -> bool {
+        clock.epoch(length) == Epoch::new(self.epoch())
+    }
+
+    /// len. Returns the number of revoked keys.
+    pub fn len(&self) -> usize {
+        self.revoked.len()
+    }
+
+    /// is_empty. Returns true if no key has been revoked.
+    pub fn is_empty(&self) -> bool {
+        self.revoked.is_empty()
+    }
+
+    /// revoke. This function adds `key` to the list. Returns false if `key` was already revoked.
+    /// # Arguments
+    /// * `key` - the key to revoke.
+    pub fn revoke(&mut self, key: RevocationKey) -> bool {
+        self.revoked.insert(key)
+    }
+
+    /// is_revoked. Returns true if `key` has been revoked.
+    /// # Arguments
+    /// * `key` - the key to look up.
+    pub fn is_revoked(&self, key: &RevocationKey) -> bool {
+        self.revoked.contains(key)
+    }
+
+    /// root. Returns the root of the list, which should be published to clients and verifiers.
+    pub fn root(&self) -> RevocationRoot {
+        self.revoked.root()
+    }
+
+    /// non_revocation_witness. This function returns a witness that `key` is not in the list,
+    /// or None if `key` has been revoked.
+    /// # Arguments
+    /// * `key` - the key of the client's token.
+    pub fn non_revocation_witness(&self, key: &RevocationKey) -> Option<NonRevocationWitness> {
+        self.revoked.exclusion_proof(key)
+    }
+}
+
+/// TagStore. This struct holds the ledger of double-spending tags that the server has seen in
+/// the Spend/Verify Protocol. Every `interval` tags, the store takes a checkpoint: a Merkle root
+/// over every tag recorded so far. The checkpoints can be published, so that third parties can
+/// audit the ledger by requesting inclusion or exclusion proofs of a tag against them.
+pub struct TagStore<B: BoomerangConfig> {
+    /// tags: the recorded tags, in the order they were recorded.
+    tags: Vec<ServerTag<B>>,
+    /// ids: the serial numbers of the recorded tags.
+    ids: Vec<<B as CurveConfig>::ScalarField>,
+    /// interval: the number of tags between two checkpoints.
+    interval: usize,
+    /// checkpoints: the number of tags that each checkpoint covers, alongside its root.
+    checkpoints: Vec<(usize, MerkleRoot)>,
+}
+
+impl<B: BoomerangConfig> TagStore<B> {
+    /// new. This function returns a new, empty, tag store that takes a checkpoint every `interval` tags.
+    /// This function panics if `interval` is zero.
+    /// # Arguments
+    /// * `interval` - the number of tags between two checkpoints.
+    pub fn new(interval: usize) -> Self {
+        assert!(interval > 0);
+        Self {
+            tags: Vec::new(),
+            ids: Vec::new(),
+            interval,
+            checkpoints: Vec::new(),
+        }
+    }
+
+    /// tag_key. Returns the key under which `tag` is held in the checkpoints.
+    /// # Arguments
+    /// * `tag` - the double-spending tag.
+    pub fn tag_key(tag: &<B as CurveConfig>::ScalarField) -> MerkleDigest {
+        merkle_hash(b"tag", &[&encode_scalar(tag, Endianness::Little)])
+    }
+
+    /// len. Returns the number of recorded tags.
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// is_empty. Returns true if no tag has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// record_spend. This function records the tag of the second message of the Spend/Verify
+    /// Protocol, and takes a checkpoint if one is due. This should be called once the message has
+    /// been accepted. Returns false if a tag with the same serial number was already recorded.
+    /// # Arguments
+    /// * `c_m` - the accepted client message.
+    /// * `s_state` - the server's state for the session of `c_m`.
+    pub fn record_spend(&mut self, c_m: &SpendVerifyM2<B>, s_state: &SpendVerifyStateS<B>) -> bool {
+        self.record_tag(ServerTag {
             tag: c_m.tag,
             id_0: c_m.id,
             r2: s_state.r2,
-        }; // TODO: this needs to be stored by the server and check regularly
+        })
+    }
 
-        let id_1 = <B as CurveConfig>::ScalarField::rand(rng);
-        let v2 = <B as CurveConfig>::ScalarField::zero();
-        let v3 = <B as CurveConfig>::ScalarField::zero();
-        let vals: Vec<<B as CurveConfig>::ScalarField> = vec![id_1, c_m.spend_state[0], v2, v3];
+    /// record_spend_multi. This function records the tag of every spent token of a
+    /// `SpendVerifyM2Multi`, as `record_spend` does for a single token. Returns false if a tag
+    /// with the same serial number as any of the spent tokens was already recorded.
+    /// # Arguments
+    /// * `c_m` - the accepted client message.
+    /// * `s_state` - the server's state for the session of `c_m`.
+    pub fn record_spend_multi(
+        &mut self,
+        c_m: &SpendVerifyM2Multi<B>,
+        s_state: &SpendVerifyStateS<B>,
+    ) -> bool {
+        let mut fresh = true;
+        for input in c_m.inputs.iter() {
+            fresh &= self.record_tag(ServerTag {
+                tag: input.tag,
+                id_0: input.id,
+                r2: s_state.r2,
+            });
+        }
+        fresh
+    }
 
-        let c1 = PedersenComm::new_multi_with_all_generators(&vals, rng, &c_m.gens);
+    /// record_transfer. This function records the tag of the sender's token in the second
+    /// message of the Transfer Protocol, as `record_spend` does for a spent token. Returns false
+    /// if a tag with the same serial number was already recorded.
+    /// # Arguments
+    /// * `c_m` - the accepted client message.
+    /// * `s_state` - the server's state for the session of `c_m`.
+    pub fn record_transfer(&mut self, c_m: &TransferM2<B>, s_state: &TransferStateS<B>) -> bool {
+        self.record_tag(ServerTag {
+            tag: c_m.tag,
+            id_0: c_m.id,
+            r2: s_state.r2,
+        })
+    }
 
-        // Compute rewards
-        let (reward_u64, reward) = match inner_product_to_u64::<B>(&c_m.spend_state, &policy_state)
+    /// record_tag. This function records `tag`, and takes a checkpoint if one is due. Returns
+    /// false if a tag with the same serial number was already recorded.
+    fn record_tag(&mut self, tag: ServerTag<B>) -> bool {
+        let fresh = !self.ids.contains(&tag.id_0);
+        self.ids.push(tag.id_0);
+        self.tags.push(tag);
+
+        if self.tags.len().is_multiple_of(self.interval) {
+            self.checkpoint();
+        }
+        fresh
+    }
+
+    /// snapshot. Returns the set of tags that are covered by the checkpoint with index `epoch`.
+    fn snapshot(&self, epoch: usize, len: usize) -> MerkleSet {
+        let mut set = MerkleSet::new(epoch as u64);
+        for tag in self.tags[..len].iter() {
+            set.insert(Self::tag_key(&tag.tag));
+        }
+        set
+    }
+
+    /// checkpoint. This function takes a checkpoint of every tag recorded so far, and returns its root.
+    pub fn checkpoint(&mut self) -> MerkleRoot {
+        let root = self
+            .snapshot(self.checkpoints.len(), self.tags.len())
+            .root();
+        self.checkpoints.push((self.tags.len(), root));
+        root
+    }
+
+    /// checkpoints. Returns the root of every checkpoint, in the order they were taken.
+    pub fn checkpoints(&self) -> Vec<MerkleRoot> {
+        self.checkpoints.iter().map(|(_, root)| *root).collect()
+    }
+
+    /// inclusion_proof. This function returns a proof that `tag` is covered by the checkpoint with
+    /// index `epoch`, or None if there is no such checkpoint or it does not cover `tag`.
+    /// # Arguments
+    /// * `epoch` - the index of the checkpoint.
+    /// * `tag` - the double-spending tag.
+    pub fn inclusion_proof(
+        &self,
+        epoch: usize,
+        tag: &<B as CurveConfig>::ScalarField,
+    ) -> Option<MerklePath> {
+        let (len, _) = self.checkpoints.get(epoch)?;
+        self.snapshot(epoch, *len)
+            .inclusion_proof(&Self::tag_key(tag))
+    }
+
+    /// exclusion_proof. This function returns a proof that `tag` is not covered by the checkpoint
+    /// with index `epoch`, or None if there is no such checkpoint or it covers `tag`.
+    /// # Arguments
+    /// * `epoch` - the index of the checkpoint.
+    /// * `tag` - the double-spending tag.
+    pub fn exclusion_proof(
+        &self,
+        epoch: usize,
+        tag: &<B as CurveConfig>::ScalarField,
+    ) -> Option<ExclusionProof> {
+        let (len, _) = self.checkpoints.get(epoch)?;
+        self.snapshot(epoch, *len)
+            .exclusion_proof(&Self::tag_key(tag))
+    }
+}
+
+/// Job. A unit of work that is run by a `VerificationPool` worker.
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// JobSlot. The state that is shared between a queued job and its `VerificationFuture`.
+struct JobSlot<R> {
+    /// result: the result of the job, once it has run.
+    result: Option<R>,
+    /// waker: the waker of the task that last polled the future, if any.
+    waker: Option<Waker>,
+}
+
+/// VerificationFuture. This struct is returned for every job that is queued on a
+/// `VerificationPool`. It resolves to the result of the job once a worker has run it,
+/// and can either be awaited from an async frontend or waited on from a plain thread.
+pub struct VerificationFuture<R> {
+    /// slot: the slot that the worker writes the result into.
+    slot: Arc<(Mutex<JobSlot<R>>, Condvar)>,
+}
+
+impl<R> VerificationFuture<R> {
+    /// wait. This function blocks the calling thread until the job has run, and
+    /// returns its result.
+    pub fn wait(self) -> R {
+        let (lock, cvar) = &*self.slot;
+        let mut slot = lock.lock().unwrap();
+        loop {
+            if let Some(result) = slot.result.take() {
+                return result;
+            }
+            slot = cvar.wait(slot).unwrap();
+        }
+    }
+}
+
+impl<R> Future for VerificationFuture<R> {
+    type Output = R;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<R> {
+        let mut slot = self.slot.0.lock().unwrap();
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// VerificationPool. This struct queues incoming verification jobs onto a fixed set of
+/// worker threads, so that request threads do not block on proof verification.
+/// The queue is bounded: once `capacity` jobs are waiting, `submit` blocks and `try_submit`
+/// hands the message back, which gives the frontend a way to apply backpressure.
+/// Dropping the pool lets the workers finish every queued job before they exit.
+pub struct VerificationPool<B: BoomerangConfig> {
+    /// key_pair: the server's keypair, shared by every worker.
+    key_pair: Arc<ServerKeyPair<B>>,
+    /// sender: the sending side of the job queue.
+    sender: Option<SyncSender<Job>>,
+    /// workers: the worker threads.
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl<B: BoomerangConfig + 'static> VerificationPool<B> {
+    /// new. This function starts a new pool with `num_workers` worker threads and room for
+    /// `capacity` waiting jobs.
+    /// # Arguments
+    /// * `key_pair` - the server's keypair.
+    /// * `num_workers` - the number of worker threads. Must be non-zero.
+    /// * `capacity` - the number of jobs that can wait in the queue.
+    pub fn new(key_pair: ServerKeyPair<B>, num_workers: usize, capacity: usize) -> Self {
+        assert!(num_workers > 0, "VerificationPool: no workers");
+
+        let (sender, receiver) = mpsc::sync_channel::<Job>(capacity);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..num_workers)
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                thread::spawn(move || Self::run_worker(&receiver))
+            })
+            .collect();
+
+        Self {
+            key_pair: Arc::new(key_pair),
+            sender: Some(sender),
+            workers,
+        }
+    }
+
+    /// run_worker. This function runs jobs from `receiver` until the pool is dropped.
+    fn run_worker(receiver: &Mutex<Receiver<Job>>) {
+        loop {
+            // The lock is released before the job runs, so that other workers can pick up jobs.
+            let job = receiver.lock().unwrap().recv();
+            match job {
+                Ok(job) => job(),
+                Err(_) => return,
+            }
+        }
+    }
+
+    /// make_job. This function wraps `f` into a job, and returns the job alongside the future
+    /// that resolves to its result. If `f` panics, the future resolves to `Err`.
+    fn make_job<F, R>(f: F) -> (Job, VerificationFuture<Result<R, &'static str>>)
+    where
+        F: FnOnce() -> Result<R, &'static str> + Send + 'static,
+        R: Send + 'static,
+    {
+        let slot = Arc::new((
+            Mutex::new(JobSlot {
+                result: None,
+                waker: None,
+            }),
+            Condvar::new(),
+        ));
+
+        let worker_slot = Arc::clone(&slot);
+        let job: Job = Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(f))
+                .unwrap_or(Err("Boomerang verification pool: job panicked"));
+
+            let (lock, cvar) = &*worker_slot;
+            let mut slot = lock.lock().unwrap();
+            slot.result = Some(result);
+            if let Some(waker) = slot.waker.take() {
+                waker.wake();
+            }
+            cvar.notify_all();
+        });
+
+        (job, VerificationFuture { slot })
+    }
+
+    /// spawn. This function queues the verification job `f`, blocking while the queue is full.
+    /// # Arguments
+    /// * `f` - the job. This should return the reason for a failed check, if any.
+    pub fn spawn<F, R>(&self, f: F) -> VerificationFuture<Result<R, &'static str>>
+    where
+        F: FnOnce() -> Result<R, &'static str> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (job, future) = Self::make_job(f);
+        self.sender
+            .as_ref()
+            .unwrap()
+            .send(job)
+            .expect("VerificationPool: workers have stopped");
+        future
+    }
+
+    /// try_spawn. This function queues the verification job `f`, or returns None if the
+    /// queue is full.
+    /// # Arguments
+    /// * `f` - the job. This should return the reason for a failed check, if any.
+    pub fn try_spawn<F, R>(&self, f: F) -> Option<VerificationFuture<Result<R, &'static str>>>
+    where
+        F: FnOnce() -> Result<R, &'static str> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (job, future) = Self::make_job(f);
+        match self.sender.as_ref().unwrap().try_send(job) {
+            Ok(()) => Some(future),
+            Err(TrySendError::Full(_)) => None,
+            Err(TrySendError::Disconnected(_)) => panic!("VerificationPool: workers have stopped"),
+        }
+    }
+
+    /// submit. This function queues the verification of the second message of the Spend/Verify
+    /// Protocol (see `SpendVerifyStateS::verify_spendverify_m2`), blocking while the queue is full.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    pub fn submit(&self, c_m: SpendVerifyM2<B>) -> VerificationFuture<Result<(), &'static str>> {
+        let key_pair = Arc::clone(&self.key_pair);
+        self.spawn(move || SpendVerifyStateS::verify_spendverify_m2(&c_m, &key_pair))
+    }
+
+    /// try_submit. This function queues the verification of the second message of the Spend/Verify
+    /// Protocol, or hands `c_m` back if the queue is full.
+    /// # Arguments
+    /// * `c_m` - the received client message.
+    pub fn try_submit(
+        &self,
+        c_m: SpendVerifyM2<B>,
+    ) -> Result<VerificationFuture<Result<(), &'static str>>, Box<SpendVerifyM2<B>>> {
+        let key_pair = Arc::clone(&self.key_pair);
+        let c_m = Arc::new(c_m);
+        let job_c_m = Arc::clone(&c_m);
+        match self.try_spawn(move || SpendVerifyStateS::verify_spendverify_m2(&job_c_m, &key_pair))
         {
-            Ok(reward_u64) => reward_u64,
-            Err(_e) => {
-                panic!("Boomerang verification: failed to compute reward")
+            Some(future) => Ok(future),
+            // The rejected job has already been dropped, along with its reference to the message.
+            None => match Arc::try_unwrap(c_m) {
+                Ok(c_m) => Err(Box::new(c_m)),
+                Err(_) => unreachable!("VerificationPool: rejected job is still alive"),
+            },
+        }
+    }
+}
+
+impl<B: BoomerangConfig> Drop for VerificationPool<B> {
+    fn drop(&mut self) {
+        // Closing the queue stops each worker once the remaining jobs have run.
+        drop(self.sender.take());
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// SUPPORTED_CURVES. The names of the curves that a server can be configured with, i.e the
+/// names of every `CurveId`.
+pub const SUPPORTED_CURVES: &[&str] = &[
+    CurveId::T256.name(),
+    CurveId::T384.name(),
+    CurveId::T521.name(),
+    CurveId::TSecp256k1.name(),
+    CurveId::TSecq256k1.name(),
+];
+
+/// RateLimit. This struct holds the per-IP rate limit that a server layer should enforce, i.e
+/// at most `requests` requests from the same address in any window of `window_secs` seconds.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    /// requests: the number of requests that are allowed in a window.
+    pub requests: u32,
+    /// window_secs: the length of the window, in seconds.
+    pub window_secs: u64,
+}
+
+/// ServerConfig. This struct holds the deployment settings of a boomerang server, i.e the
+/// settings that are not part of the protocols themselves. A config is read from TOML, from
+/// `BOOMERANG_*` environment variables, or from both, and is validated before it is returned.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// curve: the name of the curve that the server runs on. One of `SUPPORTED_CURVES`.
+    pub curve: String,
+    /// http_port: the port that plain HTTP requests are accepted (and redirected) on.
+    pub http_port: u16,
+    /// https_port: the port that the protocol messages are accepted on.
+    pub https_port: u16,
+    /// policy: the policy vector, i.e the reward of each action that a token can count.
+    pub policy: Vec<u64>,
+    /// max_catalog_size: the maximum number of actions, i.e the maximum length of `policy`.
+    pub max_catalog_size: usize,
+    /// max_request_size: the maximum size of a request body, in bytes.
+    pub max_request_size: usize,
+    /// rate_limit: the optional per-IP rate limit.
+    pub rate_limit: Option<RateLimit>,
+    /// key_path: the optional path of the serialized server keypair.
+    pub key_path: Option<PathBuf>,
+    /// tls_cert_path: the optional path of the PEM encoded TLS certificate.
+    pub tls_cert_path: Option<PathBuf>,
+    /// tls_key_path: the optional path of the PEM encoded TLS private key.
+    pub tls_key_path: Option<PathBuf>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            curve: "tsecp256k1".to_string(),
+            http_port: 7878,
+            https_port: 3000,
+            policy: vec![2],
+            max_catalog_size: 64,
+            max_request_size: 1 << 20,
+            rate_limit: None,
+            key_path: None,
+            tls_cert_path: None,
+            tls_key_path: None,
+        }
+    }
+}
+
+/// ServerConfigError. This enum describes why a server config was rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ServerConfigError {
+    /// Io: the config file could not be read.
+    Io(String),
+    /// Parse: the config is not valid TOML, or has unknown or mistyped keys.
+    Parse(String),
+    /// Env: an environment variable does not hold a valid value.
+    Env(String),
+    /// UnsupportedCurve: the curve is not one of `SUPPORTED_CURVES`.
+    UnsupportedCurve(String),
+    /// InvalidPorts: a port is zero, or both ports are the same.
+    InvalidPorts,
+    /// EmptyPolicy: the policy has no actions.
+    EmptyPolicy,
+    /// CatalogTooLarge: the policy has more actions than `max_catalog_size`.
+    CatalogTooLarge {
+        /// len: the number of actions in the policy.
+        len: usize,
+        /// max: the maximum number of actions.
+        max: usize,
+    },
+    /// InvalidLimit: a size or rate limit is zero.
+    InvalidLimit(&'static str),
+    /// IncompleteTls: only one of the TLS certificate and key paths is set.
+    IncompleteTls,
+}
+
+impl fmt::Display for ServerConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerConfigError::Io(e) => write!(f, "Boomerang config: cannot read config: {}", e),
+            ServerConfigError::Parse(e) => write!(f, "Boomerang config: invalid config: {}", e),
+            ServerConfigError::Env(var) => {
+                write!(f, "Boomerang config: invalid value for {}", var)
+            }
+            ServerConfigError::UnsupportedCurve(curve) => {
+                write!(f, "Boomerang config: unsupported curve {}", curve)
+            }
+            ServerConfigError::InvalidPorts => {
+                write!(f, "Boomerang config: ports must be non-zero and distinct")
+            }
+            ServerConfigError::EmptyPolicy => write!(f, "Boomerang config: empty policy"),
+            ServerConfigError::CatalogTooLarge { len, max } => write!(
+                f,
+                "Boomerang config: policy has {} actions, but at most {} are allowed",
+                len, max
+            ),
+            ServerConfigError::InvalidLimit(limit) => {
+                write!(f, "Boomerang config: {} must be non-zero", limit)
+            }
+            ServerConfigError::IncompleteTls => write!(
+                f,
+                "Boomerang config: the TLS certificate and key paths must be set together"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ServerConfigError {}
+
+impl ServerConfig {
+    /// from_toml_str. This function parses and validates a config written in TOML. Missing keys
+    /// take their default value.
+    /// # Arguments
+    /// * `toml` - the config.
+    pub fn from_toml_str(toml: &str) -> Result<Self, ServerConfigError> {
+        let config: Self =
+            toml::from_str(toml).map_err(|e| ServerConfigError::Parse(e.message().to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// from_toml_file. This function reads, parses and validates the TOML config at `path`.
+    /// # Arguments
+    /// * `path` - the path of the config.
+    pub fn from_toml_file(path: impl AsRef<Path>) -> Result<Self, ServerConfigError> {
+        let toml =
+            std::fs::read_to_string(path).map_err(|e| ServerConfigError::Io(e.to_string()))?;
+        Self::from_toml_str(&toml)
+    }
+
+    /// load. This function reads the TOML config at the path in the `BOOMERANG_CONFIG`
+    /// environment variable, if it is set, and starts from the default config otherwise. The
+    /// settings are then overridden by the `BOOMERANG_*` environment variables (see `apply_env`),
+    /// and the result is validated.
+    pub fn load() -> Result<Self, ServerConfigError> {
+        let mut config: Self = match std::env::var_os("BOOMERANG_CONFIG") {
+            Some(path) => {
+                let toml = std::fs::read_to_string(path)
+                    .map_err(|e| ServerConfigError::Io(e.to_string()))?;
+                toml::from_str(&toml)
+                    .map_err(|e| ServerConfigError::Parse(e.message().to_string()))?
             }
+            None => Self::default(),
         };
+        config.apply_env(std::env::vars())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// apply_env. This function overrides the settings of this config with the `BOOMERANG_*`
+    /// variables in `vars`, and ignores every other variable. The variables are named after the
+    /// settings, e.g `BOOMERANG_HTTPS_PORT`; the policy is a comma separated list, and the rate
+    /// limit is set through `BOOMERANG_RATE_LIMIT_REQUESTS` and `BOOMERANG_RATE_LIMIT_WINDOW_SECS`.
+    /// This function does not validate the result.
+    /// # Arguments
+    /// * `vars` - the (name, value) pairs of the environment.
+    pub fn apply_env(
+        &mut self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<(), ServerConfigError> {
+        fn parse<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, ServerConfigError> {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| ServerConfigError::Env(name.to_string()))
+        }
 
-        let re_proof =
-            match BRewardsProof::prove(&c_m.spend_state, &policy_state, reward_u64, reward, rng) {
-                Ok(proof) => proof,
-                Err(_e) => {
-                    panic!("Boomerang verification: failed to create rewards proof")
+        let mut requests = self.rate_limit.as_ref().map(|r| r.requests);
+        let mut window_secs = self.rate_limit.as_ref().map(|r| r.window_secs);
+
+        for (name, value) in vars {
+            match name.as_str() {
+                "BOOMERANG_CURVE" => self.curve = value.trim().to_string(),
+                "BOOMERANG_HTTP_PORT" => self.http_port = parse(&name, &value)?,
+                "BOOMERANG_HTTPS_PORT" => self.https_port = parse(&name, &value)?,
+                "BOOMERANG_POLICY" => {
+                    self.policy = value
+                        .split(',')
+                        .map(|action| parse(&name, action))
+                        .collect::<Result<_, _>>()?
                 }
-            };
+                "BOOMERANG_MAX_CATALOG_SIZE" => self.max_catalog_size = parse(&name, &value)?,
+                "BOOMERANG_MAX_REQUEST_SIZE" => self.max_request_size = parse(&name, &value)?,
+                "BOOMERANG_RATE_LIMIT_REQUESTS" => requests = Some(parse(&name, &value)?),
+                "BOOMERANG_RATE_LIMIT_WINDOW_SECS" => window_secs = Some(parse(&name, &value)?),
+                "BOOMERANG_KEY_PATH" => self.key_path = Some(PathBuf::from(value)),
+                "BOOMERANG_TLS_CERT_PATH" => self.tls_cert_path = Some(PathBuf::from(value)),
+                "BOOMERANG_TLS_KEY_PATH" => self.tls_key_path = Some(PathBuf::from(value)),
+                _ => {}
+            }
+        }
 
-        // Only if the rewards proof was successfully done
-        let c = c_m.comm - c1; // The other way around to handle the negative
-        let sig_comm = SigComm::commit(&key_pair.s_key_pair, rng, c.comm);
-        s_state.sig_commit = sig_comm;
+        self.rate_limit = match (requests, window_secs) {
+            (None, None) => None,
+            (Some(requests), Some(window_secs)) => Some(RateLimit {
+                requests,
+                window_secs,
+            }),
+            (None, _) => {
+                return Err(ServerConfigError::Env(
+                    "BOOMERANG_RATE_LIMIT_REQUESTS".into(),
+                ))
+            }
+            (_, None) => {
+                return Err(ServerConfigError::Env(
+                    "BOOMERANG_RATE_LIMIT_WINDOW_SECS".into(),
+                ))
+            }
+        };
 
-        SpendVerifyM3 {
-            id_1,
-            val: c_m.spend_state[0],
-            comm: c1,
-            sig_commit: sig_comm,
-            verifying_key: key_pair.s_key_pair.verifying_key,
-            tag_key: key_pair.s_key_pair.tag_key,
-            pi_reward: re_proof,
-        }
+        Ok(())
     }
 
-    pub fn generate_spendverify_m5(
-        c_m: &SpendVerifyM4<B>,
-        s_state: &mut SpendVerifyStateS<B>,
-        key_pair: &ServerKeyPair<B>,
-    ) -> SpendVerifyM5<B> {
-        let sig_resp = SigResp::respond(&key_pair.s_key_pair, &s_state.sig_commit, &c_m.e);
+    /// curve_id. This function returns the id of the curve that the server runs on. This
+    /// function fails if `curve` is not one of `SUPPORTED_CURVES`.
+    pub fn curve_id(&self) -> Result<CurveId, ServerConfigError> {
+        self.curve
+            .parse()
+            .map_err(|_| ServerConfigError::UnsupportedCurve(self.curve.clone()))
+    }
+
+    /// validate. This function checks that the settings of this config are consistent, and
+    /// returns the first problem that it finds.
+    pub fn validate(&self) -> Result<(), ServerConfigError> {
+        self.curve_id()?;
+
+        if self.http_port == 0 || self.https_port == 0 || self.http_port == self.https_port {
+            return Err(ServerConfigError::InvalidPorts);
+        }
+
+        if self.max_catalog_size == 0 {
+            return Err(ServerConfigError::InvalidLimit("max_catalog_size"));
+        }
+
+        if self.policy.is_empty() {
+            return Err(ServerConfigError::EmptyPolicy);
+        }
+
+        if self.policy.len() > self.max_catalog_size {
+            return Err(ServerConfigError::CatalogTooLarge {
+                len: self.policy.len(),
+                max: self.max_catalog_size,
+            });
+        }
+
+        if self.max_request_size == 0 {
+            return Err(ServerConfigError::InvalidLimit("max_request_size"));
+        }
+
+        if let Some(rate_limit) = &self.rate_limit {
+            if rate_limit.requests == 0 {
+                return Err(ServerConfigError::InvalidLimit("rate_limit.requests"));
+            }
+            if rate_limit.window_secs == 0 {
+                return Err(ServerConfigError::InvalidLimit("rate_limit.window_secs"));
+            }
+        }
+
+        if self.tls_cert_path.is_some() != self.tls_key_path.is_some() {
+            return Err(ServerConfigError::IncompleteTls);
+        }
+
+        Ok(())
+    }
 
-        SpendVerifyM5 { s: sig_resp }
+    /// policy_state. Returns the policy as the policy state of the Spend/Verify Protocol.
+    pub fn policy_state<B: BoomerangConfig>(&self) -> Vec<<B as CurveConfig>::ScalarField> {
+        self.policy
+            .iter()
+            .map(|&action| <B as CurveConfig>::ScalarField::from(action))
+            .collect()
     }
 }
@@ -0,0 +1,320 @@
+//!
+//! Module containing the exact compressed sizes of the protocol messages.
+//!
+//! The sizes are those of the compressed arkworks encoding of every message, i.e the bytes that
+//! `serialize_compressed` writes, which is the payload that a transport carries. They only
+//! depend on the curve and on the length of the spend state, not on the values in the messages,
+//! so they can be used to size HTTP limits and data budgets up front. Other encodings add to
+//! them, e.g. the `json` module adds the base64url and envelope overhead.
+//!
+//! The sizes are measured on one run of every protocol, with a seeded RNG, the first time that
+//! they are asked for, and are then cached per curve and state length. The sizes of the
+//! spend/verify messages can also be counted from the number of points and scalars in them,
+//! without running the protocol, with `spend_verify_sizes`.
+//!
+
+use ark_ec::{models::CurveConfig, short_weierstrass as sw, AffineRepr};
+use ark_ff::Zero;
+use ark_serialize::CanonicalSerialize;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use crate::client::{
+    CollectionStateC, IssuanceStateC, SpendVerifyStateC, TransferStateC, UKeyPair, UpdateStateC,
+};
+use crate::config::{BoomerangConfig, SessionId, BASE_ATTRIBUTES};
+use crate::server::{
+    CollectionStateS, IssuanceStateS, ServerKeyPair, SpendVerifyStateS, TransferStateS,
+    UpdateStateS,
+};
+use crate::utils::rewards::PolicyId;
+
+/// LEN_PREFIX. The size of the length that prefixes every vector in the compressed encoding.
+const LEN_PREFIX: usize = 8;
+
+/// TAG_COMMITS. The number of commitments of the tag proof in a spend/verify m2.
+const TAG_COMMITS: usize = 5;
+
+/// MessageSizes. This struct holds the compressed size, in bytes, of every protocol message,
+/// and of the largest proofs within them. The messages of each protocol are listed in order,
+/// i.e `issuance[0]` is the size of an `IssuanceM1`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MessageSizes {
+    /// spend_state_len: the length of the spend state that the spend/verify sizes are for.
+    pub spend_state_len: usize,
+    /// issuance: the sizes of the issuance messages m1..m4.
+    pub issuance: [usize; 4],
+    /// collection: the sizes of the collection messages m1..m5.
+    pub collection: [usize; 5],
+    /// update: the sizes of the update messages m1..m5.
+    pub update: [usize; 5],
+    /// spend_verify: the sizes of the spend/verify messages m1..m5.
+    pub spend_verify: [usize; 5],
+    /// spend_verify_ack: the size of the `SpendVerifyAck` that replaces m3 on the fast path.
+    pub spend_verify_ack: usize,
+    /// transfer_request: the size of the `TransferRequest` that the recipient sends to the
+    /// sender of a transfer.
+    pub transfer_request: usize,
+    /// transfer: the sizes of the transfer messages m1..m5.
+    pub transfer: [usize; 5],
+    /// sig_proof: the size of the proof of a signature on the spent token, as in m2.
+    pub sig_proof: usize,
+    /// sub_proof: the size of the range proof on the spent value, as in a spend/verify m2.
+    pub sub_proof: usize,
+    /// rewards_proof: the size of the rewards proof, as in a spend/verify m3.
+    pub rewards_proof: usize,
+}
+
+impl MessageSizes {
+    /// max. This function returns the size of the largest message, e.g. for a request limit
+    /// that every message fits in.
+    pub fn max(&self) -> usize {
+        self.issuance
+            .iter()
+            .chain(&self.collection)
+            .chain(&self.update)
+            .chain(&self.spend_verify)
+            .chain(&self.transfer)
+            .chain([&self.spend_verify_ack, &self.transfer_request])
+            .copied()
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// SIZES. The sizes that were measured so far, keyed by the type of the curve and the length
+/// of the spend state.
+static SIZES: OnceLock<Mutex<HashMap<(TypeId, usize), MessageSizes>>> = OnceLock::new();
+
+/// sizes. This function returns the compressed size of every message and proof on the curve
+/// `B`, for a spend state of a single value.
+pub fn sizes<B: BoomerangConfig>() -> MessageSizes {
+    sizes_for_state::<B>(1)
+}
+
+/// sizes_for_state. This function returns the compressed size of every message and proof on the
+/// curve `B`, for a spend state of `spend_state_len` values. Only the spend/verify messages
+/// and the rewards proof depend on it. This function panics if `spend_state_len` is not a
+/// power of two, as the rewards proof needs.
+/// # Arguments
+/// * `spend_state_len` - the length of the spend and policy vectors.
+pub fn sizes_for_state<B: BoomerangConfig>(spend_state_len: usize) -> MessageSizes {
+    assert!(
+        spend_state_len.is_power_of_two(),
+        "Boomerang sizes: the spend state length must be a power of two"
+    );
+    let key = (TypeId::of::<B>(), spend_state_len);
+    if let Some(sizes) = SIZES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .get(&key)
+    {
+        return *sizes;
+    }
+
+    // The lock is not held while measuring, so that other curves are not held up. Two threads
+    // may then measure the same sizes, which are equal.
+    let sizes = measure::<B>(spend_state_len);
+    SIZES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .insert(key, sizes);
+    sizes
+}
+
+/// spend_verify_sizes. This function returns the compressed sizes of the spend/verify messages
+/// m1..m5 on the curve `B`, for a spend state of `spend_state_len` values, along with the size
+/// of the `SpendVerifyAck`. Unlike `sizes_for_state`, this function does not run the protocol:
+/// it counts the points, scalars and vectors in every message, for a token without
+/// attributes. This function panics if `spend_state_len` is not a power of two, as the rewards
+/// proof needs.
+/// # Arguments
+/// * `spend_state_len` - the length of the spend and policy vectors.
+pub fn spend_verify_sizes<B: BoomerangConfig>(spend_state_len: usize) -> ([usize; 5], usize) {
+    assert!(
+        spend_state_len.is_power_of_two(),
+        "Boomerang sizes: the spend state length must be a power of two"
+    );
+    let point = sw::Affine::<B>::generator().compressed_size();
+    let scalar = <B as CurveConfig>::ScalarField::zero().compressed_size();
+    let session_id = core::mem::size_of::<SessionId>();
+    let vector = |len: usize, item: usize| LEN_PREFIX + len * item;
+
+    // The Pedersen parts of m2: a token is committed to with a generator per value, plus one
+    // for the blinding factor.
+    let comm = point + scalar;
+    let gens = vector(BASE_ATTRIBUTES + 1, point);
+    let opening = point + scalar + vector(BASE_ATTRIBUTES, scalar);
+    let add_mul = 6 * point + 9 * scalar;
+
+    // The bulletproofs: the range proofs are on 64-bit values, and the inner-product and
+    // linear arguments halve their vectors every round.
+    let rounds = |n: usize| n.trailing_zeros() as usize;
+    let inner_product = 2 * vector(rounds(u64::BITS as usize), point) + 2 * scalar;
+    let range = 4 * point + 3 * scalar + inner_product;
+    let linear = 2 * vector(rounds(spend_state_len), point) + point + 2 * scalar;
+    let sub = range + point;
+    let rewards = range + point + linear + point;
+
+    // The ACL parts: the SigProof has an equality proof and a commitment per value that is
+    // not the secret key.
+    let sig = (2 * point + 6 * scalar) + 2 * scalar;
+    let sig_proof = point
+        + 2 * scalar
+        + 3 * scalar
+        + vector(BASE_ATTRIBUTES - 1, 2 * scalar)
+        + vector(BASE_ATTRIBUTES - 1, point)
+        + point;
+    let sig_comm = 4 * point + 5 * scalar;
+    let sig_chall = 3 * point + 9 * scalar;
+    let sig_resp = 5 * scalar;
+
+    let m1 = scalar + session_id;
+    let m2 = 2 * comm
+        + 2 * gens
+        + 2 * opening
+        + add_mul
+        + sub
+        + 2 * scalar
+        + sig
+        + sig_proof
+        + vector(TAG_COMMITS, comm)
+        + vector(spend_state_len, scalar)
+        + session_id;
+    let ack = comm + sig_comm + 2 * scalar + 2 * point + session_id;
+    let m3 = ack + rewards + 2 * scalar + core::mem::size_of::<PolicyId>();
+    let m4 = sig_chall + session_id;
+    let m5 = sig_resp + session_id;
+    ([m1, m2, m3, m4, m5], ack)
+}
+
+/// measure. This function runs every protocol once and measures its messages.
+fn measure<B: BoomerangConfig>(spend_state_len: usize) -> MessageSizes {
+    let rng = &mut ChaCha20Rng::seed_from_u64(0);
+    let value = <B as CurveConfig>::ScalarField::from(1u64);
+
+    let kp = UKeyPair::<B>::generate(rng);
+    let recipient = UKeyPair::<B>::generate(rng);
+    let skp = ServerKeyPair::<B>::generate(rng);
+
+    // Issuance
+    let mut i_state = IssuanceStateC::<B>::default();
+    let mut s_i_state = IssuanceStateS::<B>::default();
+    let i_m1 = IssuanceStateC::generate_issuance_m1(&kp, &mut i_state, rng);
+    let i_m2 = IssuanceStateS::generate_issuance_m2(&i_m1, &skp, &mut s_i_state, rng);
+    let i_m3 = IssuanceStateC::generate_issuance_m3(&i_m2, &mut i_state, rng);
+    let i_m4 = IssuanceStateS::generate_issuance_m4(&i_m3, &mut s_i_state, &skp);
+    let state = IssuanceStateC::populate_state(&i_m4, &mut i_state, &skp, &kp);
+
+    // Collection
+    let mut c_state = CollectionStateC::<B>::default();
+    let mut s_c_state = CollectionStateS::<B>::default();
+    let c_m1 = CollectionStateS::<B>::generate_collection_m1(rng, &mut s_c_state);
+    let c_m2 = CollectionStateC::generate_collection_m2(rng, &state, &c_m1, &mut c_state, &skp);
+    let c_m3 = CollectionStateS::generate_collection_m3(rng, &c_m2, &mut s_c_state, &skp, value)
+        .expect("Boomerang sizes: collection failed");
+    let c_m4 = CollectionStateC::generate_collection_m4(rng, &mut c_state, &c_m3);
+    let c_m5 = CollectionStateS::generate_collection_m5(&c_m4, &mut s_c_state, &skp);
+    let state = CollectionStateC::populate_state(&mut c_state, &c_m5, &skp, &kp);
+
+    // Update, on a token without attributes.
+    let mut u_state = UpdateStateC::<B>::default();
+    let mut s_u_state = UpdateStateS::<B>::default();
+    let u_m1 = UpdateStateS::<B>::generate_update_m1(rng, &mut s_u_state, BASE_ATTRIBUTES);
+    let u_m2 = UpdateStateC::generate_update_m2(rng, &state, &u_m1, &mut u_state, &skp);
+    let u_m3 = UpdateStateS::generate_update_m3(rng, &u_m2, &mut s_u_state, &skp, value)
+        .expect("Boomerang sizes: update failed");
+    let u_m4 = UpdateStateC::generate_update_m4(rng, &mut u_state, &u_m3);
+    let u_m5 = UpdateStateS::generate_update_m5(&u_m4, &mut s_u_state, &skp);
+
+    // Spending
+    let spend_state = vec![value; spend_state_len];
+    let mut sv_state = SpendVerifyStateC::<B>::default();
+    let mut s_sv_state = SpendVerifyStateS::<B>::default();
+    let s_m1 = SpendVerifyStateS::<B>::generate_spendverify_m1(rng, &mut s_sv_state);
+    let s_m2 = SpendVerifyStateC::generate_spendverify_m2(
+        rng,
+        &state,
+        &mut sv_state,
+        &s_m1,
+        &skp,
+        &spend_state,
+    );
+    let s_m3 = SpendVerifyStateS::generate_spendverify_m3(
+        rng,
+        &s_m2,
+        &mut s_sv_state,
+        &skp,
+        spend_state.clone(),
+    )
+    .expect("Boomerang sizes: spend-verify failed");
+    let s_m4 = SpendVerifyStateC::generate_spendverify_m4(
+        rng,
+        &mut sv_state,
+        &s_m3,
+        &skp.policy_id(&spend_state),
+    )
+    .expect("Boomerang sizes: spend-verify failed");
+    let s_m5 = SpendVerifyStateS::generate_spendverify_m5(&s_m4, &mut s_sv_state, &skp);
+
+    // Transfer
+    let mut t_state = TransferStateC::<B>::default();
+    let mut s_t_state = TransferStateS::<B>::default();
+    let t_m1 = TransferStateS::<B>::generate_transfer_m1(rng, &mut s_t_state);
+    let request = TransferStateC::generate_transfer_request(rng, &recipient, &mut t_state, &t_m1);
+    let (t_m2, opening) = TransferStateC::generate_transfer_m2(rng, &state, &t_m1, &request, &skp)
+        .expect("Boomerang sizes: transfer failed");
+    let t_m3 = TransferStateS::generate_transfer_m3(rng, &t_m2, &mut s_t_state, &skp)
+        .expect("Boomerang sizes: transfer failed");
+    let t_m4 = TransferStateC::generate_transfer_m4(rng, &mut t_state, &t_m3, &opening)
+        .expect("Boomerang sizes: transfer failed");
+    let t_m5 = TransferStateS::generate_transfer_m5(&t_m4, &mut s_t_state, &skp);
+
+    MessageSizes {
+        spend_state_len,
+        issuance: [
+            i_m1.compressed_size(),
+            i_m2.compressed_size(),
+            i_m3.compressed_size(),
+            i_m4.compressed_size(),
+        ],
+        collection: [
+            c_m1.compressed_size(),
+            c_m2.compressed_size(),
+            c_m3.compressed_size(),
+            c_m4.compressed_size(),
+            c_m5.compressed_size(),
+        ],
+        update: [
+            u_m1.compressed_size(),
+            u_m2.compressed_size(),
+            u_m3.compressed_size(),
+            u_m4.compressed_size(),
+            u_m5.compressed_size(),
+        ],
+        spend_verify: [
+            s_m1.compressed_size(),
+            s_m2.compressed_size(),
+            s_m3.compressed_size(),
+            s_m4.compressed_size(),
+            s_m5.compressed_size(),
+        ],
+        spend_verify_ack: s_m3.ack().compressed_size(),
+        transfer_request: request.compressed_size(),
+        transfer: [
+            t_m1.compressed_size(),
+            t_m2.compressed_size(),
+            t_m3.compressed_size(),
+            t_m4.compressed_size(),
+            t_m5.compressed_size(),
+        ],
+        sig_proof: s_m2.s_proof.compressed_size(),
+        sub_proof: s_m2.pi_4.compressed_size(),
+        rewards_proof: s_m3.pi_reward.compressed_size(),
+    }
+}
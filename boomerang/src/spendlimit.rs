@@ -0,0 +1,455 @@
+//! Defines per-epoch spend limits, i.e anonymous rate limiting of the Spend/Verify Protocol.
+//!
+//! A token can hold a spend counter as one of its attributes, which is added with the Update
+//! Protocol and starts at zero. Every spend that is limited moves the counter of the new
+//! token forward, into the window of the current epoch: with a limit of `L` spends per epoch,
+//! the window of epoch `e` holds the counters `e * L + 1` to `(e + 1) * L`. As the counter
+//! can only move forward, a token can be spent at most `L` times per epoch, and it starts over
+//! in the next epoch by jumping to the next window.
+//!
+//! A `SpendLimitProof` shows that the counter of the new token is in the window of the epoch,
+//! with two range proofs of the smallest bit size that holds `L`, and that it is larger than
+//! the counter of the spent token, with a 64-bit range proof. The proof does not reveal either
+//! counter, so the server only learns that the client is below the limit.
+
+use ark_bulletproofs::{msm, RangeProof};
+use ark_ec::{
+    models::CurveConfig,
+    short_weierstrass::{self as sw},
+    CurveGroup,
+};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{ops::Mul, UniformRand};
+use merlin::Transcript;
+
+use crate::clock::Clock;
+use crate::config::{
+    committed_len, session_transcript, BoomerangConfig, SessionId, BASE_ATTRIBUTES,
+};
+use crate::encoding::append_point;
+use crate::rng::BoomerangRng;
+use crate::utils::rewards::RewardsGenerators;
+use acl::config::ACLConfig;
+use pedersen::pedersen_config::{Generators, PedersenComm, PedersenConfig};
+use pedersen::transcript::{append_u64, challenge_bytes};
+
+/// SPEND_LIMIT_BITSIZES. The bit sizes that the window of an epoch can be proven with.
+const SPEND_LIMIT_BITSIZES: [usize; 4] = [8, 16, 32, 64];
+
+/// SpendLimit. This struct describes the public limit of a limited spend: the attribute
+/// that holds the spend counter, and the number of spends that are allowed per epoch.
+/// The fields are private so that every limit goes through `new`, which rejects a limit
+/// of zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpendLimit {
+    /// attr: the index of the counter among the committed values, as in `UpdateM1::attr`.
+    attr: usize,
+    /// limit: the number of spends per epoch.
+    limit: u64,
+}
+
+impl SpendLimit {
+    /// new. This function returns the limit of `limit` spends per epoch, counted in the
+    /// attribute at `attr`. This function panics if `attr` is one of the base attributes, or
+    /// if `limit` is zero.
+    /// # Arguments
+    /// * `attr` - the index of the counter among the committed values.
+    /// * `limit` - the number of spends per epoch.
+    pub fn new(attr: usize, limit: u64) -> Self {
+        assert!(
+            attr >= BASE_ATTRIBUTES,
+            "Boomerang spend limit: the counter cannot be a base attribute"
+        );
+        assert!(
+            limit > 0,
+            "Boomerang spend limit: the limit must not be zero"
+        );
+        Self { attr, limit }
+    }
+
+    /// attr. This function returns the index of the counter among the committed values.
+    pub fn attr(&self) -> usize {
+        self.attr
+    }
+
+    /// limit. This function returns the number of spends per epoch.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// window. This function returns the first and the last counter of the window of `epoch`,
+    /// or None if they do not fit in 64 bits.
+    /// # Arguments
+    /// * `epoch` - the epoch.
+    pub fn window(&self, epoch: u64) -> Option<(u64, u64)> {
+        let first = epoch.checked_mul(self.limit)?.checked_add(1)?;
+        let last = first.checked_add(self.limit - 1)?;
+        Some((first, last))
+    }
+
+    /// next_counter. This function returns the counter that the next spend in `epoch` moves
+    /// `counter` to, or None if the limit of the epoch is reached.
+    /// # Arguments
+    /// * `counter` - the counter of the token.
+    /// * `epoch` - the epoch.
+    pub fn next_counter(&self, counter: u64, epoch: u64) -> Option<u64> {
+        let (first, last) = self.window(epoch)?;
+        let next = counter.checked_add(1)?.max(first);
+        (next <= last).then_some(next)
+    }
+
+    /// next_counter_with_clock. This function returns the counter that the next spend moves
+    /// `counter` to, as in `next_counter`, in the current epoch of `clock`.
+    /// # Arguments
+    /// * `counter` - the counter of the token.
+    /// * `clock` - the source of the current time.
+    /// * `length` - the length of an epoch, in seconds.
+    pub fn next_counter_with_clock(
+        &self,
+        counter: u64,
+        clock: &impl Clock,
+        length: u64,
+    ) -> Option<u64> {
+        self.next_counter(counter, clock.epoch(length).value())
+    }
+
+    /// remaining. This function returns the number of spends that are left in `epoch` for a
+    /// token with `counter`.
+    /// # Arguments
+    /// * `counter` - the counter of the token.
+    /// * `epoch` - the epoch.
+    pub fn remaining(&self, counter: u64, epoch: u64) -> u64 {
+        match self.window(epoch) {
+            Some((first, last)) if counter < first => last - first + 1,
+            Some((_, last)) => last.saturating_sub(counter),
+            None => 0,
+        }
+    }
+
+    /// bitsize. Returns the smallest bit size of a range proof that holds every offset into a
+    /// window.
+    fn bitsize(&self) -> usize {
+        SPEND_LIMIT_BITSIZES
+            .into_iter()
+            .find(|n| *n == 64 || self.limit <= 1 << n)
+            .unwrap_or(64)
+    }
+}
+
+/// counter_value. Returns the counter that `x` holds, or None if it does not fit in 64 bits.
+/// # Arguments
+/// * `x` - the committed counter.
+pub fn counter_value<B: BoomerangConfig>(x: &<B as CurveConfig>::ScalarField) -> Option<u64> {
+    let bigint = x.into_bigint();
+    let limbs = bigint.as_ref();
+    limbs[1..].iter().all(|limb| *limb == 0).then(|| limbs[0])
+}
+
+/// SpendLimitProof. This struct holds the proof that the counter of the token in the
+/// commitment of a spend is in the window of the epoch, and that it is larger than the counter
+/// of the spent token.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct SpendLimitProof<B: BoomerangConfig> {
+    /// window_comm: the commitment to the offset of the new counter into the window.
+    window_comm: sw::Affine<B>,
+    /// step_comm: the commitment to the step from the old counter to the new one, minus one.
+    step_comm: sw::Affine<B>,
+    /// lower_proof: the range proof of the offset.
+    lower_proof: RangeProof<sw::Affine<B>>,
+    /// upper_proof: the range proof of the limit minus one minus the offset.
+    upper_proof: RangeProof<sw::Affine<B>>,
+    /// step_proof: the range proof of the step.
+    step_proof: RangeProof<sw::Affine<B>>,
+    /// alpha: the commitments of the proof to the spent token, the new token, the offset and
+    /// the step, in that order.
+    alpha: Vec<sw::Affine<B>>,
+    /// z: the responses for the values and the blinding factor of the spent token, then of the
+    /// new token, and for the blinding factors of the offset and of the step.
+    z: Vec<<B as CurveConfig>::ScalarField>,
+}
+
+impl<B: BoomerangConfig> Clone for SpendLimitProof<B> {
+    fn clone(&self) -> Self {
+        Self {
+            window_comm: self.window_comm,
+            step_comm: self.step_comm,
+            lower_proof: self.lower_proof.clone(),
+            upper_proof: self.upper_proof.clone(),
+            step_proof: self.step_proof.clone(),
+            alpha: self.alpha.clone(),
+            z: self.z.clone(),
+        }
+    }
+}
+
+/// SpendLimitStatement. The public values that a `SpendLimitProof` is about.
+pub(crate) struct SpendLimitStatement<'a, B: BoomerangConfig> {
+    /// session_id: the session that the proof is bound to.
+    pub session_id: &'a SessionId,
+    /// limit: the spend limit.
+    pub limit: &'a SpendLimit,
+    /// epoch: the epoch of the spend.
+    pub epoch: u64,
+    /// prev_comm: the commitment to the spent token.
+    pub prev_comm: &'a sw::Affine<B>,
+    /// prev_gens: the generators of the spent token.
+    pub prev_gens: &'a Generators<B>,
+    /// comm: the commitment to the new token.
+    pub comm: &'a sw::Affine<B>,
+    /// gens: the generators of the new token.
+    pub gens: &'a Generators<B>,
+}
+
+impl<B: BoomerangConfig> SpendLimitStatement<'_, B> {
+    /// transcript. Returns the transcript of a proof of this statement.
+    fn transcript(&self) -> Transcript {
+        let mut transcript = session_transcript::<B>(b"BoomerangSpendLimit", self.session_id);
+        append_u64(&mut transcript, b"attr", self.limit.attr as u64);
+        append_u64(&mut transcript, b"limit", self.limit.limit);
+        append_u64(&mut transcript, b"epoch", self.epoch);
+        append_point(&mut transcript, b"prev_comm", self.prev_comm);
+        append_point(&mut transcript, b"comm", self.comm);
+        transcript
+    }
+
+    /// relations. Returns the images of the responses `z` under the relations of the proof,
+    /// i.e the commitments to the spent token, to the new token, to the offset and to the step.
+    fn relations(&self, z: &[<B as CurveConfig>::ScalarField]) -> Vec<sw::Affine<B>> {
+        let prev_len = committed_len(self.prev_gens);
+        let len = committed_len(self.gens);
+        let (prev_z, z) = z.split_at(prev_len + 1);
+        let (next_z, blinds) = z.split_at(len + 1);
+        let h = <B as PedersenConfig>::GENERATOR2;
+        let pc_gens = RewardsGenerators::<B>::shared().pc_gens;
+        let counter = next_z[self.limit.attr];
+
+        let prev = msm::msm(&self.prev_gens.generators[..prev_len], &prev_z[..prev_len]).unwrap()
+            + h.mul(prev_z[prev_len]);
+        let next =
+            msm::msm(&self.gens.generators[..len], &next_z[..len]).unwrap() + h.mul(next_z[len]);
+        let window = pc_gens.B.mul(counter) + pc_gens.B_blinding.mul(blinds[0]);
+        let step =
+            pc_gens.B.mul(counter - prev_z[self.limit.attr]) + pc_gens.B_blinding.mul(blinds[1]);
+        vec![
+            prev.into_affine(),
+            next.into_affine(),
+            window.into_affine(),
+            step.into_affine(),
+        ]
+    }
+}
+
+impl<B: BoomerangConfig> SpendLimitProof<B> {
+    /// challenge. Returns the challenge of the proof, once the commitments `alpha` are appended
+    /// to `transcript`.
+    fn challenge(
+        transcript: &mut Transcript,
+        alpha: &[sw::Affine<B>],
+    ) -> <B as CurveConfig>::ScalarField {
+        for point in alpha {
+            append_point(transcript, b"alpha", point);
+        }
+
+        let mut buf = [0u8; 64];
+        challenge_bytes(transcript, b"challenge", &mut buf);
+        <B as ACLConfig>::make_challenge_from_buffer(&buf)
+    }
+
+    /// create. This function proves that the counter in `next_vals` is in the window of the
+    /// epoch of `st`, and that it is larger than the counter in `prev_vals`.
+    /// This function fails if it is not, or if either token does not hold the counter.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `st` - the statement.
+    /// * `prev` - the commitment to the spent token.
+    /// * `prev_vals` - the committed values of the spent token.
+    /// * `next` - the commitment to the new token.
+    /// * `next_vals` - the committed values of the new token.
+    pub(crate) fn create<T: BoomerangRng>(
+        rng: &mut T,
+        st: &SpendLimitStatement<'_, B>,
+        prev: &PedersenComm<B>,
+        prev_vals: &[<B as CurveConfig>::ScalarField],
+        next: &PedersenComm<B>,
+        next_vals: &[<B as CurveConfig>::ScalarField],
+    ) -> Result<Self, &'static str> {
+        let attr = st.limit.attr;
+        let (prev_counter, counter) = match (prev_vals.get(attr), next_vals.get(attr)) {
+            (Some(prev_counter), Some(counter)) => (
+                counter_value::<B>(prev_counter),
+                counter_value::<B>(counter),
+            ),
+            _ => return Err("Boomerang spend limit: the token has no counter"),
+        };
+        let (prev_counter, counter) = prev_counter
+            .zip(counter)
+            .ok_or("Boomerang spend limit: the counter does not fit in 64 bits")?;
+        let (first, last) = st
+            .limit
+            .window(st.epoch)
+            .ok_or("Boomerang spend limit: the epoch is out of range")?;
+        if counter < first || counter > last || counter <= prev_counter {
+            return Err("Boomerang spend limit: the limit of the epoch is reached");
+        }
+
+        let gens = RewardsGenerators::<B>::shared();
+        let n = st.limit.bitsize();
+        let mut transcript = st.transcript();
+
+        let window_blind = <B as CurveConfig>::ScalarField::rand(rng);
+        let offset = counter - first;
+        let (lower_proof, window_comm) = RangeProof::prove_single_with_rng(
+            &gens.bp_gens,
+            &gens.pc_gens,
+            &mut transcript,
+            offset,
+            &window_blind,
+            n,
+            rng,
+        )
+        .map_err(|_| "Boomerang spend limit: failed to prove the window")?;
+        let (upper_proof, _) = RangeProof::prove_single_with_rng(
+            &gens.bp_gens,
+            &gens.pc_gens,
+            &mut transcript,
+            st.limit.limit - 1 - offset,
+            &-window_blind,
+            n,
+            rng,
+        )
+        .map_err(|_| "Boomerang spend limit: failed to prove the window")?;
+
+        let step_blind = <B as CurveConfig>::ScalarField::rand(rng);
+        let (step_proof, step_comm) = RangeProof::prove_single_with_rng(
+            &gens.bp_gens,
+            &gens.pc_gens,
+            &mut transcript,
+            counter - prev_counter - 1,
+            &step_blind,
+            64,
+            rng,
+        )
+        .map_err(|_| "Boomerang spend limit: failed to prove the step")?;
+
+        let w: Vec<<B as CurveConfig>::ScalarField> = prev_vals
+            .iter()
+            .chain([&prev.r])
+            .chain(next_vals)
+            .chain([&next.r, &window_blind, &step_blind])
+            .copied()
+            .collect();
+        let t: Vec<<B as CurveConfig>::ScalarField> = (0..w.len())
+            .map(|_| <B as CurveConfig>::ScalarField::rand(rng))
+            .collect();
+        let alpha = st.relations(&t);
+        let c = Self::challenge(&mut transcript, &alpha);
+
+        let z = t.iter().zip(w.iter()).map(|(t, w)| *t + c * w).collect();
+        Ok(Self {
+            window_comm,
+            step_comm,
+            lower_proof,
+            upper_proof,
+            step_proof,
+            alpha,
+            z,
+        })
+    }
+
+    /// verify. This function checks the proof against `st`.
+    /// This function returns the reason for the first failed check, if any.
+    /// # Arguments
+    /// * `st` - the statement.
+    /// * `rng` - the RNG that is used to batch the range proof checks.
+    pub(crate) fn verify<T: BoomerangRng>(
+        &self,
+        st: &SpendLimitStatement<'_, B>,
+        rng: &mut T,
+    ) -> Result<(), &'static str> {
+        let prev_len = committed_len(st.prev_gens);
+        let len = committed_len(st.gens);
+        let attr = st.limit.attr;
+        if attr < BASE_ATTRIBUTES
+            || attr >= prev_len
+            || attr >= len
+            || self.z.len() != prev_len + len + 4
+        {
+            return Err("Boomerang spend limit: the token has no counter");
+        }
+        if self.alpha.len() != 4 {
+            return Err("Boomerang spend limit: invalid proof of the counter");
+        }
+        let (first, _) = st
+            .limit
+            .window(st.epoch)
+            .ok_or("Boomerang spend limit: the epoch is out of range")?;
+
+        let gens = RewardsGenerators::<B>::shared();
+        let n = st.limit.bitsize();
+        let mut transcript = st.transcript();
+        let b = gens.pc_gens.B;
+
+        // The offset and the rest of the window add up to the limit minus one.
+        let upper_comm = (b.mul(<B as CurveConfig>::ScalarField::from(st.limit.limit - 1))
+            - self.window_comm)
+            .into_affine();
+        let check = self
+            .lower_proof
+            .verify_single_with_rng(
+                &gens.bp_gens,
+                &gens.pc_gens,
+                &mut transcript,
+                &self.window_comm,
+                n,
+                rng,
+            )
+            .is_ok()
+            && self
+                .upper_proof
+                .verify_single_with_rng(
+                    &gens.bp_gens,
+                    &gens.pc_gens,
+                    &mut transcript,
+                    &upper_comm,
+                    n,
+                    rng,
+                )
+                .is_ok();
+        if !check {
+            return Err("Boomerang spend limit: the counter is not in the window");
+        }
+
+        let check2 = self
+            .step_proof
+            .verify_single_with_rng(
+                &gens.bp_gens,
+                &gens.pc_gens,
+                &mut transcript,
+                &self.step_comm,
+                64,
+                rng,
+            )
+            .is_ok();
+        if !check2 {
+            return Err("Boomerang spend limit: the counter does not move forward");
+        }
+
+        let c = Self::challenge(&mut transcript, &self.alpha);
+        let statement = [
+            *st.prev_comm,
+            *st.comm,
+            (self.window_comm + b.mul(<B as CurveConfig>::ScalarField::from(first))).into_affine(),
+            (self.step_comm + b).into_affine(),
+        ];
+        let check3 = st
+            .relations(&self.z)
+            .iter()
+            .zip(self.alpha.iter().zip(statement.iter()))
+            .all(|(lhs, (alpha, x))| *lhs == (*alpha + x.mul(c)).into_affine());
+        if !check3 {
+            return Err("Boomerang spend limit: invalid proof of the counter");
+        }
+        Ok(())
+    }
+}
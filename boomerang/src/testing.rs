@@ -0,0 +1,280 @@
+//!
+//! Module containing helpers to test the protocols against parties that deviate from them.
+//! This module is only built for tests, or with the `testing` feature.
+//!
+
+pub mod malicious {
+    //! Defines servers and clients that deviate from the protocols in one specific way each, so
+    //! that tests can check that the honest party notices. Every message that a
+    //! `MaliciousServer` or a `MaliciousClient` does not tamper with is generated as the honest
+    //! party would.
+    //!
+    //! N.B These parties exist for tests only, and must never be deployed.
+
+    use ark_ec::{models::CurveConfig, CurveGroup};
+    use ark_std::One;
+
+    use crate::client::{
+        IssuanceM1, IssuanceM3, SpendVerifyM2, SpendVerifyM2Multi, SpendVerifyStateC,
+    };
+    use crate::config::{BoomerangConfig, State, VALUE_INDEX};
+    use crate::rng::BoomerangRng;
+    use crate::server::{
+        IssuanceM2, IssuanceM4, IssuanceStateS, ServerKeyPair, SpendVerifyM1, SpendVerifyM3,
+        SpendVerifyStateS,
+    };
+
+    /// ServerFault. The way in which a `MaliciousServer` deviates from the protocols.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum ServerFault {
+        /// WrongTagKey: the server signs, and advertises, a tag key other than its published
+        /// one, e.g. to recognise its tokens when they are spent.
+        WrongTagKey,
+        /// ReusedNonce: the server answers every issuance with the nonce, and the rest of the
+        /// second message, of its first issuance.
+        ReusedNonce,
+        /// AlteredPolicy: the server computes the rewards with another policy than the one
+        /// it advertises.
+        AlteredPolicy,
+        /// BadRewardsProof: the server sends a rewards proof that does not verify.
+        BadRewardsProof,
+    }
+
+    /// MaliciousServer. This struct holds a server that deviates from the protocols as its
+    /// `ServerFault` says.
+    #[derive(Clone)]
+    pub struct MaliciousServer<B: BoomerangConfig> {
+        /// key_pair: the published keypair of the server.
+        key_pair: ServerKeyPair<B>,
+        /// fault: the way in which the server deviates.
+        fault: ServerFault,
+        /// first_m2: the second message of the first issuance.
+        first_m2: Option<IssuanceM2<B>>,
+    }
+
+    impl<B: BoomerangConfig + Clone> MaliciousServer<B> {
+        /// new. Returns a server that holds `key_pair` and deviates as `fault` says.
+        /// # Arguments
+        /// * `key_pair` - the published keypair of the server.
+        /// * `fault` - the way in which the server deviates.
+        pub fn new(key_pair: ServerKeyPair<B>, fault: ServerFault) -> Self {
+            Self {
+                key_pair,
+                fault,
+                first_m2: None,
+            }
+        }
+
+        /// fault. Returns the way in which the server deviates.
+        pub fn fault(&self) -> ServerFault {
+            self.fault
+        }
+
+        /// key_pair. Returns the published keypair of the server.
+        pub fn key_pair(&self) -> &ServerKeyPair<B> {
+            &self.key_pair
+        }
+
+        /// signing_key_pair. Returns the keypair that the server signs with, which only differs
+        /// from the published one under `ServerFault::WrongTagKey`.
+        fn signing_key_pair(&self) -> ServerKeyPair<B> {
+            let mut key_pair = self.key_pair.clone();
+            if self.fault == ServerFault::WrongTagKey {
+                let tag_key = key_pair.s_key_pair.tag_key;
+                key_pair.s_key_pair.tag_key = (tag_key + tag_key).into_affine();
+            }
+            key_pair
+        }
+
+        /// generate_issuance_m2. This function generates the second message of the Issuance
+        /// Protocol, as `IssuanceStateS::generate_issuance_m2` does, and then deviates from it.
+        /// # Arguments
+        /// * `c_m` - the received client message.
+        /// * `state` - the server state.
+        /// * `rng` - the source of randomness.
+        pub fn generate_issuance_m2<T: BoomerangRng>(
+            &mut self,
+            c_m: &IssuanceM1<B>,
+            state: &mut IssuanceStateS<B>,
+            rng: &mut T,
+        ) -> IssuanceM2<B> {
+            let key_pair = self.signing_key_pair();
+            let m2 = IssuanceStateS::generate_issuance_m2(c_m, &key_pair, state, rng);
+            if self.fault != ServerFault::ReusedNonce {
+                return m2;
+            }
+
+            self.first_m2.get_or_insert(m2).clone()
+        }
+
+        /// generate_issuance_m4. This function generates the fourth message of the Issuance
+        /// Protocol, as `IssuanceStateS::generate_issuance_m4` does.
+        /// # Arguments
+        /// * `c_m` - the received client message.
+        /// * `state` - the server state.
+        pub fn generate_issuance_m4(
+            &self,
+            c_m: &IssuanceM3<B>,
+            state: &mut IssuanceStateS<B>,
+        ) -> IssuanceM4<B> {
+            IssuanceStateS::generate_issuance_m4(c_m, state, &self.signing_key_pair())
+        }
+
+        /// generate_spendverify_m3. This function generates the third message of the
+        /// Spend/Verify Protocol, as `SpendVerifyStateS::generate_spendverify_m3` does with the
+        /// advertised `policy_state`, and then deviates from it. This function fails if the
+        /// honest server fails.
+        /// # Arguments
+        /// * `rng` - the source of randomness.
+        /// * `c_m` - the received client message.
+        /// * `state` - the server state.
+        /// * `policy_state` - the advertised policy vector.
+        pub fn generate_spendverify_m3<T: BoomerangRng>(
+            &self,
+            rng: &mut T,
+            c_m: &SpendVerifyM2<B>,
+            state: &mut SpendVerifyStateS<B>,
+            mut policy_state: Vec<<B as CurveConfig>::ScalarField>,
+        ) -> Result<SpendVerifyM3<B>, &'static str> {
+            if self.fault == ServerFault::AlteredPolicy {
+                for policy in policy_state.iter_mut() {
+                    *policy += <B as CurveConfig>::ScalarField::one();
+                }
+            }
+
+            let key_pair = self.signing_key_pair();
+            let mut m3 = SpendVerifyStateS::generate_spendverify_m3(
+                rng,
+                c_m,
+                state,
+                &key_pair,
+                policy_state,
+            )?;
+            if self.fault == ServerFault::BadRewardsProof {
+                let r_comms = m3.pi_reward.r_comms;
+                m3.pi_reward.r_comms = (r_comms + r_comms).into_affine();
+            }
+            Ok(m3)
+        }
+    }
+
+    /// ClientFault. The way in which a `MaliciousClient` deviates from the protocols.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum ClientFault {
+        /// OverflowingBalance: the client raises the amount of a spend by 2^64 after it has
+        /// proved the remainder, so that the amount exceeds its balance and the remainder wraps
+        /// around the field.
+        OverflowingBalance,
+        /// ReusedTokenState: the client presents the state of the same token twice in a spend.
+        ReusedTokenState,
+        /// TamperedCommitment: the client adds to the balance of its new token after it has
+        /// proved the opening of the commitment.
+        TamperedCommitment,
+        /// MismatchedOpening: the client proves the opening of its new commitment in place of
+        /// the opening of the signed one.
+        MismatchedOpening,
+    }
+
+    /// MaliciousClient. This struct holds a client that deviates from the protocols as its
+    /// `ClientFault` says.
+    #[derive(Clone, Copy, Debug)]
+    pub struct MaliciousClient {
+        /// fault: the way in which the client deviates.
+        fault: ClientFault,
+    }
+
+    impl MaliciousClient {
+        /// new. Returns a client that deviates as `fault` says.
+        /// # Arguments
+        /// * `fault` - the way in which the client deviates.
+        pub fn new(fault: ClientFault) -> Self {
+            Self { fault }
+        }
+
+        /// fault. Returns the way in which the client deviates.
+        pub fn fault(&self) -> ClientFault {
+            self.fault
+        }
+
+        /// generate_spendverify_m2. This function generates the second message of the
+        /// Spend/Verify Protocol, as `SpendVerifyStateC::generate_spendverify_m2` does, and then
+        /// deviates from it.
+        /// # Arguments
+        /// * `rng` - the source of randomness.
+        /// * `state` - the local client state.
+        /// * `s_state` - the tmp client state.
+        /// * `s_m` - the received server message.
+        /// * `s_key_pair` - the server's keypair.
+        /// * `spend_state` - the values to spend passed as a slice.
+        pub fn generate_spendverify_m2<B: BoomerangConfig + Clone, T: BoomerangRng>(
+            &self,
+            rng: &mut T,
+            state: &State<B>,
+            s_state: &mut SpendVerifyStateC<B>,
+            s_m: &SpendVerifyM1<B>,
+            s_key_pair: &ServerKeyPair<B>,
+            spend_state: &[<B as CurveConfig>::ScalarField],
+        ) -> SpendVerifyM2<B> {
+            let mut m2 = SpendVerifyStateC::generate_spendverify_m2(
+                rng,
+                state,
+                s_state,
+                s_m,
+                s_key_pair,
+                spend_state,
+            );
+            match self.fault {
+                ClientFault::TamperedCommitment => {
+                    let bonus = m2.gens.generators[VALUE_INDEX];
+                    m2.comm.comm = (m2.comm.comm + bonus).into_affine();
+                }
+                ClientFault::MismatchedOpening => m2.pi_2 = m2.pi_1.clone(),
+                _ => {}
+            }
+            m2
+        }
+
+        /// generate_spendverify_m2_multi. This function generates the second message of the
+        /// Spend/Verify Protocol for several tokens, as
+        /// `SpendVerifyStateC::generate_spendverify_m2_multi` does, and then deviates from it.
+        /// This function fails if the honest client fails.
+        /// # Arguments
+        /// * `rng` - the source of randomness.
+        /// * `state` - the local client state, which holds the tokens to spend.
+        /// * `s_state` - the tmp client state.
+        /// * `s_m` - the received server message.
+        /// * `s_key_pair` - the server's keypair.
+        /// * `spend_state` - the values to spend passed as a slice.
+        pub fn generate_spendverify_m2_multi<B: BoomerangConfig + Clone, T: BoomerangRng>(
+            &self,
+            rng: &mut T,
+            state: &State<B>,
+            s_state: &mut SpendVerifyStateC<B>,
+            s_m: &SpendVerifyM1<B>,
+            s_key_pair: &ServerKeyPair<B>,
+            spend_state: &[<B as CurveConfig>::ScalarField],
+        ) -> Result<SpendVerifyM2Multi<B>, &'static str> {
+            let mut m2 = SpendVerifyStateC::generate_spendverify_m2_multi(
+                rng,
+                state,
+                s_state,
+                s_m,
+                s_key_pair,
+                spend_state,
+            )?;
+            match self.fault {
+                ClientFault::OverflowingBalance => {
+                    let overflow = <B as CurveConfig>::ScalarField::from(u64::MAX)
+                        + <B as CurveConfig>::ScalarField::one();
+                    m2.spend_state[0] += overflow;
+                }
+                ClientFault::ReusedTokenState => {
+                    let input = m2.inputs[0].clone();
+                    m2.inputs.push(input);
+                }
+                _ => {}
+            }
+            Ok(m2)
+        }
+    }
+}
@@ -0,0 +1,398 @@
+//!
+//! Module containing deterministic end-to-end test vectors for the protocol.
+//!
+//! A test vector records the compressed encoding of every message exchanged
+//! during issuance, collection and spending when all randomness is drawn from a
+//! ChaCha20 RNG seeded with a fixed value. Other implementations can replay the
+//! same seed to check interoperability against this crate.
+//!
+//! An ACL test vector does the same for a single run of the underlying blind
+//! signature, and also records the keys, the message and the signature, so that
+//! changes to the challenge computation cannot silently change the scheme.
+//!
+
+use ark_ec::{models::CurveConfig, short_weierstrass as sw, CurveGroup};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{ops::Mul, UniformRand};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::client::{
+    CollectionM2, CollectionM4, CollectionStateC, IssuanceM1, IssuanceM3, IssuanceStateC,
+    SpendVerifyM2, SpendVerifyM4, SpendVerifyStateC, UKeyPair,
+};
+use crate::config::BoomerangConfig;
+use crate::server::{
+    CollectionM1, CollectionM3, CollectionM5, CollectionStateS, IssuanceM2, IssuanceM4,
+    IssuanceStateS, ServerKeyPair, SpendVerifyM1, SpendVerifyM3, SpendVerifyM5, SpendVerifyStateS,
+};
+use acl::{
+    config::{ACLConfig, KeyPair},
+    sign::{SigChall, SigSign},
+    verify::{SigComm, SigResp, SigVerify},
+};
+
+/// The version of the test vector format.
+pub const TEST_VECTOR_VERSION: u32 = 1;
+
+/// TestVector. This struct acts as a container for a seeded protocol transcript.
+/// Every message is stored as the lowercase hex encoding of its compressed
+/// serialization.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestVector {
+    /// version: the version of the test vector format.
+    pub version: u32,
+    /// curve: the name of the curve the vector was produced on.
+    pub curve: String,
+    /// seed: the seed of the ChaCha20 RNG.
+    pub seed: u64,
+    /// collection_value: the value added during collection.
+    pub collection_value: u64,
+    /// spend_state: the state vector used during spending.
+    pub spend_state: Vec<u64>,
+    /// policy_state: the policy vector used during spending.
+    pub policy_state: Vec<u64>,
+    /// issuance: the issuance messages m1..m4.
+    pub issuance: Vec<String>,
+    /// collection: the collection messages m1..m5.
+    pub collection: Vec<String>,
+    /// spending: the spend/verify messages m1..m5.
+    pub spending: Vec<String>,
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>, String> {
+    if s.len() & 1 == 1 {
+        return Err("Odd length hex string".to_string());
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            s.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+                .ok_or_else(|| format!("Invalid hex byte at position {}", i))
+        })
+        .collect()
+}
+
+fn encode<T: CanonicalSerialize>(m: &T) -> String {
+    let mut bytes = Vec::new();
+    m.serialize_compressed(&mut bytes)
+        .expect("Failed to serialize message");
+    to_hex(&bytes)
+}
+
+fn decode<T: CanonicalDeserialize>(s: &str, name: &str) -> Result<T, String> {
+    let bytes = from_hex(s)?;
+    T::deserialize_compressed(&bytes[..]).map_err(|e| format!("{}: {}", name, e))
+}
+
+impl TestVector {
+    /// generate. This function runs issuance, collection and spending with all
+    /// randomness drawn from a ChaCha20 RNG seeded with `seed`.
+    /// # Arguments
+    /// * `curve` - the name of the curve.
+    /// * `seed` - the seed of the RNG.
+    pub fn generate<B: BoomerangConfig>(curve: &str, seed: u64) -> Self {
+        let rng = &mut ChaCha20Rng::seed_from_u64(seed);
+        let collection_value = 1u64;
+        let spend_state_u64 = vec![1u64];
+        let policy_state_u64 = vec![2u64];
+
+        let kp = UKeyPair::<B>::generate(rng);
+        let skp = ServerKeyPair::<B>::generate(rng);
+
+        // Issuance
+        let mut i_state = IssuanceStateC::<B>::default();
+        let mut s_i_state = IssuanceStateS::<B>::default();
+        let i_m1 = IssuanceStateC::generate_issuance_m1(&kp, &mut i_state, rng);
+        let i_m2 = IssuanceStateS::generate_issuance_m2(&i_m1, &skp, &mut s_i_state, rng);
+        let i_m3 = IssuanceStateC::generate_issuance_m3(&i_m2, &mut i_state, rng);
+        let i_m4 = IssuanceStateS::generate_issuance_m4(&i_m3, &mut s_i_state, &skp);
+        let state = IssuanceStateC::populate_state(&i_m4, &mut i_state, &skp, &kp);
+
+        // Collection
+        let mut c_state = CollectionStateC::<B>::default();
+        let mut s_c_state = CollectionStateS::<B>::default();
+        let c_m1 = CollectionStateS::<B>::generate_collection_m1(rng, &mut s_c_state);
+        let c_m2 = CollectionStateC::generate_collection_m2(rng, &state, &c_m1, &mut c_state, &skp);
+        let v = <B as CurveConfig>::ScalarField::from(collection_value);
+        let c_m3 = CollectionStateS::generate_collection_m3(rng, &c_m2, &mut s_c_state, &skp, v)
+            .expect("Failed to generate collection m3");
+        let c_m4 = CollectionStateC::generate_collection_m4(rng, &mut c_state, &c_m3);
+        let c_m5 = CollectionStateS::generate_collection_m5(&c_m4, &mut s_c_state, &skp);
+        let state = CollectionStateC::populate_state(&mut c_state, &c_m5, &skp, &kp);
+
+        // Spending
+        let spend_state: Vec<<B as CurveConfig>::ScalarField> = spend_state_u64
+            .iter()
+            .map(|x| <B as CurveConfig>::ScalarField::from(*x))
+            .collect();
+        let policy_state: Vec<<B as CurveConfig>::ScalarField> = policy_state_u64
+            .iter()
+            .map(|x| <B as CurveConfig>::ScalarField::from(*x))
+            .collect();
+
+        let mut sv_state = SpendVerifyStateC::<B>::default();
+        let mut s_sv_state = SpendVerifyStateS::<B>::default();
+        let s_m1 = SpendVerifyStateS::<B>::generate_spendverify_m1(rng, &mut s_sv_state);
+        let s_m2 = SpendVerifyStateC::generate_spendverify_m2(
+            rng,
+            &state,
+            &mut sv_state,
+            &s_m1,
+            &skp,
+            &spend_state,
+        );
+        let policy_id = skp.policy_id(&policy_state);
+        let s_m3 = SpendVerifyStateS::generate_spendverify_m3(
+            rng,
+            &s_m2,
+            &mut s_sv_state,
+            &skp,
+            policy_state,
+        )
+        .expect("Failed to generate spend-verify m3");
+        let s_m4 =
+            SpendVerifyStateC::generate_spendverify_m4(rng, &mut sv_state, &s_m3, &policy_id)
+                .expect("Failed to generate spend-verify m4");
+        let s_m5 = SpendVerifyStateS::generate_spendverify_m5(&s_m4, &mut s_sv_state, &skp);
+
+        Self {
+            version: TEST_VECTOR_VERSION,
+            curve: curve.to_string(),
+            seed,
+            collection_value,
+            spend_state: spend_state_u64,
+            policy_state: policy_state_u64,
+            issuance: vec![encode(&i_m1), encode(&i_m2), encode(&i_m3), encode(&i_m4)],
+            collection: vec![
+                encode(&c_m1),
+                encode(&c_m2),
+                encode(&c_m3),
+                encode(&c_m4),
+                encode(&c_m5),
+            ],
+            spending: vec![
+                encode(&s_m1),
+                encode(&s_m2),
+                encode(&s_m3),
+                encode(&s_m4),
+                encode(&s_m5),
+            ],
+        }
+    }
+
+    /// to_json. This function returns the pretty-printed JSON encoding of the
+    /// test vector.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Failed to serialize test vector")
+    }
+
+    /// from_json. This function parses a test vector from its JSON encoding.
+    /// # Arguments
+    /// * `json` - the JSON encoding of the test vector.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid test vector: {}", e))
+    }
+
+    /// check. This function checks that every message of the test vector
+    /// decodes under the given config, and that re-running the protocol with
+    /// the same seed reproduces the test vector byte for byte.
+    pub fn check<B: BoomerangConfig>(&self) -> Result<(), String> {
+        if self.version != TEST_VECTOR_VERSION {
+            return Err(format!("Unsupported test vector version {}", self.version));
+        }
+
+        if self.issuance.len() != 4 || self.collection.len() != 5 || self.spending.len() != 5 {
+            return Err("Unexpected number of messages".to_string());
+        }
+
+        decode::<IssuanceM1<B>>(&self.issuance[0], "issuance m1")?;
+        decode::<IssuanceM2<B>>(&self.issuance[1], "issuance m2")?;
+        decode::<IssuanceM3<B>>(&self.issuance[2], "issuance m3")?;
+        decode::<IssuanceM4<B>>(&self.issuance[3], "issuance m4")?;
+        decode::<CollectionM1<B>>(&self.collection[0], "collection m1")?;
+        decode::<CollectionM2<B>>(&self.collection[1], "collection m2")?;
+        decode::<CollectionM3<B>>(&self.collection[2], "collection m3")?;
+        decode::<CollectionM4<B>>(&self.collection[3], "collection m4")?;
+        decode::<CollectionM5<B>>(&self.collection[4], "collection m5")?;
+        decode::<SpendVerifyM1<B>>(&self.spending[0], "spend-verify m1")?;
+        decode::<SpendVerifyM2<B>>(&self.spending[1], "spend-verify m2")?;
+        decode::<SpendVerifyM3<B>>(&self.spending[2], "spend-verify m3")?;
+        decode::<SpendVerifyM4<B>>(&self.spending[3], "spend-verify m4")?;
+        decode::<SpendVerifyM5<B>>(&self.spending[4], "spend-verify m5")?;
+
+        let expected = Self::generate::<B>(&self.curve, self.seed);
+        let phases = [
+            ("issuance", &self.issuance, &expected.issuance),
+            ("collection", &self.collection, &expected.collection),
+            ("spend-verify", &self.spending, &expected.spending),
+        ];
+
+        for (name, got, want) in phases {
+            for (i, (g, w)) in got.iter().zip(want.iter()).enumerate() {
+                if g != w {
+                    return Err(format!("{} m{} does not match", name, i + 1));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// ACL_MESSAGE. The message that is signed in an ACL test vector.
+pub const ACL_MESSAGE: &[u8] = b"Boomerang ACL test vector";
+
+/// ACL_CONTEXT. The context that is signed in an ACL test vector.
+pub const ACL_CONTEXT: &[u8] = b"Boomerang ACL test vector context";
+
+/// AclTestVector. This struct acts as a container for a seeded run of the ACL
+/// blind signature. Every value is stored as the lowercase hex encoding of its
+/// compressed serialization.
+///
+/// N.B The commitment and the challenge hold the nonces of the signer and the
+/// blinding values of the user, which are secret outside of test vectors.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AclTestVector {
+    /// version: the version of the test vector format.
+    pub version: u32,
+    /// curve: the name of the curve the vector was produced on.
+    pub curve: String,
+    /// seed: the seed of the ChaCha20 RNG.
+    pub seed: u64,
+    /// key_pair: the signer's keypair, including its signing key.
+    pub key_pair: String,
+    /// message: the signed message.
+    pub message: String,
+    /// context: the signed context.
+    pub context: String,
+    /// comm: the commitment to the user's values.
+    pub comm: String,
+    /// commitment: the first message, including the signer's nonces.
+    pub commitment: String,
+    /// challenge: the second message, including the user's blinding values.
+    pub challenge: String,
+    /// response: the third message.
+    pub response: String,
+    /// signature: the resulting signature.
+    pub signature: String,
+}
+
+impl AclTestVector {
+    /// generate. This function generates a keypair and signs `ACL_MESSAGE` under
+    /// `ACL_CONTEXT`, with all randomness drawn from a ChaCha20 RNG seeded with `seed`.
+    /// # Arguments
+    /// * `curve` - the name of the curve.
+    /// * `seed` - the seed of the RNG.
+    pub fn generate<A: ACLConfig>(curve: &str, seed: u64) -> Self {
+        let rng = &mut ChaCha20Rng::seed_from_u64(seed);
+
+        let kp = KeyPair::<A>::generate(rng);
+        let comm: sw::Affine<A> = A::GENERATOR
+            .mul(<A as CurveConfig>::ScalarField::rand(rng))
+            .into_affine();
+
+        let m1 = SigComm::commit(&kp, rng, comm);
+        let m2 = SigChall::challenge(
+            kp.tag_key,
+            kp.verifying_key,
+            rng,
+            m1,
+            ACL_MESSAGE,
+            Some(ACL_CONTEXT),
+        );
+        let m3 = SigResp::respond(&kp, &m1, &m2);
+        let sig = SigSign::sign(
+            kp.verifying_key,
+            kp.tag_key,
+            &m2,
+            &m3,
+            ACL_MESSAGE,
+            Some(ACL_CONTEXT),
+        );
+
+        Self {
+            version: TEST_VECTOR_VERSION,
+            curve: curve.to_string(),
+            seed,
+            key_pair: encode(&kp),
+            message: to_hex(ACL_MESSAGE),
+            context: to_hex(ACL_CONTEXT),
+            comm: encode(&comm),
+            commitment: encode(&m1),
+            challenge: encode(&m2),
+            response: encode(&m3),
+            signature: encode(&sig),
+        }
+    }
+
+    /// to_json. This function returns the pretty-printed JSON encoding of the
+    /// test vector.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Failed to serialize test vector")
+    }
+
+    /// from_json. This function parses a test vector from its JSON encoding.
+    /// # Arguments
+    /// * `json` - the JSON encoding of the test vector.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid test vector: {}", e))
+    }
+
+    /// check. This function checks that the signature of the test vector verifies
+    /// under its keys, that the response follows from the recorded messages, and that re-running the signature with the same seed reproduces the
+    /// test vector byte for byte.
+    pub fn check<A: ACLConfig>(&self) -> Result<(), String> {
+        if self.version != TEST_VECTOR_VERSION {
+            return Err(format!("Unsupported test vector version {}", self.version));
+        }
+
+        let kp = decode::<KeyPair<A>>(&self.key_pair, "key pair")?;
+        let message = from_hex(&self.message)?;
+        let context = from_hex(&self.context)?;
+        let comm = decode::<sw::Affine<A>>(&self.comm, "comm")?;
+        let m1 = decode::<SigComm<A>>(&self.commitment, "commitment")?;
+        let m2 = decode::<SigChall<A>>(&self.challenge, "challenge")?;
+        decode::<SigResp<A>>(&self.response, "response")?;
+        let sig = decode::<SigSign<A>>(&self.signature, "signature")?;
+
+        if !SigVerify::verify(kp.verifying_key, kp.tag_key, &sig, &message, Some(&context)) {
+            return Err("signature does not verify".to_string());
+        }
+
+        if m1.comms != comm {
+            return Err("commitment does not match comm".to_string());
+        }
+
+        // The response only depends on the recorded values, not on the RNG.
+        if encode(&SigResp::respond(&kp, &m1, &m2)) != self.response {
+            return Err("response does not match".to_string());
+        }
+
+        let expected = Self::generate::<A>(&self.curve, self.seed);
+        let fields = [
+            ("key pair", &self.key_pair, &expected.key_pair),
+            ("message", &self.message, &expected.message),
+            ("context", &self.context, &expected.context),
+            ("comm", &self.comm, &expected.comm),
+            ("commitment", &self.commitment, &expected.commitment),
+            ("challenge", &self.challenge, &expected.challenge),
+            ("response", &self.response, &expected.response),
+            ("signature", &self.signature, &expected.signature),
+        ];
+        for (name, got, want) in fields {
+            if got != want {
+                return Err(format!("{} does not match", name));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+//! Defines sealed session tickets for running a server without a session store.
+//!
+//! Between two messages of the same protocol run, a server has to remember some per-session
+//! state (e.g. the double-spending tag value and the signature commitment). Instead of keeping
+//! that state in a store that every server instance can reach, the server can seal it into a
+//! `SessionTicket` with a `TicketKey` that is shared by every instance. The ticket is sent to the
+//! client alongside the server's message, and the client returns it unchanged alongside its next
+//! message.
+//!
+//! Tickets are encrypted and authenticated with XChaCha20-Poly1305, whose 24-byte nonces are
+//! long enough to be drawn at random for every ticket that a long-lived key seals. Each ticket
+//! is bound to a label that names the step it was issued for, and carries an expiry time that
+//! is checked when it is opened.
+//!
+//! N.B Opening a ticket does not stop it from being replayed. Replaying the ticket of a
+//! signature commitment would let a client obtain two responses to the same commitment, which
+//! leaks the server's signing key, and replaying the ticket of a first message would let a
+//! client run the rest of the protocol twice with the same session. The server therefore only
+//! opens its session tickets with `TicketKey::open_once`, which rejects any ticket whose `id` a
+//! `ReplayGuard` has already seen before that ticket expired.
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use std::collections::HashMap;
+
+use crate::rng::BoomerangRng;
+
+/// NONCE_LEN. The length of the nonce that prefixes every ticket.
+const NONCE_LEN: usize = 24;
+
+/// EXPIRY_LEN. The length of the expiry time that follows the nonce.
+const EXPIRY_LEN: usize = 8;
+
+/// TicketId. The unique identifier of a session ticket.
+pub type TicketId = [u8; NONCE_LEN];
+
+/// ReplayGuard. A store of the ids of the tickets that a server has accepted. Every server
+/// instance that handles the same sessions must share the same store, e.g. a database table
+/// keyed by the id, whose rows can be dropped once their ticket has expired.
+pub trait ReplayGuard {
+    /// accept. This function records that the ticket `id` is accepted. Returns false if `id`
+    /// was already accepted, in which case the ticket must be refused.
+    /// # Arguments
+    /// * `id` - the id of the ticket.
+    /// * `expiry` - the authenticated expiry time of the ticket, after which `id` can be forgotten.
+    /// * `now` - the current time, in the same clock as the expiry time.
+    fn accept(&mut self, id: TicketId, expiry: u64, now: u64) -> bool;
+}
+
+/// MemoryReplayGuard. A guard that keeps the ids in memory, e.g. for a single server instance.
+/// The ids of expired tickets are forgotten as new tickets are accepted.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryReplayGuard {
+    /// seen: the expiry time of every accepted ticket, keyed by its id.
+    seen: HashMap<TicketId, u64>,
+}
+
+impl MemoryReplayGuard {
+    /// new. Returns an empty guard.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// len. Returns the number of ids that the guard remembers.
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// is_empty. Returns true if the guard does not remember any id.
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+}
+
+impl ReplayGuard for MemoryReplayGuard {
+    fn accept(&mut self, id: TicketId, expiry: u64, now: u64) -> bool {
+        // An expired ticket is refused when it is opened, so its id can no longer be replayed.
+        self.seen.retain(|_, seen_expiry| *seen_expiry >= now);
+        if self.seen.contains_key(&id) {
+            return false;
+        }
+
+        self.seen.insert(id, expiry);
+        true
+    }
+}
+
+/// TicketKey. This struct holds the secret key that session tickets are sealed with.
+/// Every server instance that handles the same sessions must use the same key.
+#[derive(Clone)]
+pub struct TicketKey {
+    /// key: the XChaCha20-Poly1305 key.
+    key: [u8; 32],
+}
+
+/// SessionTicket. This struct holds a sealed piece of server state. It is opaque to the client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionTicket {
+    /// bytes: the nonce, the expiry time and the ciphertext, in that order.
+    bytes: Vec<u8>,
+}
+
+impl SessionTicket {
+    /// from_bytes. This function wraps the `bytes` that were returned by the client as a ticket.
+    /// The bytes are only checked when the ticket is opened.
+    /// # Arguments
+    /// * `bytes` - the ticket bytes.
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+
+    /// as_bytes. Returns the bytes that should be sent to the client.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// id. Returns the unique identifier of this ticket, or None if the ticket is too short to hold one.
+    /// This is the value that a server should remember in order to reject replayed tickets.
+    pub fn id(&self) -> Option<TicketId> {
+        self.bytes.get(..NONCE_LEN)?.try_into().ok()
+    }
+
+    /// expiry. Returns the time after which this ticket is no longer accepted, or None if the ticket is malformed.
+    /// N.B This value is only authenticated once the ticket has been opened.
+    pub fn expiry(&self) -> Option<u64> {
+        let bytes = self.bytes.get(NONCE_LEN..NONCE_LEN + EXPIRY_LEN)?;
+        Some(u64::from_le_bytes(bytes.try_into().ok()?))
+    }
+}
+
+impl TicketKey {
+    /// generate. This function generates a new, uniformly random, ticket key.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    pub fn generate<T: BoomerangRng>(rng: &mut T) -> Self {
+        let mut key = [0u8; 32];
+        rng.fill_bytes(&mut key);
+        Self { key }
+    }
+
+    /// from_bytes. This function builds a ticket key from existing key material, e.g. a key
+    /// that is loaded from the deployment's secret storage.
+    /// # Arguments
+    /// * `key` - the key.
+    pub fn from_bytes(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+
+    /// make_aad. This function returns the associated data for a ticket, which binds the ticket
+    /// to the step it was issued for and to its expiry time. The label is prefixed with its
+    /// length, so that no two (label, expiry) pairs have the same encoding.
+    fn make_aad(label: &[u8], expiry: &[u8]) -> Vec<u8> {
+        let mut aad = Vec::with_capacity(8 + label.len() + EXPIRY_LEN);
+        aad.extend_from_slice(&(label.len() as u64).to_le_bytes());
+        aad.extend_from_slice(label);
+        aad.extend_from_slice(expiry);
+        aad
+    }
+
+    /// seal. This function seals `state` into a new ticket.
+    /// # Arguments
+    /// * `rng` - the source of randomness.
+    /// * `label` - the label of the step that the ticket is issued for.
+    /// * `expiry` - the time (in the caller's clock, e.g. UNIX seconds) after which the ticket is no longer accepted.
+    /// * `state` - the state to seal.
+    pub fn seal<S: CanonicalSerialize, T: BoomerangRng>(
+        &self,
+        rng: &mut T,
+        label: &[u8],
+        expiry: u64,
+        state: &S,
+    ) -> SessionTicket {
+        let mut nonce = [0u8; NONCE_LEN];
+        rng.fill_bytes(&mut nonce);
+        let expiry = expiry.to_le_bytes();
+
+        let mut plaintext = Vec::new();
+        state.serialize_compressed(&mut plaintext).unwrap();
+
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&self.key));
+        let ciphertext = cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &Self::make_aad(label, &expiry),
+                },
+            )
+            .expect("TicketKey: encryption failed");
+
+        let mut bytes = Vec::with_capacity(NONCE_LEN + EXPIRY_LEN + ciphertext.len());
+        bytes.extend_from_slice(&nonce);
+        bytes.extend_from_slice(&expiry);
+        bytes.extend_from_slice(&ciphertext);
+        SessionTicket { bytes }
+    }
+
+    /// open. This function returns the state that is sealed in `ticket`. This function fails if
+    /// the ticket was not sealed with this key and `label`, if it has been modified, or if it has expired.
+    /// # Arguments
+    /// * `label` - the label of the step that the ticket must have been issued for.
+    /// * `now` - the current time, in the same clock as the expiry time.
+    /// * `ticket` - the ticket to open.
+    pub fn open<S: CanonicalDeserialize>(
+        &self,
+        label: &[u8],
+        now: u64,
+        ticket: &SessionTicket,
+    ) -> Result<S, &'static str> {
+        if ticket.bytes.len() < NONCE_LEN + EXPIRY_LEN {
+            return Err("Boomerang ticket: malformed ticket");
+        }
+
+        let (nonce, rest) = ticket.bytes.split_at(NONCE_LEN);
+        let (expiry, ciphertext) = rest.split_at(EXPIRY_LEN);
+
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&self.key));
+        let plaintext = cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &Self::make_aad(label, expiry),
+                },
+            )
+            .map_err(|_| "Boomerang ticket: invalid ticket")?;
+
+        // The expiry time is only trusted once the ticket has been authenticated.
+        if now > u64::from_le_bytes(expiry.try_into().unwrap()) {
+            return Err("Boomerang ticket: expired ticket");
+        }
+
+        S::deserialize_compressed(&plaintext[..]).map_err(|_| "Boomerang ticket: malformed state")
+    }
+
+    /// open_once. This function returns the state that is sealed in `ticket`, as `open` does,
+    /// and records the ticket in `guard`. This function fails if `open` fails, or if `guard`
+    /// has already accepted the ticket.
+    /// # Arguments
+    /// * `label` - the label of the step that the ticket must have been issued for.
+    /// * `now` - the current time, in the same clock as the expiry time.
+    /// * `ticket` - the ticket to open.
+    /// * `guard` - the ids of the tickets that were already accepted.
+    pub fn open_once<S: CanonicalDeserialize, G: ReplayGuard>(
+        &self,
+        label: &[u8],
+        now: u64,
+        ticket: &SessionTicket,
+        guard: &mut G,
+    ) -> Result<S, &'static str> {
+        let state = self.open(label, now, ticket)?;
+
+        // Only authenticated tickets are recorded, so that forged ids cannot fill the guard.
+        let (id, expiry) = ticket
+            .id()
+            .zip(ticket.expiry())
+            .ok_or("Boomerang ticket: malformed ticket")?;
+        if !guard.accept(id, expiry, now) {
+            return Err("Boomerang ticket: replayed ticket");
+        }
+
+        Ok(state)
+    }
+}
@@ -0,0 +1,142 @@
+//! Helpers for the optional `tracing` and `metrics` instrumentation of the protocol messages.
+//!
+//! Every message generation and verification function is wrapped in a span that declares a
+//! `size` field (the compressed size of the message, in bytes) and an `elapsed_us` field (the
+//! time spent in the function, in microseconds). These helpers fill in both fields once the
+//! function succeeds, so a failed check leaves them empty. With the `metrics` feature, they
+//! also update the registry of the `metrics` module. Without either feature they compile down
+//! to nothing.
+
+use ark_serialize::CanonicalSerialize;
+
+#[cfg(feature = "metrics")]
+use crate::metrics::{self, Direction};
+
+#[cfg(any(feature = "tracing", feature = "metrics"))]
+use std::time::Instant;
+
+/// SpanTimer. This struct measures the time spent in the current span.
+pub(crate) struct SpanTimer {
+    /// start: the time at which the timer was started.
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    start: Instant,
+    /// message: the type of the message that is handled, if any.
+    #[cfg(feature = "metrics")]
+    message: Option<&'static str>,
+}
+
+impl SpanTimer {
+    /// start. This function starts a new timer.
+    #[inline]
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(any(feature = "tracing", feature = "metrics"))]
+            start: Instant::now(),
+            #[cfg(feature = "metrics")]
+            message: None,
+        }
+    }
+
+    /// serve. This function starts a new timer for the handling of `msg`, and counts `msg` as
+    /// received.
+    /// # Arguments
+    /// * `msg` - the message that was received.
+    #[inline]
+    pub(crate) fn serve<T: CanonicalSerialize>(msg: &T) -> Self {
+        #[cfg(feature = "metrics")]
+        {
+            let message = metrics::message_type::<T>();
+            metrics::message(message, Direction::In, msg.compressed_size());
+            Self {
+                message: Some(message),
+                ..Self::start()
+            }
+        }
+        #[cfg(not(feature = "metrics"))]
+        {
+            let _ = msg;
+            Self::start()
+        }
+    }
+
+    /// span. This function records the compressed size of `msg` and the time since the timer
+    /// was started on the current span.
+    #[inline]
+    fn span<T: CanonicalSerialize>(&self, msg: &T) {
+        #[cfg(feature = "tracing")]
+        {
+            let span = tracing::Span::current();
+            span.record("size", msg.compressed_size());
+            span.record("elapsed_us", self.start.elapsed().as_micros() as u64);
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = msg;
+    }
+
+    /// record. This function records `msg` as checked: the compressed size of `msg` and the time
+    /// since the timer was started are recorded on the current span, and the time is added to
+    /// the verification times of `msg`'s type.
+    /// # Arguments
+    /// * `msg` - the message that was checked in the current span.
+    #[inline]
+    pub(crate) fn record<T: CanonicalSerialize>(self, msg: &T) {
+        self.span(msg);
+        #[cfg(feature = "metrics")]
+        metrics::verified(metrics::message_type::<T>(), self.start.elapsed());
+    }
+
+    /// reject. This function counts a rejected message, adds the time since the timer was
+    /// started to the verification times of the handled message, if any, and then returns
+    /// `reason`.
+    /// # Arguments
+    /// * `reason` - the reason for the rejection.
+    #[inline]
+    pub(crate) fn reject(&self, reason: &'static str) -> &'static str {
+        #[cfg(feature = "metrics")]
+        {
+            metrics::rejected(reason);
+            if let Some(message) = self.message {
+                metrics::verified(message, self.start.elapsed());
+            }
+        }
+        reason
+    }
+
+    /// finish. This function records `msg` on the current span as in `record`, counts it as
+    /// produced, and then returns it.
+    /// # Arguments
+    /// * `msg` - the message that was produced in the current span.
+    #[inline]
+    pub(crate) fn finish<T: CanonicalSerialize>(self, msg: T) -> T {
+        self.span(&msg);
+        #[cfg(feature = "metrics")]
+        metrics::message(
+            metrics::message_type::<T>(),
+            Direction::Out,
+            msg.compressed_size(),
+        );
+        msg
+    }
+}
+
+/// open_session. This function counts a new session of `protocol` as active.
+/// # Arguments
+/// * `protocol` - the protocol of the session.
+#[inline]
+pub(crate) fn open_session(protocol: &'static str) {
+    #[cfg(feature = "metrics")]
+    metrics::session(protocol, 1);
+    #[cfg(not(feature = "metrics"))]
+    let _ = protocol;
+}
+
+/// close_session. This function counts a session of `protocol` as no longer active.
+/// # Arguments
+/// * `protocol` - the protocol of the session.
+#[inline]
+pub(crate) fn close_session(protocol: &'static str) {
+    #[cfg(feature = "metrics")]
+    metrics::session(protocol, -1);
+    #[cfg(not(feature = "metrics"))]
+    let _ = protocol;
+}
@@ -1,16 +1,32 @@
 pub mod rewards {
-    use crate::config::BoomerangConfig;
-    use ark_bulletproofs::{inner_product, BulletproofGens, LinearProof, PedersenGens, RangeProof};
+    use crate::config::{session_transcript, BoomerangConfig, SessionId, VALUE_INDEX};
+    use crate::encoding::{append_point, encode_scalar, Endianness};
+    use crate::rng::BoomerangRng;
+    use crate::trace::SpanTimer;
+    pub use ark_bulletproofs::CancellationToken;
+    use ark_bulletproofs::{
+        inner_product, msm::MsmAccumulator, BulletproofGens, LinearProof, PedersenGens, ProofError,
+        ProverKey, RangeProof,
+    };
     use ark_ec::models::{
         short_weierstrass::{self as sw},
         CurveConfig,
     };
     use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+    use ark_ff::Field;
     use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-    use ark_std::UniformRand;
+    use ark_std::{UniformRand, Zero};
     use merlin::Transcript;
-    use rand::Rng;
+    use pedersen::opening_protocol::{OpeningProofMulti, OpeningProofMultiTranscriptable};
+    use pedersen::pedersen_config::{Generators, PedersenComm, PedersenConfig};
+    use pedersen::transcript::{
+        append_message, challenge_bytes, witness_rng, OpeningMultiTranscript, OPENING_MULTI_LABELS,
+    };
+    use std::any::{Any, TypeId};
+    use std::collections::HashMap;
     use std::convert::TryInto;
+    use std::fmt;
+    use std::sync::{Arc, Mutex, OnceLock};
 
     pub fn extract_u64_from_compressed_data(compressed_data: &[u8]) -> Result<u64, &'static str> {
         // Ensure we have at least 8 bytes to extract a u64
@@ -31,18 +47,491 @@ pub mod rewards {
     ) -> Result<(u64, B::ScalarField), String> {
         let res = inner_product(a, b);
 
-        let mut compressed_bytes = Vec::new();
-        if let Err(e) = res.serialize_compressed(&mut compressed_bytes) {
-            return Err(format!("Serialization error: {}", e));
-        }
-        let extracted_u64 = extract_u64_from_compressed_data(&compressed_bytes)?;
+        let extracted_u64 =
+            extract_u64_from_compressed_data(&encode_scalar(&res, Endianness::Little))?;
 
         Ok((extracted_u64, res))
     }
 
+    /// reward_due. This function returns true if `policy_state` yields a reward for
+    /// `spend_state`, i.e if their inner product is not zero. Otherwise, the Spend/Verify
+    /// Protocol can take its fast path, which skips the rewards proof. This function panics if
+    /// the vectors have different lengths.
+    /// # Arguments
+    /// * `spend_state` - the spent values.
+    /// * `policy_state` - the policy vector.
+    pub fn reward_due<B: CurveConfig>(
+        spend_state: &[<B as CurveConfig>::ScalarField],
+        policy_state: &[<B as CurveConfig>::ScalarField],
+    ) -> bool {
+        inner_product(spend_state, policy_state) != <B as CurveConfig>::ScalarField::ZERO
+    }
+
+    /// PolicyId. The published id of a policy vector, i.e a hiding commitment to it under a
+    /// secret salt (see `policy_id`).
+    pub type PolicyId = [u8; 32];
+
+    /// PolicySalt. The secret salt under which a server commits to its policy vectors.
+    pub type PolicySalt = [u8; 32];
+
+    /// policy_id. This function returns the id of `policy_state` under `salt`, i.e the hash of
+    /// `salt` and of the canonical encoding of `policy_state`. The id binds the server to the
+    /// policy, and, as long as `salt` is uniformly random and kept secret, it hides the policy
+    /// even if the policy can only take a few values, so that the server can publish it and
+    /// clients and auditors can check a rewards proof against it (see `verify_with_policy_id`).
+    /// N.B An unsalted hash of the policy would reveal it to anyone who can enumerate the
+    /// likely policies.
+    /// # Arguments
+    /// * `policy_state` - the policy vector.
+    /// * `salt` - the secret salt.
+    pub fn policy_id<B: BoomerangConfig>(
+        policy_state: &[<B as CurveConfig>::ScalarField],
+        salt: &PolicySalt,
+    ) -> PolicyId {
+        let mut transcript = B::CONTEXT.transcript(b"Boomerang policy id");
+        append_message(&mut transcript, b"salt", salt);
+        let mut bytes = Vec::new();
+        policy_state.serialize_compressed(&mut bytes).unwrap();
+        append_message(&mut transcript, b"policy_state", &bytes);
+
+        let mut id = [0u8; 32];
+        challenge_bytes(&mut transcript, b"id", &mut id);
+        id
+    }
+
+    /// policy_binding. This function returns the hash of `policy_id` and of the canonical
+    /// encoding of `spend_state`, which a bound rewards proof absorbs into its transcripts, so
+    /// that it only verifies for the same policy id and state.
+    /// # Arguments
+    /// * `spend_state` - the spent values.
+    /// * `policy_id` - the id of the policy.
+    fn policy_binding<B: BoomerangConfig>(
+        spend_state: &[<B as CurveConfig>::ScalarField],
+        policy_id: &PolicyId,
+    ) -> [u8; 32] {
+        let mut transcript = B::CONTEXT.transcript(b"Boomerang policy binding");
+        append_message(&mut transcript, b"policy_id", policy_id);
+        let mut bytes = Vec::new();
+        spend_state.serialize_compressed(&mut bytes).unwrap();
+        append_message(&mut transcript, b"spend_state", &bytes);
+
+        let mut binding = [0u8; 32];
+        challenge_bytes(&mut transcript, b"binding", &mut binding);
+        binding
+    }
+
+    /// rewards_transcript. This function returns the transcript of a rewards proof as
+    /// `session_transcript` does, with `binding`, if any, absorbed into it.
+    /// # Arguments
+    /// * `label` - the label of the transcript.
+    /// * `session_id` - the session that the proof is bound to.
+    /// * `binding` - the optional policy binding that the proof is bound to.
+    fn rewards_transcript<B: BoomerangConfig>(
+        label: &'static [u8],
+        session_id: &SessionId,
+        binding: Option<&[u8; 32]>,
+    ) -> Transcript {
+        let mut transcript = session_transcript::<B>(label, session_id);
+        if let Some(binding) = binding {
+            append_message(&mut transcript, b"policy_binding", binding);
+        }
+        transcript
+    }
+
+    /// apply_mask. This function returns `state` with every entry whose `mask` bit is false
+    /// set to zero, so that an inner product with the result only counts the masked-in entries.
+    /// This function fails if `state` and `mask` have different lengths.
+    /// # Arguments
+    /// * `state` - the vector to mask.
+    /// * `mask` - the public mask.
+    pub fn apply_mask<F: Field>(state: &[F], mask: &[bool]) -> Result<Vec<F>, String> {
+        if state.len() != mask.len() {
+            return Err(format!(
+                "Boomerang verification: mask has length {}, but the state has length {}",
+                mask.len(),
+                state.len()
+            ));
+        }
+
+        Ok(state
+            .iter()
+            .zip(mask)
+            .map(|(x, &bit)| if bit { *x } else { F::zero() })
+            .collect())
+    }
+
+    /// SparseState. This struct holds a state vector of `len` values as the (index, value)
+    /// pairs of its non-zero entries, sorted by index. For catalogs with many incentives, of
+    /// which a user only touches a few, this keeps the cost of commitments and rewards proofs
+    /// proportional to the number of non-zero entries rather than to the catalog size.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct SparseState<F: Field> {
+        len: usize,
+        entries: Vec<(usize, F)>,
+    }
+
+    impl<F: Field> SparseState<F> {
+        /// new. This function creates a sparse state of `len` values from its (index, value)
+        /// pairs, in any order. Zero values are dropped. This function fails if an index is not
+        /// below `len`, or appears more than once.
+        /// # Arguments
+        /// * `len` - the length of the state, i.e the size of the catalog.
+        /// * `entries` - the (index, value) pairs of the state.
+        pub fn new(len: usize, entries: &[(usize, F)]) -> Result<Self, String> {
+            let mut entries = entries.to_vec();
+            entries.sort_by_key(|(index, _)| *index);
+            if let Some((index, _)) = entries.iter().find(|(index, _)| *index >= len) {
+                return Err(format!(
+                    "Sparse state: index {} is out of range for length {}",
+                    index, len
+                ));
+            }
+            if let Some(pair) = entries.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+                return Err(format!("Sparse state: index {} is repeated", pair[0].0));
+            }
+            entries.retain(|(_, value)| !value.is_zero());
+
+            Ok(Self { len, entries })
+        }
+
+        /// from_dense. This function returns the sparse form of `state`.
+        /// # Arguments
+        /// * `state` - the dense state.
+        pub fn from_dense(state: &[F]) -> Self {
+            Self {
+                len: state.len(),
+                entries: state
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, value)| !value.is_zero())
+                    .map(|(index, value)| (index, *value))
+                    .collect(),
+            }
+        }
+
+        /// to_dense. This function returns the state as a vector of `len` values.
+        pub fn to_dense(&self) -> Vec<F> {
+            let mut state = vec![F::zero(); self.len];
+            for (index, value) in &self.entries {
+                state[*index] = *value;
+            }
+            state
+        }
+
+        /// len. This function returns the length of the state, including its zero entries.
+        pub fn len(&self) -> usize {
+            self.len
+        }
+
+        /// is_empty. This function returns true if the state has length zero.
+        pub fn is_empty(&self) -> bool {
+            self.len == 0
+        }
+
+        /// entries. This function returns the non-zero (index, value) pairs, sorted by index.
+        pub fn entries(&self) -> &[(usize, F)] {
+            &self.entries
+        }
+
+        /// inner_product. This function returns the inner product of the state with `other`,
+        /// which only reads `other` at the non-zero entries. This function fails if `other`
+        /// does not have length `len`.
+        /// # Arguments
+        /// * `other` - the dense vector, e.g. the policy.
+        pub fn inner_product(&self, other: &[F]) -> Result<F, String> {
+            if other.len() != self.len {
+                return Err(format!(
+                    "Sparse state: vector has length {}, but the state has length {}",
+                    other.len(),
+                    self.len
+                ));
+            }
+            Ok(self
+                .entries
+                .iter()
+                .map(|(index, value)| *value * other[*index])
+                .sum())
+        }
+
+        /// padded_support. This function returns the indices that a sparse rewards proof
+        /// runs over: the indices of the non-zero entries, padded with the lowest unused indices
+        /// up to the next power of two, in increasing order. The padding entries are zero on
+        /// both sides of the proof.
+        fn padded_support(&self) -> Vec<usize> {
+            let size = self.entries.len().max(1).next_power_of_two();
+            let mut support: Vec<usize> = self.entries.iter().map(|(index, _)| *index).collect();
+            let mut used = support.iter().peekable();
+            let padding: Vec<usize> = (0..)
+                .filter(|index| {
+                    if used.peek() == Some(&index) {
+                        used.next();
+                        false
+                    } else {
+                        true
+                    }
+                })
+                .take(size - support.len())
+                .collect();
+            support.extend(padding);
+            support.sort_unstable();
+            support
+        }
+
+        /// support_values. This function returns the values of the state at `support`, which
+        /// must be sorted.
+        fn support_values(&self, support: &[usize]) -> Vec<F> {
+            let mut entries = self.entries.iter().peekable();
+            support
+                .iter()
+                .map(|index| match entries.peek() {
+                    Some((i, value)) if i == index => {
+                        entries.next();
+                        *value
+                    }
+                    _ => F::zero(),
+                })
+                .collect()
+        }
+    }
+
+    /// RewardsProofError. This enum describes why a rewards proof was rejected, so that
+    /// servers can report the reason for a rejected spend.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum RewardsProofError {
+        /// RangeProof: the range proof on the reward does not verify.
+        RangeProof(ProofError),
+        /// LinearProof: the proof that the reward is the inner product of the states does not verify.
+        LinearProof(ProofError),
+        /// CommitmentMismatch: the proof was not made against the expected state commitment.
+        CommitmentMismatch,
+        /// InvalidMask: the mask does not match the public state.
+        InvalidMask(String),
+        /// Deserialization: the proof could not be read.
+        Deserialization(String),
+        /// UnknownVersion: the proof was written in an unknown wire format.
+        UnknownVersion(u8),
+        /// UnexpectedGenerators: a legacy proof embeds other generators than the expected ones.
+        UnexpectedGenerators,
+        /// Cancelled: the verification was given up because its cancellation token was cancelled.
+        Cancelled,
+        /// RewardMismatch: the proof does not commit to the reward that the policy gives.
+        RewardMismatch,
+        /// JointProof: the joint check of the range and linear proofs of a `JointRewardsProof`
+        /// does not pass.
+        JointProof(ProofError),
+    }
+
+    impl fmt::Display for RewardsProofError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                RewardsProofError::RangeProof(e) => write!(
+                    f,
+                    "Boomerang verification: reward range proof verification failed: {}",
+                    e
+                ),
+                RewardsProofError::LinearProof(e) => write!(
+                    f,
+                    "Boomerang verification: reward linear proof verification failed: {}",
+                    e
+                ),
+                RewardsProofError::CommitmentMismatch => write!(
+                    f,
+                    "Boomerang verification: reward proof does not match the state commitment"
+                ),
+                RewardsProofError::InvalidMask(e) => write!(f, "{}", e),
+                RewardsProofError::Deserialization(e) => write!(f, "Serialization error: {}", e),
+                RewardsProofError::UnknownVersion(version) => write!(
+                    f,
+                    "Boomerang verification: unknown reward proof version {}",
+                    version
+                ),
+                RewardsProofError::UnexpectedGenerators => write!(
+                    f,
+                    "Boomerang verification: legacy reward proof uses unexpected generators"
+                ),
+                RewardsProofError::Cancelled => {
+                    write!(
+                        f,
+                        "Boomerang verification: reward proof verification was cancelled"
+                    )
+                }
+                RewardsProofError::RewardMismatch => write!(
+                    f,
+                    "Boomerang verification: reward proof does not commit to the policy's reward"
+                ),
+                RewardsProofError::JointProof(e) => write!(
+                    f,
+                    "Boomerang verification: joint reward proof verification failed: {}",
+                    e
+                ),
+            }
+        }
+    }
+
+    impl std::error::Error for RewardsProofError {}
+
+    /// REWARDS_PROOF_VERSION. The version of the current rewards proof wire format, as
+    /// written by `BRewardsProof::to_versioned_bytes`. Version 0 is the format of
+    /// `LegacyBRewardsProof`, which embedded the generators in every proof.
+    pub const REWARDS_PROOF_VERSION: u8 = 1;
+
+    /// RewardsGenerators. This struct holds the generators that rewards proofs are made and
+    /// checked against. Both parties derive them deterministically, so they are never sent.
+    /// The generator vectors are shared, so cloning this struct is cheap.
+    pub struct RewardsGenerators<B: BoomerangConfig> {
+        /// pc_gens: the Pedersen generators of the reward commitment.
+        pub pc_gens: PedersenGens<sw::Affine<B>>,
+        /// bp_gens: the generators of the range proof and of the state vector.
+        pub bp_gens: Arc<BulletproofGens<sw::Affine<B>>>,
+        /// range_key: the generators of `bp_gens` that 64-bit range proofs are made against,
+        /// collected once so that every proof can borrow them.
+        range_key: Arc<ProverKey<sw::Affine<B>>>,
+    }
+
+    impl<B: BoomerangConfig> Clone for RewardsGenerators<B> {
+        fn clone(&self) -> Self {
+            Self {
+                pc_gens: self.pc_gens,
+                bp_gens: Arc::clone(&self.bp_gens),
+                range_key: Arc::clone(&self.range_key),
+            }
+        }
+    }
+
+    /// SHARED_GENERATORS. The default generators of every curve that `RewardsGenerators::shared`
+    /// was called with, keyed by the type of the curve.
+    static SHARED_GENERATORS: OnceLock<Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>> =
+        OnceLock::new();
+
+    impl<B: BoomerangConfig> Default for RewardsGenerators<B> {
+        fn default() -> Self {
+            Self::new(64)
+        }
+    }
+
+    impl<B: BoomerangConfig> RewardsGenerators<B> {
+        /// new. This function derives the generators for state vectors of up to `capacity`
+        /// values. The capacity must be at least 64, which is the size of the range proof.
+        /// The Pedersen generators are derived from the application label of `B::CONTEXT`, so
+        /// that deployments with different labels never share commitment keys.
+        /// # Arguments
+        /// * `capacity` - the maximum length of a state vector.
+        pub fn new(capacity: usize) -> Self {
+            assert!(capacity >= 64, "RewardsGenerators: capacity is too small");
+            let bp_gens = BulletproofGens::new(capacity, 1);
+            let range_key = ProverKey::new(&bp_gens, 64, 1)
+                .expect("RewardsGenerators: the capacity was checked");
+            Self {
+                pc_gens: PedersenGens::new_from_label(
+                    &[b"Boomerang rewards ".as_slice(), B::CONTEXT.app_label].concat(),
+                ),
+                bp_gens: Arc::new(bp_gens),
+                range_key: Arc::new(range_key),
+            }
+        }
+
+        /// prove_range. This function proves that `value` fits in 64 bits, against the
+        /// collected range proof generators, and returns the proof and the commitment to `value`.
+        /// # Arguments
+        /// * `transcript` - the transcript that the proof is bound to.
+        /// * `value` - the value in range.
+        /// * `blind` - the blinding factor of the commitment.
+        /// * `rng` - the source of randomness.
+        fn prove_range(
+            &self,
+            transcript: &mut Transcript,
+            value: u64,
+            blind: <B as CurveConfig>::ScalarField,
+            rng: &mut impl BoomerangRng,
+        ) -> Result<(RangeProof<sw::Affine<B>>, sw::Affine<B>), ProofError> {
+            let (proof, comms) = RangeProof::prove_multiple_with_key(
+                &self.bp_gens,
+                &self.range_key,
+                &self.pc_gens,
+                transcript,
+                &[value],
+                &[blind],
+                rng,
+            )?;
+            Ok((proof, comms[0]))
+        }
+
+        /// shared. This function returns the default generators, which are derived once per
+        /// process and then shared. The protocols use this function so that the generators are
+        /// not derived again for every proof.
+        pub fn shared() -> Self {
+            let mut cache = SHARED_GENERATORS
+                .get_or_init(|| Mutex::new(HashMap::new()))
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            cache
+                .entry(TypeId::of::<B>())
+                .or_insert_with(|| Box::new(Self::default()))
+                .downcast_ref::<Self>()
+                .expect("RewardsGenerators: mismatched cache entry")
+                .clone()
+        }
+
+        /// matches. Returns true if `pc_gens` and `bp_gens` are the same generators as these.
+        fn matches(
+            &self,
+            pc_gens: &PedersenGens<sw::Affine<B>>,
+            bp_gens: &BulletproofGens<sw::Affine<B>>,
+        ) -> bool {
+            self.pc_gens.B == pc_gens.B
+                && self.pc_gens.B_blinding == pc_gens.B_blinding
+                && self.bp_gens.digest() == bp_gens.digest()
+        }
+    }
+
     // Rewards proof struct
     #[derive(CanonicalSerialize, CanonicalDeserialize)]
     pub struct BRewardsProof<B: BoomerangConfig> {
+        // the range proof
+        pub range_proof: RangeProof<sw::Affine<B>>,
+        // the commitment of range proof
+        pub r_comms: sw::Affine<B>,
+        // the linear proof
+        pub linear_proof: LinearProof<sw::Affine<B>>,
+        // the commitment of linear proof
+        pub l_comms: sw::Affine<B>,
+    }
+
+    impl<B: BoomerangConfig> Clone for BRewardsProof<B> {
+        fn clone(&self) -> Self {
+            BRewardsProof {
+                range_proof: self.range_proof.clone(),
+                r_comms: self.r_comms,
+                linear_proof: self.linear_proof.clone(),
+                l_comms: self.l_comms,
+            }
+        }
+    }
+
+    /// RewardOpening. This struct holds the blinding factors of the two reward commitments of a
+    /// rewards proof. The prover hands it to a party that knows the policy, which can then check
+    /// which reward the proof commits to (see `BRewardsProof::check_reward`).
+    #[derive(CanonicalSerialize, CanonicalDeserialize)]
+    pub struct RewardOpening<B: BoomerangConfig> {
+        /// range_blind: the blinding factor of the range proof commitment, `r_comms`.
+        pub range_blind: <B as CurveConfig>::ScalarField,
+        /// linear_blind: the blinding factor of the linear proof commitment, `l_comms`.
+        pub linear_blind: <B as CurveConfig>::ScalarField,
+    }
+
+    impl<B: BoomerangConfig> Clone for RewardOpening<B> {
+        fn clone(&self) -> Self {
+            *self
+        }
+    }
+
+    impl<B: BoomerangConfig> Copy for RewardOpening<B> {}
+
+    /// LegacyBRewardsProof. This struct is the version 0 wire format of a rewards proof, which
+    /// carried its own generators. It is only kept so that proofs that were serialized in that
+    /// format can be read and migrated with `migrate`.
+    #[derive(CanonicalSerialize, CanonicalDeserialize)]
+    pub struct LegacyBRewardsProof<B: BoomerangConfig> {
         // the range proof
         pub range_proof: RangeProof<sw::Affine<B>>,
         // the pc gens for range proof
@@ -61,56 +550,190 @@ pub mod rewards {
         pub l_comms: sw::Affine<B>,
     }
 
-    impl<B: BoomerangConfig> Clone for BRewardsProof<B> {
-        fn clone(&self) -> Self {
-            BRewardsProof {
-                range_proof: self.range_proof.clone(),
-                range_gensp_r: self.range_gensp_r,
-                range_gensb_r: self.range_gensb_r.clone(),
+    impl<B: BoomerangConfig> LegacyBRewardsProof<B> {
+        /// migrate. This function converts a version 0 proof into the current format. This
+        /// function fails if the proof embeds other generators than `gens`, since the proof
+        /// would then not verify against them.
+        /// # Arguments
+        /// * `gens` - the generators that the proof should have been made against.
+        pub fn migrate(
+            self,
+            gens: &RewardsGenerators<B>,
+        ) -> Result<BRewardsProof<B>, RewardsProofError> {
+            if !gens.matches(&self.range_gensp_r, &self.range_gensb_r)
+                || !gens.matches(&self.range_gensp_l, &self.range_gensb_l)
+            {
+                return Err(RewardsProofError::UnexpectedGenerators);
+            }
+
+            Ok(BRewardsProof {
+                range_proof: self.range_proof,
                 r_comms: self.r_comms,
-                linear_proof: self.linear_proof.clone(),
-                range_gensp_l: self.range_gensp_r,
-                range_gensb_l: self.range_gensb_r.clone(),
+                linear_proof: self.linear_proof,
                 l_comms: self.l_comms,
-            }
+            })
         }
     }
 
+    /// RewardRange. A range proof on a reward, with its commitment to the reward and the
+    /// blinding factor thereof.
+    type RewardRange<B> = (
+        RangeProof<sw::Affine<B>>,
+        sw::Affine<B>,
+        <B as CurveConfig>::ScalarField,
+    );
+
     impl<B: BoomerangConfig> BRewardsProof<B> {
+        /// to_versioned_bytes. This function serializes the proof, prefixed with
+        /// `REWARDS_PROOF_VERSION`, for storage that may outlive the current wire format.
+        pub fn to_versioned_bytes(&self) -> Vec<u8> {
+            let mut bytes = vec![REWARDS_PROOF_VERSION];
+            self.serialize_compressed(&mut bytes).unwrap();
+            bytes
+        }
+
+        /// from_versioned_bytes. This function reads a proof that was written by
+        /// `to_versioned_bytes`, or a version 0 proof with a leading 0 byte, which is migrated
+        /// to the current format.
+        /// # Arguments
+        /// * `bytes` - the versioned proof.
+        /// * `gens` - the rewards generators.
+        pub fn from_versioned_bytes(
+            bytes: &[u8],
+            gens: &RewardsGenerators<B>,
+        ) -> Result<Self, RewardsProofError> {
+            let (version, body) = bytes.split_first().ok_or_else(|| {
+                RewardsProofError::Deserialization("empty reward proof".to_string())
+            })?;
+            match *version {
+                0 => LegacyBRewardsProof::deserialize_compressed(body)
+                    .map_err(|e| RewardsProofError::Deserialization(e.to_string()))?
+                    .migrate(gens),
+                REWARDS_PROOF_VERSION => Self::deserialize_compressed(body)
+                    .map_err(|e| RewardsProofError::Deserialization(e.to_string())),
+                _ => Err(RewardsProofError::UnknownVersion(*version)),
+            }
+        }
+
+        #[cfg_attr(
+            feature = "tracing",
+            tracing::instrument(name = "rewards_proof_create", skip_all, fields(size, elapsed_us))
+        )]
         pub fn prove(
+            gens: &RewardsGenerators<B>,
             spend_state: &[<B as CurveConfig>::ScalarField],
             policy_state: &[<B as CurveConfig>::ScalarField],
             reward_u64: u64,
             reward: <B as CurveConfig>::ScalarField,
-            rng: &mut impl Rng,
+            session_id: &SessionId,
+            rng: &mut impl BoomerangRng,
         ) -> Result<Self, String> {
-            // Prove that the reward falls between the range
-            let max_reward = 64; // TODO: should be app specific as it defines the maximum ammount of rewards
+            Self::prove_with_opening(
+                gens,
+                spend_state,
+                policy_state,
+                reward_u64,
+                reward,
+                session_id,
+                rng,
+            )
+            .map(|(proof, _)| proof)
+        }
 
-            let pc_gens_r: PedersenGens<sw::Affine<B>> = PedersenGens::default();
-            let bp_gens_r = BulletproofGens::new(max_reward, 1);
-            let mut transcript_r = Transcript::new(b"Boomerang verify range proof");
-            let blind = <B as CurveConfig>::ScalarField::rand(rng);
-            let (r_proof, r_comms) = RangeProof::prove_single(
-                &bp_gens_r,
-                &pc_gens_r,
-                &mut transcript_r,
+        /// prove_with_opening. This function generates a rewards proof as in `prove`, and
+        /// also returns the opening of its reward commitments.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `spend_state` - the public vector.
+        /// * `policy_state` - the private policy vector.
+        /// * `reward_u64` - the reward, as an integer.
+        /// * `reward` - the reward, i.e the inner product of the two vectors.
+        /// * `session_id` - the session that the proof is bound to.
+        /// * `rng` - the source of randomness.
+        pub fn prove_with_opening(
+            gens: &RewardsGenerators<B>,
+            spend_state: &[<B as CurveConfig>::ScalarField],
+            policy_state: &[<B as CurveConfig>::ScalarField],
+            reward_u64: u64,
+            reward: <B as CurveConfig>::ScalarField,
+            session_id: &SessionId,
+            rng: &mut impl BoomerangRng,
+        ) -> Result<(Self, RewardOpening<B>), String> {
+            Self::prove_bound(
+                gens,
+                spend_state,
+                policy_state,
                 reward_u64,
-                &blind,
-                max_reward,
+                reward,
+                session_id,
+                None,
+                rng,
             )
-            .map_err(|e| format!("Range proof error: {:?}", e))?;
+        }
+
+        /// prove_with_policy_id. This function generates a rewards proof as in
+        /// `prove_with_opening`, bound to `policy_id` and `spend_state`. The proof only verifies
+        /// with `verify_with_policy_id` and the same policy id.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `spend_state` - the public vector.
+        /// * `policy_state` - the private policy vector.
+        /// * `policy_id` - the published id of `policy_state` (see `policy_id`).
+        /// * `reward_u64` - the reward, as an integer.
+        /// * `reward` - the reward, i.e the inner product of the two vectors.
+        /// * `session_id` - the session that the proof is bound to.
+        /// * `rng` - the source of randomness.
+        #[allow(clippy::too_many_arguments)]
+        pub fn prove_with_policy_id(
+            gens: &RewardsGenerators<B>,
+            spend_state: &[<B as CurveConfig>::ScalarField],
+            policy_state: &[<B as CurveConfig>::ScalarField],
+            policy_id: &PolicyId,
+            reward_u64: u64,
+            reward: <B as CurveConfig>::ScalarField,
+            session_id: &SessionId,
+            rng: &mut impl BoomerangRng,
+        ) -> Result<(Self, RewardOpening<B>), String> {
+            let binding = policy_binding::<B>(spend_state, policy_id);
+            Self::prove_bound(
+                gens,
+                spend_state,
+                policy_state,
+                reward_u64,
+                reward,
+                session_id,
+                Some(&binding),
+                rng,
+            )
+        }
 
-            let pc_gens_l: PedersenGens<sw::Affine<B>> = PedersenGens::default();
-            let bp_gens_l = BulletproofGens::new(max_reward, 1);
-            let g: Vec<_> = bp_gens_l
+        /// prove_bound. This function generates a rewards proof, with `binding`, if any,
+        /// absorbed into its transcripts, and returns the opening of its reward commitments.
+        #[allow(clippy::too_many_arguments)]
+        fn prove_bound(
+            gens: &RewardsGenerators<B>,
+            spend_state: &[<B as CurveConfig>::ScalarField],
+            policy_state: &[<B as CurveConfig>::ScalarField],
+            reward_u64: u64,
+            reward: <B as CurveConfig>::ScalarField,
+            session_id: &SessionId,
+            binding: Option<&[u8; 32]>,
+            rng: &mut impl BoomerangRng,
+        ) -> Result<(Self, RewardOpening<B>), String> {
+            let timer = SpanTimer::start();
+            // Prove that the reward falls between the range
+            let (r_proof, r_comms, blind) =
+                Self::prove_reward_range(gens, reward_u64, session_id, binding, rng)?;
+
+            let g: Vec<_> = gens
+                .bp_gens
                 .share(0)
                 .G(spend_state.len())
                 .cloned()
                 .collect::<Vec<sw::Affine<B>>>();
 
-            let f = pc_gens_l.B;
-            let b = pc_gens_l.B_blinding;
+            let f = gens.pc_gens.B;
+            let b = gens.pc_gens.B_blinding;
 
             // c_t = <a, g> + blind_l * b + c * f
             // the policy_state is the witness and it is private
@@ -127,7 +750,8 @@ pub mod rewards {
                     .unwrap()
                     .into_affine();
 
-            let mut transcript_l = Transcript::new(b"Boomerang verify linear proof");
+            let mut transcript_l =
+                rewards_transcript::<B>(b"Boomerang verify linear proof", session_id, binding);
             let l_proof = LinearProof::<sw::Affine<B>>::create(
                 &mut transcript_l,
                 rng,
@@ -135,58 +759,197 @@ pub mod rewards {
                 blind_l,
                 policy_state.to_vec(),
                 spend_state.to_vec(),
-                g.clone(),
+                g,
                 &f,
                 &b,
             )
             .map_err(|e| format!("Linear proof error: {:?}", e))?;
 
-            Ok(Self {
+            let proof = timer.finish(Self {
                 range_proof: r_proof,
-                range_gensp_r: pc_gens_r,
-                range_gensb_r: bp_gens_r,
                 r_comms,
                 linear_proof: l_proof,
-                range_gensp_l: pc_gens_l,
-                range_gensb_l: bp_gens_l,
                 l_comms: c_t,
-            })
+            });
+            Ok((
+                proof,
+                RewardOpening {
+                    range_blind: blind,
+                    linear_blind: blind_l,
+                },
+            ))
         }
 
-        pub fn verify(
+        /// prove_reward_range. This function proves that `reward_u64` is in range, and returns
+        /// the range proof, its commitment to the reward and the blinding factor thereof.
+        fn prove_reward_range(
+            gens: &RewardsGenerators<B>,
+            reward_u64: u64,
+            session_id: &SessionId,
+            binding: Option<&[u8; 32]>,
+            rng: &mut impl BoomerangRng,
+        ) -> Result<RewardRange<B>, String> {
+            // TODO: the 64 bits of the range proof should be app specific, as they define the
+            // maximum amount of rewards.
+            let mut transcript_r =
+                rewards_transcript::<B>(b"Boomerang verify range proof", session_id, binding);
+            let blind = <B as CurveConfig>::ScalarField::rand(rng);
+            let (r_proof, r_comms) = gens
+                .prove_range(&mut transcript_r, reward_u64, blind, rng)
+                .map_err(|e| format!("Range proof error: {:?}", e))?;
+            Ok((r_proof, r_comms, blind))
+        }
+
+        /// check_reward. This function checks that the proof commits to the reward that
+        /// `policy_state` gives for `spend_state`, i.e that both reward commitments open, under
+        /// `opening`, to the inner product of the two vectors. This check does not replace
+        /// `verify`: it shows which reward the proof is about, not that the proof is valid.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `opening` - the opening of the reward commitments.
+        /// * `spend_state` - the public vector.
+        /// * `policy_state` - the policy vector.
+        pub fn check_reward(
             &self,
+            gens: &RewardsGenerators<B>,
+            opening: &RewardOpening<B>,
             spend_state: &[<B as CurveConfig>::ScalarField],
-        ) -> Result<(), String> {
-            let max_reward = 64;
+            policy_state: &[<B as CurveConfig>::ScalarField],
+        ) -> Result<(), RewardsProofError> {
+            if spend_state.len() != policy_state.len() {
+                return Err(RewardsProofError::RewardMismatch);
+            }
+            let reward = inner_product(spend_state, policy_state);
+
+            if gens.pc_gens.commit(reward, opening.range_blind) != self.r_comms {
+                return Err(RewardsProofError::RewardMismatch);
+            }
+
+            let points: Vec<_> = gens
+                .bp_gens
+                .share(0)
+                .G(policy_state.len())
+                .cloned()
+                .chain([gens.pc_gens.B_blinding, gens.pc_gens.B])
+                .collect();
+            let scalars: Vec<_> = policy_state
+                .iter()
+                .cloned()
+                .chain([opening.linear_blind, reward])
+                .collect();
+            let l_comms = <sw::Affine<B> as AffineRepr>::Group::msm(&points, &scalars)
+                .map_err(|_| RewardsProofError::RewardMismatch)?;
+            if l_comms.into_affine() != self.l_comms {
+                return Err(RewardsProofError::RewardMismatch);
+            }
+
+            Ok(())
+        }
+
+        /// verify_with_rng. This function checks the rewards proof against `spend_state`.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `spend_state` - the public vector.
+        /// * `session_id` - the session that the proof is bound to.
+        /// * `rng` - the RNG that is used to batch the range proof checks.
+        #[cfg_attr(
+            feature = "tracing",
+            tracing::instrument(name = "rewards_proof_verify", skip_all, fields(size, elapsed_us))
+        )]
+        pub fn verify_with_rng<T: BoomerangRng>(
+            &self,
+            gens: &RewardsGenerators<B>,
+            spend_state: &[<B as CurveConfig>::ScalarField],
+            session_id: &SessionId,
+            rng: &mut T,
+        ) -> Result<(), RewardsProofError> {
+            self.verify_with(gens, spend_state, session_id, None, rng, None)
+        }
+
+        /// verify_with_policy_id. This function checks a rewards proof that was made with
+        /// `prove_with_policy_id` against `spend_state` and `policy_id`. This function fails if
+        /// the proof was made for another policy id, or without one.
+        /// N.B The caller must check `policy_id` against the id that the server published for
+        /// the policy that it expects: an id that comes with the proof shows nothing.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `spend_state` - the public vector.
+        /// * `session_id` - the session that the proof is bound to.
+        /// * `policy_id` - the published id of the policy that the proof is bound to.
+        /// * `rng` - the RNG that is used to batch the range proof checks.
+        #[cfg_attr(
+            feature = "tracing",
+            tracing::instrument(name = "rewards_proof_verify", skip_all, fields(size, elapsed_us))
+        )]
+        pub fn verify_with_policy_id<T: BoomerangRng>(
+            &self,
+            gens: &RewardsGenerators<B>,
+            spend_state: &[<B as CurveConfig>::ScalarField],
+            session_id: &SessionId,
+            policy_id: &PolicyId,
+            rng: &mut T,
+        ) -> Result<(), RewardsProofError> {
+            let binding = policy_binding::<B>(spend_state, policy_id);
+            self.verify_with(gens, spend_state, session_id, Some(&binding), rng, None)
+        }
+
+        /// verify_cancellable. This function checks the rewards proof against `spend_state`
+        /// like `verify_with_rng`, but checks `cancel` between the steps of the verification,
+        /// and between the chunks of the range proof check. This function returns
+        /// `RewardsProofError::Cancelled` if `cancel` is cancelled before the check finishes.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `spend_state` - the public vector.
+        /// * `session_id` - the session that the proof is bound to.
+        /// * `rng` - the RNG that is used to batch the range proof checks.
+        /// * `cancel` - the cancellation token, e.g. one with a timeout.
+        #[cfg_attr(
+            feature = "tracing",
+            tracing::instrument(name = "rewards_proof_verify", skip_all, fields(size, elapsed_us))
+        )]
+        pub fn verify_cancellable<T: BoomerangRng>(
+            &self,
+            gens: &RewardsGenerators<B>,
+            spend_state: &[<B as CurveConfig>::ScalarField],
+            session_id: &SessionId,
+            rng: &mut T,
+            cancel: &CancellationToken,
+        ) -> Result<(), RewardsProofError> {
+            self.verify_with(gens, spend_state, session_id, None, rng, Some(cancel))
+        }
+
+        fn verify_with<T: BoomerangRng>(
+            &self,
+            gens: &RewardsGenerators<B>,
+            spend_state: &[<B as CurveConfig>::ScalarField],
+            session_id: &SessionId,
+            binding: Option<&[u8; 32]>,
+            rng: &mut T,
+            cancel: Option<&CancellationToken>,
+        ) -> Result<(), RewardsProofError> {
+            let timer = SpanTimer::start();
+            let check_cancel = || match cancel {
+                Some(cancel) if cancel.is_cancelled() => Err(RewardsProofError::Cancelled),
+                _ => Ok(()),
+            };
 
             // Verify the range proof
-            let mut transcript_r = Transcript::new(b"Boomerang verify range proof");
-            self.range_proof
-                .verify_single(
-                    &self.range_gensb_r,
-                    &self.range_gensp_r,
-                    &mut transcript_r,
-                    &self.r_comms,
-                    max_reward,
-                )
-                .map_err(|e| {
-                    format!(
-                        "Boomerang verification: reward range proof verification failed: {}",
-                        e
-                    )
-                })?;
+            check_cancel()?;
+            self.verify_reward_range(gens, session_id, binding, rng, cancel)?;
 
-            let g: Vec<_> = self
-                .range_gensb_l
+            let g: Vec<_> = gens
+                .bp_gens
                 .share(0)
                 .G(spend_state.len())
                 .cloned()
                 .collect::<Vec<sw::Affine<B>>>();
-            let f = self.range_gensp_l.B;
-            let b = self.range_gensp_l.B_blinding;
-            let mut transcript_l = Transcript::new(b"Boomerang verify linear proof");
+            let f = gens.pc_gens.B;
+            let b = gens.pc_gens.B_blinding;
+            let mut transcript_l =
+                rewards_transcript::<B>(b"Boomerang verify linear proof", session_id, binding);
 
             // Verify the linear proof
+            check_cancel()?;
             self.linear_proof
                 .verify(
                     &mut transcript_l,
@@ -196,27 +959,714 @@ pub mod rewards {
                     &b,
                     spend_state.to_vec(),
                 )
-                .map_err(|e| {
-                    format!(
-                        "Boomerang verification: reward linear proof verification failed: {}",
-                        e
-                    )
-                })?;
+                .map_err(RewardsProofError::LinearProof)?;
 
             // Return Ok if both verifications succeed
+            timer.record(self);
+            Ok(())
+        }
+
+        /// verify_reward_range. This function checks the range proof on the reward, checking
+        /// `cancel`, if any, between its chunks.
+        fn verify_reward_range<T: BoomerangRng>(
+            &self,
+            gens: &RewardsGenerators<B>,
+            session_id: &SessionId,
+            binding: Option<&[u8; 32]>,
+            rng: &mut T,
+            cancel: Option<&CancellationToken>,
+        ) -> Result<(), RewardsProofError> {
+            let max_reward = 64;
+            let mut transcript_r =
+                rewards_transcript::<B>(b"Boomerang verify range proof", session_id, binding);
+            let range_check = match cancel {
+                Some(cancel) => self.range_proof.verify_multiple_cancellable(
+                    &gens.bp_gens,
+                    &gens.pc_gens,
+                    &mut transcript_r,
+                    &[self.r_comms],
+                    max_reward,
+                    rng,
+                    cancel,
+                ),
+                None => self.range_proof.verify_single_with_rng(
+                    &gens.bp_gens,
+                    &gens.pc_gens,
+                    &mut transcript_r,
+                    &self.r_comms,
+                    max_reward,
+                    rng,
+                ),
+            };
+            range_check.map_err(|e| match e {
+                ProofError::Cancelled => RewardsProofError::Cancelled,
+                e => RewardsProofError::RangeProof(e),
+            })
+        }
+
+        /// verify. This is a convenience wrapper around `verify_with_rng` that uses the thread RNG.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `spend_state` - the public vector.
+        /// * `session_id` - the session that the proof is bound to.
+        pub fn verify(
+            &self,
+            gens: &RewardsGenerators<B>,
+            spend_state: &[<B as CurveConfig>::ScalarField],
+            session_id: &SessionId,
+        ) -> Result<(), RewardsProofError> {
+            self.verify_with_rng(gens, spend_state, session_id, &mut rand::thread_rng())
+        }
+
+        /// prove_masked. This function generates a rewards proof for a sub-policy, i.e a proof
+        /// that the reward is the inner product of `spend_state` and `policy_state` over only
+        /// the indices where `mask` is true. Both vectors keep their length, so the same state
+        /// can be used with any mask. This function fails if `mask` and `spend_state` have
+        /// different lengths.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `spend_state` - the public vector.
+        /// * `policy_state` - the secret vector.
+        /// * `mask` - the public mask of the indices that are counted.
+        /// * `reward_u64` - the masked reward.
+        /// * `reward` - the masked reward, as a scalar.
+        /// * `session_id` - the session that the proof is bound to.
+        /// * `rng` - the source of randomness.
+        #[allow(clippy::too_many_arguments)]
+        pub fn prove_masked(
+            gens: &RewardsGenerators<B>,
+            spend_state: &[<B as CurveConfig>::ScalarField],
+            policy_state: &[<B as CurveConfig>::ScalarField],
+            mask: &[bool],
+            reward_u64: u64,
+            reward: <B as CurveConfig>::ScalarField,
+            session_id: &SessionId,
+            rng: &mut impl BoomerangRng,
+        ) -> Result<Self, String> {
+            // Masking the public vector zeroes the masked-out terms of the inner product.
+            let masked_state = apply_mask(spend_state, mask)?;
+            Self::prove(
+                gens,
+                &masked_state,
+                policy_state,
+                reward_u64,
+                reward,
+                session_id,
+                rng,
+            )
+        }
+
+        /// verify_masked. This is a convenience wrapper around `verify_masked_with_rng` that
+        /// uses the thread RNG.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `spend_state` - the public vector.
+        /// * `mask` - the public mask of the indices that are counted.
+        /// * `session_id` - the session that the proof is bound to.
+        pub fn verify_masked(
+            &self,
+            gens: &RewardsGenerators<B>,
+            spend_state: &[<B as CurveConfig>::ScalarField],
+            mask: &[bool],
+            session_id: &SessionId,
+        ) -> Result<(), RewardsProofError> {
+            self.verify_masked_with_rng(
+                gens,
+                spend_state,
+                mask,
+                session_id,
+                &mut rand::thread_rng(),
+            )
+        }
+
+        /// verify_masked_with_rng. This function checks a rewards proof that was generated by
+        /// `prove_masked` against `spend_state` and `mask`.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `spend_state` - the public vector.
+        /// * `mask` - the public mask of the indices that are counted.
+        /// * `session_id` - the session that the proof is bound to.
+        /// * `rng` - the RNG that is used to batch the range proof checks.
+        pub fn verify_masked_with_rng<T: BoomerangRng>(
+            &self,
+            gens: &RewardsGenerators<B>,
+            spend_state: &[<B as CurveConfig>::ScalarField],
+            mask: &[bool],
+            session_id: &SessionId,
+            rng: &mut T,
+        ) -> Result<(), RewardsProofError> {
+            let masked_state =
+                apply_mask(spend_state, mask).map_err(RewardsProofError::InvalidMask)?;
+            self.verify_with_rng(gens, &masked_state, session_id, rng)
+        }
+
+        /// commit_state. This function returns the commitment <state, G> + blind * B to the
+        /// vector `state`, where G and B are the generators that `prove_with_commitment` and
+        /// `verify_with_commitment` work with. The vector must not be longer than the capacity
+        /// of `gens`.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `state` - the vector to commit to.
+        /// * `blind` - the blinding value.
+        pub fn commit_state(
+            gens: &RewardsGenerators<B>,
+            state: &[<B as CurveConfig>::ScalarField],
+            blind: <B as CurveConfig>::ScalarField,
+        ) -> sw::Affine<B> {
+            let points: Vec<sw::Affine<B>> = gens
+                .bp_gens
+                .share(0)
+                .G(state.len())
+                .cloned()
+                .chain(Some(gens.pc_gens.B_blinding))
+                .collect();
+            let scalars: Vec<<B as CurveConfig>::ScalarField> =
+                state.iter().cloned().chain(Some(blind)).collect();
+            <sw::Affine<B> as AffineRepr>::Group::msm(&points, &scalars)
+                .unwrap()
+                .into_affine()
+        }
+
+        /// prove_with_commitment. This function generates a rewards proof for a secret vector
+        /// that is already committed to in `comm` (see `commit_state`), rather than for a fresh
+        /// commitment. The linear proof is made against `comm` plus the commitment of the range
+        /// proof, so a verifier that holds `comm` knows that the proven reward is the inner
+        /// product of exactly the committed vector with `public_state`.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `public_state` - the public vector.
+        /// * `committed_state` - the secret vector that is committed to in `comm`.
+        /// * `comm` - the existing commitment to `committed_state`.
+        /// * `blind` - the blinding value of `comm`.
+        /// * `reward_u64` - the reward, i.e the inner product of both vectors.
+        /// * `session_id` - the session that the proof is bound to.
+        /// * `rng` - the source of randomness.
+        #[allow(clippy::too_many_arguments)]
+        #[cfg_attr(
+            feature = "tracing",
+            tracing::instrument(name = "rewards_proof_create", skip_all, fields(size, elapsed_us))
+        )]
+        pub fn prove_with_commitment(
+            gens: &RewardsGenerators<B>,
+            public_state: &[<B as CurveConfig>::ScalarField],
+            committed_state: &[<B as CurveConfig>::ScalarField],
+            comm: &sw::Affine<B>,
+            blind: <B as CurveConfig>::ScalarField,
+            reward_u64: u64,
+            session_id: &SessionId,
+            rng: &mut impl BoomerangRng,
+        ) -> Result<Self, String> {
+            let timer = SpanTimer::start();
+
+            // The range proof commits to the reward as reward * B + blind_r * B_blinding.
+            let (r_proof, r_comms, blind_r) =
+                Self::prove_reward_range(gens, reward_u64, session_id, None, rng)?;
+
+            let g: Vec<_> = gens
+                .bp_gens
+                .share(0)
+                .G(public_state.len())
+                .cloned()
+                .collect::<Vec<sw::Affine<B>>>();
+
+            let f = gens.pc_gens.B;
+            let b = gens.pc_gens.B_blinding;
+
+            // c_t = comm + r_comms = <committed_state, g> + (blind + blind_r) * b + reward * f
+            let c_t = (*comm + r_comms).into_affine();
+            let mut transcript_l =
+                session_transcript::<B>(b"Boomerang verify linear proof", session_id);
+            let l_proof = LinearProof::<sw::Affine<B>>::create(
+                &mut transcript_l,
+                rng,
+                &c_t,
+                blind + blind_r,
+                committed_state.to_vec(),
+                public_state.to_vec(),
+                g,
+                &f,
+                &b,
+            )
+            .map_err(|e| format!("Linear proof error: {:?}", e))?;
+
+            Ok(timer.finish(Self {
+                range_proof: r_proof,
+                r_comms,
+                linear_proof: l_proof,
+                l_comms: c_t,
+            }))
+        }
+
+        /// verify_with_commitment. This function checks a rewards proof that was generated by
+        /// `prove_with_commitment` against the commitment `comm` that the verifier already holds.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `public_state` - the public vector.
+        /// * `comm` - the commitment to the secret vector.
+        /// * `session_id` - the session that the proof is bound to.
+        pub fn verify_with_commitment(
+            &self,
+            gens: &RewardsGenerators<B>,
+            public_state: &[<B as CurveConfig>::ScalarField],
+            comm: &sw::Affine<B>,
+            session_id: &SessionId,
+        ) -> Result<(), RewardsProofError> {
+            self.verify_with_commitment_with_rng(
+                gens,
+                public_state,
+                comm,
+                session_id,
+                &mut rand::thread_rng(),
+            )
+        }
+
+        /// verify_with_commitment_with_rng. This function is as `verify_with_commitment`, but
+        /// uses `rng` to batch the range proof checks.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `public_state` - the public vector.
+        /// * `comm` - the commitment to the secret vector.
+        /// * `session_id` - the session that the proof is bound to.
+        /// * `rng` - the source of randomness.
+        pub fn verify_with_commitment_with_rng<T: BoomerangRng>(
+            &self,
+            gens: &RewardsGenerators<B>,
+            public_state: &[<B as CurveConfig>::ScalarField],
+            comm: &sw::Affine<B>,
+            session_id: &SessionId,
+            rng: &mut T,
+        ) -> Result<(), RewardsProofError> {
+            if self.l_comms != (*comm + self.r_comms).into_affine() {
+                return Err(RewardsProofError::CommitmentMismatch);
+            }
+
+            self.verify_with_rng(gens, public_state, session_id, rng)
+        }
+
+        /// sparse_generators. This function returns the state generators at `indices`, which
+        /// must be sorted, without walking the generators in between. This function fails if an
+        /// index is beyond the capacity of `gens`.
+        fn sparse_generators(
+            gens: &RewardsGenerators<B>,
+            indices: &[usize],
+        ) -> Result<Vec<sw::Affine<B>>, String> {
+            let capacity = gens.bp_gens.gens_capacity;
+            if indices.last().is_some_and(|index| *index >= capacity) {
+                return Err(format!(
+                    "Sparse state: the generators only cover {} values",
+                    capacity
+                ));
+            }
+
+            let mut g = gens.bp_gens.share(0).G(capacity);
+            let mut next = 0;
+            Ok(indices
+                .iter()
+                .map(|index| {
+                    let point = *g.nth(index - next).unwrap();
+                    next = index + 1;
+                    point
+                })
+                .collect())
+        }
+
+        /// commit_sparse_state. This function returns the same commitment as `commit_state`
+        /// does for the dense form of `state`, with an MSM over only its non-zero entries. The
+        /// state must not be longer than the capacity of `gens`.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `state` - the sparse vector to commit to.
+        /// * `blind` - the blinding value.
+        pub fn commit_sparse_state(
+            gens: &RewardsGenerators<B>,
+            state: &SparseState<<B as CurveConfig>::ScalarField>,
+            blind: <B as CurveConfig>::ScalarField,
+        ) -> sw::Affine<B> {
+            assert!(
+                state.len() <= gens.bp_gens.gens_capacity,
+                "commit_sparse_state: state is too long"
+            );
+            let indices: Vec<usize> = state.entries().iter().map(|(index, _)| *index).collect();
+            let points: Vec<sw::Affine<B>> = Self::sparse_generators(gens, &indices)
+                .unwrap()
+                .into_iter()
+                .chain(Some(gens.pc_gens.B_blinding))
+                .collect();
+            let scalars: Vec<<B as CurveConfig>::ScalarField> = state
+                .entries()
+                .iter()
+                .map(|(_, value)| *value)
+                .chain(Some(blind))
+                .collect();
+            <sw::Affine<B> as AffineRepr>::Group::msm(&points, &scalars)
+                .unwrap()
+                .into_affine()
+        }
+
+        /// prove_sparse. This function generates a rewards proof as in `prove`, for a public
+        /// vector that is given in sparse form. Since the zero entries of `spend_state` do not
+        /// contribute to the reward, the linear proof only runs over its non-zero entries
+        /// (padded to a power of two), so its cost does not depend on the catalog size. The
+        /// proof must be checked with `verify_sparse`. This function fails if `policy_state`
+        /// does not have the length of `spend_state`, or if the state is longer than the
+        /// capacity of `gens`.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `spend_state` - the public vector, in sparse form.
+        /// * `policy_state` - the private policy vector.
+        /// * `reward_u64` - the reward, as an integer.
+        /// * `reward` - the reward, i.e the inner product of the two vectors.
+        /// * `session_id` - the session that the proof is bound to.
+        /// * `rng` - the source of randomness.
+        #[cfg_attr(
+            feature = "tracing",
+            tracing::instrument(name = "rewards_proof_create", skip_all, fields(size, elapsed_us))
+        )]
+        pub fn prove_sparse(
+            gens: &RewardsGenerators<B>,
+            spend_state: &SparseState<<B as CurveConfig>::ScalarField>,
+            policy_state: &[<B as CurveConfig>::ScalarField],
+            reward_u64: u64,
+            reward: <B as CurveConfig>::ScalarField,
+            session_id: &SessionId,
+            rng: &mut impl BoomerangRng,
+        ) -> Result<Self, String> {
+            let timer = SpanTimer::start();
+            if policy_state.len() != spend_state.len() {
+                return Err(format!(
+                    "Sparse state: policy has length {}, but the state has length {}",
+                    policy_state.len(),
+                    spend_state.len()
+                ));
+            }
+            if spend_state.len() > gens.bp_gens.gens_capacity {
+                return Err(format!(
+                    "Sparse state: the generators only cover {} values",
+                    gens.bp_gens.gens_capacity
+                ));
+            }
+
+            let (r_proof, r_comms, _) =
+                Self::prove_reward_range(gens, reward_u64, session_id, None, rng)?;
+
+            let support = spend_state.padded_support();
+            let g = Self::sparse_generators(gens, &support)?;
+            let public = spend_state.support_values(&support);
+            // The policy is only needed where the public vector is not zero.
+            let secret: Vec<<B as CurveConfig>::ScalarField> = public
+                .iter()
+                .zip(&support)
+                .map(|(value, index)| {
+                    if *value == <B as CurveConfig>::ScalarField::ZERO {
+                        <B as CurveConfig>::ScalarField::ZERO
+                    } else {
+                        policy_state[*index]
+                    }
+                })
+                .collect();
+
+            let f = gens.pc_gens.B;
+            let b = gens.pc_gens.B_blinding;
+
+            // c_t = <secret, g> + blind_l * b + reward * f, over the support only
+            let blind_l = <B as CurveConfig>::ScalarField::rand(rng);
+            let combined_scalars: Vec<B::ScalarField> = secret
+                .iter()
+                .cloned()
+                .chain(Some(blind_l))
+                .chain(Some(reward))
+                .collect();
+            let combined_points: Vec<_> = g.iter().cloned().chain(Some(b)).chain(Some(f)).collect();
+            let c_t =
+                <sw::Affine<B> as AffineRepr>::Group::msm(&combined_points, &combined_scalars)
+                    .unwrap()
+                    .into_affine();
+
+            let mut transcript_l =
+                session_transcript::<B>(b"Boomerang verify sparse linear proof", session_id);
+            let l_proof = LinearProof::<sw::Affine<B>>::create(
+                &mut transcript_l,
+                rng,
+                &c_t,
+                blind_l,
+                secret,
+                public,
+                g,
+                &f,
+                &b,
+            )
+            .map_err(|e| format!("Linear proof error: {:?}", e))?;
+
+            Ok(timer.finish(Self {
+                range_proof: r_proof,
+                r_comms,
+                linear_proof: l_proof,
+                l_comms: c_t,
+            }))
+        }
+
+        /// verify_sparse. This is a convenience wrapper around `verify_sparse_with_rng` that
+        /// uses the thread RNG.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `spend_state` - the public vector, in sparse form.
+        /// * `session_id` - the session that the proof is bound to.
+        pub fn verify_sparse(
+            &self,
+            gens: &RewardsGenerators<B>,
+            spend_state: &SparseState<<B as CurveConfig>::ScalarField>,
+            session_id: &SessionId,
+        ) -> Result<(), RewardsProofError> {
+            self.verify_sparse_with_rng(gens, spend_state, session_id, &mut rand::thread_rng())
+        }
+
+        /// verify_sparse_with_rng. This function checks a rewards proof that was generated by
+        /// `prove_sparse` against `spend_state`.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `spend_state` - the public vector, in sparse form.
+        /// * `session_id` - the session that the proof is bound to.
+        /// * `rng` - the RNG that is used to batch the range proof checks.
+        #[cfg_attr(
+            feature = "tracing",
+            tracing::instrument(name = "rewards_proof_verify", skip_all, fields(size, elapsed_us))
+        )]
+        pub fn verify_sparse_with_rng<T: BoomerangRng>(
+            &self,
+            gens: &RewardsGenerators<B>,
+            spend_state: &SparseState<<B as CurveConfig>::ScalarField>,
+            session_id: &SessionId,
+            rng: &mut T,
+        ) -> Result<(), RewardsProofError> {
+            let timer = SpanTimer::start();
+            self.verify_reward_range(gens, session_id, None, rng, None)?;
+
+            let support = spend_state.padded_support();
+            let g = Self::sparse_generators(gens, &support)
+                .map_err(|_| RewardsProofError::LinearProof(ProofError::InvalidGeneratorsLength))?;
+            let f = gens.pc_gens.B;
+            let b = gens.pc_gens.B_blinding;
+            let mut transcript_l =
+                session_transcript::<B>(b"Boomerang verify sparse linear proof", session_id);
+            self.linear_proof
+                .verify(
+                    &mut transcript_l,
+                    &self.l_comms,
+                    &g,
+                    &f,
+                    &b,
+                    spend_state.support_values(&support),
+                )
+                .map_err(RewardsProofError::LinearProof)?;
+
+            timer.record(self);
+            Ok(())
+        }
+    }
+
+    /// JointRewardsProof. This struct is the compressed form of a rewards proof that is made
+    /// against a committed state (see `BRewardsProof::prove_with_commitment`). The range proof
+    /// and the linear proof are made on one transcript, so the challenges of the linear proof
+    /// also bind the range proof, and the verifier checks both with a single multiscalar
+    /// multiplication. The commitment of the linear proof is not sent, since the verifier
+    /// derives it from the state commitment that it holds and from `r_comms`.
+    #[derive(CanonicalSerialize, CanonicalDeserialize)]
+    pub struct JointRewardsProof<B: BoomerangConfig> {
+        /// range_proof: the range proof on the reward.
+        pub range_proof: RangeProof<sw::Affine<B>>,
+        /// r_comms: the commitment to the reward.
+        pub r_comms: sw::Affine<B>,
+        /// linear_proof: the proof that the reward is the inner product of the states.
+        pub linear_proof: LinearProof<sw::Affine<B>>,
+    }
+
+    impl<B: BoomerangConfig> Clone for JointRewardsProof<B> {
+        fn clone(&self) -> Self {
+            JointRewardsProof {
+                range_proof: self.range_proof.clone(),
+                r_comms: self.r_comms,
+                linear_proof: self.linear_proof.clone(),
+            }
+        }
+    }
+
+    impl<B: BoomerangConfig> JointRewardsProof<B> {
+        /// prove. This function generates a joint rewards proof for a secret vector that is
+        /// already committed to in `comm` (see `BRewardsProof::commit_state`).
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `public_state` - the public vector.
+        /// * `committed_state` - the secret vector that is committed to in `comm`.
+        /// * `comm` - the existing commitment to `committed_state`.
+        /// * `blind` - the blinding value of `comm`.
+        /// * `reward_u64` - the reward, i.e the inner product of both vectors.
+        /// * `session_id` - the session that the proof is bound to.
+        /// * `rng` - the source of randomness.
+        #[allow(clippy::too_many_arguments)]
+        #[cfg_attr(
+            feature = "tracing",
+            tracing::instrument(name = "rewards_proof_create", skip_all, fields(size, elapsed_us))
+        )]
+        pub fn prove(
+            gens: &RewardsGenerators<B>,
+            public_state: &[<B as CurveConfig>::ScalarField],
+            committed_state: &[<B as CurveConfig>::ScalarField],
+            comm: &sw::Affine<B>,
+            blind: <B as CurveConfig>::ScalarField,
+            reward_u64: u64,
+            session_id: &SessionId,
+            rng: &mut impl BoomerangRng,
+        ) -> Result<Self, String> {
+            let timer = SpanTimer::start();
+            let mut transcript =
+                session_transcript::<B>(b"Boomerang verify joint rewards proof", session_id);
+
+            // The range proof commits to the reward as reward * B + blind_r * B_blinding.
+            let blind_r = <B as CurveConfig>::ScalarField::rand(rng);
+            let (r_proof, r_comms) = gens
+                .prove_range(&mut transcript, reward_u64, blind_r, rng)
+                .map_err(|e| format!("Range proof error: {:?}", e))?;
+
+            let g: Vec<_> = gens
+                .bp_gens
+                .share(0)
+                .G(public_state.len())
+                .cloned()
+                .collect::<Vec<sw::Affine<B>>>();
+
+            // c_t = comm + r_comms = <committed_state, g> + (blind + blind_r) * b + reward * f
+            let c_t = (*comm + r_comms).into_affine();
+            let l_proof = LinearProof::<sw::Affine<B>>::create(
+                &mut transcript,
+                rng,
+                &c_t,
+                blind + blind_r,
+                committed_state.to_vec(),
+                public_state.to_vec(),
+                g,
+                &gens.pc_gens.B,
+                &gens.pc_gens.B_blinding,
+            )
+            .map_err(|e| format!("Linear proof error: {:?}", e))?;
+
+            Ok(timer.finish(Self {
+                range_proof: r_proof,
+                r_comms,
+                linear_proof: l_proof,
+            }))
+        }
+
+        /// verify. This is a convenience wrapper around `verify_with_rng` that uses the thread
+        /// RNG.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `public_state` - the public vector.
+        /// * `comm` - the commitment to the secret vector.
+        /// * `session_id` - the session that the proof is bound to.
+        pub fn verify(
+            &self,
+            gens: &RewardsGenerators<B>,
+            public_state: &[<B as CurveConfig>::ScalarField],
+            comm: &sw::Affine<B>,
+            session_id: &SessionId,
+        ) -> Result<(), RewardsProofError> {
+            self.verify_with_rng(
+                gens,
+                public_state,
+                comm,
+                session_id,
+                &mut rand::thread_rng(),
+            )
+        }
+
+        /// verify_with_rng. This function checks the joint rewards proof against the commitment
+        /// `comm` that the verifier already holds. The terms of both proofs are weighted by
+        /// `rng`, and summed into a single multiscalar multiplication.
+        /// # Arguments
+        /// * `gens` - the rewards generators.
+        /// * `public_state` - the public vector.
+        /// * `comm` - the commitment to the secret vector.
+        /// * `session_id` - the session that the proof is bound to.
+        /// * `rng` - the RNG that is used to weight the checks.
+        #[cfg_attr(
+            feature = "tracing",
+            tracing::instrument(name = "rewards_proof_verify", skip_all, fields(size, elapsed_us))
+        )]
+        pub fn verify_with_rng<T: BoomerangRng>(
+            &self,
+            gens: &RewardsGenerators<B>,
+            public_state: &[<B as CurveConfig>::ScalarField],
+            comm: &sw::Affine<B>,
+            session_id: &SessionId,
+            rng: &mut T,
+        ) -> Result<(), RewardsProofError> {
+            let timer = SpanTimer::start();
+            let max_reward = 64;
+            let mut transcript =
+                session_transcript::<B>(b"Boomerang verify joint rewards proof", session_id);
+            let mut acc = MsmAccumulator::new();
+
+            self.range_proof
+                .accumulate_verification_scalars(
+                    &gens.bp_gens,
+                    &mut transcript,
+                    &[self.r_comms],
+                    max_reward,
+                    rng,
+                    &mut acc,
+                )
+                .map_err(RewardsProofError::RangeProof)?;
+
+            let n = public_state.len();
+            if n > gens.bp_gens.gens_capacity {
+                return Err(RewardsProofError::LinearProof(
+                    ProofError::InvalidGeneratorsLength,
+                ));
+            }
+            let g: Vec<_> = gens.bp_gens.share(0).G(n).cloned().collect();
+            let c_t = (*comm + self.r_comms).into_affine();
+            self.linear_proof
+                .accumulate_verification_scalars(
+                    &mut transcript,
+                    &c_t,
+                    &g,
+                    &gens.pc_gens.B,
+                    &gens.pc_gens.B_blinding,
+                    public_state.to_vec(),
+                    rng,
+                    &mut acc,
+                )
+                .map_err(RewardsProofError::LinearProof)?;
+
+            // Both proofs use B_blinding, B and the G generators in the same shared slots.
+            let k = n.max(max_reward);
+            let shared_bases = [&gens.pc_gens.B_blinding, &gens.pc_gens.B]
+                .into_iter()
+                .chain(
+                    gens.bp_gens
+                        .G(k, 1)
+                        .zip(gens.bp_gens.H(k, 1))
+                        .flat_map(|(g, h)| [g, h]),
+                );
+            let check = acc
+                .evaluate(shared_bases)
+                .map_err(|_| RewardsProofError::JointProof(ProofError::InvalidGeneratorsLength))?;
+            if !check.is_zero() {
+                return Err(RewardsProofError::JointProof(ProofError::VerificationError));
+            }
+
+            timer.record(self);
             Ok(())
         }
     }
 
-    /// SubProof. This struct acts as a container for the sub-proof.
+    /// SubProof. This struct acts as a container for the sub-proof, i.e a range proof on the
+    /// spent value. The proof is made and checked against `RewardsGenerators::shared`, so it
+    /// does not carry any generators.
     #[derive(CanonicalSerialize, CanonicalDeserialize)]
     pub struct SubProof<B: BoomerangConfig> {
         // the range proof
         pub range_proof: RangeProof<sw::Affine<B>>,
-        // the pc gens for range proof
-        pub range_gensp_r: PedersenGens<sw::Affine<B>>,
-        // the bp gens for range proof
-        pub range_gensb_r: BulletproofGens<sw::Affine<B>>,
         // the commitment of range proof
         pub r_comms: sw::Affine<B>,
     }
@@ -225,51 +1675,82 @@ pub mod rewards {
         fn clone(&self) -> Self {
             SubProof {
                 range_proof: self.range_proof.clone(),
-                range_gensp_r: self.range_gensp_r,
-                range_gensb_r: self.range_gensb_r.clone(),
                 r_comms: self.r_comms,
             }
         }
     }
 
     impl<B: BoomerangConfig> SubProof<B> {
-        pub fn prove(spend_u64: u64, rng: &mut impl Rng) -> Self {
-            let max_spend = 64; // TODO: should be app specific
-
-            let pc_gens_r: PedersenGens<sw::Affine<B>> = PedersenGens::default();
-            // We instantiate with the maximum capacity
-            let bp_gens_r = BulletproofGens::new(max_spend, 1);
-            let mut transcript = Transcript::new(b"Boomerang verify sub proof");
+        pub fn prove(spend_u64: u64, session_id: &SessionId, rng: &mut impl BoomerangRng) -> Self {
             let blind = <B as CurveConfig>::ScalarField::rand(rng);
-            let (r_proof, r_comms) = RangeProof::prove_single(
-                &bp_gens_r,
-                &pc_gens_r,
-                &mut transcript,
-                spend_u64,
-                &blind,
-                max_spend,
-            )
-            .unwrap();
+            Self::prove_with_blind(spend_u64, blind, session_id, rng)
+        }
 
-            SubProof {
+        /// prove_with_blind. This function creates the sub proof of `spend_u64`, whose commitment
+        /// uses `blind` as its blinding factor. This allows the caller to tie the commitment to
+        /// other commitments under the `pc_gens` of `RewardsGenerators::shared`.
+        /// # Arguments
+        /// * `spend_u64` - the value that is proven to be in range.
+        /// * `blind` - the blinding factor of the commitment.
+        /// * `session_id` - the session that the proof is bound to.
+        /// * `rng` - the source of randomness.
+        #[cfg_attr(
+            feature = "tracing",
+            tracing::instrument(name = "sub_proof_create", skip_all, fields(size, elapsed_us))
+        )]
+        pub fn prove_with_blind(
+            spend_u64: u64,
+            blind: <B as CurveConfig>::ScalarField,
+            session_id: &SessionId,
+            rng: &mut impl BoomerangRng,
+        ) -> Self {
+            let timer = SpanTimer::start();
+            // TODO: the 64 bits of the range proof should be app specific.
+            let gens = RewardsGenerators::<B>::shared();
+            let mut transcript = session_transcript::<B>(b"Boomerang verify sub proof", session_id);
+            let (r_proof, r_comms) = gens
+                .prove_range(&mut transcript, spend_u64, blind, rng)
+                .unwrap();
+
+            timer.finish(SubProof {
                 range_proof: r_proof,
-                range_gensp_r: pc_gens_r,
-                range_gensb_r: bp_gens_r,
                 r_comms,
-            }
+            })
+        }
+
+        /// verify. This is a convenience wrapper around `verify_with_rng` that uses the thread RNG.
+        /// # Arguments
+        /// * `session_id` - the session that the proof is bound to.
+        pub fn verify(&self, session_id: &SessionId) -> Result<(), String> {
+            self.verify_with_rng(session_id, &mut rand::thread_rng())
         }
 
-        pub fn verify(&self) -> Result<(), String> {
-            let mut transcript = Transcript::new(b"Boomerang verify sub proof");
+        /// verify_with_rng. This function checks the sub proof.
+        /// # Arguments
+        /// * `session_id` - the session that the proof is bound to.
+        /// * `rng` - the RNG that is used to batch the range proof checks.
+        #[cfg_attr(
+            feature = "tracing",
+            tracing::instrument(name = "sub_proof_verify", skip_all, fields(size, elapsed_us))
+        )]
+        pub fn verify_with_rng<T: BoomerangRng>(
+            &self,
+            session_id: &SessionId,
+            rng: &mut T,
+        ) -> Result<(), String> {
+            let timer = SpanTimer::start();
+            let gens = RewardsGenerators::<B>::shared();
+            let mut transcript = session_transcript::<B>(b"Boomerang verify sub proof", session_id);
             let max_sub = 64; // TODO: should be app specific
 
             self.range_proof
-                .verify_single(
-                    &self.range_gensb_r,
-                    &self.range_gensp_r,
+                .verify_single_with_rng(
+                    &gens.bp_gens,
+                    &gens.pc_gens,
                     &mut transcript,
                     &self.r_comms,
                     max_sub,
+                    rng,
                 )
                 .map_err(|e| {
                     format!(
@@ -278,7 +1759,121 @@ pub mod rewards {
                     )
                 })?;
 
+            timer.record(self);
             Ok(())
         }
     }
+
+    /// ValueLinkProof. This struct acts as a container for a proof that the value with index
+    /// `VALUE_INDEX` of a multi-commitment is the value of a commitment under the `pc_gens` of
+    /// `RewardsGenerators::shared`. The proof extends an `OpeningProofMulti` of the
+    /// multi-commitment: both share the challenge and the response for the value, so the proof
+    /// is only valid alongside the opening proof that it was made with.
+    #[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+    pub struct ValueLinkProof<B: BoomerangConfig> {
+        /// alpha: the random point produced during setup.
+        pub alpha: sw::Affine<B>,
+        /// z: the response for the blinding factor of the value commitment.
+        pub z: <B as CurveConfig>::ScalarField,
+    }
+
+    impl<B: BoomerangConfig> ValueLinkProof<B> {
+        /// make_transcript. This function adds `value_comm` and `alpha` to `transcript`, after
+        /// the opening proof has added its own values.
+        /// # Arguments
+        /// * `transcript` - the transcript which is modified.
+        /// * `value_comm` - the commitment to the value.
+        /// * `alpha` - the alpha value of the proof.
+        fn make_transcript(
+            transcript: &mut Transcript,
+            value_comm: &sw::Affine<B>,
+            alpha: &sw::Affine<B>,
+        ) {
+            append_point(transcript, b"value_comm", value_comm);
+            append_point(transcript, b"value_alpha", alpha);
+        }
+
+        /// create. This function returns a proof of opening of `comm`, and a proof that its
+        /// value with index `VALUE_INDEX` is the value of `pc_gens.commit(vals[VALUE_INDEX],
+        /// blind)`.
+        /// # Arguments
+        /// * `transcript` - the transcript object that is modified.
+        /// * `rng` - the source of randomness.
+        /// * `vals` - the committed values of `comm`.
+        /// * `comm` - the multi-commitment.
+        /// * `gens` - the generators of `comm`.
+        /// * `blind` - the blinding factor of the value commitment.
+        pub fn create<T: BoomerangRng>(
+            transcript: &mut Transcript,
+            rng: &mut T,
+            vals: &[<B as CurveConfig>::ScalarField],
+            comm: &PedersenComm<B>,
+            gens: &Generators<B>,
+            blind: <B as CurveConfig>::ScalarField,
+        ) -> (OpeningProofMulti<B>, Self) {
+            let pc_gens = RewardsGenerators::<B>::shared().pc_gens;
+            let inter =
+                OpeningProofMulti::create_intermediates(transcript, rng, comm, vals.len(), gens);
+
+            // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+            let t =
+                <B as CurveConfig>::ScalarField::rand(&mut witness_rng(transcript, &[blind], rng));
+            let alpha = pc_gens.commit(inter.ts[VALUE_INDEX], t);
+            let value_comm = pc_gens.commit(vals[VALUE_INDEX], blind);
+            Self::make_transcript(transcript, &value_comm, &alpha);
+
+            let chal_buf = OpeningMultiTranscript::challenge_scalar(
+                transcript,
+                OPENING_MULTI_LABELS.challenge,
+            );
+            let chal = <B as PedersenConfig>::make_challenge_from_buffer(&chal_buf);
+            let opening = OpeningProofMulti::create_proof_with_challenge(vals, &inter, comm, &chal);
+            (
+                opening,
+                Self {
+                    alpha,
+                    z: blind * chal + t,
+                },
+            )
+        }
+
+        /// verify. This function returns true if `opening` is a valid proof of opening of `comm`,
+        /// and if the value with index `VALUE_INDEX` of `comm` is the value of `value_comm`.
+        /// # Arguments
+        /// * `self` - the proof that is being verified.
+        /// * `opening` - the proof of opening of `comm` that the proof was made with.
+        /// * `transcript` - the transcript object that's used.
+        /// * `comm` - the multi-commitment.
+        /// * `l` - the number of committed values of `comm`.
+        /// * `gens` - the generators of `comm`.
+        /// * `value_comm` - the commitment to the value.
+        pub fn verify(
+            &self,
+            opening: &OpeningProofMulti<B>,
+            transcript: &mut Transcript,
+            comm: &sw::Affine<B>,
+            l: usize,
+            gens: &Generators<B>,
+            value_comm: &sw::Affine<B>,
+        ) -> bool {
+            if l <= VALUE_INDEX || opening.z2.len() != l {
+                return false;
+            }
+
+            opening.add_to_transcript(transcript, comm);
+            Self::make_transcript(transcript, value_comm, &self.alpha);
+            let chal_buf = OpeningMultiTranscript::challenge_scalar(
+                transcript,
+                OPENING_MULTI_LABELS.challenge,
+            );
+            let chal = <B as PedersenConfig>::make_challenge_from_buffer(&chal_buf);
+            if !opening.verify_with_challenge(comm, &chal, l, gens) {
+                return false;
+            }
+
+            let pc_gens = RewardsGenerators::<B>::shared().pc_gens;
+            pc_gens.commit(opening.z2[VALUE_INDEX], self.z)
+                == (self.alpha + value_comm.into_group() * chal).into_affine()
+        }
+    }
 }
@@ -0,0 +1,284 @@
+//! Defines a client wallet that holds several live tokens at once.
+//!
+//! A `State` holds the token that a single protocol run produced. A client that takes part in
+//! several campaigns, or that holds tokens that expire at different times, keeps one `State` per
+//! token in a `Wallet`, alongside the campaign and the expiry of the token. Each entry is
+//! addressed by a `TokenId` that is local to the wallet, and is never sent to the server.
+//!
+//! Before a spend, the client picks the token to present with `select`, according to a
+//! `SelectionPolicy`, and marks it as in use with `begin`. A token that is in use is never
+//! selected again, so that two concurrent runs cannot present the same token, and the protocol
+//! state of one run never touches the token of another. Once the run completes, `finish`
+//! replaces the spent token with the new one, and `abort` releases it if the run failed before
+//! the client revealed its double-spending tag.
+
+use ark_ec::models::CurveConfig;
+use ark_ff::PrimeField;
+
+use crate::clock::Clock;
+use crate::config::{BoomerangConfig, State};
+
+/// TokenId. The identifier of a token within a wallet.
+pub type TokenId = u64;
+
+/// SelectionPolicy. The order in which a wallet selects the tokens that can pay for a spend.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectionPolicy {
+    /// LargestBalanceFirst: the token with the largest balance, so that few tokens are left with
+    /// small balances.
+    LargestBalanceFirst,
+    /// ExpiringFirst: the token that expires first, so that as little as possible expires unused.
+    ExpiringFirst,
+}
+
+/// WalletToken. This struct holds a token of a wallet, alongside its campaign and expiry.
+#[derive(Clone)]
+pub struct WalletToken<B: BoomerangConfig> {
+    /// id: the identifier of the token within the wallet.
+    id: TokenId,
+    /// campaign_id: the campaign that the token belongs to.
+    campaign_id: u64,
+    /// expiry: the time after which the token can no longer be spent.
+    expiry: u64,
+    /// in_use: whether a protocol run is using the token.
+    in_use: bool,
+    /// state: the client state that holds the token.
+    state: State<B>,
+}
+
+impl<B: BoomerangConfig> WalletToken<B> {
+    /// id. Returns the identifier of the token within the wallet.
+    pub fn id(&self) -> TokenId {
+        self.id
+    }
+
+    /// campaign_id. Returns the campaign that the token belongs to.
+    pub fn campaign_id(&self) -> u64 {
+        self.campaign_id
+    }
+
+    /// expiry. Returns the time after which the token can no longer be spent.
+    pub fn expiry(&self) -> u64 {
+        self.expiry
+    }
+
+    /// in_use. Returns true if a protocol run is using the token.
+    pub fn in_use(&self) -> bool {
+        self.in_use
+    }
+
+    /// state. Returns the client state that holds the token, e.g. to pass to
+    /// `SpendVerifyStateC::generate_spendverify_m2`.
+    pub fn state(&self) -> &State<B> {
+        &self.state
+    }
+
+    /// balance. Returns the balance of the token.
+    pub fn balance(&self) -> <B as CurveConfig>::ScalarField {
+        self.state.balance()
+    }
+
+    /// is_live. Returns true if the token has not expired at `now`.
+    /// # Arguments
+    /// * `now` - the current time.
+    pub fn is_live(&self, now: u64) -> bool {
+        now <= self.expiry
+    }
+}
+
+/// Wallet. This struct holds the live tokens of a client, each in its own client state.
+#[derive(Clone)]
+pub struct Wallet<B: BoomerangConfig> {
+    /// tokens: the tokens, in the order they were inserted.
+    tokens: Vec<WalletToken<B>>,
+    /// next_id: the identifier of the next token that is inserted.
+    next_id: TokenId,
+}
+
+impl<B: BoomerangConfig> Default for Wallet<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: BoomerangConfig> Wallet<B> {
+    /// new. Returns an empty wallet.
+    pub fn new() -> Self {
+        Self {
+            tokens: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// len. Returns the number of tokens in the wallet, including those that expired.
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// is_empty. Returns true if the wallet holds no tokens.
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// tokens. Returns the tokens of the wallet, in the order they were inserted.
+    pub fn tokens(&self) -> &[WalletToken<B>] {
+        &self.tokens
+    }
+
+    /// get. Returns the token with identifier `id`, if the wallet holds it.
+    /// # Arguments
+    /// * `id` - the identifier of the token.
+    pub fn get(&self, id: TokenId) -> Option<&WalletToken<B>> {
+        self.tokens.iter().find(|token| token.id == id)
+    }
+
+    /// get_mut. Returns the token with identifier `id`, if the wallet holds it.
+    fn get_mut(&mut self, id: TokenId) -> Result<&mut WalletToken<B>, &'static str> {
+        self.tokens
+            .iter_mut()
+            .find(|token| token.id == id)
+            .ok_or("Boomerang wallet: unknown token")
+    }
+
+    /// insert. This function adds the token held by `state` to the wallet, e.g. after the
+    /// Issuance Protocol, and returns its identifier.
+    /// # Arguments
+    /// * `state` - the client state that holds the token.
+    /// * `campaign_id` - the campaign that the token belongs to.
+    /// * `expiry` - the time after which the token can no longer be spent.
+    pub fn insert(&mut self, state: State<B>, campaign_id: u64, expiry: u64) -> TokenId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.tokens.push(WalletToken {
+            id,
+            campaign_id,
+            expiry,
+            in_use: false,
+            state,
+        });
+        id
+    }
+
+    /// remove. This function removes the token with identifier `id` from the wallet, and
+    /// returns it.
+    /// # Arguments
+    /// * `id` - the identifier of the token.
+    pub fn remove(&mut self, id: TokenId) -> Option<WalletToken<B>> {
+        let pos = self.tokens.iter().position(|token| token.id == id)?;
+        Some(self.tokens.remove(pos))
+    }
+
+    /// remove_expired. This function removes every token that has expired at `now` and that
+    /// is not in use, and returns them.
+    /// # Arguments
+    /// * `now` - the current time.
+    pub fn remove_expired(&mut self, now: u64) -> Vec<WalletToken<B>> {
+        let (live, expired) = core::mem::take(&mut self.tokens)
+            .into_iter()
+            .partition(|token| token.in_use || token.is_live(now));
+        self.tokens = live;
+        expired
+    }
+
+    /// remove_expired_with_clock. This function removes every token that has expired at the
+    /// current time of `clock` and that is not in use, as in `remove_expired`, and returns them.
+    /// # Arguments
+    /// * `clock` - the source of the current time.
+    pub fn remove_expired_with_clock(&mut self, clock: &impl Clock) -> Vec<WalletToken<B>> {
+        self.remove_expired(clock.now())
+    }
+
+    /// balance. Returns the sum of the balances of the live tokens of `campaign_id` at `now`.
+    /// # Arguments
+    /// * `campaign_id` - the campaign.
+    /// * `now` - the current time.
+    pub fn balance(&self, campaign_id: u64, now: u64) -> <B as CurveConfig>::ScalarField {
+        self.tokens
+            .iter()
+            .filter(|token| token.campaign_id == campaign_id && token.is_live(now))
+            .map(|token| token.balance())
+            .sum()
+    }
+
+    /// select. This function returns the token of `campaign_id` that pays for a spend of
+    /// `amount` at `now` under `policy`, i.e the first token under `policy` that is live, not in
+    /// use and holds at least `amount`. Ties are broken in favour of the token that was
+    /// inserted first. This function returns None if no token can pay.
+    /// # Arguments
+    /// * `campaign_id` - the campaign of the spend.
+    /// * `amount` - the amount that the spend needs.
+    /// * `now` - the current time.
+    /// * `policy` - the selection policy.
+    pub fn select(
+        &self,
+        campaign_id: u64,
+        amount: <B as CurveConfig>::ScalarField,
+        now: u64,
+        policy: SelectionPolicy,
+    ) -> Option<TokenId> {
+        let amount = amount.into_bigint();
+        let candidates = self.tokens.iter().filter(|token| {
+            token.campaign_id == campaign_id
+                && token.is_live(now)
+                && !token.in_use
+                && token.balance().into_bigint() >= amount
+        });
+
+        // min_by_key keeps the first of equal tokens, so the reversed balance is used to pick
+        // the largest balance rather than max_by_key, which keeps the last.
+        let token = match policy {
+            SelectionPolicy::LargestBalanceFirst => {
+                candidates.min_by_key(|token| core::cmp::Reverse(token.balance().into_bigint()))
+            }
+            SelectionPolicy::ExpiringFirst => candidates.min_by_key(|token| token.expiry),
+        };
+        token.map(|token| token.id)
+    }
+
+    /// begin. This function marks the token with identifier `id` as in use by a protocol run,
+    /// and returns its client state. This function fails if the wallet does not hold the token,
+    /// or if another run is using it.
+    /// # Arguments
+    /// * `id` - the identifier of the token.
+    pub fn begin(&mut self, id: TokenId) -> Result<&State<B>, &'static str> {
+        let token = self.get_mut(id)?;
+        if token.in_use {
+            return Err("Boomerang wallet: token is already in use");
+        }
+        token.in_use = true;
+        Ok(&token.state)
+    }
+
+    /// finish. This function replaces the token with identifier `id` with the token held by
+    /// `state`, i.e the token that the protocol run produced, and releases it. The new token
+    /// keeps the identifier, campaign and expiry of the spent one. This function fails if the
+    /// wallet does not hold the token, or if no run is using it.
+    /// # Arguments
+    /// * `id` - the identifier of the token.
+    /// * `state` - the client state that the run produced.
+    pub fn finish(&mut self, id: TokenId, state: State<B>) -> Result<(), &'static str> {
+        let token = self.get_mut(id)?;
+        if !token.in_use {
+            return Err("Boomerang wallet: token is not in use");
+        }
+        token.state = state;
+        token.in_use = false;
+        Ok(())
+    }
+
+    /// abort. This function releases the token with identifier `id` without changing it.
+    /// N.B A run must only be aborted before the client sent a message that reveals the
+    /// double-spending tag of the token: once it did, presenting the token again in another
+    /// session reveals the client's secret key. Such a token should be removed instead.
+    /// This function fails if the wallet does not hold the token, or if no run is using it.
+    /// # Arguments
+    /// * `id` - the identifier of the token.
+    pub fn abort(&mut self, id: TokenId) -> Result<(), &'static str> {
+        let token = self.get_mut(id)?;
+        if !token.in_use {
+            return Err("Boomerang wallet: token is not in use");
+        }
+        token.in_use = false;
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use ark_bulletproofs::msm;
+use ark_ec::{AffineRepr, VariableBaseMSM};
+use ark_secq256k1::{Affine, Fr};
+use ark_std::UniformRand;
+use criterion::BenchmarkId;
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand_chacha::ChaCha20Rng;
+use rand_core::SeedableRng;
+
+fn msm_inputs(size: usize) -> (Vec<Affine>, Vec<Fr>) {
+    let mut rng = ChaCha20Rng::seed_from_u64(size as u64);
+    let bases = (0..size).map(|_| Affine::rand(&mut rng)).collect();
+    let scalars = (0..size).map(|_| Fr::rand(&mut rng)).collect();
+    (bases, scalars)
+}
+
+fn msm_compare(c: &mut Criterion) {
+    let mut group = c.benchmark_group("msm");
+    for size in (2..13).map(|i| 1 << i) {
+        let (bases, scalars) = msm_inputs(size);
+
+        group.bench_with_input(BenchmarkId::new("msm", size), &size, |b, _| {
+            b.iter(|| msm::msm(&bases, &scalars).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("VariableBaseMSM", size), &size, |b, _| {
+            b.iter(|| <Affine as AffineRepr>::Group::msm(&bases, &scalars).unwrap())
+        });
+    }
+}
+
+criterion_group!(bp, msm_compare);
+criterion_main!(bp);
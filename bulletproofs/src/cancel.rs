@@ -0,0 +1,84 @@
+//! The `cancel` module contains a cooperative cancellation token for
+//! long-running verifications.
+//!
+//! A verifier that is given a [`CancellationToken`] checks it between the
+//! chunks of its multiscalar multiplications, and gives up with
+//! [`ProofError::Cancelled`](crate::ProofError::Cancelled) once it has been
+//! cancelled. This lets a server put a bound on the time that it spends on
+//! an adversarial input, without having to trust the size of the input.
+
+#![deny(missing_docs)]
+
+extern crate alloc;
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+
+/// A cooperative cancellation token.
+///
+/// Clones of a token share their state, so a token can be handed to a
+/// verifier while a clone is kept to cancel it, e.g. from another thread.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    #[cfg(feature = "std")]
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Returns a new token that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a new token that cancels itself once `timeout` has elapsed.
+    #[cfg(feature = "std")]
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            deadline: Instant::now().checked_add(timeout),
+        }
+    }
+
+    /// Cancels this token, and every clone of it.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if this token has been cancelled, or if its deadline
+    /// has passed.
+    pub fn is_cancelled(&self) -> bool {
+        #[cfg(feature = "std")]
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return true;
+        }
+
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_cancellation() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+        clone.cancel();
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn timeout_cancels() {
+        assert!(CancellationToken::with_timeout(Duration::ZERO).is_cancelled());
+        assert!(!CancellationToken::with_timeout(Duration::from_secs(3600)).is_cancelled());
+    }
+}
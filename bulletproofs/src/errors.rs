@@ -18,7 +18,7 @@ pub enum ProofError {
     /// factors does not match the number of values.
     WrongNumBlindingFactors,
     /// This error occurs when attempting to create a proof with
-    /// bitsize other than \\(8\\), \\(16\\), \\(32\\), or \\(64\\).
+    /// bitsize other than \\(8\\), \\(16\\), \\(32\\), \\(64\\), or \\(128\\).
     InvalidBitsize,
     /// This error occurs when attempting to create an aggregated
     /// proof with non-power-of-two aggregation size.
@@ -38,6 +38,12 @@ pub enum ProofError {
     ProvingError(MPCError),
     /// This error occurs if serialization fails
     SerializationError(String),
+    /// This error occurs when a verification is given up because its
+    /// cancellation token was cancelled.
+    Cancelled,
+    /// This error occurs when the scratch buffer of a heapless
+    /// verification is too small for the proof.
+    ScratchTooSmall,
 }
 
 impl fmt::Debug for ProofError {
@@ -48,7 +54,9 @@ impl fmt::Debug for ProofError {
             ProofError::WrongNumBlindingFactors => {
                 write!(f, "Wrong number of blinding factors supplied.")
             }
-            ProofError::InvalidBitsize => write!(f, "Invalid bitsize, must have n = 8,16,32,64."),
+            ProofError::InvalidBitsize => {
+                write!(f, "Invalid bitsize, must have n = 8,16,32,64,128.")
+            }
             ProofError::InvalidAggregation => {
                 write!(f, "Invalid aggregation size, m must be a power of 2.")
             }
@@ -64,6 +72,10 @@ impl fmt::Debug for ProofError {
             ProofError::InvalidInputLength => {
                 write!(f, "Input vectors must all the the same length.")
             }
+            ProofError::Cancelled => write!(f, "Verification was cancelled."),
+            ProofError::ScratchTooSmall => {
+                write!(f, "Scratch buffer is too small for the proof.")
+            }
         }
     }
 }
@@ -98,7 +110,7 @@ pub enum MPCError {
     /// which would annihilate the blinding factors.
     MaliciousDealer,
     /// This error occurs when attempting to create a proof with
-    /// bitsize other than \\(8\\), \\(16\\), \\(32\\), or \\(64\\).
+    /// bitsize other than \\(8\\), \\(16\\), \\(32\\), \\(64\\), or \\(128\\).
     InvalidBitsize,
     /// This error occurs when attempting to create an aggregated
     /// proof with non-power-of-two aggregation size.
@@ -126,7 +138,7 @@ impl fmt::Debug for MPCError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             MPCError::MaliciousDealer => write!(f, "Dealer gave a malicious challenge value."),
-            MPCError::InvalidBitsize => write!(f, "Invalid bitsize, must have n = 8,16,32,64"),
+            MPCError::InvalidBitsize => write!(f, "Invalid bitsize, must have n = 8,16,32,64,128"),
             MPCError::InvalidAggregation => {
                 write!(f, "Invalid aggregation size, m must be a power of 2")
             }
@@ -173,6 +185,7 @@ pub enum R1CSError {
     },
 }
 
+#[cfg(feature = "yoloproofs")]
 impl fmt::Debug for R1CSError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -187,6 +200,7 @@ impl fmt::Debug for R1CSError {
     }
 }
 
+#[cfg(feature = "yoloproofs")]
 impl fmt::Display for R1CSError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
@@ -8,7 +8,10 @@ extern crate alloc;
 
 use ark_ec::{AffineRepr, CurveGroup};
 use ark_ff::PrimeField;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
 use ark_std::marker::PhantomData;
 use ark_std::{ops::Add, rand::SeedableRng, vec::Vec};
 use digest::Digest;
@@ -26,6 +29,10 @@ use sha3::Sha3_512;
 /// * `B`: the basepoint;
 /// * `B_blinding`: the result of SHA3-512
 ///   hash-to-group on input `B_bytes`.
+///
+/// Applications that should not share commitment keys with each other
+/// can instead derive both bases from their own label, with
+/// [`PedersenGens::new_from_label`].
 
 #[derive(Copy, Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct PedersenGens<G: AffineRepr> {
@@ -43,6 +50,29 @@ impl<G: AffineRepr> PedersenGens<G> {
             .add(self.B_blinding.mul_bigint(blinding.into_bigint()))
             .into_affine()
     }
+
+    /// Creates the generators of the application labelled `label`.
+    ///
+    /// Both `B` and `B_blinding` are the result of SHA3-512 hash-to-group
+    /// on input `label`, so that applications with different labels never
+    /// commit under the same bases, and no discrete log relation between
+    /// the two bases is known.
+    pub fn new_from_label(label: &[u8]) -> Self {
+        let mut hash = Sha3_512::new();
+        Digest::update(&mut hash, b"PedersenGens");
+        Digest::update(&mut hash, label);
+        let h = hash.finalize();
+
+        let mut res = [0u8; 32];
+        res.copy_from_slice(&h[..32]);
+
+        let mut prng = ChaChaRng::from_seed(res);
+
+        PedersenGens {
+            B: G::rand(&mut prng),
+            B_blinding: G::rand(&mut prng),
+        }
+    }
 }
 
 impl<G: AffineRepr> Default for PedersenGens<G> {
@@ -147,7 +177,15 @@ impl<G: AffineRepr> Iterator for GeneratorsChain<G> {
 /// chain, and even forward-compatible to multiparty aggregation of
 /// constraint system proofs, since the generators are namespaced by
 /// their party index.
-#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+///
+/// # Serialization
+///
+/// Since the generators are fully determined by their capacities, a
+/// `BulletproofGens` is serialized compactly as `gens_capacity`,
+/// `party_capacity` and a digest of the generators. Deserialization
+/// rebuilds the generators and checks them against the digest, so a
+/// proof that embeds its generators no longer carries every point.
+#[derive(Clone)]
 pub struct BulletproofGens<G: AffineRepr> {
     /// The maximum number of usable generators for each party.
     pub gens_capacity: usize,
@@ -183,6 +221,26 @@ impl<G: AffineRepr> BulletproofGens<G> {
         gens
     }
 
+    /// Returns a digest of all of the generators, which can be used to
+    /// check that two parties derived the same generators.
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hash = Sha3_512::new();
+        Digest::update(&mut hash, b"BulletproofGens");
+        Digest::update(&mut hash, (self.gens_capacity as u64).to_le_bytes());
+        Digest::update(&mut hash, (self.party_capacity as u64).to_le_bytes());
+
+        let mut bytes = Vec::new();
+        for G_i in self.G_vec.iter().chain(self.H_vec.iter()).flatten() {
+            bytes.clear();
+            G_i.serialize_compressed(&mut bytes).unwrap();
+            Digest::update(&mut hash, &bytes);
+        }
+
+        let mut res = [0u8; 32];
+        res.copy_from_slice(&hash.finalize()[..32]);
+        res
+    }
+
     /// Returns j-th share of generators, with an appropriate
     /// slice of vectors G and H for the j-th range proof.
     pub fn share(&self, j: usize) -> BulletproofGensShare<'_, G> {
@@ -244,6 +302,60 @@ impl<G: AffineRepr> BulletproofGens<G> {
     }
 }
 
+/// The largest number of generators per kind (i.e. `gens_capacity *
+/// party_capacity`) that is rebuilt on deserialization. This bounds the
+/// work that an untrusted encoding can cause.
+const MAX_DESERIALIZED_GENERATORS: usize = 1 << 16;
+
+impl<G: AffineRepr> CanonicalSerialize for BulletproofGens<G> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        (self.gens_capacity as u64).serialize_with_mode(&mut writer, compress)?;
+        (self.party_capacity as u64).serialize_with_mode(&mut writer, compress)?;
+        self.digest().serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        0u64.serialized_size(compress) * 2 + [0u8; 32].serialized_size(compress)
+    }
+}
+
+impl<G: AffineRepr> Valid for BulletproofGens<G> {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl<G: AffineRepr> CanonicalDeserialize for BulletproofGens<G> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let gens_capacity = u64::deserialize_with_mode(&mut reader, compress, validate)?;
+        let party_capacity = u64::deserialize_with_mode(&mut reader, compress, validate)?;
+        let digest = <[u8; 32]>::deserialize_with_mode(&mut reader, compress, validate)?;
+
+        let gens_capacity =
+            usize::try_from(gens_capacity).map_err(|_| SerializationError::InvalidData)?;
+        let party_capacity =
+            usize::try_from(party_capacity).map_err(|_| SerializationError::InvalidData)?;
+        match gens_capacity.checked_mul(party_capacity) {
+            Some(n) if n <= MAX_DESERIALIZED_GENERATORS => {}
+            _ => return Err(SerializationError::InvalidData),
+        }
+
+        let gens = BulletproofGens::new(gens_capacity, party_capacity);
+        if gens.digest() != digest {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(gens)
+    }
+}
+
 struct AggregatedGensIter<'a, G: AffineRepr> {
     array: &'a Vec<Vec<G>>,
     n: usize,
@@ -308,6 +420,53 @@ impl<'a, G: AffineRepr> BulletproofGensShare<'a, G> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn compact_serialization_roundtrip() {
+        type G = ark_secq256k1::Affine;
+
+        let gens = BulletproofGens::<G>::new(64, 2);
+        let mut bytes = Vec::new();
+        gens.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), gens.compressed_size());
+        assert_eq!(bytes.len(), 48);
+
+        let decoded = BulletproofGens::<G>::deserialize_compressed(&bytes[..]).unwrap();
+        assert_eq!(decoded.gens_capacity, 64);
+        assert_eq!(decoded.party_capacity, 2);
+        assert_eq!(decoded.G_vec, gens.G_vec);
+        assert_eq!(decoded.H_vec, gens.H_vec);
+
+        // A digest that does not match the capacities is rejected.
+        let mut tampered = bytes.clone();
+        tampered[0] = 32;
+        assert!(BulletproofGens::<G>::deserialize_compressed(&tampered[..]).is_err());
+
+        // So are capacities that would take too long to rebuild.
+        let mut huge = Vec::new();
+        u64::MAX.serialize_compressed(&mut huge).unwrap();
+        2u64.serialize_compressed(&mut huge).unwrap();
+        gens.digest().serialize_compressed(&mut huge).unwrap();
+        assert!(BulletproofGens::<G>::deserialize_compressed(&huge[..]).is_err());
+    }
+
+    #[test]
+    fn pedersen_gens_from_label() {
+        type G = ark_secq256k1::Affine;
+
+        let gens = PedersenGens::<G>::new_from_label(b"app");
+        let again = PedersenGens::<G>::new_from_label(b"app");
+        assert_eq!((gens.B, gens.B_blinding), (again.B, again.B_blinding));
+        assert_ne!(gens.B, gens.B_blinding);
+
+        // Another label, or the default generators, give other bases.
+        let other = PedersenGens::<G>::new_from_label(b"other app");
+        assert_ne!(gens.B, other.B);
+        assert_ne!(gens.B_blinding, other.B_blinding);
+        let default = PedersenGens::<G>::default();
+        assert_ne!(gens.B, default.B);
+        assert_ne!(gens.B_blinding, default.B_blinding);
+    }
+
     #[test]
     fn aggregated_gens_iter_matches_flat_map() {
         type G = ark_secq256k1::Affine;
@@ -12,6 +12,8 @@ use ark_std::{
 use merlin::Transcript;
 
 use crate::errors::ProofError;
+use crate::generators::BulletproofGens;
+use crate::msm;
 use crate::transcript::TranscriptProtocol;
 
 #[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
@@ -36,6 +38,67 @@ pub struct VerificationScalars<G: AffineRepr> {
     pub s: Vec<G::ScalarField>,
 }
 
+/// The generators that inner-product proofs of a fixed size are made
+/// against, laid out contiguously.
+///
+/// [`InnerProductProof::create`] borrows its generators, but aggregated range
+/// proofs take them from the shares of several parties, which first have to
+/// be collected into one vector. A `ProverKey` collects them once, so that a
+/// prover that makes many proofs of the same size, e.g. with
+/// [`RangeProof::prove_multiple_with_key`](crate::RangeProof::prove_multiple_with_key),
+/// does not collect them again for every proof.
+#[derive(Clone, Debug)]
+pub struct ProverKey<G: AffineRepr> {
+    n: usize,
+    m: usize,
+    G_vec: Vec<G>,
+    H_vec: Vec<G>,
+}
+
+impl<G: AffineRepr> ProverKey<G> {
+    /// Collects the generators of `bp_gens` for proofs of `n` bits for each
+    /// of `m` parties, i.e. the first `n` generators of the first `m`
+    /// parties.
+    ///
+    /// Returns [`ProofError::InvalidGeneratorsLength`] if `bp_gens` has fewer
+    /// generators or parties, and [`ProofError::InvalidInputLength`] if
+    /// \(n \cdot m\) is not a power of two.
+    pub fn new(bp_gens: &BulletproofGens<G>, n: usize, m: usize) -> Result<Self, ProofError> {
+        if bp_gens.gens_capacity < n || bp_gens.party_capacity < m {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+        if !n.checked_mul(m).is_some_and(usize::is_power_of_two) {
+            return Err(ProofError::InvalidInputLength);
+        }
+        Ok(Self {
+            n,
+            m,
+            G_vec: bp_gens.G(n, m).copied().collect(),
+            H_vec: bp_gens.H(n, m).copied().collect(),
+        })
+    }
+
+    /// Returns the number of generators of each party.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// Returns the number of parties.
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    /// Returns the \(G\) generators of all parties, in order.
+    pub fn G(&self) -> &[G] {
+        &self.G_vec
+    }
+
+    /// Returns the \(H\) generators of all parties, in order.
+    pub fn H(&self) -> &[G] {
+        &self.H_vec
+    }
+}
+
 impl<G: AffineRepr> InnerProductProof<G> {
     /// Create an inner-product proof.
     ///
@@ -48,30 +111,30 @@ impl<G: AffineRepr> InnerProductProof<G> {
     ///
     /// The lengths of the vectors must all be the same, and must all be
     /// either 0 or a power of 2.
+    ///
+    /// The generators are borrowed: the first round folds them into new
+    /// vectors of half their length, which the later rounds fold in place.
     #[allow(clippy::too_many_arguments)]
     pub fn create(
         transcript: &mut Transcript,
         Q: &G,
         G_factors: &[G::ScalarField],
         H_factors: &[G::ScalarField],
-        mut G_vec: Vec<G>,
-        mut H_vec: Vec<G>,
+        G_vec: &[G],
+        H_vec: &[G],
         mut a_vec: Vec<G::ScalarField>,
         mut b_vec: Vec<G::ScalarField>,
     ) -> InnerProductProof<G> {
-        // Create slices G, H, a, b backed by their respective
-        // vectors.  This lets us reslice as we compress the lengths
-        // of the vectors in the main loop below.
-        let mut G = &mut G_vec[..];
-        let mut H = &mut H_vec[..];
+        // Create slices a, b backed by their respective vectors.  This
+        // lets us reslice as we compress the lengths of the vectors in
+        // the main loop below.
         let mut a = &mut a_vec[..];
         let mut b = &mut b_vec[..];
 
-        let mut n = G.len();
+        let mut n = G_vec.len();
 
         // All of the input vectors must have the same length.
-        assert_eq!(G.len(), n);
-        assert_eq!(H.len(), n);
+        assert_eq!(H_vec.len(), n);
         assert_eq!(a.len(), n);
         assert_eq!(b.len(), n);
         assert_eq!(G_factors.len(), n);
@@ -86,14 +149,19 @@ impl<G: AffineRepr> InnerProductProof<G> {
         let mut L_vec = Vec::with_capacity(lg_n);
         let mut R_vec = Vec::with_capacity(lg_n);
 
+        // The folded generators, which the first round allocates at half
+        // the length of the inputs.
+        let mut G_folded = Vec::new();
+        let mut H_folded = Vec::new();
+
         // If it's the first iteration, unroll the Hprime = H*y_inv scalar mults
         // into multiscalar muls, for performance.
         if n != 1 {
             n /= 2;
             let (a_L, a_R) = a.split_at_mut(n);
             let (b_L, b_R) = b.split_at_mut(n);
-            let (G_L, G_R) = G.split_at_mut(n);
-            let (H_L, H_R) = H.split_at_mut(n);
+            let (G_L, G_R) = G_vec.split_at(n);
+            let (H_L, H_R) = H_vec.split_at(n);
 
             let c_L = inner_product(a_L, b_R);
             let c_R = inner_product(a_R, b_L);
@@ -151,31 +219,38 @@ impl<G: AffineRepr> InnerProductProof<G> {
 
             let u_inv = u.inverse().unwrap();
 
+            G_folded.reserve_exact(n);
+            H_folded.reserve_exact(n);
             for i in 0..n {
                 a_L[i] = a_L[i] * u + u_inv * a_R[i];
                 b_L[i] = b_L[i] * u_inv + u * b_R[i];
 
-                G_L[i] = G::Group::msm(
-                    &[G_L[i], G_R[i]],
-                    &[u_inv * G_factors[i], u * G_factors[n + i]],
-                )
-                .unwrap()
-                .into_affine();
-
-                H_L[i] = G::Group::msm(
-                    &[H_L[i], H_R[i]],
-                    &[u * H_factors[i], u_inv * H_factors[n + i]],
-                )
-                .unwrap()
-                .into_affine();
+                G_folded.push(
+                    G::Group::msm(
+                        &[G_L[i], G_R[i]],
+                        &[u_inv * G_factors[i], u * G_factors[n + i]],
+                    )
+                    .unwrap()
+                    .into_affine(),
+                );
+
+                H_folded.push(
+                    G::Group::msm(
+                        &[H_L[i], H_R[i]],
+                        &[u * H_factors[i], u_inv * H_factors[n + i]],
+                    )
+                    .unwrap()
+                    .into_affine(),
+                );
             }
 
             a = a_L;
             b = b_L;
-            G = G_L;
-            H = H_L;
         }
 
+        let mut G = &mut G_folded[..];
+        let mut H = &mut H_folded[..];
+
         while n != 1 {
             n /= 2;
             let (a_L, a_R) = a.split_at_mut(n);
@@ -325,6 +400,55 @@ impl<G: AffineRepr> InnerProductProof<G> {
         })
     }
 
+    /// Computes the same verification scalars as [`verification_scalars`](Self::verification_scalars),
+    /// but writes them to the caller's buffers instead of allocating: the first \(\lg n\)
+    /// entries of `challenges_sq` and `challenges_inv_sq`, and the first \(n\) entries of `s`.
+    /// Returns [`ProofError::ScratchTooSmall`] if a buffer is too short.
+    #[cfg(feature = "heapless")]
+    pub(crate) fn verification_scalars_into(
+        &self,
+        n: usize,
+        transcript: &mut Transcript,
+        challenges_sq: &mut [G::ScalarField],
+        challenges_inv_sq: &mut [G::ScalarField],
+        s: &mut [G::ScalarField],
+    ) -> Result<(), ProofError> {
+        let lg_n = self.L_vec.len();
+        if lg_n >= 32 || n != (1 << lg_n) {
+            return Err(ProofError::VerificationError);
+        }
+        if challenges_sq.len() < lg_n || challenges_inv_sq.len() < lg_n || s.len() < n {
+            return Err(ProofError::ScratchTooSmall);
+        }
+
+        <Transcript as TranscriptProtocol<G>>::innerproduct_domain_sep(transcript, n as u64);
+
+        // 1. Recompute x_k,...,x_1 based on the proof transcript, and their inverses. The
+        // inverses are computed one by one, as a batch inversion would allocate.
+
+        let mut allinv = G::ScalarField::one();
+        for (i, (L, R)) in self.L_vec.iter().zip(self.R_vec.iter()).enumerate() {
+            transcript.validate_and_append_point(b"L", L)?;
+            transcript.validate_and_append_point(b"R", R)?;
+            let u = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"u");
+            let u_inv = u.inverse().ok_or(ProofError::VerificationError)?;
+            allinv.mul_assign(u_inv);
+            challenges_sq[i] = u * u;
+            challenges_inv_sq[i] = u_inv * u_inv;
+        }
+
+        // 2. Compute s values inductively.
+
+        s[0] = allinv;
+        for i in 1..n {
+            let lg_i = (32 - 1 - (i as u32).leading_zeros()) as usize;
+            let k = 1 << lg_i;
+            s[i] = s[i - k] * challenges_sq[(lg_n - 1) - lg_i];
+        }
+
+        Ok(())
+    }
+
     /// Verify a generated proof
     ///
     /// This method is convenient for testing, but for efficiency
@@ -387,7 +511,7 @@ impl<G: AffineRepr> InnerProductProof<G> {
             .chain(neg_u_inv_sq)
             .collect::<Vec<G::ScalarField>>();
 
-        let expect_P = G::Group::msm(&bases, &scalars).unwrap().into_affine();
+        let expect_P = msm::msm(&bases, &scalars).unwrap().into_affine();
 
         if expect_P == *P {
             Ok(())
@@ -499,8 +623,8 @@ mod tests {
             &Q,
             &G_factors,
             &H_factors,
-            G_.clone(),
-            H.clone(),
+            &G_,
+            &H,
             a.clone(),
             b.clone(),
         );
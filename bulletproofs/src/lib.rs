@@ -2,18 +2,23 @@
 
 mod util;
 
+mod cancel;
 mod errors;
 mod generators;
 mod inner_product_proof;
 mod linear_proof;
+pub mod msm;
 mod range_proof;
 mod transcript;
 
+pub use crate::cancel::CancellationToken;
 pub use crate::errors::ProofError;
 pub use crate::generators::{BulletproofGens, BulletproofGensShare, PedersenGens};
-pub use crate::inner_product_proof::{inner_product, InnerProductProof};
+pub use crate::inner_product_proof::{inner_product, InnerProductProof, ProverKey};
 pub use crate::linear_proof::LinearProof;
 pub use crate::range_proof::RangeProof;
+#[cfg(feature = "heapless")]
+pub use crate::range_proof::VerificationScratch;
 
 #[cfg(feature = "yoloproofs")]
 pub mod r1cs;
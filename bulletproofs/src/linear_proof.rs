@@ -6,14 +6,16 @@ use alloc::vec::Vec;
 use ark_ec::{AffineRepr, VariableBaseMSM};
 use ark_ff::Field;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::rand::Rng;
-use ark_std::{One, UniformRand};
+use ark_std::rand::{CryptoRng, RngCore};
+use ark_std::{vec, One, UniformRand, Zero};
 
 use merlin::Transcript;
 
 use crate::errors::ProofError;
 use crate::inner_product_proof::inner_product;
+use crate::msm;
 use crate::transcript::TranscriptProtocol;
+use crate::util;
 
 /// A linear proof, which is an "lightweight" version of a Bulletproofs inner-product proof
 /// Protocol: Section E.3 of [GHL'21](https://eprint.iacr.org/2021/1397.pdf)
@@ -36,6 +38,12 @@ type ScalarFields<G> = Vec<<G as AffineRepr>::ScalarField>;
 type ScalarField<G> = <G as AffineRepr>::ScalarField;
 type VerificationScalarsResult<G> =
     Result<(ScalarFields<G>, ScalarFields<G>, ScalarField<G>), ProofError>;
+type ReplayedChallenges<G> = (
+    ScalarFields<G>,
+    ScalarFields<G>,
+    ScalarField<G>,
+    ScalarField<G>,
+);
 
 impl<G: AffineRepr> LinearProof<G> {
     /// Create a linear proof, a lightweight variant of a Bulletproofs inner-product proof.
@@ -44,7 +52,7 @@ impl<G: AffineRepr> LinearProof<G> {
     /// The lengths of the vectors must all be the same, and must all be either 0 or a power of 2.
     /// The proof is created with respect to the bases \\(G\\).
     #[allow(clippy::too_many_arguments)]
-    pub fn create<R: Rng>(
+    pub fn create<R: RngCore + CryptoRng>(
         transcript: &mut Transcript,
         rng: &mut R,
         // Commitment to witness
@@ -180,41 +188,20 @@ impl<G: AffineRepr> LinearProof<G> {
         b_vec: Vec<G::ScalarField>,
     ) -> Result<(), ProofError> {
         let n = b_vec.len();
-        if G.len() != n {
-            return Err(ProofError::InvalidGeneratorsLength);
-        }
-
-        // Append all public data to the transcript
-        //transcript.innerproduct_domain_sep(n as u64);
-        <Transcript as TranscriptProtocol<G>>::innerproduct_domain_sep(transcript, n as u64);
-        transcript.append_point(b"C", C);
-        for b_i in &b_vec {
-            //transcript.append_scalar::<G>(b"b_i", b_i);
-            <Transcript as TranscriptProtocol<G>>::append_scalar(transcript, b"b_i", b_i);
-        }
-        for G_i in G {
-            transcript.append_point(b"G_i", G_i);
-        }
-        transcript.append_point(b"F", F);
-        transcript.append_point(b"B", B);
-
-        let (x_vec, x_inv_vec, b_0) = self.verification_scalars(n, transcript, b_vec)?;
-        transcript.append_point(b"S", &self.S);
-        //let x_star = transcript.challenge_scalar::<G>(b"x_star");
-        let x_star: G::ScalarField =
-            <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"x_star");
+        let (x_vec, x_inv_vec, b_0, x_star) =
+            self.replay_challenges(transcript, C, G, F, B, b_vec)?;
 
         // L_R_factors = sum_{j=0}^{l-1} (x_j * L_j + x_j^{-1} * R_j)
         //
         // Note: in GHL'21 the verification equation is incorrect (as of 05/03/22), with x_j and x_j^{-1} reversed.
         // (Incorrect paper equation: sum_{j=0}^{l-1} (x_j^{-1} * L_j + x_j * R_j) )
-        let L_R_factors = G::Group::msm(&self.L_vec, &x_vec).unwrap()
-            + G::Group::msm(&self.R_vec, &x_inv_vec).unwrap();
+        let L_R_factors =
+            msm::msm(&self.L_vec, &x_vec).unwrap() + msm::msm(&self.R_vec, &x_inv_vec).unwrap();
 
         // This is an optimized way to compute the base case G (G_0 in the paper):
         // G_0 = sum_{i=0}^{2^{l-1}} (x<i> * G_i)
         let s = self.subset_product(n, x_vec);
-        let G_0 = G::Group::msm(G, &s).unwrap();
+        let G_0 = msm::msm(G, &s).unwrap();
 
         // This matches the verification equation:
         // S == r_star * B + a_star * b_0 * F
@@ -233,6 +220,194 @@ impl<G: AffineRepr> LinearProof<G> {
         }
     }
 
+    /// Create an aggregated linear proof, which proves the `k` statements
+    /// \\(\langle a_j, b \rangle = c_j\\) for the commitments
+    /// \\(C_j = \langle a_j, G \rangle + c_j F + r_j B\\) at once, for a public
+    /// vector \\(b\\) that all the statements share.
+    ///
+    /// The commitments are combined with the powers of a challenge \\(\rho\\),
+    /// and a single linear proof is made for the combination, so the proof has
+    /// the size of one linear proof whatever \\(k\\) is. It must be checked with
+    /// [`LinearProof::verify_aggregated`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_aggregated<R: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut R,
+        // Commitments to the witnesses
+        C_vec: &[G],
+        // Blinding factors for C_vec
+        r_vec: &[G::ScalarField],
+        // Secret scalar vectors a_j
+        a_vecs: &[Vec<G::ScalarField>],
+        // Public scalar vector b
+        b_vec: Vec<G::ScalarField>,
+        // Generator vector
+        G_vec: Vec<G>,
+        // Pedersen generator F, for committing to the secret value
+        F: &G,
+        // Pedersen generator B, for committing to the blinding value
+        B: &G,
+    ) -> Result<LinearProof<G>, ProofError> {
+        let k = C_vec.len();
+        if k == 0 {
+            return Err(ProofError::InvalidAggregation);
+        }
+        if r_vec.len() != k {
+            return Err(ProofError::WrongNumBlindingFactors);
+        }
+        if a_vecs.len() != k || a_vecs.iter().any(|a| a.len() != b_vec.len()) {
+            return Err(ProofError::InvalidInputLength);
+        }
+
+        let (C, rho) = Self::aggregate_commitments(transcript, C_vec)?;
+        let mut r = G::ScalarField::zero();
+        let mut a_vec = vec![G::ScalarField::zero(); b_vec.len()];
+        for ((r_j, a_j), rho_j) in r_vec.iter().zip(a_vecs).zip(util::exp_iter::<G>(rho)) {
+            r += rho_j * r_j;
+            for (a_i, a_j_i) in a_vec.iter_mut().zip(a_j) {
+                *a_i += rho_j * a_j_i;
+            }
+        }
+
+        Self::create(transcript, rng, &C, r, a_vec, b_vec, G_vec, F, B)
+    }
+
+    /// Verifies an aggregated linear proof that was made by
+    /// [`LinearProof::create_aggregated`] for the commitments `C_vec`.
+    pub fn verify_aggregated(
+        &self,
+        transcript: &mut Transcript,
+        // Commitments to the witnesses
+        C_vec: &[G],
+        // Generator vector
+        G: &[G],
+        // Pedersen generator F, for committing to the secret value
+        F: &G,
+        // Pedersen generator B, for committing to the blinding value
+        B: &G,
+        // Public scalar vector b
+        b_vec: Vec<G::ScalarField>,
+    ) -> Result<(), ProofError> {
+        if C_vec.is_empty() {
+            return Err(ProofError::InvalidAggregation);
+        }
+        let (C, _) = Self::aggregate_commitments(transcript, C_vec)?;
+        self.verify(transcript, &C, G, F, B, b_vec)
+    }
+
+    /// Appends the commitments of an aggregated proof to the transcript, and
+    /// returns their combination \\(\sum_j \rho^j C_j\\) with the challenge
+    /// \\(\rho\\).
+    fn aggregate_commitments(
+        transcript: &mut Transcript,
+        C_vec: &[G],
+    ) -> Result<(G, G::ScalarField), ProofError> {
+        <Transcript as TranscriptProtocol<G>>::linearproof_aggregation_domain_sep(
+            transcript,
+            C_vec.len() as u64,
+        );
+        for C_j in C_vec {
+            transcript.validate_and_append_point(b"C_j", C_j)?;
+        }
+        let rho: G::ScalarField =
+            <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"rho");
+        let powers: Vec<_> = util::exp_iter::<G>(rho).take(C_vec.len()).collect();
+        let C = msm::msm(C_vec, &powers).map_err(|_| ProofError::InvalidInputLength)?;
+        Ok((C.into(), rho))
+    }
+
+    /// Adds the terms of the verification equation of this proof to `acc`,
+    /// weighted by a random scalar, so that it can be checked in the same
+    /// multiscalar multiplication as other proofs.
+    ///
+    /// The shared slots follow [`RangeProof::accumulate_verification_scalars`]:
+    /// `B` is in slot 0, `F` in slot 1, and the `i`-th generator of `G` in the
+    /// slot `2 + 2i`. A range proof and a linear proof over the same generators
+    /// can thus be checked together, e.g. on one transcript.
+    ///
+    /// [`RangeProof::accumulate_verification_scalars`]: crate::RangeProof::accumulate_verification_scalars
+    #[allow(clippy::too_many_arguments)]
+    pub fn accumulate_verification_scalars<T: RngCore + CryptoRng, M>(
+        &self,
+        transcript: &mut Transcript,
+        // Commitment to witness
+        C: &G,
+        // Generator vector
+        G: &[G],
+        // Pedersen generator F, for committing to the secret value
+        F: &G,
+        // Pedersen generator B, for committing to the blinding value
+        B: &G,
+        // Public scalar vector b
+        b_vec: Vec<G::ScalarField>,
+        rng: &mut T,
+        acc: &mut msm::MsmAccumulator<G, M>,
+    ) -> Result<(), ProofError> {
+        let n = b_vec.len();
+        let (x_vec, x_inv_vec, b_0, x_star) =
+            self.replay_challenges(transcript, C, G, F, B, b_vec)?;
+
+        let mut instance_rng = transcript.build_rng().finalize(rng);
+        let weight = G::ScalarField::rand(&mut instance_rng);
+        let minus_x_star = -x_star * weight;
+
+        // The terms of r_star * B + a_star * b_0 * F - x_star * (C + L_R_factors) + a_star * G_0 - S
+        acc.push(self.S, -weight);
+        acc.push(*C, minus_x_star);
+        for (L, x_j) in self.L_vec.iter().zip(&x_vec) {
+            acc.push(*L, minus_x_star * x_j);
+        }
+        for (R, x_j_inv) in self.R_vec.iter().zip(&x_inv_vec) {
+            acc.push(*R, minus_x_star * x_j_inv);
+        }
+        acc.add_shared(0, self.r * weight);
+        acc.add_shared(1, self.a * b_0 * weight);
+        for (i, s_i) in self.subset_product(n, x_vec).into_iter().enumerate() {
+            acc.add_shared(2 + 2 * i, self.a * s_i * weight);
+        }
+        Ok(())
+    }
+
+    /// Appends the public data of the proof to the transcript, and returns
+    /// the challenges \\([x\_{i}]\\) and \\([x\_{i}^{-1}]\\), the base case \\(b_0\\)
+    /// and the final challenge \\(x^*\\).
+    fn replay_challenges(
+        &self,
+        transcript: &mut Transcript,
+        C: &G,
+        G: &[G],
+        F: &G,
+        B: &G,
+        b_vec: Vec<G::ScalarField>,
+    ) -> Result<ReplayedChallenges<G>, ProofError> {
+        let n = b_vec.len();
+        if G.len() != n {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+
+        // Append all public data to the transcript
+        //transcript.innerproduct_domain_sep(n as u64);
+        <Transcript as TranscriptProtocol<G>>::innerproduct_domain_sep(transcript, n as u64);
+        transcript.append_point(b"C", C);
+        for b_i in &b_vec {
+            //transcript.append_scalar::<G>(b"b_i", b_i);
+            <Transcript as TranscriptProtocol<G>>::append_scalar(transcript, b"b_i", b_i);
+        }
+        for G_i in G {
+            transcript.append_point(b"G_i", G_i);
+        }
+        transcript.append_point(b"F", F);
+        transcript.append_point(b"B", B);
+
+        let (x_vec, x_inv_vec, b_0) = self.verification_scalars(n, transcript, b_vec)?;
+        transcript.append_point(b"S", &self.S);
+        //let x_star = transcript.challenge_scalar::<G>(b"x_star");
+        let x_star: G::ScalarField =
+            <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"x_star");
+
+        Ok((x_vec, x_inv_vec, b_0, x_star))
+    }
+
     /// Computes the vector of challenge scalars \\([x\_{i}]\\), and its inverse \\([x\_{i}^{-1}]\\)
     /// for combined multiscalar multiplication in a parent protocol.
     /// Also computes \\(b_0\\) which is the base case for public vector \\(b\\).
@@ -396,4 +571,114 @@ mod tests {
     fn test_linear_proof_64() {
         test_helper(64);
     }*/
+
+    use super::*;
+
+    use crate::generators::{BulletproofGens, PedersenGens};
+    use ark_secq256k1::{Affine, Fr};
+
+    #[test]
+    fn test_linear_proof_aggregated() {
+        let mut rng = rand::thread_rng();
+        let (n, k) = (8, 3);
+        let bp_gens = BulletproofGens::<Affine>::new(n, 1);
+        let G: Vec<Affine> = bp_gens.share(0).G(n).cloned().collect();
+        let pc_gens = PedersenGens::<Affine>::default();
+        let (F, B) = (pc_gens.B, pc_gens.B_blinding);
+
+        // k statements <a_j, b> = c_j, with a shared public b.
+        let b: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let a: Vec<Vec<Fr>> = (0..k)
+            .map(|_| (0..n).map(|_| Fr::rand(&mut rng)).collect())
+            .collect();
+        let r: Vec<Fr> = (0..k).map(|_| Fr::rand(&mut rng)).collect();
+        let commit = |a_j: &[Fr], r_j: Fr, c_j: Fr| -> Affine {
+            (msm::msm(&G, a_j).unwrap() + B * r_j + F * c_j).into()
+        };
+        let C: Vec<Affine> = a
+            .iter()
+            .zip(&r)
+            .map(|(a_j, r_j)| commit(a_j, *r_j, inner_product(a_j, &b)))
+            .collect();
+
+        let proof = LinearProof::create_aggregated(
+            &mut Transcript::new(b"aggregated linear proof test"),
+            &mut rng,
+            &C,
+            &r,
+            &a,
+            b.clone(),
+            G.clone(),
+            &F,
+            &B,
+        )
+        .unwrap();
+        assert_eq!(proof.L_vec.len(), 3);
+        assert!(proof
+            .verify_aggregated(
+                &mut Transcript::new(b"aggregated linear proof test"),
+                &C,
+                &G,
+                &F,
+                &B,
+                b.clone(),
+            )
+            .is_ok());
+
+        // The proof does not hold for a subset, or a reordering, of the commitments.
+        let verify = |C: &[Affine]| {
+            proof.verify_aggregated(
+                &mut Transcript::new(b"aggregated linear proof test"),
+                C,
+                &G,
+                &F,
+                &B,
+                b.clone(),
+            )
+        };
+        assert!(verify(&C[..2]).is_err());
+        assert!(verify(&[C[1], C[0], C[2]]).is_err());
+
+        // A single wrong statement makes the whole proof fail.
+        let mut bad_C = C.clone();
+        bad_C[1] = commit(&a[1], r[1], inner_product(&a[1], &b) + Fr::from(1u64));
+        let bad_proof = LinearProof::create_aggregated(
+            &mut Transcript::new(b"aggregated linear proof test"),
+            &mut rng,
+            &bad_C,
+            &r,
+            &a,
+            b.clone(),
+            G.clone(),
+            &F,
+            &B,
+        )
+        .unwrap();
+        assert!(bad_proof
+            .verify_aggregated(
+                &mut Transcript::new(b"aggregated linear proof test"),
+                &bad_C,
+                &G,
+                &F,
+                &B,
+                b.clone(),
+            )
+            .is_err());
+
+        assert_eq!(
+            LinearProof::create_aggregated(
+                &mut Transcript::new(b"aggregated linear proof test"),
+                &mut rng,
+                &C,
+                &r[..2],
+                &a,
+                b,
+                G,
+                &F,
+                &B,
+            )
+            .unwrap_err(),
+            ProofError::WrongNumBlindingFactors
+        );
+    }
 }
@@ -0,0 +1,556 @@
+#![deny(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Multi-scalar multiplication helpers used by the verifiers.
+//!
+//! The verification equations in this crate are single large multiscalar
+//! multiplications of the form \\(\sum_i s_i \cdot P_i\\). This module
+//! implements Pippenger's bucket method with signed window digits, picking the
+//! window size from a simple cost model over the number of terms and the bit
+//! size of the scalar field, instead of the fixed logarithmic rule used by
+//! `ark_ec::VariableBaseMSM`.
+//!
+//! Batch verifiers add the terms of many such equations to a single
+//! [`MsmAccumulator`], which sums the terms on the generators that the
+//! equations share, and which can be reused across batches.
+//!
+//! The accumulator computes its sum through an [`MsmBackend`]. The
+//! [`DefaultMsm`] backend runs the multiplication of this module, and, with
+//! the `parallel` feature, the [`ParallelMsm`] backend runs the parallel
+//! multiplication of `ark_ec`. Applications that have a faster
+//! implementation, e.g. on a GPU, implement [`MsmBackend`] for it and give it
+//! to [`MsmAccumulator::with_backend`].
+
+#[cfg(feature = "parallel")]
+use ark_ec::VariableBaseMSM;
+use ark_ec::{AffineRepr, CurveGroup, Group};
+use ark_ff::PrimeField;
+use ark_std::{vec, vec::Vec, Zero};
+
+use crate::cancel::CancellationToken;
+
+/// The smallest window size considered by [`window_size`]. Signed digits need
+/// at least two bits so that the top window can absorb the final carry.
+const MIN_WINDOW: usize = 2;
+
+/// The largest window size considered by [`window_size`].
+const MAX_WINDOW: usize = 20;
+
+/// Returns the window size, in bits, that minimises the estimated number of
+/// group operations for a multiscalar multiplication with `len` terms over
+/// scalars of `num_bits` bits.
+///
+/// With signed digits of width `c` there are `ceil(num_bits / c) + 1` windows,
+/// each of which costs `len` mixed bucket additions, `2^c` projective additions
+/// to reduce the `2^(c-1)` buckets, and `c` doublings to shift the accumulator.
+/// Projective additions are weighted as one and a half mixed additions.
+pub fn window_size(len: usize, num_bits: usize) -> usize {
+    (MIN_WINDOW..=MAX_WINDOW)
+        .min_by_key(|&c| {
+            let windows = num_bits.div_ceil(c) + 1;
+            windows * (2 * len + 3 * (1 << c) + 2 * c)
+        })
+        .unwrap_or(MIN_WINDOW)
+}
+
+/// Returns the `c` bits of `limbs` starting at bit `offset`.
+fn get_bits(limbs: &[u64], offset: usize, c: usize) -> u64 {
+    let limb = offset / 64;
+    let shift = offset % 64;
+    let mask = (1u64 << c) - 1;
+
+    let lo = limbs.get(limb).map_or(0, |l| l >> shift);
+    let hi = if shift + c > 64 {
+        limbs.get(limb + 1).map_or(0, |l| l << (64 - shift))
+    } else {
+        0
+    };
+
+    (lo | hi) & mask
+}
+
+/// Splits `scalar` into `num_windows` signed digits of width `c`, each in the
+/// range \\([-2^{c-1}, 2^{c-1})\\), least significant digit first.
+fn signed_digits<F: PrimeField>(scalar: &F, c: usize, num_windows: usize) -> Vec<i64> {
+    let bigint = scalar.into_bigint();
+    let limbs = bigint.as_ref();
+    let radix = 1i64 << c;
+    let half = radix >> 1;
+
+    let mut carry = 0i64;
+    (0..num_windows)
+        .map(|w| {
+            let mut digit = get_bits(limbs, w * c, c) as i64 + carry;
+            carry = (digit + half) >> c;
+            digit -= carry * radix;
+            digit
+        })
+        .collect()
+}
+
+/// Computes \\(\sum_i s_i \cdot P_i\\) for `bases` \\(P_i\\) and `scalars`
+/// \\(s_i\\).
+///
+/// This mirrors the interface of `VariableBaseMSM::msm`: if the inputs have
+/// different lengths, the length of the shorter one is returned as an error.
+pub fn msm<G: AffineRepr>(bases: &[G], scalars: &[G::ScalarField]) -> Result<G::Group, usize> {
+    msm_with(bases, scalars, None).map(|p| p.expect("msm: cancelled without a token"))
+}
+
+/// Computes \\(\sum_i s_i \cdot P_i\\) like [`msm`], but checks `cancel`
+/// before every window and returns `Ok(None)` once it has been cancelled.
+pub fn msm_cancellable<G: AffineRepr>(
+    bases: &[G],
+    scalars: &[G::ScalarField],
+    cancel: &CancellationToken,
+) -> Result<Option<G::Group>, usize> {
+    msm_with(bases, scalars, Some(cancel))
+}
+
+/// Computes \\(\sum_i s_i \cdot P_i\\), checking `cancel`, if any, before
+/// every window.
+fn msm_with<G: AffineRepr>(
+    bases: &[G],
+    scalars: &[G::ScalarField],
+    cancel: Option<&CancellationToken>,
+) -> Result<Option<G::Group>, usize> {
+    if bases.len() != scalars.len() {
+        return Err(bases.len().min(scalars.len()));
+    }
+
+    let terms: Vec<(&G, &G::ScalarField)> = bases
+        .iter()
+        .zip(scalars.iter())
+        .filter(|(_, s)| !s.is_zero())
+        .collect();
+
+    match terms.len() {
+        0 => return Ok(Some(G::Group::zero())),
+        1 => return Ok(Some(*terms[0].0 * terms[0].1)),
+        _ => {}
+    }
+
+    let num_bits = G::ScalarField::MODULUS_BIT_SIZE as usize;
+    let c = window_size(terms.len(), num_bits);
+    let num_windows = num_bits.div_ceil(c) + 1;
+
+    let digits: Vec<Vec<i64>> = terms
+        .iter()
+        .map(|(_, s)| signed_digits(*s, c, num_windows))
+        .collect();
+
+    let mut total = G::Group::zero();
+    for w in (0..num_windows).rev() {
+        if cancel.is_some_and(CancellationToken::is_cancelled) {
+            return Ok(None);
+        }
+
+        for _ in 0..c {
+            total.double_in_place();
+        }
+
+        let mut buckets = vec![G::Group::zero(); 1 << (c - 1)];
+        for ((base, _), digit) in terms.iter().zip(digits.iter()) {
+            let d = digit[w];
+            if d > 0 {
+                buckets[(d - 1) as usize] += *base;
+            } else if d < 0 {
+                buckets[(-d - 1) as usize] -= *base;
+            }
+        }
+
+        let mut running = G::Group::zero();
+        let mut window = G::Group::zero();
+        for bucket in buckets.into_iter().rev() {
+            running += bucket;
+            window += running;
+        }
+
+        total += window;
+    }
+
+    Ok(Some(total))
+}
+
+/// Computes \\(\sum_i s_i \cdot P_i\\) and normalises the result to affine
+/// form.
+pub fn msm_affine<G: AffineRepr>(bases: &[G], scalars: &[G::ScalarField]) -> Result<G, usize> {
+    msm(bases, scalars).map(|p| p.into_affine())
+}
+
+/// An implementation of multiscalar multiplication that verifiers run their
+/// checks on.
+///
+/// A backend may hold state, e.g. the handle of a device, and need only be
+/// implemented for the groups that it supports.
+pub trait MsmBackend<G: AffineRepr> {
+    /// Computes \\(\sum_i s_i \cdot P_i\\) for `bases` \\(P_i\\) and `scalars`
+    /// \\(s_i\\).
+    ///
+    /// Like [`msm`], this must return the length of the shorter input as an
+    /// error if the inputs have different lengths.
+    fn msm(&self, bases: &[G], scalars: &[G::ScalarField]) -> Result<G::Group, usize>;
+}
+
+impl<G: AffineRepr, M: MsmBackend<G> + ?Sized> MsmBackend<G> for &M {
+    fn msm(&self, bases: &[G], scalars: &[G::ScalarField]) -> Result<G::Group, usize> {
+        (**self).msm(bases, scalars)
+    }
+}
+
+/// The backend that runs [`msm`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DefaultMsm;
+
+impl<G: AffineRepr> MsmBackend<G> for DefaultMsm {
+    fn msm(&self, bases: &[G], scalars: &[G::ScalarField]) -> Result<G::Group, usize> {
+        msm(bases, scalars)
+    }
+}
+
+/// The backend that runs `ark_ec::VariableBaseMSM`, which splits the
+/// multiplication across the threads of the rayon pool.
+///
+/// This pays off for the large multiplications of batch verification, but the
+/// threads are not worth starting for a handful of terms.
+#[cfg(feature = "parallel")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParallelMsm;
+
+#[cfg(feature = "parallel")]
+impl<G: AffineRepr> MsmBackend<G> for ParallelMsm {
+    fn msm(&self, bases: &[G], scalars: &[G::ScalarField]) -> Result<G::Group, usize> {
+        G::Group::msm(bases, scalars)
+    }
+}
+
+/// Accumulates the terms of several verification equations, so that they are
+/// checked together with a single multiscalar multiplication.
+///
+/// Terms on bases that belong to a single instance, e.g. the commitments of a
+/// proof, are pushed as they are. Terms on bases that all instances share,
+/// e.g. the generators, are summed into numbered slots, so that each shared
+/// base appears once in the final multiplication, and is only given to
+/// [`evaluate`](Self::evaluate).
+///
+/// [`clear`](Self::clear) empties the accumulator but keeps its buffers, so
+/// that verifying batch after batch with the same accumulator does not
+/// reallocate once the buffers have grown to the size of a batch.
+///
+/// [`evaluate`](Self::evaluate) runs the multiplication on the backend `M`.
+#[derive(Clone, Debug)]
+pub struct MsmAccumulator<G: AffineRepr, M = DefaultMsm> {
+    bases: Vec<G>,
+    scalars: Vec<G::ScalarField>,
+    shared: Vec<G::ScalarField>,
+    backend: M,
+}
+
+impl<G: AffineRepr, M: Default> Default for MsmAccumulator<G, M> {
+    fn default() -> Self {
+        Self::with_backend(M::default())
+    }
+}
+
+impl<G: AffineRepr> MsmAccumulator<G> {
+    /// Returns an empty accumulator on the [`DefaultMsm`] backend.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<G: AffineRepr, M> MsmAccumulator<G, M> {
+    /// Returns an empty accumulator that evaluates on `backend`.
+    pub fn with_backend(backend: M) -> Self {
+        Self {
+            bases: Vec::new(),
+            scalars: Vec::new(),
+            shared: Vec::new(),
+            backend,
+        }
+    }
+
+    /// Returns the backend of this accumulator.
+    pub fn backend(&self) -> &M {
+        &self.backend
+    }
+
+    /// Empties the accumulator, keeping its buffers for the next batch.
+    pub fn clear(&mut self) {
+        self.bases.clear();
+        self.scalars.clear();
+        self.shared.clear();
+    }
+
+    /// Returns the number of terms on bases of single instances.
+    pub fn len(&self) -> usize {
+        self.bases.len()
+    }
+
+    /// Returns true if no term has been added since the last
+    /// [`clear`](Self::clear).
+    pub fn is_empty(&self) -> bool {
+        self.bases.is_empty() && self.shared.is_empty()
+    }
+
+    /// Returns the number of shared slots, i.e. one more than the largest slot
+    /// that a term was added to.
+    pub fn shared_len(&self) -> usize {
+        self.shared.len()
+    }
+
+    /// Adds the term \(s \cdot P\) on the base `base` of a single instance.
+    pub fn push(&mut self, base: G, scalar: G::ScalarField) {
+        self.bases.push(base);
+        self.scalars.push(scalar);
+    }
+
+    /// Adds `scalar` to the scalar of the shared base in `slot`.
+    pub fn add_shared(&mut self, slot: usize, scalar: G::ScalarField) {
+        if slot >= self.shared.len() {
+            self.shared.resize(slot + 1, G::ScalarField::zero());
+        }
+        self.shared[slot] += scalar;
+    }
+
+    /// Adds every term of `other` to this accumulator, e.g. to combine the
+    /// accumulators that the instances of a batch were checked into
+    /// separately. The backend of `other` is not used.
+    pub fn append<N>(&mut self, other: &MsmAccumulator<G, N>) {
+        self.bases.extend_from_slice(&other.bases);
+        self.scalars.extend_from_slice(&other.scalars);
+        for (slot, scalar) in other.shared.iter().enumerate() {
+            self.add_shared(slot, *scalar);
+        }
+    }
+
+    /// Computes the sum of all the terms, where the `i`-th of `shared_bases` is
+    /// the base of slot `i`.
+    ///
+    /// If there are fewer shared bases than slots, the number of shared bases
+    /// is returned as an error. Extra shared bases are ignored.
+    pub fn evaluate<'a>(
+        &mut self,
+        shared_bases: impl IntoIterator<Item = &'a G>,
+    ) -> Result<G::Group, usize>
+    where
+        G: 'a,
+        M: MsmBackend<G>,
+    {
+        let instance_len = self.bases.len();
+        self.bases
+            .extend(shared_bases.into_iter().take(self.shared.len()));
+        let given = self.bases.len() - instance_len;
+        let result = if given < self.shared.len() {
+            Err(given)
+        } else {
+            self.scalars.extend_from_slice(&self.shared);
+            self.backend.msm(&self.bases, &self.scalars)
+        };
+
+        // The shared terms stay in their slots, so that more terms can still
+        // be added.
+        self.bases.truncate(instance_len);
+        self.scalars.truncate(instance_len);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ark_ec::VariableBaseMSM;
+    use ark_secq256k1::{Affine, Fr, Projective};
+    use ark_std::UniformRand;
+
+    #[test]
+    fn window_size_grows_with_input() {
+        let mut prev = 0;
+        for len in [1, 4, 16, 64, 256, 1024, 4096] {
+            let c = window_size(len, 256);
+            assert!(c >= prev);
+            prev = c;
+        }
+    }
+
+    #[test]
+    fn signed_digits_recompose() {
+        let mut rng = rand::thread_rng();
+        for c in MIN_WINDOW..=MAX_WINDOW {
+            let s = Fr::rand(&mut rng);
+            let num_windows = 256usize.div_ceil(c) + 1;
+            let digits = signed_digits(&s, c, num_windows);
+            let base = Fr::from(1u64 << c);
+            let recomposed = digits.iter().rev().fold(Fr::zero(), |acc, d| {
+                let d = if *d < 0 {
+                    -Fr::from((-d) as u64)
+                } else {
+                    Fr::from(*d as u64)
+                };
+                acc * base + d
+            });
+            assert_eq!(recomposed, s);
+        }
+    }
+
+    #[test]
+    fn matches_variable_base_msm() {
+        let mut rng = rand::thread_rng();
+        for n in [0, 1, 2, 3, 17, 64, 300] {
+            let bases: Vec<Affine> = (0..n).map(|_| Affine::rand(&mut rng)).collect();
+            let mut scalars: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+            if n > 2 {
+                scalars[1] = Fr::zero();
+                scalars[2] = -Fr::from(1u64);
+            }
+
+            let expected = <Affine as AffineRepr>::Group::msm(&bases, &scalars).unwrap();
+            assert_eq!(msm(&bases, &scalars).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn cancellable_stops_once_cancelled() {
+        let mut rng = rand::thread_rng();
+        let bases: Vec<Affine> = (0..64).map(|_| Affine::rand(&mut rng)).collect();
+        let scalars: Vec<Fr> = (0..64).map(|_| Fr::rand(&mut rng)).collect();
+
+        let cancel = CancellationToken::new();
+        assert_eq!(
+            msm_cancellable(&bases, &scalars, &cancel),
+            Ok(Some(msm(&bases, &scalars).unwrap()))
+        );
+
+        cancel.cancel();
+        assert_eq!(msm_cancellable(&bases, &scalars, &cancel), Ok(None));
+    }
+
+    #[test]
+    fn accumulator_matches_msm() {
+        let mut rng = rand::thread_rng();
+        let shared: Vec<Affine> = (0..4).map(|_| Affine::rand(&mut rng)).collect();
+        let mut acc = MsmAccumulator::new();
+
+        // The accumulator is reused, so the second batch must not see the
+        // terms of the first.
+        for _ in 0..2 {
+            acc.clear();
+            let mut bases = shared.clone();
+            let mut scalars = vec![Fr::zero(); shared.len()];
+            for _ in 0..3 {
+                let base = Affine::rand(&mut rng);
+                let scalar = Fr::rand(&mut rng);
+                acc.push(base, scalar);
+                bases.push(base);
+                scalars.push(scalar);
+
+                let slot = u64::rand(&mut rng) as usize % shared.len();
+                let scalar = Fr::rand(&mut rng);
+                acc.add_shared(slot, scalar);
+                scalars[slot] += scalar;
+            }
+
+            let expected = msm(&bases, &scalars).unwrap();
+            assert_eq!(acc.evaluate(&shared), Ok(expected));
+            // Evaluating does not consume the terms.
+            assert_eq!(acc.evaluate(&shared), Ok(expected));
+            assert_eq!(acc.len(), 3);
+        }
+
+        acc.add_shared(shared.len(), Fr::from(1u64));
+        assert_eq!(acc.evaluate(&shared), Err(shared.len()));
+        assert_eq!(acc.len(), 3);
+
+        acc.clear();
+        assert!(acc.is_empty());
+        assert_eq!(acc.evaluate(&shared), Ok(Zero::zero()));
+    }
+
+    #[test]
+    fn appended_accumulators_match_one_accumulator() {
+        let mut rng = rand::thread_rng();
+        let shared: Vec<Affine> = (0..4).map(|_| Affine::rand(&mut rng)).collect();
+        let mut whole = MsmAccumulator::new();
+        let mut parts = [MsmAccumulator::new(), MsmAccumulator::new()];
+
+        for i in 0..6 {
+            let base = Affine::rand(&mut rng);
+            let scalar = Fr::rand(&mut rng);
+            whole.push(base, scalar);
+            parts[i % 2].push(base, scalar);
+
+            // The parts need not have the same number of shared slots.
+            let slot = i % (2 + i % 2 * 2);
+            let scalar = Fr::rand(&mut rng);
+            whole.add_shared(slot, scalar);
+            parts[i % 2].add_shared(slot, scalar);
+        }
+
+        let mut acc = MsmAccumulator::new();
+        for part in &parts {
+            acc.append(part);
+        }
+        assert_eq!(acc.len(), whole.len());
+        assert_eq!(acc.shared_len(), whole.shared_len());
+        assert_eq!(acc.evaluate(&shared), whole.evaluate(&shared));
+    }
+
+    #[test]
+    fn backends_match_msm() {
+        let mut rng = rand::thread_rng();
+        let bases: Vec<Affine> = (0..64).map(|_| Affine::rand(&mut rng)).collect();
+        let scalars: Vec<Fr> = (0..64).map(|_| Fr::rand(&mut rng)).collect();
+
+        let expected = msm(&bases, &scalars);
+        assert_eq!(DefaultMsm.msm(&bases, &scalars), expected);
+        assert_eq!(DefaultMsm.msm(&bases[..2], &scalars[..3]), Err(2));
+        #[cfg(feature = "parallel")]
+        {
+            assert_eq!(ParallelMsm.msm(&bases, &scalars), expected);
+            assert_eq!(ParallelMsm.msm(&bases[..2], &scalars[..3]), Err(2));
+        }
+    }
+
+    #[test]
+    fn accumulator_evaluates_on_its_backend() {
+        struct CountingMsm(core::cell::Cell<usize>);
+
+        impl MsmBackend<Affine> for CountingMsm {
+            fn msm(&self, bases: &[Affine], scalars: &[Fr]) -> Result<Projective, usize> {
+                self.0.set(self.0.get() + 1);
+                msm(bases, scalars)
+            }
+        }
+
+        let mut rng = rand::thread_rng();
+        let shared: Vec<Affine> = (0..2).map(|_| Affine::rand(&mut rng)).collect();
+        let mut acc = MsmAccumulator::with_backend(CountingMsm(Default::default()));
+        let mut whole = MsmAccumulator::new();
+        for i in 0..3 {
+            let base = Affine::rand(&mut rng);
+            let scalar = Fr::rand(&mut rng);
+            acc.push(base, scalar);
+            whole.push(base, scalar);
+            acc.add_shared(i % 2, scalar);
+            whole.add_shared(i % 2, scalar);
+        }
+        assert_eq!(acc.evaluate(&shared), whole.evaluate(&shared));
+        assert_eq!(acc.backend().0.get(), 1);
+
+        // Accumulators on other backends can be appended.
+        let mut part = MsmAccumulator::new();
+        part.push(Affine::rand(&mut rng), Fr::rand(&mut rng));
+        acc.append(&part);
+        whole.append(&part);
+        assert_eq!(acc.evaluate(&shared), whole.evaluate(&shared));
+        assert_eq!(acc.backend().0.get(), 2);
+    }
+
+    #[test]
+    fn rejects_mismatched_lengths() {
+        let bases = vec![Affine::generator(); 3];
+        let scalars = vec![Fr::from(1u64); 2];
+        assert_eq!(msm(&bases, &scalars), Err(2));
+    }
+}
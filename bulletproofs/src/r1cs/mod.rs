@@ -12,7 +12,7 @@ pub use self::constraint_system::{
 pub use self::linear_combination::{LinearCombination, Variable};
 pub use self::proof::R1CSProof;
 pub use self::prover::Prover;
-pub use self::verifier::batch_verify;
 pub use self::verifier::Verifier;
+pub use self::verifier::{batch_verify, batch_verify_with_accumulator};
 
 pub use crate::errors::R1CSError;
@@ -801,8 +801,8 @@ impl<'g, G: AffineRepr, T: BorrowMut<Transcript>> Prover<'g, G, T> {
             &Q.into_affine(),
             &G_factors,
             &H_factors,
-            gens.G(padded_n).cloned().collect(),
-            gens.H(padded_n).cloned().collect(),
+            &gens.G(padded_n).cloned().collect::<Vec<_>>(),
+            &gens.H(padded_n).cloned().collect::<Vec<_>>(),
             l_vec,
             r_vec,
         );
@@ -1,6 +1,6 @@
 #![allow(non_snake_case)]
 
-use ark_ec::{AffineRepr, VariableBaseMSM};
+use ark_ec::AffineRepr;
 use ark_ff::{Field, UniformRand};
 use ark_std::{
     borrow::BorrowMut,
@@ -21,6 +21,7 @@ use super::{
 use crate::errors::R1CSError;
 use crate::generators::{BulletproofGens, PedersenGens};
 use crate::inner_product_proof::VerificationScalars;
+use crate::msm;
 use crate::transcript::TranscriptProtocol;
 
 type DeferredConstraint<G, T> =
@@ -580,7 +581,7 @@ impl<G: AffineRepr, T: BorrowMut<Transcript>> Verifier<G, T> {
 
         let padded_n = self.num_vars.next_power_of_two();
 
-        let mega_check = G::Group::msm(
+        let mega_check = msm::msm(
             &iter::once(&pc_gens.B)
                 .chain(iter::once(&pc_gens.B_blinding))
                 .chain(gens.G(padded_n))
@@ -619,79 +620,74 @@ pub fn batch_verify<'a, G: AffineRepr, I, R: CryptoRng + RngCore>(
 where
     I: IntoIterator<Item = (Verifier<G, &'a mut Transcript>, &'a R1CSProof<G>)>,
 {
+    batch_verify_with_accumulator(
+        prng,
+        instances,
+        pc_gens,
+        bp_gens,
+        &mut msm::MsmAccumulator::new(),
+    )
+}
+
+/// Batch verification of R1CS proofs, like [`batch_verify`], collecting the
+/// terms of the check in `acc`.
+///
+/// `acc` is cleared first, and its buffers are kept, so that a verifier that
+/// checks batch after batch can pass the same accumulator every time rather
+/// than allocating the check anew. The check runs on the backend of `acc`.
+pub fn batch_verify_with_accumulator<'a, G: AffineRepr, I, R: CryptoRng + RngCore, M>(
+    prng: &mut R,
+    instances: I,
+    pc_gens: &PedersenGens<G>,
+    bp_gens: &BulletproofGens<G>,
+    acc: &mut msm::MsmAccumulator<G, M>,
+) -> Result<(), R1CSError>
+where
+    I: IntoIterator<Item = (Verifier<G, &'a mut Transcript>, &'a R1CSProof<G>)>,
+    M: msm::MsmBackend<G>,
+{
+    acc.clear();
     let mut max_n_padded = 0;
-    let mut verifiers: Vec<Verifier<G, _>> = vec![];
-    let mut proofs: Vec<&R1CSProof<G>> = vec![];
-    let mut verification_scalars = vec![];
     for (verifier, proof) in instances.into_iter() {
         // verification_scalars method is mutable, need to run before obtaining verifier.num_vars
         let (verifier, scalars) = verifier.verification_scalars(proof, bp_gens)?;
-        let n = verifier.num_vars.next_power_of_two();
-        if n > max_n_padded {
-            max_n_padded = n;
-        }
-        verification_scalars.push(scalars);
-        verifiers.push(verifier);
-        proofs.push(proof);
-    }
-    let mut all_scalars = vec![];
-    let mut all_elems = vec![];
-
-    for _ in 0..(2 * max_n_padded + 2) {
-        all_scalars.push(G::ScalarField::zero());
-    }
-    all_elems.push(pc_gens.B);
-    all_elems.push(pc_gens.B_blinding);
-    let gens = bp_gens.share(0);
-    for G in gens.G(max_n_padded) {
-        all_elems.push(*G);
-    }
-    for H in gens.H(max_n_padded) {
-        all_elems.push(*H);
-    }
+        let padded_n = verifier.num_vars.next_power_of_two();
+        max_n_padded = max_n_padded.max(padded_n);
 
-    for ((verifier, proof), scalars) in verifiers
-        .into_iter()
-        .zip(proofs.iter())
-        .zip(verification_scalars.iter())
-    {
+        // B and B_blinding are in the shared slots 0 and 1, and the i-th G and H generators
+        // in the slots 2 + 2i and 3 + 2i.
         let alpha = G::ScalarField::rand(prng);
-        let scaled_scalars: Vec<G::ScalarField> = scalars.iter().map(|s| alpha * s).collect();
-        let padded_n = verifier.num_vars.next_power_of_two();
-        all_scalars[0] += scaled_scalars[0]; // B
-        all_scalars[1] += scaled_scalars[1]; // B_blinding
-                                             // g values
-        for (i, s) in scaled_scalars[2..2 + padded_n].iter().enumerate() {
-            all_scalars[i + 2] += s;
+        acc.add_shared(0, alpha * scalars[0]);
+        acc.add_shared(1, alpha * scalars[1]);
+        for (i, s) in scalars[2..2 + padded_n].iter().enumerate() {
+            acc.add_shared(2 + 2 * i, alpha * s);
         }
-        // h values
-        for (i, s) in scaled_scalars[2 + padded_n..2 + 2 * padded_n]
-            .iter()
-            .enumerate()
-        {
-            all_scalars[2 + max_n_padded + i] += *s;
+        for (i, s) in scalars[2 + padded_n..2 + 2 * padded_n].iter().enumerate() {
+            acc.add_shared(3 + 2 * i, alpha * s);
         }
 
-        for s in &scaled_scalars[2 + 2 * padded_n..] {
-            all_scalars.push(*s);
+        let instance_bases = [
+            proof.A_I1, proof.A_O1, proof.S1, proof.A_I2, proof.A_O2, proof.S2,
+        ]
+        .into_iter()
+        .chain(verifier.V.iter().copied())
+        .chain([proof.T_1, proof.T_3, proof.T_4, proof.T_5, proof.T_6])
+        .chain(proof.ipp_proof.L_vec.iter().copied())
+        .chain(proof.ipp_proof.R_vec.iter().copied());
+        for (base, s) in instance_bases.zip(&scalars[2 + 2 * padded_n..]) {
+            acc.push(base, alpha * s);
         }
-        all_elems.push(proof.A_I1);
-        all_elems.push(proof.A_O1);
-        all_elems.push(proof.S1);
-        all_elems.push(proof.A_I2);
-        all_elems.push(proof.A_O2);
-        all_elems.push(proof.S2);
-        all_elems.extend_from_slice(verifier.V.as_slice());
-        all_elems.push(proof.T_1);
-        all_elems.push(proof.T_3);
-        all_elems.push(proof.T_4);
-        all_elems.push(proof.T_5);
-        all_elems.push(proof.T_6);
-        all_elems.extend_from_slice(&proof.ipp_proof.L_vec);
-        all_elems.extend_from_slice(&proof.ipp_proof.R_vec);
     }
 
-    let multi_exp = G::Group::msm(&all_elems, &all_scalars).unwrap();
+    let gens = bp_gens.share(0);
+    let shared_bases = iter::once(&pc_gens.B)
+        .chain(iter::once(&pc_gens.B_blinding))
+        .chain(
+            gens.G(max_n_padded)
+                .zip(gens.H(max_n_padded))
+                .flat_map(|(G, H)| [G, H]),
+        );
+    let multi_exp = acc.evaluate(shared_bases).unwrap();
     if !multi_exp.is_zero() {
         Err(R1CSError::VerificationError)
     } else {
@@ -17,11 +17,11 @@ use merlin::Transcript;
 
 use crate::errors::MPCError;
 use crate::generators::{BulletproofGens, PedersenGens};
-use crate::inner_product_proof;
+use crate::inner_product_proof::{self, ProverKey};
 use crate::range_proof::RangeProof;
 use crate::transcript::TranscriptProtocol;
 use crate::util;
-use std::marker::PhantomData;
+use core::marker::PhantomData;
 
 #[cfg(feature = "std")]
 use ark_std::rand::thread_rng;
@@ -42,7 +42,38 @@ impl<G: AffineRepr> Dealer<G> {
         n: usize,
         m: usize,
     ) -> Result<DealerAwaitingBitCommitments<'a, 'b, G>, MPCError> {
-        if !(n == 8 || n == 16 || n == 32 || n == 64) {
+        Self::init_with(bp_gens, None, pc_gens, transcript, n, m)
+    }
+
+    /// Creates a new dealer like [`Dealer::init`], which makes the
+    /// inner-product proof against the generators of `key` rather than
+    /// collecting them from `bp_gens`.
+    ///
+    /// `key` must have been made from `bp_gens` for `n` bits and `m`
+    /// parties.
+    pub fn init_with_key<'a, 'b>(
+        bp_gens: &'b BulletproofGens<G>,
+        key: &'b ProverKey<G>,
+        pc_gens: &'b PedersenGens<G>,
+        transcript: &'a mut Transcript,
+        n: usize,
+        m: usize,
+    ) -> Result<DealerAwaitingBitCommitments<'a, 'b, G>, MPCError> {
+        if key.n() != n || key.m() != m {
+            return Err(MPCError::InvalidGeneratorsLength);
+        }
+        Self::init_with(bp_gens, Some(key), pc_gens, transcript, n, m)
+    }
+
+    fn init_with<'a, 'b>(
+        bp_gens: &'b BulletproofGens<G>,
+        prover_key: Option<&'b ProverKey<G>>,
+        pc_gens: &'b PedersenGens<G>,
+        transcript: &'a mut Transcript,
+        n: usize,
+        m: usize,
+    ) -> Result<DealerAwaitingBitCommitments<'a, 'b, G>, MPCError> {
+        if !(n == 8 || n == 16 || n == 32 || n == 64 || n == 128) {
             return Err(MPCError::InvalidBitsize);
         }
         if !m.is_power_of_two() {
@@ -75,6 +106,7 @@ impl<G: AffineRepr> Dealer<G> {
 
         Ok(DealerAwaitingBitCommitments {
             bp_gens,
+            prover_key,
             pc_gens,
             transcript,
             initial_transcript,
@@ -87,6 +119,7 @@ impl<G: AffineRepr> Dealer<G> {
 /// A dealer waiting for the parties to send their [`BitCommitment`]s.
 pub struct DealerAwaitingBitCommitments<'a, 'b, G: AffineRepr> {
     bp_gens: &'b BulletproofGens<G>,
+    prover_key: Option<&'b ProverKey<G>>,
     pc_gens: &'b PedersenGens<G>,
     transcript: &'a mut Transcript,
     /// The dealer keeps a copy of the initial transcript state, so
@@ -137,6 +170,7 @@ impl<'a, 'b, G: AffineRepr> DealerAwaitingBitCommitments<'a, 'b, G> {
                 transcript: self.transcript,
                 initial_transcript: self.initial_transcript,
                 bp_gens: self.bp_gens,
+                prover_key: self.prover_key,
                 pc_gens: self.pc_gens,
                 bit_challenge,
                 bit_commitments,
@@ -156,6 +190,7 @@ pub struct DealerAwaitingPolyCommitments<'a, 'b, G: AffineRepr> {
     transcript: &'a mut Transcript,
     initial_transcript: Transcript,
     bp_gens: &'b BulletproofGens<G>,
+    prover_key: Option<&'b ProverKey<G>>,
     pc_gens: &'b PedersenGens<G>,
     bit_challenge: BitChallenge<G>,
     bit_commitments: Vec<BitCommitment<G>>,
@@ -200,6 +235,7 @@ impl<'a, 'b, G: AffineRepr> DealerAwaitingPolyCommitments<'a, 'b, G> {
                 transcript: self.transcript,
                 initial_transcript: self.initial_transcript,
                 bp_gens: self.bp_gens,
+                prover_key: self.prover_key,
                 pc_gens: self.pc_gens,
                 bit_challenge: self.bit_challenge,
                 bit_commitments: self.bit_commitments,
@@ -225,6 +261,7 @@ pub struct DealerAwaitingProofShares<'a, 'b, G: AffineRepr> {
     transcript: &'a mut Transcript,
     initial_transcript: Transcript,
     bp_gens: &'b BulletproofGens<G>,
+    prover_key: Option<&'b ProverKey<G>>,
     pc_gens: &'b PedersenGens<G>,
     bit_challenge: BitChallenge<G>,
     bit_commitments: Vec<BitCommitment<G>>,
@@ -302,13 +339,22 @@ impl<'a, 'b, G: AffineRepr> DealerAwaitingProofShares<'a, 'b, G> {
             .flat_map(|ps| ps.r_vec.clone().into_iter())
             .collect();
 
+        let collected;
+        let key = match self.prover_key {
+            Some(key) => key,
+            None => {
+                collected = ProverKey::new(self.bp_gens, self.n, self.m)
+                    .map_err(|_| MPCError::InvalidGeneratorsLength)?;
+                &collected
+            }
+        };
         let ipp_proof = inner_product_proof::InnerProductProof::create(
             self.transcript,
             &Q.into_affine(),
             &G_factors,
             &H_factors,
-            self.bp_gens.G(self.n, self.m).cloned().collect(),
-            self.bp_gens.H(self.n, self.m).cloned().collect(),
+            key.G(),
+            key.H(),
             l_vec,
             r_vec,
         );
@@ -0,0 +1,285 @@
+//! The `heapless` module contains a verifier for range proofs that does
+//! not allocate, for embedded verifiers such as secure enclaves.
+//!
+//! [`RangeProof::verify_multiple_heapless`] checks the same equation as
+//! [`RangeProof::verify_multiple_with_rng`], but it keeps its working
+//! values in a scratch buffer of \\(n \cdot m\\) scalars that is given by
+//! the caller, and it computes the final check as a running sum of scalar
+//! multiplications, rather than with a Pippenger multiscalar
+//! multiplication, whose buckets are allocated. It is therefore slower,
+//! but its memory use is fixed before the proof is seen.
+//!
+//! A [`VerificationScratch`] is such a buffer, whose size is fixed at
+//! compile time by the largest bitsize and aggregation size that it
+//! accepts.
+//!
+//! N.B The proof itself still holds the inner-product argument in a `Vec`,
+//! so deserialising a proof needs `alloc`.
+
+use ark_ec::AffineRepr;
+use ark_ff::Field;
+use ark_std::{
+    ops::Neg,
+    rand::{CryptoRng, RngCore},
+    Zero,
+};
+use merlin::Transcript;
+
+use super::{delta, Challenges, RangeProof};
+use crate::errors::ProofError;
+use crate::generators::{BulletproofGens, PedersenGens};
+use crate::util;
+
+/// The largest number of rounds of an inner-product argument that the
+/// heapless verifier accepts, i.e. \\(\lg(n \cdot m) < 32\\).
+const MAX_LG_NM: usize = 32;
+
+impl<G: AffineRepr> RangeProof<G> {
+    /// Verifies an aggregated rangeproof for the given value commitments,
+    /// without allocating.
+    ///
+    /// The first \\(n \cdot m\\) entries of `scratch` are overwritten.
+    /// Returns [`ProofError::ScratchTooSmall`] if `scratch` is shorter.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_multiple_heapless<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens<G>,
+        pc_gens: &PedersenGens<G>,
+        transcript: &mut Transcript,
+        value_commitments: &[G],
+        n: usize,
+        rng: &mut T,
+        scratch: &mut [G::ScalarField],
+    ) -> Result<(), ProofError> {
+        let m = value_commitments.len();
+        let Challenges { y, z, x, w, c } =
+            self.replay_challenges(bp_gens, transcript, value_commitments, n, rng)?;
+        let zz = z * z;
+        let minus_z = z.neg();
+
+        let nm = n.checked_mul(m).ok_or(ProofError::InvalidAggregation)?;
+        let lg_nm = self.ipp_proof.L_vec.len();
+        let mut challenges_sq = [G::ScalarField::zero(); MAX_LG_NM];
+        let mut challenges_inv_sq = [G::ScalarField::zero(); MAX_LG_NM];
+        self.ipp_proof.verification_scalars_into(
+            nm,
+            transcript,
+            &mut challenges_sq,
+            &mut challenges_inv_sq,
+            scratch,
+        )?;
+        let s = &scratch[..nm];
+
+        let a = self.ipp_proof.a;
+        let b = self.ipp_proof.b;
+
+        // The terms are added in the same order as the bases of
+        // `verify_multiple_with`.
+        let mut check = self.A.into_group();
+        check += self.S * x;
+        check += self.T_1 * (c * x);
+        check += self.T_2 * (c * x * x);
+        for (L, u_sq) in self.ipp_proof.L_vec.iter().zip(&challenges_sq[..lg_nm]) {
+            check += *L * u_sq;
+        }
+        for (R, u_inv_sq) in self.ipp_proof.R_vec.iter().zip(&challenges_inv_sq[..lg_nm]) {
+            check += *R * u_inv_sq;
+        }
+        for (V, z_exp) in value_commitments.iter().zip(util::exp_iter::<G>(z)) {
+            check += *V * (c * zz * z_exp);
+        }
+        check += pc_gens.B_blinding * (self.e_blinding.neg() - c * self.t_x_blinding);
+        check += pc_gens.B * (w * (self.t_x - a * b) + c * (delta::<G>(n, m, &y, &z) - self.t_x));
+
+        for (G_i, s_i) in bp_gens.G(n, m).zip(s) {
+            check += *G_i * (minus_z - a * s_i);
+        }
+
+        // The scalar of the i-th H generator is z + y^-i (z^2 z^j 2^k - b s_{nm-1-i}), for
+        // i = j * n + k.
+        let concat_z_and_2 = util::exp_iter::<G>(z).take(m).flat_map(|exp_z| {
+            util::exp_iter::<G>(G::ScalarField::from(2u64))
+                .take(n)
+                .map(move |exp_2| exp_2 * exp_z)
+        });
+        let y_inv = y.inverse().ok_or(ProofError::VerificationError)?;
+        for (((H_i, s_i_inv), exp_y_inv), z_and_2) in bp_gens
+            .H(n, m)
+            .zip(s.iter().rev())
+            .zip(util::exp_iter::<G>(y_inv))
+            .zip(concat_z_and_2)
+        {
+            check += *H_i * (z + exp_y_inv * (zz * z_and_2 - b * s_i_inv));
+        }
+
+        if check.is_zero() {
+            Ok(())
+        } else {
+            Err(ProofError::VerificationError)
+        }
+    }
+}
+
+/// A scratch buffer for [`RangeProof::verify_multiple_heapless`], which
+/// accepts proofs of up to `MAX_N` bits for up to `MAX_M` values.
+///
+/// The buffer holds `MAX_N * MAX_M` scalars, so it can be kept on the
+/// stack, or in a `static`, for small bounds.
+pub struct VerificationScratch<G: AffineRepr, const MAX_N: usize, const MAX_M: usize> {
+    s: [[G::ScalarField; MAX_N]; MAX_M],
+}
+
+impl<G: AffineRepr, const MAX_N: usize, const MAX_M: usize> Default
+    for VerificationScratch<G, MAX_N, MAX_M>
+{
+    fn default() -> Self {
+        Self {
+            s: [[G::ScalarField::zero(); MAX_N]; MAX_M],
+        }
+    }
+}
+
+impl<G: AffineRepr, const MAX_N: usize, const MAX_M: usize> VerificationScratch<G, MAX_N, MAX_M> {
+    /// Returns a new scratch buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verifies an aggregated rangeproof for the given value commitments
+    /// with this buffer, without allocating.
+    ///
+    /// Returns [`ProofError::ScratchTooSmall`] if `n` is larger than
+    /// `MAX_N`, or if there are more than `MAX_M` value commitments.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify<T: RngCore + CryptoRng>(
+        &mut self,
+        proof: &RangeProof<G>,
+        bp_gens: &BulletproofGens<G>,
+        pc_gens: &PedersenGens<G>,
+        transcript: &mut Transcript,
+        value_commitments: &[G],
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(), ProofError> {
+        if n > MAX_N || value_commitments.len() > MAX_M {
+            return Err(ProofError::ScratchTooSmall);
+        }
+
+        proof.verify_multiple_heapless(
+            bp_gens,
+            pc_gens,
+            transcript,
+            value_commitments,
+            n,
+            rng,
+            self.s.as_flattened_mut(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ark_secq256k1::{Affine, Fr};
+    use ark_std::{vec::Vec, One, UniformRand};
+    use rand::rngs::OsRng;
+
+    fn prove(n: usize, m: usize) -> (RangeProof<Affine>, Vec<Affine>) {
+        let bp_gens = BulletproofGens::new(128, 8);
+        let pc_gens = PedersenGens::default();
+        let values: Vec<u64> = (0..m as u64).map(|i| (i + 1) * 10).collect();
+        let blindings: Vec<Fr> = (0..m).map(|_| Fr::rand(&mut OsRng)).collect();
+        RangeProof::prove_multiple_with_rng(
+            &bp_gens,
+            &pc_gens,
+            &mut Transcript::new(b"HeaplessTest"),
+            &values,
+            &blindings,
+            n,
+            &mut OsRng,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn heapless_matches_verify_multiple() {
+        let bp_gens = BulletproofGens::new(128, 8);
+        let pc_gens = PedersenGens::default();
+        let mut scratch = VerificationScratch::<Affine, 64, 4>::new();
+
+        for (n, m) in [(8, 1), (32, 2), (64, 4)] {
+            let (proof, commitments) = prove(n, m);
+            assert!(proof
+                .verify_multiple(
+                    &bp_gens,
+                    &pc_gens,
+                    &mut Transcript::new(b"HeaplessTest"),
+                    &commitments,
+                    n
+                )
+                .is_ok());
+            assert!(scratch
+                .verify(
+                    &proof,
+                    &bp_gens,
+                    &pc_gens,
+                    &mut Transcript::new(b"HeaplessTest"),
+                    &commitments,
+                    n,
+                    &mut OsRng,
+                )
+                .is_ok());
+
+            let mut wrong = commitments.clone();
+            wrong[0] = (wrong[0] + pc_gens.B).into();
+            assert_eq!(
+                scratch.verify(
+                    &proof,
+                    &bp_gens,
+                    &pc_gens,
+                    &mut Transcript::new(b"HeaplessTest"),
+                    &wrong,
+                    n,
+                    &mut OsRng,
+                ),
+                Err(ProofError::VerificationError)
+            );
+        }
+    }
+
+    #[test]
+    fn heapless_rejects_small_scratch() {
+        let bp_gens = BulletproofGens::new(128, 8);
+        let pc_gens = PedersenGens::default();
+        let (proof, commitments) = prove(64, 2);
+
+        let mut scratch = VerificationScratch::<Affine, 32, 2>::new();
+        assert_eq!(
+            scratch.verify(
+                &proof,
+                &bp_gens,
+                &pc_gens,
+                &mut Transcript::new(b"HeaplessTest"),
+                &commitments,
+                64,
+                &mut OsRng,
+            ),
+            Err(ProofError::ScratchTooSmall)
+        );
+
+        let mut buffer = [Fr::one(); 127];
+        assert_eq!(
+            proof.verify_multiple_heapless(
+                &bp_gens,
+                &pc_gens,
+                &mut Transcript::new(b"HeaplessTest"),
+                &commitments,
+                64,
+                &mut OsRng,
+                &mut buffer,
+            ),
+            Err(ProofError::ScratchTooSmall)
+        );
+    }
+}
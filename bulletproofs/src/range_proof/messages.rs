@@ -4,11 +4,12 @@
 //! For more explanation of how the `dealer`, `party`, and `messages` modules orchestrate the protocol execution, see
 //! [the API for the aggregated multiparty computation protocol](../aggregation/index.html#api-for-the-aggregated-multiparty-computation-protocol).
 
-use ark_ec::{AffineRepr, VariableBaseMSM};
+use ark_ec::AffineRepr;
 use ark_ff::Field;
 use ark_std::{iter, ops::Neg, vec::Vec, One, Zero};
 
 use crate::generators::{BulletproofGens, PedersenGens};
+use crate::msm;
 
 /// A commitment to the bits of a party's value.
 #[derive(Copy, Clone, Debug)]
@@ -122,7 +123,7 @@ impl<G: AffineRepr> ProofShare<G> {
                 *z + exp_y_inv * y_jn_inv * r_i.neg() + exp_y_inv * y_jn_inv * (zz * z_j * exp_2)
             });
 
-        let P_check = G::Group::msm(
+        let P_check = msm::msm(
             &iter::once(&bit_commitment.A_j)
                 .chain(iter::once(&bit_commitment.S_j))
                 .chain(iter::once(&pc_gens.B_blinding))
@@ -144,7 +145,7 @@ impl<G: AffineRepr> ProofShare<G> {
         let sum_of_powers_y = util::sum_of_powers::<G>(y, n);
         let sum_of_powers_2 = util::sum_of_powers::<G>(&G::ScalarField::from(2u64), n);
         let delta = (*z - zz) * sum_of_powers_y * y_jn - *z * zz * sum_of_powers_2 * z_j;
-        let t_check = G::Group::msm(
+        let t_check = msm::msm(
             &iter::once(&bit_commitment.V_j)
                 .chain(iter::once(&poly_commitment.T_1_j))
                 .chain(iter::once(&poly_commitment.T_2_j))
@@ -1,11 +1,11 @@
 #![allow(non_snake_case)]
 
-use ark_ec::{AffineRepr, VariableBaseMSM};
+use ark_ec::{AffineRepr, CurveGroup};
 use ark_ff::{Field, UniformRand};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::{
     iter,
-    ops::{AddAssign, Neg},
+    ops::Neg,
     rand::{CryptoRng, RngCore},
     vec,
     vec::Vec,
@@ -13,10 +13,18 @@ use ark_std::{
 };
 
 use merlin::Transcript;
+#[cfg(feature = "parallel")]
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
+use clear_on_drop::clear::Clear;
+
+use crate::cancel::CancellationToken;
 use crate::errors::ProofError;
 use crate::generators::{BulletproofGens, PedersenGens};
-use crate::inner_product_proof::{InnerProductProof, VerificationScalars};
+use crate::inner_product_proof::{InnerProductProof, ProverKey, VerificationScalars};
+use crate::msm;
 use crate::transcript::TranscriptProtocol;
 use crate::util;
 
@@ -26,6 +34,21 @@ pub mod dealer;
 pub mod messages;
 pub mod party;
 
+#[cfg(feature = "heapless")]
+mod heapless;
+#[cfg(feature = "heapless")]
+pub use self::heapless::VerificationScratch;
+
+/// The challenges of a range proof, as replayed by the verifier, together with the
+/// verifier's random weight `c` for the statements that are batched.
+struct Challenges<F> {
+    y: F,
+    z: F,
+    x: F,
+    w: F,
+    c: F,
+}
+
 /// The `RangeProof` struct represents a proof that one or more values
 /// are in a range.
 ///
@@ -38,17 +61,19 @@ pub mod party;
 /// the verifier.
 ///
 /// This implementation requires that both the bitsize `n` and the
-/// aggregation size `m` be powers of two, so that `n = 8, 16, 32, 64`
+/// aggregation size `m` be powers of two, so that `n = 8, 16, 32, 64, 128`
 /// and `m = 1, 2, 4, 8, 16, ...`.  Note that the aggregation size is
 /// not given as an explicit parameter, but is determined by the
 /// number of values or commitments passed to the prover or verifier.
 ///
 /// # Note
 ///
-/// For proving, these functions run the multiparty aggregation
-/// protocol locally.  That API is exposed in the [`aggregation`](::range_proof_mpc)
-/// module and can be used to perform online aggregation between
-/// parties without revealing secret values to each other.
+/// For proving several values, these functions run the multiparty
+/// aggregation protocol locally.  That API is exposed in the
+/// [`aggregation`](::range_proof_mpc) module and can be used to perform
+/// online aggregation between parties without revealing secret values to
+/// each other.  A single value is proved directly, without the dealer and
+/// party state machines, which yields the same proof.
 #[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct RangeProof<G: AffineRepr> {
     /// Commitment to the bits of the value
@@ -118,7 +143,32 @@ impl<G: AffineRepr> RangeProof<G> {
         )
     }
 
+    /// Create a rangeproof for a given pair of 128-bit value `v` and
+    /// blinding scalar `v_blinding`.
+    /// This is a convenience wrapper around [`RangeProof::prove_multiple_u128_with_rng`].
+    pub fn prove_single_u128_with_rng<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens<G>,
+        pc_gens: &PedersenGens<G>,
+        transcript: &mut Transcript,
+        v: u128,
+        v_blinding: &G::ScalarField,
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(RangeProof<G>, G), ProofError> {
+        let (p, Vs) = RangeProof::prove_multiple_u128_with_rng(
+            bp_gens,
+            pc_gens,
+            transcript,
+            &[v],
+            &[*v_blinding],
+            n,
+            rng,
+        )?;
+        Ok((p, Vs[0]))
+    }
+
     /// Create a rangeproof for a set of values.
+    /// This is a convenience wrapper around [`RangeProof::prove_multiple_u128_with_rng`].
     pub fn prove_multiple_with_rng<T: RngCore + CryptoRng>(
         bp_gens: &BulletproofGens<G>,
         pc_gens: &PedersenGens<G>,
@@ -128,14 +178,101 @@ impl<G: AffineRepr> RangeProof<G> {
         n: usize,
         rng: &mut T,
     ) -> Result<(RangeProof<G>, Vec<G>), ProofError> {
-        use self::dealer::*;
-        use self::party::*;
+        let values: Vec<u128> = values.iter().map(|&v| v.into()).collect();
+        RangeProof::prove_multiple_u128_with_rng(
+            bp_gens, pc_gens, transcript, &values, blindings, n, rng,
+        )
+    }
 
+    /// Create a rangeproof for a set of values, whose inner-product
+    /// argument is made against the generators of `key`, so that they are not
+    /// collected again for every proof. The bitsize is that of `key`.
+    ///
+    /// `key` must have been made from `bp_gens`, for as many parties as there
+    /// are values.
+    pub fn prove_multiple_with_key<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens<G>,
+        key: &ProverKey<G>,
+        pc_gens: &PedersenGens<G>,
+        transcript: &mut Transcript,
+        values: &[u64],
+        blindings: &[G::ScalarField],
+        rng: &mut T,
+    ) -> Result<(RangeProof<G>, Vec<G>), ProofError> {
+        let values: Vec<u128> = values.iter().map(|&v| v.into()).collect();
+        RangeProof::prove_multiple_u128_with(
+            bp_gens,
+            Some(key),
+            pc_gens,
+            transcript,
+            &values,
+            blindings,
+            key.n(),
+            rng,
+        )
+    }
+
+    /// Create a rangeproof for a set of 128-bit values.
+    /// Values of up to 128 bits are needed for `n = 128`.
+    pub fn prove_multiple_u128_with_rng<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens<G>,
+        pc_gens: &PedersenGens<G>,
+        transcript: &mut Transcript,
+        values: &[u128],
+        blindings: &[G::ScalarField],
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(RangeProof<G>, Vec<G>), ProofError> {
+        RangeProof::prove_multiple_u128_with(
+            bp_gens, None, pc_gens, transcript, values, blindings, n, rng,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn prove_multiple_u128_with<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens<G>,
+        key: Option<&ProverKey<G>>,
+        pc_gens: &PedersenGens<G>,
+        transcript: &mut Transcript,
+        values: &[u128],
+        blindings: &[G::ScalarField],
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(RangeProof<G>, Vec<G>), ProofError> {
         if values.len() != blindings.len() {
             return Err(ProofError::WrongNumBlindingFactors);
         }
 
-        let dealer = Dealer::init(bp_gens, pc_gens, transcript, n, values.len())?;
+        if let ([v], [v_blinding]) = (values, blindings) {
+            let (proof, V) = RangeProof::prove_single_party(
+                bp_gens, key, pc_gens, transcript, *v, v_blinding, n, rng,
+            )?;
+            return Ok((proof, vec![V]));
+        }
+
+        RangeProof::prove_with_parties(bp_gens, key, pc_gens, transcript, values, blindings, n, rng)
+    }
+
+    /// Create a rangeproof for a set of values by running the dealer and
+    /// the parties of the aggregation protocol locally.
+    #[allow(clippy::too_many_arguments)]
+    fn prove_with_parties<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens<G>,
+        key: Option<&ProverKey<G>>,
+        pc_gens: &PedersenGens<G>,
+        transcript: &mut Transcript,
+        values: &[u128],
+        blindings: &[G::ScalarField],
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(RangeProof<G>, Vec<G>), ProofError> {
+        use self::dealer::*;
+        use self::party::*;
+
+        let dealer = match key {
+            Some(key) => Dealer::init_with_key(bp_gens, key, pc_gens, transcript, n, values.len())?,
+            None => Dealer::init(bp_gens, pc_gens, transcript, n, values.len())?,
+        };
 
         let parties: Vec<_> = values
             .iter()
@@ -175,6 +312,176 @@ impl<G: AffineRepr> RangeProof<G> {
         Ok((proof, value_commitments))
     }
 
+    /// Create a rangeproof for a single value, as the aggregation protocol
+    /// does for `m = 1`, but without the dealer and party state machines.
+    ///
+    /// The proof, the transcript and the use of `rng` are the same as those
+    /// of the aggregation protocol, so that either prover can be used. As the
+    /// only party is the prover itself, the checks that the dealer makes on the
+    /// messages of the parties are skipped, and the shares are not collected
+    /// into intermediate vectors.
+    #[allow(clippy::too_many_arguments)]
+    fn prove_single_party<T: RngCore + CryptoRng>(
+        bp_gens: &BulletproofGens<G>,
+        key: Option<&ProverKey<G>>,
+        pc_gens: &PedersenGens<G>,
+        transcript: &mut Transcript,
+        v: u128,
+        v_blinding: &G::ScalarField,
+        n: usize,
+        rng: &mut T,
+    ) -> Result<(RangeProof<G>, G), ProofError> {
+        if key.is_some_and(|key| key.n() != n || key.m() != 1) {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+        if !(n == 8 || n == 16 || n == 32 || n == 64 || n == 128) {
+            return Err(ProofError::InvalidBitsize);
+        }
+        if bp_gens.gens_capacity < n || bp_gens.party_capacity < 1 {
+            return Err(ProofError::InvalidGeneratorsLength);
+        }
+
+        <Transcript as TranscriptProtocol<G>>::rangeproof_domain_sep(transcript, n as u64, 1);
+
+        let V = pc_gens.commit(v.into(), *v_blinding);
+        let bp_share = bp_gens.share(0);
+
+        // A = <a_L, G> + <a_R, H> + a_blinding * B_blinding, where each bit
+        // adds G[i] if it is set, and -H[i] otherwise.
+        let mut a_blinding = G::ScalarField::rand(rng);
+        let mut A = pc_gens.B_blinding * a_blinding;
+        for (i, (G_i, H_i)) in bp_share.G(n).zip(bp_share.H(n)).enumerate() {
+            if (v >> i) & 1 == 1 {
+                A += G_i;
+            } else {
+                A += H_i.into_group().neg();
+            }
+        }
+        let A = A.into_affine();
+
+        // S = <s_L, G> + <s_R, H> + s_blinding * B_blinding
+        let mut s_blinding = G::ScalarField::rand(rng);
+        let mut s_L: Vec<G::ScalarField> = (0..n).map(|_| G::ScalarField::rand(rng)).collect();
+        let mut s_R: Vec<G::ScalarField> = (0..n).map(|_| G::ScalarField::rand(rng)).collect();
+        let S = msm::msm_affine(
+            &iter::once(&pc_gens.B_blinding)
+                .chain(bp_share.G(n))
+                .chain(bp_share.H(n))
+                .cloned()
+                .collect::<Vec<G>>(),
+            &iter::once(&s_blinding)
+                .chain(s_L.iter())
+                .chain(s_R.iter())
+                .copied()
+                .collect::<Vec<G::ScalarField>>(),
+        )
+        .expect("The bases and scalars have the same length");
+
+        transcript.append_point(b"V", &V);
+        transcript.append_point(b"A", &A);
+        transcript.append_point(b"S", &S);
+        let y = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"y");
+        let z = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"z");
+
+        // l(x) = (a_L - z) + s_L * x
+        // r(x) = y^n o (a_R + z + s_R * x) + z^2 * 2^n
+        let zz = z * z;
+        let mut l_poly = util::VecPoly1::<G>::zero(n);
+        let mut r_poly = util::VecPoly1::<G>::zero(n);
+        let mut exp_y = G::ScalarField::one();
+        let mut exp_2 = G::ScalarField::one();
+        for i in 0..n {
+            let a_L_i = G::ScalarField::from((v >> i) & 1);
+            let a_R_i = a_L_i - G::ScalarField::one();
+
+            l_poly.0[i] = a_L_i - z;
+            l_poly.1[i] = s_L[i];
+            r_poly.0[i] = exp_y * (a_R_i + z) + zz * exp_2;
+            r_poly.1[i] = exp_y * s_R[i];
+
+            exp_y *= y;
+            exp_2 = exp_2 + exp_2;
+        }
+        let t_poly = l_poly.inner_product(&r_poly);
+
+        let mut t_1_blinding = G::ScalarField::rand(rng);
+        let mut t_2_blinding = G::ScalarField::rand(rng);
+        let T_1 = pc_gens.commit(t_poly.1, t_1_blinding);
+        let T_2 = pc_gens.commit(t_poly.2, t_2_blinding);
+
+        transcript.append_point(b"T_1", &T_1);
+        transcript.append_point(b"T_2", &T_2);
+        let x = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"x");
+
+        let t_x = t_poly.eval(x);
+        let t_x_blinding = util::Poly2::<G>(zz * v_blinding, t_1_blinding, t_2_blinding).eval(x);
+        let e_blinding = a_blinding + s_blinding * x;
+        let l_vec = l_poly.eval(x);
+        let r_vec = r_poly.eval(x);
+
+        <Transcript as TranscriptProtocol<G>>::append_scalar(transcript, b"t_x", &t_x);
+        <Transcript as TranscriptProtocol<G>>::append_scalar(
+            transcript,
+            b"t_x_blinding",
+            &t_x_blinding,
+        );
+        <Transcript as TranscriptProtocol<G>>::append_scalar(
+            transcript,
+            b"e_blinding",
+            &e_blinding,
+        );
+
+        // Get a challenge value to combine statements for the IPP
+        let w = <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"w");
+        let Q = (pc_gens.B * w).into_affine();
+
+        let G_factors: Vec<G::ScalarField> = iter::repeat(G::ScalarField::one()).take(n).collect();
+        let H_factors: Vec<G::ScalarField> =
+            util::exp_iter::<G>(y.inverse().unwrap()).take(n).collect();
+
+        let collected;
+        let key = match key {
+            Some(key) => key,
+            None => {
+                collected = ProverKey::new(bp_gens, n, 1)?;
+                &collected
+            }
+        };
+        let ipp_proof = InnerProductProof::create(
+            transcript,
+            &Q,
+            &G_factors,
+            &H_factors,
+            key.G(),
+            key.H(),
+            l_vec,
+            r_vec,
+        );
+
+        // Overwrite the secrets, as the parties do when they are dropped.
+        a_blinding.clear();
+        s_blinding.clear();
+        t_1_blinding.clear();
+        t_2_blinding.clear();
+        for e in s_L.iter_mut().chain(s_R.iter_mut()) {
+            e.clear();
+        }
+
+        Ok((
+            RangeProof {
+                A,
+                S,
+                T_1,
+                T_2,
+                t_x,
+                t_x_blinding,
+                e_blinding,
+                ipp_proof,
+            },
+            V,
+        ))
+    }
+
     /// Create a rangeproof for a set of values.
     /// This is a convenience wrapper around [`RangeProof::prove_multiple_with_rng`],
     /// passing in a threadsafe RNG.
@@ -245,6 +552,55 @@ impl<G: AffineRepr> RangeProof<G> {
         value_commitments: &[G],
         n: usize,
         rng: &mut T,
+    ) -> Result<(), ProofError> {
+        self.verify_multiple_with(
+            bp_gens,
+            pc_gens,
+            transcript,
+            value_commitments,
+            n,
+            rng,
+            None,
+        )
+    }
+
+    /// Verifies an aggregated rangeproof for the given value commitments,
+    /// checking `cancel` between the chunks of the verification.
+    ///
+    /// Returns [`ProofError::Cancelled`] if `cancel` is cancelled before the
+    /// verification finishes.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_multiple_cancellable<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens<G>,
+        pc_gens: &PedersenGens<G>,
+        transcript: &mut Transcript,
+        value_commitments: &[G],
+        n: usize,
+        rng: &mut T,
+        cancel: &CancellationToken,
+    ) -> Result<(), ProofError> {
+        self.verify_multiple_with(
+            bp_gens,
+            pc_gens,
+            transcript,
+            value_commitments,
+            n,
+            rng,
+            Some(cancel),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn verify_multiple_with<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens<G>,
+        pc_gens: &PedersenGens<G>,
+        transcript: &mut Transcript,
+        value_commitments: &[G],
+        n: usize,
+        rng: &mut T,
+        cancel: Option<&CancellationToken>,
     ) -> Result<(), ProofError> {
         let m = value_commitments.len();
 
@@ -256,23 +612,31 @@ impl<G: AffineRepr> RangeProof<G> {
             rng,
         )?;
 
-        let mega_check = G::Group::msm(
-            &iter::once(self.A)
-                .chain(iter::once(self.S))
-                .chain(iter::once(self.T_1))
-                .chain(iter::once(self.T_2))
-                .chain(self.ipp_proof.L_vec.iter().cloned())
-                .chain(self.ipp_proof.R_vec.iter().cloned())
-                .chain(value_commitments.iter().cloned())
-                .chain(iter::once(pc_gens.B_blinding))
-                .chain(iter::once(pc_gens.B))
-                .chain(bp_gens.G(n, m).copied())
-                .chain(bp_gens.H(n, m).copied())
-                .collect::<Vec<G>>(),
-            &scalars,
-        );
+        if cancel.is_some_and(CancellationToken::is_cancelled) {
+            return Err(ProofError::Cancelled);
+        }
+
+        let bases = iter::once(self.A)
+            .chain(iter::once(self.S))
+            .chain(iter::once(self.T_1))
+            .chain(iter::once(self.T_2))
+            .chain(self.ipp_proof.L_vec.iter().cloned())
+            .chain(self.ipp_proof.R_vec.iter().cloned())
+            .chain(value_commitments.iter().cloned())
+            .chain(iter::once(pc_gens.B_blinding))
+            .chain(iter::once(pc_gens.B))
+            .chain(bp_gens.G(n, m).copied())
+            .chain(bp_gens.H(n, m).copied())
+            .collect::<Vec<G>>();
+
+        let mega_check = match cancel {
+            Some(cancel) => msm::msm_cancellable(&bases, &scalars, cancel)
+                .unwrap()
+                .ok_or(ProofError::Cancelled)?,
+            None => msm::msm(&bases, &scalars).unwrap(),
+        };
 
-        if mega_check.unwrap().is_zero() {
+        if mega_check.is_zero() {
             Ok(())
         } else {
             Err(ProofError::VerificationError)
@@ -289,9 +653,75 @@ impl<G: AffineRepr> RangeProof<G> {
     ) -> Result<Vec<G::ScalarField>, ProofError> {
         let m = value_commitments.len();
 
-        // First, replay the "interactive" protocol using the proof
-        // data to recompute all challenges.
-        if !(n == 8 || n == 16 || n == 32 || n == 64) {
+        let Challenges { y, z, x, w, c } =
+            self.replay_challenges(bp_gens, transcript, value_commitments, n, rng)?;
+        let zz = z * z;
+        let minus_z = z.neg();
+
+        let VerificationScalars {
+            mut challenges_sq,
+            mut challenges_inv_sq,
+            s,
+        } = self.ipp_proof.verification_scalars(n * m, transcript)?;
+        let s_inv = s.iter().rev();
+
+        let a: G::ScalarField = self.ipp_proof.a;
+        let b: G::ScalarField = self.ipp_proof.b;
+
+        // Construct concat_z_and_2, an iterator of the values of
+        // z^0 * \vec(2)^n || z^1 * \vec(2)^n || ... || z^(m-1) * \vec(2)^n
+        let powers_of_2: Vec<G::ScalarField> = util::exp_iter::<G>(G::ScalarField::from(2u64))
+            .take(n)
+            .collect();
+        let concat_z_and_2: Vec<G::ScalarField> = util::exp_iter::<G>(z)
+            .take(m)
+            .flat_map(|exp_z| powers_of_2.iter().map(move |exp_2| *exp_2 * exp_z))
+            .collect();
+
+        let mut g: Vec<G::ScalarField> = s.iter().map(|s_i| minus_z - a * s_i).collect();
+        let mut h: Vec<G::ScalarField> = s_inv
+            .zip(util::exp_iter::<G>(y.inverse().unwrap()))
+            .zip(concat_z_and_2.iter())
+            .map(|((s_i_inv, exp_y_inv), z_and_2)| z + exp_y_inv * (zz * z_and_2 - b * s_i_inv))
+            .collect();
+
+        let mut value_commitment_scalars: Vec<G::ScalarField> = util::exp_iter::<G>(z)
+            .take(m)
+            .map(|z_exp| c * zz * z_exp)
+            .collect();
+
+        let tmp: G::ScalarField = delta::<G>(n, m, &y, &z);
+        let basepoint_scalar: G::ScalarField = w * (self.t_x - a * b) + c * (tmp - self.t_x);
+
+        let mut scalars = vec![
+            G::ScalarField::one(), // A
+            x,                     // S
+            c * x,                 // T_1
+            c * x * x,
+        ]; //T_2
+        scalars.append(&mut challenges_sq); // L_vec TODO avoid append, better chaining iterators
+        scalars.append(&mut challenges_inv_sq); // R_vec
+        scalars.append(&mut value_commitment_scalars); //Value com
+        scalars.push(self.e_blinding.neg() - c * self.t_x_blinding); // B_blinding
+        scalars.push(basepoint_scalar); // B
+        scalars.append(&mut g); // G_vec
+        scalars.append(&mut h); // H_vec
+        Ok(scalars)
+    }
+
+    /// Replays the "interactive" protocol using the proof data to
+    /// recompute all challenges, up to the inner-product argument.
+    fn replay_challenges<T: RngCore + CryptoRng>(
+        &self,
+        bp_gens: &BulletproofGens<G>,
+        transcript: &mut Transcript,
+        value_commitments: &[G],
+        n: usize,
+        rng: &mut T,
+    ) -> Result<Challenges<G::ScalarField>, ProofError> {
+        let m = value_commitments.len();
+
+        if !(n == 8 || n == 16 || n == 32 || n == 64 || n == 128) {
             return Err(ProofError::InvalidBitsize);
         }
         if bp_gens.gens_capacity < n {
@@ -318,8 +748,6 @@ impl<G: AffineRepr> RangeProof<G> {
             <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"y");
         let z: G::ScalarField =
             <Transcript as TranscriptProtocol<G>>::challenge_scalar(transcript, b"z");
-        let zz = z * z;
-        let minus_z = z.neg();
 
         transcript.validate_and_append_point(b"T_1", &self.T_1)?;
         transcript.validate_and_append_point(b"T_2", &self.T_2)?;
@@ -344,58 +772,99 @@ impl<G: AffineRepr> RangeProof<G> {
         // Challenge value for batching statements to be verified
         let c = G::ScalarField::rand(rng);
 
+        Ok(Challenges { y, z, x, w, c })
+    }
+
+    /// Adds the terms of the verification equation of this proof to `acc`,
+    /// weighted by a random scalar so that the proofs of a batch are checked
+    /// independently.
+    ///
+    /// The bases of the proof and of `value_commitments` are pushed as they
+    /// are. `B_blinding` and `B` are in the shared slots 0 and 1, and the
+    /// `i`-th `G` and `H` generators in the slots `2 + 2i` and `3 + 2i`.
+    ///
+    /// This is the building block of [`RangeProof::batch_verify`]. It can also
+    /// be used to check the proof together with other proofs that share the
+    /// generators, e.g. a [`LinearProof`](crate::LinearProof) on the same
+    /// transcript.
+    pub fn accumulate_verification_scalars<T: RngCore + CryptoRng, M>(
+        &self,
+        bp_gens: &BulletproofGens<G>,
+        transcript: &mut Transcript,
+        value_commitments: &[G],
+        n: usize,
+        rng: &mut T,
+        acc: &mut msm::MsmAccumulator<G, M>,
+    ) -> Result<(), ProofError> {
+        let m = value_commitments.len();
+
+        let Challenges { y, z, x, w, c } =
+            self.replay_challenges(bp_gens, transcript, value_commitments, n, rng)?;
+        let zz = z * z;
+        let minus_z = z.neg();
+
         let VerificationScalars {
-            mut challenges_sq,
-            mut challenges_inv_sq,
+            challenges_sq,
+            challenges_inv_sq,
             s,
         } = self.ipp_proof.verification_scalars(n * m, transcript)?;
-        let s_inv = s.iter().rev();
+
+        let mut instance_rng = transcript.build_rng().finalize(rng);
+        let weight = G::ScalarField::rand(&mut instance_rng);
 
         let a: G::ScalarField = self.ipp_proof.a;
         let b: G::ScalarField = self.ipp_proof.b;
 
-        // Construct concat_z_and_2, an iterator of the values of
-        // z^0 * \vec(2)^n || z^1 * \vec(2)^n || ... || z^(m-1) * \vec(2)^n
-        let powers_of_2: Vec<G::ScalarField> = util::exp_iter::<G>(G::ScalarField::from(2u64))
-            .take(n)
-            .collect();
-        let concat_z_and_2: Vec<G::ScalarField> = util::exp_iter::<G>(z)
-            .take(m)
-            .flat_map(|exp_z| powers_of_2.iter().map(move |exp_2| *exp_2 * exp_z))
-            .collect();
-
-        let mut g: Vec<G::ScalarField> = s.iter().map(|s_i| minus_z - a * s_i).collect();
-        let mut h: Vec<G::ScalarField> = s_inv
-            .zip(util::exp_iter::<G>(y.inverse().unwrap()))
-            .zip(concat_z_and_2.iter())
-            .map(|((s_i_inv, exp_y_inv), z_and_2)| z + exp_y_inv * (zz * z_and_2 - b * s_i_inv))
-            .collect();
-
-        let mut value_commitment_scalars: Vec<G::ScalarField> = util::exp_iter::<G>(z)
-            .take(m)
-            .map(|z_exp| c * zz * z_exp)
-            .collect();
+        acc.push(self.A, weight);
+        acc.push(self.S, x * weight);
+        acc.push(self.T_1, c * x * weight);
+        acc.push(self.T_2, c * x * x * weight);
+        for (L, u_sq) in self.ipp_proof.L_vec.iter().zip(&challenges_sq) {
+            acc.push(*L, *u_sq * weight);
+        }
+        for (R, u_inv_sq) in self.ipp_proof.R_vec.iter().zip(&challenges_inv_sq) {
+            acc.push(*R, *u_inv_sq * weight);
+        }
+        for (V, z_exp) in value_commitments.iter().zip(util::exp_iter::<G>(z)) {
+            acc.push(*V, c * zz * z_exp * weight);
+        }
 
-        let tmp: G::ScalarField = delta::<G>(n, m, &y, &z);
-        let basepoint_scalar: G::ScalarField = w * (self.t_x - a * b) + c * (tmp - self.t_x);
+        acc.add_shared(0, (self.e_blinding.neg() - c * self.t_x_blinding) * weight);
+        acc.add_shared(
+            1,
+            (w * (self.t_x - a * b) + c * (delta::<G>(n, m, &y, &z) - self.t_x)) * weight,
+        );
+        for (i, s_i) in s.iter().enumerate() {
+            acc.add_shared(2 + 2 * i, (minus_z - a * s_i) * weight);
+        }
 
-        let mut scalars = vec![
-            G::ScalarField::one(), // A
-            x,                     // S
-            c * x,                 // T_1
-            c * x * x,
-        ]; //T_2
-        scalars.append(&mut challenges_sq); // L_vec TODO avoid append, better chaining iterators
-        scalars.append(&mut challenges_inv_sq); // R_vec
-        scalars.append(&mut value_commitment_scalars); //Value com
-        scalars.push(self.e_blinding.neg() - c * self.t_x_blinding); // B_blinding
-        scalars.push(basepoint_scalar); // B
-        scalars.append(&mut g); // G_vec
-        scalars.append(&mut h); // H_vec
-        Ok(scalars)
+        // The scalar of the i-th H generator is z + y^-i (z^2 z^j 2^k - b s_{nm-1-i}), for
+        // i = j * n + k.
+        let concat_z_and_2 = util::exp_iter::<G>(z).take(m).flat_map(|exp_z| {
+            util::exp_iter::<G>(G::ScalarField::from(2u64))
+                .take(n)
+                .map(move |exp_2| exp_2 * exp_z)
+        });
+        let y_inv = y.inverse().ok_or(ProofError::VerificationError)?;
+        for (i, ((s_i_inv, exp_y_inv), z_and_2)) in s
+            .iter()
+            .rev()
+            .zip(util::exp_iter::<G>(y_inv))
+            .zip(concat_z_and_2)
+            .enumerate()
+        {
+            acc.add_shared(
+                3 + 2 * i,
+                (z + exp_y_inv * (zz * z_and_2 - b * s_i_inv)) * weight,
+            );
+        }
+        Ok(())
     }
 
     /// Verifies multiple aggregated rangeproofs with a single multiexponentiation
+    ///
+    /// With the `parallel` feature, the verification scalars of the proofs are
+    /// computed concurrently, before the final multiexponentiation.
     pub fn batch_verify<T: RngCore + CryptoRng>(
         rng: &mut T,
         proofs: &[&RangeProof<G>],
@@ -405,103 +874,113 @@ impl<G: AffineRepr> RangeProof<G> {
         pc_gens: &PedersenGens<G>,
         n: usize,
     ) -> Result<(), ProofError> {
-        let mut all_scalars = vec![];
-        let mut random_scalars = vec![];
-        let mut max_m = 0;
+        Self::batch_verify_with_accumulator(
+            rng,
+            proofs,
+            transcripts,
+            value_commitments,
+            bp_gens,
+            pc_gens,
+            n,
+            &mut msm::MsmAccumulator::new(),
+        )
+    }
+
+    /// Verifies multiple aggregated rangeproofs like [`RangeProof::batch_verify`],
+    /// collecting the terms of the check in `acc`.
+    ///
+    /// `acc` is cleared first, and its buffers are kept, so that a verifier
+    /// that checks batch after batch can pass the same accumulator every time
+    /// rather than allocating the check anew. The check runs on the backend of
+    /// `acc`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn batch_verify_with_accumulator<T: RngCore + CryptoRng, M: msm::MsmBackend<G>>(
+        rng: &mut T,
+        proofs: &[&RangeProof<G>],
+        transcripts: &mut [Transcript],
+        value_commitments: &[&[G]],
+        bp_gens: &BulletproofGens<G>,
+        pc_gens: &PedersenGens<G>,
+        n: usize,
+        acc: &mut msm::MsmAccumulator<G, M>,
+    ) -> Result<(), ProofError> {
+        acc.clear();
+        let max_m = value_commitments
+            .iter()
+            .take(proofs.len().min(transcripts.len()))
+            .map(|value_commitment| value_commitment.len())
+            .max()
+            .unwrap_or(0);
+
+        #[cfg(feature = "parallel")]
+        {
+            // The instances are independent, so their scalars are computed
+            // concurrently, each into an accumulator of its own. The seeds are
+            // drawn sequentially so that the batch only depends on `rng`.
+            let seeds: Vec<[u8; 32]> = proofs
+                .iter()
+                .map(|_| {
+                    let mut seed = [0u8; 32];
+                    rng.fill_bytes(&mut seed);
+                    seed
+                })
+                .collect();
+
+            let parts: Vec<Result<msm::MsmAccumulator<G>, ProofError>> = proofs
+                .par_iter()
+                .zip(transcripts.par_iter_mut())
+                .zip(value_commitments.par_iter())
+                .zip(seeds.into_par_iter())
+                .map(|(((proof, transcript), value_commitment), seed)| {
+                    let mut instance_rng = ChaCha20Rng::from_seed(seed);
+                    let mut part = msm::MsmAccumulator::new();
+                    proof.accumulate_verification_scalars(
+                        bp_gens,
+                        transcript,
+                        value_commitment,
+                        n,
+                        &mut instance_rng,
+                        &mut part,
+                    )?;
+                    Ok(part)
+                })
+                .collect();
+            for part in parts {
+                acc.append(&part?);
+            }
+        }
+
+        #[cfg(not(feature = "parallel"))]
         for ((proof, transcript), value_commitment) in proofs
             .iter()
             .zip(transcripts.iter_mut())
             .zip(value_commitments.iter())
         {
-            let instance_scalars = proof.compute_verification_scalars_with_rng(
+            proof.accumulate_verification_scalars(
                 bp_gens,
                 transcript,
                 value_commitment,
                 n,
                 rng,
+                acc,
             )?;
-            let mut rng = transcript
-                .build_rng()
-                .finalize(&mut ark_std::rand::thread_rng());
-            random_scalars.push(G::ScalarField::rand(&mut rng));
-            all_scalars.push((instance_scalars, value_commitment.len()));
-        }
-        let mut all_scaled_scalars = vec![];
-        for ((scalars, m_i), rand_scalar) in all_scalars.iter().zip(random_scalars.iter()) {
-            let scaled_scalars: Vec<G::ScalarField> =
-                scalars.iter().map(|s| *s * rand_scalar).collect();
-            all_scaled_scalars.push((scaled_scalars, *m_i));
-            if *m_i > max_m {
-                max_m = *m_i;
-            }
         }
-        let grouped_scalars = Self::group_scalars(all_scaled_scalars.as_slice(), n, max_m).to_vec();
-
-        let mut elems = vec![];
-        for (proof, value_commitments) in proofs.iter().zip(value_commitments) {
-            elems.push(proof.A);
-            elems.push(proof.S);
-            elems.push(proof.T_1);
-            elems.push(proof.T_2);
-            for L in proof.ipp_proof.L_vec.iter() {
-                elems.push(*L);
-            }
-            for R in proof.ipp_proof.R_vec.iter() {
-                elems.push(*R);
-            }
-            for V in value_commitments.iter() {
-                elems.push(*V)
-            }
-        }
-        elems.push(pc_gens.B_blinding);
-        elems.push(pc_gens.B);
-        for G in bp_gens.G(n, max_m) {
-            elems.push(*G);
-        }
-        for H in bp_gens.H(n, max_m) {
-            elems.push(*H);
-        }
-        let mega_check = G::Group::msm(&elems, &grouped_scalars);
+
+        let shared_bases = iter::once(&pc_gens.B_blinding)
+            .chain(iter::once(&pc_gens.B))
+            .chain(
+                bp_gens
+                    .G(n, max_m)
+                    .zip(bp_gens.H(n, max_m))
+                    .flat_map(|(G, H)| [G, H]),
+            );
+        let mega_check = acc.evaluate(shared_bases);
         if !mega_check.unwrap().is_zero() {
             return Err(ProofError::VerificationError);
         }
         Ok(())
     }
 
-    fn group_scalars(
-        all_scalars: &[(Vec<G::ScalarField>, usize)],
-        n: usize,
-        max_m: usize,
-    ) -> Vec<G::ScalarField> {
-        let mut agg_scalars = vec![];
-        let mut b_blind_scalars = G::ScalarField::from(0u8);
-        let mut b_scalars = G::ScalarField::from(0u8);
-        let mut g_scalars = vec![G::ScalarField::from(0u8); n * max_m];
-        let mut h_scalars = vec![G::ScalarField::from(0u8); n * max_m];
-
-        for (instance_scalars, m_i) in all_scalars {
-            let N_i = *m_i * n; // size of this instance
-            let lgN = (N_i as u64).trailing_zeros() as usize; // size of L and R vecs
-                                                              // A,S,T1,T2 (4 elements) and L_vec, R_vec (lgN elemens each) + V (m_i elements)
-            let k_i = 4usize + 2usize * lgN + *m_i; // number of elements unique to this instance
-            for j in 0..k_i {
-                agg_scalars.push(*instance_scalars.get(j).unwrap());
-            }
-            b_blind_scalars.add_assign(&instance_scalars[k_i]);
-            b_scalars.add_assign(&instance_scalars[k_i + 1]);
-            for i in k_i + 2..k_i + 2 + N_i {
-                g_scalars[i - k_i - 2] += &instance_scalars[i];
-                h_scalars[i - k_i - 2] += &instance_scalars[N_i + i];
-            }
-        }
-        agg_scalars.push(b_blind_scalars);
-        agg_scalars.push(b_scalars);
-        agg_scalars.append(&mut g_scalars);
-        agg_scalars.append(&mut h_scalars);
-
-        agg_scalars
-    }
-
     /// Verifies an aggregated rangeproof for the given value commitments.
     /// This is a convenience wrapper around [`RangeProof::verify_multiple_with_rng`],
     /// passing in a threadsafe RNG.
@@ -600,19 +1079,24 @@ mod tests {
             let mut rng = rand::thread_rng();
 
             // 0. Create witness data
-            let (min, max) = (0u64, ((1u128 << n) - 1) as u64);
-            let values: Vec<u64> = (0..m).map(|_| rng.gen_range(min..max)).collect();
+            let max = if n == 128 {
+                u128::MAX
+            } else {
+                (1u128 << n) - 1
+            };
+            let values: Vec<u128> = (0..m).map(|_| rng.gen_range(0..max)).collect();
             let blindings: Vec<Fr> = (0..m).map(|_| Fr::rand(&mut rng)).collect();
 
             // 1. Create the proof
             let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
-            let (proof, value_commitments) = RangeProof::prove_multiple(
+            let (proof, value_commitments) = RangeProof::prove_multiple_u128_with_rng(
                 &bp_gens,
                 &pc_gens,
                 &mut transcript,
                 &values,
                 &blindings,
                 n,
+                &mut rng,
             )
             .unwrap();
 
@@ -682,6 +1166,55 @@ mod tests {
         }
     }
 
+    #[test]
+    fn verify_cancellable() {
+        let pc_gens: PedersenGens<Affine> = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(64, 2);
+        let mut rng = rand::thread_rng();
+
+        let blindings: Vec<Fr> = (0..2).map(|_| Fr::rand(&mut rng)).collect();
+        let mut transcript = Transcript::new(b"CancellableRangeProofTest");
+        let (proof, value_commitments) = RangeProof::prove_multiple_with_rng(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &[3, 5],
+            &blindings,
+            64,
+            &mut rng,
+        )
+        .unwrap();
+
+        let cancel = CancellationToken::new();
+        let mut transcript = Transcript::new(b"CancellableRangeProofTest");
+        assert!(proof
+            .verify_multiple_cancellable(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &value_commitments,
+                64,
+                &mut rng,
+                &cancel,
+            )
+            .is_ok());
+
+        cancel.cancel();
+        let mut transcript = Transcript::new(b"CancellableRangeProofTest");
+        assert_eq!(
+            proof.verify_multiple_cancellable(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                &value_commitments,
+                64,
+                &mut rng,
+                &cancel,
+            ),
+            Err(ProofError::Cancelled)
+        );
+    }
+
     #[test]
     fn create_and_verify_n_32_m_1() {
         singleparty_create_and_verify_helper(32, 1);
@@ -722,6 +1255,317 @@ mod tests {
         singleparty_create_and_verify_helper(64, 8);
     }
 
+    #[test]
+    fn create_and_verify_n_128_m_1() {
+        singleparty_create_and_verify_helper(128, 1);
+    }
+
+    #[test]
+    fn create_and_verify_n_128_m_2() {
+        singleparty_create_and_verify_helper(128, 2);
+    }
+
+    #[test]
+    fn create_and_verify_u128() {
+        let pc_gens: PedersenGens<Affine> = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(128, 2);
+        let mut rng = rand::thread_rng();
+
+        // Both values need more than 64 bits.
+        let values = [u128::MAX, (1u128 << 64) + rng.gen::<u64>() as u128];
+        let blindings: Vec<Fr> = (0..2).map(|_| Fr::rand(&mut rng)).collect();
+
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        let (proof, value_commitments) = RangeProof::prove_multiple_u128_with_rng(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &values,
+            &blindings,
+            128,
+            &mut rng,
+        )
+        .unwrap();
+
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        assert!(proof
+            .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, 128)
+            .is_ok());
+
+        // The same values are out of range for n = 64.
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        let (proof, value_commitments) = RangeProof::prove_multiple_u128_with_rng(
+            &bp_gens,
+            &pc_gens,
+            &mut transcript,
+            &values,
+            &blindings,
+            64,
+            &mut rng,
+        )
+        .unwrap();
+
+        let mut transcript = Transcript::new(b"AggregatedRangeProofTest");
+        assert!(proof
+            .verify_multiple(&bp_gens, &pc_gens, &mut transcript, &value_commitments, 64)
+            .is_err());
+    }
+
+    #[test]
+    fn prove_and_batch_verify_with_seeded_rng() {
+        use ark_std::rand::SeedableRng;
+        use rand_chacha::ChaChaRng;
+
+        let pc_gens: PedersenGens<Affine> = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(64, 1);
+
+        // With an injected RNG, proving is deterministic.
+        let prove = |v: u64| {
+            let mut rng = ChaChaRng::from_seed([v as u8; 32]);
+            let blinding = Fr::rand(&mut rng);
+            let mut transcript = Transcript::new(b"BatchRangeProofTest");
+            RangeProof::prove_single_with_rng(
+                &bp_gens,
+                &pc_gens,
+                &mut transcript,
+                v,
+                &blinding,
+                64,
+                &mut rng,
+            )
+            .unwrap()
+        };
+
+        let to_bytes = |proof: &RangeProof<Affine>| {
+            let mut bytes = Vec::new();
+            proof.serialize_compressed(&mut bytes).unwrap();
+            bytes
+        };
+
+        let (proof_a, comm_a) = prove(7);
+        let (proof_b, comm_b) = prove(9);
+        assert_eq!(to_bytes(&proof_a), to_bytes(&prove(7).0));
+        assert_ne!(to_bytes(&proof_a), to_bytes(&proof_b));
+
+        let mut transcripts = [
+            Transcript::new(b"BatchRangeProofTest"),
+            Transcript::new(b"BatchRangeProofTest"),
+        ];
+        assert!(RangeProof::batch_verify(
+            &mut ChaChaRng::from_seed([0u8; 32]),
+            &[&proof_a, &proof_b],
+            &mut transcripts,
+            &[&[comm_a], &[comm_b]],
+            &bp_gens,
+            &pc_gens,
+            64,
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn prove_with_reused_key() {
+        let pc_gens: PedersenGens<Affine> = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 2);
+        let key = ProverKey::new(&bp_gens, 32, 2).unwrap();
+        let mut rng = rand::thread_rng();
+
+        for values in [[1, 2], [3, u32::MAX as u64]] {
+            let blindings = [Fr::rand(&mut rng), Fr::rand(&mut rng)];
+            let (proof, commitments) = RangeProof::prove_multiple_with_key(
+                &bp_gens,
+                &key,
+                &pc_gens,
+                &mut Transcript::new(b"ProverKeyTest"),
+                &values,
+                &blindings,
+                &mut rng,
+            )
+            .unwrap();
+            assert!(proof
+                .verify_multiple(
+                    &bp_gens,
+                    &pc_gens,
+                    &mut Transcript::new(b"ProverKeyTest"),
+                    &commitments,
+                    32
+                )
+                .is_ok());
+        }
+
+        // The key only fits proofs for as many values as it has parties.
+        assert_eq!(
+            RangeProof::prove_multiple_with_key(
+                &bp_gens,
+                &key,
+                &pc_gens,
+                &mut Transcript::new(b"ProverKeyTest"),
+                &[1],
+                &[Fr::rand(&mut rng)],
+                &mut rng,
+            )
+            .unwrap_err(),
+            ProofError::InvalidGeneratorsLength
+        );
+        assert_eq!(
+            ProverKey::new(&bp_gens, 64, 1).unwrap_err(),
+            ProofError::InvalidGeneratorsLength
+        );
+        assert_eq!(
+            ProverKey::new(&bp_gens, 24, 1).unwrap_err(),
+            ProofError::InvalidInputLength
+        );
+    }
+
+    #[test]
+    fn single_party_matches_parties() {
+        use ark_std::rand::SeedableRng;
+        use rand_chacha::ChaChaRng;
+
+        let pc_gens: PedersenGens<Affine> = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(64, 1);
+        let key = ProverKey::new(&bp_gens, 64, 1).unwrap();
+
+        let to_bytes = |(proof, V): (RangeProof<Affine>, Affine)| {
+            let mut bytes = Vec::new();
+            proof.serialize_compressed(&mut bytes).unwrap();
+            V.serialize_compressed(&mut bytes).unwrap();
+            bytes
+        };
+
+        // With the same RNG and transcript, the direct prover makes the same
+        // proof as the dealer and the party, with or without a key.
+        for (n, v) in [(8, 0u128), (8, 255), (32, 12345), (64, u64::MAX as u128)] {
+            let keys = if n == 64 {
+                vec![None, Some(&key)]
+            } else {
+                vec![None]
+            };
+            for key in keys {
+                let mut rng = ChaChaRng::from_seed([n as u8; 32]);
+                let blinding = Fr::rand(&mut rng);
+
+                let mut transcript = Transcript::new(b"SinglePartyTest");
+                let single = RangeProof::prove_single_party(
+                    &bp_gens,
+                    key,
+                    &pc_gens,
+                    &mut transcript,
+                    v,
+                    &blinding,
+                    n,
+                    &mut rng.clone(),
+                )
+                .unwrap();
+                let mut parties_transcript = Transcript::new(b"SinglePartyTest");
+                let (proof, Vs) = RangeProof::prove_with_parties(
+                    &bp_gens,
+                    key,
+                    &pc_gens,
+                    &mut parties_transcript,
+                    &[v],
+                    &[blinding],
+                    n,
+                    &mut rng,
+                )
+                .unwrap();
+
+                let mut a = [0u8; 32];
+                let mut b = [0u8; 32];
+                transcript.challenge_bytes(b"end", &mut a);
+                parties_transcript.challenge_bytes(b"end", &mut b);
+                assert_eq!(a, b);
+
+                let commitment = single.1;
+                assert_eq!(to_bytes(single.clone()), to_bytes((proof, Vs[0])));
+                assert!(single
+                    .0
+                    .verify_single(
+                        &bp_gens,
+                        &pc_gens,
+                        &mut Transcript::new(b"SinglePartyTest"),
+                        &commitment,
+                        n
+                    )
+                    .is_ok());
+            }
+        }
+
+        // The direct prover rejects the same parameters as the dealer.
+        let blinding = Fr::rand(&mut rand::thread_rng());
+        for (n, err) in [
+            (7, ProofError::InvalidBitsize),
+            (128, ProofError::InvalidGeneratorsLength),
+        ] {
+            assert_eq!(
+                RangeProof::prove_single_party(
+                    &bp_gens,
+                    None,
+                    &pc_gens,
+                    &mut Transcript::new(b"SinglePartyTest"),
+                    1,
+                    &blinding,
+                    n,
+                    &mut rand::thread_rng(),
+                )
+                .unwrap_err(),
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn batch_verify_with_reused_accumulator() {
+        let pc_gens: PedersenGens<Affine> = PedersenGens::default();
+        let bp_gens = BulletproofGens::new(32, 2);
+        let mut rng = rand::thread_rng();
+
+        // Proofs of one and of two values are checked in the same batch.
+        let prove = |values: &[u64]| {
+            let blindings: Vec<Fr> = values
+                .iter()
+                .map(|_| Fr::rand(&mut rand::thread_rng()))
+                .collect();
+            RangeProof::prove_multiple(
+                &bp_gens,
+                &pc_gens,
+                &mut Transcript::new(b"AccumulatorTest"),
+                values,
+                &blindings,
+                32,
+            )
+            .unwrap()
+        };
+        let (proof_a, comm_a) = prove(&[3]);
+        let (proof_b, comm_b) = prove(&[5, 8]);
+        let mut wrong = comm_b.clone();
+        wrong[1] = (wrong[1] + pc_gens.B).into();
+
+        let mut acc = msm::MsmAccumulator::new();
+        for (commitments, expected) in [
+            (&comm_b, Ok(())),
+            (&wrong, Err(ProofError::VerificationError)),
+        ] {
+            let mut transcripts = [
+                Transcript::new(b"AccumulatorTest"),
+                Transcript::new(b"AccumulatorTest"),
+            ];
+            assert_eq!(
+                RangeProof::batch_verify_with_accumulator(
+                    &mut rng,
+                    &[&proof_a, &proof_b],
+                    &mut transcripts,
+                    &[&comm_a, commitments],
+                    &bp_gens,
+                    &pc_gens,
+                    32,
+                    &mut acc,
+                ),
+                expected
+            );
+        }
+    }
+
     #[test]
     fn detect_dishonest_party_during_aggregation() {
         use self::dealer::*;
@@ -742,20 +1586,20 @@ mod tests {
         // Parties 0, 2 are honest and use a 32-bit value
         let v0 = rng.gen::<u32>() as u64;
         let v0_blinding = Fr::rand(&mut rng);
-        let party0 = Party::init(&bp_gens, &pc_gens, v0, v0_blinding, n).unwrap();
+        let party0 = Party::init(&bp_gens, &pc_gens, v0.into(), v0_blinding, n).unwrap();
 
         let v2 = rng.gen::<u32>() as u64;
         let v2_blinding = Fr::rand(&mut rng);
-        let party2 = Party::init(&bp_gens, &pc_gens, v2, v2_blinding, n).unwrap();
+        let party2 = Party::init(&bp_gens, &pc_gens, v2.into(), v2_blinding, n).unwrap();
 
         // Parties 1, 3 are dishonest and use a 64-bit value
         let v1 = rng.gen::<u64>();
         let v1_blinding = Fr::rand(&mut rng);
-        let party1 = Party::init(&bp_gens, &pc_gens, v1, v1_blinding, n).unwrap();
+        let party1 = Party::init(&bp_gens, &pc_gens, v1.into(), v1_blinding, n).unwrap();
 
         let v3 = rng.gen::<u64>();
         let v3_blinding = Fr::rand(&mut rng);
-        let party3 = Party::init(&bp_gens, &pc_gens, v3, v3_blinding, n).unwrap();
+        let party3 = Party::init(&bp_gens, &pc_gens, v3.into(), v3_blinding, n).unwrap();
 
         let dealer = Dealer::init(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
 
@@ -813,7 +1657,7 @@ mod tests {
 
         let v0 = rng.gen::<u32>() as u64;
         let v0_blinding = Fr::rand(&mut rng);
-        let party0 = Party::init(&bp_gens, &pc_gens, v0, v0_blinding, n).unwrap();
+        let party0 = Party::init(&bp_gens, &pc_gens, v0.into(), v0_blinding, n).unwrap();
 
         let dealer = Dealer::init(&bp_gens, &pc_gens, &mut transcript, n, m).unwrap();
 
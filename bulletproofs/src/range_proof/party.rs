@@ -25,7 +25,7 @@ use clear_on_drop::clear::Clear;
 use crate::errors::MPCError;
 use crate::generators::{BulletproofGens, PedersenGens};
 use crate::util;
-use std::marker::PhantomData;
+use core::marker::PhantomData;
 
 #[cfg(feature = "std")]
 use rand::thread_rng;
@@ -39,14 +39,16 @@ pub struct Party<G: AffineRepr> {
 
 impl<G: AffineRepr> Party<G> {
     /// Constructs a `PartyAwaitingPosition` with the given rangeproof parameters.
+    /// The value `v` is a `u128`, so that `n = 128` is supported, but any
+    /// supported bitsize is accepted.
     pub fn init<'a>(
         bp_gens: &'a BulletproofGens<G>,
         pc_gens: &'a PedersenGens<G>,
-        v: u64,
+        v: u128,
         v_blinding: G::ScalarField,
         n: usize,
     ) -> Result<PartyAwaitingPosition<'a, G>, MPCError> {
-        if !(n == 8 || n == 16 || n == 32 || n == 64) {
+        if !(n == 8 || n == 16 || n == 32 || n == 64 || n == 128) {
             return Err(MPCError::InvalidBitsize);
         }
         if bp_gens.gens_capacity < n {
@@ -71,7 +73,7 @@ pub struct PartyAwaitingPosition<'a, G: AffineRepr> {
     bp_gens: &'a BulletproofGens<G>,
     pc_gens: &'a PedersenGens<G>,
     n: usize,
-    v: u64,
+    v: u128,
     v_blinding: G::ScalarField,
     V: G,
 }
@@ -168,7 +170,7 @@ impl<'a, G: AffineRepr> Drop for PartyAwaitingPosition<'a, G> {
 /// and is waiting for the aggregated value challenge from the dealer.
 pub struct PartyAwaitingBitChallenge<'a, G: AffineRepr> {
     n: usize, // bitsize of the range
-    v: u64,
+    v: u128,
     v_blinding: G::ScalarField,
     j: usize,
     pc_gens: &'a PedersenGens<G>,
@@ -8,6 +8,29 @@ use rand_chacha::ChaChaRng;
 
 use crate::errors::ProofError;
 
+/// The length of the largest encoding that is appended to a transcript without allocating.
+/// This covers the uncompressed points and the scalars of every curve up to 521 bits.
+const MAX_STACK_ENCODING: usize = 256;
+
+/// Appends the uncompressed encoding of `value` with the given `label`. The encoding is written
+/// to a buffer on the stack when it fits, so that verifiers do not allocate for it.
+fn append_encoding<T: CanonicalSerialize>(
+    transcript: &mut Transcript,
+    label: &'static [u8],
+    value: &T,
+) {
+    let len = value.uncompressed_size();
+    if len <= MAX_STACK_ENCODING {
+        let mut buf = [0u8; MAX_STACK_ENCODING];
+        value.serialize_uncompressed(&mut buf[..len]).unwrap();
+        transcript.append_message(label, &buf[..len]);
+    } else {
+        let mut bytes = Vec::with_capacity(len);
+        value.serialize_uncompressed(&mut bytes).unwrap();
+        transcript.append_message(label, &bytes);
+    }
+}
+
 pub trait TranscriptProtocol<G: AffineRepr> {
     /// Append a domain separator for an `n`-bit, `m`-party range proof.
     fn rangeproof_domain_sep(&mut self, n: u64, m: u64);
@@ -15,13 +38,19 @@ pub trait TranscriptProtocol<G: AffineRepr> {
     /// Append a domain separator for a length-`n` inner product proof.
     fn innerproduct_domain_sep(&mut self, n: u64);
 
+    /// Append a domain separator for an aggregation of `k` linear proofs.
+    fn linearproof_aggregation_domain_sep(&mut self, k: u64);
+
     /// Append a domain separator for a constraint system.
+    #[cfg(feature = "yoloproofs")]
     fn r1cs_domain_sep(&mut self);
 
     /// Commit a domain separator for a CS without randomized constraints.
+    #[cfg(feature = "yoloproofs")]
     fn r1cs_1phase_domain_sep(&mut self);
 
     /// Commit a domain separator for a CS with randomized constraints.
+    #[cfg(feature = "yoloproofs")]
     fn r1cs_2phase_domain_sep(&mut self);
 
     /// Append a `scalar` with the given `label`.
@@ -54,28 +83,32 @@ impl<G: AffineRepr> TranscriptProtocol<G> for Transcript {
         self.append_u64(b"n", n);
     }
 
+    fn linearproof_aggregation_domain_sep(&mut self, k: u64) {
+        self.append_message(b"dom-sep", b"linear aggregation v1");
+        self.append_u64(b"k", k);
+    }
+
+    #[cfg(feature = "yoloproofs")]
     fn r1cs_domain_sep(&mut self) {
         self.append_message(b"dom-sep", b"r1cs v1");
     }
 
+    #[cfg(feature = "yoloproofs")]
     fn r1cs_1phase_domain_sep(&mut self) {
         self.append_message(b"dom-sep", b"r1cs-1phase");
     }
 
+    #[cfg(feature = "yoloproofs")]
     fn r1cs_2phase_domain_sep(&mut self) {
         self.append_message(b"dom-sep", b"r1cs-2phase");
     }
 
     fn append_scalar(&mut self, label: &'static [u8], scalar: &G::ScalarField) {
-        let mut bytes = Vec::new();
-        scalar.serialize_uncompressed(&mut bytes).unwrap();
-        self.append_message(label, &bytes);
+        append_encoding(self, label, scalar);
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &G) {
-        let mut bytes = Vec::new();
-        point.serialize_uncompressed(&mut bytes).unwrap();
-        self.append_message(label, &bytes);
+        append_encoding(self, label, point);
     }
 
     fn validate_and_append_point(
@@ -86,9 +119,7 @@ impl<G: AffineRepr> TranscriptProtocol<G> for Transcript {
         if point.is_zero() {
             Err(ProofError::VerificationError)
         } else {
-            let mut bytes = Vec::new();
-            point.serialize_uncompressed(&mut bytes).unwrap();
-            self.append_message(label, &bytes);
+            append_encoding(self, label, point);
             Ok(())
         }
     }
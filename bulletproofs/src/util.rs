@@ -4,6 +4,7 @@
 
 use ark_ec::AffineRepr;
 use ark_std::{vec, vec::Vec, One, Zero};
+#[cfg(feature = "yoloproofs")]
 use clear_on_drop::clear::Clear;
 
 use crate::inner_product_proof::inner_product;
@@ -2,8 +2,7 @@
 #![allow(clippy::await_holding_lock)]
 
 use rand::rngs::OsRng;
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use reqwest::{Client, Response};
 use std::error::Error;
 use std::time::Instant;
 
@@ -12,6 +11,8 @@ use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::One;
 
 use boomerang::client::{CollectionStateC, IssuanceStateC, SpendVerifyStateC, UKeyPair};
+use boomerang::params::{ClientParams, ServerParams};
+use boomerang::routes::{self, Method, Route};
 use boomerang::server::{
     CollectionM1, CollectionM3, CollectionM5, IssuanceM2, IssuanceM4, ServerKeyPair, SpendVerifyM1,
     SpendVerifyM3, SpendVerifyM5,
@@ -33,20 +34,25 @@ type SBSM3 = SpendVerifyM3<Config>;
 type SBSM5 = SpendVerifyM5<Config>;
 type SBCM = SpendVerifyStateC<Config>;
 
-#[derive(Serialize, Deserialize)]
-enum MessageType {
-    M1,
-    M3,
-    M6,
-    M10,
-    M13,
-    M14,
-}
-
-#[derive(Serialize, Deserialize)]
-struct Message {
-    msg_type: MessageType,
-    data: Vec<u8>,
+const HTTP_SERVER: &str = "http://127.0.0.1:7878";
+const HTTPS_SERVER: &str = "https://127.0.0.1:3000";
+
+// Sends `body` to `route` on the server at `base`, with the method that the route expects.
+async fn send(
+    client: &Client,
+    base: &str,
+    route: Route,
+    body: Vec<u8>,
+) -> reqwest::Result<Response> {
+    let method = match route.method {
+        Method::Get => reqwest::Method::GET,
+        Method::Post => reqwest::Method::POST,
+    };
+    client
+        .request(method, route.url(base))
+        .body(body)
+        .send()
+        .await
 }
 
 #[tokio::main]
@@ -58,6 +64,16 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .danger_accept_invalid_certs(true) // Accept self-signed certificates
         .build()?;
 
+    // Agree with the server on the parameters of the protocols before issuance.
+    let params_bytes = send(&client, HTTPS_SERVER, routes::PARAMS, Vec::new())
+        .await?
+        .bytes()
+        .await?;
+    let server_params = ServerParams::deserialize_compressed(&mut params_bytes.as_ref())
+        .expect("Failed to deserialize the server params");
+    let params = ClientParams::new(&["tsecp256k1"]).negotiate(&server_params)?;
+    println!("Negotiated parameters: {:?}", params);
+
     let mut rng = OsRng;
     let kp = CBKP::generate(&mut rng);
     let mut state = IBCM::default();
@@ -68,21 +84,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let mut m1_bytes = Vec::new();
     m1.serialize_compressed(&mut m1_bytes).unwrap();
 
-    let m1_message = Message {
-        msg_type: MessageType::M1,
-        data: m1_bytes,
-    };
-    let m1_message_bytes = bincode::serialize(&m1_message).unwrap();
-    println!(
-        "Bytes sent issuance (m1_message_bytes): {}",
-        m1_message_bytes.len()
-    );
-
-    let http_response = client
-        .post("http://127.0.0.1:7878")
-        .body(m1_message_bytes.clone())
-        .send()
-        .await?;
+    println!("Bytes sent issuance (m1_bytes): {}", m1_bytes.len());
+
+    let http_response = send(&client, HTTP_SERVER, routes::ISSUANCE_M2, m1_bytes.clone()).await?;
 
     if http_response.status().is_success() {
         let m2_bytes = http_response.bytes().await?;
@@ -93,21 +97,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
         let mut m3_bytes = Vec::new();
         m3.serialize_compressed(&mut m3_bytes).unwrap();
 
-        let m3_message = Message {
-            msg_type: MessageType::M3,
-            data: m3_bytes,
-        };
-        let m3_message_bytes = bincode::serialize(&m3_message).unwrap();
-        println!(
-            "Bytes sent issuance (m3_message_bytes): {}",
-            m3_message_bytes.len()
-        );
-
-        let m3_response = client
-            .post("http://127.0.0.1:7878")
-            .body(m3_message_bytes)
-            .send()
-            .await?;
+        println!("Bytes sent issuance (m3_bytes): {}", m3_bytes.len());
+
+        let m3_response = send(&client, HTTP_SERVER, routes::ISSUANCE_M4, m3_bytes).await?;
 
         if m3_response.status().is_success() {
             println!("Successfully sent m3 to the server.");
@@ -126,7 +118,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
             println!("Successfully received m4 and skp from the server.");
 
-            let _p_state = IBCM::populate_state(&m4, &mut state, &skp, kp.clone());
+            let _p_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
 
             println!("Issuance protocol sucessful!");
         } else {
@@ -136,11 +128,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         println!("HTTP Error: {}", http_response.status());
     }
 
-    let https_response = client
-        .post("https://127.0.0.1:3000")
-        .body(m1_message_bytes.clone())
-        .send()
-        .await?;
+    let https_response = send(&client, HTTPS_SERVER, routes::ISSUANCE_M2, m1_bytes.clone()).await?;
 
     if https_response.status().is_success() {
         let m2_bytes = https_response.bytes().await?;
@@ -151,21 +139,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
         let mut m3_bytes = Vec::new();
         m3.serialize_compressed(&mut m3_bytes).unwrap();
 
-        let m3_message = Message {
-            msg_type: MessageType::M3,
-            data: m3_bytes,
-        };
-        let m3_message_bytes = bincode::serialize(&m3_message).unwrap();
-        println!(
-            "Bytes sent issuance (m3_message_bytes): {}",
-            m3_message_bytes.len()
-        );
-
-        let m3_response = client
-            .post("http://127.0.0.1:7878")
-            .body(m3_message_bytes)
-            .send()
-            .await?;
+        println!("Bytes sent issuance (m3_bytes): {}", m3_bytes.len());
+
+        let m3_response = send(&client, HTTP_SERVER, routes::ISSUANCE_M4, m3_bytes).await?;
 
         if m3_response.status().is_success() {
             println!("Successfully received m4 from the server.");
@@ -184,36 +160,30 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
             println!("Successfully received m4 and skp from the server.");
 
-            let p_state = IBCM::populate_state(&m4, &mut state, &skp, kp.clone());
+            let p_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
 
             println!("Issuance protocol sucessful!");
             println!("Successfully received m4 from the server.");
 
-            let mut m5_slice = remaining_bytes;
-            let m5: CBSM1 = CBSM1::deserialize_compressed(&mut m5_slice)
+            let m5_bytes = send(&client, HTTP_SERVER, routes::COLLECTION_M1, Vec::new())
+                .await?
+                .bytes()
+                .await?;
+            let m5: CBSM1 = CBSM1::deserialize_compressed(&mut m5_bytes.as_ref())
                 .expect("Failed to deserialize Collection M1");
 
             println!("Successfully received collection m1 from the server.");
 
-            let m6 = CBCM::generate_collection_m2(&mut rng, p_state, &m5, &mut col_state, &skp);
+            let m6 = CBCM::generate_collection_m2(&mut rng, &p_state, &m5, &mut col_state, &skp);
             let mut m6_bytes = Vec::new();
             m6.serialize_compressed(&mut m6_bytes).unwrap();
 
-            let m6_message = Message {
-                msg_type: MessageType::M6,
-                data: m6_bytes,
-            };
-            let m6_message_bytes = bincode::serialize(&m6_message).unwrap();
             println!(
                 "Bytes sent collection (m2_message_bytes): {}",
-                m6_message_bytes.len()
+                m6_bytes.len()
             );
 
-            let m6_response = client
-                .post("http://127.0.0.1:7878")
-                .body(m6_message_bytes)
-                .send()
-                .await?;
+            let m6_response = send(&client, HTTP_SERVER, routes::COLLECTION_M3, m6_bytes).await?;
 
             if m6_response.status().is_success() {
                 let m9_bytes = m6_response.bytes().await?;
@@ -227,21 +197,13 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 let mut m10_bytes = Vec::new();
                 m10.serialize_compressed(&mut m10_bytes).unwrap();
 
-                let m10_message = Message {
-                    msg_type: MessageType::M10,
-                    data: m10_bytes,
-                };
-                let m10_message_bytes = bincode::serialize(&m10_message).unwrap();
                 println!(
                     "Bytes sent collection (m4_message_bytes): {}",
-                    m10_message_bytes.len()
+                    m10_bytes.len()
                 );
 
-                let m10_response = client
-                    .post("http://127.0.0.1:7878")
-                    .body(m10_message_bytes)
-                    .send()
-                    .await?;
+                let m10_response =
+                    send(&client, HTTP_SERVER, routes::COLLECTION_M5, m10_bytes).await?;
 
                 if m10_response.status().is_success() {
                     let m11_bytes = m10_response.bytes().await?;
@@ -251,43 +213,38 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
                     println!("Successfully received m5 collection from the server.");
 
-                    let c_col_state = CBCM::populate_state(&mut col_state, &m11, &skp, kp.clone());
+                    let c_col_state = CBCM::populate_state(&mut col_state, &m11, &skp, &kp);
                     println!("Collection protocol sucessful!");
 
-                    let mut m12_slice = m11_slice;
-                    let m12: SBSM1 = SBSM1::deserialize_compressed(&mut m12_slice)
-                        .expect("Failed to deserialize Collection M1");
+                    let m12_bytes = send(&client, HTTP_SERVER, routes::SPEND_VERIFY_M1, Vec::new())
+                        .await?
+                        .bytes()
+                        .await?;
+                    let m12: SBSM1 = SBSM1::deserialize_compressed(&mut m12_bytes.as_ref())
+                        .expect("Failed to deserialize Spend-Verify M1");
 
-                    println!("Successfully received collection m1 from the server.");
+                    println!("Successfully received spend-verify m1 from the server.");
 
                     let spend_state: Vec<<Config as CurveConfig>::ScalarField> =
-                        vec![<Config as CurveConfig>::ScalarField::one()];
+                        vec![<Config as CurveConfig>::ScalarField::one(); params.catalog_size];
                     let m13 = SBCM::generate_spendverify_m2(
                         &mut rng,
-                        c_col_state,
+                        &c_col_state,
                         &mut s_state,
                         &m12,
                         &skp,
-                        spend_state,
+                        &spend_state,
                     );
                     let mut m13_bytes = Vec::new();
                     m13.serialize_compressed(&mut m13_bytes).unwrap();
 
-                    let m13_message = Message {
-                        msg_type: MessageType::M13,
-                        data: m13_bytes,
-                    };
-                    let m13_message_bytes = bincode::serialize(&m13_message).unwrap();
                     println!(
                         "Bytes sent spend-verify (m2_message_bytes): {}",
-                        m13_message_bytes.len()
+                        m13_bytes.len()
                     );
 
-                    let m13_response = client
-                        .post("http://127.0.0.1:7878")
-                        .body(m13_message_bytes)
-                        .send()
-                        .await?;
+                    let m13_response =
+                        send(&client, HTTP_SERVER, routes::SPEND_VERIFY_M3, m13_bytes).await?;
                     if m13_response.status().is_success() {
                         let m15_bytes = m13_response.bytes().await?;
                         let mut m15_slice = &m15_bytes[..];
@@ -296,25 +253,26 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
                         println!("Successfully received m3 spend-verify from the server.");
 
-                        let m14 = SBCM::generate_spendverify_m4(&mut rng, &mut s_state, &m15);
+                        // N.B The demo takes the policy id from the server's message. A real
+                        // client checks the proof against the id that the server published for
+                        // the campaign.
+                        let m14 = SBCM::generate_spendverify_m4(
+                            &mut rng,
+                            &mut s_state,
+                            &m15,
+                            &m15.policy_id,
+                        )
+                        .expect("Failed to generate Spend-Verify M4");
                         let mut m14_bytes = Vec::new();
                         m14.serialize_compressed(&mut m14_bytes).unwrap();
 
-                        let m14_message = Message {
-                            msg_type: MessageType::M14,
-                            data: m14_bytes,
-                        };
-                        let m14_message_bytes = bincode::serialize(&m14_message).unwrap();
                         println!(
                             "Bytes sent spend-verify (m4_message_bytes): {}",
-                            m14_message_bytes.len()
+                            m14_bytes.len()
                         );
 
-                        let m14_response = client
-                            .post("http://127.0.0.1:7878")
-                            .body(m14_message_bytes)
-                            .send()
-                            .await?;
+                        let m14_response =
+                            send(&client, HTTP_SERVER, routes::SPEND_VERIFY_M5, m14_bytes).await?;
                         if m14_response.status().is_success() {
                             let m16_bytes = m14_response.bytes().await?;
                             let mut m16_slice = &m16_bytes[..];
@@ -323,8 +281,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
                             println!("Successfully received m5 spend-verify from the server.");
 
-                            let _spt_state =
-                                SBCM::populate_state(&mut s_state, &m16, &skp, kp.clone());
+                            let _spt_state = SBCM::populate_state(&mut s_state, &m16, &skp, &kp);
 
                             println!("Spend-Verify protocol sucessful!");
                         } else {
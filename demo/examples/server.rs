@@ -8,16 +8,16 @@ use axum::{
     handler::HandlerWithoutStateExt,
     http::{StatusCode, Uri},
     response::{Redirect, Response},
-    routing::get,
+    routing::{get, on, MethodFilter},
     BoxError, Router,
 };
 use axum_server::tls_rustls::RustlsConfig;
 use lazy_static::lazy_static;
-use serde::{Deserialize, Serialize};
 use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Mutex;
-use std::{net::SocketAddr, path::PathBuf};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt};
 
 use ark_ec::CurveConfig;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
@@ -26,7 +26,12 @@ use ark_std::One;
 use boomerang::client::{
     CollectionM2, CollectionM4, IssuanceM1, IssuanceM3, SpendVerifyM2, SpendVerifyM4,
 };
-use boomerang::server::{CollectionStateS, IssuanceStateS, ServerKeyPair, SpendVerifyStateS};
+use boomerang::params::ServerParams;
+use boomerang::policy::{PolicyCache, PolicyKey, StaticPolicy};
+use boomerang::routes::{self, Method, Route};
+use boomerang::server::{
+    CollectionStateS, IssuanceStateS, ServerConfig, ServerKeyPair, SpendVerifyStateS,
+};
 use tsecp256k1::Config;
 
 use rand_core::OsRng;
@@ -48,53 +53,77 @@ type CBCM2 = CollectionM2<Config>;
 type CBCM4 = CollectionM4<Config>;
 
 type SBSM = SpendVerifyStateS<Config>;
+
+const DEMO_CAMPAIGN: u64 = 0;
 type SBCM2 = SpendVerifyM2<Config>;
 type SBCM4 = SpendVerifyM4<Config>;
 
-#[derive(Serialize, Deserialize)]
-enum MessageType {
-    M1,
-    M3,
-    M6,
-    M10,
-    M13,
-    M14,
-}
-
-#[derive(Serialize, Deserialize)]
-struct Message {
-    msg_type: MessageType,
-    data: Vec<u8>, // Serialized data
-}
+// The protocol routes that the demo serves.
+const PROTOCOL_ROUTES: [Route; 8] = [
+    routes::ISSUANCE_M2,
+    routes::ISSUANCE_M4,
+    routes::COLLECTION_M1,
+    routes::COLLECTION_M3,
+    routes::COLLECTION_M5,
+    routes::SPEND_VERIFY_M1,
+    routes::SPEND_VERIFY_M3,
+    routes::SPEND_VERIFY_M5,
+];
 
 #[tokio::main]
 async fn main() {
+    // The boomerang spans report the size of each message and the time spent on it when they close.
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "example_tls_rustls=debug".into()),
+                .unwrap_or_else(|_| "server=debug,boomerang=info".into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_span_events(FmtSpan::CLOSE))
         .init();
 
+    // This binary is built for a single curve, so the configured curve must match it.
+    if CONFIG.curve != "tsecp256k1" {
+        panic!(
+            "the demo server only runs on tsecp256k1, not {}",
+            CONFIG.curve
+        );
+    }
+    if CONFIG.rate_limit.is_some() {
+        tracing::warn!("the demo server does not enforce the configured rate limit");
+    }
+
     let ports = Ports {
-        http: 7878,
-        https: 3000,
+        http: CONFIG.http_port,
+        https: CONFIG.https_port,
     };
     tokio::spawn(redirect_http_to_https(ports));
 
+    let examples = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples");
     let config = RustlsConfig::from_pem_file(
-        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .join("examples")
-            .join("cert.pem"),
-        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .join("examples")
-            .join("key.pem"),
+        CONFIG
+            .tls_cert_path
+            .clone()
+            .unwrap_or_else(|| examples.join("cert.pem")),
+        CONFIG
+            .tls_key_path
+            .clone()
+            .unwrap_or_else(|| examples.join("key.pem")),
     )
     .await
     .unwrap();
 
-    let app = Router::new().route("/", get(handler).post(post_handler));
+    let mut app = Router::new()
+        .route(routes::ROOT.path, get(handler))
+        .route(routes::METRICS.path, get(metrics_handler))
+        .route(routes::PARAMS.path, get(params_handler));
+    for route in PROTOCOL_ROUTES {
+        app = app.route(
+            route.path,
+            on(method_filter(route.method), move |body: Body| {
+                protocol_handler(route, body)
+            }),
+        );
+    }
 
     // run https server
     let addr = SocketAddr::from(([127, 0, 0, 1], ports.https));
@@ -110,19 +139,71 @@ async fn handler() -> &'static str {
     "Hello, Client!"
 }
 
+// Maps the method of a route to the filter that axum routes it with.
+fn method_filter(method: Method) -> MethodFilter {
+    match method {
+        Method::Get => MethodFilter::GET,
+        Method::Post => MethodFilter::POST,
+    }
+}
+
+// Advertises the parameters that the server runs the protocols with.
+async fn params_handler() -> Response {
+    let mut bytes = Vec::new();
+    ServerParams::from(&*CONFIG)
+        .serialize_compressed(&mut bytes)
+        .expect("Failed to serialize the server params");
+    Response::builder().body(Body::from(bytes)).unwrap()
+}
+
+// Serves the protocol metrics in the Prometheus text format.
+async fn metrics_handler() -> Response {
+    Response::builder()
+        .header("content-type", "text/plain; version=0.0.4")
+        .body(Body::from(boomerang::metrics::render()))
+        .unwrap()
+}
+
 lazy_static! {
-    static ref SKP: Mutex<Option<SBKP>> = Mutex::new({
-        let mut rng = OsRng;
-        Some(SBKP::generate(&mut rng))
-    });
+    static ref CONFIG: ServerConfig = ServerConfig::load().unwrap_or_else(|e| panic!("{}", e));
+    static ref SKP: Mutex<Option<SBKP>> = Mutex::new(Some(load_key_pair(&CONFIG)));
+    static ref POLICIES: PolicyCache<Config, StaticPolicy<Config>> = PolicyCache::new(
+        StaticPolicy(CONFIG.policy_state::<Config>()),
+        PolicyKey::generate(&mut OsRng)
+    );
     static ref IBSM_DEFAULT: Mutex<IBSM> = Mutex::new(IBSM::default());
     static ref CBSM_DEFAULT: Mutex<CBSM> = Mutex::new(CBSM::default());
     static ref SBSM_DEFAULT: Mutex<SBSM> = Mutex::new(SBSM::default());
 }
 
-async fn post_handler(body: Body) -> Result<Response, Infallible> {
-    let bytes = body::to_bytes(body, usize::MAX).await.unwrap();
-    let message: Message = bincode::deserialize(&bytes).expect("Failed to deserialize message");
+// Reads the server keypair from the configured key path, or generates one, and stores it there
+// if a path is configured, so that tokens survive a restart of the server.
+fn load_key_pair(config: &ServerConfig) -> SBKP {
+    let Some(path) = &config.key_path else {
+        return SBKP::generate(&mut OsRng);
+    };
+
+    if let Ok(bytes) = std::fs::read(path) {
+        return SBKP::deserialize_compressed(&mut bytes.as_slice())
+            .expect("Failed to deserialize the server keypair");
+    }
+
+    let skp = SBKP::generate(&mut OsRng);
+    let mut bytes = Vec::new();
+    skp.serialize_compressed(&mut bytes)
+        .expect("Failed to serialize ServerKeyPair");
+    std::fs::write(path, bytes).expect("Failed to write the server keypair");
+    skp
+}
+
+// Answers the protocol message that was sent to `route` with the next server message.
+async fn protocol_handler(route: Route, body: Body) -> Result<Response, Infallible> {
+    let Ok(bytes) = body::to_bytes(body, CONFIG.max_request_size).await else {
+        return Ok(Response::builder()
+            .status(StatusCode::PAYLOAD_TOO_LARGE)
+            .body(Body::empty())
+            .expect("Failed to create response"));
+    };
 
     let mut rng = OsRng;
     // Access shared SKP and IBSM instances
@@ -140,10 +221,10 @@ async fn post_handler(body: Body) -> Result<Response, Infallible> {
     let mut sbsm_lock = SBSM_DEFAULT.lock().unwrap();
     let mut spend_state = sbsm_lock.clone();
 
-    match message.msg_type {
-        MessageType::M1 => {
-            println!("Received m1 message, processing...");
-            let m1: IBCM1 = IBCM1::deserialize_compressed(&mut message.data.as_slice())
+    match route {
+        routes::ISSUANCE_M2 => {
+            tracing::info!("received issuance m1, processing...");
+            let m1: IBCM1 = IBCM1::deserialize_compressed(&mut bytes.as_ref())
                 .expect("Failed to deserialize compressed Issuance M1");
 
             let m2 =
@@ -151,7 +232,7 @@ async fn post_handler(body: Body) -> Result<Response, Infallible> {
             let mut m2_bytes = Vec::new();
             m2.serialize_compressed(&mut m2_bytes)
                 .expect("Failed to serialize Issuance M2");
-            println!("Bytes sent issuance (m2_message_bytes): {}", m2_bytes.len());
+            tracing::info!(bytes = m2_bytes.len(), "sending issuance m2");
 
             *ibsm_lock = s_state;
 
@@ -160,24 +241,34 @@ async fn post_handler(body: Body) -> Result<Response, Infallible> {
                 .body(Body::from(m2_bytes))
                 .expect("Failed to create response"))
         }
-        MessageType::M3 => {
-            println!("Received m3 message, processing...");
+        routes::ISSUANCE_M4 => {
+            tracing::info!("received issuance m3, processing...");
 
-            let m3: IBCM3 = IBCM3::deserialize_compressed(&mut message.data.as_slice())
+            let m3: IBCM3 = IBCM3::deserialize_compressed(&mut bytes.as_ref())
                 .expect("Failed to deserialize compressed Issuance M3");
 
             let m4 = IssuanceStateS::<Config>::generate_issuance_m4(&m3, &mut s_state, skp);
             let mut m4_bytes = Vec::new();
             m4.serialize_compressed(&mut m4_bytes)
                 .expect("Failed to serialize Issuance M4");
-            println!("Bytes sent issuance (m4_message_bytes): {}", m4_bytes.len());
+            tracing::info!(bytes = m4_bytes.len(), "sending issuance m4");
 
             // Serialize SKP
             let mut skp_bytes = Vec::new();
             skp.serialize_compressed(&mut skp_bytes)
                 .expect("Failed to serialize ServerKeyPair");
 
-            // Also send the collection-procedure first message
+            // The demo has no other way to publish its public key, so it sends it along.
+            let mut response_bytes = Vec::new();
+            response_bytes.extend_from_slice(&m4_bytes);
+            response_bytes.extend_from_slice(&skp_bytes);
+
+            Ok(Response::builder()
+                .status(StatusCode::OK)
+                .body(Body::from(response_bytes))
+                .expect("Failed to create response"))
+        }
+        routes::COLLECTION_M1 => {
             let collection_m1 =
                 CollectionStateS::<Config>::generate_collection_m1(&mut rng, &mut col_state);
             *cbsm_lock = col_state;
@@ -186,27 +277,17 @@ async fn post_handler(body: Body) -> Result<Response, Infallible> {
             collection_m1
                 .serialize_compressed(&mut m1_c_bytes)
                 .expect("Failed to serialize Collection M1");
-            println!(
-                "Bytes sent collection: (m1_message_bytes): {}",
-                m1_c_bytes.len()
-            );
-
-            let mut response_bytes = Vec::new();
-            response_bytes.extend_from_slice(&m4_bytes);
-            response_bytes.extend_from_slice(&skp_bytes);
-            response_bytes.extend_from_slice(&m1_c_bytes);
-
-            println!("Sending M4 and first of Collection...");
+            tracing::info!(bytes = m1_c_bytes.len(), "sending collection m1");
 
             Ok(Response::builder()
                 .status(StatusCode::OK)
-                .body(Body::from(response_bytes))
+                .body(Body::from(m1_c_bytes))
                 .expect("Failed to create response"))
         }
-        MessageType::M6 => {
-            println!("Received m2 message of collection, processing...");
+        routes::COLLECTION_M3 => {
+            tracing::info!("received collection m2, processing...");
 
-            let m7: CBCM2 = CBCM2::deserialize_compressed(&mut message.data.as_slice())
+            let m7: CBCM2 = CBCM2::deserialize_compressed(&mut bytes.as_ref())
                 .expect("Failed to deserialize compressed Collection M2");
 
             let v = <Config as CurveConfig>::ScalarField::one();
@@ -216,27 +297,25 @@ async fn post_handler(body: Body) -> Result<Response, Infallible> {
                 &mut col_state,
                 skp,
                 v,
-            );
+            )
+            .expect("Failed to generate Collection M3");
 
             *cbsm_lock = col_state;
 
             let mut m8_bytes = Vec::new();
             m8.serialize_compressed(&mut m8_bytes)
                 .expect("Failed to serialize Collection M3");
-            println!(
-                "Bytes sent collection: (m3_message_bytes): {}",
-                m8_bytes.len()
-            );
+            tracing::info!(bytes = m8_bytes.len(), "sending collection m3");
 
             Ok(Response::builder()
                 .status(StatusCode::OK)
                 .body(Body::from(m8_bytes))
                 .expect("Failed to create response"))
         }
-        MessageType::M10 => {
-            println!("Received m4 message of collection, processing...");
+        routes::COLLECTION_M5 => {
+            tracing::info!("received collection m4, processing...");
 
-            let m10: CBCM4 = CBCM4::deserialize_compressed(&mut message.data.as_slice())
+            let m10: CBCM4 = CBCM4::deserialize_compressed(&mut bytes.as_ref())
                 .expect("Failed to deserialize compressed Collection M4");
 
             let m11 = CBSM::generate_collection_m5(&m10, &mut col_state, skp);
@@ -245,12 +324,14 @@ async fn post_handler(body: Body) -> Result<Response, Infallible> {
             let mut m11_bytes = Vec::new();
             m11.serialize_compressed(&mut m11_bytes)
                 .expect("Failed to serialize Collection M5");
-            println!(
-                "Bytes sent collection: (m5_message_bytes): {}",
-                m11_bytes.len()
-            );
+            tracing::info!(bytes = m11_bytes.len(), "sending collection m5");
 
-            // Also send the spend/verify-procedure first message
+            Ok(Response::builder()
+                .status(StatusCode::OK)
+                .body(Body::from(m11_bytes))
+                .expect("Failed to create response"))
+        }
+        routes::SPEND_VERIFY_M1 => {
             let spendverify_m1 =
                 SpendVerifyStateS::<Config>::generate_spendverify_m1(&mut rng, &mut spend_state);
             *sbsm_lock = spend_state;
@@ -259,58 +340,51 @@ async fn post_handler(body: Body) -> Result<Response, Infallible> {
             spendverify_m1
                 .serialize_compressed(&mut m1_s_bytes)
                 .expect("Failed to serialize Spend Verify M1");
-            println!(
-                "Bytes sent spend-verify: (m1_message_bytes): {}",
-                m1_s_bytes.len()
-            );
-
-            let mut response_bytes = Vec::new();
-            response_bytes.extend_from_slice(&m11_bytes);
-            response_bytes.extend_from_slice(&m1_s_bytes);
-
-            println!("Sending M5 and first of SpendVerify...");
+            tracing::info!(bytes = m1_s_bytes.len(), "sending spend-verify m1");
 
             Ok(Response::builder()
                 .status(StatusCode::OK)
-                .body(Body::from(response_bytes))
+                .body(Body::from(m1_s_bytes))
                 .expect("Failed to create response"))
         }
-        MessageType::M13 => {
-            println!("Received m2 message of spend-verify, processing...");
+        routes::SPEND_VERIFY_M3 => {
+            tracing::info!("received spend-verify m2, processing...");
 
-            let m14: SBCM2 = SBCM2::deserialize_compressed(&mut message.data.as_slice())
+            let m14: SBCM2 = SBCM2::deserialize_compressed(&mut bytes.as_ref())
                 .expect("Failed to deserialize compressed Spend-verify M2");
 
-            let policy_state: Vec<<Config as CurveConfig>::ScalarField> =
-                vec![<Config as CurveConfig>::ScalarField::from(2)];
-            let m15 = SBSM::generate_spendverify_m3(
+            // The demo runs a single campaign, whose policy may change once a day.
+            let epoch = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .expect("Clock is before the UNIX epoch")
+                .as_secs()
+                / 86400;
+            let m15 = SBSM::generate_spendverify_m3_with_policy(
                 &mut rng,
                 &m14,
                 &mut spend_state,
                 skp,
-                policy_state.clone(),
-            );
+                &*POLICIES,
+                DEMO_CAMPAIGN,
+                epoch,
+            )
+            .expect("Failed to apply the policy");
             *sbsm_lock = spend_state;
 
             let mut m15_bytes = Vec::new();
             m15.serialize_compressed(&mut m15_bytes)
                 .expect("Failed to serialize Spend-Verify M3");
-            println!(
-                "Bytes sent spend-verify: (m3_message_bytes): {}",
-                m15_bytes.len()
-            );
-
-            println!("Sending M3 of SpendVerify...");
+            tracing::info!(bytes = m15_bytes.len(), "sending spend-verify m3");
 
             Ok(Response::builder()
                 .status(StatusCode::OK)
                 .body(Body::from(m15_bytes))
                 .expect("Failed to create response"))
         }
-        MessageType::M14 => {
-            println!("Received m4 message of spend-verify, processing...");
+        routes::SPEND_VERIFY_M5 => {
+            tracing::info!("received spend-verify m4, processing...");
 
-            let m15: SBCM4 = SBCM4::deserialize_compressed(&mut message.data.as_slice())
+            let m15: SBCM4 = SBCM4::deserialize_compressed(&mut bytes.as_ref())
                 .expect("Failed to deserialize compressed Spend-verify M4");
 
             let m16 = SBSM::generate_spendverify_m5(&m15, &mut spend_state, skp);
@@ -319,18 +393,17 @@ async fn post_handler(body: Body) -> Result<Response, Infallible> {
             let mut m16_bytes = Vec::new();
             m16.serialize_compressed(&mut m16_bytes)
                 .expect("Failed to serialize Spend-Verify M6");
-            println!(
-                "Bytes sent spend-verify: (m5_message_bytes): {}",
-                m16_bytes.len()
-            );
-
-            println!("Sending M5 of SpendVerify...");
+            tracing::info!(bytes = m16_bytes.len(), "sending spend-verify m5");
 
             Ok(Response::builder()
                 .status(StatusCode::OK)
                 .body(Body::from(m16_bytes))
                 .expect("Failed to create response"))
         }
+        _ => Ok(Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .expect("Failed to create response")),
     }
 }
 
@@ -46,7 +46,14 @@ macro_rules! bench_tacl_challenge_time {
             // Now we can just benchmark how long it takes to create a new multi proof.
             c.bench_function(concat!($curve_name, " acl challenge time"), |b| {
                 b.iter(|| {
-                    ACLCH::challenge(kp.tag_key, kp.verifying_key, &mut OsRng, m1, "message");
+                    ACLCH::challenge(
+                        kp.tag_key,
+                        kp.verifying_key,
+                        &mut OsRng,
+                        m1,
+                        b"message",
+                        None,
+                    );
                 });
             });
         }
@@ -70,7 +77,14 @@ macro_rules! bench_tacl_respond_time {
             let (com, gens) = PedersenComm::<$config>::new_multi(&vals, &mut OsRng);
             let kp = ACLKP::generate(&mut OsRng);
             let m1 = ACLSC::commit(&kp, &mut OsRng, com.comm);
-            let m2 = ACLCH::challenge(kp.tag_key, kp.verifying_key, &mut OsRng, m1, "message");
+            let m2 = ACLCH::challenge(
+                kp.tag_key,
+                kp.verifying_key,
+                &mut OsRng,
+                m1,
+                b"message",
+                None,
+            );
 
             // Now we can just benchmark how long it takes to create a new multi proof.
             c.bench_function(concat!($curve_name, " acl respond time"), |b| {
@@ -99,13 +113,20 @@ macro_rules! bench_tacl_sign_time {
             let (com, gens) = PedersenComm::<$config>::new_multi(&vals, &mut OsRng);
             let kp = ACLKP::generate(&mut OsRng);
             let m1 = ACLSC::commit(&kp, &mut OsRng, com.comm);
-            let m2 = ACLCH::challenge(kp.tag_key, kp.verifying_key, &mut OsRng, m1, "message");
+            let m2 = ACLCH::challenge(
+                kp.tag_key,
+                kp.verifying_key,
+                &mut OsRng,
+                m1,
+                b"message",
+                None,
+            );
             let m3 = ACLSR::respond(&kp, &m1, &m2);
 
             // Now we can just benchmark how long it takes to create a new multi proof.
             c.bench_function(concat!($curve_name, " acl sign time"), |b| {
                 b.iter(|| {
-                    ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, "message");
+                    ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, b"message", None);
                 });
             });
         }
@@ -129,14 +150,21 @@ macro_rules! bench_tacl_verify_time {
             let (com, gens) = PedersenComm::<$config>::new_multi(&vals, &mut OsRng);
             let kp = ACLKP::generate(&mut OsRng);
             let m1 = ACLSC::commit(&kp, &mut OsRng, com.comm);
-            let m2 = ACLCH::challenge(kp.tag_key, kp.verifying_key, &mut OsRng, m1, "message");
+            let m2 = ACLCH::challenge(
+                kp.tag_key,
+                kp.verifying_key,
+                &mut OsRng,
+                m1,
+                b"message",
+                None,
+            );
             let m3 = ACLSR::respond(&kp, &m1, &m2);
-            let m4 = ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, "message");
+            let m4 = ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, b"message", None);
 
             // Now we can just benchmark how long it takes to create a new multi proof.
             c.bench_function(concat!($curve_name, " acl verify time"), |b| {
                 b.iter(|| {
-                    ACLSV::verify(kp.verifying_key, kp.tag_key, &m4, "message");
+                    ACLSV::verify(kp.verifying_key, kp.tag_key, &m4, b"message", None);
                 });
             });
         }
@@ -160,15 +188,30 @@ macro_rules! bench_tacl_sign_proof_time {
             let (com, gens) = PedersenComm::<$config>::new_multi(&vals, &mut OsRng);
             let kp = ACLKP::generate(&mut OsRng);
             let m1 = ACLSC::commit(&kp, &mut OsRng, com.comm);
-            let m2 = ACLCH::challenge(kp.tag_key, kp.verifying_key, &mut OsRng, m1, "message");
+            let m2 = ACLCH::challenge(
+                kp.tag_key,
+                kp.verifying_key,
+                &mut OsRng,
+                m1,
+                b"message",
+                None,
+            );
             let m3 = ACLSR::respond(&kp, &m1, &m2);
-            let m4 = ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, "message");
-            ACLSV::verify(kp.verifying_key, kp.tag_key, &m4, "message");
+            let m4 = ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, b"message", None);
+            ACLSV::verify(kp.verifying_key, kp.tag_key, &m4, b"message", None);
 
             // Now we can just benchmark how long it takes to create a new multi proof.
             c.bench_function(concat!($curve_name, " acl proof sign time"), |b| {
                 b.iter(|| {
-                    ACLSP::prove(&mut OsRng, kp.tag_key, &m4, &vals, &gens.generators, com.r);
+                    ACLSP::prove(
+                        &mut OsRng,
+                        kp.tag_key,
+                        &m4,
+                        &vals,
+                        &gens.generators,
+                        com.r,
+                        None,
+                    );
                 });
             });
         }
@@ -192,15 +235,30 @@ macro_rules! bench_tacl_sign_verify_time {
             let (com, gens) = PedersenComm::<$config>::new_multi(&vals, &mut OsRng);
             let kp = ACLKP::generate(&mut OsRng);
             let m1 = ACLSC::commit(&kp, &mut OsRng, com.comm);
-            let m2 = ACLCH::challenge(kp.tag_key, kp.verifying_key, &mut OsRng, m1, "message");
+            let m2 = ACLCH::challenge(
+                kp.tag_key,
+                kp.verifying_key,
+                &mut OsRng,
+                m1,
+                b"message",
+                None,
+            );
             let m3 = ACLSR::respond(&kp, &m1, &m2);
-            let m4 = ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, "message");
-            ACLSV::verify(kp.verifying_key, kp.tag_key, &m4, "message");
-            let proof = ACLSP::prove(&mut OsRng, kp.tag_key, &m4, &vals, &gens.generators, com.r);
+            let m4 = ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, b"message", None);
+            ACLSV::verify(kp.verifying_key, kp.tag_key, &m4, b"message", None);
+            let proof = ACLSP::prove(
+                &mut OsRng,
+                kp.tag_key,
+                &m4,
+                &vals,
+                &gens.generators,
+                com.r,
+                None,
+            );
 
             // Now we can just benchmark how long it takes to create a new multi proof.
             c.bench_function(concat!($curve_name, " acl proof verify time"), |b| {
-                b.iter(|| ACLSPV::verify(&proof, kp.tag_key, &m4, &gens.generators));
+                b.iter(|| ACLSPV::verify(&proof, kp.tag_key, &m4, &gens.generators, None));
             });
         }
     };
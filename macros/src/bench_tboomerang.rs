@@ -1,15 +1,99 @@
+use std::time::Duration;
+
+/// MessageProfile. This struct holds the measurements of one message of the boomerang protocols.
+#[derive(Clone, Debug)]
+pub struct MessageProfile {
+    /// name: the name of the message, e.g "issuance m1".
+    pub name: &'static str,
+    /// total: the total time spent generating the message, over every run.
+    pub total: Duration,
+    /// runs: the number of runs.
+    pub runs: u32,
+    /// size: the compressed size of the message, in bytes.
+    pub size: usize,
+}
+
+impl MessageProfile {
+    /// mean. Returns the mean time spent generating the message.
+    pub fn mean(&self) -> Duration {
+        self.total / self.runs.max(1)
+    }
+}
+
+/// CurveProfile. This struct holds the measurements of every message of the boomerang
+/// protocols on a single curve, in protocol order.
+#[derive(Clone, Debug, Default)]
+pub struct CurveProfile {
+    /// messages: the measured messages.
+    pub messages: Vec<MessageProfile>,
+}
+
+impl CurveProfile {
+    /// record. This function adds a measurement of the message `name` to the profile.
+    /// # Arguments
+    /// * `name` - the name of the message.
+    /// * `elapsed` - the time spent generating the message.
+    /// * `size` - the compressed size of the message.
+    pub fn record(&mut self, name: &'static str, elapsed: Duration, size: usize) {
+        match self.messages.iter_mut().find(|m| m.name == name) {
+            Some(m) => {
+                m.total += elapsed;
+                m.runs += 1;
+                m.size = size;
+            }
+            None => self.messages.push(MessageProfile {
+                name,
+                total: elapsed,
+                runs: 1,
+                size,
+            }),
+        }
+    }
+}
+
+/// comparison_table. This function returns a markdown table with a row for every message and a
+/// column for every curve, which holds the mean time and the compressed size of the message.
+/// The rows follow the order of the first profile.
+/// # Arguments
+/// * `profiles` - the name and the profile of every curve.
+pub fn comparison_table(profiles: &[(&str, CurveProfile)]) -> String {
+    let mut table = String::from("| message |");
+    for (curve, _) in profiles {
+        table.push_str(&format!(" {} |", curve));
+    }
+    table.push_str("\n|---|");
+    table.push_str(&"---|".repeat(profiles.len()));
+
+    let names = profiles
+        .first()
+        .map(|(_, p)| p.messages.iter().map(|m| m.name).collect::<Vec<_>>())
+        .unwrap_or_default();
+    for name in names {
+        table.push_str(&format!("\n| {} |", name));
+        for (_, profile) in profiles {
+            match profile.messages.iter().find(|m| m.name == name) {
+                Some(m) => table.push_str(&format!(" {:.2?} / {} B |", m.mean(), m.size)),
+                None => table.push_str(" - |"),
+            }
+        }
+    }
+
+    table
+}
+
 #[macro_export]
 macro_rules! bench_tboomerang_issuance_m1_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the first message of the boomerang scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
             let mut state = IBCM::default();
 
             // Now we can just benchmark how long it takes for the first message.
             c.bench_function(concat!($curve_name, " issuance m1 time"), |b| {
                 b.iter(|| {
-                    IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
+                    IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
                 });
             });
         }
@@ -21,15 +105,16 @@ macro_rules! bench_tboomerang_issuance_m2_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the second message of the boomerang scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
             let mut state = IBCM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
             let mut s_state = IBSM::default();
 
             c.bench_function(concat!($curve_name, " issuance m2 time"), |b| {
                 b.iter(|| {
-                    IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
+                    IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
                 });
             });
         }
@@ -41,16 +126,17 @@ macro_rules! bench_tboomerang_issuance_m3_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the third message of the boomerang scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
             let mut state = IBCM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
             let mut s_state = IBSM::default();
-            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
 
             c.bench_function(concat!($curve_name, " issuance m3 time"), |b| {
                 b.iter(|| {
-                    IBCM::generate_issuance_m3(&m2, &mut state, &mut OsRng);
+                    IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
                 });
             });
         }
@@ -62,13 +148,14 @@ macro_rules! bench_tboomerang_issuance_m4_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the fourth message of the boomerang scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
             let mut state = IBCM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
             let mut s_state = IBSM::default();
-            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
-            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut OsRng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
 
             // Now we can just benchmark how long it takes for the first message.
             c.bench_function(concat!($curve_name, " issuance m4 time"), |b| {
@@ -85,18 +172,19 @@ macro_rules! bench_tboomerang_issuance_m5_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the local population of the boomerang issuance scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
             let mut state = IBCM::default();
             let mut s_state = IBSM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
-            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
-            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
             let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
 
             c.bench_function(concat!($curve_name, " issuance populate state time"), |b| {
                 b.iter(|| {
-                    IBCM::populate_state(&m4, &mut state, &skp, kp.clone());
+                    IBCM::populate_state(&m4, &mut state, &skp, &kp);
                 });
             });
         }
@@ -108,20 +196,21 @@ macro_rules! bench_tboomerang_collection_m1_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the first message of the boomerang collection scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
             let mut state = IBCM::default();
             let mut s_state = IBSM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
-            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
-            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
             let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
-            let i_state = IBCM::populate_state(&m4, &mut state, &skp, kp.clone());
+            let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
             let mut s_col_state = CBSM::default();
 
             c.bench_function(concat!($curve_name, " collection m1 time"), |b| {
                 b.iter(|| {
-                    CBSM::<$config>::generate_collection_m1(&mut OsRng, &mut s_col_state);
+                    CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
                 });
             });
         }
@@ -133,58 +222,135 @@ macro_rules! bench_tboomerang_collection_m2_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the second message of the boomerang collection scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
             let mut state = IBCM::default();
             let mut s_state = IBSM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
-            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
-            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
             let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
-            let i_state = IBCM::populate_state(&m4, &mut state, &skp, kp.clone());
+            let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
             let mut s_col_state = CBSM::default();
             let mut c_col_state = CBCM::default();
-            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
 
             c.bench_function(concat!($curve_name, " collection m2 time"), |b| {
                 b.iter(|| {
-                    CBCM::generate_collection_m2(
-                        &mut OsRng,
-                        i_state.clone(),
-                        &c_m1,
-                        &mut c_col_state,
-                        &skp,
-                    );
+                    CBCM::generate_collection_m2(&mut rng, &i_state, &c_m1, &mut c_col_state, &skp);
                 });
             });
         }
     };
 }
 
+#[macro_export]
+macro_rules! bench_tboomerang_collection_m2_verify_time {
+    ($config: ty, $bench_name: ident, $curve_name: tt) => {
+        pub fn $bench_name(c: &mut Criterion) {
+            // Bench the server's check of the second message of the boomerang collection scheme,
+            // and of its two commitment openings, one by one and with a single MSM.
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let mut state = IBCM::default();
+            let mut s_state = IBSM::default();
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
+            let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
+            let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
+            let mut s_col_state = CBSM::default();
+            let mut c_col_state = CBCM::default();
+            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
+            let c_m2 =
+                CBCM::generate_collection_m2(&mut rng, &i_state, &c_m1, &mut c_col_state, &skp);
+            let len = committed_len(&c_m2.gens);
+            let transcript = |label| session_transcript::<$config>(label, &c_m2.session_id);
+
+            c.bench_function(concat!($curve_name, " collection m2 verify time"), |b| {
+                b.iter(|| {
+                    CBSM::verify_collection_m2_with_rng(&c_m2, &skp, &mut rng).unwrap();
+                });
+            });
+
+            c.bench_function(concat!($curve_name, " collection m2 openings time"), |b| {
+                b.iter(|| {
+                    assert!(c_m2.pi_1.verify(
+                        &mut transcript(b"BoomerangCollectionM2O1"),
+                        &c_m2.comm.comm,
+                        len,
+                        &c_m2.gens
+                    ));
+                    assert!(c_m2.pi_2.verify(
+                        &mut transcript(b"BoomerangCollectionM2O2"),
+                        &c_m2.prev_comm.comm,
+                        len,
+                        &c_m2.prev_gens
+                    ));
+                });
+            });
+
+            c.bench_function(
+                concat!($curve_name, " collection m2 batched openings time"),
+                |b| {
+                    b.iter(|| {
+                        let mut batch = BatchVerifier::<$config>::new();
+                        let equation = c_m2
+                            .pi_1
+                            .verification_equation(
+                                &mut transcript(b"BoomerangCollectionM2O1"),
+                                &c_m2.comm.comm,
+                                len,
+                                &c_m2.gens,
+                            )
+                            .unwrap();
+                        batch.add(&mut rng, equation);
+                        let equation = c_m2
+                            .pi_2
+                            .verification_equation(
+                                &mut transcript(b"BoomerangCollectionM2O2"),
+                                &c_m2.prev_comm.comm,
+                                len,
+                                &c_m2.prev_gens,
+                            )
+                            .unwrap();
+                        batch.add(&mut rng, equation);
+                        assert!(batch.verify());
+                    });
+                },
+            );
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! bench_tboomerang_collection_m3_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the third message of the boomerang collection scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
             let mut state = IBCM::default();
             let mut s_state = IBSM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
-            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
-            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
             let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
-            let i_state = IBCM::populate_state(&m4, &mut state, &skp, kp.clone());
+            let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
             let mut s_col_state = CBSM::default();
             let mut c_col_state = CBCM::default();
-            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
             let c_m2 =
-                CBCM::generate_collection_m2(&mut OsRng, i_state, &c_m1, &mut c_col_state, &skp);
+                CBCM::generate_collection_m2(&mut rng, &i_state, &c_m1, &mut c_col_state, &skp);
 
             let v = <$config as CurveConfig>::ScalarField::one();
             c.bench_function(concat!($curve_name, " collection m3 time"), |b| {
                 b.iter(|| {
-                    CBSM::generate_collection_m3(&mut OsRng, &c_m2, &mut s_col_state, &skp, v);
+                    CBSM::generate_collection_m3(&mut rng, &c_m2, &mut s_col_state, &skp, v)
+                        .unwrap();
                 });
             });
         }
@@ -196,27 +362,29 @@ macro_rules! bench_tboomerang_collection_m4_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the fourth message of the boomerang collection scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
             let mut state = IBCM::default();
             let mut s_state = IBSM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
-            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
-            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
             let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
-            let i_state = IBCM::populate_state(&m4, &mut state, &skp, kp.clone());
+            let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
             let mut s_col_state = CBSM::default();
             let mut c_col_state = CBCM::default();
-            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
             let c_m2 =
-                CBCM::generate_collection_m2(&mut OsRng, i_state, &c_m1, &mut c_col_state, &skp);
+                CBCM::generate_collection_m2(&mut rng, &i_state, &c_m1, &mut c_col_state, &skp);
 
             let v = <$config as CurveConfig>::ScalarField::one();
-            let c_m3 = CBSM::generate_collection_m3(&mut OsRng, &c_m2, &mut s_col_state, &skp, v);
+            let c_m3 =
+                CBSM::generate_collection_m3(&mut rng, &c_m2, &mut s_col_state, &skp, v).unwrap();
 
             c.bench_function(concat!($curve_name, " collection m4 time"), |b| {
                 b.iter(|| {
-                    CBCM::generate_collection_m4(&mut OsRng, &mut c_col_state, &c_m3);
+                    CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &c_m3);
                 });
             });
         }
@@ -228,25 +396,27 @@ macro_rules! bench_tboomerang_collection_m5_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the fifth message of the boomerang collection scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
 
             let mut state = IBCM::default();
             let mut s_state = IBSM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
-            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
-            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
             let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
-            let i_state = IBCM::populate_state(&m4, &mut state, &skp, kp.clone());
+            let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
             let mut s_col_state = CBSM::default();
             let mut c_col_state = CBCM::default();
-            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
             let c_m2 =
-                CBCM::generate_collection_m2(&mut OsRng, i_state, &c_m1, &mut c_col_state, &skp);
+                CBCM::generate_collection_m2(&mut rng, &i_state, &c_m1, &mut c_col_state, &skp);
 
             let v = <$config as CurveConfig>::ScalarField::one();
-            let c_m3 = CBSM::generate_collection_m3(&mut OsRng, &c_m2, &mut s_col_state, &skp, v);
-            let c_m4 = CBCM::generate_collection_m4(&mut OsRng, &mut c_col_state, &c_m3);
+            let c_m3 =
+                CBSM::generate_collection_m3(&mut rng, &c_m2, &mut s_col_state, &skp, v).unwrap();
+            let c_m4 = CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &c_m3);
 
             c.bench_function(concat!($curve_name, " collection m5 time"), |b| {
                 b.iter(|| {
@@ -262,29 +432,31 @@ macro_rules! bench_tboomerang_collection_m6_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the population of the boomerang collection scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
             let mut state = IBCM::default();
             let mut s_state = IBSM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
-            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
-            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
             let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
-            let i_state = IBCM::populate_state(&m4, &mut state, &skp, kp.clone());
+            let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
             let mut s_col_state = CBSM::default();
             let mut c_col_state = CBCM::default();
-            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
             let c_m2 =
-                CBCM::generate_collection_m2(&mut OsRng, i_state, &c_m1, &mut c_col_state, &skp);
+                CBCM::generate_collection_m2(&mut rng, &i_state, &c_m1, &mut c_col_state, &skp);
 
             let v = <$config as CurveConfig>::ScalarField::one();
-            let c_m3 = CBSM::generate_collection_m3(&mut OsRng, &c_m2, &mut s_col_state, &skp, v);
-            let c_m4 = CBCM::generate_collection_m4(&mut OsRng, &mut c_col_state, &c_m3);
+            let c_m3 =
+                CBSM::generate_collection_m3(&mut rng, &c_m2, &mut s_col_state, &skp, v).unwrap();
+            let c_m4 = CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &c_m3);
             let c_m5 = CBSM::generate_collection_m5(&c_m4, &mut s_col_state, &skp);
 
             c.bench_function(concat!($curve_name, " collection state time"), |b| {
                 b.iter(|| {
-                    CBCM::populate_state(&mut c_col_state, &c_m5, &skp, kp.clone());
+                    CBCM::populate_state(&mut c_col_state, &c_m5, &skp, &kp);
                 });
             });
         }
@@ -296,31 +468,33 @@ macro_rules! bench_tboomerang_spending_m1_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the first message of the boomerang spending scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
             let mut state = IBCM::default();
             let mut s_state = IBSM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
-            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
-            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
             let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
-            let i_state = IBCM::populate_state(&m4, &mut state, &skp, kp.clone());
+            let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
             let mut s_col_state = CBSM::default();
             let mut c_col_state = CBCM::default();
-            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
             let c_m2 =
-                CBCM::generate_collection_m2(&mut OsRng, i_state, &c_m1, &mut c_col_state, &skp);
+                CBCM::generate_collection_m2(&mut rng, &i_state, &c_m1, &mut c_col_state, &skp);
 
             let v = <$config as CurveConfig>::ScalarField::one();
-            let c_m3 = CBSM::generate_collection_m3(&mut OsRng, &c_m2, &mut s_col_state, &skp, v);
-            let c_m4 = CBCM::generate_collection_m4(&mut OsRng, &mut c_col_state, &c_m3);
+            let c_m3 =
+                CBSM::generate_collection_m3(&mut rng, &c_m2, &mut s_col_state, &skp, v).unwrap();
+            let c_m4 = CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &c_m3);
             let c_m5 = CBSM::generate_collection_m5(&c_m4, &mut s_col_state, &skp);
-            let c_state = CBCM::populate_state(&mut c_col_state, &c_m5, &skp, kp.clone());
+            let c_state = CBCM::populate_state(&mut c_col_state, &c_m5, &skp, &kp);
             let mut s_spend_state = SVBS::default();
 
             c.bench_function(concat!($curve_name, " spend-verify m1 time"), |b| {
                 b.iter(|| {
-                    SVBS::<$config>::generate_spendverify_m1(&mut OsRng, &mut s_spend_state);
+                    SVBS::<$config>::generate_spendverify_m1(&mut rng, &mut s_spend_state);
                 });
             });
         }
@@ -332,41 +506,43 @@ macro_rules! bench_tboomerang_spending_m2_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the second message of the boomerang spending scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
             let mut state = IBCM::default();
             let mut s_state = IBSM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
-            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
-            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
             let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
-            let i_state = IBCM::populate_state(&m4, &mut state, &skp, kp.clone());
+            let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
             let mut s_col_state = CBSM::default();
             let mut c_col_state = CBCM::default();
-            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
             let c_m2 =
-                CBCM::generate_collection_m2(&mut OsRng, i_state, &c_m1, &mut c_col_state, &skp);
+                CBCM::generate_collection_m2(&mut rng, &i_state, &c_m1, &mut c_col_state, &skp);
 
             let v = <$config as CurveConfig>::ScalarField::one();
-            let c_m3 = CBSM::generate_collection_m3(&mut OsRng, &c_m2, &mut s_col_state, &skp, v);
-            let c_m4 = CBCM::generate_collection_m4(&mut OsRng, &mut c_col_state, &c_m3);
+            let c_m3 =
+                CBSM::generate_collection_m3(&mut rng, &c_m2, &mut s_col_state, &skp, v).unwrap();
+            let c_m4 = CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &c_m3);
             let c_m5 = CBSM::generate_collection_m5(&c_m4, &mut s_col_state, &skp);
-            let c_state = CBCM::populate_state(&mut c_col_state, &c_m5, &skp, kp.clone());
+            let c_state = CBCM::populate_state(&mut c_col_state, &c_m5, &skp, &kp);
             let mut s_spend_state = SVBS::default();
             let mut c_spend_state = SVBC::default();
-            let s_m1 = SVBS::generate_spendverify_m1(&mut OsRng, &mut s_spend_state);
+            let s_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
             let spend_state: Vec<<$config as CurveConfig>::ScalarField> =
                 vec![<$config as CurveConfig>::ScalarField::one()];
 
             c.bench_function(concat!($curve_name, " spend-verify m2 time"), |b| {
                 b.iter(|| {
                     SVBC::generate_spendverify_m2(
-                        &mut OsRng,
-                        c_state.clone(),
+                        &mut rng,
+                        &c_state,
                         &mut c_spend_state,
                         &s_m1,
                         &skp,
-                        spend_state.clone(),
+                        &spend_state,
                     );
                 });
             });
@@ -379,38 +555,40 @@ macro_rules! bench_tboomerang_spending_m3_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the third message of the boomerang spending scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
             let mut state = IBCM::default();
             let mut s_state = IBSM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
-            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
-            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
             let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
-            let i_state = IBCM::populate_state(&m4, &mut state, &skp, kp.clone());
+            let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
             let mut s_col_state = CBSM::default();
             let mut c_col_state = CBCM::default();
-            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
             let c_m2 =
-                CBCM::generate_collection_m2(&mut OsRng, i_state, &c_m1, &mut c_col_state, &skp);
+                CBCM::generate_collection_m2(&mut rng, &i_state, &c_m1, &mut c_col_state, &skp);
 
             let v = <$config as CurveConfig>::ScalarField::one();
-            let c_m3 = CBSM::generate_collection_m3(&mut OsRng, &c_m2, &mut s_col_state, &skp, v);
-            let c_m4 = CBCM::generate_collection_m4(&mut OsRng, &mut c_col_state, &c_m3);
+            let c_m3 =
+                CBSM::generate_collection_m3(&mut rng, &c_m2, &mut s_col_state, &skp, v).unwrap();
+            let c_m4 = CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &c_m3);
             let c_m5 = CBSM::generate_collection_m5(&c_m4, &mut s_col_state, &skp);
-            let c_state = CBCM::populate_state(&mut c_col_state, &c_m5, &skp, kp.clone());
+            let c_state = CBCM::populate_state(&mut c_col_state, &c_m5, &skp, &kp);
             let mut s_spend_state = SVBS::default();
             let mut c_spend_state = SVBC::default();
-            let s_m1 = SVBS::generate_spendverify_m1(&mut OsRng, &mut s_spend_state);
+            let s_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
             let spend_state: Vec<<$config as CurveConfig>::ScalarField> =
                 vec![<$config as CurveConfig>::ScalarField::one()];
             let s_m2 = SVBC::generate_spendverify_m2(
-                &mut OsRng,
-                c_state,
+                &mut rng,
+                &c_state,
                 &mut c_spend_state,
                 &s_m1,
                 &skp,
-                spend_state,
+                &spend_state,
             );
             let policy_state: Vec<<$config as CurveConfig>::ScalarField> =
                 vec![<$config as CurveConfig>::ScalarField::from(2)];
@@ -418,12 +596,13 @@ macro_rules! bench_tboomerang_spending_m3_time {
             c.bench_function(concat!($curve_name, " spend-verify m3 time"), |b| {
                 b.iter(|| {
                     SVBS::generate_spendverify_m3(
-                        &mut OsRng,
+                        &mut rng,
                         &s_m2,
                         &mut s_spend_state,
                         &skp,
                         policy_state.clone(),
-                    );
+                    )
+                    .unwrap();
                 });
             });
         }
@@ -435,52 +614,57 @@ macro_rules! bench_tboomerang_spending_m4_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the forth message of the boomerang spending scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
             let mut state = IBCM::default();
             let mut s_state = IBSM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
-            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
-            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
             let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
-            let i_state = IBCM::populate_state(&m4, &mut state, &skp, kp.clone());
+            let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
             let mut s_col_state = CBSM::default();
             let mut c_col_state = CBCM::default();
-            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
             let c_m2 =
-                CBCM::generate_collection_m2(&mut OsRng, i_state, &c_m1, &mut c_col_state, &skp);
+                CBCM::generate_collection_m2(&mut rng, &i_state, &c_m1, &mut c_col_state, &skp);
 
             let v = <$config as CurveConfig>::ScalarField::one();
-            let c_m3 = CBSM::generate_collection_m3(&mut OsRng, &c_m2, &mut s_col_state, &skp, v);
-            let c_m4 = CBCM::generate_collection_m4(&mut OsRng, &mut c_col_state, &c_m3);
+            let c_m3 =
+                CBSM::generate_collection_m3(&mut rng, &c_m2, &mut s_col_state, &skp, v).unwrap();
+            let c_m4 = CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &c_m3);
             let c_m5 = CBSM::generate_collection_m5(&c_m4, &mut s_col_state, &skp);
-            let c_state = CBCM::populate_state(&mut c_col_state, &c_m5, &skp, kp.clone());
+            let c_state = CBCM::populate_state(&mut c_col_state, &c_m5, &skp, &kp);
             let mut s_spend_state = SVBS::default();
             let mut c_spend_state = SVBC::default();
-            let s_m1 = SVBS::generate_spendverify_m1(&mut OsRng, &mut s_spend_state);
+            let s_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
             let spend_state: Vec<<$config as CurveConfig>::ScalarField> =
                 vec![<$config as CurveConfig>::ScalarField::one()];
             let s_m2 = SVBC::generate_spendverify_m2(
-                &mut OsRng,
-                c_state,
+                &mut rng,
+                &c_state,
                 &mut c_spend_state,
                 &s_m1,
                 &skp,
-                spend_state,
+                &spend_state,
             );
             let policy_state: Vec<<$config as CurveConfig>::ScalarField> =
                 vec![<$config as CurveConfig>::ScalarField::from(2)];
             let s_m3 = SVBS::generate_spendverify_m3(
-                &mut OsRng,
+                &mut rng,
                 &s_m2,
                 &mut s_spend_state,
                 &skp,
                 policy_state.clone(),
-            );
+            )
+            .unwrap();
 
+            let policy_id = skp.policy_id(&policy_state);
             c.bench_function(concat!($curve_name, " spend-verify m4 time"), |b| {
                 b.iter(|| {
-                    SVBC::generate_spendverify_m4(&mut OsRng, &mut c_spend_state, &s_m3);
+                    SVBC::generate_spendverify_m4(&mut rng, &mut c_spend_state, &s_m3, &policy_id)
+                        .unwrap();
                 });
             });
         }
@@ -492,49 +676,58 @@ macro_rules! bench_tboomerang_spending_m5_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the fifth message of the boomerang spending scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
             let mut state = IBCM::default();
             let mut s_state = IBSM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
-            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
-            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
             let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
-            let i_state = IBCM::populate_state(&m4, &mut state, &skp, kp.clone());
+            let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
             let mut s_col_state = CBSM::default();
             let mut c_col_state = CBCM::default();
-            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
             let c_m2 =
-                CBCM::generate_collection_m2(&mut OsRng, i_state, &c_m1, &mut c_col_state, &skp);
+                CBCM::generate_collection_m2(&mut rng, &i_state, &c_m1, &mut c_col_state, &skp);
 
             let v = <$config as CurveConfig>::ScalarField::one();
-            let c_m3 = CBSM::generate_collection_m3(&mut OsRng, &c_m2, &mut s_col_state, &skp, v);
-            let c_m4 = CBCM::generate_collection_m4(&mut OsRng, &mut c_col_state, &c_m3);
+            let c_m3 =
+                CBSM::generate_collection_m3(&mut rng, &c_m2, &mut s_col_state, &skp, v).unwrap();
+            let c_m4 = CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &c_m3);
             let c_m5 = CBSM::generate_collection_m5(&c_m4, &mut s_col_state, &skp);
-            let c_state = CBCM::populate_state(&mut c_col_state, &c_m5, &skp, kp.clone());
+            let c_state = CBCM::populate_state(&mut c_col_state, &c_m5, &skp, &kp);
             let mut s_spend_state = SVBS::default();
             let mut c_spend_state = SVBC::default();
-            let s_m1 = SVBS::generate_spendverify_m1(&mut OsRng, &mut s_spend_state);
+            let s_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
             let spend_state: Vec<<$config as CurveConfig>::ScalarField> =
                 vec![<$config as CurveConfig>::ScalarField::one()];
             let s_m2 = SVBC::generate_spendverify_m2(
-                &mut OsRng,
-                c_state,
+                &mut rng,
+                &c_state,
                 &mut c_spend_state,
                 &s_m1,
                 &skp,
-                spend_state,
+                &spend_state,
             );
             let policy_state: Vec<<$config as CurveConfig>::ScalarField> =
                 vec![<$config as CurveConfig>::ScalarField::from(2)];
             let s_m3 = SVBS::generate_spendverify_m3(
-                &mut OsRng,
+                &mut rng,
                 &s_m2,
                 &mut s_spend_state,
                 &skp,
                 policy_state.clone(),
-            );
-            let s_m4 = SVBC::generate_spendverify_m4(&mut OsRng, &mut c_spend_state, &s_m3);
+            )
+            .unwrap();
+            let s_m4 = SVBC::generate_spendverify_m4(
+                &mut rng,
+                &mut c_spend_state,
+                &s_m3,
+                &skp.policy_id(&policy_state),
+            )
+            .unwrap();
 
             c.bench_function(concat!($curve_name, " spend-verify m5 time"), |b| {
                 b.iter(|| {
@@ -550,56 +743,65 @@ macro_rules! bench_tboomerang_spending_m6_time {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
             // Bench the sixth message of the boomerang spending scheme.
-            let kp = CBKP::<$config>::generate(&mut OsRng);
-            let skp = SBKP::generate(&mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
             let mut state = IBCM::default();
             let mut s_state = IBSM::default();
-            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut OsRng);
-            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut OsRng);
-            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut OsRng);
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
             let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
-            let i_state = IBCM::populate_state(&m4, &mut state, &skp, kp.clone());
+            let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
             let mut s_col_state = CBSM::default();
             let mut c_col_state = CBCM::default();
-            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
             let c_m2 =
-                CBCM::generate_collection_m2(&mut OsRng, i_state, &c_m1, &mut c_col_state, &skp);
+                CBCM::generate_collection_m2(&mut rng, &i_state, &c_m1, &mut c_col_state, &skp);
 
             let v = <$config as CurveConfig>::ScalarField::one();
-            let c_m3 = CBSM::generate_collection_m3(&mut OsRng, &c_m2, &mut s_col_state, &skp, v);
-            let c_m4 = CBCM::generate_collection_m4(&mut OsRng, &mut c_col_state, &c_m3);
+            let c_m3 =
+                CBSM::generate_collection_m3(&mut rng, &c_m2, &mut s_col_state, &skp, v).unwrap();
+            let c_m4 = CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &c_m3);
             let c_m5 = CBSM::generate_collection_m5(&c_m4, &mut s_col_state, &skp);
-            let c_state = CBCM::populate_state(&mut c_col_state, &c_m5, &skp, kp.clone());
+            let c_state = CBCM::populate_state(&mut c_col_state, &c_m5, &skp, &kp);
             let mut s_spend_state = SVBS::default();
             let mut c_spend_state = SVBC::default();
-            let s_m1 = SVBS::generate_spendverify_m1(&mut OsRng, &mut s_spend_state);
+            let s_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
             let spend_state: Vec<<$config as CurveConfig>::ScalarField> =
                 vec![<$config as CurveConfig>::ScalarField::one()];
             let s_m2 = SVBC::generate_spendverify_m2(
-                &mut OsRng,
-                c_state,
+                &mut rng,
+                &c_state,
                 &mut c_spend_state,
                 &s_m1,
                 &skp,
-                spend_state,
+                &spend_state,
             );
             let policy_state: Vec<<$config as CurveConfig>::ScalarField> =
                 vec![<$config as CurveConfig>::ScalarField::from(2)];
             let s_m3 = SVBS::generate_spendverify_m3(
-                &mut OsRng,
+                &mut rng,
                 &s_m2,
                 &mut s_spend_state,
                 &skp,
                 policy_state.clone(),
-            );
-            let s_m4 = SVBC::generate_spendverify_m4(&mut OsRng, &mut c_spend_state, &s_m3);
+            )
+            .unwrap();
+            let s_m4 = SVBC::generate_spendverify_m4(
+                &mut rng,
+                &mut c_spend_state,
+                &s_m3,
+                &skp.policy_id(&policy_state),
+            )
+            .unwrap();
             let s_m5 = SVBS::generate_spendverify_m5(&s_m4, &mut s_spend_state, &skp);
 
             c.bench_function(
                 concat!($curve_name, " spend-verify populate state time"),
                 |b| {
                     b.iter(|| {
-                        SVBC::populate_state(&mut c_spend_state, &s_m5, &skp, kp.clone());
+                        SVBC::populate_state(&mut c_spend_state, &s_m5, &skp, &kp);
                     });
                 },
             );
@@ -611,20 +813,26 @@ macro_rules! bench_tboomerang_spending_m6_time {
 macro_rules! bench_tboomerang_rewards_proof {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
+            let mut rng = SeededRng::from_env();
             let spend_state: Vec<<$config as CurveConfig>::ScalarField> =
                 vec![<$config as CurveConfig>::ScalarField::one()];
             let policy_state: Vec<<$config as CurveConfig>::ScalarField> =
                 vec![<$config as CurveConfig>::ScalarField::from(2)];
+            let session_id = new_session_id(&mut rng);
+            let gens = RWG::<$config>::shared();
 
             c.bench_function(concat!($curve_name, " rewards-proof prove time"), |b| {
                 b.iter(|| {
                     RWP::<$config>::prove(
+                        &gens,
                         &spend_state,
                         &policy_state,
                         2,
                         <$config as CurveConfig>::ScalarField::from(2),
-                        &mut OsRng,
-                    );
+                        &session_id,
+                        &mut rng,
+                    )
+                    .unwrap();
                 });
             });
         }
@@ -635,23 +843,28 @@ macro_rules! bench_tboomerang_rewards_proof {
 macro_rules! bench_tboomerang_rewards_proof_verify {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
+            let mut rng = SeededRng::from_env();
             let spend_state: Vec<<$config as CurveConfig>::ScalarField> =
                 vec![<$config as CurveConfig>::ScalarField::one()];
             let policy_state: Vec<<$config as CurveConfig>::ScalarField> =
                 vec![<$config as CurveConfig>::ScalarField::from(2)];
+            let session_id = new_session_id(&mut rng);
+            let gens = RWG::<$config>::shared();
             let proof = RWP::<$config>::prove(
+                &gens,
                 &spend_state,
                 &policy_state,
                 2,
                 <$config as CurveConfig>::ScalarField::from(2),
-                &mut OsRng,
+                &session_id,
+                &mut rng,
             );
 
             c.bench_function(concat!($curve_name, " rewards-proof verify time"), |b| {
                 b.iter(|| {
-                    <Result<RWP<$config>, String> as Clone>::clone(&proof)
-                        .expect("Failed to get rewards proof")
-                        .verify(&spend_state);
+                    let proof = <Result<RWP<$config>, String> as Clone>::clone(&proof)
+                        .expect("Failed to get rewards proof");
+                    black_box(proof.verify(&gens, &spend_state, &session_id)).unwrap();
                 });
             });
         }
@@ -662,9 +875,12 @@ macro_rules! bench_tboomerang_rewards_proof_verify {
 macro_rules! bench_tboomerang_sub_proof {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
+            let mut rng = SeededRng::from_env();
+            let session_id = new_session_id(&mut rng);
+
             c.bench_function(concat!($curve_name, " sub-proof prove time"), |b| {
                 b.iter(|| {
-                    SP::<$config>::prove(2, &mut OsRng);
+                    SP::<$config>::prove(2, &session_id, &mut rng);
                 });
             });
         }
@@ -675,11 +891,231 @@ macro_rules! bench_tboomerang_sub_proof {
 macro_rules! bench_tboomerang_sub_proof_verify {
     ($config: ty, $bench_name: ident, $curve_name: tt) => {
         pub fn $bench_name(c: &mut Criterion) {
-            let proof = SP::<$config>::prove(2, &mut OsRng);
+            let mut rng = SeededRng::from_env();
+            let session_id = new_session_id(&mut rng);
+            let proof = SP::<$config>::prove(2, &session_id, &mut rng);
 
             c.bench_function(concat!($curve_name, " sub-proof verify time"), |b| {
                 b.iter(|| {
-                    proof.verify();
+                    black_box(proof.verify(&session_id)).unwrap();
+                });
+            });
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! bench_tboomerang_issuance_bandwidth {
+    ($config: ty, $bench_name: ident, $curve_name: tt) => {
+        pub fn $bench_name(c: &mut Criterion) {
+            // Report the compressed size of each message of the boomerang issuance scheme.
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let mut state = IBCM::default();
+            let mut s_state = IBSM::default();
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
+            let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
+
+            println!(
+                "{} issuance m1 size: {} bytes",
+                $curve_name,
+                m1.compressed_size()
+            );
+            println!(
+                "{} issuance m2 size: {} bytes",
+                $curve_name,
+                m2.compressed_size()
+            );
+            println!(
+                "{} issuance m3 size: {} bytes",
+                $curve_name,
+                m3.compressed_size()
+            );
+            println!(
+                "{} issuance m4 size: {} bytes",
+                $curve_name,
+                m4.compressed_size()
+            );
+
+            c.bench_function(concat!($curve_name, " issuance serialize time"), |b| {
+                b.iter(|| {
+                    let mut bytes = Vec::new();
+                    m1.serialize_compressed(&mut bytes).unwrap();
+                    m2.serialize_compressed(&mut bytes).unwrap();
+                    m3.serialize_compressed(&mut bytes).unwrap();
+                    m4.serialize_compressed(&mut bytes).unwrap();
+                    black_box(bytes);
+                });
+            });
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! bench_tboomerang_collection_bandwidth {
+    ($config: ty, $bench_name: ident, $curve_name: tt) => {
+        pub fn $bench_name(c: &mut Criterion) {
+            // Report the compressed size of each message of the boomerang collection scheme.
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let mut state = IBCM::default();
+            let mut s_state = IBSM::default();
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
+            let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
+            let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
+            let mut s_col_state = CBSM::default();
+            let mut c_col_state = CBCM::default();
+            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
+            let c_m2 =
+                CBCM::generate_collection_m2(&mut rng, &i_state, &c_m1, &mut c_col_state, &skp);
+
+            let v = <$config as CurveConfig>::ScalarField::one();
+            let c_m3 =
+                CBSM::generate_collection_m3(&mut rng, &c_m2, &mut s_col_state, &skp, v).unwrap();
+            let c_m4 = CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &c_m3);
+            let c_m5 = CBSM::generate_collection_m5(&c_m4, &mut s_col_state, &skp);
+
+            println!(
+                "{} collection m1 size: {} bytes",
+                $curve_name,
+                c_m1.compressed_size()
+            );
+            println!(
+                "{} collection m2 size: {} bytes",
+                $curve_name,
+                c_m2.compressed_size()
+            );
+            println!(
+                "{} collection m3 size: {} bytes",
+                $curve_name,
+                c_m3.compressed_size()
+            );
+            println!(
+                "{} collection m4 size: {} bytes",
+                $curve_name,
+                c_m4.compressed_size()
+            );
+            println!(
+                "{} collection m5 size: {} bytes",
+                $curve_name,
+                c_m5.compressed_size()
+            );
+
+            c.bench_function(concat!($curve_name, " collection serialize time"), |b| {
+                b.iter(|| {
+                    let mut bytes = Vec::new();
+                    c_m1.serialize_compressed(&mut bytes).unwrap();
+                    c_m2.serialize_compressed(&mut bytes).unwrap();
+                    c_m3.serialize_compressed(&mut bytes).unwrap();
+                    c_m4.serialize_compressed(&mut bytes).unwrap();
+                    c_m5.serialize_compressed(&mut bytes).unwrap();
+                    black_box(bytes);
+                });
+            });
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! bench_tboomerang_spending_bandwidth {
+    ($config: ty, $bench_name: ident, $curve_name: tt) => {
+        pub fn $bench_name(c: &mut Criterion) {
+            // Report the compressed size of each message of the boomerang spending scheme.
+            let mut rng = SeededRng::from_env();
+            let kp = CBKP::<$config>::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let mut state = IBCM::default();
+            let mut s_state = IBSM::default();
+            let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+            let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+            let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
+            let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
+            let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
+            let mut s_col_state = CBSM::default();
+            let mut c_col_state = CBCM::default();
+            let c_m1 = CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
+            let c_m2 =
+                CBCM::generate_collection_m2(&mut rng, &i_state, &c_m1, &mut c_col_state, &skp);
+
+            let v = <$config as CurveConfig>::ScalarField::one();
+            let c_m3 =
+                CBSM::generate_collection_m3(&mut rng, &c_m2, &mut s_col_state, &skp, v).unwrap();
+            let c_m4 = CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &c_m3);
+            let c_m5 = CBSM::generate_collection_m5(&c_m4, &mut s_col_state, &skp);
+            let c_state = CBCM::populate_state(&mut c_col_state, &c_m5, &skp, &kp);
+            let mut s_spend_state = SVBS::default();
+            let mut c_spend_state = SVBC::default();
+            let s_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let spend_state: Vec<<$config as CurveConfig>::ScalarField> =
+                vec![<$config as CurveConfig>::ScalarField::one()];
+            let s_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &c_state,
+                &mut c_spend_state,
+                &s_m1,
+                &skp,
+                &spend_state,
+            );
+            let policy_state: Vec<<$config as CurveConfig>::ScalarField> =
+                vec![<$config as CurveConfig>::ScalarField::from(2)];
+            let s_m3 = SVBS::generate_spendverify_m3(
+                &mut rng,
+                &s_m2,
+                &mut s_spend_state,
+                &skp,
+                policy_state.clone(),
+            )
+            .unwrap();
+            let s_m4 = SVBC::generate_spendverify_m4(
+                &mut rng,
+                &mut c_spend_state,
+                &s_m3,
+                &skp.policy_id(&policy_state),
+            )
+            .unwrap();
+            let s_m5 = SVBS::generate_spendverify_m5(&s_m4, &mut s_spend_state, &skp);
+
+            println!(
+                "{} spend-verify m1 size: {} bytes",
+                $curve_name,
+                s_m1.compressed_size()
+            );
+            println!(
+                "{} spend-verify m2 size: {} bytes",
+                $curve_name,
+                s_m2.compressed_size()
+            );
+            println!(
+                "{} spend-verify m3 size: {} bytes",
+                $curve_name,
+                s_m3.compressed_size()
+            );
+            println!(
+                "{} spend-verify m4 size: {} bytes",
+                $curve_name,
+                s_m4.compressed_size()
+            );
+            println!(
+                "{} spend-verify m5 size: {} bytes",
+                $curve_name,
+                s_m5.compressed_size()
+            );
+
+            c.bench_function(concat!($curve_name, " spend-verify serialize time"), |b| {
+                b.iter(|| {
+                    let mut bytes = Vec::new();
+                    s_m1.serialize_compressed(&mut bytes).unwrap();
+                    s_m2.serialize_compressed(&mut bytes).unwrap();
+                    s_m3.serialize_compressed(&mut bytes).unwrap();
+                    s_m4.serialize_compressed(&mut bytes).unwrap();
+                    s_m5.serialize_compressed(&mut bytes).unwrap();
+                    black_box(bytes);
                 });
             });
         }
@@ -704,11 +1140,13 @@ macro_rules! bench_tboomerang_import_everything {
         use ark_std::One;
         use ark_std::UniformRand;
         use boomerang::{
-            client::CollectionStateC as CBCM, client::IssuanceStateC as IBCM,
-            client::SpendVerifyStateC as SVBC, client::UKeyPair as CBKP, config::BoomerangConfig,
-            server::CollectionStateS as CBSM, server::IssuanceStateS as IBSM,
+            batch::BatchVerifier, client::CollectionStateC as CBCM, client::IssuanceStateC as IBCM,
+            client::SpendVerifyStateC as SVBC, client::UKeyPair as CBKP, config::committed_len,
+            config::new_session_id, config::session_transcript, config::BoomerangConfig,
+            rng::SeededRng, server::CollectionStateS as CBSM, server::IssuanceStateS as IBSM,
             server::ServerKeyPair as SBKP, server::SpendVerifyStateS as SVBS,
-            utils::rewards::BRewardsProof as RWP, utils::rewards::SubProof as SP,
+            utils::rewards::BRewardsProof as RWP, utils::rewards::RewardsGenerators as RWG,
+            utils::rewards::SubProof as SP,
         };
         use core::ops::Mul;
         use criterion::{black_box, criterion_group, criterion_main, Criterion};
@@ -718,16 +1156,14 @@ macro_rules! bench_tboomerang_import_everything {
             opening_protocol::OpeningProofMulti as OPM, pedersen_config::PedersenComm,
             pedersen_config::PedersenConfig,
         };
-        use rand_core::OsRng;
         use sha2::{Digest, Sha512};
         use std::time::Duration;
     };
 }
 
 #[macro_export]
-macro_rules! bench_tboomerang_make_all {
+macro_rules! bench_tboomerang_define_all {
     ($config: ty, $curve_name: tt) => {
-        $crate::bench_tboomerang_import_everything!();
         $crate::bench_tboomerang_issuance_m1_time!($config, boomerang_issuance_m1, $curve_name);
         $crate::bench_tboomerang_issuance_m2_time!($config, boomerang_issuance_m2, $curve_name);
         $crate::bench_tboomerang_issuance_m3_time!($config, boomerang_issuance_m3, $curve_name);
@@ -735,6 +1171,11 @@ macro_rules! bench_tboomerang_make_all {
         $crate::bench_tboomerang_issuance_m5_time!($config, boomerang_issuance_m5, $curve_name);
         $crate::bench_tboomerang_collection_m1_time!($config, boomerang_collection_m1, $curve_name);
         $crate::bench_tboomerang_collection_m2_time!($config, boomerang_collection_m2, $curve_name);
+        $crate::bench_tboomerang_collection_m2_verify_time!(
+            $config,
+            boomerang_collection_m2_verify,
+            $curve_name
+        );
         $crate::bench_tboomerang_collection_m3_time!($config, boomerang_collection_m3, $curve_name);
         $crate::bench_tboomerang_collection_m4_time!($config, boomerang_collection_m4, $curve_name);
         $crate::bench_tboomerang_collection_m5_time!($config, boomerang_collection_m5, $curve_name);
@@ -757,6 +1198,21 @@ macro_rules! bench_tboomerang_make_all {
             boomerang_sub_proof_verify,
             $curve_name
         );
+        $crate::bench_tboomerang_issuance_bandwidth!(
+            $config,
+            boomerang_issuance_bandwidth,
+            $curve_name
+        );
+        $crate::bench_tboomerang_collection_bandwidth!(
+            $config,
+            boomerang_collection_bandwidth,
+            $curve_name
+        );
+        $crate::bench_tboomerang_spending_bandwidth!(
+            $config,
+            boomerang_spending_bandwidth,
+            $curve_name
+        );
 
         criterion_group!(
             benches,
@@ -767,6 +1223,7 @@ macro_rules! bench_tboomerang_make_all {
             boomerang_issuance_m5,
             boomerang_collection_m1,
             boomerang_collection_m2,
+            boomerang_collection_m2_verify,
             boomerang_collection_m3,
             boomerang_collection_m4,
             boomerang_collection_m5,
@@ -781,7 +1238,142 @@ macro_rules! bench_tboomerang_make_all {
             boomerang_rewards_proof_verify,
             boomerang_sub_proof,
             boomerang_sub_proof_verify,
+            boomerang_issuance_bandwidth,
+            boomerang_collection_bandwidth,
+            boomerang_spending_bandwidth,
         );
+    };
+}
+
+#[macro_export]
+macro_rules! bench_tboomerang_make_all {
+    ($config: ty, $curve_name: tt) => {
+        $crate::bench_tboomerang_import_everything!();
+        $crate::bench_tboomerang_define_all!($config, $curve_name);
         criterion_main!(benches);
     };
 }
+
+#[macro_export]
+macro_rules! bench_tboomerang_profile {
+    ($config: ty, $profile_name: ident) => {
+        pub fn $profile_name(runs: u32) -> $crate::bench_tboomerang::CurveProfile {
+            // Time and measure every message of a full run of the boomerang protocols.
+            let mut rng = SeededRng::from_env();
+            let mut profile = $crate::bench_tboomerang::CurveProfile::default();
+            let v = <$config as CurveConfig>::ScalarField::one();
+
+            for _ in 0..runs {
+                let kp = CBKP::<$config>::generate(&mut rng);
+                let skp = SBKP::generate(&mut rng);
+
+                let mut state = IBCM::default();
+                let mut s_state = IBSM::default();
+                let start = Instant::now();
+                let m1 = IBCM::generate_issuance_m1(&kp, &mut state, &mut rng);
+                profile.record("issuance m1", start.elapsed(), m1.compressed_size());
+                let start = Instant::now();
+                let m2 = IBSM::generate_issuance_m2(&m1, &skp, &mut s_state, &mut rng);
+                profile.record("issuance m2", start.elapsed(), m2.compressed_size());
+                let start = Instant::now();
+                let m3 = IBCM::generate_issuance_m3(&m2, &mut state, &mut rng);
+                profile.record("issuance m3", start.elapsed(), m3.compressed_size());
+                let start = Instant::now();
+                let m4 = IBSM::generate_issuance_m4(&m3, &mut s_state, &skp);
+                profile.record("issuance m4", start.elapsed(), m4.compressed_size());
+                let i_state = IBCM::populate_state(&m4, &mut state, &skp, &kp);
+
+                let mut s_col_state = CBSM::default();
+                let mut c_col_state = CBCM::default();
+                let start = Instant::now();
+                let c_m1 = CBSM::<$config>::generate_collection_m1(&mut rng, &mut s_col_state);
+                profile.record("collection m1", start.elapsed(), c_m1.compressed_size());
+                let start = Instant::now();
+                let c_m2 =
+                    CBCM::generate_collection_m2(&mut rng, &i_state, &c_m1, &mut c_col_state, &skp);
+                profile.record("collection m2", start.elapsed(), c_m2.compressed_size());
+                let start = Instant::now();
+                let c_m3 = CBSM::generate_collection_m3(&mut rng, &c_m2, &mut s_col_state, &skp, v)
+                    .unwrap();
+                profile.record("collection m3", start.elapsed(), c_m3.compressed_size());
+                let start = Instant::now();
+                let c_m4 = CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &c_m3);
+                profile.record("collection m4", start.elapsed(), c_m4.compressed_size());
+                let start = Instant::now();
+                let c_m5 = CBSM::generate_collection_m5(&c_m4, &mut s_col_state, &skp);
+                profile.record("collection m5", start.elapsed(), c_m5.compressed_size());
+                let c_state = CBCM::populate_state(&mut c_col_state, &c_m5, &skp, &kp);
+
+                let mut s_spend_state = SVBS::default();
+                let mut c_spend_state = SVBC::default();
+                let start = Instant::now();
+                let s_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+                profile.record("spend-verify m1", start.elapsed(), s_m1.compressed_size());
+                let start = Instant::now();
+                let s_m2 = SVBC::generate_spendverify_m2(
+                    &mut rng,
+                    &c_state,
+                    &mut c_spend_state,
+                    &s_m1,
+                    &skp,
+                    &[v],
+                );
+                profile.record("spend-verify m2", start.elapsed(), s_m2.compressed_size());
+                let start = Instant::now();
+                let s_m3 = SVBS::generate_spendverify_m3(
+                    &mut rng,
+                    &s_m2,
+                    &mut s_spend_state,
+                    &skp,
+                    vec![<$config as CurveConfig>::ScalarField::from(2)],
+                )
+                .unwrap();
+                profile.record("spend-verify m3", start.elapsed(), s_m3.compressed_size());
+                let start = Instant::now();
+                let s_m4 = SVBC::generate_spendverify_m4(
+                    &mut rng,
+                    &mut c_spend_state,
+                    &s_m3,
+                    &skp.policy_id(&[<$config as CurveConfig>::ScalarField::from(2)]),
+                )
+                .unwrap();
+                profile.record("spend-verify m4", start.elapsed(), s_m4.compressed_size());
+                let start = Instant::now();
+                let s_m5 = SVBS::generate_spendverify_m5(&s_m4, &mut s_spend_state, &skp);
+                profile.record("spend-verify m5", start.elapsed(), s_m5.compressed_size());
+            }
+
+            profile
+        }
+    };
+}
+
+/// bench_all_curves. This macro instantiates the full boomerang bench group once for every
+/// given curve, each in a module of its own, and ends with a bench that prints a comparison
+/// table of the mean time and the compressed size of every message on every curve.
+/// # Arguments
+/// * `runs` - the number of full protocol runs that the comparison table is averaged over.
+/// * `module` - the name of the module that holds the benches of a curve.
+/// * `config` - the `BoomerangConfig` of a curve.
+/// * `curve_name` - the name of a curve, as used in the bench names.
+#[macro_export]
+macro_rules! bench_all_curves {
+    ($runs: expr; $(($module: ident, $config: ty, $curve_name: tt)),+ $(,)?) => {
+        $(
+            mod $module {
+                $crate::bench_tboomerang_import_everything!();
+                use std::time::Instant;
+                $crate::bench_tboomerang_define_all!($config, $curve_name);
+                $crate::bench_tboomerang_profile!($config, profile);
+            }
+        )+
+
+        pub fn boomerang_curve_comparison(_c: &mut criterion::Criterion) {
+            let profiles = [$(($curve_name, $module::profile($runs))),+];
+            println!("{}", $crate::bench_tboomerang::comparison_table(&profiles));
+        }
+
+        criterion::criterion_group!(comparison, boomerang_curve_comparison);
+        criterion::criterion_main!($($module::benches,)+ comparison);
+    };
+}
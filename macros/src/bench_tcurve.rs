@@ -279,6 +279,81 @@ macro_rules! bench_tcurve_mul_verifier_time {
     };
 }
 
+#[macro_export]
+macro_rules! bench_tcurve_verifier_context_time {
+    ($config: ty, $bench_name: ident, $curve_name: tt, $OtherProjectiveType: ty) => {
+        pub fn $bench_name(c: &mut Criterion) {
+            type SF = <$config as CurveConfig>::ScalarField;
+            type PC = PedersenComm<$config>;
+
+            let a = SF::rand(&mut OsRng);
+            let x = SF::rand(&mut OsRng);
+            let z = a * x;
+
+            let c1: PC = PC::new(a, &mut OsRng);
+            let c2: PC = PC::new(x, &mut OsRng);
+            let c3: PC = PC::new(z, &mut OsRng);
+            let c4: PC = PC::new(a, &mut OsRng);
+
+            let mut transcript = Transcript::new(b"PedersenOpen");
+            let op = OP::create(&mut transcript, &mut OsRng, &a, &c1);
+            let mut transcript = Transcript::new(b"PedersenEq");
+            let ep = EP::create(&mut transcript, &mut OsRng, &c1, &c4);
+            let mut transcript = Transcript::new(b"PedersenMul");
+            let mp = MP::create(&mut transcript, &mut OsRng, &a, &x, &c1, &c2, &c3);
+
+            c.bench_function(concat!($curve_name, " verifier context build time"), |b| {
+                b.iter(|| VerifierContext::<$config>::new());
+            });
+
+            // The context is built once and shared by every verification below.
+            let ctx = VerifierContext::<$config>::new();
+
+            c.bench_function(
+                concat!(
+                    $curve_name,
+                    " opening proof verifier time (verifier context)"
+                ),
+                |b| {
+                    b.iter(|| {
+                        let mut transcript_v = Transcript::new(b"PedersenOpen");
+                        op.verify_with_context(&mut transcript_v, &c1.comm, &ctx);
+                    });
+                },
+            );
+
+            c.bench_function(
+                concat!(
+                    $curve_name,
+                    " equality proof verifier time (verifier context)"
+                ),
+                |b| {
+                    b.iter(|| {
+                        let mut transcript_v = Transcript::new(b"PedersenEq");
+                        ep.verify_with_context(&mut transcript_v, &c1.comm, &c4.comm, &ctx);
+                    });
+                },
+            );
+
+            c.bench_function(
+                concat!($curve_name, " mul proof verifier time (verifier context)"),
+                |b| {
+                    b.iter(|| {
+                        let mut transcript_v = Transcript::new(b"PedersenMul");
+                        mp.verify_with_context(
+                            &mut transcript_v,
+                            &c1.comm,
+                            &c2.comm,
+                            &c3.comm,
+                            &ctx,
+                        );
+                    });
+                },
+            );
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! bench_tcurve_add_mul_prover_time {
     ($config: ty, $bench_name: ident, $curve_name: tt, $OtherProjectiveType: ty) => {
@@ -436,7 +511,7 @@ macro_rules! bench_tcurve_import_everything {
             issuance_protocol::IssuanceProofMulti as IPM, mul_protocol::MulProof as MP,
             non_zero_protocol::NonZeroProof as NZP, opening_protocol::OpeningProof as OP,
             opening_protocol::OpeningProofMulti as OPM, pedersen_config::PedersenComm,
-            pedersen_config::PedersenConfig,
+            pedersen_config::PedersenConfig, verifier_context::VerifierContext,
         };
         use rand_core::OsRng;
         use sha2::{Digest, Sha512};
@@ -508,6 +583,12 @@ macro_rules! bench_tcurve_make_all {
             $curve_name,
             $OtherProjectiveType
         );
+        $crate::bench_tcurve_verifier_context_time!(
+            $config,
+            verifier_context_verification,
+            $curve_name,
+            $OtherProjectiveType
+        );
         $crate::bench_tcurve_add_mul_prover_time!(
             $config,
             add_mul_proof_creation,
@@ -545,6 +626,7 @@ macro_rules! bench_tcurve_make_all {
             equality_proof_verification,
             mul_proof_creation,
             mul_proof_verification,
+            verifier_context_verification,
             add_mul_proof_creation,
             add_mul_proof_verification,
         );
@@ -0,0 +1,60 @@
+#[macro_export]
+macro_rules! bench_tglv_mul_time {
+    ($config: ty, $bench_name: ident, $curve_name: tt) => {
+        pub fn $bench_name(c: &mut Criterion) {
+            let p = sw::Projective::<$config>::rand(&mut OsRng);
+            let k = <$config as CurveConfig>::ScalarField::rand(&mut OsRng).into_bigint();
+
+            c.bench_function(concat!($curve_name, " glv mul time"), |b| {
+                b.iter(|| <$config>::glv_mul(black_box(&p), black_box(&k.0)))
+            });
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! bench_tglv_double_and_add_time {
+    ($config: ty, $bench_name: ident, $curve_name: tt) => {
+        pub fn $bench_name(c: &mut Criterion) {
+            let p = sw::Projective::<$config>::rand(&mut OsRng);
+            let k = <$config as CurveConfig>::ScalarField::rand(&mut OsRng).into_bigint();
+
+            // This is the default SWCurveConfig::mul_projective that glv_mul replaces.
+            c.bench_function(concat!($curve_name, " double-and-add mul time"), |b| {
+                b.iter(|| {
+                    let mut res = sw::Projective::<$config>::zero();
+                    for bit in BitIteratorBE::without_leading_zeros(black_box(&k.0)) {
+                        res.double_in_place();
+                        if bit {
+                            res += black_box(&p);
+                        }
+                    }
+                    res
+                })
+            });
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! bench_tglv_import_everything {
+    () => {
+        use ark_ec::{models::CurveConfig, short_weierstrass as sw, Group};
+        use ark_ff::{BitIteratorBE, PrimeField, Zero};
+        use ark_std::UniformRand;
+        use criterion::{black_box, criterion_group, criterion_main, Criterion};
+        use rand_core::OsRng;
+    };
+}
+
+#[macro_export]
+macro_rules! bench_tglv_make_all {
+    ($config: ty, $curve_name: tt) => {
+        $crate::bench_tglv_import_everything!();
+        $crate::bench_tglv_mul_time!($config, glv_mul, $curve_name);
+        $crate::bench_tglv_double_and_add_time!($config, double_and_add_mul, $curve_name);
+
+        criterion_group!(benches, glv_mul, double_and_add_mul);
+        criterion_main!(benches);
+    };
+}
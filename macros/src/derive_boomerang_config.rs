@@ -0,0 +1,96 @@
+/// derive_boomerang_config. This macro derives the full trait stack of a new T-curve, i.e
+/// `PedersenConfig`, `ACLConfig` and `BoomerangConfig` (as `derive_conversion!` does), and,
+/// if an `id` is given, `RegisteredCurve`, from the constants of the curve pair alone. The
+/// config must already implement `CurveConfig` and `SWCurveConfig`, and `CurveConfig` and
+/// `sw` must be in scope. The tests of the stack are derived with `test_boomerang_config!`.
+///
+/// The arguments are:
+/// * `config`: the config of the T-curve.
+/// * `scalar_field`: the scalar field of the T-curve, and its `MontConfig`.
+/// * `limbs`: the number of 64-bit limbs of the fields.
+/// * `security`: the security level of the curve, in bits.
+/// * `other_curve`: the config of the original curve.
+/// * `other_base_field`: the base field of the original curve, and its `MontConfig`.
+/// * `other_scalar_field`: the scalar field of the original curve, and its `MontConfig`.
+/// * `generator2`: the constants that hold the co-ordinates of the second generator of the
+///   T-curve.
+/// * `other_generator2`: the co-ordinates of the second generator of the original curve, as
+///   decimal strings.
+/// * `id`: optionally, the `CurveId` variant of the curve.
+///
+/// ```ignore
+/// derive_boomerang_config!(
+///     config: Config,
+///     scalar_field: (Fr, FrConfig),
+///     limbs: 4,
+///     security: 128,
+///     other_curve: secp256r1conf,
+///     other_base_field: (secp256r1Fq, secp256FqConfig),
+///     other_scalar_field: (secp256r1Fr, secp256FrConfig),
+///     generator2: (G_GENERATOR_X2, G_GENERATOR_Y2),
+///     other_generator2: ("5", "3146..."),
+///     id: T256,
+/// );
+/// ```
+#[macro_export]
+macro_rules! derive_boomerang_config {
+    (
+        config: $config: ty,
+        scalar_field: ($fr: ty, $fr_config: ty),
+        limbs: $dim: expr,
+        security: $sec_param: expr,
+        other_curve: $OtherCurve: ty,
+        other_base_field: ($other_q: ty, $other_q_conf: ty),
+        other_scalar_field: ($other_r: ty, $other_r_conf: ty),
+        generator2: ($G2_X: ident, $G2_Y: ident),
+        other_generator2: ($GSX: expr, $GSY: expr),
+        id: $id: ident $(,)?
+    ) => {
+        $crate::derive_boomerang_config!(
+            config: $config,
+            scalar_field: ($fr, $fr_config),
+            limbs: $dim,
+            security: $sec_param,
+            other_curve: $OtherCurve,
+            other_base_field: ($other_q, $other_q_conf),
+            other_scalar_field: ($other_r, $other_r_conf),
+            generator2: ($G2_X, $G2_Y),
+            other_generator2: ($GSX, $GSY),
+        );
+
+        impl boomerang::curves::RegisteredCurve for $config {
+            const ID: boomerang::curves::CurveId = boomerang::curves::CurveId::$id;
+        }
+    };
+    (
+        config: $config: ty,
+        scalar_field: ($fr: ty, $fr_config: ty),
+        limbs: $dim: expr,
+        security: $sec_param: expr,
+        other_curve: $OtherCurve: ty,
+        other_base_field: ($other_q: ty, $other_q_conf: ty),
+        other_scalar_field: ($other_r: ty, $other_r_conf: ty),
+        generator2: ($G2_X: ident, $G2_Y: ident),
+        other_generator2: ($GSX: expr, $GSY: expr) $(,)?
+    ) => {
+        $crate::derive_conversion!(
+            $config,
+            $dim,
+            $sec_param,
+            $OtherCurve,
+            $G2_X,
+            $G2_Y,
+            $fr,
+            $fr_config,
+            $other_q,
+            $other_r,
+            $other_q_conf,
+            $other_r_conf,
+            sw::Affine<$config>,
+            $GSX,
+            $GSY,
+            $config,
+            $config
+        );
+    };
+}
@@ -1,11 +1,10 @@
 #[macro_export]
 #[doc(hidden)]
 macro_rules! __derive_conversion {
-    ($config: ty, $dim: expr, $sec_param: expr, $OtherCurve: ty, $G2_X: ident, $G2_Y: ident, $fr: ty, $fr_config: ty, $other_q: ty, $other_r: ty, $other_q_conf: ty, $other_r_conf: ty, $affine: ty, $GSX: expr, $GSY: expr, $aclconfig: ty, $boomerangconfig: ty) => {
+    ($config: ty, $dim: expr, $sec_param: expr, $OtherCurve: ty, $G2_X: ident, $G2_Y: ident, $fr: ty, $fr_config: ty, $other_q: ty, $other_r: ty, $other_q_conf: ty, $other_r_conf: ty, $affine: ty, $GSX: expr, $GSY: expr, $aclconfig: ty, $boomerangconfig: ty, $hash: ty) => {
         // Define the conversion functions for this particular
         // mapping.
         type OtherBaseField = <$OtherCurve as CurveConfig>::BaseField;
-        type OtherScalarField = <$OtherCurve as CurveConfig>::ScalarField;
 
         macro_rules! StrToOtherFq {
             ($c0:expr) => {{
@@ -21,74 +20,6 @@ macro_rules! __derive_conversion {
             }};
         }
 
-        struct FrStruct($fr);
-        impl FrStruct {
-            pub fn new(x: $fr) -> FrStruct {
-                FrStruct(x)
-            }
-
-            pub fn as_fr(&self) -> $fr {
-                self.0
-            }
-        }
-
-        impl From<BigInt<$dim>> for FrStruct {
-            fn from(x: BigInt<$dim>) -> Self {
-                let x_t = <$fr_config>::from_bigint(x).unwrap();
-                FrStruct::new(x_t)
-            }
-        }
-
-        impl From<FrStruct> for BigInt<$dim> {
-            fn from(val: FrStruct) -> Self {
-                FrConfig::into_bigint(val.0)
-            }
-        }
-
-        struct OtherBase(OtherBaseField);
-        impl OtherBase {
-            pub fn new(x: $other_q) -> OtherBase {
-                OtherBase(x)
-            }
-        }
-
-        impl From<OtherBase> for BigInt<$dim> {
-            fn from(x: OtherBase) -> Self {
-                <$other_q_conf>::into_bigint(x.0)
-            }
-        }
-
-        impl From<BigInt<$dim>> for OtherBase {
-            fn from(x: BigInt<$dim>) -> OtherBase {
-                let x_t = <$other_q_conf>::from_bigint(x).unwrap();
-                OtherBase::new(x_t)
-            }
-        }
-
-        struct OtherScalar(OtherScalarField);
-        impl OtherScalar {
-            pub fn new(x: $other_r) -> OtherScalar {
-                OtherScalar(x)
-            }
-
-            pub fn as_fr(&self) -> OtherScalarField {
-                self.0
-            }
-        }
-
-        impl From<OtherScalar> for BigInt<$dim> {
-            fn from(x: OtherScalar) -> Self {
-                <$other_r_conf>::into_bigint(x.0)
-            }
-        }
-
-        impl From<BigInt<$dim>> for OtherScalar {
-            fn from(x: BigInt<$dim>) -> OtherScalar {
-                let x_t = <$other_r_conf>::from_bigint(x).unwrap();
-                OtherScalar::new(x_t)
-            }
-        }
-
         // Define the Pedersen commitment type.
         impl PedersenConfig for $config {
             type OCurve = $OtherCurve;
@@ -98,29 +29,26 @@ macro_rules! __derive_conversion {
             /// GENERATOR2 = (G2_X, G2_Y)
             const GENERATOR2: $affine = <$affine>::new_unchecked($G2_X, $G2_Y);
 
+            fn from_oc(x: $other_r) -> <$config as CurveConfig>::ScalarField {
+                pedersen::ct::convert::<$other_r_conf, $fr_config, $dim>(x)
+            }
+
             fn from_ob_to_sf(x: OtherBaseField) -> <$config as CurveConfig>::ScalarField {
-                let x_t: BigInt<$dim> = x.into();
-                let x_v: FrStruct = FrStruct::from(x_t);
-                x_v.as_fr()
+                pedersen::ct::convert::<$other_q_conf, $fr_config, $dim>(x)
             }
 
             fn from_ob_to_os(x: OtherBaseField) -> <Self::OCurve as CurveConfig>::ScalarField {
-                let x_t: BigInt<$dim> = x.into();
-                OtherScalar::from(x_t).as_fr()
+                pedersen::ct::convert::<$other_q_conf, $other_r_conf, $dim>(x)
             }
 
-            fn from_os_to_sf(x: OtherScalarField) -> <$config as CurveConfig>::ScalarField {
-                let x_t: BigInt<$dim> = x.into();
-                let x_v: FrStruct = FrStruct::from(x_t);
-                x_v.as_fr()
+            fn from_os_to_sf(x: $other_r) -> <$config as CurveConfig>::ScalarField {
+                pedersen::ct::convert::<$other_r_conf, $fr_config, $dim>(x)
             }
 
             fn from_bf_to_sf(
                 x: <Self as CurveConfig>::BaseField,
             ) -> <Self as CurveConfig>::ScalarField {
-                let x_t: BigInt<$dim> = x.into();
-                let x_v: FrStruct = FrStruct::from(x_t);
-                x_v.as_fr()
+                pedersen::ct::convert(x)
             }
 
             fn make_single_bit_challenge(v: u8) -> <$config as CurveConfig>::ScalarField {
@@ -132,9 +60,13 @@ macro_rules! __derive_conversion {
             }
 
             fn from_u64_to_sf(x: u64) -> <Self as CurveConfig>::ScalarField {
-                let x_t = BigInt::<$dim>::from(x);
-                let x_v = FrStruct::from(x_t);
-                x_v.as_fr()
+                pedersen::ct::from_bigint_mod_order::<$fr_config, $dim>(&BigInt::from(x))
+            }
+
+            fn make_blind<T: ark_std::rand::RngCore + ark_std::rand::CryptoRng>(
+                rng: &mut T,
+            ) -> <Self as CurveConfig>::ScalarField {
+                pedersen::ct::rand::<$fr_config, T, $dim>(rng)
             }
 
             const OGENERATOR2: sw::Affine<Self::OCurve> =
@@ -150,6 +82,8 @@ macro_rules! __derive_conversion {
 
             const SECPARAM: usize = $sec_param;
 
+            type Hash = $hash;
+
             /// GENERATOR2 = (G2_X, G2_Y)
             const GENERATOR2: $affine = <$affine>::new_unchecked($G2_X, $G2_Y);
         }
@@ -164,6 +98,28 @@ macro_rules! __derive_conversion {
 #[macro_export]
 macro_rules! derive_conversion {
     ($config: ty, $dim: expr, $sec_param: expr, $OtherCurve: ty, $G2_X: ident, $G2_Y: ident, $fr: ty, $fr_config: ty, $other_q: ty, $other_r: ty, $other_q_conf: ty, $other_r_conf: ty, $affine: ty, $GSX: expr, $GSY: expr, $aclconfig: ty, $boomerangconfig: ty) => {
+        $crate::derive_conversion!(
+            $config,
+            $dim,
+            $sec_param,
+            $OtherCurve,
+            $G2_X,
+            $G2_Y,
+            $fr,
+            $fr_config,
+            $other_q,
+            $other_r,
+            $other_q_conf,
+            $other_r_conf,
+            $affine,
+            $GSX,
+            $GSY,
+            $aclconfig,
+            $boomerangconfig,
+            acl::config::DefaultHash
+        );
+    };
+    ($config: ty, $dim: expr, $sec_param: expr, $OtherCurve: ty, $G2_X: ident, $G2_Y: ident, $fr: ty, $fr_config: ty, $other_q: ty, $other_r: ty, $other_q_conf: ty, $other_r_conf: ty, $affine: ty, $GSX: expr, $GSY: expr, $aclconfig: ty, $boomerangconfig: ty, $hash: ty) => {
         use acl::config::ACLConfig;
         use ark_ff::BigInt;
         use ark_ff::{Field, MontConfig, MontFp};
@@ -188,7 +144,8 @@ macro_rules! derive_conversion {
             $GSX,
             $GSY,
             $aclconfig,
-            $boomerangconfig
+            $boomerangconfig,
+            $hash
         );
     };
 }
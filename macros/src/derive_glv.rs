@@ -0,0 +1,164 @@
+/// glv_limbs. This function parses a signed decimal constant into an
+/// (is_negative, little-endian limbs) pair. It is meant to be evaluated at
+/// compile time by `derive_glv`, so the constants are never parsed at runtime.
+/// # Arguments
+/// * `s` - the decimal string, optionally prefixed with `-`.
+pub const fn glv_limbs(s: &str) -> (bool, [u64; 4]) {
+    let bytes = s.as_bytes();
+    let neg = !bytes.is_empty() && bytes[0] == b'-';
+    let mut limbs = [0u64; 4];
+    let mut i = if neg { 1 } else { 0 };
+    while i < bytes.len() {
+        assert!(bytes[i].is_ascii_digit(), "GLV constants must be decimal");
+        let mut carry = (bytes[i] - b'0') as u128;
+        let mut j = 0;
+        while j < limbs.len() {
+            let t = limbs[j] as u128 * 10 + carry;
+            limbs[j] = t as u64;
+            carry = t >> 64;
+            j += 1;
+        }
+        assert!(carry == 0, "GLV constants must fit in 256 bits");
+        i += 1;
+    }
+    (neg, limbs)
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __derive_glv {
+    ($config: ty, $beta: tt, $lambda: tt, $a1: tt, $b1: tt, $a2: tt, $b2: tt) => {
+        // GLV acceleration for curves of the form y^2 = x^3 + b with a cube root
+        // of unity in both fields. The map (x, y) -> (BETA * x, y) acts on the
+        // curve as multiplication by LAMBDA, so a 256-bit scalar multiplication
+        // can be split into two ~128-bit ones that share their doublings.
+        impl $config {
+            /// GLV_BETA: a cube root of unity in the base field.
+            pub const GLV_BETA: <$config as CurveConfig>::BaseField = MontFp!($beta);
+
+            /// GLV_LAMBDA: the cube root of unity in the scalar field that
+            /// corresponds to GLV_BETA.
+            pub const GLV_LAMBDA: <$config as CurveConfig>::ScalarField = MontFp!($lambda);
+
+            // The reduced lattice basis, parsed once at compile time.
+            const GLV_A1: (bool, [u64; 4]) = $crate::derive_glv::glv_limbs($a1);
+            const GLV_B1: (bool, [u64; 4]) = $crate::derive_glv::glv_limbs($b1);
+            const GLV_A2: (bool, [u64; 4]) = $crate::derive_glv::glv_limbs($a2);
+            const GLV_B2: (bool, [u64; 4]) = $crate::derive_glv::glv_limbs($b2);
+
+            fn glv_bit(limbs: &[u64], i: usize) -> bool {
+                limbs
+                    .get(i / 64)
+                    .map_or(false, |l| (l >> (i % 64)) & 1 == 1)
+            }
+
+            fn glv_num_bits(limbs: &[u64]) -> usize {
+                limbs
+                    .iter()
+                    .rposition(|l| *l != 0)
+                    .map_or(0, |i| i * 64 + 64 - limbs[i].leading_zeros() as usize)
+            }
+
+            /// glv_endomorphism. This function maps `p` to LAMBDA * p.
+            /// # Arguments
+            /// * `p` - the point to map.
+            pub fn glv_endomorphism(p: &sw::Projective<$config>) -> sw::Projective<$config> {
+                // Jacobian coordinates: x = X / Z^2, so scaling X scales x.
+                let mut res = *p;
+                res.x *= Self::GLV_BETA;
+                res
+            }
+
+            /// glv_decompose. This function splits `scalar` into (k1, k2) such
+            /// that scalar = k1 + LAMBDA * k2 mod r, where both halves are roughly
+            /// half the size of r. Each half is returned as a (is_negative, limbs) pair.
+            /// # Arguments
+            /// * `scalar` - the little-endian limbs of the scalar.
+            pub fn glv_decompose(scalar: &[u64]) -> ((bool, Vec<u64>), (bool, Vec<u64>)) {
+                use num_bigint::{BigInt, BigUint, Sign};
+
+                let to_bytes = |limbs: &[u64]| -> Vec<u8> {
+                    limbs.iter().flat_map(|l| l.to_le_bytes()).collect()
+                };
+
+                let modulus = <<$config as CurveConfig>::ScalarField as PrimeField>::MODULUS;
+                let r =
+                    BigInt::from_biguint(Sign::Plus, BigUint::from_bytes_le(&to_bytes(&modulus.0)));
+                let k = BigInt::from_biguint(Sign::Plus, BigUint::from_bytes_le(&to_bytes(scalar)))
+                    % &r;
+
+                let to_int = |(neg, limbs): (bool, [u64; 4])| -> BigInt {
+                    let x =
+                        BigInt::from_biguint(Sign::Plus, BigUint::from_bytes_le(&to_bytes(&limbs)));
+                    if neg {
+                        -x
+                    } else {
+                        x
+                    }
+                };
+
+                let a1 = to_int(Self::GLV_A1);
+                let b1 = to_int(Self::GLV_B1);
+                let a2 = to_int(Self::GLV_A2);
+                let b2 = to_int(Self::GLV_B2);
+
+                // The lattice basis is chosen so that b2 > 0 and b1 < 0, so both
+                // rounded quotients below are non-negative.
+                let half_r = &r >> 1;
+                let c1: BigInt = (&b2 * &k + &half_r) / &r;
+                let c2: BigInt = (-&b1 * &k + &half_r) / &r;
+
+                let k1: BigInt = &k - &c1 * &a1 - &c2 * &a2;
+                let k2: BigInt = -(&c1 * &b1) - &c2 * &b2;
+
+                let split = |x: BigInt| -> (bool, Vec<u64>) {
+                    let (sign, limbs) = x.to_u64_digits();
+                    (sign == Sign::Minus, limbs)
+                };
+
+                (split(k1), split(k2))
+            }
+
+            /// glv_mul. This function computes scalar * base using the GLV
+            /// decomposition and a joint double-and-add.
+            /// # Arguments
+            /// * `base` - the point to multiply.
+            /// * `scalar` - the little-endian limbs of the scalar.
+            pub fn glv_mul(
+                base: &sw::Projective<$config>,
+                scalar: &[u64],
+            ) -> sw::Projective<$config> {
+                let ((neg1, k1), (neg2, k2)) = Self::glv_decompose(scalar);
+
+                let p1 = if neg1 { -*base } else { *base };
+                let p2 = Self::glv_endomorphism(base);
+                let p2 = if neg2 { -p2 } else { p2 };
+                let p12 = p1 + p2;
+
+                let bits = Self::glv_num_bits(&k1).max(Self::glv_num_bits(&k2));
+                let mut res = sw::Projective::<$config>::zero();
+                for i in (0..bits).rev() {
+                    res.double_in_place();
+                    match (Self::glv_bit(&k1, i), Self::glv_bit(&k2, i)) {
+                        (true, true) => res += p12,
+                        (true, false) => res += p1,
+                        (false, true) => res += p2,
+                        (false, false) => {}
+                    }
+                }
+
+                res
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! derive_glv {
+    ($config: ty, $beta: tt, $lambda: tt, $a1: tt, $b1: tt, $a2: tt, $b2: tt) => {
+        use ark_ec::Group;
+        use ark_ff::PrimeField;
+
+        $crate::__derive_glv!($config, $beta, $lambda, $a1, $b1, $a2, $b2);
+    };
+}
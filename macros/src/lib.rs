@@ -3,6 +3,15 @@ pub mod test_pedersen;
 pub mod bench_tacl;
 pub mod bench_tboomerang;
 pub mod bench_tcurve;
+pub mod bench_tglv;
+pub mod derive_boomerang_config;
 pub mod derive_conversion;
+pub mod derive_glv;
 pub mod test_acl;
 pub mod test_boomerang;
+pub mod test_boomerang_config;
+pub mod test_curve_pair;
+pub mod test_glv;
+pub mod test_rfc6979;
+pub mod test_vectors;
+pub mod test_zkp_ecdsa;
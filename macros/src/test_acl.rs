@@ -12,6 +12,10 @@ macro_rules! __test_acl {
         type ACLSP = SigProof<$aclconfig>;
         type ACLSPV = SigVerifProof<$aclconfig>;
         type ACLSubVals = SubVals<$aclconfig>;
+        type ACLAKP = AuditorKeyPair<$aclconfig>;
+        type ACLASP = AuditableSigProof<$aclconfig>;
+        type ACLAH = AuxHasher<$aclconfig>;
+        type ACLPC = protocol::Commitment<$aclconfig>;
         type PC = PedersenComm<$config>;
         type SF = <$config as CurveConfig>::ScalarField;
         type OSF = <<$config as PedersenConfig>::OCurve as CurveConfig>::ScalarField;
@@ -78,7 +82,14 @@ macro_rules! __test_acl {
             assert!(m1.a2.is_on_curve());
 
             // Test the second message of the signature scheme.
-            let m2 = ACLCH::challenge(kp.tag_key, kp.verifying_key, &mut OsRng, m1, "message");
+            let m2 = ACLCH::challenge(
+                kp.tag_key,
+                kp.verifying_key,
+                &mut OsRng,
+                m1,
+                b"message",
+                None,
+            );
         }
 
         #[test]
@@ -108,7 +119,14 @@ macro_rules! __test_acl {
             assert!(m1.a1.is_on_curve());
             assert!(m1.a2.is_on_curve());
 
-            let m2 = ACLCH::challenge(kp.tag_key, kp.verifying_key, &mut OsRng, m1, "message");
+            let m2 = ACLCH::challenge(
+                kp.tag_key,
+                kp.verifying_key,
+                &mut OsRng,
+                m1,
+                b"message",
+                None,
+            );
 
             // Test the third message of the signature scheme.
             let m3 = ACLSR::respond(&kp, &m1, &m2);
@@ -141,11 +159,18 @@ macro_rules! __test_acl {
             assert!(m1.a1.is_on_curve());
             assert!(m1.a2.is_on_curve());
 
-            let m2 = ACLCH::challenge(kp.tag_key, kp.verifying_key, &mut OsRng, m1, "message");
+            let m2 = ACLCH::challenge(
+                kp.tag_key,
+                kp.verifying_key,
+                &mut OsRng,
+                m1,
+                b"message",
+                None,
+            );
 
             let m3 = ACLSR::respond(&kp, &m1, &m2);
 
-            let m4 = ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, "message");
+            let m4 = ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, b"message", None);
             assert!(m4.sigma.zeta.is_on_curve());
             assert!(m4.sigma.zeta1.is_on_curve());
         }
@@ -177,18 +202,227 @@ macro_rules! __test_acl {
             assert!(m1.a1.is_on_curve());
             assert!(m1.a2.is_on_curve());
 
-            let m2 = ACLCH::challenge(kp.tag_key, kp.verifying_key, &mut OsRng, m1, "message");
+            let m2 = ACLCH::challenge(
+                kp.tag_key,
+                kp.verifying_key,
+                &mut OsRng,
+                m1,
+                b"message",
+                None,
+            );
 
             let m3 = ACLSR::respond(&kp, &m1, &m2);
 
-            let m4 = ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, "message");
+            let m4 = ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, b"message", None);
             assert!(m4.sigma.zeta.is_on_curve());
             assert!(m4.sigma.zeta1.is_on_curve());
 
-            let check = ACLSV::verify(kp.verifying_key, kp.tag_key, &m4, "message");
+            let check = ACLSV::verify(kp.verifying_key, kp.tag_key, &m4, b"message", None);
             assert!(check == true);
         }
 
+        #[test]
+        fn test_sign_message_context() {
+            // Test that the signature is bound to both the message and the context.
+            let b = SF::rand(&mut OsRng);
+            let c = SF::rand(&mut OsRng);
+            let vals: Vec<SF> = vec![b, c];
+
+            let (c1, _) = PC::new_multi(&vals, &mut OsRng);
+
+            let kp = ACLKP::generate(&mut OsRng);
+            let message: &[u8] = &[0u8, 1, 2, 255];
+            let context: &[u8] = b"context";
+
+            let m1 = ACLSC::commit(&kp, &mut OsRng, c1.comm);
+            let m2 = ACLCH::challenge(
+                kp.tag_key,
+                kp.verifying_key,
+                &mut OsRng,
+                m1,
+                message,
+                Some(context),
+            );
+            let m3 = ACLSR::respond(&kp, &m1, &m2);
+            let m4 = ACLSG::sign(
+                kp.verifying_key,
+                kp.tag_key,
+                &m2,
+                &m3,
+                message,
+                Some(context),
+            );
+
+            assert!(ACLSV::verify(
+                kp.verifying_key,
+                kp.tag_key,
+                &m4,
+                message,
+                Some(context)
+            ));
+            assert!(!ACLSV::verify(
+                kp.verifying_key,
+                kp.tag_key,
+                &m4,
+                message,
+                None
+            ));
+            assert!(!ACLSV::verify(
+                kp.verifying_key,
+                kp.tag_key,
+                &m4,
+                message,
+                Some(b"other")
+            ));
+            assert!(!ACLSV::verify(
+                kp.verifying_key,
+                kp.tag_key,
+                &m4,
+                b"other",
+                Some(context)
+            ));
+        }
+
+        #[test]
+        fn test_sign_aux_data() {
+            // Test that large auxiliary data is streamed into the signature.
+            let (c1, _) = PC::new_multi(&[SF::rand(&mut OsRng)], &mut OsRng);
+            let kp = ACLKP::generate(&mut OsRng);
+            let message: &[u8] = b"message";
+            let context: &[u8] = b"context";
+
+            // The digest does not depend on how the data is chunked.
+            let aux_len = 1 << 20;
+            let mut hasher = ACLAH::new();
+            std::io::copy(&mut std::io::repeat(7).take(aux_len), &mut hasher).unwrap();
+            let aux = hasher.finalize();
+            let mut chunked = ACLAH::new();
+            chunked.update(&[7u8; 1000]);
+            for _ in 0..(aux_len - 1000) / 8 {
+                chunked.update(&[7u8; 8]);
+            }
+            assert_eq!(chunked.finalize(), aux);
+
+            let mut shorter = ACLAH::new();
+            shorter.update(&[7u8; 1000]);
+            let other_aux = shorter.finalize();
+            assert_ne!(other_aux, aux);
+
+            let m1 = ACLSC::commit(&kp, &mut OsRng, c1.comm);
+            let m2 = ACLCH::challenge_with_aux(
+                kp.tag_key,
+                kp.verifying_key,
+                &mut OsRng,
+                m1,
+                message,
+                Some(context),
+                &aux,
+            );
+            let m3 = ACLSR::respond(&kp, &m1, &m2);
+            let m4 = ACLSG::sign_with_aux(
+                kp.verifying_key,
+                kp.tag_key,
+                &m2,
+                &m3,
+                message,
+                Some(context),
+                &aux,
+            );
+
+            assert!(ACLSV::verify_with_aux(
+                kp.verifying_key,
+                kp.tag_key,
+                &m4,
+                message,
+                Some(context),
+                &aux
+            ));
+            assert!(!ACLSV::verify_with_aux(
+                kp.verifying_key,
+                kp.tag_key,
+                &m4,
+                message,
+                Some(context),
+                &other_aux
+            ));
+            assert!(!ACLSV::verify(
+                kp.verifying_key,
+                kp.tag_key,
+                &m4,
+                message,
+                Some(context)
+            ));
+        }
+
+        #[test]
+        fn test_acl_protocol() {
+            // Test that the explicit steps produce a valid signature, and reject bad messages.
+            let (c1, _) = PC::new_multi(&[SF::rand(&mut OsRng)], &mut OsRng);
+            let kp = ACLKP::generate(&mut OsRng);
+            let message: &[u8] = b"survey";
+            let context: &[u8] = b"context";
+
+            let (signer, commitment) = protocol::commit(&kp, &mut OsRng, c1.comm);
+
+            // The messages survive a round trip.
+            let mut bytes = Vec::new();
+            commitment.serialize_compressed(&mut bytes).unwrap();
+            let commitment = ACLPC::deserialize_compressed(&bytes[..]).unwrap();
+
+            let (user, challenge) = protocol::challenge(
+                kp.verifying_key,
+                kp.tag_key,
+                &mut OsRng,
+                &commitment,
+                message,
+                Some(context),
+            )
+            .unwrap();
+            let response = protocol::respond(&kp, signer, &challenge);
+            let sig = protocol::unblind(user, &response).unwrap();
+
+            assert!(ACLSV::verify(
+                kp.verifying_key,
+                kp.tag_key,
+                &sig,
+                message,
+                Some(context)
+            ));
+
+            // A tampered response is rejected.
+            let (signer, commitment) = protocol::commit(&kp, &mut OsRng, c1.comm);
+            let (user, challenge) = protocol::challenge(
+                kp.verifying_key,
+                kp.tag_key,
+                &mut OsRng,
+                &commitment,
+                message,
+                None,
+            )
+            .unwrap();
+            let mut response = protocol::respond(&kp, signer, &challenge);
+            response.r += SF::from(1u64);
+            assert_eq!(
+                protocol::unblind(user, &response).err(),
+                Some(ProtocolError::InvalidResponse)
+            );
+
+            // A commitment with a zero rand is rejected.
+            let (_, mut commitment) = protocol::commit(&kp, &mut OsRng, c1.comm);
+            commitment.rand = SF::from(0u64);
+            assert!(matches!(
+                protocol::challenge(
+                    kp.verifying_key,
+                    kp.tag_key,
+                    &mut OsRng,
+                    &commitment,
+                    message,
+                    None,
+                ),
+                Err(ProtocolError::InvalidCommitment)
+            ));
+        }
+
         #[test]
         fn test_sign_proof() {
             // Test that creating multi commitments goes through.
@@ -216,30 +450,40 @@ macro_rules! __test_acl {
             assert!(m1.a1.is_on_curve());
             assert!(m1.a2.is_on_curve());
 
-            let m2 = ACLCH::challenge(kp.tag_key, kp.verifying_key, &mut OsRng, m1, "message");
+            let m2 = ACLCH::challenge(
+                kp.tag_key,
+                kp.verifying_key,
+                &mut OsRng,
+                m1,
+                b"message",
+                None,
+            );
 
             let m3 = ACLSR::respond(&kp, &m1, &m2);
 
-            let m4 = ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, "message");
+            let m4 = ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, b"message", None);
             assert!(m4.sigma.zeta.is_on_curve());
             assert!(m4.sigma.zeta1.is_on_curve());
 
-            let check = ACLSV::verify(kp.verifying_key, kp.tag_key, &m4, "message");
+            let check = ACLSV::verify(kp.verifying_key, kp.tag_key, &m4, b"message", None);
             assert!(check == true);
 
-            let proof = ACLSP::prove(&mut OsRng, kp.tag_key, &m4, &vals, &gens.generators, c1.r);
+            let proof = ACLSP::prove(
+                &mut OsRng,
+                kp.tag_key,
+                &m4,
+                &vals,
+                &gens.generators,
+                c1.r,
+                None,
+            );
 
             assert!(proof.b_gamma.is_on_curve());
-            assert!(proof.pi1.t1.is_on_curve());
-            assert!(proof.pi1.t2.is_on_curve());
-            assert!(proof.pi2.t3.is_on_curve());
+            assert!(proof.val.is_on_curve());
             for h in proof.h_vec.iter() {
                 assert!(h.is_on_curve());
             }
-            for pi in proof.pi3.iter() {
-                assert!(pi.t1.is_on_curve());
-                assert!(pi.t2.is_on_curve());
-            }
+            assert!(proof.pi3.len() == vals.len() - 1);
         }
 
         #[test]
@@ -269,33 +513,219 @@ macro_rules! __test_acl {
             assert!(m1.a1.is_on_curve());
             assert!(m1.a2.is_on_curve());
 
-            let m2 = ACLCH::challenge(kp.tag_key, kp.verifying_key, &mut OsRng, m1, "message");
+            let m2 = ACLCH::challenge(
+                kp.tag_key,
+                kp.verifying_key,
+                &mut OsRng,
+                m1,
+                b"message",
+                None,
+            );
 
             let m3 = ACLSR::respond(&kp, &m1, &m2);
 
-            let m4 = ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, "message");
+            let m4 = ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, b"message", None);
             assert!(m4.sigma.zeta.is_on_curve());
             assert!(m4.sigma.zeta1.is_on_curve());
 
-            let check = ACLSV::verify(kp.verifying_key, kp.tag_key, &m4, "message");
+            let check = ACLSV::verify(kp.verifying_key, kp.tag_key, &m4, b"message", None);
             assert!(check == true);
 
-            let proof = ACLSP::prove(&mut OsRng, kp.tag_key, &m4, &vals, &gens.generators, c1.r);
+            let proof = ACLSP::prove(
+                &mut OsRng,
+                kp.tag_key,
+                &m4,
+                &vals,
+                &gens.generators,
+                c1.r,
+                None,
+            );
 
             assert!(proof.b_gamma.is_on_curve());
-            assert!(proof.pi1.t1.is_on_curve());
-            assert!(proof.pi1.t2.is_on_curve());
-            assert!(proof.pi2.t3.is_on_curve());
+            assert!(proof.val.is_on_curve());
             for h in proof.h_vec.iter() {
                 assert!(h.is_on_curve());
             }
-            for pi in proof.pi3.iter() {
-                assert!(pi.t1.is_on_curve());
-                assert!(pi.t2.is_on_curve());
-            }
+            assert!(proof.pi3.len() == vals.len() - 1);
 
-            let check = ACLSPV::verify(&proof, kp.tag_key, &m4, &gens.generators);
+            let check = ACLSPV::verify(&proof, kp.tag_key, &m4, &gens.generators, None);
             assert!(check == true);
+
+            // The proof only carries the challenges and responses of its sub-proofs.
+            let points = 2 + proof.h_vec.len();
+            let scalars = 2 + 3 + 2 * proof.pi3.len();
+            let lens = 2 * 8;
+            assert!(
+                proof.compressed_size()
+                    == points * proof.b_gamma.compressed_size()
+                        + scalars * SF::ONE.compressed_size()
+                        + lens
+            );
+
+            // Tampering with any of the sub-proofs, including the last of `pi3`, is detected.
+            let mut bad_proof = proof.clone();
+            bad_proof.pi1.ch += SF::ONE;
+            assert!(!ACLSPV::verify(
+                &bad_proof,
+                kp.tag_key,
+                &m4,
+                &gens.generators,
+                None
+            ));
+
+            let mut bad_proof = proof.clone();
+            bad_proof.pi3.last_mut().unwrap().a1 += SF::ONE;
+            assert!(!ACLSPV::verify(
+                &bad_proof,
+                kp.tag_key,
+                &m4,
+                &gens.generators,
+                None
+            ));
+
+            let mut bad_proof = proof.clone();
+            bad_proof.pi2.a4 += SF::ONE;
+            assert!(!ACLSPV::verify(
+                &bad_proof,
+                kp.tag_key,
+                &m4,
+                &gens.generators,
+                None
+            ));
+
+            let mut bad_proof = proof.clone();
+            bad_proof.h_vec.pop();
+            assert!(!ACLSPV::verify(
+                &bad_proof,
+                kp.tag_key,
+                &m4,
+                &gens.generators,
+                None
+            ));
+        }
+
+        #[test]
+        fn test_sign_proof_escrow() {
+            // Test that the issuance tag escrowed by an auditable proof can be opened by
+            // the auditor, and that the proof is bound to the auditor's key.
+            let vals: Vec<SF> = (0..3).map(|_| SF::rand(&mut OsRng)).collect();
+            let (c1, gens) = PC::new_multi(&vals, &mut OsRng);
+
+            let kp = ACLKP::generate(&mut OsRng);
+            let m1 = ACLSC::commit(&kp, &mut OsRng, c1.comm);
+            let m2 = ACLCH::challenge(
+                kp.tag_key,
+                kp.verifying_key,
+                &mut OsRng,
+                m1,
+                b"message",
+                None,
+            );
+            let m3 = ACLSR::respond(&kp, &m1, &m2);
+            let m4 = ACLSG::sign(kp.verifying_key, kp.tag_key, &m2, &m3, b"message", None);
+
+            let auditor = ACLAKP::generate(&mut OsRng);
+            let proof = ACLASP::prove(
+                &mut OsRng,
+                kp.tag_key,
+                &m4,
+                &vals,
+                &gens.generators,
+                c1.r,
+                auditor.public_key(),
+                Some(b"ctx"),
+            );
+
+            assert!(proof.verify(
+                kp.tag_key,
+                &m4,
+                &gens.generators,
+                auditor.public_key(),
+                Some(b"ctx")
+            ));
+
+            // The auditor recovers the commitment that the signer saw during issuance.
+            let z1 =
+                (<$aclconfig as SWCurveConfig>::GENERATOR.mul(m1.rand) + c1.comm).into_affine();
+            assert_eq!(auditor.open(&proof.escrow), z1);
+
+            // The proof fails against another auditor, or another context.
+            let other = ACLAKP::generate(&mut OsRng);
+            assert!(!proof.verify(
+                kp.tag_key,
+                &m4,
+                &gens.generators,
+                other.public_key(),
+                Some(b"ctx")
+            ));
+            assert!(!proof.verify(
+                kp.tag_key,
+                &m4,
+                &gens.generators,
+                auditor.public_key(),
+                None
+            ));
+
+            // And an escrow of another value does not verify.
+            let mut forged = proof.clone();
+            forged.escrow.e2 =
+                (forged.escrow.e2 + <$aclconfig as SWCurveConfig>::GENERATOR).into_affine();
+            assert!(!forged.verify(
+                kp.tag_key,
+                &m4,
+                &gens.generators,
+                auditor.public_key(),
+                Some(b"ctx")
+            ));
+        }
+
+        #[test]
+        fn test_challenge_hash_vectors() {
+            // Test the challenge expansion against fixed vectors for several hash functions.
+            fn expand<D: Digest + Clone>() -> Vec<u8> {
+                let mut hasher = D::new();
+                append_to_hash(&mut hasher, b"dom-sep", b"acl-challenge");
+                append_to_hash(&mut hasher, b"message", b"message");
+                expand_hash(&hasher, 80)
+            }
+
+            let to_hex =
+                |bytes: Vec<u8>| -> String { bytes.iter().map(|b| format!("{:02x}", b)).collect() };
+
+            let sha512 = expand::<Sha512>();
+            assert_eq!(
+                to_hex(sha512.clone()),
+                "8717192e6a6dd54f481849f4fad19a97711c923df280412aacda80595ba28934\
+                 934002503e2b3e39ec28b6b4d1d0aa039da94687f0b4adf847ec765f3bc2efdd\
+                 7dfdd9211932ea92c01a28b1acabe061"
+            );
+            assert_eq!(
+                to_hex(expand::<Sha3_512>()),
+                "f081534b0e9670ad5e2f1e6df1ee8b3477f2a36155b3d79fcf8a3526b84a7433\
+                 03146298caceb451be4be119cf85c04825fc3b281729aff113866c9d9d9cd2d6\
+                 13b449299525bc13e736f62c80ad4a6d"
+            );
+            assert_eq!(
+                to_hex(expand::<Blake2b512>()),
+                "def6faf012264d92643e3b52e4e945a84318a71901f954c3fd236d63ee66d780\
+                 860c3bf339a9b05c0a078e6dd2c5458e0c4935591e440b12b2e925bf21d7ba5a\
+                 af854329f010ca49d38985715b297136"
+            );
+
+            // The curves in this workspace use the default hash (SHA-512).
+            let mut hasher = <$aclconfig as ACLConfig>::Hash::new();
+            append_to_hash(&mut hasher, b"dom-sep", b"acl-challenge");
+            append_to_hash(&mut hasher, b"message", b"message");
+            // The whole expansion is reduced, and not only its first scalar-sized bytes.
+            let size = (SF::MODULUS_BIT_SIZE as usize).div_ceil(8);
+            let len = (size + <$aclconfig as ACLConfig>::SECPARAM.div_ceil(8))
+                .max(::acl::sign::CHALLENGE_SIZE);
+            let challenge = <$aclconfig as ACLConfig>::make_challenge_from_hash(&hasher);
+            assert_eq!(
+                challenge,
+                SF::from_le_bytes_mod_order(&expand_hash(&hasher, len))
+            );
+            assert_ne!(challenge, SF::from_le_bytes_mod_order(&sha512[..size]));
         }
     };
 }
@@ -306,7 +736,9 @@ macro_rules! test_acl {
         mod $mod_name {
             use super::*;
             use ::acl::{
-                config::ACLConfig, config::KeyPair, sign::SigChall, sign::SigProof, sign::SigSign,
+                config::append_to_hash, config::expand_hash, config::ACLConfig, config::AuxHasher,
+                config::KeyPair, escrow::AuditableSigProof, escrow::AuditorKeyPair, protocol,
+                protocol::ProtocolError, sign::SigChall, sign::SigProof, sign::SigSign,
                 sign::SubVals, verify::SigComm, verify::SigResp, verify::SigVerifProof,
                 verify::SigVerify,
             };
@@ -316,13 +748,16 @@ macro_rules! test_acl {
                 AffineRepr, CurveGroup,
             };
             use ark_ff::{Field, PrimeField};
-            use ark_serialize::CanonicalSerialize;
+            use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
             use ark_std::UniformRand;
+            use blake2::Blake2b512;
             use core::ops::Mul;
             use merlin::Transcript;
             use pedersen::{pedersen_config::PedersenComm, pedersen_config::PedersenConfig};
             use rand_core::OsRng;
             use sha2::{Digest, Sha512};
+            use sha3::Sha3_512;
+            use std::io::Read;
             $crate::__test_acl!($aclconfig, $config, $OtherProjectiveType);
         }
     };
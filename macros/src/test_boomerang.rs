@@ -11,6 +11,9 @@ macro_rules! __test_boomerang {
         type CBSM = CollectionStateS<$boomerangconfig>;
         type SVBC = SpendVerifyStateC<$boomerangconfig>;
         type SVBS = SpendVerifyStateS<$boomerangconfig>;
+        type UBCM = UpdateStateC<$boomerangconfig>;
+        type UBSM = UpdateStateS<$boomerangconfig>;
+        type TGS = TagStore<$boomerangconfig>;
         type ACLKP = KeyPair<$aclconfig>;
         type ACLSC = SigComm<$aclconfig>;
         type ACLCH = SigChall<$aclconfig>;
@@ -29,34 +32,367 @@ macro_rules! __test_boomerang {
         const OGENERATOR: sw::Affine<<$config as PedersenConfig>::OCurve> =
             <<$config as PedersenConfig>::OCurve as SWCurveConfig>::GENERATOR;
 
+        /// TestRng. A `SeededRng` that prints its seed when the test that holds it fails, so
+        /// that the failure can be replayed by setting `BOOMERANG_SEED` to it.
+        struct TestRng(SeededRng);
+
+        impl TestRng {
+            fn from_env() -> Self {
+                Self(SeededRng::from_env())
+            }
+        }
+
+        impl Drop for TestRng {
+            fn drop(&mut self) {
+                if std::thread::panicking() {
+                    eprintln!("{}={}", ::boomerang::rng::SEED_VAR, self.0.seed());
+                }
+            }
+        }
+
+        impl RngCore for TestRng {
+            fn next_u32(&mut self) -> u32 {
+                self.0.next_u32()
+            }
+
+            fn next_u64(&mut self) -> u64 {
+                self.0.next_u64()
+            }
+
+            fn fill_bytes(&mut self, dest: &mut [u8]) {
+                self.0.fill_bytes(dest)
+            }
+
+            fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), ::rand_core::Error> {
+                self.0.try_fill_bytes(dest)
+            }
+        }
+
+        impl ::rand_core::CryptoRng for TestRng {}
+
+        /// issue_token. Runs the Issuance Protocol between the client `ckp` and the server `skp`,
+        /// for a token that starts with `balance`, and returns the client's new token.
+        fn issue_token(
+            rng: &mut TestRng,
+            ckp: &CBKP,
+            skp: &SBKP,
+            balance: SF,
+        ) -> ::boomerang::config::State<$boomerangconfig> {
+            let mut state = IBCM::default();
+            let issuance_m1 = IBCM::generate_issuance_m1(ckp, &mut state, rng);
+            let mut s_state = IBSM::default();
+            let issuance_m2 =
+                IBSM::generate_issuance_m2_with_balance(&issuance_m1, skp, &mut s_state, balance, rng);
+            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, rng);
+            let issuance_m4 = IBSM::generate_issuance_m4(&issuance_m3, &mut s_state, skp);
+            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, skp, ckp);
+
+            assert!(ACLSV::verify(
+                skp.s_key_pair.verifying_key,
+                skp.s_key_pair.tag_key,
+                &issuance_state.sig_state[0],
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
+            ));
+            issuance_state
+        }
+
+        #[test]
+        fn test_boomerang_validate_config() {
+            // The constants of every config in this workspace must pass the self-test.
+            assert_eq!(<$boomerangconfig as BoomerangConfig>::validate(), Ok(()));
+        }
+
+        #[test]
+        fn test_boomerang_sizes() {
+            // Test that the message sizes match those of a run with other values.
+            let sizes = ::boomerang::sizes::<$boomerangconfig>();
+            let vector = TestVector::generate::<$boomerangconfig>("sizes", 7);
+            let lens = |messages: &[String]| -> Vec<usize> {
+                messages.iter().map(|m| m.len() / 2).collect()
+            };
+            assert_eq!(lens(&vector.issuance), sizes.issuance);
+            assert_eq!(lens(&vector.collection), sizes.collection);
+            assert_eq!(lens(&vector.spending), sizes.spend_verify);
+            assert!(sizes.max() >= sizes.spend_verify[1]);
+            assert!(sizes.rewards_proof < sizes.spend_verify[2]);
+
+            // The proof sizes are those of the proofs that they name.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &issuance_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::one()],
+            );
+            let spendverify_m3 = SVBS::generate_spendverify_m3(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state,
+                &skp,
+                vec![SF::one()],
+            ).unwrap();
+            assert_eq!(sizes.sig_proof, spendverify_m2.s_proof.compressed_size());
+            assert_eq!(sizes.sub_proof, spendverify_m2.pi_4.compressed_size());
+            assert_eq!(
+                sizes.rewards_proof,
+                spendverify_m3.pi_reward.compressed_size()
+            );
+
+            // A longer spend state only changes the spend/verify sizes.
+            let longer = sizes_for_state::<$boomerangconfig>(4);
+            assert_eq!(longer.spend_state_len, 4);
+            assert_eq!(longer.issuance, sizes.issuance);
+            assert_eq!(longer.transfer, sizes.transfer);
+            assert!(longer.spend_verify[1] > sizes.spend_verify[1]);
+            assert!(longer.rewards_proof > sizes.rewards_proof);
+            assert_eq!(sizes_for_state::<$boomerangconfig>(1), sizes);
+        }
+
+        #[test]
+        fn test_boomerang_estimate() {
+            // Test that the estimates of a spend match the reward and the measured sizes.
+            use ::boomerang::client::{estimate_reward, estimate_spend_sizes};
+
+            let spend_state = [SF::from(3u64), SF::from(5u64)];
+            let policy_state = [SF::from(2u64), SF::from(7u64)];
+            assert_eq!(
+                estimate_reward::<$boomerangconfig>(&spend_state, &policy_state),
+                Ok(41)
+            );
+
+            let sizes = sizes_for_state::<$boomerangconfig>(2);
+            let estimate =
+                estimate_spend_sizes::<$boomerangconfig>(&spend_state, &policy_state).unwrap();
+            assert!(!estimate.fast_path);
+            assert_eq!(
+                estimate.sent,
+                sizes.spend_verify[1] + sizes.spend_verify[3]
+            );
+            assert_eq!(
+                estimate.total(),
+                sizes.spend_verify.iter().sum::<usize>()
+            );
+
+            // The counted sizes are those of the messages of a real spend.
+            for len in [1, 2, 4] {
+                let measured = sizes_for_state::<$boomerangconfig>(len);
+                assert_eq!(
+                    spend_verify_sizes::<$boomerangconfig>(len),
+                    (measured.spend_verify, measured.spend_verify_ack)
+                );
+            }
+
+            // A policy that gives no reward takes the fast path, which receives less.
+            let zero = [SF::zero(), SF::zero()];
+            assert_eq!(estimate_reward::<$boomerangconfig>(&spend_state, &zero), Ok(0));
+            let fast = estimate_spend_sizes::<$boomerangconfig>(&spend_state, &zero).unwrap();
+            assert!(fast.fast_path);
+            assert_eq!(fast.sent, estimate.sent);
+            assert!(fast.received < estimate.received);
+
+            // A reward that the range proof cannot hold, and mismatched or odd lengths, fail.
+            let large = [SF::from(u64::MAX), SF::zero()];
+            assert_eq!(
+                estimate_reward::<$boomerangconfig>(&large, &[SF::one(), SF::zero()]),
+                Ok(u64::MAX)
+            );
+            assert!(estimate_reward::<$boomerangconfig>(&large, &[SF::from(2u64), SF::zero()])
+                .is_err());
+            assert!(estimate_reward::<$boomerangconfig>(&spend_state, &[SF::one()]).is_err());
+            assert!(estimate_spend_sizes::<$boomerangconfig>(&spend_state, &[SF::one()]).is_err());
+            let three = [SF::one(); 3];
+            assert!(estimate_spend_sizes::<$boomerangconfig>(&three, &three).is_err());
+        }
+
+        #[test]
+        fn test_boomerang_curve_registry() {
+            // Test that the id of the curve describes its config, and that messages and
+            // negotiated parameters are dispatched to the config of their curve.
+            use ::boomerang::curves::{CurveHandler, CurveId, CurveRegistry, RegisteredCurve};
+            use ::boomerang::json::{message_curve, JsonMessage};
+            use ::boomerang::params::{ClientParams, ServerParams};
+            use ::boomerang::server::{SpendVerifyM1, SUPPORTED_CURVES};
+
+            let id = <$boomerangconfig as RegisteredCurve>::ID;
+            let info = id.info();
+            assert_eq!(
+                info.point_size,
+                <$boomerangconfig as SWCurveConfig>::GENERATOR.compressed_size()
+            );
+            assert_eq!(info.scalar_size, SF::one().compressed_size());
+            assert_eq!(
+                info.security_bits,
+                <$boomerangconfig as ACLConfig>::SECPARAM
+            );
+            assert_eq!(id.name().parse::<CurveId>(), Ok(id));
+            assert_eq!(CurveId::from_wire_id(id.wire_id()), Some(id));
+
+            let names: Vec<_> = CurveId::ALL.iter().map(CurveId::name).collect();
+            assert_eq!(names, SUPPORTED_CURVES);
+            assert!("t255".parse::<CurveId>().is_err());
+            assert_eq!(CurveId::from_wire_id(0), None);
+
+            struct PointSize;
+            impl CurveHandler for PointSize {
+                type Output = usize;
+
+                fn handle<B: RegisteredCurve>(self) -> usize {
+                    <B as SWCurveConfig>::GENERATOR.compressed_size()
+                }
+            }
+
+            let registry = CurveRegistry::<PointSize>::new().register::<$boomerangconfig>();
+            assert_eq!(registry.ids(), vec![id]);
+            assert_eq!(registry.dispatch(id, PointSize), Ok(info.point_size));
+            let other = CurveId::ALL.into_iter().find(|other| *other != id).unwrap();
+            assert!(registry.dispatch(other, PointSize).is_err());
+
+            // A message that is tagged with its curve is dispatched before it is decoded.
+            let mut rng = TestRng::from_env();
+            let m1 = SVBS::generate_spendverify_m1(&mut rng, &mut SVBS::default());
+            let json = m1.to_json_with_curve(id);
+            assert_eq!(message_curve(&json), Ok(Some(id)));
+            assert!(SpendVerifyM1::<$boomerangconfig>::from_json(&json).is_ok());
+            assert_eq!(message_curve(&m1.to_json()), Ok(None));
+            let unknown = json.replace(id.name(), "t255");
+            assert!(message_curve(&unknown).is_err());
+
+            // The negotiated curve is one that the registry dispatches to.
+            let server = ServerParams::new(&registry.names(), 1);
+            let params = ClientParams::from_ids(&CurveId::ALL)
+                .negotiate(&server)
+                .unwrap();
+            assert_eq!(params.curve_id(), Ok(id));
+            assert!(registry.contains(params.curve_id().unwrap()));
+        }
+
+        #[test]
+        fn test_boomerang_routes() {
+            // Test that every route is distinct, and is found by its method and path.
+            use ::boomerang::routes::{self, Method, Route};
+            for (i, route) in routes::ALL.iter().enumerate() {
+                assert!(route.path.starts_with('/'));
+                assert!(routes::ALL[..i].iter().all(|r| r.path != route.path));
+                assert_eq!(Route::find(route.method, route.path), Some(*route));
+            }
+            assert_eq!(routes::ISSUANCE_M2.method, Method::Post);
+            assert_eq!(routes::COLLECTION_M1.method, Method::Get);
+            assert_eq!(Route::find(Method::Get, routes::ISSUANCE_M2.path), None);
+            assert_eq!(
+                routes::COLLECTION_M3.url("https://127.0.0.1:3000/"),
+                "https://127.0.0.1:3000/collection/m3"
+            );
+            assert_eq!(routes::SPEND_VERIFY_M5.to_string(), "POST /spend-verify/m5");
+        }
+
+        #[test]
+        fn test_boomerang_params() {
+            // Test that a client negotiates the parameters that the server advertises.
+            use ::boomerang::params::{
+                ClientParams, Params, ParamsError, ServerParams, PROTOCOL_VERSION,
+            };
+            use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+            let config = ServerConfig::from_toml_str("curve = \"t256\"\npolicy = [1, 2, 3, 4]").unwrap();
+            let server = ServerParams::from(&config);
+            let mut bytes = Vec::new();
+            server.serialize_compressed(&mut bytes).unwrap();
+            let server = ServerParams::deserialize_compressed(&*bytes).unwrap();
+
+            assert_eq!(
+                ClientParams::default().negotiate(&server),
+                Ok(Params {
+                    curve: "t256".to_string(),
+                    catalog_size: 4,
+                    bitsize: 64,
+                    version: PROTOCOL_VERSION,
+                })
+            );
+
+            // The client's preference between the common curves wins, and the largest common
+            // bit size and the highest common version are picked.
+            let mut wide = ServerParams::new(&["t384", "t256"], 4);
+            wide.bitsizes = vec![32, 64, 128];
+            wide.versions = vec![PROTOCOL_VERSION, PROTOCOL_VERSION + 1];
+            let client = ClientParams {
+                bitsizes: vec![32, 64],
+                ..ClientParams::new(&["t256", "t384"])
+            };
+            let params = client.negotiate(&wide).unwrap();
+            assert_eq!(params.curve, "t256");
+            assert_eq!(params.bitsize, 64);
+            assert_eq!(params.version, PROTOCOL_VERSION);
+
+            assert_eq!(
+                ClientParams::new(&["t521"]).negotiate(&server),
+                Err(ParamsError::NoCommonCurve)
+            );
+            assert_eq!(
+                ClientParams {
+                    bitsizes: vec![32],
+                    ..ClientParams::default()
+                }
+                .negotiate(&server),
+                Err(ParamsError::NoCommonBitsize)
+            );
+            assert_eq!(
+                ClientParams {
+                    versions: vec![PROTOCOL_VERSION + 1],
+                    ..ClientParams::default()
+                }
+                .negotiate(&server),
+                Err(ParamsError::NoCommonVersion)
+            );
+            assert_eq!(
+                ClientParams::default().negotiate(&ServerParams::new(&["t256"], 0)),
+                Err(ParamsError::EmptyCatalog)
+            );
+            assert_eq!(
+                ClientParams::default().negotiate(&ServerParams::new(&["t256"], 65)),
+                Err(ParamsError::CatalogTooLarge { len: 65, max: 64 })
+            );
+        }
+
         #[test]
         fn test_boomerang_issuance_m1() {
             // Test the first message of the boomerang scheme.
-            let ckp = CBKP::generate(&mut OsRng);
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
             assert!(ckp.public_key.is_on_curve());
 
             let mut state = IBCM::default();
-            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut OsRng);
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
             assert!(issuance_m1.u_pk.is_on_curve());
         }
 
         #[test]
         fn test_boomerang_issuance_m2() {
             // Test the second message of the boomerang scheme.
-            let ckp = CBKP::generate(&mut OsRng);
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
             assert!(ckp.public_key.is_on_curve());
 
-            let skp = SBKP::generate(&mut OsRng);
+            let skp = SBKP::generate(&mut rng);
             assert!(skp.s_key_pair.verifying_key.is_on_curve());
             assert!(skp.s_key_pair.tag_key.is_on_curve());
 
             let mut state = IBCM::default();
-            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut OsRng);
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
             assert!(issuance_m1.u_pk.is_on_curve());
 
             let mut s_state = IBSM::default();
             let issuance_m2 =
-                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut OsRng);
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut rng);
             assert!(issuance_m2.verifying_key.is_on_curve());
             assert!(issuance_m2.tag_key.is_on_curve());
         }
@@ -64,47 +400,49 @@ macro_rules! __test_boomerang {
         #[test]
         fn test_boomerang_issuance_m3() {
             // Test the third message of the boomerang scheme.
-            let ckp = CBKP::generate(&mut OsRng);
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
             assert!(ckp.public_key.is_on_curve());
 
-            let skp = SBKP::generate(&mut OsRng);
+            let skp = SBKP::generate(&mut rng);
             assert!(skp.s_key_pair.verifying_key.is_on_curve());
             assert!(skp.s_key_pair.tag_key.is_on_curve());
 
             let mut state = IBCM::default();
-            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut OsRng);
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
             assert!(issuance_m1.u_pk.is_on_curve());
 
             let mut s_state = IBSM::default();
             let issuance_m2 =
-                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut OsRng);
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut rng);
             assert!(issuance_m2.verifying_key.is_on_curve());
             assert!(issuance_m2.tag_key.is_on_curve());
 
-            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut OsRng);
+            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut rng);
         }
 
         #[test]
         fn test_boomerang_issuance_m4() {
             // Test the fourth message of the boomerang scheme.
-            let ckp = CBKP::generate(&mut OsRng);
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
             assert!(ckp.public_key.is_on_curve());
 
-            let skp = SBKP::generate(&mut OsRng);
+            let skp = SBKP::generate(&mut rng);
             assert!(skp.s_key_pair.verifying_key.is_on_curve());
             assert!(skp.s_key_pair.tag_key.is_on_curve());
 
             let mut state = IBCM::default();
-            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut OsRng);
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
             assert!(issuance_m1.u_pk.is_on_curve());
 
             let mut s_state = IBSM::default();
             let issuance_m2 =
-                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut OsRng);
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut rng);
             assert!(issuance_m2.verifying_key.is_on_curve());
             assert!(issuance_m2.tag_key.is_on_curve());
 
-            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut OsRng);
+            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut rng);
 
             let issuance_m4 = IBSM::generate_issuance_m4(&issuance_m3, &mut s_state, &skp);
         }
@@ -112,28 +450,29 @@ macro_rules! __test_boomerang {
         #[test]
         fn test_boomerang_issuance_full() {
             // Test the full boomerang issuance scheme.
-            let ckp = CBKP::generate(&mut OsRng);
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
             assert!(ckp.public_key.is_on_curve());
 
-            let skp = SBKP::generate(&mut OsRng);
+            let skp = SBKP::generate(&mut rng);
             assert!(skp.s_key_pair.verifying_key.is_on_curve());
             assert!(skp.s_key_pair.tag_key.is_on_curve());
 
             let mut state = IBCM::default();
-            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut OsRng);
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
             assert!(issuance_m1.u_pk.is_on_curve());
 
             let mut s_state = IBSM::default();
             let issuance_m2 =
-                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut OsRng);
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut rng);
             assert!(issuance_m2.verifying_key.is_on_curve());
             assert!(issuance_m2.tag_key.is_on_curve());
 
-            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut OsRng);
+            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut rng);
 
             let issuance_m4 = IBSM::generate_issuance_m4(&issuance_m3, &mut s_state, &skp);
 
-            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, ckp.clone());
+            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, &ckp);
 
             assert!(issuance_state.sig_state[0].sigma.zeta.is_on_curve());
             assert!(issuance_state.sig_state[0].sigma.zeta1.is_on_curve());
@@ -144,35 +483,471 @@ macro_rules! __test_boomerang {
                 skp.s_key_pair.verifying_key,
                 skp.s_key_pair.tag_key,
                 &sig,
-                "message",
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
             );
             assert!(check == true);
+            assert_eq!(issuance_m3.session_id, issuance_m4.session_id);
+        }
+
+        #[test]
+        #[should_panic(expected = "Boomerang issuance: session id mismatch")]
+        fn test_boomerang_issuance_replay() {
+            // Test that a recorded third message is rejected by a run with a fresh second message.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let mut state = IBCM::default();
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
+
+            let mut s_state_a = IBSM::default();
+            let issuance_m2_a =
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state_a, &mut rng);
+            let mut s_state_b = IBSM::default();
+            let issuance_m2_b =
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state_b, &mut rng);
+            assert_ne!(issuance_m2_a.nonce, issuance_m2_b.nonce);
+
+            // The third message of run `a` is replayed against the server of run `b`.
+            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2_a, &mut state, &mut rng);
+            IBSM::generate_issuance_m4(&issuance_m3, &mut s_state_b, &skp);
+        }
+
+        #[test]
+        #[should_panic(expected = "Boomerang issuance: invalid proof")]
+        fn test_boomerang_issuance_client_nonce() {
+            // Test that the proof of the first message only holds with the client's nonce.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let mut state = IBCM::default();
+            let mut issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
+            issuance_m1.nonce[0] ^= 1;
+
+            let mut s_state = IBSM::default();
+            IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut rng);
+        }
+
+        #[test]
+        fn test_boomerang_issuance_balance() {
+            // Test that a token can be issued with a balance that the server sets.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let balance = SF::from(25u64);
+
+            let mut state = IBCM::default();
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
+
+            let mut s_state = IBSM::default();
+            let issuance_m2 = IBSM::generate_issuance_m2_with_balance(
+                &issuance_m1,
+                &skp,
+                &mut s_state,
+                balance,
+                &mut rng,
+            );
+            assert!(issuance_m2.balance == balance);
+            assert!(IBCM::verify_issuance_m2(&issuance_m2, &state, &skp).is_ok());
+
+            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut rng);
+            let issuance_m4 = IBSM::generate_issuance_m4(&issuance_m3, &mut s_state, &skp);
+            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, &ckp);
+
+            assert!(issuance_state.balance() == balance);
+            assert!(issuance_state.is_consistent());
+        }
+
+        #[test]
+        #[should_panic(expected = "Boomerang issuance: invalid proof of balance")]
+        fn test_boomerang_issuance_balance_mismatch() {
+            // Test that the client rejects a balance that the server's commitment does not hold.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let mut state = IBCM::default();
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
+
+            let mut s_state = IBSM::default();
+            let mut issuance_m2 =
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut rng);
+            issuance_m2.balance = SF::from(25u64);
+
+            IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut rng);
+        }
+
+        #[test]
+        fn test_boomerang_malicious_server_tag_key() {
+            // Test that the client rejects a token that is signed under another tag key than
+            // the published one.
+            use ::boomerang::testing::malicious::{MaliciousServer, ServerFault};
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let mut server = MaliciousServer::new(skp.clone(), ServerFault::WrongTagKey);
+
+            let mut state = IBCM::default();
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
+            let mut s_state = IBSM::default();
+            let issuance_m2 = server.generate_issuance_m2(&issuance_m1, &mut s_state, &mut rng);
+            assert!(issuance_m2.tag_key != skp.s_key_pair.tag_key);
+
+            // The client rejects the keys before it answers.
+            assert_eq!(
+                IBCM::verify_issuance_m2(&issuance_m2, &state, &skp).err(),
+                Some("Boomerang issuance: the server advertised another key than its published one")
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Boomerang issuance: invalid proof of balance")]
+        fn test_boomerang_malicious_server_nonce() {
+            // Test that the client rejects a second message that was sent in another session.
+            use ::boomerang::testing::malicious::{MaliciousServer, ServerFault};
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let mut server = MaliciousServer::new(skp.clone(), ServerFault::ReusedNonce);
+
+            let mut state_a = IBCM::default();
+            let issuance_m1_a = IBCM::generate_issuance_m1(&ckp, &mut state_a, &mut rng);
+            let mut s_state_a = IBSM::default();
+            let issuance_m2_a =
+                server.generate_issuance_m2(&issuance_m1_a, &mut s_state_a, &mut rng);
+            IBCM::generate_issuance_m3(&issuance_m2_a, &mut state_a, &mut rng);
+
+            let mut state_b = IBCM::default();
+            let issuance_m1_b = IBCM::generate_issuance_m1(&ckp, &mut state_b, &mut rng);
+            let mut s_state_b = IBSM::default();
+            let issuance_m2_b =
+                server.generate_issuance_m2(&issuance_m1_b, &mut s_state_b, &mut rng);
+            assert_eq!(issuance_m2_a.nonce, issuance_m2_b.nonce);
+
+            IBCM::generate_issuance_m3(&issuance_m2_b, &mut state_b, &mut rng);
+        }
+
+        #[test]
+        fn test_boomerang_malicious_server_policy() {
+            // Test that the client rejects rewards that were computed with another policy than
+            // the advertised one.
+            use ::boomerang::testing::malicious::{MaliciousServer, ServerFault};
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let server = MaliciousServer::new(skp.clone(), ServerFault::AlteredPolicy);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &issuance_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::from(3u64)],
+            );
+
+            let policy_state = vec![SF::from(2u64)];
+            let spendverify_m3 = server.generate_spendverify_m3(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state,
+                policy_state.clone(),
+            ).unwrap();
+            assert_eq!(
+                SVBC::generate_spendverify_m4_with_policy(
+                    &mut rng,
+                    &mut c_spend_state,
+                    &spendverify_m3,
+                    &policy_state,
+                    &skp.policy_id(&policy_state),
+                )
+                .err(),
+                Some("Boomerang spend-verify: reward does not match the policy")
+            );
+        }
+
+        #[test]
+        #[should_panic(expected = "Boomerang verification: reward proof verification failed")]
+        fn test_boomerang_malicious_server_rewards_proof() {
+            // Test that the client rejects a rewards proof that does not verify.
+            use ::boomerang::testing::malicious::{MaliciousServer, ServerFault};
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let server = MaliciousServer::new(skp.clone(), ServerFault::BadRewardsProof);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &issuance_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::from(3u64)],
+            );
+
+            let spendverify_m3 = server.generate_spendverify_m3(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state,
+                vec![SF::from(2u64)],
+            ).unwrap();
+            SVBC::generate_spendverify_m4(
+                &mut rng,
+                &mut c_spend_state,
+                &spendverify_m3,
+                &server.key_pair().policy_id(&[SF::from(2u64)]),
+            )
+            .unwrap();
+        }
+
+        #[test]
+        fn test_boomerang_malicious_client_balance() {
+            // Test that the server rejects a spend whose amount exceeds the balance that the
+            // client proved.
+            use ::boomerang::testing::malicious::{ClientFault, MaliciousClient};
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let client = MaliciousClient::new(ClientFault::OverflowingBalance);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::from(10u64));
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = client
+                .generate_spendverify_m2_multi(
+                    &mut rng,
+                    &issuance_state,
+                    &mut c_spend_state,
+                    &spendverify_m1,
+                    &skp,
+                    &[SF::from(3u64)],
+                )
+                .unwrap();
+            assert_eq!(
+                SVBS::generate_spendverify_m3_multi(
+                    &mut rng,
+                    &spendverify_m2,
+                    &mut s_spend_state,
+                    &skp,
+                    vec![SF::one()],
+                )
+                .err(),
+                Some("Boomerang spend-verify: balances do not match the sub proof")
+            );
+        }
+
+        #[test]
+        fn test_boomerang_malicious_client_token_state() {
+            // Test that the server rejects a spend that presents the same token twice.
+            use ::boomerang::testing::malicious::{ClientFault, MaliciousClient};
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let client = MaliciousClient::new(ClientFault::ReusedTokenState);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::from(10u64));
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = client
+                .generate_spendverify_m2_multi(
+                    &mut rng,
+                    &issuance_state,
+                    &mut c_spend_state,
+                    &spendverify_m1,
+                    &skp,
+                    &[SF::from(3u64)],
+                )
+                .unwrap();
+            assert_eq!(
+                SVBS::generate_spendverify_m3_multi(
+                    &mut rng,
+                    &spendverify_m2,
+                    &mut s_spend_state,
+                    &skp,
+                    vec![SF::one()],
+                )
+                .err(),
+                Some("Boomerang spend-verify: token presented twice")
+            );
+        }
+
+        #[test]
+        fn test_boomerang_malicious_client_commitment() {
+            // Test that the server rejects a new commitment that the client changed after it
+            // proved its opening.
+            use ::boomerang::testing::malicious::{ClientFault, MaliciousClient};
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let client = MaliciousClient::new(ClientFault::TamperedCommitment);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::from(10u64));
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = client.generate_spendverify_m2(
+                &mut rng,
+                &issuance_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::from(3u64)],
+            );
+            assert_eq!(
+                SVBS::generate_spendverify_m3(
+                    &mut rng,
+                    &spendverify_m2,
+                    &mut s_spend_state,
+                    &skp,
+                    vec![SF::one()],
+                )
+                .err(),
+                Some("Boomerang spend-verify: invalid proof opening 1")
+            );
+        }
+
+        #[test]
+        fn test_boomerang_malicious_client_opening() {
+            // Test that the server rejects an opening proof of another commitment than the
+            // signed one.
+            use ::boomerang::testing::malicious::{ClientFault, MaliciousClient};
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let client = MaliciousClient::new(ClientFault::MismatchedOpening);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::from(10u64));
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = client.generate_spendverify_m2(
+                &mut rng,
+                &issuance_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::from(3u64)],
+            );
+            assert_eq!(
+                SVBS::generate_spendverify_m3(
+                    &mut rng,
+                    &spendverify_m2,
+                    &mut s_spend_state,
+                    &skp,
+                    vec![SF::one()],
+                )
+                .err(),
+                Some("Boomerang spend-verify: invalid proof opening 2")
+            );
+        }
+
+        #[test]
+        fn test_boomerang_credential_log() {
+            // Test that every issued token is logged under the key that signed it.
+            use ::boomerang::credlog::{CredentialLog, MemoryCredentialLog};
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let other = SBKP::generate(&mut rng);
+            assert_eq!(skp.key_id(), skp.clone().key_id());
+            assert_ne!(skp.key_id(), other.key_id());
+
+            let mut log = MemoryCredentialLog::new();
+            let mut issue = |log: &mut MemoryCredentialLog<_>, skp: &SBKP, epoch: u64| {
+                let mut state = IBCM::default();
+                let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
+                let mut s_state = IBSM::default();
+                let issuance_m2 =
+                    IBSM::generate_issuance_m2(&issuance_m1, skp, &mut s_state, &mut rng);
+                let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut rng);
+                let issuance_m4 = IBSM::generate_issuance_m4_with_log(
+                    &issuance_m3,
+                    &mut s_state,
+                    skp,
+                    log,
+                    epoch,
+                    1000 + epoch,
+                )
+                .unwrap();
+                IBCM::populate_state(&issuance_m4, &mut state, skp, &ckp)
+            };
+
+            let issuance_state = issue(&mut log, &skp, 1);
+            issue(&mut log, &skp, 2);
+            issue(&mut log, &other, 2);
+            assert_eq!(log.records().len(), 3);
+
+            // The record holds the signed commitment, and nothing about the user.
+            let record = &log.records()[0];
+            assert!(record.key_id == skp.key_id());
+            assert_eq!((record.epoch, record.issued_at), (1, 1001));
+            assert!(record.commitment == issuance_state.comm_state[0].comm);
+            assert!(record.commitment != ckp.public_key);
+
+            assert_eq!(log.count(&skp.key_id(), 0..3), 2);
+            assert_eq!(log.count(&skp.key_id(), 2..3), 1);
+            assert_eq!(log.count(&other.key_id(), 0..2), 0);
+            let rotated = log.query(&other.key_id(), 0..u64::MAX);
+            assert_eq!(rotated.len(), 1);
+            assert!(rotated[0].key_id == other.key_id());
+
+            let drained = log.drain();
+            assert_eq!(drained.len(), 3);
+            assert!(log.records().is_empty());
         }
 
         #[test]
         fn test_boomerang_collection_round_m1() {
             // Test the first boomerang collection scheme.
-            let ckp = CBKP::generate(&mut OsRng);
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
             assert!(ckp.public_key.is_on_curve());
 
-            let skp = SBKP::generate(&mut OsRng);
+            let skp = SBKP::generate(&mut rng);
             assert!(skp.s_key_pair.verifying_key.is_on_curve());
             assert!(skp.s_key_pair.tag_key.is_on_curve());
 
             let mut state = IBCM::default();
-            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut OsRng);
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
             assert!(issuance_m1.u_pk.is_on_curve());
 
             let mut s_state = IBSM::default();
             let issuance_m2 =
-                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut OsRng);
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut rng);
             assert!(issuance_m2.verifying_key.is_on_curve());
             assert!(issuance_m2.tag_key.is_on_curve());
 
-            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut OsRng);
+            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut rng);
 
             let issuance_m4 = IBSM::generate_issuance_m4(&issuance_m3, &mut s_state, &skp);
-            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, ckp.clone());
+            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, &ckp);
 
             assert!(issuance_state.sig_state[0].sigma.zeta.is_on_curve());
             assert!(issuance_state.sig_state[0].sigma.zeta1.is_on_curve());
@@ -183,17 +958,18 @@ macro_rules! __test_boomerang {
                 skp.s_key_pair.verifying_key,
                 skp.s_key_pair.tag_key,
                 &sig,
-                "message",
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
             );
             assert!(check == true);
 
             let mut s_col_state = CBSM::default();
-            let collection_m1 = CBSM::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let collection_m1 = CBSM::generate_collection_m1(&mut rng, &mut s_col_state);
 
             let mut c_col_state = CBCM::default();
             let collection_m2 = CBCM::generate_collection_m2(
-                &mut OsRng,
-                issuance_state,
+                &mut rng,
+                &issuance_state,
                 &collection_m1,
                 &mut c_col_state,
                 &skp,
@@ -205,28 +981,29 @@ macro_rules! __test_boomerang {
         #[test]
         fn test_boomerang_collection_round_m2() {
             // Test the first boomerang collection scheme.
-            let ckp = CBKP::generate(&mut OsRng);
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
             assert!(ckp.public_key.is_on_curve());
 
-            let skp = SBKP::generate(&mut OsRng);
+            let skp = SBKP::generate(&mut rng);
             assert!(skp.s_key_pair.verifying_key.is_on_curve());
             assert!(skp.s_key_pair.tag_key.is_on_curve());
 
             let mut state = IBCM::default();
-            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut OsRng);
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
             assert!(issuance_m1.u_pk.is_on_curve());
 
             let mut s_state = IBSM::default();
             let issuance_m2 =
-                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut OsRng);
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut rng);
             assert!(issuance_m2.verifying_key.is_on_curve());
             assert!(issuance_m2.tag_key.is_on_curve());
 
-            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut OsRng);
+            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut rng);
 
             let issuance_m4 = IBSM::generate_issuance_m4(&issuance_m3, &mut s_state, &skp);
 
-            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, ckp.clone());
+            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, &ckp);
 
             assert!(issuance_state.sig_state[0].sigma.zeta.is_on_curve());
             assert!(issuance_state.sig_state[0].sigma.zeta1.is_on_curve());
@@ -237,17 +1014,18 @@ macro_rules! __test_boomerang {
                 skp.s_key_pair.verifying_key,
                 skp.s_key_pair.tag_key,
                 &sig,
-                "message",
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
             );
             assert!(check == true);
 
             let mut s_col_state = CBSM::default();
-            let collection_m1 = CBSM::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let collection_m1 = CBSM::generate_collection_m1(&mut rng, &mut s_col_state);
 
             let mut c_col_state = CBCM::default();
             let collection_m2 = CBCM::generate_collection_m2(
-                &mut OsRng,
-                issuance_state,
+                &mut rng,
+                &issuance_state,
                 &collection_m1,
                 &mut c_col_state,
                 &skp,
@@ -257,35 +1035,93 @@ macro_rules! __test_boomerang {
 
             let v = SF::one();
             let collection_m3 =
-                CBSM::generate_collection_m3(&mut OsRng, &collection_m2, &mut s_col_state, &skp, v);
+                CBSM::generate_collection_m3(&mut rng, &collection_m2, &mut s_col_state, &skp, v).unwrap();
 
             assert!(collection_m3.comm.comm.is_on_curve());
         }
 
+        #[test]
+        fn test_boomerang_collection_m2_batch_verify() {
+            // Test that the checks of collection m2 accept an honest message, and reject a
+            // message in which the signature proof or any of the batched openings was changed.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let mut s_col_state = CBSM::default();
+            let collection_m1 = CBSM::generate_collection_m1(&mut rng, &mut s_col_state);
+            let mut c_col_state = CBCM::default();
+            let collection_m2 = CBCM::generate_collection_m2(
+                &mut rng,
+                &issuance_state,
+                &collection_m1,
+                &mut c_col_state,
+                &skp,
+            );
+
+            assert!(CBSM::verify_collection_m2(&collection_m2, &skp).is_ok());
+
+            let mut bad_sig_proof = collection_m2.clone();
+            bad_sig_proof.s_proof.pi2.a3 += SF::one();
+            assert_eq!(
+                CBSM::verify_collection_m2(&bad_sig_proof, &skp),
+                Err("Boomerang collection: invalid proof sig")
+            );
+
+            let mut bad_opening = collection_m2.clone();
+            bad_opening.pi_1.z1 += SF::one();
+            assert_eq!(
+                CBSM::verify_collection_m2(&bad_opening, &skp),
+                Err("Boomerang collection: invalid proof opening")
+            );
+
+            let mut bad_prev_opening = collection_m2.clone();
+            bad_prev_opening.pi_2.z1 += SF::one();
+            assert_eq!(
+                CBSM::verify_collection_m2(&bad_prev_opening, &skp),
+                Err("Boomerang collection: invalid proof opening")
+            );
+
+            // Equations that share points are merged, so the batch holds each point once.
+            let g = <Config as SWCurveConfig>::GENERATOR;
+            let mut batch = BatchVerifier::<Config>::new();
+            assert!(batch.is_empty());
+            batch.add(&mut rng, [(g, SF::one()), (g, -SF::one())]);
+            let g2 = (g * SF::from(2u64)).into_affine();
+            batch.add(&mut rng, [(g, SF::from(2u64)), (g2, -SF::one())]);
+            assert_eq!(batch.len(), 2);
+            assert!(batch.verify());
+            batch.add(&mut rng, [(g, SF::one())]);
+            assert!(!batch.verify());
+        }
+
         #[test]
         fn test_boomerang_collection_round_m4() {
             // Test the first boomerang collection scheme.
-            let ckp = CBKP::generate(&mut OsRng);
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
             assert!(ckp.public_key.is_on_curve());
 
-            let skp = SBKP::generate(&mut OsRng);
+            let skp = SBKP::generate(&mut rng);
             assert!(skp.s_key_pair.verifying_key.is_on_curve());
             assert!(skp.s_key_pair.tag_key.is_on_curve());
 
             let mut state = IBCM::default();
-            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut OsRng);
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
             assert!(issuance_m1.u_pk.is_on_curve());
 
             let mut s_state = IBSM::default();
             let issuance_m2 =
-                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut OsRng);
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut rng);
             assert!(issuance_m2.verifying_key.is_on_curve());
             assert!(issuance_m2.tag_key.is_on_curve());
 
-            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut OsRng);
+            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut rng);
 
             let issuance_m4 = IBSM::generate_issuance_m4(&issuance_m3, &mut s_state, &skp);
-            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, ckp.clone());
+            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, &ckp);
 
             assert!(issuance_state.sig_state[0].sigma.zeta.is_on_curve());
             assert!(issuance_state.sig_state[0].sigma.zeta1.is_on_curve());
@@ -296,17 +1132,18 @@ macro_rules! __test_boomerang {
                 skp.s_key_pair.verifying_key,
                 skp.s_key_pair.tag_key,
                 &sig,
-                "message",
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
             );
             assert!(check == true);
 
             let mut s_col_state = CBSM::default();
-            let collection_m1 = CBSM::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let collection_m1 = CBSM::generate_collection_m1(&mut rng, &mut s_col_state);
 
             let mut c_col_state = CBCM::default();
             let collection_m2 = CBCM::generate_collection_m2(
-                &mut OsRng,
-                issuance_state,
+                &mut rng,
+                &issuance_state,
                 &collection_m1,
                 &mut c_col_state,
                 &skp,
@@ -316,18 +1153,18 @@ macro_rules! __test_boomerang {
 
             let v = SF::one();
             let collection_m3 =
-                CBSM::generate_collection_m3(&mut OsRng, &collection_m2, &mut s_col_state, &skp, v);
+                CBSM::generate_collection_m3(&mut rng, &collection_m2, &mut s_col_state, &skp, v).unwrap();
 
             assert!(collection_m3.comm.comm.is_on_curve());
 
             let collection_m4 =
-                CBCM::generate_collection_m4(&mut OsRng, &mut c_col_state, &collection_m3);
+                CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &collection_m3);
 
             let collection_m5 =
                 CBSM::generate_collection_m5(&collection_m4, &mut s_col_state, &skp);
 
             let collection_state =
-                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, ckp.clone());
+                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, &ckp);
 
             assert!(collection_state.sig_state[0].sigma.zeta.is_on_curve());
             assert!(collection_state.sig_state[0].sigma.zeta1.is_on_curve());
@@ -338,57 +1175,576 @@ macro_rules! __test_boomerang {
                 skp.s_key_pair.verifying_key,
                 skp.s_key_pair.tag_key,
                 &sig_n,
-                "message",
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
             );
             assert!(check == true);
         }
 
         #[test]
-        fn test_boomerang_accumulate() {
-            let ckp = CBKP::generate(&mut OsRng);
+        fn test_boomerang_collection_stateless() {
+            // Test the collection scheme with a server that keeps its state in tickets.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
             assert!(ckp.public_key.is_on_curve());
 
-            let skp = SBKP::generate(&mut OsRng);
+            let skp = SBKP::generate(&mut rng);
             assert!(skp.s_key_pair.verifying_key.is_on_curve());
             assert!(skp.s_key_pair.tag_key.is_on_curve());
 
-            let mut state = IBCM::default();
-            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut OsRng);
-            assert!(issuance_m1.u_pk.is_on_curve());
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
 
-            let mut s_state = IBSM::default();
-            let issuance_m2 =
-                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut OsRng);
-            assert!(issuance_m2.verifying_key.is_on_curve());
-            assert!(issuance_m2.tag_key.is_on_curve());
+            let ticket_key = TicketKey::generate(&mut rng);
+            let (now, expiry) = (1000, 1060);
 
-            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut OsRng);
+            let (collection_m1, ticket_m1) =
+                CBSM::generate_collection_m1_stateless(&mut rng, &ticket_key, expiry);
+            assert!(ticket_m1.id().is_some());
+            assert_eq!(ticket_m1.expiry(), Some(expiry));
 
-            let issuance_m4 = IBSM::generate_issuance_m4(&issuance_m3, &mut s_state, &skp);
+            let mut c_col_state = CBCM::default();
+            let collection_m2 = CBCM::generate_collection_m2(
+                &mut rng,
+                &issuance_state,
+                &collection_m1,
+                &mut c_col_state,
+                &skp,
+            );
+
+            // Only the client's copy of the ticket is needed to carry on.
+            let returned = SessionTicket::from_bytes(ticket_m1.as_bytes().to_vec());
+            let v = SF::one();
+            let mut guard = MemoryReplayGuard::new();
+
+            // A ticket that has expired, been modified, or was sealed under a different key is rejected.
+            assert!(CBSM::generate_collection_m3_stateless(
+                &mut rng,
+                &collection_m2,
+                &returned,
+                &ticket_key,
+                &mut guard,
+                expiry + 1,
+                expiry,
+                &skp,
+                v
+            )
+            .is_err());
+
+            let mut tampered = returned.as_bytes().to_vec();
+            *tampered.last_mut().unwrap() ^= 1;
+            assert!(CBSM::generate_collection_m3_stateless(
+                &mut rng,
+                &collection_m2,
+                &SessionTicket::from_bytes(tampered),
+                &ticket_key,
+                &mut guard,
+                now,
+                expiry,
+                &skp,
+                v
+            )
+            .is_err());
+
+            let other_key = TicketKey::generate(&mut rng);
+            assert!(CBSM::generate_collection_m3_stateless(
+                &mut rng,
+                &collection_m2,
+                &returned,
+                &other_key,
+                &mut guard,
+                now,
+                expiry,
+                &skp,
+                v
+            )
+            .is_err());
+
+            // A ticket is not spent by a message that fails to verify.
+            let mut forged = collection_m2.clone();
+            forged.comm = forged.prev_comm;
+            assert!(CBSM::generate_collection_m3_stateless(
+                &mut rng,
+                &forged,
+                &returned,
+                &ticket_key,
+                &mut guard,
+                now,
+                expiry,
+                &skp,
+                v
+            )
+            .is_err());
+            assert!(guard.is_empty());
+
+            let (collection_m3, ticket_m3) = CBSM::generate_collection_m3_stateless(
+                &mut rng,
+                &collection_m2,
+                &returned,
+                &ticket_key,
+                &mut guard,
+                now,
+                expiry,
+                &skp,
+                v,
+            )
+            .unwrap();
+
+            // The ticket of the first message is only accepted once, so that the same session
+            // cannot be run twice.
+            assert_eq!(
+                CBSM::generate_collection_m3_stateless(
+                    &mut rng,
+                    &collection_m2,
+                    &returned,
+                    &ticket_key,
+                    &mut guard,
+                    now,
+                    expiry,
+                    &skp,
+                    v,
+                )
+                .err(),
+                Some("Boomerang ticket: replayed ticket")
+            );
 
-            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, ckp.clone());
+            let collection_m4 =
+                CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &collection_m3);
+
+            // A ticket is only accepted for the step it was issued for.
+            assert!(CBSM::generate_collection_m5_stateless(
+                &collection_m4,
+                &ticket_m1,
+                &ticket_key,
+                &mut guard,
+                now,
+                &skp
+            )
+            .is_err());
+            assert_eq!(guard.len(), 1);
+
+            let collection_m5 = CBSM::generate_collection_m5_stateless(
+                &collection_m4,
+                &ticket_m3,
+                &ticket_key,
+                &mut guard,
+                now,
+                &skp,
+            )
+            .unwrap();
+
+            // The signature commitment of a ticket is only ever answered once, since a second
+            // response to it would leak the server's signing key.
+            assert_eq!(
+                CBSM::generate_collection_m5_stateless(
+                    &collection_m4,
+                    &ticket_m3,
+                    &ticket_key,
+                    &mut guard,
+                    now,
+                    &skp,
+                )
+                .err(),
+                Some("Boomerang ticket: replayed ticket")
+            );
 
-            assert!(issuance_state.sig_state[0].sigma.zeta.is_on_curve());
-            assert!(issuance_state.sig_state[0].sigma.zeta1.is_on_curve());
+            // Another guard, e.g. one that is not shared with the server that accepted the
+            // ticket, does not know about it.
+            assert!(CBSM::generate_collection_m5_stateless(
+                &collection_m4,
+                &ticket_m3,
+                &ticket_key,
+                &mut MemoryReplayGuard::new(),
+                now,
+                &skp
+            )
+            .is_ok());
+
+            // The guard forgets the ticket once it has expired, when it can no longer be opened.
+            assert!(!guard.accept(ticket_m3.id().unwrap(), expiry, expiry));
+            assert!(guard.accept([0u8; 24], expiry + 2, expiry + 1));
+            assert_eq!(guard.len(), 1);
 
-            let sig = &issuance_state.sig_state[0];
+            let collection_state =
+                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, &ckp);
 
             let check = ACLSV::verify(
                 skp.s_key_pair.verifying_key,
                 skp.s_key_pair.tag_key,
-                &sig,
-                "message",
+                &collection_state.sig_state[0],
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
             );
-            assert!(check == true);
+            assert!(check);
+        }
 
-            let mut s_col_state = CBSM::default();
-            let collection_m1 = CBSM::generate_collection_m1(&mut OsRng, &mut s_col_state);
+        #[test]
+        fn test_boomerang_collection_session_mixing() {
+            // Test that a message from one collection session is rejected by another.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let mut s_col_state_a = CBSM::default();
+            let collection_m1_a = CBSM::generate_collection_m1(&mut rng, &mut s_col_state_a);
+            let mut s_col_state_b = CBSM::default();
+            let collection_m1_b = CBSM::generate_collection_m1(&mut rng, &mut s_col_state_b);
+            assert_ne!(collection_m1_a.session_id, collection_m1_b.session_id);
 
             let mut c_col_state = CBCM::default();
             let collection_m2 = CBCM::generate_collection_m2(
-                &mut OsRng,
-                issuance_state,
-                &collection_m1,
+                &mut rng,
+                &issuance_state,
+                &collection_m1_a,
+                &mut c_col_state,
+                &skp,
+            );
+            assert_eq!(collection_m2.session_id, collection_m1_a.session_id);
+
+            // The second message of session `a` is answered by the server of session `b`.
+            assert_eq!(
+                CBSM::generate_collection_m3(
+                    &mut rng,
+                    &collection_m2,
+                    &mut s_col_state_b,
+                    &skp,
+                    SF::one(),
+                )
+                .err(),
+                Some("Boomerang collection: session id mismatch")
+            );
+        }
+
+        #[test]
+        fn test_boomerang_checkpoint() {
+            // Test that a client can resume a collection run from a persisted checkpoint, and
+            // that a checkpoint only resumes the run and step that it was taken for.
+            use ::boomerang::checkpoint::Checkpoint;
+            use ::boomerang::client::CollectionM2;
+            use ::boomerang::server::CollectionM3;
+            use ark_serialize::CanonicalDeserialize;
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let mut state = IBCM::default();
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
+            let bytes = Checkpoint::new(&state, &issuance_m1).to_bytes();
+            let mut s_state = IBSM::default();
+            let issuance_m2 =
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut rng);
+            let mut state = Checkpoint::<IBCM>::from_bytes(&bytes)
+                .unwrap()
+                .resume(&issuance_m2)
+                .unwrap();
+            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut rng);
+            let issuance_m4 = IBSM::generate_issuance_m4(&issuance_m3, &mut s_state, &skp);
+            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, &ckp);
+
+            let mut s_col_state = CBSM::default();
+            let collection_m1 = CBSM::generate_collection_m1(&mut rng, &mut s_col_state);
+            let mut c_col_state = CBCM::default();
+            let collection_m2 = CBCM::generate_collection_m2(
+                &mut rng,
+                &issuance_state,
+                &collection_m1,
+                &mut c_col_state,
+                &skp,
+            );
+            let bytes = Checkpoint::new(&c_col_state, &collection_m2).to_bytes();
+            drop(c_col_state);
+
+            // The client restarts, and sends the very same message again.
+            let checkpoint = Checkpoint::<CBCM>::from_bytes(&bytes).unwrap();
+            assert_eq!(checkpoint.step(), 2);
+            assert_eq!(checkpoint.session_id(), &collection_m1.session_id);
+            let resent: CollectionM2<$boomerangconfig> = checkpoint.sent().unwrap();
+            let (mut sent_bytes, mut resent_bytes) = (Vec::new(), Vec::new());
+            collection_m2.serialize_compressed(&mut sent_bytes).unwrap();
+            resent.serialize_compressed(&mut resent_bytes).unwrap();
+            assert_eq!(sent_bytes, resent_bytes);
+
+            let collection_m3 =
+                CBSM::generate_collection_m3(&mut rng, &resent, &mut s_col_state, &skp, SF::one()).unwrap();
+
+            // The checkpoint does not resume another protocol, version, session or step.
+            assert!(Checkpoint::<SVBC>::from_bytes(&bytes).is_err());
+            let mut other_version = bytes.clone();
+            other_version[0] += 1;
+            assert!(Checkpoint::<CBCM>::from_bytes(&other_version).is_err());
+            let mut trailing = bytes.clone();
+            trailing.push(0);
+            assert!(Checkpoint::<CBCM>::from_bytes(&trailing).is_err());
+            assert!(Checkpoint::<CBCM>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+            let mut m3_bytes = Vec::new();
+            collection_m3.serialize_compressed(&mut m3_bytes).unwrap();
+            let mut other_session =
+                CollectionM3::<$boomerangconfig>::deserialize_compressed(&m3_bytes[..]).unwrap();
+            other_session.session_id[0] ^= 1;
+            assert_eq!(
+                checkpoint.clone().resume(&other_session).err(),
+                Some("Boomerang checkpoint: session id mismatch")
+            );
+            assert!(checkpoint.clone().resume(&collection_m1).is_err());
+
+            // The checkpoint is sealed while it is persisted.
+            let key = TicketKey::generate(&mut rng);
+            let sealed = checkpoint.seal(&mut rng, &key, 100);
+            assert!(Checkpoint::<SVBC>::open(&key, 50, &sealed).is_err());
+            assert!(Checkpoint::<CBCM>::open(&key, 101, &sealed).is_err());
+            let checkpoint = Checkpoint::<CBCM>::open(&key, 50, &sealed).unwrap();
+
+            let mut c_col_state = checkpoint.resume(&collection_m3).unwrap();
+            let collection_m4 =
+                CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &collection_m3);
+            let bytes = Checkpoint::new(&c_col_state, &collection_m4).to_bytes();
+            let collection_m5 =
+                CBSM::generate_collection_m5(&collection_m4, &mut s_col_state, &skp);
+
+            let checkpoint = Checkpoint::<CBCM>::from_bytes(&bytes).unwrap();
+            assert!(checkpoint.clone().resume(&collection_m3).is_err());
+            let mut c_col_state = checkpoint.resume(&collection_m5).unwrap();
+            let collection_state =
+                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, &ckp);
+
+            assert!(ACLSV::verify(
+                skp.s_key_pair.verifying_key,
+                skp.s_key_pair.tag_key,
+                &collection_state.sig_state[0],
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
+            ));
+        }
+
+        #[test]
+        fn test_boomerang_update() {
+            // Test that a token can gain and update an attribute, and that the attribute
+            // survives the other protocols.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let mut token_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+            assert!(token_state.token_state[0].attrs().is_empty());
+
+            // The first update adds a new attribute, and the second one adds to it.
+            for (delta, expected) in [(2u64, 2u64), (1, 3)] {
+                let mut s_up_state = UBSM::default();
+                let update_m1 =
+                    UBSM::generate_update_m1(&mut rng, &mut s_up_state, BASE_ATTRIBUTES);
+
+                let mut c_up_state = UBCM::default();
+                let update_m2 = UBCM::generate_update_m2(
+                    &mut rng,
+                    &token_state,
+                    &update_m1,
+                    &mut c_up_state,
+                    &skp,
+                );
+                let update_m3 = UBSM::generate_update_m3(
+                    &mut rng,
+                    &update_m2,
+                    &mut s_up_state,
+                    &skp,
+                    SF::from(delta),
+                ).unwrap();
+                let update_m4 = UBCM::generate_update_m4(&mut rng, &mut c_up_state, &update_m3);
+                let update_m5 = UBSM::generate_update_m5(&update_m4, &mut s_up_state, &skp);
+                token_state = UBCM::populate_state(&mut c_up_state, &update_m5, &skp, &ckp);
+
+                assert_eq!(token_state.token_state[0].attrs(), &[SF::from(expected)]);
+                let check = ACLSV::verify(
+                    skp.s_key_pair.verifying_key,
+                    skp.s_key_pair.tag_key,
+                    &token_state.sig_state[0],
+                    &skp.token_message(),
+                    Some(TOKEN_CONTEXT),
+                );
+                assert!(check);
+            }
+
+            // The updated token can still be used for collection.
+            let mut s_col_state = CBSM::default();
+            let collection_m1 = CBSM::generate_collection_m1(&mut rng, &mut s_col_state);
+            let mut c_col_state = CBCM::default();
+            let collection_m2 = CBCM::generate_collection_m2(
+                &mut rng,
+                &token_state,
+                &collection_m1,
+                &mut c_col_state,
+                &skp,
+            );
+            let collection_m3 = CBSM::generate_collection_m3(
+                &mut rng,
+                &collection_m2,
+                &mut s_col_state,
+                &skp,
+                SF::one(),
+            ).unwrap();
+            let collection_m4 =
+                CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &collection_m3);
+            let collection_m5 =
+                CBSM::generate_collection_m5(&collection_m4, &mut s_col_state, &skp);
+            let collection_state =
+                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, &ckp);
+            assert_eq!(collection_state.token_state[0].attrs(), &[SF::from(3u64)]);
+
+            // And for spending.
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &collection_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::one()],
+            );
+            assert!(SVBS::verify_spendverify_m2(&spendverify_m2, &skp).is_ok());
+        }
+
+        #[test]
+        #[should_panic(expected = "Boomerang update: attribute cannot be updated")]
+        fn test_boomerang_update_secret_key() {
+            // Test that the secret key of a token cannot be updated.
+            let mut rng = TestRng::from_env();
+            let mut s_up_state = UBSM::default();
+            UBSM::generate_update_m1(&mut rng, &mut s_up_state, 2);
+        }
+
+        #[test]
+        fn test_boomerang_spend_verify_stateless_replay() {
+            // Test that a stateless server only answers the ticket of a spend once.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let ticket_key = TicketKey::generate(&mut rng);
+            let (now, expiry) = (1000, 1060);
+            let (spendverify_m1, ticket_m1) =
+                SVBS::generate_spendverify_m1_stateless(&mut rng, &ticket_key, expiry);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &issuance_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::zero()],
+            );
+            let mut guard = MemoryReplayGuard::new();
+            let (spendverify_m3, ticket_m3) = SVBS::generate_spendverify_m3_stateless(
+                &mut rng,
+                &spendverify_m2,
+                &ticket_m1,
+                &ticket_key,
+                &mut guard,
+                now,
+                expiry,
+                &skp,
+                vec![SF::zero()],
+            )
+            .unwrap();
+            assert_eq!(
+                SVBS::generate_spendverify_m3_stateless(
+                    &mut rng,
+                    &spendverify_m2,
+                    &ticket_m1,
+                    &ticket_key,
+                    &mut guard,
+                    now,
+                    expiry,
+                    &skp,
+                    vec![SF::zero()],
+                )
+                .err(),
+                Some("Boomerang ticket: replayed ticket")
+            );
+            let spendverify_m4 = SVBC::generate_spendverify_m4(
+                &mut rng,
+                &mut c_spend_state,
+                &spendverify_m3,
+                &skp.policy_id(&[SF::zero()]),
+            )
+            .unwrap();
+
+            assert!(SVBS::generate_spendverify_m5_stateless(
+                &spendverify_m4,
+                &ticket_m3,
+                &ticket_key,
+                &mut guard,
+                now,
+                &skp
+            )
+            .is_ok());
+            assert_eq!(
+                SVBS::generate_spendverify_m5_stateless(
+                    &spendverify_m4,
+                    &ticket_m3,
+                    &ticket_key,
+                    &mut guard,
+                    now,
+                    &skp
+                )
+                .err(),
+                Some("Boomerang ticket: replayed ticket")
+            );
+        }
+
+        #[test]
+        fn test_boomerang_accumulate() {
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            assert!(ckp.public_key.is_on_curve());
+
+            let skp = SBKP::generate(&mut rng);
+            assert!(skp.s_key_pair.verifying_key.is_on_curve());
+            assert!(skp.s_key_pair.tag_key.is_on_curve());
+
+            let mut state = IBCM::default();
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
+            assert!(issuance_m1.u_pk.is_on_curve());
+
+            let mut s_state = IBSM::default();
+            let issuance_m2 =
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut rng);
+            assert!(issuance_m2.verifying_key.is_on_curve());
+            assert!(issuance_m2.tag_key.is_on_curve());
+
+            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut rng);
+
+            let issuance_m4 = IBSM::generate_issuance_m4(&issuance_m3, &mut s_state, &skp);
+
+            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, &ckp);
+
+            assert!(issuance_state.sig_state[0].sigma.zeta.is_on_curve());
+            assert!(issuance_state.sig_state[0].sigma.zeta1.is_on_curve());
+
+            let sig = &issuance_state.sig_state[0];
+
+            let check = ACLSV::verify(
+                skp.s_key_pair.verifying_key,
+                skp.s_key_pair.tag_key,
+                &sig,
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
+            );
+            assert!(check == true);
+
+            let mut s_col_state = CBSM::default();
+            let collection_m1 = CBSM::generate_collection_m1(&mut rng, &mut s_col_state);
+
+            let mut c_col_state = CBCM::default();
+            let collection_m2 = CBCM::generate_collection_m2(
+                &mut rng,
+                &issuance_state,
+                &collection_m1,
                 &mut c_col_state,
                 &skp,
             );
@@ -397,18 +1753,18 @@ macro_rules! __test_boomerang {
 
             let v = SF::one();
             let collection_m3 =
-                CBSM::generate_collection_m3(&mut OsRng, &collection_m2, &mut s_col_state, &skp, v);
+                CBSM::generate_collection_m3(&mut rng, &collection_m2, &mut s_col_state, &skp, v).unwrap();
 
             assert!(collection_m3.comm.comm.is_on_curve());
 
             let collection_m4 =
-                CBCM::generate_collection_m4(&mut OsRng, &mut c_col_state, &collection_m3);
+                CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &collection_m3);
 
             let collection_m5 =
                 CBSM::generate_collection_m5(&collection_m4, &mut s_col_state, &skp);
 
             let collection_state =
-                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, ckp.clone());
+                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, &ckp);
 
             let sig_n = &collection_state.sig_state[0];
 
@@ -416,17 +1772,18 @@ macro_rules! __test_boomerang {
                 skp.s_key_pair.verifying_key,
                 skp.s_key_pair.tag_key,
                 &sig_n,
-                "message",
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
             );
             assert!(check == true);
 
             let mut s_col_state_2 = CBSM::default();
-            let collection_m1_2 = CBSM::generate_collection_m1(&mut OsRng, &mut s_col_state_2);
+            let collection_m1_2 = CBSM::generate_collection_m1(&mut rng, &mut s_col_state_2);
 
             let mut c_col_state_2 = CBCM::default();
             let collection_m2_2 = CBCM::generate_collection_m2(
-                &mut OsRng,
-                collection_state,
+                &mut rng,
+                &collection_state,
                 &collection_m1_2,
                 &mut c_col_state_2,
                 &skp,
@@ -436,23 +1793,23 @@ macro_rules! __test_boomerang {
 
             let v = SF::one();
             let collection_m3_2 = CBSM::generate_collection_m3(
-                &mut OsRng,
+                &mut rng,
                 &collection_m2_2,
                 &mut s_col_state_2,
                 &skp,
                 v,
-            );
+            ).unwrap();
 
             assert!(collection_m3_2.comm.comm.is_on_curve());
 
             let collection_m4_2 =
-                CBCM::generate_collection_m4(&mut OsRng, &mut c_col_state_2, &collection_m3_2);
+                CBCM::generate_collection_m4(&mut rng, &mut c_col_state_2, &collection_m3_2);
 
             let collection_m5_2 =
                 CBSM::generate_collection_m5(&collection_m4_2, &mut s_col_state_2, &skp);
 
             let collection_state_2 =
-                CBCM::populate_state(&mut c_col_state_2, &collection_m5_2, &skp, ckp.clone());
+                CBCM::populate_state(&mut c_col_state_2, &collection_m5_2, &skp, &ckp);
 
             assert!(collection_state_2.sig_state[0].sigma.zeta.is_on_curve());
             assert!(collection_state_2.sig_state[0].sigma.zeta1.is_on_curve());
@@ -463,35 +1820,152 @@ macro_rules! __test_boomerang {
                 skp.s_key_pair.verifying_key,
                 skp.s_key_pair.tag_key,
                 &sig_n_2,
-                "message",
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
             );
             assert!(check_2 == true);
         }
 
+        #[test]
+        fn test_boomerang_conservation() {
+            // Test that the balance of the token is the sum of the collected values, less the
+            // spent value, for a random number of collections of random values, and that a
+            // client that adds to the value of its token is rejected on its next spend.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let mut c_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+            assert!(c_state.is_consistent());
+            assert_eq!(c_state.balance(), SF::zero());
+
+            // Runs a collection of `v`, and adds `extra` to the value that the server sent.
+            let collect = |rng: &mut TestRng, c_state: &_, v: u64, extra: u64| {
+                let mut s_col_state = CBSM::default();
+                let m1 = CBSM::generate_collection_m1(rng, &mut s_col_state);
+                let mut c_col_state = CBCM::default();
+                let m2 = CBCM::generate_collection_m2(rng, c_state, &m1, &mut c_col_state, &skp);
+                let mut m3 =
+                    CBSM::generate_collection_m3(rng, &m2, &mut s_col_state, &skp, SF::from(v)).unwrap();
+                m3.val += SF::from(extra);
+                let m4 = CBCM::generate_collection_m4(rng, &mut c_col_state, &m3);
+                let m5 = CBSM::generate_collection_m5(&m4, &mut s_col_state, &skp);
+                CBCM::populate_state(&mut c_col_state, &m5, &skp, &ckp)
+            };
+
+            let k = 1 + rng.next_u64() % 4;
+            let mut sum = 0u64;
+            for _ in 0..k {
+                let v = rng.next_u64() % 1000;
+                c_state = collect(&mut rng, &c_state, v, 0);
+                sum += v;
+                assert!(c_state.is_consistent());
+                assert_eq!(c_state.balance(), SF::from(sum));
+            }
+
+            // Spend part of the balance.
+            let spend = rng.next_u64() % (sum + 1);
+            let mut s_spend_state = SVBS::default();
+            let m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spend_state = vec![SF::from(spend)];
+            let m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &c_state,
+                &mut c_spend_state,
+                &m1,
+                &skp,
+                &spend_state,
+            );
+            assert!(SVBS::verify_spendverify_m2(&m2, &skp).is_ok());
+            let m3 = SVBS::generate_spendverify_m3(
+                &mut rng,
+                &m2,
+                &mut s_spend_state,
+                &skp,
+                vec![SF::one()],
+            ).unwrap();
+            let m4 = SVBC::generate_spendverify_m4(
+                &mut rng,
+                &mut c_spend_state,
+                &m3,
+                &skp.policy_id(&[SF::one()]),
+            )
+            .unwrap();
+            let m5 = SVBS::generate_spendverify_m5(&m4, &mut s_spend_state, &skp);
+            let c_state = SVBC::populate_state(&mut c_spend_state, &m5, &skp, &ckp);
+            assert!(c_state.is_consistent());
+            assert_eq!(c_state.balance(), SF::from(sum - spend));
+
+            // A client that adds to the collected value holds a token that does not open its
+            // signed commitment, and the server rejects its next spend.
+            let v = rng.next_u64() % 1000;
+            let extra = 1 + rng.next_u64() % 1000;
+            let tampered = collect(&mut rng, &c_state, v, extra);
+            assert!(!tampered.is_consistent());
+            assert_eq!(tampered.balance(), SF::from(sum - spend + v + extra));
+
+            let mut s_spend_state = SVBS::default();
+            let m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &tampered,
+                &mut c_spend_state,
+                &m1,
+                &skp,
+                &[SF::zero()],
+            );
+            assert!(SVBS::verify_spendverify_m2(&m2, &skp).is_err());
+        }
+
+        #[test]
+        fn test_boomerang_state_commitment() {
+            // Test that both commitment backends open to their values, and add them up.
+            fn check<C: StateCommitment<$boomerangconfig>>(key: &C::Key) {
+                let mut rng = TestRng::from_env();
+                let a: Vec<SF> = (0..4).map(|_| SF::rand(&mut rng)).collect();
+                let b: Vec<SF> = (0..4).map(|_| SF::rand(&mut rng)).collect();
+                let sum: Vec<SF> = a.iter().zip(&b).map(|(x, y)| *x + y).collect();
+
+                let ca = C::commit(&a, key, &mut rng);
+                let cb = C::commit(&b, key, &mut rng);
+                assert!(ca.opens(&a, key));
+                assert!(!ca.opens(&b, key));
+                assert!(!ca.opens(&a[..3], key));
+                assert!(ca.combine(&cb).opens(&sum, key));
+            }
+
+            let (_, gens) = PC::new_multi(&[SF::zero(); 4], &mut TestRng::from_env());
+            check::<PC>(&gens);
+            check::<SplitComm<$boomerangconfig>>(&());
+        }
+
         #[test]
         fn test_boomerang_spend_verify_round_m1() {
             // Test the full boomerang issuance scheme.
-            let ckp = CBKP::generate(&mut OsRng);
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
             assert!(ckp.public_key.is_on_curve());
 
-            let skp = SBKP::generate(&mut OsRng);
+            let skp = SBKP::generate(&mut rng);
             assert!(skp.s_key_pair.verifying_key.is_on_curve());
             assert!(skp.s_key_pair.tag_key.is_on_curve());
 
             let mut state = IBCM::default();
-            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut OsRng);
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
             assert!(issuance_m1.u_pk.is_on_curve());
 
             let mut s_state = IBSM::default();
             let issuance_m2 =
-                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut OsRng);
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut rng);
             assert!(issuance_m2.verifying_key.is_on_curve());
             assert!(issuance_m2.tag_key.is_on_curve());
 
-            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut OsRng);
+            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut rng);
 
             let issuance_m4 = IBSM::generate_issuance_m4(&issuance_m3, &mut s_state, &skp);
-            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, ckp.clone());
+            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, &ckp);
 
             assert!(issuance_state.sig_state[0].sigma.zeta.is_on_curve());
             assert!(issuance_state.sig_state[0].sigma.zeta1.is_on_curve());
@@ -502,17 +1976,18 @@ macro_rules! __test_boomerang {
                 skp.s_key_pair.verifying_key,
                 skp.s_key_pair.tag_key,
                 &sig,
-                "message",
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
             );
             assert!(check == true);
 
             let mut s_col_state = CBSM::default();
-            let collection_m1 = CBSM::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let collection_m1 = CBSM::generate_collection_m1(&mut rng, &mut s_col_state);
 
             let mut c_col_state = CBCM::default();
             let collection_m2 = CBCM::generate_collection_m2(
-                &mut OsRng,
-                issuance_state,
+                &mut rng,
+                &issuance_state,
                 &collection_m1,
                 &mut c_col_state,
                 &skp,
@@ -522,18 +1997,18 @@ macro_rules! __test_boomerang {
 
             let v = SF::one();
             let collection_m3 =
-                CBSM::generate_collection_m3(&mut OsRng, &collection_m2, &mut s_col_state, &skp, v);
+                CBSM::generate_collection_m3(&mut rng, &collection_m2, &mut s_col_state, &skp, v).unwrap();
 
             assert!(collection_m3.comm.comm.is_on_curve());
 
             let collection_m4 =
-                CBCM::generate_collection_m4(&mut OsRng, &mut c_col_state, &collection_m3);
+                CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &collection_m3);
 
             let collection_m5 =
                 CBSM::generate_collection_m5(&collection_m4, &mut s_col_state, &skp);
 
             let collection_state =
-                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, ckp.clone());
+                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, &ckp);
 
             assert!(collection_state.sig_state[0].sigma.zeta.is_on_curve());
             assert!(collection_state.sig_state[0].sigma.zeta1.is_on_curve());
@@ -544,23 +2019,24 @@ macro_rules! __test_boomerang {
                 skp.s_key_pair.verifying_key,
                 skp.s_key_pair.tag_key,
                 &sig_n,
-                "message",
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
             );
             assert!(check == true);
 
             // Start Spend/Verify protocol
             let mut s_spend_state = SVBS::default();
-            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut OsRng, &mut s_spend_state);
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
 
             let spend_state: Vec<SF> = vec![SF::one()];
             let mut c_spend_state = SVBC::default();
             let spendverify_m2 = SVBC::generate_spendverify_m2(
-                &mut OsRng,
-                collection_state,
+                &mut rng,
+                &collection_state,
                 &mut c_spend_state,
                 &spendverify_m1,
                 &skp,
-                spend_state,
+                &spend_state,
             );
             assert!(spendverify_m2.comm.comm.is_on_curve());
         }
@@ -568,28 +2044,29 @@ macro_rules! __test_boomerang {
         #[test]
         fn test_boomerang_spend_verify_round_m2() {
             // Test the full boomerang issuance scheme.
-            let ckp = CBKP::generate(&mut OsRng);
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
             assert!(ckp.public_key.is_on_curve());
 
-            let skp = SBKP::generate(&mut OsRng);
+            let skp = SBKP::generate(&mut rng);
             assert!(skp.s_key_pair.verifying_key.is_on_curve());
             assert!(skp.s_key_pair.tag_key.is_on_curve());
 
             let mut state = IBCM::default();
-            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut OsRng);
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
             assert!(issuance_m1.u_pk.is_on_curve());
 
             let mut s_state = IBSM::default();
             let issuance_m2 =
-                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut OsRng);
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut rng);
             assert!(issuance_m2.verifying_key.is_on_curve());
             assert!(issuance_m2.tag_key.is_on_curve());
 
-            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut OsRng);
+            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut rng);
 
             let issuance_m4 = IBSM::generate_issuance_m4(&issuance_m3, &mut s_state, &skp);
 
-            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, ckp.clone());
+            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, &ckp);
 
             assert!(issuance_state.sig_state[0].sigma.zeta.is_on_curve());
             assert!(issuance_state.sig_state[0].sigma.zeta1.is_on_curve());
@@ -600,17 +2077,18 @@ macro_rules! __test_boomerang {
                 skp.s_key_pair.verifying_key,
                 skp.s_key_pair.tag_key,
                 &sig,
-                "message",
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
             );
             assert!(check == true);
 
             let mut s_col_state = CBSM::default();
-            let collection_m1 = CBSM::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let collection_m1 = CBSM::generate_collection_m1(&mut rng, &mut s_col_state);
 
             let mut c_col_state = CBCM::default();
             let collection_m2 = CBCM::generate_collection_m2(
-                &mut OsRng,
-                issuance_state,
+                &mut rng,
+                &issuance_state,
                 &collection_m1,
                 &mut c_col_state,
                 &skp,
@@ -620,18 +2098,18 @@ macro_rules! __test_boomerang {
 
             let v = SF::one();
             let collection_m3 =
-                CBSM::generate_collection_m3(&mut OsRng, &collection_m2, &mut s_col_state, &skp, v);
+                CBSM::generate_collection_m3(&mut rng, &collection_m2, &mut s_col_state, &skp, v).unwrap();
 
             assert!(collection_m3.comm.comm.is_on_curve());
 
             let collection_m4 =
-                CBCM::generate_collection_m4(&mut OsRng, &mut c_col_state, &collection_m3);
+                CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &collection_m3);
 
             let collection_m5 =
                 CBSM::generate_collection_m5(&collection_m4, &mut s_col_state, &skp);
 
             let collection_state =
-                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, ckp.clone());
+                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, &ckp);
 
             assert!(collection_state.sig_state[0].sigma.zeta.is_on_curve());
             assert!(collection_state.sig_state[0].sigma.zeta1.is_on_curve());
@@ -642,66 +2120,73 @@ macro_rules! __test_boomerang {
                 skp.s_key_pair.verifying_key,
                 skp.s_key_pair.tag_key,
                 &sig_n,
-                "message",
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
             );
             assert!(check == true);
 
             // Start Spend/Verify protocol
             let mut s_spend_state = SVBS::default();
-            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut OsRng, &mut s_spend_state);
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
 
             let spend_state: Vec<SF> = vec![SF::one()];
             let mut c_spend_state = SVBC::default();
             let spendverify_m2 = SVBC::generate_spendverify_m2(
-                &mut OsRng,
-                collection_state,
+                &mut rng,
+                &collection_state,
                 &mut c_spend_state,
                 &spendverify_m1,
                 &skp,
-                spend_state,
+                &spend_state,
             );
             assert!(spendverify_m2.comm.comm.is_on_curve());
 
             // Create reward proof - server side
             let policy_state: Vec<SF> = vec![SF::from(2)];
             let spendverify_m3 = SVBS::generate_spendverify_m3(
-                &mut OsRng,
+                &mut rng,
                 &spendverify_m2,
                 &mut s_spend_state,
                 &skp,
                 policy_state.clone(),
-            );
+            ).unwrap();
             assert!(spendverify_m3.comm.comm.is_on_curve());
 
-            let spendverify_m4 =
-                SVBC::generate_spendverify_m4(&mut OsRng, &mut c_spend_state, &spendverify_m3);
+            let spendverify_m4 = SVBC::generate_spendverify_m4(
+                &mut rng,
+                &mut c_spend_state,
+                &spendverify_m3,
+                &skp.policy_id(&policy_state),
+            )
+            .unwrap();
         }
 
         #[test]
         fn test_boomerang_spend_verify_round_m3() {
             // Test the full boomerang issuance scheme.
-            let ckp = CBKP::generate(&mut OsRng);
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
             assert!(ckp.public_key.is_on_curve());
 
-            let skp = SBKP::generate(&mut OsRng);
+            let skp = SBKP::generate(&mut rng);
             assert!(skp.s_key_pair.verifying_key.is_on_curve());
             assert!(skp.s_key_pair.tag_key.is_on_curve());
 
             let mut state = IBCM::default();
-            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut OsRng);
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
             assert!(issuance_m1.u_pk.is_on_curve());
 
             let mut s_state = IBSM::default();
             let issuance_m2 =
-                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut OsRng);
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut rng);
             assert!(issuance_m2.verifying_key.is_on_curve());
             assert!(issuance_m2.tag_key.is_on_curve());
 
-            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut OsRng);
+            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut rng);
 
             let issuance_m4 = IBSM::generate_issuance_m4(&issuance_m3, &mut s_state, &skp);
 
-            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, ckp.clone());
+            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, &ckp);
 
             assert!(issuance_state.sig_state[0].sigma.zeta.is_on_curve());
             assert!(issuance_state.sig_state[0].sigma.zeta1.is_on_curve());
@@ -712,17 +2197,18 @@ macro_rules! __test_boomerang {
                 skp.s_key_pair.verifying_key,
                 skp.s_key_pair.tag_key,
                 &sig,
-                "message",
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
             );
             assert!(check == true);
 
             let mut s_col_state = CBSM::default();
-            let collection_m1 = CBSM::generate_collection_m1(&mut OsRng, &mut s_col_state);
+            let collection_m1 = CBSM::generate_collection_m1(&mut rng, &mut s_col_state);
 
             let mut c_col_state = CBCM::default();
             let collection_m2 = CBCM::generate_collection_m2(
-                &mut OsRng,
-                issuance_state,
+                &mut rng,
+                &issuance_state,
                 &collection_m1,
                 &mut c_col_state,
                 &skp,
@@ -732,18 +2218,18 @@ macro_rules! __test_boomerang {
 
             let v = SF::one();
             let collection_m3 =
-                CBSM::generate_collection_m3(&mut OsRng, &collection_m2, &mut s_col_state, &skp, v);
+                CBSM::generate_collection_m3(&mut rng, &collection_m2, &mut s_col_state, &skp, v).unwrap();
 
             assert!(collection_m3.comm.comm.is_on_curve());
 
             let collection_m4 =
-                CBCM::generate_collection_m4(&mut OsRng, &mut c_col_state, &collection_m3);
+                CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &collection_m3);
 
             let collection_m5 =
                 CBSM::generate_collection_m5(&collection_m4, &mut s_col_state, &skp);
 
             let collection_state =
-                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, ckp.clone());
+                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, &ckp);
 
             assert!(collection_state.sig_state[0].sigma.zeta.is_on_curve());
             assert!(collection_state.sig_state[0].sigma.zeta1.is_on_curve());
@@ -754,45 +2240,51 @@ macro_rules! __test_boomerang {
                 skp.s_key_pair.verifying_key,
                 skp.s_key_pair.tag_key,
                 &sig_n,
-                "message",
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
             );
             assert!(check == true);
 
             // Start Spend/Verify protocol
             let mut s_spend_state = SVBS::default();
-            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut OsRng, &mut s_spend_state);
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
 
             let spend_state: Vec<SF> = vec![SF::one()];
             let mut c_spend_state = SVBC::default();
             let spendverify_m2 = SVBC::generate_spendverify_m2(
-                &mut OsRng,
-                collection_state,
+                &mut rng,
+                &collection_state,
                 &mut c_spend_state,
                 &spendverify_m1,
                 &skp,
-                spend_state,
+                &spend_state,
             );
             assert!(spendverify_m2.comm.comm.is_on_curve());
 
             // Create reward proof - server side
             let policy_state: Vec<SF> = vec![SF::from(2)];
             let spendverify_m3 = SVBS::generate_spendverify_m3(
-                &mut OsRng,
+                &mut rng,
                 &spendverify_m2,
                 &mut s_spend_state,
                 &skp,
                 policy_state.clone(),
-            );
+            ).unwrap();
             assert!(spendverify_m3.comm.comm.is_on_curve());
 
-            let spendverify_m4 =
-                SVBC::generate_spendverify_m4(&mut OsRng, &mut c_spend_state, &spendverify_m3);
+            let spendverify_m4 = SVBC::generate_spendverify_m4(
+                &mut rng,
+                &mut c_spend_state,
+                &spendverify_m3,
+                &skp.policy_id(&policy_state),
+            )
+            .unwrap();
 
             let spendverify_m5 =
                 SVBS::generate_spendverify_m5(&spendverify_m4, &mut s_spend_state, &skp);
 
             let spendverify_state =
-                SVBC::populate_state(&mut c_spend_state, &spendverify_m5, &skp, ckp.clone());
+                SVBC::populate_state(&mut c_spend_state, &spendverify_m5, &skp, &ckp);
             assert!(spendverify_state.sig_state[0].sigma.zeta.is_on_curve());
             assert!(spendverify_state.sig_state[0].sigma.zeta1.is_on_curve());
 
@@ -802,9 +2294,2656 @@ macro_rules! __test_boomerang {
                 skp.s_key_pair.verifying_key,
                 skp.s_key_pair.tag_key,
                 &sig_s,
-                "message",
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
+            );
+            assert!(check == true);
+        }
+
+        #[test]
+        fn test_boomerang_spend_verify_pool() {
+            // Test that spend messages can be verified on a verification pool.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            assert!(ckp.public_key.is_on_curve());
+
+            let skp = SBKP::generate(&mut rng);
+            assert!(skp.s_key_pair.verifying_key.is_on_curve());
+            assert!(skp.s_key_pair.tag_key.is_on_curve());
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let mut s_col_state = CBSM::default();
+            let collection_m1 = CBSM::generate_collection_m1(&mut rng, &mut s_col_state);
+
+            let mut c_col_state = CBCM::default();
+            let collection_m2 = CBCM::generate_collection_m2(
+                &mut rng,
+                &issuance_state,
+                &collection_m1,
+                &mut c_col_state,
+                &skp,
+            );
+
+            assert!(collection_m2.comm.comm.is_on_curve());
+
+            let v = SF::one();
+            let collection_m3 =
+                CBSM::generate_collection_m3(&mut rng, &collection_m2, &mut s_col_state, &skp, v).unwrap();
+
+            assert!(collection_m3.comm.comm.is_on_curve());
+
+            let collection_m4 =
+                CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &collection_m3);
+
+            let collection_m5 =
+                CBSM::generate_collection_m5(&collection_m4, &mut s_col_state, &skp);
+
+            let collection_state =
+                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, &ckp);
+
+            assert!(collection_state.sig_state[0].sigma.zeta.is_on_curve());
+            assert!(collection_state.sig_state[0].sigma.zeta1.is_on_curve());
+
+            let sig_n = &collection_state.sig_state[0];
+
+            let check = ACLSV::verify(
+                skp.s_key_pair.verifying_key,
+                skp.s_key_pair.tag_key,
+                &sig_n,
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
             );
             assert!(check == true);
+
+            // Start Spend/Verify protocol
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+
+            let spend_state: Vec<SF> = vec![SF::one()];
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &collection_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &spend_state,
+            );
+            assert!(spendverify_m2.comm.comm.is_on_curve());
+
+            let pool = VerificationPool::new(skp.clone(), 2, 4);
+
+            // A valid message passes, both on the pool and inline.
+            let mut bad_m2 = spendverify_m2.clone();
+            assert!(pool.submit(spendverify_m2.clone()).wait().is_ok());
+            assert!(SVBS::verify_spendverify_m2(&spendverify_m2, &skp).is_ok());
+
+            // A message whose tag has been swapped is rejected without panicking.
+            bad_m2.tag_commits.swap(0, 1);
+            assert_eq!(
+                pool.submit(bad_m2).wait(),
+                Err("Boomerang spend-verify: invalid proof of tag")
+            );
+
+            // Once the single worker is busy and the queue is full, new messages are handed back.
+            let pool = VerificationPool::new(skp.clone(), 1, 1);
+            let (release, blocked) = std::sync::mpsc::channel::<()>();
+            let busy = pool.spawn(move || blocked.recv().map_err(|_| "not released"));
+            let queued = pool.submit(spendverify_m2.clone());
+            let Err(spendverify_m2) = pool.try_submit(spendverify_m2) else {
+                panic!("the queue should be full");
+            };
+
+            release.send(()).unwrap();
+            assert!(busy.wait().is_ok());
+            assert!(queued.wait().is_ok());
+            let retried = pool.try_submit(*spendverify_m2).ok().unwrap();
+            assert!(retried.wait().is_ok());
+        }
+
+        #[test]
+        fn test_boomerang_protocol_context() {
+            // Test that a proof made in the context of one application does not verify in
+            // the context of another.
+            use pedersen::opening_protocol::OpeningProofMulti;
+
+            let mut rng = TestRng::from_env();
+            let label = b"BoomerangContext";
+            let app_a = ProtocolContext::new(b"app-a");
+            let app_b = ProtocolContext::new(b"app-b");
+
+            let session_id = new_session_id(&mut rng);
+            let vals: Vec<SF> = (0..4).map(|_| SF::rand(&mut rng)).collect();
+            let (comm, gens) = PedersenComm::<$boomerangconfig>::new_multi(&vals, &mut rng);
+
+            let mut transcript = app_a.session_transcript(label, &session_id);
+            let proof = OpeningProofMulti::create(&mut transcript, &mut rng, &vals, &comm, &gens);
+
+            let mut transcript_v = app_a.session_transcript(label, &session_id);
+            assert!(proof.verify(&mut transcript_v, &comm.comm, vals.len(), &gens));
+            let mut transcript_f = app_b.session_transcript(label, &session_id);
+            assert!(!proof.verify(&mut transcript_f, &comm.comm, vals.len(), &gens));
+
+            // The protocols use the context of the config, which is the default one here.
+            assert_eq!(
+                <$boomerangconfig as BoomerangConfig>::CONTEXT,
+                ProtocolContext::default()
+            );
+            let mut transcript_c = session_transcript::<$boomerangconfig>(label, &session_id);
+            let mut transcript_d = ProtocolContext::DEFAULT.session_transcript(label, &session_id);
+            let mut chal_c = [0u8; 32];
+            let mut chal_d = [0u8; 32];
+            transcript_c.challenge_bytes(b"check", &mut chal_c);
+            transcript_d.challenge_bytes(b"check", &mut chal_d);
+            assert_eq!(chal_c, chal_d);
+        }
+
+        #[test]
+        fn test_boomerang_rewards_proof_with_commitment() {
+            // Test a rewards proof against an existing commitment to the secret vector.
+            let mut rng = TestRng::from_env();
+            type RWP = BRewardsProof<$boomerangconfig>;
+            let gens = RewardsGenerators::<$boomerangconfig>::default();
+
+            let session_id = new_session_id(&mut rng);
+            let public_state: Vec<SF> = vec![SF::from(1u64), SF::from(4u64)];
+            let committed_state: Vec<SF> = vec![SF::from(2u64), SF::from(3u64)];
+            let blind = SF::rand(&mut rng);
+            let comm = RWP::commit_state(&gens, &committed_state, blind);
+
+            let proof = RWP::prove_with_commitment(
+                &gens,
+                &public_state,
+                &committed_state,
+                &comm,
+                blind,
+                14,
+                &session_id,
+                &mut rng,
+            )
+            .unwrap();
+            assert!(proof
+                .verify_with_commitment(&gens, &public_state, &comm, &session_id)
+                .is_ok());
+
+            // The proof only holds for the commitment it was made against.
+            let other_comm = RWP::commit_state(&gens, &committed_state, SF::rand(&mut rng));
+            assert!(proof
+                .verify_with_commitment(&gens, &public_state, &other_comm, &session_id)
+                .is_err());
+            assert!(proof
+                .verify_with_commitment(&gens, &public_state, &comm, &new_session_id(&mut rng))
+                .is_err());
+
+            // A reward that is not the inner product of the committed vector is rejected.
+            let bad_proof = RWP::prove_with_commitment(
+                &gens,
+                &public_state,
+                &committed_state,
+                &comm,
+                blind,
+                15,
+                &session_id,
+                &mut rng,
+            )
+            .unwrap();
+            assert!(bad_proof
+                .verify_with_commitment(&gens, &public_state, &comm, &session_id)
+                .is_err());
+        }
+
+        #[test]
+        fn test_boomerang_joint_rewards_proof() {
+            // Test a joint rewards proof, whose range and linear proofs share a transcript.
+            use ark_serialize::CanonicalSerialize;
+            let mut rng = TestRng::from_env();
+            type RWP = BRewardsProof<$boomerangconfig>;
+            type JRWP = JointRewardsProof<$boomerangconfig>;
+            let gens = RewardsGenerators::<$boomerangconfig>::default();
+
+            let session_id = new_session_id(&mut rng);
+            let public_state: Vec<SF> = vec![SF::from(1u64), SF::from(4u64)];
+            let committed_state: Vec<SF> = vec![SF::from(2u64), SF::from(3u64)];
+            let blind = SF::rand(&mut rng);
+            let comm = RWP::commit_state(&gens, &committed_state, blind);
+
+            let proof = JRWP::prove(
+                &gens,
+                &public_state,
+                &committed_state,
+                &comm,
+                blind,
+                14,
+                &session_id,
+                &mut rng,
+            )
+            .unwrap();
+            assert!(proof
+                .verify(&gens, &public_state, &comm, &session_id)
+                .is_ok());
+
+            // The proof only holds for its commitment, public state and session.
+            let other_comm = RWP::commit_state(&gens, &committed_state, SF::rand(&mut rng));
+            assert!(proof
+                .verify(&gens, &public_state, &other_comm, &session_id)
+                .is_err());
+            assert!(proof
+                .verify(&gens, &[SF::from(1u64), SF::from(5u64)], &comm, &session_id)
+                .is_err());
+            assert!(proof
+                .verify(&gens, &public_state, &comm, &new_session_id(&mut rng))
+                .is_err());
+
+            // The range proof is bound to the linear proof: mixing the parts of two proofs fails.
+            let other = JRWP::prove(
+                &gens,
+                &public_state,
+                &committed_state,
+                &comm,
+                blind,
+                14,
+                &session_id,
+                &mut rng,
+            )
+            .unwrap();
+            let mut mixed = proof.clone();
+            mixed.range_proof = other.range_proof.clone();
+            mixed.r_comms = other.r_comms;
+            assert!(mixed
+                .verify(&gens, &public_state, &comm, &session_id)
+                .is_err());
+
+            // A reward that is not the inner product of the committed vector is rejected.
+            let bad_proof = JRWP::prove(
+                &gens,
+                &public_state,
+                &committed_state,
+                &comm,
+                blind,
+                15,
+                &session_id,
+                &mut rng,
+            )
+            .unwrap();
+            assert!(bad_proof
+                .verify(&gens, &public_state, &comm, &session_id)
+                .is_err());
+
+            // The joint proof drops the commitment of the linear proof.
+            let split = RWP::prove_with_commitment(
+                &gens,
+                &public_state,
+                &committed_state,
+                &comm,
+                blind,
+                14,
+                &session_id,
+                &mut rng,
+            )
+            .unwrap();
+            assert_eq!(
+                proof.compressed_size() + split.l_comms.compressed_size(),
+                split.compressed_size()
+            );
+        }
+
+        #[test]
+        fn test_boomerang_rewards_proof_masked() {
+            // Test a rewards proof that only counts the indices of a public mask.
+            let mut rng = TestRng::from_env();
+            type RWP = BRewardsProof<$boomerangconfig>;
+            let gens = RewardsGenerators::<$boomerangconfig>::default();
+
+            let session_id = new_session_id(&mut rng);
+            let spend_state: Vec<SF> = vec![SF::one(); 4];
+            let policy_state: Vec<SF> = vec![
+                SF::from(2u64),
+                SF::from(3u64),
+                SF::from(4u64),
+                SF::from(5u64),
+            ];
+            let mask = [true, false, true, false];
+
+            let proof = RWP::prove_masked(
+                &gens,
+                &spend_state,
+                &policy_state,
+                &mask,
+                6,
+                SF::from(6u64),
+                &session_id,
+                &mut rng,
+            )
+            .unwrap();
+            assert!(proof
+                .verify_masked(&gens, &spend_state, &mask, &session_id)
+                .is_ok());
+
+            // The proof does not hold for another mask, nor for the whole policy.
+            assert!(proof
+                .verify_masked(&gens, &spend_state, &[true; 4], &session_id)
+                .is_err());
+            assert!(proof.verify(&gens, &spend_state, &session_id).is_err());
+
+            // The mask must cover the whole state.
+            assert!(proof
+                .verify_masked(&gens, &spend_state, &mask[..2], &session_id)
+                .is_err());
+
+            // A reward over the masked-out indices is rejected.
+            let bad_proof = RWP::prove_masked(
+                &gens,
+                &spend_state,
+                &policy_state,
+                &mask,
+                14,
+                SF::from(14u64),
+                &session_id,
+                &mut rng,
+            )
+            .unwrap();
+            assert!(bad_proof
+                .verify_masked(&gens, &spend_state, &mask, &session_id)
+                .is_err());
+        }
+
+        #[test]
+        fn test_boomerang_rewards_proof_sparse() {
+            // Test a rewards proof over a large catalog that the user only touches in a few places.
+            let mut rng = TestRng::from_env();
+            type RWP = BRewardsProof<$boomerangconfig>;
+            let gens = RewardsGenerators::<$boomerangconfig>::new(1024);
+
+            let session_id = new_session_id(&mut rng);
+            let spend_state = SparseState::new(
+                1024,
+                &[(700, SF::from(2u64)), (3, SF::one()), (12, SF::zero())],
+            )
+            .unwrap();
+            assert_eq!(spend_state.entries(), &[(3, SF::one()), (700, SF::from(2u64))]);
+            assert_eq!(SparseState::from_dense(&spend_state.to_dense()), spend_state);
+
+            let mut policy_state = vec![SF::from(9u64); 1024];
+            policy_state[3] = SF::from(4u64);
+            policy_state[700] = SF::from(5u64);
+            assert_eq!(spend_state.inner_product(&policy_state), Ok(SF::from(14u64)));
+
+            let proof = RWP::prove_sparse(
+                &gens,
+                &spend_state,
+                &policy_state,
+                14,
+                SF::from(14u64),
+                &session_id,
+                &mut rng,
+            )
+            .unwrap();
+            assert!(proof.verify_sparse(&gens, &spend_state, &session_id).is_ok());
+
+            // The proof does not hold for another state, session or reward.
+            let other_state = SparseState::new(1024, &[(3, SF::one()), (701, SF::from(2u64))]).unwrap();
+            assert!(proof.verify_sparse(&gens, &other_state, &session_id).is_err());
+            assert!(proof
+                .verify_sparse(&gens, &spend_state, &new_session_id(&mut rng))
+                .is_err());
+            let bad_proof = RWP::prove_sparse(
+                &gens,
+                &spend_state,
+                &policy_state,
+                15,
+                SF::from(15u64),
+                &session_id,
+                &mut rng,
+            )
+            .unwrap();
+            assert!(bad_proof.verify_sparse(&gens, &spend_state, &session_id).is_err());
+
+            // A sparse commitment is the commitment to the dense state.
+            let blind = SF::rand(&mut rng);
+            assert_eq!(
+                RWP::commit_sparse_state(&gens, &spend_state, blind),
+                RWP::commit_state(&gens, &spend_state.to_dense(), blind)
+            );
+
+            // Out of range and repeated indices are rejected.
+            assert!(SparseState::new(1024, &[(1024, SF::one())]).is_err());
+            assert!(SparseState::new(1024, &[(3, SF::one()), (3, SF::one())]).is_err());
+            assert!(RWP::prove_sparse(
+                &gens,
+                &spend_state,
+                &policy_state[..512],
+                14,
+                SF::from(14u64),
+                &session_id,
+                &mut rng,
+            )
+            .is_err());
+        }
+
+        #[test]
+        fn test_boomerang_rewards_proof_versions() {
+            // Test that rewards proofs can be stored with a version, and that proofs in the
+            // legacy format (which embedded the generators) can still be read.
+            let mut rng = TestRng::from_env();
+            type RWP = BRewardsProof<$boomerangconfig>;
+            let gens = RewardsGenerators::<$boomerangconfig>::default();
+
+            let session_id = new_session_id(&mut rng);
+            let spend_state: Vec<SF> = vec![SF::one()];
+            let policy_state: Vec<SF> = vec![SF::from(2u64)];
+            let proof = RWP::prove(
+                &gens,
+                &spend_state,
+                &policy_state,
+                2,
+                SF::from(2u64),
+                &session_id,
+                &mut rng,
+            )
+            .unwrap();
+
+            let bytes = proof.to_versioned_bytes();
+            assert_eq!(bytes[0], REWARDS_PROOF_VERSION);
+            let decoded = RWP::from_versioned_bytes(&bytes, &gens).unwrap();
+            assert!(decoded.verify(&gens, &spend_state, &session_id).is_ok());
+
+            let legacy = LegacyBRewardsProof::<$boomerangconfig> {
+                range_proof: proof.range_proof.clone(),
+                range_gensp_r: gens.pc_gens,
+                range_gensb_r: (*gens.bp_gens).clone(),
+                r_comms: proof.r_comms,
+                linear_proof: proof.linear_proof.clone(),
+                range_gensp_l: gens.pc_gens,
+                range_gensb_l: (*gens.bp_gens).clone(),
+                l_comms: proof.l_comms,
+            };
+            let mut legacy_bytes = vec![0u8];
+            legacy.serialize_compressed(&mut legacy_bytes).unwrap();
+            assert!(legacy_bytes.len() > bytes.len());
+
+            let migrated = RWP::from_versioned_bytes(&legacy_bytes, &gens).unwrap();
+            assert!(migrated.verify(&gens, &spend_state, &session_id).is_ok());
+
+            // A legacy proof is only migrated if it was made against the expected generators.
+            assert!(
+                RWP::from_versioned_bytes(&legacy_bytes, &RewardsGenerators::new(128)).is_err()
+            );
+
+            let mut unknown = bytes.clone();
+            unknown[0] = REWARDS_PROOF_VERSION + 1;
+            assert!(RWP::from_versioned_bytes(&unknown, &gens).is_err());
+            assert!(RWP::from_versioned_bytes(&[], &gens).is_err());
+        }
+
+        #[test]
+        fn test_boomerang_rewards_proof_errors() {
+            // Test that a rejected rewards proof reports the check that failed.
+            let mut rng = TestRng::from_env();
+            type RWP = BRewardsProof<$boomerangconfig>;
+            let gens = RewardsGenerators::<$boomerangconfig>::default();
+
+            let session_id = new_session_id(&mut rng);
+            let spend_state: Vec<SF> = vec![SF::one(), SF::one()];
+            let policy_state: Vec<SF> = vec![SF::from(2u64), SF::from(3u64)];
+            let proof = RWP::prove(
+                &gens,
+                &spend_state,
+                &policy_state,
+                5,
+                SF::from(5u64),
+                &session_id,
+                &mut rng,
+            )
+            .unwrap();
+            assert_eq!(proof.verify(&gens, &spend_state, &session_id), Ok(()));
+
+            // The range proof is checked first, and is bound to the session.
+            assert!(matches!(
+                proof.verify(&gens, &spend_state, &new_session_id(&mut rng)),
+                Err(RewardsProofError::RangeProof(_))
+            ));
+
+            // A proof over another public state fails the linear proof.
+            let other_state: Vec<SF> = vec![SF::one(), SF::from(2u64)];
+            assert!(matches!(
+                proof.verify(&gens, &other_state, &session_id),
+                Err(RewardsProofError::LinearProof(_))
+            ));
+
+            assert!(matches!(
+                proof.verify_masked(&gens, &spend_state, &[true], &session_id),
+                Err(RewardsProofError::InvalidMask(_))
+            ));
+
+            let comm = RWP::commit_state(&gens, &policy_state, SF::rand(&mut rng));
+            assert_eq!(
+                proof.verify_with_commitment(&gens, &spend_state, &comm, &session_id),
+                Err(RewardsProofError::CommitmentMismatch)
+            );
+
+            let bytes = proof.to_versioned_bytes();
+            assert!(matches!(
+                RWP::from_versioned_bytes(&bytes[..bytes.len() - 1], &gens),
+                Err(RewardsProofError::Deserialization(_))
+            ));
+            let mut unknown = bytes.clone();
+            unknown[0] = REWARDS_PROOF_VERSION + 1;
+            assert!(matches!(
+                RWP::from_versioned_bytes(&unknown, &gens),
+                Err(RewardsProofError::UnknownVersion(v)) if v == REWARDS_PROOF_VERSION + 1
+            ));
+        }
+
+        #[test]
+        fn test_boomerang_rewards_proof_policy_id() {
+            // Test that a rewards proof that is bound to a policy id only verifies against the
+            // same id, and that the id of a policy depends on the salt.
+            let mut rng = TestRng::from_env();
+            type RWP = BRewardsProof<$boomerangconfig>;
+            let gens = RewardsGenerators::<$boomerangconfig>::default();
+
+            let session_id = new_session_id(&mut rng);
+            let spend_state: Vec<SF> = vec![SF::one(), SF::one()];
+            let policy_state: Vec<SF> = vec![SF::from(2u64), SF::from(3u64)];
+            let salt = [7u8; 32];
+            let id = policy_id::<$boomerangconfig>(&policy_state, &salt);
+            let (proof, _) = RWP::prove_with_policy_id(
+                &gens,
+                &spend_state,
+                &policy_state,
+                &id,
+                5,
+                SF::from(5u64),
+                &session_id,
+                &mut rng,
+            )
+            .unwrap();
+            assert_eq!(
+                proof.verify_with_policy_id(&gens, &spend_state, &session_id, &id, &mut rng),
+                Ok(())
+            );
+
+            // Another policy with the same reward has another id, which the proof rejects.
+            let other_policy: Vec<SF> = vec![SF::from(3u64), SF::from(2u64)];
+            let other_id = policy_id::<$boomerangconfig>(&other_policy, &salt);
+            assert_ne!(id, other_id);
+            assert!(proof
+                .verify_with_policy_id(&gens, &spend_state, &session_id, &other_id, &mut rng)
+                .is_err());
+
+            // The id cannot be recomputed from the policy alone, and a bound proof is not an
+            // unbound one.
+            assert_ne!(id, policy_id::<$boomerangconfig>(&policy_state, &[0u8; 32]));
+            assert!(proof.verify(&gens, &spend_state, &session_id).is_err());
+        }
+
+        #[test]
+        fn test_boomerang_rewards_proof_cancellation() {
+            // Test that a cancelled verification is given up, and reported as such.
+            let mut rng = TestRng::from_env();
+            type RWP = BRewardsProof<$boomerangconfig>;
+            let gens = RewardsGenerators::<$boomerangconfig>::shared();
+
+            let session_id = new_session_id(&mut rng);
+            let spend_state: Vec<SF> = vec![SF::one(), SF::one()];
+            let policy_state: Vec<SF> = vec![SF::from(2u64), SF::from(3u64)];
+            let proof = RWP::prove(
+                &gens,
+                &spend_state,
+                &policy_state,
+                5,
+                SF::from(5u64),
+                &session_id,
+                &mut rng,
+            )
+            .unwrap();
+
+            let cancel = CancellationToken::new();
+            assert_eq!(
+                proof.verify_cancellable(&gens, &spend_state, &session_id, &mut rng, &cancel),
+                Ok(())
+            );
+
+            // A cancellation does not hide a proof that fails before it.
+            let other_state: Vec<SF> = vec![SF::one(), SF::from(2u64)];
+            assert!(matches!(
+                proof.verify_cancellable(&gens, &other_state, &session_id, &mut rng, &cancel),
+                Err(RewardsProofError::LinearProof(_))
+            ));
+
+            cancel.clone().cancel();
+            assert_eq!(
+                proof.verify_cancellable(&gens, &spend_state, &session_id, &mut rng, &cancel),
+                Err(RewardsProofError::Cancelled)
+            );
+            assert_eq!(
+                proof.verify_cancellable(
+                    &gens,
+                    &spend_state,
+                    &session_id,
+                    &mut rng,
+                    &CancellationToken::with_timeout(std::time::Duration::ZERO)
+                ),
+                Err(RewardsProofError::Cancelled)
+            );
+        }
+
+        #[test]
+        fn test_boomerang_rewards_generators_shared() {
+            // Test that the shared generators are derived once, and that proofs made against them
+            // do not need to carry them.
+            let mut rng = TestRng::from_env();
+            type RWG = RewardsGenerators<$boomerangconfig>;
+            let gens = RWG::shared();
+            assert!(std::sync::Arc::ptr_eq(&gens.bp_gens, &RWG::shared().bp_gens));
+
+            // The Pedersen generators are derived from the application label, so the value base
+            // is not the basepoint that the default generators use.
+            assert_ne!(gens.pc_gens.B, AT::generator());
+            assert_ne!(gens.pc_gens.B, gens.pc_gens.B_blinding);
+
+            let session_id = new_session_id(&mut rng);
+            let sub_proof = SubProof::<$boomerangconfig>::prove(2, &session_id, &mut rng);
+            assert!(sub_proof.verify(&session_id).is_ok());
+            assert!(sub_proof.verify(&new_session_id(&mut rng)).is_err());
+
+            use ark_serialize::CanonicalDeserialize;
+            let mut bytes = Vec::new();
+            sub_proof.serialize_compressed(&mut bytes).unwrap();
+            let decoded =
+                SubProof::<$boomerangconfig>::deserialize_compressed(&bytes[..]).unwrap();
+            assert!(decoded.verify(&session_id).is_ok());
+        }
+
+        #[test]
+        fn test_boomerang_server_config() {
+            // Test that a server config is read from TOML and the environment, and validated.
+            assert_eq!(ServerConfig::from_toml_str(""), Ok(ServerConfig::default()));
+
+            let config = ServerConfig::from_toml_str(
+                r#"
+                curve = "tsecq256k1"
+                https_port = 8443
+                policy = [1, 2, 3]
+                max_request_size = 4096
+
+                [rate_limit]
+                requests = 10
+                window_secs = 60
+                "#,
+            )
+            .unwrap();
+            assert_eq!(config.curve, "tsecq256k1");
+            assert_eq!(config.http_port, ServerConfig::default().http_port);
+            assert_eq!(config.https_port, 8443);
+            assert_eq!(config.max_request_size, 4096);
+            assert_eq!(
+                config.rate_limit,
+                Some(RateLimit {
+                    requests: 10,
+                    window_secs: 60
+                })
+            );
+            assert_eq!(
+                config.policy_state::<Config>(),
+                vec![SF::from(1u64), SF::from(2u64), SF::from(3u64)]
+            );
+
+            assert!(matches!(
+                ServerConfig::from_toml_str("max_requests = 1"),
+                Err(ServerConfigError::Parse(_))
+            ));
+            assert_eq!(
+                ServerConfig::from_toml_str(r#"curve = "ed25519""#),
+                Err(ServerConfigError::UnsupportedCurve("ed25519".into()))
+            );
+            assert_eq!(
+                ServerConfig::from_toml_str("http_port = 3000"),
+                Err(ServerConfigError::InvalidPorts)
+            );
+            assert_eq!(
+                ServerConfig::from_toml_str("policy = []"),
+                Err(ServerConfigError::EmptyPolicy)
+            );
+            assert_eq!(
+                ServerConfig::from_toml_str("policy = [1, 2, 3]\nmax_catalog_size = 2"),
+                Err(ServerConfigError::CatalogTooLarge { len: 3, max: 2 })
+            );
+            assert_eq!(
+                ServerConfig::from_toml_str("max_request_size = 0"),
+                Err(ServerConfigError::InvalidLimit("max_request_size"))
+            );
+            assert_eq!(
+                ServerConfig::from_toml_str(r#"tls_cert_path = "cert.pem""#),
+                Err(ServerConfigError::IncompleteTls)
+            );
+
+            let env = |vars: &[(&str, &str)]| {
+                let mut config = ServerConfig::default();
+                config
+                    .apply_env(vars.iter().map(|(k, v)| (k.to_string(), v.to_string())))
+                    .map(|_| config)
+            };
+            let config = env(&[
+                ("BOOMERANG_HTTPS_PORT", "8443"),
+                ("BOOMERANG_POLICY", "4, 5"),
+                ("BOOMERANG_RATE_LIMIT_REQUESTS", "100"),
+                ("BOOMERANG_RATE_LIMIT_WINDOW_SECS", "1"),
+                ("PATH", "/usr/bin"),
+            ])
+            .unwrap();
+            assert_eq!(config.https_port, 8443);
+            assert_eq!(config.policy, vec![4, 5]);
+            assert_eq!(config.rate_limit.as_ref().map(|r| r.requests), Some(100));
+            assert_eq!(config.validate(), Ok(()));
+
+            assert_eq!(
+                env(&[("BOOMERANG_HTTP_PORT", "http")]),
+                Err(ServerConfigError::Env("BOOMERANG_HTTP_PORT".into()))
+            );
+            assert_eq!(
+                env(&[("BOOMERANG_RATE_LIMIT_REQUESTS", "100")]),
+                Err(ServerConfigError::Env("BOOMERANG_RATE_LIMIT_WINDOW_SECS".into()))
+            );
+        }
+
+        #[test]
+        fn test_boomerang_encoding() {
+            // Test that the wire encodings round-trip, and that malformed encodings are rejected.
+            use encoding::Endianness;
+
+            let mut rng = TestRng::from_env();
+            type Aff = sw::Affine<$config>;
+
+            let point = (<$config as SWCurveConfig>::GENERATOR * SF::rand(&mut rng)).into_affine();
+            let bytes = encoding::encode_point(&point);
+            assert_eq!(bytes.len(), encoding::point_len::<$config>());
+            assert_eq!(encoding::decode_point::<$config>(&bytes), Ok(point));
+            assert!(encoding::decode_point::<$config>(&bytes[1..]).is_err());
+            assert_eq!(
+                encoding::decode_point::<$config>(&encoding::encode_point(&Aff::zero())),
+                Ok(Aff::zero())
+            );
+
+            // Not every x co-ordinate is on the curve.
+            assert!((0u8..=255).any(|i| {
+                let mut bytes = bytes.clone();
+                bytes[0] ^= i;
+                encoding::decode_point::<$config>(&bytes).is_err()
+            }));
+
+            let scalar = SF::rand(&mut rng);
+            let le = encoding::encode_scalar(&scalar, Endianness::Little);
+            let mut be = encoding::encode_scalar(&scalar, Endianness::Big);
+            assert_eq!(le.len(), encoding::scalar_len::<SF>());
+            assert_eq!(encoding::decode_scalar(&le, Endianness::Little), Ok(scalar));
+            assert_eq!(encoding::decode_scalar(&be, Endianness::Big), Ok(scalar));
+            be.reverse();
+            assert_eq!(be, le);
+            assert!(encoding::decode_scalar::<SF>(&le[1..], Endianness::Little).is_err());
+            assert!(encoding::decode_scalar::<SF>(&vec![0xff; le.len()], Endianness::Big).is_err());
+
+            let mut out = Vec::new();
+            encoding::encode_bytes(&mut out, b"boomerang");
+            encoding::encode_bytes(&mut out, b"");
+            let mut input = &out[..];
+            assert_eq!(encoding::decode_bytes(&mut input), Ok(&b"boomerang"[..]));
+            assert_eq!(encoding::decode_bytes(&mut input), Ok(&b""[..]));
+            assert!(input.is_empty());
+            assert!(encoding::decode_bytes(&mut &out[..13]).is_ok());
+            assert!(encoding::decode_bytes(&mut &out[..12]).is_err());
+        }
+
+        #[test]
+        fn test_boomerang_spend_verify_session_binding() {
+            // Test that the proofs of a spend message only hold in the session they were made in.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let mut s_col_state = CBSM::default();
+            let collection_m1 = CBSM::generate_collection_m1(&mut rng, &mut s_col_state);
+            let mut c_col_state = CBCM::default();
+            let collection_m2 = CBCM::generate_collection_m2(
+                &mut rng,
+                &issuance_state,
+                &collection_m1,
+                &mut c_col_state,
+                &skp,
+            );
+            let collection_m3 = CBSM::generate_collection_m3(
+                &mut rng,
+                &collection_m2,
+                &mut s_col_state,
+                &skp,
+                SF::one(),
+            ).unwrap();
+            let collection_m4 =
+                CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &collection_m3);
+            let collection_m5 =
+                CBSM::generate_collection_m5(&collection_m4, &mut s_col_state, &skp);
+            let collection_state =
+                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, &ckp);
+
+            let mut s_spend_state_a = SVBS::default();
+            let spendverify_m1_a = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state_a);
+            let mut s_spend_state_b = SVBS::default();
+            let spendverify_m1_b = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state_b);
+
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &collection_state,
+                &mut c_spend_state,
+                &spendverify_m1_a,
+                &skp,
+                &[SF::one()],
+            );
+            assert!(SVBS::verify_spendverify_m2(&spendverify_m2, &skp).is_ok());
+
+            // Relabelling the message with the id of another session breaks its proofs.
+            let mut relabelled = spendverify_m2.clone();
+            relabelled.session_id = spendverify_m1_b.session_id;
+            assert_eq!(
+                SVBS::verify_spendverify_m2(&relabelled, &skp),
+                Err("Boomerang spend-verify: invalid proof sig")
+            );
+
+            // The message is still accepted by the session it was made in.
+            let policy_state: Vec<SF> = vec![SF::from(2u64)];
+            let spendverify_m3 = SVBS::generate_spendverify_m3(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state_a,
+                &skp,
+                policy_state,
+            ).unwrap();
+            assert_eq!(spendverify_m3.session_id, spendverify_m1_a.session_id);
+
+            let spendverify_m4 = SVBC::generate_spendverify_m4(
+                &mut rng,
+                &mut c_spend_state,
+                &spendverify_m3,
+                &skp.policy_id(&[SF::from(2u64)]),
+            )
+            .unwrap();
+            let spendverify_m5 =
+                SVBS::generate_spendverify_m5(&spendverify_m4, &mut s_spend_state_a, &skp);
+            let spend_state =
+                SVBC::populate_state(&mut c_spend_state, &spendverify_m5, &skp, &ckp);
+
+            let check = ACLSV::verify(
+                skp.s_key_pair.verifying_key,
+                skp.s_key_pair.tag_key,
+                &spend_state.sig_state[0],
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
+            );
+            assert!(check);
+        }
+
+        #[test]
+        fn test_boomerang_spend_verify_policy() {
+            // Test that the policy of a spend comes from the provider, per campaign and epoch,
+            // and that the cache signs each policy once.
+            use ::boomerang::policy::{PolicyCache, PolicyError, PolicyKey, StaticPolicy};
+            use std::cell::Cell;
+            use std::sync::Arc;
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &issuance_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::one()],
+            );
+
+            // Campaign 1 has a policy from epoch 10 on, which doubles in epoch 11.
+            let calls = Cell::new(0);
+            let provider = |campaign_id: u64, epoch: u64| {
+                calls.set(calls.get() + 1);
+                match (campaign_id, epoch) {
+                    (1, 10) => Ok(vec![SF::from(2u64)]),
+                    (1, e) if e > 10 => Ok(vec![SF::from(4u64)]),
+                    (3, _) => Err(PolicyError::Unavailable("policy backend is down")),
+                    _ => Err(PolicyError::NoPolicy),
+                }
+            };
+            let cache = PolicyCache::<Config, _>::new(provider, PolicyKey::generate(&mut rng));
+
+            let signed = cache.get(1, 10, &mut rng).unwrap();
+            assert_eq!(signed.policy, vec![SF::from(2u64)]);
+            assert!(signed.verify(cache.public_key()));
+            assert!(Arc::ptr_eq(&cache.get(1, 10, &mut rng).unwrap(), &signed));
+            assert_eq!(calls.get(), 1);
+
+            let mut forged = (*signed).clone();
+            forged.policy = vec![SF::from(3u64)];
+            assert!(!forged.verify(cache.public_key()));
+            assert!(!signed.verify(&PolicyKey::<Config>::generate(&mut rng).public_key));
+
+            assert_eq!(cache.get(1, 9, &mut rng).err(), Some(PolicyError::NoPolicy));
+            assert_eq!(cache.len(), 1);
+            assert!(cache.get(1, 11, &mut rng).is_ok());
+            cache.evict_before(11);
+            assert_eq!(cache.len(), 1);
+
+            assert!(SVBS::generate_spendverify_m3_with_policy(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state.clone(),
+                &skp,
+                &cache,
+                2,
+                10,
+            )
+            .is_err());
+            // The reason of a failed lookup reaches the server.
+            assert_eq!(
+                SVBS::generate_spendverify_m3_with_policy(
+                    &mut rng,
+                    &spendverify_m2,
+                    &mut s_spend_state.clone(),
+                    &skp,
+                    &cache,
+                    3,
+                    10,
+                )
+                .err(),
+                Some("policy backend is down")
+            );
+            assert!(SVBS::generate_spendverify_m3_with_policy(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state.clone(),
+                &skp,
+                &StaticPolicy(vec![SF::one(), SF::one()]),
+                1,
+                10,
+            )
+            .is_err());
+
+            let spendverify_m3 = SVBS::generate_spendverify_m3_with_policy(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state,
+                &skp,
+                &cache,
+                1,
+                11,
+            )
+            .unwrap();
+            assert_eq!(spendverify_m3.session_id, spendverify_m2.session_id);
+        }
+
+        #[test]
+        fn test_boomerang_spend_verify_reward_check() {
+            // Test that the client recomputes the reward from the policy, and rejects a third
+            // message that commits to another reward.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spend_state = vec![SF::from(3u64)];
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &issuance_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &spend_state,
+            );
+
+            let policy_state = vec![SF::from(2u64)];
+            let spendverify_m3 = SVBS::generate_spendverify_m3(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state,
+                &skp,
+                policy_state.clone(),
+            ).unwrap();
+
+            let gens = RewardsGenerators::<Config>::shared();
+            let proof = &spendverify_m3.pi_reward;
+            let opening = &spendverify_m3.reward_opening;
+            assert!(proof
+                .check_reward(&gens, opening, &spend_state, &policy_state)
+                .is_ok());
+            assert_eq!(
+                proof.check_reward(&gens, opening, &spend_state, &[SF::one()]),
+                Err(RewardsProofError::RewardMismatch)
+            );
+            assert_eq!(
+                proof.check_reward(&gens, opening, &spend_state, &[SF::one(), SF::one()]),
+                Err(RewardsProofError::RewardMismatch)
+            );
+
+            // A server that proves a smaller reward than the policy gives is caught.
+            let mut underpaid = spendverify_m3.clone();
+            let (pi_reward, reward_opening) = BRewardsProof::prove_with_opening(
+                &gens,
+                &spend_state,
+                &[SF::one()],
+                3,
+                SF::from(3u64),
+                &spendverify_m3.session_id,
+                &mut rng,
+            )
+            .unwrap();
+            underpaid.pi_reward = pi_reward;
+            underpaid.reward_opening = reward_opening;
+            let policy_id = skp.policy_id(&policy_state);
+            assert!(SVBC::generate_spendverify_m4_with_policy(
+                &mut rng,
+                &mut c_spend_state,
+                &underpaid,
+                &policy_state,
+                &policy_id,
+            )
+            .is_err());
+
+            let mut other_policy = spendverify_m3.clone();
+            other_policy.policy_id = skp.policy_id(&[SF::from(3u64)]);
+            assert_eq!(
+                SVBC::generate_spendverify_m4_with_policy(
+                    &mut rng,
+                    &mut c_spend_state,
+                    &other_policy,
+                    &policy_state,
+                    &policy_id,
+                )
+                .err(),
+                Some("Boomerang spend-verify: rewards proof is for another policy")
+            );
+
+            let mut bad_opening = spendverify_m3.clone();
+            bad_opening.reward_opening.linear_blind += SF::one();
+            assert!(SVBC::generate_spendverify_m4_with_policy(
+                &mut rng,
+                &mut c_spend_state,
+                &bad_opening,
+                &policy_state,
+                &policy_id,
+            )
+            .is_err());
+
+            let spendverify_m4 = SVBC::generate_spendverify_m4_with_policy(
+                &mut rng,
+                &mut c_spend_state,
+                &spendverify_m3,
+                &policy_state,
+                &policy_id,
+            )
+            .unwrap();
+            let spendverify_m5 =
+                SVBS::generate_spendverify_m5(&spendverify_m4, &mut s_spend_state, &skp);
+            let spend_state = SVBC::populate_state(&mut c_spend_state, &spendverify_m5, &skp, &ckp);
+            assert!(spend_state.sig_state[0].sigma.zeta.is_on_curve());
+        }
+
+        #[test]
+        fn test_boomerang_spend_verify_fast_path() {
+            // Test that the server skips the rewards proof when the policy yields no reward,
+            // that the token is still refreshed, and that the client refuses the fast path when
+            // a reward is due.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let spend = |rng: &mut TestRng,
+                         s_spend_state: &mut SVBS,
+                         c_spend_state: &mut SVBC| {
+                let spendverify_m1 = SVBS::generate_spendverify_m1(rng, s_spend_state);
+                SVBC::generate_spendverify_m2(
+                    rng,
+                    &issuance_state,
+                    c_spend_state,
+                    &spendverify_m1,
+                    &skp,
+                    &[SF::from(3u64)],
+                )
+            };
+
+            // A policy that yields a reward takes the full path.
+            let mut s_spend_state = SVBS::default();
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = spend(&mut rng, &mut s_spend_state, &mut c_spend_state);
+            let reply = SVBS::generate_spendverify_reply(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state,
+                &skp,
+                vec![SF::from(2u64)],
+            )
+            .unwrap();
+            let SpendVerifyReply::Full(spendverify_m3) = reply else {
+                panic!("a reward is due");
+            };
+
+            // The client does not accept an acknowledgement when a reward is due.
+            assert!(SVBC::generate_spendverify_m4_fast(
+                &mut rng,
+                &mut c_spend_state,
+                &spendverify_m3.ack(),
+                &[SF::from(2u64)],
+            )
+            .is_err());
+
+            // A policy that yields no reward takes the fast path.
+            let mut s_spend_state = SVBS::default();
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = spend(&mut rng, &mut s_spend_state, &mut c_spend_state);
+            assert!(SVBS::generate_spendverify_reply(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state,
+                &skp,
+                vec![SF::zero(), SF::zero()],
+            )
+            .is_err());
+            let reply = SVBS::generate_spendverify_reply(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state,
+                &skp,
+                vec![SF::zero()],
+            )
+            .unwrap();
+            let SpendVerifyReply::Fast(ack) = reply else {
+                panic!("no reward is due");
+            };
+            assert!(ack.compressed_size() < spendverify_m3.compressed_size());
+
+            let spendverify_m4 =
+                SVBC::generate_spendverify_m4_fast(&mut rng, &mut c_spend_state, &ack, &[SF::zero()])
+                    .unwrap();
+            let spendverify_m5 =
+                SVBS::generate_spendverify_m5(&spendverify_m4, &mut s_spend_state, &skp);
+            let spend_state = SVBC::populate_state(&mut c_spend_state, &spendverify_m5, &skp, &ckp);
+            assert!(spend_state.sig_state[0].sigma.zeta.is_on_curve());
+            assert_eq!(
+                spend_state.token_state[0].value(),
+                issuance_state.token_state[0].value() - SF::from(3u64)
+            );
+        }
+
+        #[test]
+        fn test_boomerang_spend_verify_multi() {
+            // Test that a client can spend more than the balance of any single token by
+            // presenting several tokens at once.
+            use ::boomerang::config::State;
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            // Issues a token, and collects `v` into it.
+            let token_with = |rng: &mut TestRng, v: u64| {
+                let issuance_state = issue_token(rng, &ckp, &skp, SF::zero());
+
+                let mut s_col_state = CBSM::default();
+                let collection_m1 = CBSM::generate_collection_m1(rng, &mut s_col_state);
+                let mut c_col_state = CBCM::default();
+                let collection_m2 = CBCM::generate_collection_m2(
+                    rng,
+                    &issuance_state,
+                    &collection_m1,
+                    &mut c_col_state,
+                    &skp,
+                );
+                let collection_m3 = CBSM::generate_collection_m3(
+                    rng,
+                    &collection_m2,
+                    &mut s_col_state,
+                    &skp,
+                    SF::from(v),
+                ).unwrap();
+                let collection_m4 =
+                    CBCM::generate_collection_m4(rng, &mut c_col_state, &collection_m3);
+                let collection_m5 =
+                    CBSM::generate_collection_m5(&collection_m4, &mut s_col_state, &skp);
+                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, &ckp)
+            };
+
+            let a = token_with(&mut rng, 3);
+            let b = token_with(&mut rng, 2);
+            assert_eq!(a.token_state[0].value(), SF::from(3u64));
+            let state = State {
+                sig_state: [a.sig_state.clone(), b.sig_state.clone()].concat(),
+                comm_state: [a.comm_state.clone(), b.comm_state.clone()].concat(),
+                token_state: [a.token_state.clone(), b.token_state.clone()].concat(),
+                c_key_pair: ckp.clone(),
+            };
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+
+            // The balances do not cover 6, and a token cannot be presented twice.
+            let mut c_spend_state = SVBC::default();
+            assert!(SVBC::generate_spendverify_m2_multi(
+                &mut rng,
+                &state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::from(6u64)],
+            )
+            .is_err());
+            let twice = State {
+                sig_state: [a.sig_state.clone(), a.sig_state.clone()].concat(),
+                comm_state: [a.comm_state.clone(), a.comm_state.clone()].concat(),
+                token_state: [a.token_state.clone(), a.token_state.clone()].concat(),
+                c_key_pair: ckp.clone(),
+            };
+            assert!(SVBC::generate_spendverify_m2_multi(
+                &mut rng,
+                &twice,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::from(4u64)],
+            )
+            .is_err());
+
+            let spendverify_m2 = SVBC::generate_spendverify_m2_multi(
+                &mut rng,
+                &state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::from(4u64)],
+            )
+            .unwrap();
+            assert_eq!(spendverify_m2.inputs.len(), 2);
+            assert!(SVBS::verify_spendverify_m2_multi(&spendverify_m2, &skp, &mut rng).is_ok());
+
+            // The server notices a claimed balance that does not match the sub proof, and an
+            // input that is presented twice.
+            let mut inflated = spendverify_m2.clone();
+            inflated.inputs[1].balance_comm = inflated.inputs[0].balance_comm;
+            assert!(SVBS::verify_spendverify_m2_multi(&inflated, &skp, &mut rng).is_err());
+            let mut repeated = spendverify_m2.clone();
+            repeated.inputs.push(repeated.inputs[0].clone());
+            assert!(SVBS::verify_spendverify_m2_multi(&repeated, &skp, &mut rng).is_err());
+            let mut cheaper = spendverify_m2.clone();
+            cheaper.spend_state = vec![SF::from(3u64)];
+            assert!(SVBS::verify_spendverify_m2_multi(&cheaper, &skp, &mut rng).is_err());
+
+            // A balance commitment that claims more than its token holds is rejected, even
+            // when the amount is raised so that the sub proof still matches.
+            let pc_gens = RewardsGenerators::<$boomerangconfig>::shared().pc_gens;
+            let mut raised = spendverify_m2.clone();
+            raised.inputs[0].balance_comm =
+                (raised.inputs[0].balance_comm.into_group() + pc_gens.B).into_affine();
+            raised.spend_state[0] += SF::one();
+            assert_eq!(
+                SVBS::verify_spendverify_m2_multi(&raised, &skp, &mut rng).err(),
+                Some("Boomerang spend-verify: invalid proof opening 2")
+            );
+
+            // Every spent token leaves a tag.
+            let mut store = TGS::new(4);
+            assert!(store.record_spend_multi(&spendverify_m2, &s_spend_state));
+            assert_eq!(store.len(), 2);
+            assert!(!store.record_spend_multi(&spendverify_m2, &s_spend_state));
+
+            let spendverify_m3 = SVBS::generate_spendverify_m3_multi(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state,
+                &skp,
+                vec![SF::one()],
+            )
+            .unwrap();
+            let spendverify_m4 = SVBC::generate_spendverify_m4(
+                &mut rng,
+                &mut c_spend_state,
+                &spendverify_m3,
+                &skp.policy_id(&[SF::one()]),
+            )
+            .unwrap();
+            let spendverify_m5 =
+                SVBS::generate_spendverify_m5(&spendverify_m4, &mut s_spend_state, &skp);
+            let spend_state = SVBC::populate_state(&mut c_spend_state, &spendverify_m5, &skp, &ckp);
+
+            assert_eq!(spend_state.token_state.len(), 1);
+            assert_eq!(spend_state.token_state[0].value(), SF::one());
+            let check = ACLSV::verify(
+                skp.s_key_pair.verifying_key,
+                skp.s_key_pair.tag_key,
+                &spend_state.sig_state[0],
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
+            );
+            assert!(check);
+        }
+
+        #[test]
+        fn test_boomerang_wallet() {
+            // Test that a wallet selects the token that pays for a spend, and keeps the tokens
+            // of concurrent runs apart.
+            use ::boomerang::wallet::{SelectionPolicy, Wallet};
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            // Issues a token, and collects `v` into it.
+            let token_with = |rng: &mut TestRng, v: u64| {
+                let issuance_state = issue_token(rng, &ckp, &skp, SF::zero());
+
+                let mut s_col_state = CBSM::default();
+                let collection_m1 = CBSM::generate_collection_m1(rng, &mut s_col_state);
+                let mut c_col_state = CBCM::default();
+                let collection_m2 = CBCM::generate_collection_m2(
+                    rng,
+                    &issuance_state,
+                    &collection_m1,
+                    &mut c_col_state,
+                    &skp,
+                );
+                let collection_m3 = CBSM::generate_collection_m3(
+                    rng,
+                    &collection_m2,
+                    &mut s_col_state,
+                    &skp,
+                    SF::from(v),
+                ).unwrap();
+                let collection_m4 =
+                    CBCM::generate_collection_m4(rng, &mut c_col_state, &collection_m3);
+                let collection_m5 =
+                    CBSM::generate_collection_m5(&collection_m4, &mut s_col_state, &skp);
+                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, &ckp)
+            };
+
+            // Campaign 1 holds tokens of 3 (expiring at 100) and 5 (expiring at 200), and
+            // campaign 2 a token of 7.
+            let mut wallet = Wallet::new();
+            let a = wallet.insert(token_with(&mut rng, 3), 1, 100);
+            let b = wallet.insert(token_with(&mut rng, 5), 1, 200);
+            let c = wallet.insert(token_with(&mut rng, 7), 2, 300);
+            assert_eq!(wallet.len(), 3);
+            assert_eq!(wallet.balance(1, 50), SF::from(8u64));
+            assert_eq!(wallet.balance(1, 150), SF::from(5u64));
+
+            let largest = SelectionPolicy::LargestBalanceFirst;
+            let expiring = SelectionPolicy::ExpiringFirst;
+            assert_eq!(wallet.select(1, SF::from(2u64), 50, largest), Some(b));
+            assert_eq!(wallet.select(1, SF::from(2u64), 50, expiring), Some(a));
+            assert_eq!(wallet.select(1, SF::from(4u64), 50, expiring), Some(b));
+            assert_eq!(wallet.select(1, SF::from(2u64), 150, expiring), Some(b));
+            assert_eq!(wallet.select(1, SF::from(6u64), 50, largest), None);
+            assert_eq!(wallet.select(2, SF::from(6u64), 50, expiring), Some(c));
+            assert_eq!(wallet.select(3, SF::zero(), 50, expiring), None);
+
+            // A token that is in use is not selected, nor used by a second run.
+            let state = wallet.begin(b).unwrap().clone();
+            assert!(wallet.get(b).unwrap().in_use());
+            assert!(wallet.begin(b).is_err());
+            assert_eq!(wallet.select(1, SF::from(2u64), 50, largest), Some(a));
+            assert_eq!(wallet.select(1, SF::from(4u64), 50, largest), None);
+
+            // Spend 4 of the token of 5.
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::from(4u64)],
+            );
+            let spendverify_m3 = SVBS::generate_spendverify_m3(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state,
+                &skp,
+                vec![SF::one()],
+            ).unwrap();
+            let spendverify_m4 = SVBC::generate_spendverify_m4(
+                &mut rng,
+                &mut c_spend_state,
+                &spendverify_m3,
+                &skp.policy_id(&[SF::one()]),
+            )
+            .unwrap();
+            let spendverify_m5 =
+                SVBS::generate_spendverify_m5(&spendverify_m4, &mut s_spend_state, &skp);
+            let spend_state = SVBC::populate_state(&mut c_spend_state, &spendverify_m5, &skp, &ckp);
+
+            // The new token replaces the spent one, and the other tokens are untouched.
+            assert!(wallet.finish(b, spend_state).is_ok());
+            assert!(wallet.finish(b, state.clone()).is_err());
+            assert!(wallet.abort(b).is_err());
+            assert!(!wallet.get(b).unwrap().in_use());
+            assert_eq!(wallet.get(b).unwrap().balance(), SF::one());
+            assert_eq!(wallet.get(a).unwrap().balance(), SF::from(3u64));
+            assert_eq!(wallet.select(1, SF::from(2u64), 50, largest), Some(a));
+
+            // An aborted run releases its token unchanged.
+            wallet.begin(a).unwrap();
+            assert!(wallet.abort(a).is_ok());
+            assert_eq!(wallet.get(a).unwrap().balance(), SF::from(3u64));
+
+            // Expired tokens are removed, unless they are in use.
+            wallet.begin(b).unwrap();
+            let expired = wallet.remove_expired(250);
+            assert_eq!(expired.len(), 1);
+            assert_eq!(expired[0].id(), a);
+            assert_eq!(wallet.len(), 2);
+            assert!(wallet.remove(c).is_some());
+            assert!(wallet.get(c).is_none());
+            assert!(wallet.begin(c).is_err());
+        }
+
+        #[test]
+        fn test_boomerang_transfer() {
+            // Test that a token's value can be re-issued to another user's key, and that the
+            // recipient can then spend it.
+            use ::boomerang::{client::TransferStateC, server::TransferStateS};
+
+            let mut rng = TestRng::from_env();
+            let alice = CBKP::generate(&mut rng);
+            let bob = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let issuance_state = issue_token(&mut rng, &alice, &skp, SF::zero());
+
+            let mut s_col_state = CBSM::default();
+            let collection_m1 = CBSM::generate_collection_m1(&mut rng, &mut s_col_state);
+            let mut c_col_state = CBCM::default();
+            let collection_m2 = CBCM::generate_collection_m2(
+                &mut rng,
+                &issuance_state,
+                &collection_m1,
+                &mut c_col_state,
+                &skp,
+            );
+            let collection_m3 = CBSM::generate_collection_m3(
+                &mut rng,
+                &collection_m2,
+                &mut s_col_state,
+                &skp,
+                SF::from(5u64),
+            ).unwrap();
+            let collection_m4 =
+                CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &collection_m3);
+            let collection_m5 =
+                CBSM::generate_collection_m5(&collection_m4, &mut s_col_state, &skp);
+            let alice_state =
+                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, &alice);
+
+            let mut s_transfer_state = TransferStateS::default();
+            let transfer_m1 =
+                TransferStateS::generate_transfer_m1(&mut rng, &mut s_transfer_state);
+
+            let mut bob_state = TransferStateC::default();
+            let request = TransferStateC::generate_transfer_request(
+                &mut rng,
+                &bob,
+                &mut bob_state,
+                &transfer_m1,
+            );
+
+            // A request that claims another key is rejected by the sender and by the server.
+            let mut stolen = request.clone();
+            stolen.public_key = alice.public_key;
+            assert!(TransferStateC::generate_transfer_m2(
+                &mut rng,
+                &alice_state,
+                &transfer_m1,
+                &stolen,
+                &skp,
+            )
+            .is_err());
+
+            let (transfer_m2, opening) = TransferStateC::generate_transfer_m2(
+                &mut rng,
+                &alice_state,
+                &transfer_m1,
+                &request,
+                &skp,
+            )
+            .unwrap();
+            assert_eq!(opening.value, SF::from(5u64));
+            assert!(TransferStateS::verify_transfer_m2(&transfer_m2, &skp).is_ok());
+
+            let mut redirected = transfer_m2.clone();
+            redirected.request.public_key = alice.public_key;
+            assert!(TransferStateS::verify_transfer_m2(&redirected, &skp).is_err());
+            let mut inflated = transfer_m2.clone();
+            inflated.value_comm = (inflated.value_comm + inflated.value_comm).into_affine();
+            assert!(TransferStateS::verify_transfer_m2(&inflated, &skp).is_err());
+
+            let mut store = TGS::new(4);
+            assert!(store.record_transfer(&transfer_m2, &s_transfer_state));
+            assert!(!store.record_transfer(&transfer_m2, &s_transfer_state));
+
+            let transfer_m3 = TransferStateS::generate_transfer_m3(
+                &mut rng,
+                &transfer_m2,
+                &mut s_transfer_state,
+                &skp,
+            )
+            .unwrap();
+
+            // The recipient checks the value that it is given.
+            let mut wrong = opening;
+            wrong.value += SF::one();
+            assert!(TransferStateC::generate_transfer_m4(
+                &mut rng,
+                &mut bob_state.clone(),
+                &transfer_m3,
+                &wrong,
+            )
+            .is_err());
+
+            let transfer_m4 =
+                TransferStateC::generate_transfer_m4(&mut rng, &mut bob_state, &transfer_m3, &opening)
+                    .unwrap();
+            let transfer_m5 =
+                TransferStateS::generate_transfer_m5(&transfer_m4, &mut s_transfer_state, &skp);
+            let bob_token =
+                TransferStateC::populate_state(&mut bob_state, &transfer_m5, &skp, &bob);
+
+            assert_eq!(bob_token.token_state[0].value(), SF::from(5u64));
+            let check = ACLSV::verify(
+                skp.s_key_pair.verifying_key,
+                skp.s_key_pair.tag_key,
+                &bob_token.sig_state[0],
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
+            );
+            assert!(check);
+
+            // The recipient can spend the transferred token.
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &bob_token,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::from(2u64)],
+            );
+            assert!(SVBS::verify_spendverify_m2(&spendverify_m2, &skp).is_ok());
+        }
+
+        #[test]
+        fn test_boomerang_recovery() {
+            // Test that a user can move the value of its token to a fresh key with the backup
+            // key that it registered, and that the old token is then spent.
+            use ::boomerang::{
+                client::{BackupKeyPair, TransferStateC},
+                server::{RecoveryRegistry, TransferStateS},
+            };
+
+            let mut rng = TestRng::from_env();
+            let seed = [7u8; 32];
+            let alice = CBKP::from_seed(&seed);
+            assert!(alice == CBKP::from_seed(&seed));
+            let backup = BackupKeyPair::<$boomerangconfig>::from_seed(&seed);
+            assert_ne!(alice.public_key, backup.public_key);
+            let fresh = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let issuance_state = issue_token(&mut rng, &alice, &skp, SF::zero());
+
+            let mut s_col_state = CBSM::default();
+            let collection_m1 = CBSM::generate_collection_m1(&mut rng, &mut s_col_state);
+            let mut c_col_state = CBCM::default();
+            let collection_m2 = CBCM::generate_collection_m2(
+                &mut rng,
+                &issuance_state,
+                &collection_m1,
+                &mut c_col_state,
+                &skp,
+            );
+            let collection_m3 = CBSM::generate_collection_m3(
+                &mut rng,
+                &collection_m2,
+                &mut s_col_state,
+                &skp,
+                SF::from(5u64),
+            ).unwrap();
+            let collection_m4 =
+                CBCM::generate_collection_m4(&mut rng, &mut c_col_state, &collection_m3);
+            let collection_m5 =
+                CBSM::generate_collection_m5(&collection_m4, &mut s_col_state, &skp);
+            let alice_state =
+                CBCM::populate_state(&mut c_col_state, &collection_m5, &skp, &alice);
+
+            // The backup key is registered once, and cannot be replaced.
+            let mut registry = RecoveryRegistry::new();
+            let registration = backup.register(&mut rng, &alice);
+            assert!(registration.verify());
+            assert!(registry.register(&registration).is_ok());
+            assert!(registry.register(&registration).is_ok());
+            assert_eq!(registry.len(), 1);
+            let other = BackupKeyPair::<$boomerangconfig>::from_seed(&[8u8; 32]);
+            assert!(registry.register(&other.register(&mut rng, &alice)).is_err());
+            let mut forged = registration.clone();
+            forged.backup_pk = other.public_key;
+            assert!(registry.register(&forged).is_err());
+            assert_eq!(registry.backup_key(&alice.public_key), Some(backup.public_key));
+
+            let mut s_transfer_state = TransferStateS::default();
+            let transfer_m1 =
+                TransferStateS::generate_transfer_m1(&mut rng, &mut s_transfer_state);
+            let mut fresh_state = TransferStateC::default();
+            let request = TransferStateC::generate_transfer_request(
+                &mut rng,
+                &fresh,
+                &mut fresh_state,
+                &transfer_m1,
+            );
+
+            // The old key cannot be the fresh key.
+            let mut old_state = TransferStateC::default();
+            let old_request = TransferStateC::generate_transfer_request(
+                &mut rng,
+                &alice,
+                &mut old_state,
+                &transfer_m1,
+            );
+            assert!(TransferStateC::generate_recovery_m2(
+                &mut rng,
+                &alice_state,
+                &transfer_m1,
+                &old_request,
+                &backup,
+                &skp,
+            )
+            .is_err());
+
+            let (recovery_m2, opening) = TransferStateC::generate_recovery_m2(
+                &mut rng,
+                &alice_state,
+                &transfer_m1,
+                &request,
+                &backup,
+                &skp,
+            )
+            .unwrap();
+            assert!(TransferStateS::verify_recovery_m2(&recovery_m2, &skp, &registry).is_ok());
+
+            // A recovery with another backup key, or for a key without a backup, is rejected.
+            let (wrong_backup, _) = TransferStateC::generate_recovery_m2(
+                &mut rng,
+                &alice_state,
+                &transfer_m1,
+                &request,
+                &other,
+                &skp,
+            )
+            .unwrap();
+            assert!(TransferStateS::verify_recovery_m2(&wrong_backup, &skp, &registry).is_err());
+            assert!(
+                TransferStateS::verify_recovery_m2(&recovery_m2, &skp, &RecoveryRegistry::new())
+                    .is_err()
+            );
+            let mut claimed = recovery_m2.clone();
+            claimed.user_pk = fresh.public_key;
+            assert!(TransferStateS::verify_recovery_m2(&claimed, &skp, &registry).is_err());
+
+            let transfer_m3 = TransferStateS::generate_recovery_m3(
+                &mut rng,
+                &recovery_m2,
+                &mut s_transfer_state,
+                &skp,
+                &registry,
+            )
+            .unwrap();
+
+            // The tag of the old token is recorded, so that it cannot be spent again.
+            let mut store = TGS::new(4);
+            assert!(store.record_transfer(&recovery_m2.transfer, &s_transfer_state));
+            assert!(!store.record_transfer(&recovery_m2.transfer, &s_transfer_state));
+
+            let transfer_m4 = TransferStateC::generate_transfer_m4(
+                &mut rng,
+                &mut fresh_state,
+                &transfer_m3,
+                &opening,
+            )
+            .unwrap();
+            let transfer_m5 =
+                TransferStateS::generate_transfer_m5(&transfer_m4, &mut s_transfer_state, &skp);
+            let recovered =
+                TransferStateC::populate_state(&mut fresh_state, &transfer_m5, &skp, &fresh);
+
+            assert_eq!(recovered.token_state[0].value(), SF::from(5u64));
+            let check = ACLSV::verify(
+                skp.s_key_pair.verifying_key,
+                skp.s_key_pair.tag_key,
+                &recovered.sig_state[0],
+                &skp.token_message(),
+                Some(TOKEN_CONTEXT),
+            );
+            assert!(check);
+        }
+
+        #[test]
+        fn test_boomerang_clock() {
+            // Test that the epoch of a clock drives the revocation list and the spend limit,
+            // and that a test clock only moves when it is told to.
+            use ::boomerang::clock::{Clock, Epoch, SystemClock, TestClock};
+            use ::boomerang::spendlimit::SpendLimit;
+
+            let length = 3600;
+            let clock = TestClock::new(7 * length + 10);
+            assert_eq!(clock.epoch(length), Epoch::new(7));
+            assert_eq!(Epoch::new(7).start(length), Some(7 * length));
+            assert_eq!(Epoch::new(u64::MAX).next(), None);
+
+            let revocation = RevocationList::new(7);
+            assert!(revocation.is_current(&clock, length));
+
+            let limit = SpendLimit::new(BASE_ATTRIBUTES, 2);
+            let counter = limit.next_counter_with_clock(0, &clock, length).unwrap();
+            assert_eq!(counter, 15);
+            let counter = limit.next_counter_with_clock(counter, &clock, length).unwrap();
+            assert_eq!(limit.next_counter_with_clock(counter, &clock, length), None);
+
+            // The last second of the epoch still belongs to it, the next one does not.
+            clock.set(8 * length - 1);
+            assert!(revocation.is_current(&clock, length));
+            clock.advance(1);
+            assert_eq!(clock.epoch(length), Epoch::new(8));
+            assert!(!revocation.is_current(&clock, length));
+            assert_eq!(limit.next_counter_with_clock(counter, &clock, length), Some(17));
+
+            clock.set(u64::MAX);
+            clock.advance(1);
+            assert_eq!(clock.now(), u64::MAX);
+
+            assert!(SystemClock.now() > 0);
+        }
+
+        #[test]
+        fn test_boomerang_spend_limit() {
+            // Test that a token with a spend counter can be spent at most `limit` times per
+            // epoch, and that it starts over in the next epoch.
+            use ::boomerang::spendlimit::{counter_value, SpendLimit};
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            // The counter is added as a new attribute, which starts at zero.
+            let mut s_up_state = UBSM::default();
+            let update_m1 = UBSM::generate_update_m1(&mut rng, &mut s_up_state, BASE_ATTRIBUTES);
+            let mut c_up_state = UBCM::default();
+            let update_m2 = UBCM::generate_update_m2(
+                &mut rng,
+                &issuance_state,
+                &update_m1,
+                &mut c_up_state,
+                &skp,
+            );
+            let update_m3 =
+                UBSM::generate_update_m3(&mut rng, &update_m2, &mut s_up_state, &skp, SF::zero()).unwrap();
+            let update_m4 = UBCM::generate_update_m4(&mut rng, &mut c_up_state, &update_m3);
+            let update_m5 = UBSM::generate_update_m5(&update_m4, &mut s_up_state, &skp);
+            let mut token_state = UBCM::populate_state(&mut c_up_state, &update_m5, &skp, &ckp);
+
+            let limit = SpendLimit::new(BASE_ATTRIBUTES, 2);
+            assert_eq!(limit.window(3), Some((7, 8)));
+            assert_eq!(limit.next_counter(0, 3), Some(7));
+            assert_eq!(limit.next_counter(7, 3), Some(8));
+            assert_eq!(limit.next_counter(8, 3), None);
+            assert_eq!(limit.remaining(0, 3), 2);
+            assert_eq!(limit.remaining(7, 3), 1);
+
+            for (epoch, expected) in [(3u64, 7u64), (3, 8), (4, 9)] {
+                let mut s_spend_state = SVBS::default();
+                let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+                let mut c_spend_state = SVBC::default();
+                let (spendverify_m2, pi) = SVBC::generate_spendverify_m2_with_spend_limit(
+                    &mut rng,
+                    &token_state,
+                    &mut c_spend_state,
+                    &spendverify_m1,
+                    &skp,
+                    &[SF::zero()],
+                    &limit,
+                    epoch,
+                )
+                .unwrap();
+                assert!(SVBS::verify_spendverify_m2(&spendverify_m2, &skp).is_ok());
+                assert!(SVBS::verify_spend_limit(&spendverify_m2, &pi, &limit, epoch).is_ok());
+                assert!(SVBS::verify_spend_limit_with_rng(
+                    &spendverify_m2,
+                    &pi,
+                    &limit,
+                    epoch,
+                    &mut rng
+                )
+                .is_ok());
+
+                // The proof only holds for its epoch and limit.
+                assert!(SVBS::verify_spend_limit(&spendverify_m2, &pi, &limit, epoch + 1).is_err());
+                let other = SpendLimit::new(BASE_ATTRIBUTES, 3);
+                assert!(SVBS::verify_spend_limit(&spendverify_m2, &pi, &other, epoch).is_err());
+
+                let spendverify_m3 = SVBS::generate_spendverify_m3(
+                    &mut rng,
+                    &spendverify_m2,
+                    &mut s_spend_state,
+                    &skp,
+                    vec![SF::one()],
+                ).unwrap();
+                let spendverify_m4 = SVBC::generate_spendverify_m4(
+                    &mut rng,
+                    &mut c_spend_state,
+                    &spendverify_m3,
+                    &skp.policy_id(&[SF::one()]),
+                )
+                .unwrap();
+                let spendverify_m5 =
+                    SVBS::generate_spendverify_m5(&spendverify_m4, &mut s_spend_state, &skp);
+                token_state =
+                    SVBC::populate_state(&mut c_spend_state, &spendverify_m5, &skp, &ckp);
+
+                let counter = counter_value::<$boomerangconfig>(&token_state.token_state[0].attrs()[0]);
+                assert_eq!(counter, Some(expected));
+
+                // The second spend of an epoch exhausts the limit.
+                if expected == 8 {
+                    let mut s_spend_state = SVBS::default();
+                    let spendverify_m1 =
+                        SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+                    assert!(SVBC::generate_spendverify_m2_with_spend_limit(
+                        &mut rng,
+                        &token_state,
+                        &mut SVBC::default(),
+                        &spendverify_m1,
+                        &skp,
+                        &[SF::zero()],
+                        &limit,
+                        epoch,
+                    )
+                    .is_err());
+                }
+            }
+
+            // A token without the counter cannot be limited.
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            assert!(SVBC::generate_spendverify_m2_with_spend_limit(
+                &mut rng,
+                &issuance_state,
+                &mut SVBC::default(),
+                &spendverify_m1,
+                &skp,
+                &[SF::zero()],
+                &limit,
+                3,
+            )
+            .is_err());
+        }
+
+        #[test]
+        fn test_boomerang_spend_verify_precompute() {
+            // Test that the second message of a spend can be generated from a bundle that was
+            // computed before the server's first message, and that it matches the message that
+            // is generated in one go from the same randomness.
+            use ark_serialize::CanonicalSerialize;
+            use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            // Offline: the bundle only needs the token.
+            let mut pre_rng = StdRng::seed_from_u64(11);
+            let pre = SVBC::precompute(&mut pre_rng, &issuance_state);
+
+            // Online.
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2_precomputed(
+                &mut pre_rng,
+                &issuance_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::one()],
+                pre,
+            )
+            .unwrap();
+            assert!(SVBS::verify_spendverify_m2(&spendverify_m2, &skp).is_ok());
+
+            let expected = SVBC::generate_spendverify_m2(
+                &mut StdRng::seed_from_u64(11),
+                &issuance_state,
+                &mut SVBC::default(),
+                &spendverify_m1,
+                &skp,
+                &[SF::one()],
+            );
+            let (mut bytes, mut expected_bytes) = (Vec::new(), Vec::new());
+            spendverify_m2.serialize_compressed(&mut bytes).unwrap();
+            expected.serialize_compressed(&mut expected_bytes).unwrap();
+            assert_eq!(bytes, expected_bytes);
+
+            // A bundle only holds for the token that it was computed for.
+            let other_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+            let other_pre = SVBC::precompute(&mut rng, &other_state);
+            assert!(SVBC::generate_spendverify_m2_precomputed(
+                &mut rng,
+                &issuance_state,
+                &mut SVBC::default(),
+                &spendverify_m1,
+                &skp,
+                &[SF::one()],
+                other_pre,
+            )
+            .is_err());
+
+            let spendverify_m3 = SVBS::generate_spendverify_m3(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state,
+                &skp,
+                vec![SF::from(2u64)],
+            ).unwrap();
+            let spendverify_m4 = SVBC::generate_spendverify_m4(
+                &mut rng,
+                &mut c_spend_state,
+                &spendverify_m3,
+                &skp.policy_id(&[SF::from(2u64)]),
+            )
+            .unwrap();
+            let spendverify_m5 =
+                SVBS::generate_spendverify_m5(&spendverify_m4, &mut s_spend_state, &skp);
+            let spend_state = SVBC::populate_state(&mut c_spend_state, &spendverify_m5, &skp, &ckp);
+            assert!(spend_state.sig_state[0].sigma.zeta.is_on_curve());
+        }
+
+        #[test]
+        fn test_boomerang_json() {
+            // Test that every message of a spend survives its JSON representation, and that the
+            // decoder rejects messages of another type or version, or with a missing field.
+            use ::boomerang::client::SpendVerifyM2;
+            use ::boomerang::json::{JsonMessage, JSON_VERSION};
+            use ::boomerang::server::{CollectionM1, SpendVerifyM1};
+            use ark_serialize::CanonicalSerialize;
+
+            let mut rng = TestRng::from_env();
+            fn roundtrip<T: JsonMessage + CanonicalSerialize>(m: &T) -> String {
+                let json = m.to_json();
+                let decoded = T::from_json(&json).unwrap();
+                let (mut bytes, mut decoded_bytes) = (Vec::new(), Vec::new());
+                m.serialize_compressed(&mut bytes).unwrap();
+                decoded.serialize_compressed(&mut decoded_bytes).unwrap();
+                assert_eq!(bytes, decoded_bytes);
+                json
+            }
+
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let mut state = IBCM::default();
+            let issuance_m1 = IBCM::generate_issuance_m1(&ckp, &mut state, &mut rng);
+            roundtrip(&issuance_m1);
+            let mut s_state = IBSM::default();
+            let issuance_m2 =
+                IBSM::generate_issuance_m2(&issuance_m1, &skp, &mut s_state, &mut rng);
+            roundtrip(&issuance_m2);
+            let issuance_m3 = IBCM::generate_issuance_m3(&issuance_m2, &mut state, &mut rng);
+            roundtrip(&issuance_m3);
+            let issuance_m4 = IBSM::generate_issuance_m4(&issuance_m3, &mut s_state, &skp);
+            roundtrip(&issuance_m4);
+            let issuance_state = IBCM::populate_state(&issuance_m4, &mut state, &skp, &ckp);
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let m1_json = roundtrip(&spendverify_m1);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &issuance_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::one()],
+            );
+            let m2_json = roundtrip(&spendverify_m2);
+            let decoded = SpendVerifyM2::<$boomerangconfig>::from_json(&m2_json).unwrap();
+            assert!(SVBS::verify_spendverify_m2(&decoded, &skp).is_ok());
+
+            let spendverify_m3 = SVBS::generate_spendverify_m3(
+                &mut rng,
+                &decoded,
+                &mut s_spend_state,
+                &skp,
+                vec![SF::from(2u64)],
+            ).unwrap();
+            roundtrip(&spendverify_m3);
+            let spendverify_m4 = SVBC::generate_spendverify_m4(
+                &mut rng,
+                &mut c_spend_state,
+                &spendverify_m3,
+                &skp.policy_id(&[SF::from(2u64)]),
+            )
+            .unwrap();
+            roundtrip(&spendverify_m4);
+            let spendverify_m5 =
+                SVBS::generate_spendverify_m5(&spendverify_m4, &mut s_spend_state, &skp);
+            roundtrip(&spendverify_m5);
+
+            // The representation names the fields, and carries the version and type.
+            let version = format!("\"version\":{}", JSON_VERSION);
+            assert!(m1_json.contains(&version));
+            assert!(m1_json.contains("\"type\":\"SpendVerifyM1\""));
+            let field = |json: &str, name: &str| {
+                let start = json.find(&format!("\"{}\":\"", name)).unwrap() + name.len() + 4;
+                json[start..start + json[start..].find('"').unwrap()].to_string()
+            };
+            let session_id = field(&m1_json, "session_id");
+            assert!(session_id
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_'));
+
+            // Unknown members are ignored.
+            let extended = m1_json.replacen('{', "{\"comment\":\"added later\",", 1);
+            assert!(SpendVerifyM1::<$boomerangconfig>::from_json(&extended).is_ok());
+
+            // A message of another type is rejected.
+            assert!(CollectionM1::<$boomerangconfig>::from_json(&m1_json).is_err());
+
+            // A message of another version is rejected.
+            let other_version = m1_json.replace(&version, &format!("\"version\":{}", JSON_VERSION + 1));
+            assert!(SpendVerifyM1::<$boomerangconfig>::from_json(&other_version).is_err());
+
+            // A message with a missing field is rejected.
+            let r2 = format!("\"r2\":\"{}\",", field(&m1_json, "r2"));
+            let missing = m1_json.replace(&r2, "");
+            assert!(SpendVerifyM1::<$boomerangconfig>::from_json(&missing).is_err());
+
+            // A payload with trailing bytes is rejected.
+            let trailing = m1_json.replace(&session_id, &format!("{}AAAA", session_id));
+            assert!(SpendVerifyM1::<$boomerangconfig>::from_json(&trailing).is_err());
+        }
+
+        #[cfg(feature = "cbor")]
+        #[test]
+        fn test_boomerang_cbor() {
+            // Test that the messages of a spend survive their CBOR representation, which is
+            // deterministic and has a fraction of the overhead of the JSON representation.
+            use ::boomerang::cbor::{cose_sign1, sig_structure, CborMessage};
+            use ::boomerang::client::SpendVerifyM2;
+            use ::boomerang::json::JsonMessage;
+            use ::boomerang::server::{CollectionM1, SpendVerifyM1};
+            use ark_serialize::CanonicalSerialize;
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &issuance_state,
+                &mut SVBC::default(),
+                &spendverify_m1,
+                &skp,
+                &[SF::one()],
+            );
+
+            let cbor = spendverify_m2.to_cbor();
+            let decoded = SpendVerifyM2::<$boomerangconfig>::from_cbor(&cbor).unwrap();
+            assert!(SVBS::verify_spendverify_m2(&decoded, &skp).is_ok());
+            assert_eq!(decoded.to_cbor(), cbor);
+
+            let (mut bytes, mut decoded_bytes) = (Vec::new(), Vec::new());
+            spendverify_m2.serialize_compressed(&mut bytes).unwrap();
+            decoded.serialize_compressed(&mut decoded_bytes).unwrap();
+            assert_eq!(bytes, decoded_bytes);
+
+            // The overhead over the compressed encoding is less than half of the JSON one.
+            let json = spendverify_m2.to_json();
+            assert!(2 * (cbor.len() - bytes.len()) < json.len() - bytes.len());
+
+            // Only the deterministic encoding is accepted.
+            let m1 = spendverify_m1.to_cbor();
+            assert!(SpendVerifyM1::<$boomerangconfig>::from_cbor(&m1).is_ok());
+            assert_eq!(m1[0], 0xa4);
+            let mut long_header = vec![0xb8, 0x04];
+            long_header.extend_from_slice(&m1[1..]);
+            assert!(SpendVerifyM1::<$boomerangconfig>::from_cbor(&long_header).is_err());
+            let mut trailing = m1.clone();
+            trailing.push(0);
+            assert!(SpendVerifyM1::<$boomerangconfig>::from_cbor(&trailing).is_err());
+            assert!(CollectionM1::<$boomerangconfig>::from_cbor(&m1).is_err());
+
+            // The COSE_Sign1 structures wrap the payload as a byte string.
+            let to_sign = sig_structure(&[0xa0], &[], &m1);
+            assert_eq!(&to_sign[..12], b"\x84\x6aSignature1");
+            assert!(to_sign.ends_with(&m1));
+            let envelope = cose_sign1(&[0xa0], &m1, &[1, 2, 3]);
+            assert_eq!(envelope[0], 0xd2);
+            assert!(envelope.ends_with(&[0x43, 1, 2, 3]));
+        }
+
+        #[cfg(feature = "unsafe_deterministic_provers")]
+        #[test]
+        fn test_boomerang_deterministic_provers() {
+            // Test that provers that are given an AuditRng make the same proofs, bit for bit,
+            // from the same seed and witness, and different ones from a different seed.
+            use ::boomerang::rng::AuditRng;
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+
+            // The witness of the spend: the values of the token.
+            let mut witness = Vec::new();
+            issuance_state.token_state[0]
+                .value()
+                .serialize_compressed(&mut witness)
+                .unwrap();
+
+            let spend = |seed: &[u8; 32]| {
+                let mut audit_rng = AuditRng::new(seed, b"spend-verify-m2", &[&witness]);
+                let m2 = SVBC::generate_spendverify_m2(
+                    &mut audit_rng,
+                    &issuance_state,
+                    &mut SVBC::default(),
+                    &spendverify_m1,
+                    &skp,
+                    &[SF::one()],
+                );
+                let mut bytes = Vec::new();
+                m2.serialize_compressed(&mut bytes).unwrap();
+                (m2, bytes)
+            };
+
+            let (m2, bytes) = spend(&[7u8; 32]);
+            assert!(SVBS::verify_spendverify_m2(&m2, &skp).is_ok());
+            assert_eq!(spend(&[7u8; 32]).1, bytes);
+            assert_ne!(spend(&[8u8; 32]).1, bytes);
+
+            // The signer of the ACL signatures is derandomised in the same way.
+            let sign = |seed: &[u8; 32]| {
+                let mut audit_rng = AuditRng::new(seed, b"issuance-m2", &[]);
+                let m2 = IBSM::generate_issuance_m2(
+                    &issuance_m1,
+                    &skp,
+                    &mut IBSM::default(),
+                    &mut audit_rng,
+                );
+                let mut bytes = Vec::new();
+                m2.serialize_compressed(&mut bytes).unwrap();
+                bytes
+            };
+            assert_eq!(sign(&[7u8; 32]), sign(&[7u8; 32]));
+            assert_ne!(sign(&[7u8; 32]), sign(&[8u8; 32]));
+        }
+
+        #[test]
+        fn test_boomerang_spend_verify_progress() {
+            // Test that the progress callback sees every phase, can abort the generation, and
+            // does not change the generated message.
+            use ::boomerang::client::{SPEND_M2_PHASES, SPEND_M4_PHASES};
+            use ::boomerang::progress::{Progress, ProofPhase};
+            use ark_std::rand::{rngs::StdRng, SeedableRng};
+            use std::ops::ControlFlow;
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+
+            let mut phases = Vec::new();
+            let mut record = |progress: Progress| {
+                assert_eq!(progress.steps, SPEND_M2_PHASES.len());
+                phases.push(progress.phase);
+                ControlFlow::Continue(())
+            };
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2_with_progress(
+                &mut StdRng::seed_from_u64(7),
+                &issuance_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::one()],
+                Some(&mut record),
+            )
+            .unwrap();
+            assert_eq!(&phases[..SPEND_M2_PHASES.len()], SPEND_M2_PHASES);
+            assert_eq!(phases.last(), Some(&ProofPhase::Done));
+
+            // Without a callback, the same randomness gives the same message.
+            let expected = SVBC::generate_spendverify_m2(
+                &mut StdRng::seed_from_u64(7),
+                &issuance_state,
+                &mut SVBC::default(),
+                &spendverify_m1,
+                &skp,
+                &[SF::one()],
+            );
+            let (mut bytes, mut expected_bytes) = (Vec::new(), Vec::new());
+            spendverify_m2.serialize_compressed(&mut bytes).unwrap();
+            expected.serialize_compressed(&mut expected_bytes).unwrap();
+            assert_eq!(bytes, expected_bytes);
+
+            // The caller can abort before any phase.
+            let mut abort = |progress: Progress| {
+                if progress.phase == ProofPhase::SubProof {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            };
+            assert!(SVBC::generate_spendverify_m2_with_progress(
+                &mut rng,
+                &issuance_state,
+                &mut SVBC::default(),
+                &spendverify_m1,
+                &skp,
+                &[SF::one()],
+                Some(&mut abort),
+            )
+            .is_err());
+
+            let spendverify_m3 = SVBS::generate_spendverify_m3(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state,
+                &skp,
+                vec![SF::from(2u64)],
+            ).unwrap();
+
+            let mut phases = Vec::new();
+            let mut record = |progress: Progress| {
+                phases.push(progress.phase);
+                ControlFlow::Continue(())
+            };
+            let spendverify_m4 = SVBC::generate_spendverify_m4_with_progress(
+                &mut rng,
+                &mut c_spend_state,
+                &spendverify_m3,
+                &skp.policy_id(&[SF::from(2u64)]),
+                Some(&mut record),
+            )
+            .unwrap();
+            assert_eq!(&phases[..SPEND_M4_PHASES.len()], SPEND_M4_PHASES);
+            assert_eq!(phases.last(), Some(&ProofPhase::Done));
+
+            let spendverify_m5 =
+                SVBS::generate_spendverify_m5(&spendverify_m4, &mut s_spend_state, &skp);
+            let spend_state =
+                SVBC::populate_state(&mut c_spend_state, &spendverify_m5, &skp, &ckp);
+            assert_eq!(spend_state.sig_state.len(), 1);
+        }
+
+        #[test]
+        fn test_boomerang_spend_bundle() {
+            // Test that a spend can be checked by a third party that only holds the public keys.
+            use ::boomerang::{verify_spend_bundle, SpendBundle};
+            use ark_serialize::CanonicalDeserialize;
+
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut other_spend_state = SVBS::default();
+            let other_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut other_spend_state);
+
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &issuance_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::one()],
+            );
+            let spendverify_m3 = SVBS::generate_spendverify_m3(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state,
+                &skp,
+                vec![SF::from(2u64)],
+            ).unwrap();
+
+            let policy_id = skp.policy_id(&[SF::from(2u64)]);
+            let bundle = SpendBundle::new(spendverify_m2.clone(), &spendverify_m3).unwrap();
+            assert_eq!(*bundle.tag(), spendverify_m2.tag);
+            assert!(
+                verify_spend_bundle(&bundle, skp.public_key(), skp.tag_key(), &policy_id).is_ok()
+            );
+
+            // The bundle does not hold for another policy than the one that was published.
+            assert_eq!(
+                verify_spend_bundle(
+                    &bundle,
+                    skp.public_key(),
+                    skp.tag_key(),
+                    &skp.policy_id(&[SF::one()])
+                ),
+                Err("Boomerang spend bundle: rewards proof is for another policy")
+            );
+
+            // The bundle survives a round trip through its wire format.
+            let mut bytes = Vec::new();
+            bundle.serialize_compressed(&mut bytes).unwrap();
+            let decoded = SpendBundle::deserialize_compressed(&bytes[..]).unwrap();
+            assert!(
+                verify_spend_bundle(&decoded, skp.public_key(), skp.tag_key(), &policy_id).is_ok()
+            );
+
+            // The bundle does not hold under the keys of another server.
+            let other = SBKP::generate(&mut rng);
+            assert_eq!(
+                verify_spend_bundle(&bundle, other.public_key(), other.tag_key(), &policy_id),
+                Err("Boomerang spend-verify: invalid signature")
+            );
+
+            // Changing the spend state breaks the rewards proof.
+            let mut tampered = SpendBundle::new(spendverify_m2.clone(), &spendverify_m3).unwrap();
+            tampered.m2.spend_state = vec![SF::from(3u64)];
+            assert_eq!(
+                verify_spend_bundle(&tampered, skp.public_key(), skp.tag_key(), &policy_id),
+                Err("Boomerang spend bundle: invalid rewards proof")
+            );
+
+            // Messages from different sessions cannot be bundled.
+            let mut relabelled = spendverify_m3.clone();
+            relabelled.session_id = other_m1.session_id;
+            assert!(SpendBundle::new(spendverify_m2, &relabelled).is_err());
+        }
+
+        #[cfg(feature = "r1cs")]
+        #[test]
+        fn test_boomerang_r1cs_gadgets() {
+            use ::boomerang::constraints::{
+                ACLVerifyGadget, GVar, PedersenOpeningGadget, ScalarVar, SignatureVar,
+            };
+            use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+            use ark_relations::r1cs::ConstraintSystem;
+
+            // Get a token signature by running the issuance protocol.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+            let sigma = issuance_state.sig_state[0].sigma.clone();
+
+            let cs = ConstraintSystem::new_ref();
+            let pub_key = GVar::<$boomerangconfig>::new_input(cs.clone(), || {
+                Ok(skp.s_key_pair.verifying_key)
+            })
+            .unwrap();
+            let tag_key =
+                GVar::<$boomerangconfig>::new_input(cs.clone(), || Ok(skp.s_key_pair.tag_key))
+                    .unwrap();
+            let sig = SignatureVar::new_witness(cs.clone(), || Ok(sigma.clone())).unwrap();
+
+            // The recomputed commitments match those of the native verifier.
+            let inputs = ACLVerifyGadget::challenge_inputs(&pub_key, &tag_key, &sig).unwrap();
+            let tmp1 = <$boomerangconfig as SWCurveConfig>::GENERATOR.mul(sigma.rho)
+                + skp.s_key_pair.verifying_key.mul(sigma.omega);
+            assert_eq!(inputs[0].value().unwrap(), tmp1);
+
+            // The challenge must be omega + omega1.
+            let epsilon = sigma.omega + sigma.omega1;
+            let e = ScalarVar::new_input(cs.clone(), || Ok(epsilon)).unwrap();
+            assert!(ACLVerifyGadget::verify(&sig, &e).unwrap().value().unwrap());
+            let e = ScalarVar::new_input(cs.clone(), || Ok(epsilon + SF::one())).unwrap();
+            assert!(!ACLVerifyGadget::verify(&sig, &e).unwrap().value().unwrap());
+
+            // Check a Pedersen opening, and that a wrong value is rejected.
+            let x = SF::rand(&mut rng);
+            let c = PC::new(x, &mut rng);
+            let comm = GVar::<$boomerangconfig>::new_input(cs.clone(), || Ok(c.comm)).unwrap();
+            let r = ScalarVar::new_witness(cs.clone(), || Ok(c.r)).unwrap();
+            let x_var = ScalarVar::new_witness(cs.clone(), || Ok(x)).unwrap();
+            assert!(PedersenOpeningGadget::verify(&comm, &x_var, &r)
+                .unwrap()
+                .value()
+                .unwrap());
+            let y_var = ScalarVar::new_witness(cs.clone(), || Ok(x + SF::one())).unwrap();
+            assert!(!PedersenOpeningGadget::verify(&comm, &y_var, &r)
+                .unwrap()
+                .value()
+                .unwrap());
+
+            assert!(cs.is_satisfied().unwrap());
+        }
+
+        #[cfg(feature = "metrics")]
+        #[test]
+        fn test_boomerang_metrics() {
+            // Test that the server functions update the metrics. The registry is shared by
+            // every test, so only the increments are checked.
+            let mut rng = TestRng::from_env();
+            fn value(metrics: &str, series: &str) -> f64 {
+                metrics
+                    .lines()
+                    .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+                    .map_or(0.0, |v| v.parse().unwrap())
+            }
+
+            let m1_in = "boomerang_messages_total{message=\"IssuanceM1\",direction=\"in\"}";
+            let m2_out = "boomerang_message_bytes_total{message=\"IssuanceM2\",direction=\"out\"}";
+            let verify = "boomerang_verify_duration_seconds_count{message=\"SpendVerifyM2\"}";
+            let reject = "boomerang_rejects_total{reason=\"Boomerang spend-verify: invalid signature\"}";
+            let before = ::boomerang::metrics::render();
+
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &issuance_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::one()],
+            );
+            assert!(SVBS::verify_spendverify_m2(&spendverify_m2, &skp).is_ok());
+
+            // A token of another server is rejected.
+            let other = SBKP::generate(&mut rng);
+            assert_eq!(
+                SVBS::verify_spendverify_m2(&spendverify_m2, &other),
+                Err("Boomerang spend-verify: invalid signature")
+            );
+
+            let after = ::boomerang::metrics::render();
+            assert!(value(&after, m1_in) >= value(&before, m1_in) + 1.0);
+            assert!(
+                value(&after, m2_out)
+                    >= value(&before, m2_out) + issuance_m2.compressed_size() as f64
+            );
+            assert!(value(&after, verify) >= value(&before, verify) + 2.0);
+            assert!(value(&after, reject) >= value(&before, reject) + 1.0);
+            assert!(after.contains("# TYPE boomerang_sessions_active gauge"));
+        }
+
+        #[test]
+        fn test_boomerang_revocation() {
+            // Test that a revoked token is rejected in spend m3.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            // Revoke a few unrelated tokens.
+            let mut revocation = RevocationList::new(7);
+            for i in 0..5u8 {
+                assert!(revocation.revoke([i * 50; 32]));
+            }
+            assert!(!revocation.revoke([0; 32]));
+            assert_eq!(revocation.len(), 5);
+
+            let key = issuance_state.revocation_key();
+            let root = revocation.root();
+            let witness = revocation.non_revocation_witness(&key).unwrap();
+            assert!(witness.verify(&root, &key));
+            assert!(!witness.verify(&RevocationList::new(8).root(), &key));
+
+            // Every revoked key is rejected, and every other key has a witness.
+            for i in 0..5u8 {
+                assert!(revocation.non_revocation_witness(&[i * 50; 32]).is_none());
+                let other = [i * 50 + 1; 32];
+                let other_witness = revocation.non_revocation_witness(&other).unwrap();
+                assert!(other_witness.verify(&root, &other));
+                assert!(!other_witness.verify(&root, &[i * 50; 32]));
+            }
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spendverify_m2 = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &issuance_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::zero()],
+            );
+            assert!(SVBS::verify_not_revoked(&spendverify_m2, &root, &witness).is_ok());
+
+            // Once the token is revoked, the old witness no longer holds and no new one can be made.
+            let mut revoked = revocation.clone();
+            assert!(revoked.revoke(key));
+            assert!(revoked.non_revocation_witness(&key).is_none());
+            assert_eq!(
+                SVBS::verify_not_revoked(&spendverify_m2, &revoked.root(), &witness),
+                Err("Boomerang spend-verify: token is revoked")
+            );
+
+            let policy_state: Vec<SF> = vec![SF::from(2u64)];
+            let spendverify_m3 = SVBS::generate_spendverify_m3_with_revocation(
+                &mut rng,
+                &spendverify_m2,
+                &mut s_spend_state,
+                &skp,
+                policy_state,
+                &root,
+                &witness,
+            );
+            assert!(spendverify_m3.is_ok());
+        }
+
+        #[test]
+        fn test_boomerang_tag_store_checkpoints() {
+            // Test that recorded tags can be audited against the checkpoints of the tag store.
+            let mut rng = TestRng::from_env();
+            let ckp = CBKP::generate(&mut rng);
+            let skp = SBKP::generate(&mut rng);
+
+            let issuance_state = issue_token(&mut rng, &ckp, &skp, SF::zero());
+
+            let mut s_spend_state = SVBS::default();
+            let spendverify_m1 = SVBS::generate_spendverify_m1(&mut rng, &mut s_spend_state);
+            let mut c_spend_state = SVBC::default();
+            let spend_a = SVBC::generate_spendverify_m2(
+                &mut rng,
+                &issuance_state,
+                &mut c_spend_state,
+                &spendverify_m1,
+                &skp,
+                &[SF::zero()],
+            );
+
+            // Stand-ins for the messages of other spends.
+            let mut spend_b = spend_a.clone();
+            spend_b.tag += SF::one();
+            spend_b.id += SF::one();
+            let mut spend_c = spend_a.clone();
+            spend_c.tag += SF::from(2u64);
+
+            let mut store = TGS::new(2);
+            assert!(store.record_spend(&spend_a, &s_spend_state));
+            assert!(store.checkpoints().is_empty());
+            assert!(store.record_spend(&spend_b, &s_spend_state));
+            assert_eq!(store.checkpoints().len(), 1);
+
+            // spend_c reuses the serial number of spend_a.
+            assert!(!store.record_spend(&spend_c, &s_spend_state));
+            assert_eq!(store.len(), 3);
+
+            let root0 = store.checkpoints()[0];
+            assert_eq!(root0.len, 2);
+            for tag in [spend_a.tag, spend_b.tag] {
+                let path = store.inclusion_proof(0, &tag).unwrap();
+                assert_eq!(path.key, TGS::tag_key(&tag));
+                assert!(path.verify(&root0));
+                assert!(store.exclusion_proof(0, &tag).is_none());
+            }
+
+            let key_c = TGS::tag_key(&spend_c.tag);
+            assert!(store.inclusion_proof(0, &spend_c.tag).is_none());
+            let exclusion = store.exclusion_proof(0, &spend_c.tag).unwrap();
+            assert!(exclusion.verify(&root0, &key_c));
+
+            // A later checkpoint covers spend_c, and the old proofs do not hold against it.
+            let root1 = store.checkpoint();
+            assert_eq!(store.checkpoints(), vec![root0, root1]);
+            assert!(store
+                .inclusion_proof(1, &spend_c.tag)
+                .unwrap()
+                .verify(&root1));
+            assert!(!exclusion.verify(&root1, &key_c));
+            assert!(!store
+                .inclusion_proof(0, &spend_a.tag)
+                .unwrap()
+                .verify(&root1));
+            assert!(store.inclusion_proof(2, &spend_a.tag).is_none());
         }
     };
 }
@@ -820,9 +4959,22 @@ macro_rules! test_boomerang {
                 verify::SigVerify,
             };
             use ::boomerang::{
-                client::CollectionStateC, client::IssuanceStateC, client::SpendVerifyStateC,
-                client::UKeyPair, config::BoomerangConfig, server::CollectionStateS,
-                server::IssuanceStateS, server::ServerKeyPair, server::SpendVerifyStateS,
+                batch::BatchVerifier, client::CollectionStateC, client::IssuanceStateC,
+                client::SpendVerifyStateC, client::UKeyPair, client::UpdateStateC,
+                commitment::SplitComm, commitment::StateCommitment, config::new_session_id,
+                config::session_transcript, config::BoomerangConfig, config::ProtocolContext,
+                config::BASE_ATTRIBUTES, config::TOKEN_CONTEXT, encoding, rng::SeededRng,
+                server::CollectionStateS, server::IssuanceStateS, server::RateLimit,
+                server::RevocationList, server::ServerConfig, server::ServerConfigError,
+                server::ServerKeyPair, server::SpendVerifyReply, server::SpendVerifyStateS,
+                server::TagStore, server::UpdateStateS, server::VerificationPool,
+                sizes::sizes_for_state, sizes::spend_verify_sizes, testvectors::TestVector,
+                ticket::MemoryReplayGuard, ticket::ReplayGuard, ticket::SessionTicket,
+                ticket::TicketKey, utils::rewards::policy_id, utils::rewards::BRewardsProof,
+                utils::rewards::CancellationToken, utils::rewards::JointRewardsProof,
+                utils::rewards::LegacyBRewardsProof, utils::rewards::RewardsGenerators,
+                utils::rewards::RewardsProofError, utils::rewards::SparseState,
+                utils::rewards::SubProof, utils::rewards::REWARDS_PROOF_VERSION,
             };
             use ark_ec::{
                 models::CurveConfig,
@@ -837,7 +4989,7 @@ macro_rules! test_boomerang {
             use core::ops::Mul;
             use merlin::Transcript;
             use pedersen::{pedersen_config::PedersenComm, pedersen_config::PedersenConfig};
-            use rand_core::OsRng;
+            use rand_core::RngCore;
             use sha2::{Digest, Sha512};
             $crate::__test_boomerang!($aclconfig, $config, $boomerangconfig, $OtherProjectiveType);
         }
@@ -0,0 +1,14 @@
+/// test_boomerang_config. This macro derives the tests of the trait stack that
+/// `derive_boomerang_config!` derives, i.e the Pedersen, ACL and Boomerang tests, and the
+/// checks of the constants of the curve pair, in the modules `tp`, `acl`, `boomerang` and
+/// `pair`. An attribute can be given for the field order test of the pair, as in
+/// `test_curve_pair!`.
+#[macro_export]
+macro_rules! test_boomerang_config {
+    ($config: ty, $OtherProjectiveType: ty $(; #[$field_orders: meta])?) => {
+        $crate::test_pedersen!(tp; $config, $OtherProjectiveType);
+        $crate::test_acl!(acl; $config, $config, $OtherProjectiveType);
+        $crate::test_boomerang!(boomerang; $config, $config, $config, $OtherProjectiveType);
+        $crate::test_curve_pair!(pair; $config $(; #[$field_orders])?);
+    };
+}
@@ -0,0 +1,131 @@
+/// test_curve_pair. This macro checks the constants that pair a T curve with its O curve. An
+/// attribute can be given for the field order test, e.g. to ignore it on a crate that fails it.
+#[macro_export]
+macro_rules! test_curve_pair {
+    ($mod_name: ident; $config: ty $(; #[$field_orders: meta])?) => {
+        mod $mod_name {
+            use super::*;
+            use ::acl::config::ACLConfig;
+            use ::boomerang::config::BoomerangConfig;
+            use ark_ec::{
+                models::CurveConfig,
+                short_weierstrass::{self as sw, SWCurveConfig},
+                AffineRepr,
+            };
+            use ark_ff::{BigInteger, Field, PrimeField};
+            use ark_std::{UniformRand, Zero};
+            use pedersen::pedersen_config::PedersenConfig;
+            use rand_core::OsRng;
+            use std::any::TypeId;
+
+            type OC = <$config as PedersenConfig>::OCurve;
+            type SF = <$config as CurveConfig>::ScalarField;
+            type BF = <$config as CurveConfig>::BaseField;
+            type OSF = <OC as CurveConfig>::ScalarField;
+            type OBF = <OC as CurveConfig>::BaseField;
+
+            // The little-endian bytes of `x`, without the zero limbs that differently sized
+            // big integers are padded with.
+            fn le_bytes<B: BigInteger>(x: B) -> Vec<u8> {
+                let mut bytes = x.to_bytes_le();
+                while bytes.last() == Some(&0) {
+                    bytes.pop();
+                }
+                bytes
+            }
+
+            fn assert_prime_order<C: SWCurveConfig>(name: &str, point: &sw::Affine<C>) {
+                assert!(point.is_on_curve(), "{} is not on the curve", name);
+                assert!(!point.is_zero(), "{} is the identity", name);
+                assert!(
+                    point.is_in_correct_subgroup_assuming_on_curve(),
+                    "{} is not in the prime order subgroup",
+                    name
+                );
+                assert!(
+                    point
+                        .mul_bigint(<C::ScalarField as PrimeField>::MODULUS)
+                        .is_zero(),
+                    "{} does not have the order of the scalar field",
+                    name
+                );
+            }
+
+            #[test]
+            $(#[$field_orders])?
+            fn test_curve_pair_field_orders() {
+                // The scalar field of the T curve is the base field of the O curve.
+                assert_eq!(le_bytes(SF::MODULUS), le_bytes(OBF::MODULUS));
+            }
+
+            #[test]
+            fn test_curve_pair_configs() {
+                // Every config of the crate is paired with the same O curve.
+                assert_eq!(
+                    TypeId::of::<<$config as ACLConfig>::OCurve>(),
+                    TypeId::of::<OC>()
+                );
+                assert_eq!(
+                    TypeId::of::<<$config as BoomerangConfig>::Curve>(),
+                    TypeId::of::<OC>()
+                );
+            }
+
+            #[test]
+            fn test_curve_pair_generators() {
+                let g = <$config as SWCurveConfig>::GENERATOR;
+                let h = <$config as PedersenConfig>::GENERATOR2;
+                assert_prime_order("GENERATOR", &g);
+                assert_prime_order("GENERATOR2", &h);
+                assert_ne!(g, h);
+                assert_eq!(<$config as ACLConfig>::GENERATOR2, h);
+
+                let og = <OC as SWCurveConfig>::GENERATOR;
+                let oh = <$config as PedersenConfig>::OGENERATOR2;
+                assert_prime_order("OCurve GENERATOR", &og);
+                assert_prime_order("OGENERATOR2", &oh);
+                assert_ne!(og, oh);
+            }
+
+            #[test]
+            fn test_curve_pair_conversions() {
+                assert_eq!(
+                    <$config as PedersenConfig>::make_single_bit_challenge(0),
+                    -SF::ONE
+                );
+                assert_eq!(
+                    <$config as PedersenConfig>::make_single_bit_challenge(1),
+                    SF::ONE
+                );
+
+                for _ in 0..32 {
+                    // The O curve's base field is the T curve's scalar field, so the conversion
+                    // between them round trips.
+                    let x = OBF::rand(&mut OsRng);
+                    let y = <$config as PedersenConfig>::from_ob_to_sf(x);
+                    assert_eq!(OBF::from_bigint(y.into_bigint()), Some(x));
+
+                    // The other conversions reduce modulo the order of the target field.
+                    assert_eq!(
+                        <$config as PedersenConfig>::from_ob_to_os(x),
+                        OSF::from_le_bytes_mod_order(&x.into_bigint().to_bytes_le())
+                    );
+
+                    let s = OSF::rand(&mut OsRng);
+                    let expected = SF::from_le_bytes_mod_order(&s.into_bigint().to_bytes_le());
+                    assert_eq!(<$config as PedersenConfig>::from_os_to_sf(s), expected);
+                    assert_eq!(<$config as PedersenConfig>::from_oc(s), expected);
+
+                    let b = BF::rand(&mut OsRng);
+                    assert_eq!(
+                        <$config as PedersenConfig>::from_bf_to_sf(b),
+                        SF::from_le_bytes_mod_order(&b.into_bigint().to_bytes_le())
+                    );
+
+                    let v = u64::rand(&mut OsRng);
+                    assert_eq!(<$config as PedersenConfig>::from_u64_to_sf(v), SF::from(v));
+                }
+            }
+        }
+    };
+}
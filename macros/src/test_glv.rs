@@ -0,0 +1,96 @@
+#[macro_export]
+macro_rules! test_glv {
+    ($mod_name: ident; $config: ty) => {
+        mod $mod_name {
+            use super::*;
+            use ark_ec::{
+                models::CurveConfig, short_weierstrass::SWCurveConfig, AffineRepr, CurveGroup,
+                Group,
+            };
+            use ark_ff::{BigInteger, Field, PrimeField};
+            use ark_std::{UniformRand, Zero};
+            use rand_core::OsRng;
+
+            type SF = <$config as CurveConfig>::ScalarField;
+            type PT = sw::Projective<$config>;
+
+            // Plain double-and-add, used as the reference implementation.
+            fn naive_mul(base: &PT, scalar: &SF) -> PT {
+                let mut res = PT::zero();
+                for b in scalar.into_bigint().to_bits_be() {
+                    res.double_in_place();
+                    if b {
+                        res += base;
+                    }
+                }
+                res
+            }
+
+            #[test]
+            fn test_glv_endomorphism() {
+                let g: PT = <$config as SWCurveConfig>::GENERATOR.into();
+                let lambda = <$config>::GLV_LAMBDA;
+                assert!(lambda != SF::ONE);
+                assert!(lambda * lambda * lambda == SF::ONE);
+                assert!(<$config>::glv_endomorphism(&g) == naive_mul(&g, &lambda));
+            }
+
+            #[test]
+            fn test_glv_decompose() {
+                for _ in 0..100 {
+                    let k = SF::rand(&mut OsRng);
+                    let ((neg1, k1), (neg2, k2)) = <$config>::glv_decompose(&k.into_bigint().0);
+                    assert!(k1.len() <= 3 && k2.len() <= 3);
+
+                    let to_sf = |neg: bool, limbs: &[u64]| -> SF {
+                        let bytes: Vec<u8> = limbs.iter().flat_map(|l| l.to_le_bytes()).collect();
+                        let x = SF::from_le_bytes_mod_order(&bytes);
+                        if neg {
+                            -x
+                        } else {
+                            x
+                        }
+                    };
+
+                    assert!(to_sf(neg1, &k1) + <$config>::GLV_LAMBDA * to_sf(neg2, &k2) == k);
+                }
+            }
+
+            #[test]
+            fn test_glv_mul() {
+                let g: PT = <$config as SWCurveConfig>::GENERATOR.into();
+                let p = g * SF::rand(&mut OsRng);
+
+                for k in [SF::zero(), SF::ONE, -SF::ONE, SF::from(2u64)] {
+                    assert!(p * k == naive_mul(&p, &k));
+                }
+
+                for _ in 0..20 {
+                    let k = SF::rand(&mut OsRng);
+                    assert!(p * k == naive_mul(&p, &k));
+                    assert!(p.into_affine() * k == naive_mul(&p, &k));
+                }
+
+                assert!(PT::zero() * SF::rand(&mut OsRng) == PT::zero());
+            }
+
+            #[test]
+            fn test_glv_msm() {
+                for n in [1, 5, 40] {
+                    let bases: Vec<_> =
+                        (0..n).map(|_| PT::rand(&mut OsRng).into_affine()).collect();
+                    let scalars: Vec<_> = (0..n).map(|_| SF::rand(&mut OsRng)).collect();
+
+                    let expected = bases
+                        .iter()
+                        .zip(scalars.iter())
+                        .fold(PT::zero(), |acc, (b, s)| acc + naive_mul(&(*b).into(), s));
+                    assert!(<$config as SWCurveConfig>::msm(&bases, &scalars).unwrap() == expected);
+                }
+
+                let bases = vec![<$config as SWCurveConfig>::GENERATOR; 2];
+                assert!(<$config as SWCurveConfig>::msm(&bases, &[SF::ONE]).is_err());
+            }
+        }
+    };
+}
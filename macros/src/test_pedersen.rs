@@ -263,6 +263,38 @@ macro_rules! __test_pedersen {
             assert!(!proof.verify(&mut transcript_f, &c3.comm));
         }
 
+        #[cfg(feature = "transcript_dump")]
+        #[test]
+        fn test_pedersen_transcript_dump() {
+            // Test that the dumps of the prover and of the verifier agree, and that the diff
+            // points at the first input that they disagree on.
+            use pedersen::transcript_dump::{record, TranscriptOp};
+            let label = b"PedersenOpen";
+
+            let a = SF::rand(&mut OsRng);
+            let c1: PC = PC::new(a, &mut OsRng);
+            let (proof, prover) =
+                record(|| OP::create(&mut Transcript::new(label), &mut OsRng, &a, &c1));
+
+            let (ok, verifier) = record(|| proof.verify(&mut Transcript::new(label), &c1.comm));
+            assert!(ok);
+            assert!(!prover.ops.is_empty());
+            assert!(prover.diff(&verifier).is_none());
+
+            let c2: PC = PC::new(a + SF::ONE, &mut OsRng);
+            let (ok, verifier) = record(|| proof.verify(&mut Transcript::new(label), &c2.comm));
+            assert!(!ok);
+            let diff = prover.diff(&verifier).unwrap();
+            assert_eq!(diff.left.as_ref().unwrap().label(), b"C1");
+            assert!(matches!(diff.right, Some(TranscriptOp::Append { .. })));
+            assert!(diff.to_string().contains("append C1"));
+
+            // Operations outside of a recording are not recorded.
+            let _ = proof.verify(&mut Transcript::new(label), &c1.comm);
+            let (_, empty) = record(|| ());
+            assert!(empty.ops.is_empty());
+        }
+
         #[test]
         fn test_pedersen_multi_comm() {
             // Test that creating multi commitments goes through.
@@ -336,6 +368,251 @@ macro_rules! __test_pedersen {
             assert!(proof.verify(&mut transcript_v, &c1.comm, &pk, vals.len(), &gens));
         }
 
+        #[test]
+        fn test_pedersen_multi_comm_issuance_layout() {
+            // Test that the issuance proof works with layouts other than the boomerang one.
+            let label = b"PedersenIssuanceMultiLayout";
+
+            // A 3-attribute layout with the secret key first and no zero attribute.
+            let layout = AttributeLayout::new()
+                .with_attribute("sk", AttributeKind::SecretKey)
+                .with_attribute("value", AttributeKind::Hidden)
+                .with_attribute("rand", AttributeKind::Hidden);
+            assert_eq!(layout.index_of("value"), Some(1));
+
+            let lambda = SF::rand(&mut OsRng);
+            let pk = PC::get_main_generator().mul(lambda).into_affine();
+            let vals = vec![lambda, SF::rand(&mut OsRng), SF::rand(&mut OsRng)];
+            let (c1, gens) = PC::new_multi(&vals, &mut OsRng);
+
+            let mut transcript = Transcript::new(label);
+            let proof =
+                IPM::create_with_layout(&mut transcript, &mut OsRng, &vals, &c1, &layout, &gens);
+            let mut transcript_v = Transcript::new(label);
+            assert!(proof.verify_with_layout(&mut transcript_v, &c1.comm, &pk, &layout, &gens));
+
+            // The boomerang layout puts the secret key elsewhere, so it must not verify.
+            let mut transcript_v = Transcript::new(label);
+            assert!(!proof.verify(&mut transcript_v, &c1.comm, &pk, vals.len(), &gens));
+
+            // An 8-attribute layout with two zero attributes and no secret key.
+            let layout = (0..8).fold(AttributeLayout::new(), |layout, i| {
+                let kind = if i % 3 == 1 {
+                    AttributeKind::Zero
+                } else {
+                    AttributeKind::Hidden
+                };
+                layout.with_attribute(&format!("attr{}", i), kind)
+            });
+            assert_eq!(layout.secret_key_index(), None);
+
+            let vals: Vec<SF> = (0..8)
+                .map(|i| {
+                    if i % 3 == 1 {
+                        SF::zero()
+                    } else {
+                        SF::rand(&mut OsRng)
+                    }
+                })
+                .collect();
+            let (c1, gens) = PC::new_multi(&vals, &mut OsRng);
+
+            let mut transcript = Transcript::new(label);
+            let proof =
+                IPM::create_with_layout(&mut transcript, &mut OsRng, &vals, &c1, &layout, &gens);
+            let mut transcript_v = Transcript::new(label);
+            assert!(proof.verify_with_layout(&mut transcript_v, &c1.comm, &pk, &layout, &gens));
+
+            // A commitment with a non-zero value in a zero slot must not verify.
+            let mut bad_vals = vals.clone();
+            bad_vals[4] = SF::from(1u64);
+            let (c2, gens2) = PC::new_multi(&bad_vals, &mut OsRng);
+            let mut transcript = Transcript::new(label);
+            let proof = IPM::create_with_layout(
+                &mut transcript,
+                &mut OsRng,
+                &bad_vals,
+                &c2,
+                &layout,
+                &gens2,
+            );
+            let mut transcript_v = Transcript::new(label);
+            assert!(!proof.verify_with_layout(&mut transcript_v, &c2.comm, &pk, &layout, &gens2));
+        }
+
+        #[test]
+        fn test_pedersen_or() {
+            // Test that an OR proof goes through with the witness of either statement.
+            let label = b"PedersenOr";
+            type ORP = OrProof<$config, OP<$config>, EP<$config>>;
+
+            let a = SF::rand(&mut OsRng);
+            let c1: PC = PC::new(a, &mut OsRng);
+            let c2: PC = PC::new(a, &mut OsRng);
+            let c3: PC = PC::new(a + SF::ONE, &mut OsRng);
+
+            // The opening of c1 is known, and c1 and c3 hold different values.
+            let mut transcript = Transcript::new(label);
+            let proof = ORP::create(
+                &mut transcript,
+                &mut OsRng,
+                &c1.comm,
+                &(c1.comm, c3.comm),
+                &OrWitness::Left((a, c1.r)),
+            );
+            let mut transcript_v = Transcript::new(label);
+            assert!(proof.verify(&mut transcript_v, &c1.comm, &(c1.comm, c3.comm)));
+
+            // The opening of c3 is unknown, but c1 and c2 hold the same value.
+            let mut transcript = Transcript::new(label);
+            let proof = ORP::create(
+                &mut transcript,
+                &mut OsRng,
+                &c3.comm,
+                &(c1.comm, c2.comm),
+                &OrWitness::Right((c1.r, c2.r)),
+            );
+            let mut transcript_v = Transcript::new(label);
+            assert!(proof.verify(&mut transcript_v, &c3.comm, &(c1.comm, c2.comm)));
+
+            // And now check that it fails on other statements.
+            let mut transcript_f = Transcript::new(label);
+            assert!(!proof.verify(&mut transcript_f, &c3.comm, &(c1.comm, c3.comm)));
+        }
+
+        #[test]
+        fn test_pedersen_or_neither() {
+            // Test that an OR proof made without the witness of either statement fails.
+            let label = b"PedersenOr";
+            type ORP = OrProof<$config, OP<$config>, EP<$config>>;
+
+            let a = SF::rand(&mut OsRng);
+            let c1: PC = PC::new(a, &mut OsRng);
+            let c2: PC = PC::new(a + SF::ONE, &mut OsRng);
+
+            // The witness is a wrong opening of c1, and c1 and c2 hold different values.
+            let mut transcript = Transcript::new(label);
+            let proof = ORP::create(
+                &mut transcript,
+                &mut OsRng,
+                &c1.comm,
+                &(c1.comm, c2.comm),
+                &OrWitness::Left((a + SF::ONE, c1.r)),
+            );
+            let mut transcript_v = Transcript::new(label);
+            assert!(!proof.verify(&mut transcript_v, &c1.comm, &(c1.comm, c2.comm)));
+
+            // Nor can the challenges be moved from one proof to the other.
+            let mut transcript = Transcript::new(label);
+            let mut proof = ORP::create(
+                &mut transcript,
+                &mut OsRng,
+                &c1.comm,
+                &(c1.comm, c2.comm),
+                &OrWitness::Left((a, c1.r)),
+            );
+            proof.chal_a += SF::ONE;
+            let mut transcript_v = Transcript::new(label);
+            assert!(!proof.verify(&mut transcript_v, &c1.comm, &(c1.comm, c2.comm)));
+        }
+
+        #[test]
+        fn test_pedersen_and() {
+            // Test that an AND proof goes through, also when nested, or as a branch of an
+            // OR proof.
+            let label = b"PedersenAnd";
+            type ANDP = AndProof<$config, OP<$config>, EP<$config>>;
+
+            let a = SF::rand(&mut OsRng);
+            let c1: PC = PC::new(a, &mut OsRng);
+            let c2: PC = PC::new(a, &mut OsRng);
+            let c3: PC = PC::new(a + SF::ONE, &mut OsRng);
+
+            let mut transcript = Transcript::new(label);
+            let proof = ANDP::create(
+                &mut transcript,
+                &mut OsRng,
+                &c3.comm,
+                &(c1.comm, c2.comm),
+                &(a + SF::ONE, c3.r),
+                &(c1.r, c2.r),
+            );
+            let mut transcript_v = Transcript::new(label);
+            assert!(proof.verify(&mut transcript_v, &c3.comm, &(c1.comm, c2.comm)));
+
+            // And now check that it fails if either statement does not hold.
+            let mut transcript_f = Transcript::new(label);
+            assert!(!proof.verify(&mut transcript_f, &c1.comm, &(c1.comm, c2.comm)));
+            let mut transcript_f = Transcript::new(label);
+            assert!(!proof.verify(&mut transcript_f, &c3.comm, &(c1.comm, c3.comm)));
+
+            // Three statements, by nesting.
+            type ANDP3 = AndProof<$config, OP<$config>, ANDP>;
+            let mut transcript = Transcript::new(label);
+            let proof = ANDP3::create(
+                &mut transcript,
+                &mut OsRng,
+                &c1.comm,
+                &(c3.comm, (c1.comm, c2.comm)),
+                &(a, c1.r),
+                &((a + SF::ONE, c3.r), (c1.r, c2.r)),
+            );
+            let mut transcript_v = Transcript::new(label);
+            assert!(proof.verify(&mut transcript_v, &c1.comm, &(c3.comm, (c1.comm, c2.comm))));
+
+            // An OR of the AND proof with an equality proof that does not hold.
+            type ORP = OrProof<$config, ANDP, EP<$config>>;
+            let mut transcript = Transcript::new(label);
+            let proof = ORP::create(
+                &mut transcript,
+                &mut OsRng,
+                &(c3.comm, (c1.comm, c2.comm)),
+                &(c1.comm, c3.comm),
+                &OrWitness::Left(((a + SF::ONE, c3.r), (c1.r, c2.r))),
+            );
+            let mut transcript_v = Transcript::new(label);
+            assert!(proof.verify(
+                &mut transcript_v,
+                &(c3.comm, (c1.comm, c2.comm)),
+                &(c1.comm, c3.comm)
+            ));
+        }
+
+        #[test]
+        fn test_pedersen_opening_stuck_rng() {
+            // Test that the blinding values do not only depend on the caller's RNG.
+            struct StuckRng;
+            impl rand_core::RngCore for StuckRng {
+                fn next_u32(&mut self) -> u32 {
+                    7
+                }
+                fn next_u64(&mut self) -> u64 {
+                    7
+                }
+                fn fill_bytes(&mut self, dest: &mut [u8]) {
+                    dest.fill(7);
+                }
+                fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+                    dest.fill(7);
+                    Ok(())
+                }
+            }
+            impl rand_core::CryptoRng for StuckRng {}
+
+            let label = b"PedersenOpen";
+            let a = SF::rand(&mut OsRng);
+            let b = SF::rand(&mut OsRng);
+            let c1: PC = PC::new(a, &mut OsRng);
+            let c2: PC = PC::new(b, &mut OsRng);
+
+            let proof_a = OP::create(&mut Transcript::new(label), &mut StuckRng, &a, &c1);
+            let proof_b = OP::create(&mut Transcript::new(label), &mut StuckRng, &b, &c2);
+            assert!(proof_a.alpha != proof_b.alpha);
+
+            assert!(proof_a.verify(&mut Transcript::new(label), &c1.comm));
+            assert!(proof_b.verify(&mut Transcript::new(label), &c2.comm));
+        }
+
         #[test]
         fn test_pedersen_opening_other_challenge() {
             // Test that the proof fails if the wrong challenge is used.
@@ -462,6 +739,43 @@ macro_rules! __test_pedersen {
             assert!(!proof.verify(&mut transcript_f, &c1.comm, &c2.comm, &c4.comm));
         }
 
+        #[test]
+        fn test_pedersen_chained_product() {
+            // Test that the chained product proof goes through.
+            let label = b"PedersenChainedProduct";
+
+            let values: Vec<SF> = (0..4).map(|_| SF::rand(&mut OsRng)).collect();
+            let product: SF = values.iter().product();
+
+            let comms: Vec<PC> = values.iter().map(|v| PC::new(*v, &mut OsRng)).collect();
+            let cprod: PC = PC::new(product, &mut OsRng);
+
+            let mut transcript = Transcript::new(label);
+            let proof = CPP::create(&mut transcript, &mut OsRng, &values, &comms, &cprod);
+            assert_eq!(proof.intermediates.len(), 2);
+            assert_eq!(proof.proofs.len(), 3);
+
+            // Now check that the proof verifies.
+            let points: Vec<_> = comms.iter().map(|c| c.comm).collect();
+            let mut transcript_v = Transcript::new(label);
+            assert!(proof.verify(&mut transcript_v, &points, &cprod.comm));
+
+            // And now check it would fail on a different product, or on the
+            // values in another order.
+            let cf: PC = PC::new(product + SF::ONE, &mut OsRng);
+            let mut transcript_f = Transcript::new(label);
+            assert!(!proof.verify(&mut transcript_f, &points, &cf.comm));
+
+            let mut swapped = points.clone();
+            swapped.swap(0, 3);
+            let mut transcript_s = Transcript::new(label);
+            assert!(!proof.verify(&mut transcript_s, &swapped, &cprod.comm));
+
+            // And that it fails if a commitment is missing.
+            let mut transcript_m = Transcript::new(label);
+            assert!(!proof.verify(&mut transcript_m, &points[..3], &cprod.comm));
+        }
+
         #[test]
         fn test_pedersen_mul_other_challenge() {
             // Check that the mul proof fails if the wrong challenge is used.
@@ -576,6 +890,420 @@ macro_rules! __test_pedersen {
             assert!(!proof.verify_proof(&c1.comm, &c2.comm, &c3.comm, &cf[..]));
         }
 
+        #[test]
+        fn test_pedersen_point_commitment() {
+            // Test that a committed point on the other curve can be shown to be on the curve.
+            // This only holds if the scalar field matches the base field of the other curve.
+            type OBF = <<$config as PedersenConfig>::OCurve as CurveConfig>::BaseField;
+            if SF::MODULUS.to_bytes_le() != OBF::MODULUS.to_bytes_le() {
+                return;
+            }
+
+            let label = b"PedersenPointCommitment";
+            let point = (OGENERATOR.mul(OSF::rand(&mut OsRng))).into_affine();
+            let comm: PTC<$config> = PTC::new(&point, &mut OsRng);
+            let (c_x, c_y) = comm.comms();
+            assert_eq!(
+                c_x,
+                PC::new_with_both(
+                    <$config as PedersenConfig>::from_ob_to_sf(point.x),
+                    comm.x.r
+                )
+                .comm
+            );
+
+            let mut transcript = Transcript::new(label);
+            let proof = OCP::create(&mut transcript, &mut OsRng, &point, &comm);
+
+            let mut transcript_v = Transcript::new(label);
+            assert!(proof.verify(&mut transcript_v, &c_x, &c_y));
+
+            // It should fail for a different point.
+            let other: PTC<$config> = PTC::new(&OGENERATOR, &mut OsRng);
+            let mut transcript_f = Transcript::new(label);
+            assert!(!proof.verify(&mut transcript_f, &c_x, &other.y.comm));
+
+            // And a proof for a pair of coordinates that is not on the curve should not verify.
+            let mut off_curve = point;
+            off_curve.y += OBF::from(1u64);
+            let comm_f: PTC<$config> = PTC::new(&off_curve, &mut OsRng);
+            let mut transcript = Transcript::new(label);
+            let proof_f = OCP::create(&mut transcript, &mut OsRng, &off_curve, &comm_f);
+            let mut transcript_f = Transcript::new(label);
+            assert!(!proof_f.verify(&mut transcript_f, &comm_f.x.comm, &comm_f.y.comm));
+        }
+
+        #[test]
+        fn test_pedersen_add_mul_multi() {
+            // Test that the n-term add-mul proof goes through.
+            let label = b"PedersenAddMulMulti";
+
+            for n in [1, 2, 5] {
+                let x: Vec<SF> = (0..n).map(|_| SF::rand(&mut OsRng)).collect();
+                let y: Vec<SF> = (0..n).map(|_| SF::rand(&mut OsRng)).collect();
+                let z = x
+                    .iter()
+                    .zip(y.iter())
+                    .fold(SF::zero(), |acc, (a, b)| acc + *a * b);
+
+                let cx: Vec<PC> = x.iter().map(|a| PC::new(*a, &mut OsRng)).collect();
+                let cy: Vec<PC> = y.iter().map(|b| PC::new(*b, &mut OsRng)).collect();
+                let cz: PC = PC::new(z, &mut OsRng);
+
+                let mut transcript = Transcript::new(label);
+                let proof = AMPM::create(&mut transcript, &mut OsRng, &x, &y, &cx, &cy, &cz);
+                assert!(proof.tz.is_on_curve());
+                assert_eq!(proof.tx.len(), n);
+
+                let cx_comms: Vec<AT> = cx.iter().map(|c| c.comm).collect();
+                let cy_comms: Vec<AT> = cy.iter().map(|c| c.comm).collect();
+
+                // Now check that the proof verifies.
+                let mut transcript_v = Transcript::new(label);
+                assert!(proof.verify(&mut transcript_v, &cx_comms, &cy_comms, &cz.comm));
+
+                // And now check it would fail on a commitment to a different value.
+                let cf: PC = PC::new(z + SF::ONE, &mut OsRng);
+                let mut transcript_f = Transcript::new(label);
+                assert!(!proof.verify(&mut transcript_f, &cx_comms, &cy_comms, &cf.comm));
+
+                // And on a truncated set of commitments.
+                let mut transcript_t = Transcript::new(label);
+                assert!(!proof.verify(&mut transcript_t, &cx_comms[1..], &cy_comms[1..], &cz.comm));
+            }
+        }
+
+        #[test]
+        fn test_pedersen_msm_backend() {
+            // Test that the multi-commitment verifiers run on the backend that they are given.
+            struct CountingMsm(core::cell::Cell<usize>);
+
+            impl MsmBackend<AT> for CountingMsm {
+                fn msm(
+                    &self,
+                    bases: &[AT],
+                    scalars: &[SF],
+                ) -> Result<sw::Projective<$config>, usize> {
+                    self.0.set(self.0.get() + 1);
+                    DefaultMsm.msm(bases, scalars)
+                }
+            }
+
+            let backend = CountingMsm(core::cell::Cell::new(0));
+            // The second value is the zero attribute of an issuance commitment, and the third its
+            // secret key.
+            let mut vals: Vec<SF> = (0..4).map(|_| SF::rand(&mut OsRng)).collect();
+            vals[1] = SF::zero();
+            let (c1, gens) = PC::new_multi(&vals, &mut OsRng);
+
+            let label = b"PedersenOpenMulti";
+            let proof = OPM::create(&mut Transcript::new(label), &mut OsRng, &vals, &c1, &gens);
+            assert!(proof.verify_with_backend(
+                &mut Transcript::new(label),
+                &c1.comm,
+                vals.len(),
+                &gens,
+                &backend
+            ));
+            assert_eq!(backend.0.get(), 1);
+            let wrong = (c1.comm + PC::get_main_generator()).into_affine();
+            assert!(!proof.verify_with_backend(
+                &mut Transcript::new(label),
+                &wrong,
+                vals.len(),
+                &gens,
+                &backend
+            ));
+            assert_eq!(backend.0.get(), 2);
+
+            let label = b"PedersenIssuanceMulti";
+            let layout = AttributeLayout::boomerang(vals.len());
+            let pk = PC::get_main_generator().mul(vals[2]).into_affine();
+            let proof = IPM::create(&mut Transcript::new(label), &mut OsRng, &vals, &c1, &gens);
+            assert!(proof.verify_with_layout_and_backend(
+                &mut Transcript::new(label),
+                &c1.comm,
+                &pk,
+                &layout,
+                &gens,
+                &backend
+            ));
+            assert_eq!(backend.0.get(), 3);
+
+            let label = b"PedersenAddMulMulti";
+            let y: Vec<SF> = (0..vals.len()).map(|_| SF::rand(&mut OsRng)).collect();
+            let z = vals
+                .iter()
+                .zip(y.iter())
+                .fold(SF::zero(), |acc, (a, b)| acc + *a * b);
+            let cx: Vec<PC> = vals.iter().map(|a| PC::new(*a, &mut OsRng)).collect();
+            let cy: Vec<PC> = y.iter().map(|b| PC::new(*b, &mut OsRng)).collect();
+            let cz: PC = PC::new(z, &mut OsRng);
+            let proof = AMPM::create(
+                &mut Transcript::new(label),
+                &mut OsRng,
+                &vals,
+                &y,
+                &cx,
+                &cy,
+                &cz,
+            );
+            let cx_comms: Vec<AT> = cx.iter().map(|c| c.comm).collect();
+            let cy_comms: Vec<AT> = cy.iter().map(|c| c.comm).collect();
+            assert!(proof.verify_with_backend(
+                &mut Transcript::new(label),
+                &cx_comms,
+                &cy_comms,
+                &cz.comm,
+                &backend
+            ));
+            assert_eq!(backend.0.get(), 4);
+        }
+
+        #[test]
+        fn test_pedersen_verifier_context() {
+            // Test that proofs verify in the same way with and without a shared verifier context.
+            let a = SF::rand(&mut OsRng);
+            let b = SF::rand(&mut OsRng);
+            let z = a * b;
+            let vals = vec![a, b, z];
+
+            let c1: PC = PC::new(a, &mut OsRng);
+            let c2: PC = PC::new(b, &mut OsRng);
+            let c3: PC = PC::new(z, &mut OsRng);
+            let c4: PC = PC::new(a, &mut OsRng);
+            let (cm, gens) = PC::new_multi(&vals, &mut OsRng);
+
+            let ctx = VerifierContext::<$config>::new_with_generators(&gens);
+            assert_eq!(ctx.num_generators(), gens.generators.len());
+            assert_eq!(ctx.commit(&a, &c1.r).into_affine(), c1.comm);
+            assert_eq!(ctx.commit_multi(&vals, &cm.r).into_affine(), cm.comm);
+
+            let mut transcript = Transcript::new(b"PedersenCtxOpen");
+            let op = OP::create(&mut transcript, &mut OsRng, &a, &c1);
+            let mut transcript = Transcript::new(b"PedersenCtxOpenMulti");
+            let opm = OPM::create(&mut transcript, &mut OsRng, &vals, &cm, &gens);
+            let mut transcript = Transcript::new(b"PedersenCtxEq");
+            let ep = EP::create(&mut transcript, &mut OsRng, &c1, &c4);
+            let mut transcript = Transcript::new(b"PedersenCtxMul");
+            let mp = MP::create(&mut transcript, &mut OsRng, &a, &b, &c1, &c2, &c3);
+
+            let mut transcript_v = Transcript::new(b"PedersenCtxOpen");
+            assert!(op.verify_with_context(&mut transcript_v, &c1.comm, &ctx));
+            let mut transcript_v = Transcript::new(b"PedersenCtxOpenMulti");
+            assert!(opm.verify_with_context(&mut transcript_v, &cm.comm, vals.len(), &ctx));
+            let mut transcript_v = Transcript::new(b"PedersenCtxEq");
+            assert!(ep.verify_with_context(&mut transcript_v, &c1.comm, &c4.comm, &ctx));
+            let mut transcript_v = Transcript::new(b"PedersenCtxMul");
+            assert!(mp.verify_with_context(&mut transcript_v, &c1.comm, &c2.comm, &c3.comm, &ctx));
+
+            // The results must match the context-free verifier on every kind of challenge.
+            for chal in [
+                <$config as PedersenConfig>::CM1,
+                <$config as PedersenConfig>::CP1,
+                SF::rand(&mut OsRng),
+            ] {
+                assert_eq!(
+                    op.verify_with_challenge(&c1.comm, &chal),
+                    op.verify_with_challenge_and_context(&c1.comm, &chal, &ctx)
+                );
+                assert_eq!(
+                    opm.verify_with_challenge(&cm.comm, &chal, vals.len(), &gens),
+                    opm.verify_with_challenge_and_context(&cm.comm, &chal, vals.len(), &ctx)
+                );
+                assert_eq!(
+                    ep.verify_with_challenge(&c1.comm, &c4.comm, &chal),
+                    ep.verify_with_challenge_and_context(&c1.comm, &c4.comm, &chal, &ctx)
+                );
+                assert_eq!(
+                    mp.verify_with_challenge(&c1.comm, &c2.comm, &c3.comm, &chal),
+                    mp.verify_with_challenge_and_context(&c1.comm, &c2.comm, &c3.comm, &chal, &ctx)
+                );
+            }
+
+            // And check that the proofs fail against the wrong commitments.
+            let mut transcript_f = Transcript::new(b"PedersenCtxOpen");
+            assert!(!op.verify_with_context(&mut transcript_f, &c2.comm, &ctx));
+            let mut transcript_f = Transcript::new(b"PedersenCtxEq");
+            assert!(!ep.verify_with_context(&mut transcript_f, &c1.comm, &c2.comm, &ctx));
+            let mut transcript_f = Transcript::new(b"PedersenCtxMul");
+            assert!(!mp.verify_with_context(&mut transcript_f, &c1.comm, &c2.comm, &c4.comm, &ctx));
+
+            // A context without the multi-commitment generators cannot verify multi-openings.
+            let mut transcript_f = Transcript::new(b"PedersenCtxOpenMulti");
+            let ctx_single = VerifierContext::<$config>::new();
+            assert!(!opm.verify_with_context(&mut transcript_f, &cm.comm, vals.len(), &ctx_single));
+        }
+
+        #[test]
+        fn test_pedersen_ecdsa_parallel() {
+            // Test that an ECDSA proof built with the parallel prover verifies,
+            // and that the prover's transcript matches the one the verifier rebuilds.
+            let label = b"PedersenECDSAParallel";
+
+            // The point addition proofs only hold if the scalar field matches the base
+            // field of the other curve.
+            type OBF = <<$config as PedersenConfig>::OCurve as CurveConfig>::BaseField;
+            if SF::MODULUS.to_bytes_le() != OBF::MODULUS.to_bytes_le() {
+                return;
+            }
+
+            let x = OSF::rand(&mut OsRng);
+            let q = (OGENERATOR.mul(x)).into_affine();
+            let t = OSF::rand(&mut OsRng);
+
+            let k = OSF::rand(&mut OsRng);
+            let r = (OGENERATOR.mul(k)).into_affine();
+            let r_x = <$config as PedersenConfig>::from_ob_to_os(r.x);
+            let s = (t + r_x * x) / k;
+
+            let mut transcript = Transcript::new(label);
+            let proof = ECDSASigProof::<$config, ZKAttestCollective>::create_parallel(
+                &mut transcript,
+                &mut OsRng,
+                &t,
+                &r,
+                &r_x,
+                &s,
+                &q,
+            );
+
+            let mut transcript_v = Transcript::new(label);
+            assert!(proof.verify(&mut transcript_v, &r, &t));
+
+            let mut chal = [0u8; 64];
+            let mut chal_v = [0u8; 64];
+            transcript.challenge_bytes(b"check", &mut chal);
+            transcript_v.challenge_bytes(b"check", &mut chal_v);
+            assert_eq!(chal, chal_v);
+
+            // And now check it would fail on a different message.
+            let mut transcript_f = Transcript::new(label);
+            assert!(!proof.verify(&mut transcript_f, &r, &(t + OSF::ONE)));
+        }
+
+        #[test]
+        fn test_pedersen_scalar_mul_streaming() {
+            // Test that a scalar multiplication proof built with the streaming prover verifies,
+            // and that the size of the window does not change the proof.
+            use ark_std::rand::{rngs::StdRng, SeedableRng};
+            type SMP = FSSMP<$config, ZKSMP<$config>>;
+            let label = b"PedersenScalarMulStreaming";
+
+            // The point addition proofs only hold if the scalar field matches the base
+            // field of the other curve.
+            type OBF = <<$config as PedersenConfig>::OCurve as CurveConfig>::BaseField;
+            if SF::MODULUS.to_bytes_le() != OBF::MODULUS.to_bytes_le() {
+                return;
+            }
+
+            let lambda = OSF::rand(&mut OsRng);
+            let p = (OGENERATOR.mul(OSF::rand(&mut OsRng))).into_affine();
+            let s = (p.mul(lambda)).into_affine();
+
+            let (c1, r1) = <$config as PedersenConfig>::create_commit_other(&lambda, &mut OsRng);
+            let c2 = PC::new(<$config as PedersenConfig>::from_ob_to_sf(s.x), &mut OsRng);
+            let c3 = PC::new(<$config as PedersenConfig>::from_ob_to_sf(s.y), &mut OsRng);
+
+            let make = |window: usize| {
+                let mut transcript = Transcript::new(label);
+                let proof = SMP::create_streaming(
+                    &mut transcript,
+                    &mut StdRng::seed_from_u64(7),
+                    window,
+                    &s,
+                    &lambda,
+                    &p,
+                    &c1,
+                    &r1,
+                    &c2,
+                    &c3,
+                );
+                let mut chal = [0u8; 64];
+                transcript.challenge_bytes(b"check", &mut chal);
+                (proof, chal)
+            };
+
+            let (proof, chal) = make(1);
+            let mut transcript_v = Transcript::new(label);
+            assert!(proof.verify(&mut transcript_v, &p, &c1, &c2.comm, &c3.comm));
+
+            // The prover's transcript matches the one the verifier rebuilds.
+            let mut chal_v = [0u8; 64];
+            transcript_v.challenge_bytes(b"check", &mut chal_v);
+            assert_eq!(chal, chal_v);
+
+            // Any window produces the same proof from the same randomness.
+            let (proof_w, chal_w) = make(7);
+            assert_eq!(chal, chal_w);
+            let mut transcript_w = Transcript::new(label);
+            assert!(proof_w.verify(&mut transcript_w, &p, &c1, &c2.comm, &c3.comm));
+
+            // And now check it would fail on a different point.
+            let mut transcript_f = Transcript::new(label);
+            assert!(!proof.verify(&mut transcript_f, &s, &c1, &c2.comm, &c3.comm));
+        }
+
+        #[test]
+        fn test_pedersen_complete_point_add() {
+            // Test that the complete point addition proof goes through for both an addition and
+            // a doubling, and that the sums it cannot prove are rejected rather than panicking.
+            let label = b"PedersenCompletePointAdd";
+
+            // The point addition proofs only hold if the scalar field matches the base
+            // field of the other curve.
+            type OBF = <<$config as PedersenConfig>::OCurve as CurveConfig>::BaseField;
+            if SF::MODULUS.to_bytes_le() != OBF::MODULUS.to_bytes_le() {
+                return;
+            }
+
+            let prove = |a: OSA, b: OSA, t: OSA| {
+                let c: Vec<PC> = [a.x, a.y, b.x, b.y, t.x, t.y]
+                    .iter()
+                    .map(|x| PC::new(<$config as PedersenConfig>::from_ob_to_sf(*x), &mut OsRng))
+                    .collect();
+
+                let mut transcript = Transcript::new(label);
+                let proof = ZKCPAP::create_with_existing_commitments(
+                    &mut transcript,
+                    &mut OsRng,
+                    a,
+                    b,
+                    t,
+                    &c[0],
+                    &c[1],
+                    &c[2],
+                    &c[3],
+                    &c[4],
+                    &c[5],
+                );
+
+                let mut transcript_v = Transcript::new(label);
+                proof.verify(
+                    &mut transcript_v,
+                    &c[0].comm,
+                    &c[1].comm,
+                    &c[2].comm,
+                    &c[3].comm,
+                    &c[4].comm,
+                    &c[5].comm,
+                )
+            };
+
+            let a: OSA = (OGENERATOR.mul(OSF::rand(&mut OsRng))).into_affine();
+            let b: OSA = (OGENERATOR.mul(OSF::rand(&mut OsRng))).into_affine();
+
+            // An addition and a doubling both verify.
+            assert!(prove(a, b, (a + b).into_affine()));
+            assert!(prove(a, a, (a + a).into_affine()));
+
+            // A wrong sum is rejected.
+            assert!(!prove(a, b, (a + a).into_affine()));
+
+            // So is a sum that is the point at infinity, without a panic.
+            assert!(!prove(a, -a, OSA::identity()));
+        }
+
         #[test]
         fn test_pedersen_add_mul() {
             // Test that the add-mul proof goes through.
@@ -646,6 +1374,97 @@ macro_rules! __test_pedersen {
             ));
         }
 
+        #[test]
+        fn test_pedersen_consistency() {
+            // Test that the consistency proof goes through for two commitments to the same
+            // vector under different generators, and only for those.
+            let label = b"PedersenConsistency";
+
+            let vals: Vec<SF> = (0..3).map(|_| SF::rand(&mut OsRng)).collect();
+            let (c1, gens) = PC::new_multi(&vals, &mut OsRng);
+            let basis1 = CommitmentBasis::from_generators(&gens);
+
+            let basis2 = CommitmentBasis {
+                generators: (0..3u8)
+                    .map(|i| PC::affine_from_bytes_tai(&[b'g', i]))
+                    .collect(),
+                blinding: PC::affine_from_bytes_tai(b"h"),
+            };
+            let c2 = basis2.commit(&vals, &mut OsRng);
+
+            let mut transcript = Transcript::new(label);
+            let proof = CSP::create(
+                &mut transcript,
+                &mut OsRng,
+                &vals,
+                &c1,
+                &basis1,
+                &c2,
+                &basis2,
+            );
+
+            let mut transcript_v = Transcript::new(label);
+            assert!(proof.verify(&mut transcript_v, &c1.comm, &basis1, &c2.comm, &basis2));
+
+            // The proof does not hold for a commitment to another vector, nor for other
+            // generators.
+            let mut other_vals = vals.clone();
+            other_vals[1] += SF::ONE;
+            let c3 = basis2.commit(&other_vals, &mut OsRng);
+            let mut transcript_f = Transcript::new(label);
+            assert!(!proof.verify(&mut transcript_f, &c1.comm, &basis1, &c3.comm, &basis2));
+
+            let mut transcript_f = Transcript::new(label);
+            assert!(!proof.verify(&mut transcript_f, &c1.comm, &basis1, &c2.comm, &basis1));
+
+            // Nor is a proof for another vector accepted.
+            let mut transcript = Transcript::new(label);
+            let proof = CSP::create(
+                &mut transcript,
+                &mut OsRng,
+                &vals,
+                &c1,
+                &basis1,
+                &c3,
+                &basis2,
+            );
+            let mut transcript_f = Transcript::new(label);
+            assert!(!proof.verify(&mut transcript_f, &c1.comm, &basis1, &c3.comm, &basis2));
+        }
+
+        #[test]
+        fn test_pedersen_k_ary() {
+            // Test that the k-ary proof goes through for every value in {0, ..., k-1}, and
+            // only for those.
+            let label = b"PedersenKAry";
+
+            for k in [1usize, 2, 5] {
+                for v in 0..=k as u64 {
+                    let m = SF::from(v);
+                    let c: PC = PC::new(m, &mut OsRng);
+
+                    let mut transcript = Transcript::new(label);
+                    let proof = KAP::create(&mut transcript, &mut OsRng, k, &m, &c);
+                    assert_eq!(proof.cd.len(), k);
+
+                    let mut transcript_v = Transcript::new(label);
+                    assert_eq!(proof.verify(&mut transcript_v, k, &c.comm), v < k as u64);
+                }
+            }
+
+            // The proof is bound to `k` and to the commitment.
+            let m = SF::from(3u64);
+            let c: PC = PC::new(m, &mut OsRng);
+            let mut transcript = Transcript::new(label);
+            let proof = KAP::create(&mut transcript, &mut OsRng, 4, &m, &c);
+            assert!(proof.verify(&mut Transcript::new(label), 4, &c.comm));
+            assert!(!proof.verify(&mut Transcript::new(label), 5, &c.comm));
+            assert!(!proof.verify(&mut Transcript::new(label), 3, &c.comm));
+
+            let c2: PC = PC::new(m, &mut OsRng);
+            assert!(!proof.verify(&mut Transcript::new(label), 4, &c2.comm));
+        }
+
         #[test]
         fn test_pedersen_non_zero() {
             // Test that the non-zero proof goes through.
@@ -813,7 +1632,7 @@ macro_rules! test_pedersen {
                 short_weierstrass::{self as sw, SWCurveConfig},
                 AffineRepr, CurveGroup,
             };
-            use ark_ff::{Field, PrimeField};
+            use ark_ff::{BigInteger, Field, PrimeField};
             use ark_serialize::CanonicalSerialize;
             use ark_std::UniformRand;
             use ark_std::Zero;
@@ -821,19 +1640,31 @@ macro_rules! test_pedersen {
             use merlin::Transcript;
             use pedersen::{
                 add_mul_protocol::AddMulProof as AMP,
+                add_mul_protocol::AddMulProofMulti as AMPM,
+                and_protocol::AndProof,
+                consistency_protocol::{CommitmentBasis, ConsistencyProof as CSP},
                 ec_collective::CDLSCollective,
                 ec_point_add_protocol::{ECPointAddIntermediate as EPAI, ECPointAddProof as EPAP},
                 ecdsa_protocol::ECDSASigProof,
                 equality_protocol::EqualityProof as EP,
-                issuance_protocol::IssuanceProofMulti as IPM,
+                fs_scalar_mul_protocol::FSECScalarMulProof as FSSMP,
+                gk_k_ary_protocol::KAryProof as KAP,
+                issuance_protocol::{AttributeKind, AttributeLayout, IssuanceProofMulti as IPM},
+                msm::{DefaultMsm, MsmBackend},
                 mul_protocol::MulProof as MP,
                 non_zero_protocol::NonZeroProof as NZP,
                 opening_protocol::OpeningProof as OP,
                 opening_protocol::OpeningProofMulti as OPM,
+                or_protocol::{OrProof, OrWitness},
                 pedersen_config::PedersenComm,
                 pedersen_config::PedersenConfig,
                 point_add::PointAddProtocol,
-                product_protocol::ProductProof as PP,
+                point_commitment::{OnCurveProof as OCP, PointCommitment as PTC},
+                product_protocol::{ChainedProductProof as CPP, ProductProof as PP},
+                verifier_context::VerifierContext,
+                zk_attest_collective::ZKAttestCollective,
+                zk_attest_complete_point_add_protocol::ZKAttestCompletePointAddProof as ZKCPAP,
+                zk_attest_scalar_mul_protocol::ZKAttestECScalarMulProof as ZKSMP,
             };
             use rand_core::OsRng;
             use sha2::{Digest, Sha512};
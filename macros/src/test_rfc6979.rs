@@ -0,0 +1,91 @@
+#[macro_export]
+macro_rules! test_rfc6979 {
+    ($mod_name: ident; $config: ty, $ecdsa: ident) => {
+        mod $mod_name {
+            use super::*;
+            use ark_ec::{models::CurveConfig, short_weierstrass::SWCurveConfig, CurveGroup};
+            use ark_ff::{BigInteger, Field, PrimeField};
+            use ark_std::UniformRand;
+            use core::ops::Mul;
+            use merlin::Transcript;
+            use pedersen::{
+                ecdsa_protocol::ECDSASigProof, pedersen_config::PedersenConfig, rfc6979,
+                zk_attest_collective::ZKAttestCollective,
+            };
+            use rand_core::OsRng;
+            use sha2::{Digest, Sha256};
+            use $ecdsa::ecdsa::{signature::hazmat::PrehashSigner, Signature, SigningKey};
+
+            type SF = <$config as CurveConfig>::ScalarField;
+            type OSF = <<$config as PedersenConfig>::OCurve as CurveConfig>::ScalarField;
+            type OBF = <<$config as PedersenConfig>::OCurve as CurveConfig>::BaseField;
+            type ECDSA = ECDSASigProof<$config, ZKAttestCollective>;
+
+            #[test]
+            fn test_rfc6979_matches_external_signer() {
+                for i in 0..10u8 {
+                    let x = OSF::rand(&mut OsRng);
+                    let h = Sha256::digest([b"rfc6979 message ".as_slice(), &[i]].concat());
+
+                    let key = SigningKey::from_slice(&x.into_bigint().to_bytes_be()).unwrap();
+                    let sig: Signature = key.sign_prehash(&h).unwrap();
+                    let (sig_r, sig_s) = sig.split_bytes();
+
+                    let (r, r_x, s) = ECDSA::sign_rfc6979::<Sha256>(&x, &h);
+                    assert_eq!(r_x, OSF::from_be_bytes_mod_order(&sig_r));
+
+                    // Some signers normalise s to the lower half of the field.
+                    let sig_s = OSF::from_be_bytes_mod_order(&sig_s);
+                    assert!(s == sig_s || -s == sig_s);
+
+                    // The nonce should be tied to both the key and the message.
+                    assert!(ECDSA::check_rfc6979_nonce::<Sha256>(&x, &h, &r));
+                    let h_other = Sha256::digest(b"another message");
+                    assert!(!ECDSA::check_rfc6979_nonce::<Sha256>(&x, &h_other, &r));
+                    assert!(!ECDSA::check_rfc6979_nonce::<Sha256>(
+                        &(x + OSF::ONE),
+                        &h,
+                        &r
+                    ));
+                }
+            }
+
+            #[test]
+            fn test_rfc6979_signature_verifies() {
+                let x = OSF::rand(&mut OsRng);
+                let q = <<$config as PedersenConfig>::OCurve as SWCurveConfig>::GENERATOR
+                    .mul(x)
+                    .into_affine();
+                let h = Sha256::digest(b"rfc6979 message");
+
+                let (r, r_x, s) = ECDSA::sign_rfc6979::<Sha256>(&x, &h);
+                let t: OSF = rfc6979::bits2field(&h);
+                let g = <<$config as PedersenConfig>::OCurve as SWCurveConfig>::GENERATOR;
+                let u1 = t / s;
+                let u2 = r_x / s;
+                assert_eq!((g.mul(u1) + q.mul(u2)).into_affine(), r);
+            }
+
+            #[test]
+            fn test_rfc6979_proof() {
+                // The point addition proofs only hold if the scalar field matches the base
+                // field of the other curve.
+                if SF::MODULUS.to_bytes_le() != OBF::MODULUS.to_bytes_le() {
+                    return;
+                }
+
+                let label = b"PedersenECDSARFC6979";
+                let x = OSF::rand(&mut OsRng);
+                let h = Sha256::digest(b"rfc6979 message");
+
+                let mut transcript = Transcript::new(label);
+                let proof = ECDSA::create_rfc6979::<_, Sha256>(&mut transcript, &mut OsRng, &x, &h);
+                assert!(ECDSA::check_rfc6979_nonce::<Sha256>(&x, &h, &proof.r));
+
+                let t: OSF = rfc6979::bits2field(&h);
+                let mut transcript_v = Transcript::new(label);
+                assert!(proof.verify(&mut transcript_v, &proof.r, &t));
+            }
+        }
+    };
+}
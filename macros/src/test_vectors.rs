@@ -0,0 +1,96 @@
+#[macro_export]
+macro_rules! test_vectors {
+    ($mod_name: ident; $boomerangconfig: ty, $curve_name: tt, $path: tt, $acl_path: tt) => {
+        mod $mod_name {
+            use super::*;
+            use ::boomerang::testvectors::{AclTestVector, TestVector};
+
+            const SEED: u64 = 0x426f6f6d6572616e;
+
+            #[test]
+            fn test_vectors_are_deterministic() {
+                // Running the protocol twice with the same seed must produce the same transcript.
+                let a = TestVector::generate::<$boomerangconfig>($curve_name, SEED);
+                let b = TestVector::generate::<$boomerangconfig>($curve_name, SEED);
+                assert!(a == b);
+
+                let c = TestVector::generate::<$boomerangconfig>($curve_name, SEED + 1);
+                assert!(a != c);
+            }
+
+            #[test]
+            fn test_vectors_json_round_trip() {
+                let a = TestVector::generate::<$boomerangconfig>($curve_name, SEED);
+                let b = TestVector::from_json(&a.to_json()).expect("Failed to parse JSON");
+                assert!(a == b);
+            }
+
+            #[test]
+            fn test_vectors_match_fixture() {
+                // Set BOOMERANG_REGENERATE_VECTORS=1 to rewrite the fixture after an intended
+                // change to the wire format.
+                let path = concat!(env!("CARGO_MANIFEST_DIR"), "/", $path);
+                if std::env::var("BOOMERANG_REGENERATE_VECTORS").is_ok() {
+                    let tv = TestVector::generate::<$boomerangconfig>($curve_name, SEED);
+                    std::fs::write(path, tv.to_json() + "\n").expect("Failed to write fixture");
+                }
+
+                let json = std::fs::read_to_string(path).expect("Failed to read fixture");
+                let tv = TestVector::from_json(&json).expect("Failed to parse fixture");
+                assert!(tv.curve == $curve_name);
+                assert!(tv.seed == SEED);
+                tv.check::<$boomerangconfig>()
+                    .expect("Test vector does not match");
+            }
+
+            #[test]
+            fn test_vectors_detect_tampering() {
+                let mut tv = TestVector::generate::<$boomerangconfig>($curve_name, SEED);
+                let last = tv.spending[4].pop().unwrap();
+                tv.spending[4].push(if last == '0' { '1' } else { '0' });
+                assert!(tv.check::<$boomerangconfig>().is_err());
+            }
+
+            #[test]
+            fn test_acl_vectors_match_fixture() {
+                // Set BOOMERANG_REGENERATE_VECTORS=1 to rewrite the fixture after an intended
+                // change to the signature scheme.
+                let path = concat!(env!("CARGO_MANIFEST_DIR"), "/", $acl_path);
+                if std::env::var("BOOMERANG_REGENERATE_VECTORS").is_ok() {
+                    let tv = AclTestVector::generate::<$boomerangconfig>($curve_name, SEED);
+                    std::fs::write(path, tv.to_json() + "\n").expect("Failed to write fixture");
+                }
+
+                let json = std::fs::read_to_string(path).expect("Failed to read fixture");
+                let tv = AclTestVector::from_json(&json).expect("Failed to parse fixture");
+                assert!(tv.curve == $curve_name);
+                assert!(tv.seed == SEED);
+                tv.check::<$boomerangconfig>()
+                    .expect("ACL test vector does not match");
+            }
+
+            #[test]
+            fn test_acl_vectors_detect_tampering() {
+                let tv = AclTestVector::generate::<$boomerangconfig>($curve_name, SEED);
+                assert!(tv.check::<$boomerangconfig>().is_ok());
+
+                let flip = |s: &mut String| {
+                    let last = s.pop().unwrap();
+                    s.push(if last == '0' { '1' } else { '0' });
+                };
+
+                let mut bad = tv.clone();
+                flip(&mut bad.signature);
+                assert!(bad.check::<$boomerangconfig>().is_err());
+
+                let mut bad = tv.clone();
+                flip(&mut bad.message);
+                assert!(bad.check::<$boomerangconfig>().is_err());
+
+                let mut bad = tv.clone();
+                flip(&mut bad.challenge);
+                assert!(bad.check::<$boomerangconfig>().is_err());
+            }
+        }
+    };
+}
@@ -0,0 +1,152 @@
+#[macro_export]
+macro_rules! test_zkp_ecdsa {
+    ($mod_name: ident; $config: ty, $curve_name: tt, $path: tt) => {
+        mod $mod_name {
+            use super::*;
+            use ark_ec::{models::CurveConfig, short_weierstrass::SWCurveConfig, CurveGroup};
+            use ark_ff::{BigInteger, Field, PrimeField};
+            use ark_std::UniformRand;
+            use core::ops::Mul;
+            use merlin::Transcript;
+            use pedersen::{
+                ecdsa_protocol::ECDSASigProof,
+                pedersen_config::PedersenConfig,
+                zk_attest_collective::ZKAttestCollective,
+                zkp_ecdsa::{
+                    point_from_hex, point_to_hex, scalar_from_hex, scalar_to_hex, ZkpEcdsaJson,
+                    ZkpEcdsaVector,
+                },
+            };
+            use rand_core::OsRng;
+
+            type SF = <$config as CurveConfig>::ScalarField;
+            type OSF = <<$config as PedersenConfig>::OCurve as CurveConfig>::ScalarField;
+            type ECDSA = ECDSASigProof<$config, ZKAttestCollective>;
+
+            const SEED: u64 = 0x7a6b702d6563;
+
+            #[test]
+            fn test_zkp_ecdsa_vectors_match_fixture() {
+                // Set BOOMERANG_REGENERATE_VECTORS=1 to rewrite the fixture after an intended
+                // change to the representation.
+                let path = concat!(env!("CARGO_MANIFEST_DIR"), "/", $path);
+                if std::env::var("BOOMERANG_REGENERATE_VECTORS").is_ok() {
+                    let tv = ZkpEcdsaVector::generate::<$config>($curve_name, SEED);
+                    std::fs::write(path, tv.to_json() + "\n").expect("Failed to write fixture");
+                }
+
+                let json = std::fs::read_to_string(path).expect("Failed to read fixture");
+                let tv = ZkpEcdsaVector::from_json(&json).expect("Failed to parse fixture");
+                assert!(tv.curve == $curve_name);
+                assert!(tv.seed == SEED);
+                tv.check::<$config>()
+                    .expect("zkp-ecdsa vector does not match");
+            }
+
+            #[test]
+            fn test_zkp_ecdsa_vectors_detect_tampering() {
+                let tv = ZkpEcdsaVector::generate::<$config>($curve_name, SEED);
+                assert!(tv.check::<$config>().is_ok());
+
+                // A proof does not verify against another challenge.
+                let mut bad = tv.clone();
+                bad.point_add[0]["challenge"] = 1.into();
+                assert!(bad.check::<$config>().is_err());
+
+                let mut bad = tv.clone();
+                bad.scalar_mul[0]["challenge"] = 3.into();
+                assert!(bad.check::<$config>().is_err());
+
+                // Nor with a changed response.
+                let mut bad = tv.clone();
+                let z =
+                    scalar_from_hex::<SF>(bad.point_add[1]["proof"]["e1"]["z"].as_str().unwrap())
+                        .unwrap();
+                bad.point_add[1]["proof"]["e1"]["z"] = scalar_to_hex(&(z + SF::ONE)).into();
+                assert!(bad.check::<$config>().is_err());
+
+                // And a missing member is rejected.
+                let mut bad = tv.clone();
+                bad.scalar_mul[1]["proof"]
+                    .as_object_mut()
+                    .unwrap()
+                    .remove("z1");
+                assert!(bad.check::<$config>().is_err());
+            }
+
+            #[test]
+            fn test_zkp_ecdsa_encoding() {
+                // Points are compressed SEC1 points, and scalars are big-endian.
+                let g = <$config as SWCurveConfig>::GENERATOR;
+                let hex = point_to_hex(&g);
+                assert_eq!(hex.len(), 2 + 2 * SF::MODULUS_BIT_SIZE.div_ceil(8) as usize);
+                assert!(hex.starts_with("02") || hex.starts_with("03"));
+                assert_eq!(point_from_hex::<$config>(&hex), Ok(g));
+                let neg = -g;
+                assert_eq!(point_from_hex::<$config>(&point_to_hex(&neg)), Ok(neg));
+                assert_ne!(point_to_hex(&neg)[..2], hex[..2]);
+                let zero = sw::Affine::<$config>::identity();
+                assert_eq!(point_to_hex(&zero), "00");
+                assert_eq!(point_from_hex::<$config>("00"), Ok(zero));
+
+                assert!(point_from_hex::<$config>(&format!("04{}", &hex[2..])).is_err());
+                assert!(point_from_hex::<$config>(&hex[..hex.len() - 2]).is_err());
+                assert!(point_from_hex::<$config>("zz").is_err());
+
+                let x = SF::rand(&mut OsRng);
+                assert_eq!(scalar_from_hex::<SF>(&scalar_to_hex(&x)), Ok(x));
+                let one = scalar_to_hex(&SF::ONE);
+                assert!(
+                    one.ends_with("01") && one.trim_end_matches("01").chars().all(|c| c == '0')
+                );
+
+                // The modulus itself is not a reduced scalar.
+                let modulus = SF::MODULUS.to_bytes_be();
+                let modulus: String = modulus.iter().map(|b| format!("{:02x}", b)).collect();
+                let modulus = &modulus[modulus.len() - one.len()..];
+                assert!(scalar_from_hex::<SF>(modulus).is_err());
+            }
+
+            #[test]
+            fn test_zkp_ecdsa_signature_proof() {
+                // The point addition proofs only hold if the scalar field matches the base
+                // field of the other curve.
+                type OBF = <<$config as PedersenConfig>::OCurve as CurveConfig>::BaseField;
+                if SF::MODULUS.to_bytes_le() != OBF::MODULUS.to_bytes_le() {
+                    return;
+                }
+
+                let label = b"PedersenZkpEcdsa";
+                let g = <<$config as PedersenConfig>::OCurve as SWCurveConfig>::GENERATOR;
+                let x = OSF::rand(&mut OsRng);
+                let q = g.mul(x).into_affine();
+                let t = OSF::rand(&mut OsRng);
+                let k = OSF::rand(&mut OsRng);
+                let r = g.mul(k).into_affine();
+                let r_x = <$config as PedersenConfig>::from_ob_to_os(r.x);
+                let s = (t + r_x * x) / k;
+
+                let proof = ECDSA::create(
+                    &mut Transcript::new(label),
+                    &mut OsRng,
+                    &t,
+                    &r,
+                    &r_x,
+                    &s,
+                    &q,
+                );
+                let json = proof.to_zkp_ecdsa_json();
+                let decoded = ECDSA::from_zkp_ecdsa_json(&json).expect("Failed to decode proof");
+                assert_eq!(decoded.to_zkp_ecdsa_json(), json);
+                assert!(decoded.verify(&mut Transcript::new(label), &r, &t));
+                assert!(!decoded.verify(&mut Transcript::new(label), &r, &(t + OSF::ONE)));
+
+                // Every repetition of the scalar multiplication proof must be present.
+                let mut value = proof.to_zkp_ecdsa();
+                value["scalar_mul"].as_array_mut().unwrap().pop();
+                assert!(ECDSA::from_zkp_ecdsa(&value).is_err());
+                assert!(ECDSA::from_zkp_ecdsa_json("[]").is_err());
+            }
+        }
+    };
+}
@@ -2,7 +2,10 @@
 //! That is, let p be a prime and let x, y be two values in F_p.
 //! This protocol proves that C_3 is a Pedersen commitment to z = x * y (over F_p)
 //! The exact protocol we use here is the one given in https://eprint.iacr.org/2017/1132.pdf, Appendix A ("proving a product relationship").
+//! This module also provides `AddMulProofMulti`, which proves that C_z is a commitment to
+//! z = sum_i x_i * y_i for a small number of terms with a single combined proof.
 
+use ark_bulletproofs::msm::{self, DefaultMsm, MsmBackend};
 use ark_ec::{
     short_weierstrass::{self as sw},
     CurveConfig, CurveGroup,
@@ -10,11 +13,15 @@ use ark_ec::{
 use merlin::Transcript;
 
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::{ops::Mul, UniformRand};
+use ark_std::{ops::Mul, UniformRand, Zero};
 use rand::{CryptoRng, RngCore};
 
 use crate::{
-    pedersen_config::PedersenComm, pedersen_config::PedersenConfig, transcript::AddMulTranscript,
+    pedersen_config::PedersenComm,
+    pedersen_config::PedersenConfig,
+    transcript::{
+        witness_rng, AddMulMultiTranscript, AddMulTranscript, ADD_MUL_LABELS, ADD_MUL_MULTI_LABELS,
+    },
 };
 
 /// AddMulProofTranscriptable. This trait provides a notion of `Transcriptable`, which implies
@@ -194,40 +201,40 @@ impl<P: PedersenConfig> AddMulProof<P> {
         // This function just builds the transcript out of the various input values.
         // N.B Because of how we define the serialisation API to handle different numbers,
         // we use a temporary buffer here.
-        transcript.domain_sep();
+        AddMulTranscript::domain_sep(transcript);
         let mut compressed_bytes = Vec::new();
         c1.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_point(b"C1", &compressed_bytes[..]);
+        AddMulTranscript::append_point(transcript, b"C1", &compressed_bytes[..]);
 
         c2.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_point(b"C2", &compressed_bytes[..]);
+        AddMulTranscript::append_point(transcript, b"C2", &compressed_bytes[..]);
 
         c3.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_point(b"C3", &compressed_bytes[..]);
+        AddMulTranscript::append_point(transcript, b"C3", &compressed_bytes[..]);
 
         c4.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_point(b"C4", &compressed_bytes[..]);
+        AddMulTranscript::append_point(transcript, b"C4", &compressed_bytes[..]);
 
         c5.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_point(b"C5", &compressed_bytes[..]);
+        AddMulTranscript::append_point(transcript, b"C5", &compressed_bytes[..]);
 
         t1.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_point(b"t1", &compressed_bytes[..]);
+        AddMulTranscript::append_point(transcript, b"t1", &compressed_bytes[..]);
 
         t2.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_point(b"t2", &compressed_bytes[..]);
+        AddMulTranscript::append_point(transcript, b"t2", &compressed_bytes[..]);
 
         t3.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_point(b"t3", &compressed_bytes[..]);
+        AddMulTranscript::append_point(transcript, b"t3", &compressed_bytes[..]);
 
         t4.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_point(b"t4", &compressed_bytes[..]);
+        AddMulTranscript::append_point(transcript, b"t4", &compressed_bytes[..]);
 
         t5.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_point(b"t5", &compressed_bytes[..]);
+        AddMulTranscript::append_point(transcript, b"t5", &compressed_bytes[..]);
 
         t6.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_point(b"t6", &compressed_bytes[..]);
+        AddMulTranscript::append_point(transcript, b"t6", &compressed_bytes[..]);
     }
 
     /// create. This function returns a new multiplication proof of the fact that c5 is a commitment
@@ -266,7 +273,7 @@ impl<P: PedersenConfig> AddMulProof<P> {
             c3,
             c4,
             c5,
-            &transcript.challenge_scalar(b"c")[..],
+            &AddMulTranscript::challenge_scalar(transcript, ADD_MUL_LABELS.challenge)[..],
         )
     }
 
@@ -289,6 +296,9 @@ impl<P: PedersenConfig> AddMulProof<P> {
         c4: &PedersenComm<P>,
         c5: &PedersenComm<P>,
     ) -> AddMulProofIntermediate<P> {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(transcript, &[c1.r, c2.r, c3.r, c4.r, c5.r], rng);
+
         // Generate the random values.
         let b1 = <P as CurveConfig>::ScalarField::rand(rng);
         let b2 = <P as CurveConfig>::ScalarField::rand(rng);
@@ -446,7 +456,14 @@ impl<P: PedersenConfig> AddMulProof<P> {
             transcript, c1, c2, c3, c4, c5, &self.t1, &self.t2, &self.t3, &self.t4, &self.t5,
             &self.t6,
         );
-        self.verify_proof(c1, c2, c3, c4, c5, &transcript.challenge_scalar(b"c")[..])
+        self.verify_proof(
+            c1,
+            c2,
+            c3,
+            c4,
+            c5,
+            &AddMulTranscript::challenge_scalar(transcript, ADD_MUL_LABELS.challenge)[..],
+        )
     }
 
     /// verify_proof. This function simply verifies that the proof held by `self` is a valid
@@ -587,3 +604,389 @@ impl<P: PedersenConfig> AddMulProofIntermediateTranscript<P> {
             + self.t6.compressed_size()
     }
 }
+
+/// AddMulProofMulti. This struct acts as a container for an n-term AddMulProof.
+/// It proves that `cz` is a commitment to `z = sum_i x_i * y_i`, given commitments
+/// `cx_i` to each `x_i` and `cy_i` to each `y_i`, using a single combined proof object.
+/// Essentially, a new proof object can be created by calling `create`, whereas
+/// an existing proof can be verified by calling `verify`.
+/// We use the notation that X_i = x_i g + r_xi h, Y_i = y_i g + r_yi h and Z = zg + r_z h.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AddMulProofMulti<P: PedersenConfig> {
+    /// tx: the random points used to prove knowledge of the openings of X_i.
+    pub tx: Vec<sw::Affine<P>>,
+    /// ty: the random points used to prove knowledge of the openings of Y_i.
+    pub ty: Vec<sw::Affine<P>>,
+    /// tz: the random point used to prove that Z = sum_i y_i X_i + (r_z - sum_i y_i r_xi) h.
+    pub tz: sw::Affine<P>,
+
+    /// zx: the responses for each x_i. These are the same as bx_i + c * x_i.
+    pub zx: Vec<<P as CurveConfig>::ScalarField>,
+    /// zrx: the responses for each r_xi. These are the same as brx_i + c * r_xi.
+    pub zrx: Vec<<P as CurveConfig>::ScalarField>,
+    /// zy: the responses for each y_i. These are the same as by_i + c * y_i.
+    pub zy: Vec<<P as CurveConfig>::ScalarField>,
+    /// zry: the responses for each r_yi. These are the same as bry_i + c * r_yi.
+    pub zry: Vec<<P as CurveConfig>::ScalarField>,
+    /// zz: the response for Z. This is the same as bz + c * (r_z - sum_i y_i r_xi).
+    pub zz: <P as CurveConfig>::ScalarField,
+}
+
+/// AddMulProofMultiIntermediate. This struct provides a convenient wrapper
+/// for building all of the random values _before_ the challenge is generated.
+/// This struct should only be used if the transcript needs to modified in some way
+/// before the proof is generated.
+#[derive(Clone)]
+pub struct AddMulProofMultiIntermediate<P: PedersenConfig> {
+    /// tx: the random points used to prove knowledge of the openings of X_i.
+    pub tx: Vec<sw::Affine<P>>,
+    /// ty: the random points used to prove knowledge of the openings of Y_i.
+    pub ty: Vec<sw::Affine<P>>,
+    /// tz: the random point used to prove the sum of products.
+    pub tz: sw::Affine<P>,
+
+    /// bx: random private values made during setup.
+    pub bx: Vec<<P as CurveConfig>::ScalarField>,
+    /// brx: random private values made during setup.
+    pub brx: Vec<<P as CurveConfig>::ScalarField>,
+    /// by: random private values made during setup.
+    pub by: Vec<<P as CurveConfig>::ScalarField>,
+    /// bry: random private values made during setup.
+    pub bry: Vec<<P as CurveConfig>::ScalarField>,
+    /// bz: a random private value made during setup.
+    pub bz: <P as CurveConfig>::ScalarField,
+}
+
+impl<P: PedersenConfig> AddMulProofMulti<P> {
+    /// make_transcript. This function simply adds the commitments `cx`, `cy`, `cz` and the
+    /// random points `tx`, `ty` and `tz` to the `transcript` object.
+    /// # Arguments
+    /// * `transcript` - the transcript which is modified.
+    /// * `cx` - the commitments to each `x_i`.
+    /// * `cy` - the commitments to each `y_i`.
+    /// * `cz` - the commitment to `z`.
+    /// * `tx` - the random points for each `x_i`.
+    /// * `ty` - the random points for each `y_i`.
+    /// * `tz` - the random point for `z`.
+    pub fn make_transcript(
+        transcript: &mut Transcript,
+        cx: &[sw::Affine<P>],
+        cy: &[sw::Affine<P>],
+        cz: &sw::Affine<P>,
+        tx: &[sw::Affine<P>],
+        ty: &[sw::Affine<P>],
+        tz: &sw::Affine<P>,
+    ) {
+        AddMulMultiTranscript::domain_sep(transcript);
+        AddMulMultiTranscript::append_point(transcript, b"n", &(cx.len() as u64).to_le_bytes());
+
+        let mut compressed_bytes = Vec::new();
+        let mut append = |label: &'static [u8], point: &sw::Affine<P>| {
+            compressed_bytes.clear();
+            point.serialize_compressed(&mut compressed_bytes).unwrap();
+            AddMulMultiTranscript::append_point(transcript, label, &compressed_bytes[..]);
+        };
+
+        cx.iter().for_each(|c| append(b"CX", c));
+        cy.iter().for_each(|c| append(b"CY", c));
+        append(b"CZ", cz);
+        tx.iter().for_each(|t| append(b"tx", t));
+        ty.iter().for_each(|t| append(b"ty", t));
+        append(b"tz", tz);
+    }
+
+    /// create. This function returns a new proof of the fact that `cz` is a commitment
+    /// to `sum_i x_i * y_i`.
+    /// # Arguments
+    /// * `transcript` - the transcript object that is modified.
+    /// * `rng` - the RNG that is used to produce the random values. Must be cryptographically secure.
+    /// * `x` - the left-hand values.
+    /// * `y` - the right-hand values.
+    /// * `cx` - the commitments to each `x_i`.
+    /// * `cy` - the commitments to each `y_i`.
+    /// * `cz` - the commitment to `z = sum_i x_i * y_i`.
+    pub fn create<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        x: &[<P as CurveConfig>::ScalarField],
+        y: &[<P as CurveConfig>::ScalarField],
+        cx: &[PedersenComm<P>],
+        cy: &[PedersenComm<P>],
+        cz: &PedersenComm<P>,
+    ) -> Self {
+        let inter = Self::create_intermediates(transcript, rng, cx, cy, cz);
+        let chal_buf =
+            AddMulMultiTranscript::challenge_scalar(transcript, ADD_MUL_MULTI_LABELS.challenge);
+        Self::create_proof(x, y, &inter, cx, cy, cz, &chal_buf[..])
+    }
+
+    /// create_intermediates. This function returns a new set of intermediates
+    /// for an n-term add-mul proof.
+    /// # Arguments
+    /// * `transcript` - the transcript object that is modified.
+    /// * `rng` - the RNG that is used to produce the random values. Must be cryptographically secure.
+    /// * `cx` - the commitments to each `x_i`.
+    /// * `cy` - the commitments to each `y_i`.
+    /// * `cz` - the commitment to `z = sum_i x_i * y_i`.
+    pub fn create_intermediates<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        cx: &[PedersenComm<P>],
+        cy: &[PedersenComm<P>],
+        cz: &PedersenComm<P>,
+    ) -> AddMulProofMultiIntermediate<P> {
+        assert_eq!(cx.len(), cy.len());
+        let n = cx.len();
+
+        let mut rand_vec = |n: usize| -> Vec<<P as CurveConfig>::ScalarField> {
+            (0..n)
+                .map(|_| <P as CurveConfig>::ScalarField::rand(rng))
+                .collect()
+        };
+
+        let bx = rand_vec(n);
+        let brx = rand_vec(n);
+        let by = rand_vec(n);
+        let bry = rand_vec(n);
+        let bz = rand_vec(1)[0];
+
+        let tx: Vec<sw::Affine<P>> = bx
+            .iter()
+            .zip(brx.iter())
+            .map(|(b, br)| (P::GENERATOR.mul(b) + P::GENERATOR2.mul(br)).into_affine())
+            .collect();
+        let ty: Vec<sw::Affine<P>> = by
+            .iter()
+            .zip(bry.iter())
+            .map(|(b, br)| (P::GENERATOR.mul(b) + P::GENERATOR2.mul(br)).into_affine())
+            .collect();
+
+        let cx_comms: Vec<sw::Affine<P>> = cx.iter().map(|c| c.comm).collect();
+        let cy_comms: Vec<sw::Affine<P>> = cy.iter().map(|c| c.comm).collect();
+        let tz = (msm::msm(&cx_comms, &by).unwrap() + P::GENERATOR2.mul(bz)).into_affine();
+
+        Self::make_transcript(transcript, &cx_comms, &cy_comms, &cz.comm, &tx, &ty, &tz);
+
+        AddMulProofMultiIntermediate {
+            tx,
+            ty,
+            tz,
+            bx,
+            brx,
+            by,
+            bry,
+            bz,
+        }
+    }
+
+    /// create_proof. This function returns a new n-term add-mul proof using the previously
+    /// collected intermediates. Note that this function builds the challenge from the bytes
+    /// supplied in `chal_buf`.
+    /// # Arguments
+    /// * `x` - the left-hand values.
+    /// * `y` - the right-hand values.
+    /// * `inter` - the intermediary values produced by a call to `create_intermediates`.
+    /// * `cx` - the commitments to each `x_i`.
+    /// * `cy` - the commitments to each `y_i`.
+    /// * `cz` - the commitment to `z = sum_i x_i * y_i`.
+    /// * `chal_buf` - the pre-determined challenge bytes.
+    pub fn create_proof(
+        x: &[<P as CurveConfig>::ScalarField],
+        y: &[<P as CurveConfig>::ScalarField],
+        inter: &AddMulProofMultiIntermediate<P>,
+        cx: &[PedersenComm<P>],
+        cy: &[PedersenComm<P>],
+        cz: &PedersenComm<P>,
+        chal_buf: &[u8],
+    ) -> Self {
+        let chal = <P as PedersenConfig>::make_challenge_from_buffer(chal_buf);
+        Self::create_proof_with_challenge(x, y, inter, cx, cy, cz, &chal)
+    }
+
+    /// create_proof_with_challenge. This function creates an n-term add-mul proof
+    /// using the pre-existing challenge `chal`. This function should only be used when the
+    /// challenge is fixed across multiple, separate proofs.
+    /// # Arguments
+    /// * `x` - the left-hand values.
+    /// * `y` - the right-hand values.
+    /// * `inter` - the intermediary values produced by a call to `create_intermediates`.
+    /// * `cx` - the commitments to each `x_i`.
+    /// * `cy` - the commitments to each `y_i`.
+    /// * `cz` - the commitment to `z = sum_i x_i * y_i`.
+    /// * `chal` - the challenge.
+    pub fn create_proof_with_challenge(
+        x: &[<P as CurveConfig>::ScalarField],
+        y: &[<P as CurveConfig>::ScalarField],
+        inter: &AddMulProofMultiIntermediate<P>,
+        cx: &[PedersenComm<P>],
+        cy: &[PedersenComm<P>],
+        cz: &PedersenComm<P>,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> Self {
+        let rx: Vec<_> = cx.iter().map(|c| c.r).collect();
+        let ry: Vec<_> = cy.iter().map(|c| c.r).collect();
+        let respond = |b: &[<P as CurveConfig>::ScalarField],
+                       v: &[<P as CurveConfig>::ScalarField]| {
+            b.iter()
+                .zip(v.iter())
+                .map(|(b, v)| *b + *chal * v)
+                .collect()
+        };
+
+        let zx = respond(&inter.bx, x);
+        let zrx = respond(&inter.brx, &rx);
+        let zy = respond(&inter.by, y);
+        let zry = respond(&inter.bry, &ry);
+
+        let r_sum = y
+            .iter()
+            .zip(cx.iter())
+            .fold(<P as CurveConfig>::ScalarField::zero(), |acc, (y, c)| {
+                acc + *y * c.r
+            });
+        let zz = inter.bz + *chal * (cz.r - r_sum);
+
+        Self {
+            tx: inter.tx.clone(),
+            ty: inter.ty.clone(),
+            tz: inter.tz,
+            zx,
+            zrx,
+            zy,
+            zry,
+            zz,
+        }
+    }
+
+    /// verify. This function returns true if the proof held by `self` shows that `cz` is a
+    /// commitment to `sum_i x_i * y_i`, and false otherwise.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `transcript` - the transcript object that's used.
+    /// * `cx` - the commitments to each `x_i`.
+    /// * `cy` - the commitments to each `y_i`.
+    /// * `cz` - the commitment to `z`.
+    pub fn verify(
+        &self,
+        transcript: &mut Transcript,
+        cx: &[sw::Affine<P>],
+        cy: &[sw::Affine<P>],
+        cz: &sw::Affine<P>,
+    ) -> bool {
+        Self::make_transcript(transcript, cx, cy, cz, &self.tx, &self.ty, &self.tz);
+        let chal_buf =
+            AddMulMultiTranscript::challenge_scalar(transcript, ADD_MUL_MULTI_LABELS.challenge);
+        self.verify_proof(cx, cy, cz, &chal_buf[..])
+    }
+
+    /// verify_proof. This function verifies the proof held by `self` using the
+    /// pre-existing challenge bytes supplied in `chal_buf`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `cx` - the commitments to each `x_i`.
+    /// * `cy` - the commitments to each `y_i`.
+    /// * `cz` - the commitment to `z`.
+    /// * `chal_buf` - the pre-determined challenge bytes.
+    pub fn verify_proof(
+        &self,
+        cx: &[sw::Affine<P>],
+        cy: &[sw::Affine<P>],
+        cz: &sw::Affine<P>,
+        chal_buf: &[u8],
+    ) -> bool {
+        let chal = <P as PedersenConfig>::make_challenge_from_buffer(chal_buf);
+        self.verify_with_challenge(cx, cy, cz, &chal)
+    }
+
+    /// verify_with_challenge. This function verifies the proof held by `self` using the
+    /// pre-existing challenge supplied in `chal`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `cx` - the commitments to each `x_i`.
+    /// * `cy` - the commitments to each `y_i`.
+    /// * `cz` - the commitment to `z`.
+    /// * `chal` - the challenge.
+    pub fn verify_with_challenge(
+        &self,
+        cx: &[sw::Affine<P>],
+        cy: &[sw::Affine<P>],
+        cz: &sw::Affine<P>,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> bool {
+        self.verify_with_challenge_and_backend(cx, cy, cz, chal, &DefaultMsm)
+    }
+
+    /// verify_with_backend. This function returns true if the proof held by `self` shows that
+    /// `cz` is a commitment to `sum_i x_i * y_i`, and false otherwise. This function behaves
+    /// like `verify`, but computes the sum over `cx` on `backend`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `transcript` - the transcript object that's used.
+    /// * `cx` - the commitments to each `x_i`.
+    /// * `cy` - the commitments to each `y_i`.
+    /// * `cz` - the commitment to `z`.
+    /// * `backend` - the multi-scalar multiplication backend.
+    pub fn verify_with_backend<M: MsmBackend<sw::Affine<P>>>(
+        &self,
+        transcript: &mut Transcript,
+        cx: &[sw::Affine<P>],
+        cy: &[sw::Affine<P>],
+        cz: &sw::Affine<P>,
+        backend: &M,
+    ) -> bool {
+        Self::make_transcript(transcript, cx, cy, cz, &self.tx, &self.ty, &self.tz);
+        let chal_buf =
+            AddMulMultiTranscript::challenge_scalar(transcript, ADD_MUL_MULTI_LABELS.challenge);
+        let chal = <P as PedersenConfig>::make_challenge_from_buffer(&chal_buf[..]);
+        self.verify_with_challenge_and_backend(cx, cy, cz, &chal, backend)
+    }
+
+    /// verify_with_challenge_and_backend. This function verifies the proof held by `self` using
+    /// the pre-existing challenge supplied in `chal`, computing the sum over `cx` on `backend`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `cx` - the commitments to each `x_i`.
+    /// * `cy` - the commitments to each `y_i`.
+    /// * `cz` - the commitment to `z`.
+    /// * `chal` - the challenge.
+    /// * `backend` - the multi-scalar multiplication backend.
+    pub fn verify_with_challenge_and_backend<M: MsmBackend<sw::Affine<P>>>(
+        &self,
+        cx: &[sw::Affine<P>],
+        cy: &[sw::Affine<P>],
+        cz: &sw::Affine<P>,
+        chal: &<P as CurveConfig>::ScalarField,
+        backend: &M,
+    ) -> bool {
+        let n = cx.len();
+        if cy.len() != n
+            || self.tx.len() != n
+            || self.ty.len() != n
+            || self.zx.len() != n
+            || self.zrx.len() != n
+            || self.zy.len() != n
+            || self.zry.len() != n
+        {
+            return false;
+        }
+
+        let openings = |c: &[sw::Affine<P>],
+                        t: &[sw::Affine<P>],
+                        z: &[<P as CurveConfig>::ScalarField],
+                        zr: &[<P as CurveConfig>::ScalarField]| {
+            (0..n).all(|i| {
+                t[i] + c[i].mul(*chal) == P::GENERATOR.mul(z[i]) + P::GENERATOR2.mul(zr[i])
+            })
+        };
+
+        openings(cx, &self.tx, &self.zx, &self.zrx)
+            && openings(cy, &self.ty, &self.zy, &self.zry)
+            && (self.tz + cz.mul(*chal)
+                == backend.msm(cx, &self.zy).unwrap() + P::GENERATOR2.mul(self.zz))
+    }
+
+    /// serialized_size. Returns the number of bytes needed to represent this proof object once serialised.
+    pub fn serialized_size(&self) -> usize {
+        self.compressed_size()
+    }
+}
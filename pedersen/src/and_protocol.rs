@@ -0,0 +1,153 @@
+//! Defines an AND composition of two sigma protocols.
+//! That is, this protocol proves in ZK that the prover knows a witness for both of two
+//! statements, using a single challenge for both sub-proofs.
+//!
+//! The commitments of both sub-proofs are added to one transcript, and the single challenge
+//! that is derived from it answers both of them. This is the pattern that e.g
+//! `zk_attest_point_add_protocol` follows by hand for its multiplication and equality proofs.
+//! As `AndProof` is itself a `SigmaProtocol`, k statements can be proved together by nesting,
+//! e.g `AndProof<P, A, AndProof<P, B, C>>`, and an `AndProof` can be a branch of an `OrProof`.
+
+use ark_ec::CurveConfig;
+use core::marker::PhantomData;
+use merlin::Transcript;
+
+use rand::{CryptoRng, RngCore};
+
+use crate::{
+    pedersen_config::PedersenConfig,
+    sigma_protocol::SigmaProtocol,
+    transcript::{AndTranscript, AND_LABELS},
+};
+
+/// AndProof. This struct acts as a container for a proof that two statements hold.
+/// New proof objects can be made via the `create` function, whereas existing
+/// proofs may be verified via the `verify` function.
+pub struct AndProof<P: PedersenConfig, A: SigmaProtocol<P>, B: SigmaProtocol<P>> {
+    /// a: the proof of the first statement.
+    pub a: A,
+    /// b: the proof of the second statement.
+    pub b: B,
+    /// _p: the curve of the sub-proofs, which neither of them needs to hold.
+    _p: PhantomData<P>,
+}
+
+impl<P: PedersenConfig, A: SigmaProtocol<P>, B: SigmaProtocol<P>> AndProof<P, A, B> {
+    /// challenge. This function returns the challenge of the AND proof from `transcript`.
+    /// # Arguments
+    /// * `transcript` - the transcript object.
+    fn challenge(transcript: &mut Transcript) -> <P as CurveConfig>::ScalarField {
+        <P as PedersenConfig>::make_challenge_from_buffer(
+            &transcript.challenge_scalar(AND_LABELS.challenge),
+        )
+    }
+
+    /// create. This function returns a new proof that both `sa` and `sb` hold.
+    /// # Arguments
+    /// * `transcript` - the transcript object that is modified.
+    /// * `rng` - the RNG that is used to produce the random values. Must be cryptographically secure.
+    /// * `sa` - the first statement.
+    /// * `sb` - the second statement.
+    /// * `wa` - the witness of `sa`.
+    /// * `wb` - the witness of `sb`.
+    pub fn create<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        sa: &A::Statement,
+        sb: &B::Statement,
+        wa: &A::Witness,
+        wb: &B::Witness,
+    ) -> Self {
+        transcript.domain_sep();
+        let inter_a = A::create_intermediates(transcript, rng, sa, wa);
+        let inter_b = B::create_intermediates(transcript, rng, sb, wb);
+
+        let chal = Self::challenge(transcript);
+        Self {
+            a: A::create_proof_with_challenge(&inter_a, sa, wa, &chal),
+            b: B::create_proof_with_challenge(&inter_b, sb, wb, &chal),
+            _p: PhantomData,
+        }
+    }
+
+    /// verify. This function returns true if the proof held by `self` shows that both `sa` and
+    /// `sb` hold, and false otherwise.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `transcript` - the transcript object that's used.
+    /// * `sa` - the first statement.
+    /// * `sb` - the second statement.
+    pub fn verify(
+        &self,
+        transcript: &mut Transcript,
+        sa: &A::Statement,
+        sb: &B::Statement,
+    ) -> bool {
+        transcript.domain_sep();
+        self.a.add_proof_to_transcript(transcript, sa);
+        self.b.add_proof_to_transcript(transcript, sb);
+
+        let chal = Self::challenge(transcript);
+        self.a.verify_with_challenge(sa, &chal) && self.b.verify_with_challenge(sb, &chal)
+    }
+}
+
+impl<P: PedersenConfig, A: SigmaProtocol<P>, B: SigmaProtocol<P>> SigmaProtocol<P>
+    for AndProof<P, A, B>
+{
+    type Statement = (A::Statement, B::Statement);
+    type Witness = (A::Witness, B::Witness);
+    type Intermediate = (A::Intermediate, B::Intermediate);
+
+    fn create_intermediates<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        statement: &Self::Statement,
+        witness: &Self::Witness,
+    ) -> Self::Intermediate {
+        transcript.domain_sep();
+        let inter_a = A::create_intermediates(transcript, rng, &statement.0, &witness.0);
+        let inter_b = B::create_intermediates(transcript, rng, &statement.1, &witness.1);
+        (inter_a, inter_b)
+    }
+
+    fn create_proof_with_challenge(
+        inter: &Self::Intermediate,
+        statement: &Self::Statement,
+        witness: &Self::Witness,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> Self {
+        Self {
+            a: A::create_proof_with_challenge(&inter.0, &statement.0, &witness.0, chal),
+            b: B::create_proof_with_challenge(&inter.1, &statement.1, &witness.1, chal),
+            _p: PhantomData,
+        }
+    }
+
+    fn simulate<T: RngCore + CryptoRng>(
+        rng: &mut T,
+        statement: &Self::Statement,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> Self {
+        Self {
+            a: A::simulate(rng, &statement.0, chal),
+            b: B::simulate(rng, &statement.1, chal),
+            _p: PhantomData,
+        }
+    }
+
+    fn add_proof_to_transcript(&self, transcript: &mut Transcript, statement: &Self::Statement) {
+        transcript.domain_sep();
+        self.a.add_proof_to_transcript(transcript, &statement.0);
+        self.b.add_proof_to_transcript(transcript, &statement.1);
+    }
+
+    fn verify_with_challenge(
+        &self,
+        statement: &Self::Statement,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> bool {
+        self.a.verify_with_challenge(&statement.0, chal)
+            && self.b.verify_with_challenge(&statement.1, chal)
+    }
+}
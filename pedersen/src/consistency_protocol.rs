@@ -0,0 +1,241 @@
+//! Defines a protocol for proving that two multi-commitments under different generator sets
+//! commit to the same vector, for various PedersenConfig types.
+//! That is, this protocol proves knowledge of x_1, ..., x_n, r_1 and r_2 such that
+//! C_1 = sum_i x_i g_i + r_1 h and C_2 = sum_i x_i g'_i + r_2 h', for two `CommitmentBasis`
+//! (g_1, ..., g_n, h) and (g'_1, ..., g'_n, h'). This glues e.g. a commitment under the
+//! generators of this crate to one under the bulletproofs `PedersenGens`, without revealing
+//! the vector.
+//!
+//! The proof is the usual Schnorr-style proof of equality of discrete logarithms, with one
+//! response per committed value, shared by both commitments, and one response per blinding
+//! value.
+
+use ark_bulletproofs::{msm, PedersenGens};
+use ark_ec::{
+    short_weierstrass::{self as sw},
+    CurveConfig, CurveGroup,
+};
+use merlin::Transcript;
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{ops::Mul, UniformRand};
+use rand::{CryptoRng, RngCore};
+
+use crate::{
+    pedersen_config::{Generators, PedersenComm, PedersenConfig},
+    transcript::{witness_rng, ConsistencyTranscript, CONSISTENCY_LABELS},
+};
+
+/// CommitmentBasis. This struct holds the generators of a multi-commitment: one generator per
+/// committed value, and the generator of the blinding value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitmentBasis<P: PedersenConfig> {
+    /// generators: the generators of the committed values.
+    pub generators: Vec<sw::Affine<P>>,
+    /// blinding: the generator of the blinding value.
+    pub blinding: sw::Affine<P>,
+}
+
+impl<P: PedersenConfig> CommitmentBasis<P> {
+    /// from_generators. This function returns the basis of the multi-commitments of this crate
+    /// with the generators `gens`, whose blinding generator is `P::GENERATOR2`.
+    /// # Arguments
+    /// * `gens` - the generators of the committed values.
+    pub fn from_generators(gens: &Generators<P>) -> Self {
+        Self {
+            generators: gens.generators.to_vec(),
+            blinding: P::GENERATOR2,
+        }
+    }
+
+    /// from_pedersen_gens. This function returns the basis of the single-value commitments of
+    /// the bulletproofs crate with the generators `gens`.
+    /// # Arguments
+    /// * `gens` - the bulletproofs Pedersen generators.
+    pub fn from_pedersen_gens(gens: &PedersenGens<sw::Affine<P>>) -> Self {
+        Self {
+            generators: vec![gens.B],
+            blinding: gens.B_blinding,
+        }
+    }
+
+    /// commit_with_blinding. This function returns the commitment to `vals` with the blinding
+    /// value `r` under this basis. This function panics if there are more values than
+    /// generators.
+    /// # Arguments
+    /// * `vals` - the values that are committed to.
+    /// * `r` - the blinding value.
+    pub fn commit_with_blinding(
+        &self,
+        vals: &[<P as CurveConfig>::ScalarField],
+        r: <P as CurveConfig>::ScalarField,
+    ) -> PedersenComm<P> {
+        assert!(vals.len() <= self.generators.len());
+        let mut bases = self.generators[..vals.len()].to_vec();
+        let mut scalars = vals.to_vec();
+        bases.push(self.blinding);
+        scalars.push(r);
+
+        PedersenComm {
+            comm: msm::msm(&bases, &scalars).unwrap().into_affine(),
+            r,
+        }
+    }
+
+    /// commit. This function returns a new commitment to `vals` under this basis.
+    /// # Arguments
+    /// * `vals` - the values that are committed to.
+    /// * `rng` - the random number generator used to produce the randomness.
+    ///   Must be cryptographically secure.
+    pub fn commit<T: RngCore + CryptoRng>(
+        &self,
+        vals: &[<P as CurveConfig>::ScalarField],
+        rng: &mut T,
+    ) -> PedersenComm<P> {
+        self.commit_with_blinding(vals, P::make_blind(rng))
+    }
+
+    /// add_to_transcript. This function adds the generators of this basis to `transcript`.
+    fn add_to_transcript(&self, transcript: &mut Transcript, label: &'static [u8]) {
+        let mut compressed_bytes = Vec::new();
+        self.generators
+            .serialize_compressed(&mut compressed_bytes)
+            .unwrap();
+        self.blinding
+            .serialize_compressed(&mut compressed_bytes)
+            .unwrap();
+        transcript.append_point(label, &compressed_bytes[..]);
+    }
+}
+
+/// ConsistencyProof. This struct acts as a container for a ConsistencyProof.
+/// Essentially, a new proof object can be created by calling `create`, whereas
+/// an existing proof can be verified by calling `verify`.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ConsistencyProof<P: PedersenConfig> {
+    /// t1: the commitment to the random values under the first basis.
+    pub t1: sw::Affine<P>,
+    /// t2: the commitment to the random values under the second basis.
+    pub t2: sw::Affine<P>,
+    /// z: the responses for the committed values (i.e z_i = x_i c + a_i).
+    pub z: Vec<<P as CurveConfig>::ScalarField>,
+    /// w1: the response for the first blinding value (i.e w1 = r_1 c + s_1).
+    pub w1: <P as CurveConfig>::ScalarField,
+    /// w2: the response for the second blinding value (i.e w2 = r_2 c + s_2).
+    pub w2: <P as CurveConfig>::ScalarField,
+}
+
+impl<P: PedersenConfig> ConsistencyProof<P> {
+    /// make_transcript. This function adds both bases, both commitments and the prover's
+    /// commitments `t1`, `t2` to the `transcript` object.
+    /// # Arguments
+    /// * `transcript` - the transcript object.
+    /// * `c1` - the commitment under the first basis.
+    /// * `basis1` - the first basis.
+    /// * `c2` - the commitment under the second basis.
+    /// * `basis2` - the second basis.
+    /// * `t1` - the prover's commitment under the first basis.
+    /// * `t2` - the prover's commitment under the second basis.
+    pub fn make_transcript(
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        basis1: &CommitmentBasis<P>,
+        c2: &sw::Affine<P>,
+        basis2: &CommitmentBasis<P>,
+        t1: &sw::Affine<P>,
+        t2: &sw::Affine<P>,
+    ) {
+        transcript.domain_sep();
+        basis1.add_to_transcript(transcript, b"basis1");
+        basis2.add_to_transcript(transcript, b"basis2");
+
+        for (label, point) in [
+            (b"c1" as &'static [u8], c1),
+            (b"c2", c2),
+            (b"t1", t1),
+            (b"t2", t2),
+        ] {
+            let mut compressed_bytes = Vec::new();
+            point.serialize_compressed(&mut compressed_bytes).unwrap();
+            transcript.append_point(label, &compressed_bytes[..]);
+        }
+    }
+
+    /// create. This function returns a new proof that `c1` and `c2` commit to `vals`, under
+    /// `basis1` and `basis2` respectively. This function panics if either basis has fewer
+    /// generators than there are values.
+    /// # Arguments
+    /// * `transcript` - the transcript object.
+    /// * `rng` - the random number generator to use. Must be cryptographically random.
+    /// * `vals` - the committed values.
+    /// * `c1` - the commitment to `vals` under `basis1`.
+    /// * `basis1` - the first basis.
+    /// * `c2` - the commitment to `vals` under `basis2`.
+    /// * `basis2` - the second basis.
+    pub fn create<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        vals: &[<P as CurveConfig>::ScalarField],
+        c1: &PedersenComm<P>,
+        basis1: &CommitmentBasis<P>,
+        c2: &PedersenComm<P>,
+        basis2: &CommitmentBasis<P>,
+    ) -> Self {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let witness: Vec<_> = vals.iter().copied().chain([c1.r, c2.r]).collect();
+        let rng = &mut witness_rng(transcript, &witness, rng);
+
+        let a: Vec<_> = vals
+            .iter()
+            .map(|_| <P as CurveConfig>::ScalarField::rand(rng))
+            .collect();
+        let s1 = <P as CurveConfig>::ScalarField::rand(rng);
+        let s2 = <P as CurveConfig>::ScalarField::rand(rng);
+
+        let t1 = basis1.commit_with_blinding(&a, s1).comm;
+        let t2 = basis2.commit_with_blinding(&a, s2).comm;
+
+        Self::make_transcript(transcript, &c1.comm, basis1, &c2.comm, basis2, &t1, &t2);
+        let c = <P as PedersenConfig>::make_challenge_from_buffer(
+            &transcript.challenge_scalar(CONSISTENCY_LABELS.challenge)[..],
+        );
+
+        Self {
+            t1,
+            t2,
+            z: vals.iter().zip(&a).map(|(x, a)| *x * c + a).collect(),
+            w1: c1.r * c + s1,
+            w2: c2.r * c + s2,
+        }
+    }
+
+    /// verify. This function returns true if the proof held by `self` shows that `c1` and `c2`
+    /// commit to the same vector, under `basis1` and `basis2` respectively.
+    /// # Arguments
+    /// * `transcript` - the transcript object.
+    /// * `c1` - the commitment under the first basis.
+    /// * `basis1` - the first basis.
+    /// * `c2` - the commitment under the second basis.
+    /// * `basis2` - the second basis.
+    pub fn verify(
+        &self,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        basis1: &CommitmentBasis<P>,
+        c2: &sw::Affine<P>,
+        basis2: &CommitmentBasis<P>,
+    ) -> bool {
+        if self.z.len() > basis1.generators.len() || self.z.len() > basis2.generators.len() {
+            return false;
+        }
+
+        Self::make_transcript(transcript, c1, basis1, c2, basis2, &self.t1, &self.t2);
+        let c = <P as PedersenConfig>::make_challenge_from_buffer(
+            &transcript.challenge_scalar(CONSISTENCY_LABELS.challenge)[..],
+        );
+
+        basis1.commit_with_blinding(&self.z, self.w1).comm == (self.t1 + c1.mul(c)).into_affine()
+            && basis2.commit_with_blinding(&self.z, self.w2).comm
+                == (self.t2 + c2.mul(c)).into_affine()
+    }
+}
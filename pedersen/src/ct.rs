@@ -0,0 +1,314 @@
+//! Defines constant-time conversions between prime fields in Montgomery form.
+//!
+//! The arkworks conversions between a field element and its integer value (`into_bigint`,
+//! `from_bigint`, and the `num_bigint::BigUint` round trip) end in comparisons with the modulus
+//! that exit early, and in reductions that are only done when they are needed. Their running
+//! time therefore depends on the value that is being converted, which is a problem when that
+//! value is a secret, e.g. the co-ordinate of a point that is attested to, or a blinding factor.
+//!
+//! The functions in this module do the same conversions with word-wise Montgomery arithmetic
+//! that only branches on public values (the modulus and the number of limbs): every carry is
+//! propagated through all limbs, and every reduction is done with a mask rather than a branch.
+//! The functions that `derive_conversion!` generates for `PedersenConfig` use this module.
+//!
+//! N.B The timing of these functions is checked with dudect-style statistical tests, which
+//! are slow and noisy, and so only run with the `dudect` feature (e.g.
+//! `cargo test -p pedersen --release --features dudect`).
+
+use ark_ff::{BigInt, Fp, MontBackend, MontConfig};
+use core::marker::PhantomData;
+use rand::{CryptoRng, RngCore};
+
+/// mac. Returns `a + b * c + carry`, as a (low, high) pair of words.
+#[inline(always)]
+fn mac(a: u64, b: u64, c: u64, carry: u64) -> (u64, u64) {
+    let t = (a as u128) + (b as u128) * (c as u128) + (carry as u128);
+    (t as u64, (t >> 64) as u64)
+}
+
+/// adc. Returns `a + b + carry`, as a (sum, carry) pair of words.
+#[inline(always)]
+fn adc(a: u64, b: u64, carry: u64) -> (u64, u64) {
+    let t = (a as u128) + (b as u128) + (carry as u128);
+    (t as u64, (t >> 64) as u64)
+}
+
+/// sbb. Returns `a - b - borrow`, as a (difference, borrow) pair of words, where the borrow
+/// is either 0 or 1.
+#[inline(always)]
+fn sbb(a: u64, b: u64, borrow: u64) -> (u64, u64) {
+    let t = (a as u128).wrapping_sub((b as u128) + (borrow as u128));
+    (t as u64, (t >> 127) as u64)
+}
+
+/// reduce_once. Returns `hi * 2^(64N) + t`, minus `m` if that is at least `m`. The input must
+/// be smaller than `2m`, and `hi` must be either 0 or 1.
+#[inline(always)]
+fn reduce_once<const N: usize>(t: &[u64; N], hi: u64, m: &[u64; N]) -> [u64; N] {
+    let mut d = [0u64; N];
+    let mut borrow = 0;
+    for j in 0..N {
+        (d[j], borrow) = sbb(t[j], m[j], borrow);
+    }
+
+    // The difference is the result if the input overflowed N limbs, or if the subtraction did
+    // not borrow.
+    // N.B The mask goes through `black_box` so that the compiler does not turn the selection
+    // back into a branch.
+    let mask = core::hint::black_box(0u64.wrapping_sub(hi | (borrow ^ 1)));
+    let mut out = [0u64; N];
+    for j in 0..N {
+        out[j] = (d[j] & mask) | (t[j] & !mask);
+    }
+    out
+}
+
+/// mont_mul. Returns `a * b * 2^(-64N) mod m`, fully reduced. `b` must be smaller than `m`,
+/// but `a` can be any N-limb integer.
+/// # Arguments
+/// * `a` - the first factor.
+/// * `b` - the second factor.
+/// * `m` - the modulus, which must be odd.
+/// * `inv` - `-m^(-1) mod 2^64`.
+fn mont_mul<const N: usize>(a: &[u64; N], b: &[u64; N], m: &[u64; N], inv: u64) -> [u64; N] {
+    // This is the CIOS method, with two extra words so that nothing is lost if `m` has no
+    // spare bit. On exit, the value is smaller than 2m.
+    let mut t = [0u64; N];
+    let mut hi = 0u64;
+    for b_i in b.iter() {
+        let mut carry = 0;
+        for j in 0..N {
+            (t[j], carry) = mac(t[j], a[j], *b_i, carry);
+        }
+        let (sum, top) = adc(hi, carry, 0);
+
+        let k = t[0].wrapping_mul(inv);
+        let (_, mut carry) = mac(t[0], k, m[0], 0);
+        for j in 1..N {
+            (t[j - 1], carry) = mac(t[j], k, m[j], carry);
+        }
+        let (sum, c) = adc(sum, carry, 0);
+        t[N - 1] = sum;
+        hi = top + c;
+    }
+
+    reduce_once(&t, hi, m)
+}
+
+/// into_bigint. Returns the integer value of `x`, in constant time.
+/// # Arguments
+/// * `x` - the field element.
+pub fn into_bigint<C: MontConfig<N>, const N: usize>(x: &Fp<MontBackend<C, N>, N>) -> BigInt<N> {
+    let mut one = [0u64; N];
+    one[0] = 1;
+    BigInt(mont_mul(&(x.0).0, &one, &C::MODULUS.0, C::INV))
+}
+
+/// from_bigint_mod_order. Returns `x` reduced modulo the order of the field, in constant time.
+/// Unlike `PrimeField::from_bigint`, this function never fails.
+/// # Arguments
+/// * `x` - the integer.
+pub fn from_bigint_mod_order<C: MontConfig<N>, const N: usize>(
+    x: &BigInt<N>,
+) -> Fp<MontBackend<C, N>, N> {
+    Fp(
+        BigInt(mont_mul(&x.0, &C::R2.0, &C::MODULUS.0, C::INV)),
+        PhantomData,
+    )
+}
+
+/// convert. Returns the integer value of `x`, reduced modulo the order of the target field, in
+/// constant time. Both fields must have the same number of limbs.
+/// # Arguments
+/// * `x` - the element of the source field.
+pub fn convert<A: MontConfig<N>, B: MontConfig<N>, const N: usize>(
+    x: Fp<MontBackend<A, N>, N>,
+) -> Fp<MontBackend<B, N>, N> {
+    from_bigint_mod_order::<B, N>(&into_bigint(&x))
+}
+
+/// rand. Returns a uniformly random field element, in constant time. This function draws twice
+/// as many bits as the modulus has, and reduces them, rather than rejecting the draws that are
+/// not smaller than the modulus: the result is within 2^(-64N) of uniform.
+/// # Arguments
+/// * `rng` - the source of randomness. Must be cryptographically secure.
+pub fn rand<C: MontConfig<N>, T: RngCore + CryptoRng, const N: usize>(
+    rng: &mut T,
+) -> Fp<MontBackend<C, N>, N> {
+    let (m, inv) = (&C::MODULUS.0, C::INV);
+    let mut lo = [0u64; N];
+    let mut hi = [0u64; N];
+    lo.iter_mut()
+        .chain(hi.iter_mut())
+        .for_each(|w| *w = rng.next_u64());
+
+    // The Montgomery form of lo + hi * 2^(64N) is lo * R + hi * R^2, where R = 2^(64N).
+    let lo = mont_mul(&lo, &C::R2.0, m, inv);
+    let hi = mont_mul(&mont_mul(&hi, &C::R2.0, m, inv), &C::R2.0, m, inv);
+
+    let mut sum = [0u64; N];
+    let mut carry = 0;
+    for j in 0..N {
+        (sum[j], carry) = adc(lo[j], hi[j], carry);
+    }
+    Fp(BigInt(reduce_once(&sum, carry, m)), PhantomData)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::{BigInteger, PrimeField};
+    use ark_secp256k1::{Fq, FqConfig, Fr, FrConfig};
+    use ark_std::{One, UniformRand, Zero};
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn into_bigint_matches_arkworks() {
+        for x in [Fq::zero(), Fq::one(), -Fq::one()] {
+            assert_eq!(into_bigint(&x), x.into_bigint());
+        }
+        for _ in 0..100 {
+            let x = Fq::rand(&mut OsRng);
+            assert_eq!(into_bigint(&x), x.into_bigint());
+        }
+    }
+
+    #[test]
+    fn convert_matches_arkworks() {
+        // The order of Fr is smaller than the order of Fq, so large elements of Fq wrap around.
+        for _ in 0..100 {
+            let x = Fq::rand(&mut OsRng);
+            let expected = Fr::from_le_bytes_mod_order(&x.into_bigint().to_bytes_le());
+            assert_eq!(convert::<FqConfig, FrConfig, 4>(x), expected);
+
+            let y = Fr::rand(&mut OsRng);
+            assert_eq!(
+                convert::<FrConfig, FqConfig, 4>(y),
+                Fq::from_bigint(y.into_bigint()).unwrap()
+            );
+        }
+        assert!(convert::<FqConfig, FrConfig, 4>(Fq::from_bigint(Fr::MODULUS).unwrap()).is_zero());
+        assert_eq!(convert::<FqConfig, FrConfig, 4>(-Fq::one()), {
+            let mut v = Fq::MODULUS;
+            v.sub_with_borrow(&Fr::MODULUS);
+            v.sub_with_borrow(&BigInt::from(1u64));
+            Fr::from_bigint(v).unwrap()
+        });
+    }
+
+    #[test]
+    fn from_bigint_mod_order_reduces() {
+        assert_eq!(
+            from_bigint_mod_order::<FqConfig, 4>(&BigInt::from(5u64)),
+            Fq::from(5u64)
+        );
+        assert!(from_bigint_mod_order::<FqConfig, 4>(&Fq::MODULUS).is_zero());
+        let max = BigInt([u64::MAX; 4]);
+        let expected = Fq::from_le_bytes_mod_order(&max.to_bytes_le());
+        assert_eq!(from_bigint_mod_order::<FqConfig, 4>(&max), expected);
+    }
+
+    #[test]
+    fn rand_is_reduced() {
+        for _ in 0..100 {
+            let x = rand::<FrConfig, _, 4>(&mut OsRng);
+            assert!(!x.is_geq_modulus());
+            assert_ne!(x, rand::<FrConfig, _, 4>(&mut OsRng));
+        }
+    }
+
+    /// These tests compare the running time on a fixed input with the running time on random
+    /// inputs, with Welch's t-test, as in "Dude, is my code constant time?" (Reparaz, Balasch
+    /// and Verbauwhede, 2017). A |t| above 4.5 is taken as evidence of a leak.
+    #[cfg(feature = "dudect")]
+    mod dudect {
+        use super::*;
+        use std::hint::black_box;
+        use std::time::Instant;
+
+        const MEASUREMENTS: usize = 200_000;
+        const REPS: usize = 16;
+        const THRESHOLD: f64 = 4.5;
+
+        /// t_statistic. Returns Welch's t statistic of the two classes of `samples`, after the
+        /// slowest 10% of each class are cropped.
+        fn t_statistic(samples: &mut [Vec<f64>; 2]) -> f64 {
+            let stats = samples.iter_mut().map(|class| {
+                class.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                class.truncate(class.len() * 9 / 10);
+                let n = class.len() as f64;
+                let mean = class.iter().sum::<f64>() / n;
+                let var = class.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (n - 1.0);
+                (mean, var, n)
+            });
+            let [(m0, v0, n0), (m1, v1, n1)]: [_; 2] =
+                stats.collect::<Vec<_>>().try_into().unwrap();
+            (m0 - m1) / (v0 / n0 + v1 / n1).sqrt()
+        }
+
+        /// measure. Times `f` on `fixed` and on random inputs, in a random order, and returns the
+        /// t statistic of the two classes. The inputs are all drawn before the first measurement,
+        /// so that drawing them does not disturb the timings.
+        fn measure<X: Copy>(fixed: X, random: impl Fn() -> X, f: impl Fn(X)) -> f64 {
+            let inputs: Vec<_> = (0..MEASUREMENTS)
+                .map(|_| match OsRng.next_u32() & 1 {
+                    0 => (0, fixed),
+                    _ => (1, random()),
+                })
+                .collect();
+
+            let mut samples = [Vec::new(), Vec::new()];
+            for (class, x) in inputs {
+                let start = Instant::now();
+                for _ in 0..REPS {
+                    f(black_box(x));
+                }
+                samples[class].push(start.elapsed().as_nanos() as f64);
+            }
+            t_statistic(&mut samples)
+        }
+
+        #[test]
+        fn convert_is_constant_time() {
+            let t = measure(
+                Fq::zero(),
+                || Fq::rand(&mut OsRng),
+                |x| {
+                    black_box(convert::<FqConfig, FrConfig, 4>(x));
+                },
+            );
+            assert!(t.abs() < THRESHOLD, "t = {t}");
+        }
+
+        #[test]
+        fn into_bigint_is_constant_time() {
+            let t = measure(
+                -Fq::one(),
+                || Fq::rand(&mut OsRng),
+                |x| {
+                    black_box(into_bigint(&x));
+                },
+            );
+            assert!(t.abs() < THRESHOLD, "t = {t}");
+        }
+
+        #[test]
+        fn from_bigint_mod_order_is_constant_time() {
+            let t = measure(
+                BigInt::from(1u64),
+                || {
+                    BigInt([
+                        OsRng.next_u64(),
+                        OsRng.next_u64(),
+                        OsRng.next_u64(),
+                        u64::MAX,
+                    ])
+                },
+                |x| {
+                    black_box(from_bigint_mod_order::<FrConfig, 4>(&x));
+                },
+            );
+            assert!(t.abs() < THRESHOLD, "t = {t}");
+        }
+    }
+}
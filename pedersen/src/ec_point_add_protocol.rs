@@ -28,7 +28,7 @@ use crate::{
     pedersen_config::PedersenComm,
     pedersen_config::PedersenConfig,
     point_add::PointAddProtocol,
-    transcript::ECPointAdditionTranscript,
+    transcript::{witness_rng, ECPointAdditionTranscript, EC_POINT_ADDITION_LABELS},
 };
 
 /// ECPointAddProofTranscriptable. This trait provides a notion of `Transcriptable` which implies that
@@ -121,7 +121,7 @@ impl<P: PedersenConfig> PointAddProtocol<P> for ECPointAddProof<P> {
     type IntermediateTranscript = ECPointAddIntermediateTranscript<P>;
 
     fn challenge_scalar(transcript: &mut Transcript) -> [u8; 64] {
-        ECPointAdditionTranscript::challenge_scalar(transcript, b"c")
+        ECPointAdditionTranscript::challenge_scalar(transcript, EC_POINT_ADDITION_LABELS.challenge)
     }
 
     /// make_intermediate_transcript. This function accepts a set of intermediate values (`inter`)
@@ -164,6 +164,13 @@ impl<P: PedersenConfig> PointAddProtocol<P> for ECPointAddProof<P> {
         c5: &PedersenComm<P>,
         c6: &PedersenComm<P>,
     ) -> ECPointAddIntermediate<P> {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(
+            transcript,
+            &(a, b, [c1.r, c2.r, c3.r, c4.r, c5.r, c6.r]),
+            rng,
+        );
+
         // This proof does not show work for point doubling.
         assert!(a != b);
         // c7 is the commitment to tau, the gradient.
@@ -5,11 +5,12 @@ use ark_ec::{
     short_weierstrass::{self as sw, SWCurveConfig},
     CurveConfig, CurveGroup,
 };
-use merlin::Transcript;
+use merlin::{Transcript, TranscriptRng};
 
 use ark_ff::Field;
 use ark_serialize::CanonicalSerialize;
 use ark_std::ops::Mul;
+use hmac::digest::{core_api::BlockSizeUser, Digest};
 use rand::{CryptoRng, RngCore};
 
 use crate::{
@@ -17,9 +18,28 @@ use crate::{
     fs_scalar_mul_protocol::{FSECScalarMulProof, FSECScalarMulProofIntermediate},
     pedersen_config::{PedersenComm, PedersenConfig},
     point_add::PointAddProtocol,
-    transcript::ECDSASignatureTranscript,
+    rfc6979,
+    transcript::{witness_rng, ECDSASignatureTranscript},
 };
 
+#[cfg(feature = "parallel")]
+use crate::scalar_mul::ScalarMulProtocol;
+
+/// ScalarMulIntermediateFn. This type is the signature shared by the functions that build
+/// the Fiat-Shamir scalar multiplication intermediates.
+type ScalarMulIntermediateFn<P, PT, T> =
+    fn(
+        &mut Transcript,
+        &mut T,
+        &sw::Affine<<P as PedersenConfig>::OCurve>,
+        &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        &sw::Affine<<P as PedersenConfig>::OCurve>,
+        &sw::Affine<<P as PedersenConfig>::OCurve>,
+        &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        &PedersenComm<P>,
+        &PedersenComm<P>,
+    ) -> FSECScalarMulProofIntermediate<P, <PT as Collective<P>>::ScalarMul>;
+
 pub struct ECDSASigProof<P: PedersenConfig, PT: Collective<P>> {
     /// r: the signature value (i.e R = u1g + u2q).
     /// This is entirely random.
@@ -169,6 +189,73 @@ impl<P: PedersenConfig, PT: Collective<P>> ECDSASigProof<P, PT> {
         r_x: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
         s: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
         q: &sw::Affine<<P as PedersenConfig>::OCurve>,
+    ) -> ECDSASigProofIntermediate<P, PT> {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(transcript, &([*t, *r_x, *s], *r), rng);
+
+        Self::create_intermediates_with(
+            transcript,
+            rng,
+            t,
+            r,
+            r_x,
+            s,
+            q,
+            FSECScalarMulProof::<P, PT::ScalarMul>::create_intermediate,
+        )
+    }
+
+    /// create_intermediates_parallel. This function creates the same intermediate values as
+    /// `create_intermediates`, but builds the repetitions of the scalar multiplication proof
+    /// concurrently. See `FSECScalarMulProof::create_intermediate_parallel` for details.
+    /// # Arguments
+    /// * `transcript` - the transcript object.
+    /// * `rng` - the cryptographically random number generator.
+    /// * `t` - the hash of the message.
+    /// * `r` - the R value from the ECDSA signature verification equation.
+    /// * `r_x` - the truncated `x` co-ordinate of `R`.
+    /// * `s` - the other part of the ECDSA signature.
+    /// * `q` - the public key.
+    #[cfg(feature = "parallel")]
+    pub fn create_intermediates_parallel<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        t: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        r: &sw::Affine<<P as PedersenConfig>::OCurve>,
+        r_x: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        s: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        q: &sw::Affine<<P as PedersenConfig>::OCurve>,
+    ) -> ECDSASigProofIntermediate<P, PT>
+    where
+        <PT::ScalarMul as ScalarMulProtocol<P>>::Intermediate: Send,
+    {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(transcript, &([*t, *r_x, *s], *r), rng);
+
+        Self::create_intermediates_with(
+            transcript,
+            rng,
+            t,
+            r,
+            r_x,
+            s,
+            q,
+            FSECScalarMulProof::<P, PT::ScalarMul>::create_intermediate_parallel,
+        )
+    }
+
+    /// create_intermediates_with. This function builds the intermediate values for ECDSA
+    /// signature verification, using `make_mpi` to build the scalar multiplication intermediates.
+    #[allow(clippy::too_many_arguments)]
+    fn create_intermediates_with(
+        transcript: &mut Transcript,
+        rng: &mut TranscriptRng,
+        t: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        r: &sw::Affine<<P as PedersenConfig>::OCurve>,
+        r_x: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        s: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        q: &sw::Affine<<P as PedersenConfig>::OCurve>,
+        make_mpi: ScalarMulIntermediateFn<P, PT, TranscriptRng>,
     ) -> ECDSASigProofIntermediate<P, PT> {
         // To begin we essentially have to compute the various portions of the ECDSA
         // signature verification.
@@ -201,9 +288,7 @@ impl<P: PedersenConfig, PT: Collective<P>> ECDSASigProof<P, PT> {
         let c_lhs_y = PedersenComm::new(<P as PedersenConfig>::from_ob_to_sf(lhs.y), rng);
 
         // Prove that zr = trm1g + q.
-        let mpi = FSECScalarMulProof::<P, PT::ScalarMul>::create_intermediate(
-            transcript, rng, &lhs, &z, r, &cz, &cr, &c_lhs_x, &c_lhs_y,
-        );
+        let mpi = make_mpi(transcript, rng, &lhs, &z, r, &cz, &cr, &c_lhs_x, &c_lhs_y);
 
         // Prove that lhs = trm1g + q from our already existing commitments.
         let addpi = PT::PointAdd::create_intermediates_with_existing_commitments(
@@ -254,6 +339,110 @@ impl<P: PedersenConfig, PT: Collective<P>> ECDSASigProof<P, PT> {
         Self::create_proof(transcript, r, &inter, q)
     }
 
+    /// create_parallel. This function creates a proof of ECDSA signature under a committed public key `q`,
+    /// building the repetitions of the scalar multiplication proof concurrently.
+    /// # Arguments
+    /// * `transcript` - the transcript object.
+    /// * `rng` - the cryptographically random number generator.
+    /// * `t` - the hash of the message.
+    /// * `r` - the R value from the ECDSA signature verification equation.
+    /// * `r_x` - the truncated `x` co-ordinate of `R`.
+    /// * `s` - the other part of the ECDSA signature.
+    /// * `q` - the public key.
+    #[cfg(feature = "parallel")]
+    pub fn create_parallel<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        t: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        r: &sw::Affine<<P as PedersenConfig>::OCurve>,
+        r_x: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        s: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        q: &sw::Affine<<P as PedersenConfig>::OCurve>,
+    ) -> Self
+    where
+        <PT::ScalarMul as ScalarMulProtocol<P>>::Intermediate: Send,
+    {
+        let inter = Self::create_intermediates_parallel(transcript, rng, t, r, r_x, s, q);
+        Self::create_proof(transcript, r, &inter, q)
+    }
+
+    /// sign_rfc6979. This function produces an ECDSA signature on the message hash `h` under the
+    /// secret key `x`, using the deterministic nonce from RFC6979 with HMAC-`D`.
+    /// This returns the point `R` along with the usual (r, s) pair, which is the form of
+    /// witness that `create` expects. Note that `s` is not normalised.
+    /// # Arguments
+    /// * `x` - the secret key.
+    /// * `h` - the hash of the message.
+    #[allow(clippy::type_complexity)]
+    pub fn sign_rfc6979<D: Digest + BlockSizeUser + Clone>(
+        x: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        h: &[u8],
+    ) -> (
+        sw::Affine<<P as PedersenConfig>::OCurve>,
+        <<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        <<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+    ) {
+        let k = rfc6979::generate_k::<_, D>(x, h);
+        let t =
+            rfc6979::bits2field::<<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField>(h);
+
+        let r = <<P as PedersenConfig>::OCurve as SWCurveConfig>::GENERATOR
+            .mul(k)
+            .into_affine();
+        let r_x = P::from_ob_to_os(r.x);
+        let s = (t + r_x * x) / k;
+        (r, r_x, s)
+    }
+
+    /// check_rfc6979_nonce. This function returns true if `r` is the point that signing the
+    /// message hash `h` under the secret key `x` produces with the RFC6979 nonce, and false
+    /// otherwise. This lets a holder of `x` check that a witness came from the deterministic
+    /// derivation before proving with it.
+    /// # Arguments
+    /// * `x` - the secret key.
+    /// * `h` - the hash of the message.
+    /// * `r` - the R value from the ECDSA signature.
+    pub fn check_rfc6979_nonce<D: Digest + BlockSizeUser + Clone>(
+        x: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        h: &[u8],
+        r: &sw::Affine<<P as PedersenConfig>::OCurve>,
+    ) -> bool {
+        let k = rfc6979::generate_k::<_, D>(x, h);
+        <<P as PedersenConfig>::OCurve as SWCurveConfig>::GENERATOR
+            .mul(k)
+            .into_affine()
+            == *r
+    }
+
+    /// create_rfc6979. This function signs the message hash `h` under the secret key `x` with
+    /// the RFC6979 nonce and then creates a proof of the resulting signature under the
+    /// committed public key xG. The proof verifies against `R` (held in the proof) and
+    /// the scalar returned by `rfc6979::bits2field(h)`.
+    /// N.B The proof does not show that `R` was derived with RFC6979: it only proves that
+    /// the signature is valid, and a verifier learns nothing about how the nonce was chosen.
+    /// The derivation is only checked by a `debug_assert` on the prover's side; a verifier
+    /// that needs it must recompute the nonce with `check_rfc6979_nonce`, which needs `x`.
+    /// # Arguments
+    /// * `transcript` - the transcript object.
+    /// * `rng` - the cryptographically random number generator.
+    /// * `x` - the secret key.
+    /// * `h` - the hash of the message.
+    pub fn create_rfc6979<T: RngCore + CryptoRng, D: Digest + BlockSizeUser + Clone>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        x: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        h: &[u8],
+    ) -> Self {
+        let (r, r_x, s) = Self::sign_rfc6979::<D>(x, h);
+        debug_assert!(Self::check_rfc6979_nonce::<D>(x, h, &r));
+
+        let t = rfc6979::bits2field(h);
+        let q = <<P as PedersenConfig>::OCurve as SWCurveConfig>::GENERATOR
+            .mul(*x)
+            .into_affine();
+        Self::create(transcript, rng, &t, &r, &r_x, &s, &q)
+    }
+
     /// create_proof. This function takes a pre-existing set of intermediates (`inter`) and builds
     /// an ECDSA signature verification proof from them.
     /// Note that this function generates each sub-challenge internally.
@@ -10,17 +10,22 @@ use ark_ec::{
 };
 use merlin::Transcript;
 
-use ark_serialize::CanonicalSerialize;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::{ops::Mul, UniformRand};
 use rand::{CryptoRng, RngCore};
 
 use crate::{
-    pedersen_config::PedersenComm, pedersen_config::PedersenConfig, transcript::EqualityTranscript,
+    pedersen_config::PedersenComm,
+    pedersen_config::PedersenConfig,
+    sigma_protocol::SigmaProtocol,
+    transcript::{witness_rng, EqualityTranscript, EQUALITY_LABELS},
+    verifier_context::VerifierContext,
 };
 
 /// EqualityProof. This struct acts as a container for an EqualityProof.
 /// New proof objects can be made via the `create` function, whereas existing
 /// proofs may be verified via the `verify` function.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct EqualityProof<P: PedersenConfig> {
     /// alpha: the random point produced during setup.
     pub alpha: sw::Affine<P>,
@@ -121,6 +126,9 @@ impl<P: PedersenConfig> EqualityProof<P> {
         c1: &PedersenComm<P>,
         c2: &PedersenComm<P>,
     ) -> EqualityProofIntermediate<P> {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(transcript, &[c1.r, c2.r], rng);
+
         let r = <P as CurveConfig>::ScalarField::rand(rng);
         let alpha = P::GENERATOR2.mul(r).into_affine();
         Self::make_transcript(transcript, &c1.comm, &c2.comm, &alpha);
@@ -143,7 +151,7 @@ impl<P: PedersenConfig> EqualityProof<P> {
             &Self::create_intermediates(transcript, rng, c1, c2),
             c1,
             c2,
-            &transcript.challenge_scalar(b"c")[..],
+            &transcript.challenge_scalar(EQUALITY_LABELS.challenge)[..],
         )
     }
 
@@ -211,7 +219,11 @@ impl<P: PedersenConfig> EqualityProof<P> {
     ) -> bool {
         // Make the transcript.
         self.add_to_transcript(transcript, c1, c2);
-        self.verify_proof(c1, c2, &transcript.challenge_scalar(b"c")[..])
+        self.verify_proof(
+            c1,
+            c2,
+            &transcript.challenge_scalar(EQUALITY_LABELS.challenge)[..],
+        )
     }
 
     /// verify_proof. This function returns true if the proof held by `self` is valid, and false otherwise.
@@ -255,12 +267,162 @@ impl<P: PedersenConfig> EqualityProof<P> {
         P::GENERATOR2.mul(self.z) - self.alpha == rhs
     }
 
+    /// verify_with_context. This function returns true if the proof held by `self` is valid, and false otherwise.
+    /// This function behaves like `verify`, but uses the precomputed generator tables in `ctx`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `transcript` - the transcript object that's used.
+    /// * `c1` - the c1 commitment.
+    /// * `c2` - the c2 commitment.
+    /// * `ctx` - the verifier context.
+    pub fn verify_with_context(
+        &self,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        c2: &sw::Affine<P>,
+        ctx: &VerifierContext<P>,
+    ) -> bool {
+        self.add_to_transcript(transcript, c1, c2);
+        self.verify_proof_with_context(
+            c1,
+            c2,
+            &transcript.challenge_scalar(EQUALITY_LABELS.challenge)[..],
+            ctx,
+        )
+    }
+
+    /// verify_proof_with_context. This function behaves like `verify_proof`, but uses the precomputed
+    /// generator tables in `ctx`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `c1` - the c1 commitment.
+    /// * `c2` - the c2 commitment.
+    /// * `chal_buf` - the buffer that contains the challenge bytes.
+    /// * `ctx` - the verifier context.
+    pub fn verify_proof_with_context(
+        &self,
+        c1: &sw::Affine<P>,
+        c2: &sw::Affine<P>,
+        chal_buf: &[u8],
+        ctx: &VerifierContext<P>,
+    ) -> bool {
+        self.verify_with_challenge_and_context(
+            c1,
+            c2,
+            &<P as PedersenConfig>::make_challenge_from_buffer(chal_buf),
+            ctx,
+        )
+    }
+
+    /// verify_with_challenge_and_context. This function behaves like `verify_with_challenge`, but uses
+    /// the precomputed generator tables in `ctx`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `c1` - the c1 commitment.
+    /// * `c2` - the c2 commitment.
+    /// * `chal` - the challenge.
+    /// * `ctx` - the verifier context.
+    pub fn verify_with_challenge_and_context(
+        &self,
+        c1: &sw::Affine<P>,
+        c2: &sw::Affine<P>,
+        chal: &<P as CurveConfig>::ScalarField,
+        ctx: &VerifierContext<P>,
+    ) -> bool {
+        let rhs = if *chal == P::CP1 {
+            (c1.into_group() - c2).into_affine()
+        } else if *chal == P::CM1 {
+            (c2.into_group() - c1).into_affine()
+        } else {
+            ((c1.into_group() - c2).mul(*chal)).into_affine()
+        };
+
+        ctx.mul_h(&self.z) - self.alpha == rhs
+    }
+
     /// serialized_size. Returns the number of bytes needed to represent this proof object once serialised.
     pub fn serialized_size(&self) -> usize {
         self.z.compressed_size() + self.alpha.compressed_size()
     }
 }
 
+impl<P: PedersenConfig> SigmaProtocol<P> for EqualityProof<P> {
+    /// Statement: the two commitments that hold the same value.
+    type Statement = (sw::Affine<P>, sw::Affine<P>);
+    /// Witness: the randomness of each commitment.
+    type Witness = (
+        <P as CurveConfig>::ScalarField,
+        <P as CurveConfig>::ScalarField,
+    );
+    type Intermediate = EqualityProofIntermediate<P>;
+
+    fn create_intermediates<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        statement: &Self::Statement,
+        witness: &Self::Witness,
+    ) -> Self::Intermediate {
+        let (c1, c2) = Self::open_statement(statement, witness);
+        Self::create_intermediates(transcript, rng, &c1, &c2)
+    }
+
+    fn create_proof_with_challenge(
+        inter: &Self::Intermediate,
+        statement: &Self::Statement,
+        witness: &Self::Witness,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> Self {
+        let (c1, c2) = Self::open_statement(statement, witness);
+        Self::create_proof_with_challenge(inter, &c1, &c2, chal)
+    }
+
+    fn simulate<T: RngCore + CryptoRng>(
+        rng: &mut T,
+        statement: &Self::Statement,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> Self {
+        // Pick the response first, and then the only alpha that it verifies against.
+        let z = <P as CurveConfig>::ScalarField::rand(rng);
+        let diff = statement.0.into_group() - statement.1;
+        let alpha = (P::GENERATOR2.mul(z) - diff.mul(*chal)).into_affine();
+        Self { alpha, z }
+    }
+
+    fn add_proof_to_transcript(&self, transcript: &mut Transcript, statement: &Self::Statement) {
+        self.add_to_transcript(transcript, &statement.0, &statement.1);
+    }
+
+    fn verify_with_challenge(
+        &self,
+        statement: &Self::Statement,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> bool {
+        self.verify_with_challenge(&statement.0, &statement.1, chal)
+    }
+}
+
+impl<P: PedersenConfig> EqualityProof<P> {
+    /// open_statement. Returns the commitments of `statement` alongside their randomness.
+    fn open_statement(
+        statement: &(sw::Affine<P>, sw::Affine<P>),
+        witness: &(
+            <P as CurveConfig>::ScalarField,
+            <P as CurveConfig>::ScalarField,
+        ),
+    ) -> (PedersenComm<P>, PedersenComm<P>) {
+        (
+            PedersenComm {
+                comm: statement.0,
+                r: witness.0,
+            },
+            PedersenComm {
+                comm: statement.1,
+                r: witness.1,
+            },
+        )
+    }
+}
+
 impl<P: PedersenConfig> EqualityProofTranscriptable for EqualityProof<P> {
     type Affine = sw::Affine<P>;
     fn add_to_transcript(&self, transcript: &mut Transcript, c1: &Self::Affine, c2: &Self::Affine) {
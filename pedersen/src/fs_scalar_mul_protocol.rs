@@ -7,7 +7,11 @@ use ark_ec::{
 };
 
 use merlin::Transcript;
-use rand::{CryptoRng, RngCore};
+use rand::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 use crate::{
     pedersen_config::{PedersenComm, PedersenConfig},
@@ -30,6 +34,70 @@ pub struct FSECScalarMulProofIntermediate<P: PedersenConfig, PT: ScalarMulProtoc
 }
 
 impl<P: PedersenConfig, PT: ScalarMulProtocol<P>> FSECScalarMulProof<P, PT> {
+    /// proofs. Returns the sub-proofs, one per repetition.
+    pub fn proofs(&self) -> &[PT] {
+        &self.proofs
+    }
+
+    /// from_proofs. This function builds a proof from its sub-proofs, e.g. after they were
+    /// decoded from another representation. This function returns None if there is not exactly
+    /// one sub-proof per repetition.
+    /// # Arguments
+    /// * `proofs` - the sub-proofs.
+    pub fn from_proofs(proofs: Vec<PT>) -> Option<Self> {
+        (proofs.len() == PT::SHIFT_BY * P::SECPARAM / 8 * PT::SUB_ITER).then_some(Self {
+            proofs,
+            _p: PhantomData,
+        })
+    }
+
+    /// draw_seeds. This function draws one ChaCha20 seed from `rng` for each repetition.
+    /// # Arguments
+    /// * `rng` - the cryptographically secure RNG.
+    fn draw_seeds<T: RngCore + CryptoRng>(rng: &mut T) -> Vec<<ChaCha20Rng as SeedableRng>::Seed> {
+        (0..P::SECPARAM)
+            .map(|_| {
+                let mut seed = <ChaCha20Rng as SeedableRng>::Seed::default();
+                rng.fill_bytes(&mut seed);
+                seed
+            })
+            .collect()
+    }
+
+    /// intermediate_from_seed. This function builds the intermediate values for a single
+    /// repetition, drawing its randomness from a ChaCha20 RNG seeded with `seed`. The intermediate
+    /// is built against a scratch transcript, so the same `seed` always produces the same intermediate.
+    /// # Arguments
+    /// * `seed` - the seed of the repetition.
+    /// * `s` - the secret, target point.
+    /// * `lambda` - the scalar multiple that is used.
+    /// * `p` - the publicly known generator.
+    #[allow(clippy::too_many_arguments)]
+    fn intermediate_from_seed(
+        seed: <ChaCha20Rng as SeedableRng>::Seed,
+        s: &sw::Affine<<P as PedersenConfig>::OCurve>,
+        lambda: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        p: &sw::Affine<<P as PedersenConfig>::OCurve>,
+        c1: &sw::Affine<P::OCurve>,
+        r1: &<P::OCurve as CurveConfig>::ScalarField,
+        c2: &PedersenComm<P>,
+        c3: &PedersenComm<P>,
+    ) -> PT::Intermediate {
+        let mut sub_rng = ChaCha20Rng::from_seed(seed);
+        let mut scratch = Transcript::new(b"");
+        PT::create_intermediates_with_existing_commitments(
+            &mut scratch,
+            &mut sub_rng,
+            s,
+            lambda,
+            p,
+            c1,
+            r1,
+            c2,
+            c3,
+        )
+    }
+
     /// create_intermediate. This function returns a set of intermediate values for
     /// s = λp for some publicly known point `P`. Note that `s` and `p` are both members of P::OCurve, and not the
     /// associated T Curve.
@@ -67,6 +135,94 @@ impl<P: PedersenConfig, PT: ScalarMulProtocol<P>> FSECScalarMulProof<P, PT> {
         }
     }
 
+    /// create_intermediate_parallel. This function returns the same kind of intermediate values as
+    /// `create_intermediate`, but builds the intermediates for each repetition concurrently.
+    /// Each repetition draws its randomness from a ChaCha20 RNG seeded from `rng`, and is built against
+    /// a scratch transcript. The commitments are then appended to `transcript` in repetition order,
+    /// so the resulting transcript is exactly the one a verifier rebuilds from the final proof.
+    /// # Arguments
+    /// * `transcript` - the transcript object to use.
+    /// * `s` - the secret, target point.
+    /// * `rng` - the cryptographically secure RNG.
+    /// * `lambda` - the scalar multiple that is used.
+    /// * `p` - the publicly known generator.
+    #[cfg(feature = "parallel")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_intermediate_parallel<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        s: &sw::Affine<<P as PedersenConfig>::OCurve>,
+        lambda: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        p: &sw::Affine<<P as PedersenConfig>::OCurve>,
+        c1: &sw::Affine<P::OCurve>,
+        r1: &<P::OCurve as CurveConfig>::ScalarField,
+        c2: &PedersenComm<P>,
+        c3: &PedersenComm<P>,
+    ) -> FSECScalarMulProofIntermediate<P, PT>
+    where
+        PT::Intermediate: Send,
+    {
+        // Domain separate the transcript.
+        PT::initialise_transcript(transcript);
+
+        // The seeds are drawn sequentially so that the output only depends on `rng`,
+        // and not on how the work is scheduled.
+        let seeds = Self::draw_seeds(rng);
+
+        let intermediates: Vec<PT::Intermediate> = seeds
+            .into_par_iter()
+            .map(|seed| Self::intermediate_from_seed(seed, s, lambda, p, c1, r1, c2, c3))
+            .collect();
+
+        // Now merge everything into the real transcript in order.
+        for inter in &intermediates {
+            PT::add_intermediate_to_transcript(inter, transcript, c1, &c2.comm, &c3.comm);
+        }
+
+        FSECScalarMulProofIntermediate {
+            intermediates,
+            _p: PhantomData,
+        }
+    }
+
+    /// create_parallel. This function creates a new scalar multiplication proof for s = λp for some
+    /// publicly known point `P`, building the intermediates for each repetition concurrently.
+    /// The resulting proof verifies with `verify`.
+    /// # Arguments
+    /// * `transcript` - the transcript object to use.
+    /// * `s` - the secret, target point.
+    /// * `rng` - the cryptographically secure RNG.
+    /// * `lambda` - the scalar multiple that is used.
+    /// * `p` - the publicly known generator.
+    #[cfg(feature = "parallel")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_parallel<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        s: &sw::Affine<<P as PedersenConfig>::OCurve>,
+        lambda: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        p: &sw::Affine<<P as PedersenConfig>::OCurve>,
+        c1: &sw::Affine<P::OCurve>,
+        r1: &<P::OCurve as CurveConfig>::ScalarField,
+        c2: &PedersenComm<P>,
+        c3: &PedersenComm<P>,
+    ) -> Self
+    where
+        PT::Intermediate: Send,
+    {
+        Self::create_proof(
+            s,
+            lambda,
+            p,
+            &Self::create_intermediate_parallel(transcript, rng, s, lambda, p, c1, r1, c2, c3),
+            c1,
+            r1,
+            c2,
+            c3,
+            &PT::challenge_scalar(transcript)[0..(PT::SHIFT_BY * P::SECPARAM / 8)],
+        )
+    }
+
     /// create. This function creates a new scalar multiplication proof for s = λp for some publicly known point `P`.
     /// Note that `s` and `p` are both members of P::OCurve, and not the
     /// associated T Curve.
@@ -101,6 +257,75 @@ impl<P: PedersenConfig, PT: ScalarMulProtocol<P>> FSECScalarMulProof<P, PT> {
         )
     }
 
+    /// create_streaming. This function creates the same kind of scalar multiplication proof as
+    /// `create`, but only holds the intermediates for `window` repetitions at a time.
+    /// Each repetition draws its randomness from a ChaCha20 RNG seeded from `rng`. The intermediates are
+    /// built a window at a time, appended to `transcript` and dropped. Once the challenge is known, each
+    /// window is rebuilt from its seeds and answered. Hence the peak memory is bounded by `window`
+    /// intermediates (plus a seed per repetition), at the cost of building every intermediate twice.
+    /// The transcript is exactly the one a verifier rebuilds from the final proof, so the proof verifies
+    /// with `verify`. This function panics if `window` is zero.
+    /// # Arguments
+    /// * `transcript` - the transcript object to use.
+    /// * `rng` - the cryptographically secure RNG.
+    /// * `window` - the number of repetitions that are held in memory at once.
+    /// * `s` - the secret, target point.
+    /// * `lambda` - the scalar multiple that is used.
+    /// * `p` - the publicly known generator.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_streaming<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        window: usize,
+        s: &sw::Affine<<P as PedersenConfig>::OCurve>,
+        lambda: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
+        p: &sw::Affine<<P as PedersenConfig>::OCurve>,
+        c1: &sw::Affine<P::OCurve>,
+        r1: &<P::OCurve as CurveConfig>::ScalarField,
+        c2: &PedersenComm<P>,
+        c3: &PedersenComm<P>,
+    ) -> Self {
+        assert!(
+            window != 0,
+            "FSECScalarMulProof: the window must not be empty"
+        );
+
+        // Domain separate the transcript.
+        PT::initialise_transcript(transcript);
+        let seeds = Self::draw_seeds(rng);
+
+        // First pass: commit to every repetition. Each intermediate is dropped once it is
+        // in the transcript.
+        for seed in &seeds {
+            let inter = Self::intermediate_from_seed(*seed, s, lambda, p, c1, r1, c2, c3);
+            PT::add_intermediate_to_transcript(&inter, transcript, c1, &c2.comm, &c3.comm);
+        }
+
+        let chal_buf = &PT::challenge_scalar(transcript)[0..(PT::SHIFT_BY * P::SECPARAM / 8)];
+
+        // Second pass: rebuild each window and answer its part of the challenge.
+        let mut proofs = Vec::with_capacity(P::SECPARAM);
+        for (w, chunk) in seeds.chunks(window).enumerate() {
+            let intermediates: Vec<PT::Intermediate> = chunk
+                .iter()
+                .map(|seed| Self::intermediate_from_seed(*seed, s, lambda, p, c1, r1, c2, c3))
+                .collect();
+
+            for (j, inter) in intermediates.iter().enumerate() {
+                let k = w * window + j;
+                let byte = chal_buf[k / (8 / PT::SHIFT_BY)] >> (PT::SHIFT_BY * (k % PT::SUB_ITER));
+                proofs.push(PT::create_proof_with_challenge_byte(
+                    s, lambda, p, inter, byte, c1, r1, c2, c3,
+                ));
+            }
+        }
+
+        Self {
+            proofs,
+            _p: PhantomData,
+        }
+    }
+
     /// create_proof_own_challenge.
     /// This function creates a new scalar multiplication proof for s = λp for some publicly known point `P`.
     /// Note that `s` and `p` are both members of P::OCurve, and not the associated T Curve.
@@ -0,0 +1,199 @@
+//! Defines a protocol for proving that a commitment is to a value in {0, ..., k-1} for various
+//! PedersenConfig types. This generalises the zero-one proof in `gk_zero_one_protocol`, which is
+//! the case k = 2, so that a small enumerated value (e.g. a region code) can be shown to be
+//! well-formed with a single proof, rather than with a zero-one proof per bit.
+//!
+//! As in https://eprint.iacr.org/2014/764.pdf, Figure 1, the prover commits to a random `a` and
+//! reveals f = mx + a for the challenge x. The polynomial prod_i (f - ix) = prod_i ((m - i)x + a)
+//! has degree k in x, and its leading coefficient prod_i (m - i) is zero exactly when m is in
+//! {0, ..., k-1}. The prover therefore commits to the k lower coefficients d_0, ..., d_{k-1}
+//! before the challenge, and the verifier checks that the product opens to the commitment
+//! sum_j x^j C_{d_j}.
+
+use ark_ec::{
+    short_weierstrass::{self as sw},
+    CurveConfig, CurveGroup,
+};
+use merlin::Transcript;
+
+use ark_ff::{Field, One, Zero};
+use ark_serialize::CanonicalSerialize;
+use ark_std::UniformRand;
+use rand::{CryptoRng, RngCore};
+use std::ops::Mul;
+
+use crate::{
+    pedersen_config::{PedersenComm, PedersenConfig},
+    transcript::{witness_rng, GKKAryTranscript, GK_K_ARY_LABELS},
+};
+
+/// KAryProof. This struct acts as a container for a proof that a commitment is to a value in
+/// {0, ..., k-1}. New proof objects can be made via the `create` function, whereas existing
+/// proofs may be verified via the `verify` function.
+pub struct KAryProof<P: PedersenConfig> {
+    /// ca: the commitment to the random value `a`.
+    pub ca: sw::Affine<P>,
+
+    /// cd: the commitments to the coefficients d_0, ..., d_{k-1}.
+    pub cd: Vec<sw::Affine<P>>,
+
+    /// f: the mx + a value.
+    pub f: <P as CurveConfig>::ScalarField,
+    /// z_a: the rx + s value.
+    pub z_a: <P as CurveConfig>::ScalarField,
+
+    /// z_d: the sum of x^j * rho_j, where rho_j is the blinding value of the j-th coefficient.
+    pub z_d: <P as CurveConfig>::ScalarField,
+}
+
+impl<P: PedersenConfig> KAryProof<P> {
+    /// make_transcript. This function adds `k`, the commitment `c` and the commitments `ca`, `cd`
+    /// to the `transcript` object.
+    /// # Arguments
+    /// * `transcript` - the transcript object.
+    /// * `k` - the number of allowed values.
+    /// * `c` - the existing commitment to `m`.
+    /// * `ca` - the ca commitment.
+    /// * `cd` - the commitments to the coefficients.
+    pub fn make_transcript(
+        transcript: &mut Transcript,
+        k: usize,
+        c: &sw::Affine<P>,
+        ca: &sw::Affine<P>,
+        cd: &[sw::Affine<P>],
+    ) {
+        transcript.domain_sep();
+        transcript.append_point(b"k", &(k as u64).to_le_bytes());
+
+        let mut compressed_bytes = Vec::new();
+        c.serialize_compressed(&mut compressed_bytes).unwrap();
+        transcript.append_point(b"c0", &compressed_bytes[..]);
+
+        compressed_bytes.clear();
+        ca.serialize_compressed(&mut compressed_bytes).unwrap();
+        transcript.append_point(b"ca", &compressed_bytes[..]);
+
+        for d in cd {
+            compressed_bytes.clear();
+            d.serialize_compressed(&mut compressed_bytes).unwrap();
+            transcript.append_point(b"cd", &compressed_bytes[..]);
+        }
+    }
+
+    /// create. This function creates a new KAryProof on `m`, returning the result. The proof
+    /// only verifies if `m` is in {0, ..., k-1}.
+    /// # Arguments
+    /// * `transcript` - the transcript object. The intermediate commitments etc are added to this transcript.
+    /// * `rng` - the random number generator to use. Must be cryptographically random.
+    /// * `k` - the number of allowed values. Must be at least 1.
+    /// * `m` - the value to which we are committing.
+    /// * `c` - a pre-existing commitment to `m`.
+    pub fn create<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        k: usize,
+        m: &<P as CurveConfig>::ScalarField,
+        c: &PedersenComm<P>,
+    ) -> Self {
+        assert!(k > 0, "KAryProof: k must be at least 1");
+
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(transcript, &[*m, c.r], rng);
+
+        let a = <P as CurveConfig>::ScalarField::rand(rng);
+        let s = <P as CurveConfig>::ScalarField::rand(rng);
+        let rho: Vec<_> = (0..k)
+            .map(|_| <P as CurveConfig>::ScalarField::rand(rng))
+            .collect();
+
+        // The coefficients of prod_i ((m - i)X + a), in increasing degree. The last one,
+        // prod_i (m - i), is not committed to.
+        let mut d = vec![<P as CurveConfig>::ScalarField::one()];
+        for i in 0..k {
+            let m_i = *m - <P as CurveConfig>::ScalarField::from(i as u64);
+            let mut next = vec![<P as CurveConfig>::ScalarField::zero(); d.len() + 1];
+            for (j, d_j) in d.iter().enumerate() {
+                next[j] += a * d_j;
+                next[j + 1] += m_i * d_j;
+            }
+            d = next;
+        }
+
+        let ca = PedersenComm::<P>::new_with_both(a, s).comm;
+        let cd: Vec<_> = d
+            .iter()
+            .zip(&rho)
+            .map(|(d_j, rho_j)| PedersenComm::<P>::new_with_both(*d_j, *rho_j).comm)
+            .collect();
+
+        Self::make_transcript(transcript, k, &c.comm, &ca, &cd);
+        let x = <P as PedersenConfig>::make_challenge_from_buffer(
+            &transcript.challenge_scalar(GK_K_ARY_LABELS.challenge),
+        );
+
+        let mut z_d = <P as CurveConfig>::ScalarField::zero();
+        for rho_j in rho.iter().rev() {
+            z_d = z_d * x + rho_j;
+        }
+
+        Self {
+            ca,
+            cd,
+            f: *m * x + a,
+            z_a: c.r * x + s,
+            z_d,
+        }
+    }
+
+    /// verify. This function verifies that the proof held by `self` is valid, returning true if
+    /// so, i.e that `c` is a commitment to a value in {0, ..., k-1}.
+    /// # Arguments
+    /// * `self` - the proof object.
+    /// * `transcript` - the transcript object.
+    /// * `k` - the number of allowed values.
+    /// * `c` - the already-received commitment to `m`.
+    pub fn verify(&self, transcript: &mut Transcript, k: usize, c: &sw::Affine<P>) -> bool {
+        Self::make_transcript(transcript, k, c, &self.ca, &self.cd);
+        self.verify_with_challenge(
+            k,
+            c,
+            &<P as PedersenConfig>::make_challenge_from_buffer(
+                &transcript.challenge_scalar(GK_K_ARY_LABELS.challenge),
+            ),
+        )
+    }
+
+    /// verify_with_challenge. This function verifies that the proof held by `self` is valid,
+    /// returning true if so. This function uses the challenge `chal`.
+    /// # Arguments
+    /// * `self` - the proof object.
+    /// * `k` - the number of allowed values.
+    /// * `c` - the already-received commitment to `m`.
+    /// * `chal` - the challenge to use.
+    pub fn verify_with_challenge(
+        &self,
+        k: usize,
+        c: &sw::Affine<P>,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> bool {
+        if k == 0 || self.cd.len() != k {
+            return false;
+        }
+
+        // prod_i (f - ix), which must open the committed polynomial at x.
+        let mut product = <P as CurveConfig>::ScalarField::one();
+        let mut ix = <P as CurveConfig>::ScalarField::ZERO;
+        for _ in 0..k {
+            product *= self.f - ix;
+            ix += chal;
+        }
+
+        let mut cd = sw::Projective::<P>::zero();
+        for d in self.cd.iter().rev() {
+            cd = cd * chal + d;
+        }
+
+        (self.ca + c.mul(*chal) == PedersenComm::new_with_both(self.f, self.z_a).comm)
+            && (cd.into_affine() == PedersenComm::new_with_both(product, self.z_d).comm)
+    }
+}
@@ -17,7 +17,7 @@ use std::ops::Mul;
 
 use crate::{
     pedersen_config::{PedersenComm, PedersenConfig},
-    transcript::GKZeroOneTranscript,
+    transcript::{witness_rng, GKZeroOneTranscript, GK_ZERO_ONE_LABELS},
 };
 
 /// ZeroOneProofTranscriptable. This trait provides a notion of `Transcriptable`, which implies
@@ -146,6 +146,9 @@ impl<P: PedersenConfig> ZeroOneProof<P> {
         m: &<P as CurveConfig>::ScalarField,
         c: &PedersenComm<P>,
     ) -> ZeroOneProofIntermediate<P> {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(transcript, &[*m, c.r], rng);
+
         // Make the initial random values.
         let a = <P as CurveConfig>::ScalarField::rand(rng);
         let s = <P as CurveConfig>::ScalarField::rand(rng);
@@ -175,7 +178,7 @@ impl<P: PedersenConfig> ZeroOneProof<P> {
             &Self::create_intermediates(transcript, rng, m, c),
             m,
             c,
-            &transcript.challenge_scalar(b"c")[..],
+            &transcript.challenge_scalar(GK_ZERO_ONE_LABELS.challenge)[..],
         )
     }
 
@@ -230,7 +233,10 @@ impl<P: PedersenConfig> ZeroOneProof<P> {
     /// * `c` - the already-received commitment to `m`.
     pub fn verify(&self, transcript: &mut Transcript, c: &sw::Affine<P>) -> bool {
         self.add_to_transcript(transcript, c);
-        self.verify_proof(c, &transcript.challenge_scalar(b"c")[..])
+        self.verify_proof(
+            c,
+            &transcript.challenge_scalar(GK_ZERO_ONE_LABELS.challenge)[..],
+        )
     }
 
     /// verify_proof. This function verifies that the proof held by `self` is valid, returning true if so.
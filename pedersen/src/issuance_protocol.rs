@@ -3,6 +3,7 @@
 //! The proof used here follows the same notation as https://eprint.iacr.org/2017/1132.pdf, Appendix A (the "Knowledge of Opening").
 //! This is originally due to Schnorr.
 
+use ark_bulletproofs::msm::{DefaultMsm, MsmBackend};
 use ark_ec::{
     short_weierstrass::{self as sw},
     CurveConfig, CurveGroup,
@@ -14,11 +15,125 @@ use ark_std::{ops::Mul, UniformRand};
 use rand::{CryptoRng, RngCore};
 
 use crate::{
-    pedersen_config::Generators, pedersen_config::PedersenComm, pedersen_config::PedersenConfig,
-    transcript::IssuanceTranscript,
+    pedersen_config::Generators,
+    pedersen_config::PedersenComm,
+    pedersen_config::PedersenConfig,
+    transcript::{witness_rng, IssuanceTranscript, ISSUANCE_LABELS},
 };
 use ark_std::Zero;
 
+/// AttributeKind. This enum describes how a single attribute of a multi-commitment is
+/// treated by an issuance proof.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeKind {
+    /// Hidden: the attribute is only known to the prover.
+    Hidden,
+    /// Zero: the attribute is publicly known to be zero.
+    Zero,
+    /// SecretKey: the attribute is the secret key behind the public key that is passed to the verifier.
+    SecretKey,
+}
+
+/// AttributeLayout. This struct describes the attributes that are held in a multi-commitment,
+/// in the order of the generators that are used to commit to them. Each attribute has a name
+/// and an `AttributeKind`. At most one attribute can be bound to a secret key.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AttributeLayout {
+    /// names: the name of each attribute.
+    names: Vec<String>,
+    /// kinds: the kind of each attribute.
+    kinds: Vec<AttributeKind>,
+}
+
+impl AttributeLayout {
+    /// new. This function returns a new, empty layout.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// with_attribute. This function returns `self` with a new attribute appended to it.
+    /// This function panics if `kind` is `AttributeKind::SecretKey` and the layout already
+    /// has an attribute that is bound to a secret key, or if `name` is already in use.
+    /// # Arguments
+    /// * `name` - the name of the attribute.
+    /// * `kind` - the kind of the attribute.
+    pub fn with_attribute(mut self, name: &str, kind: AttributeKind) -> Self {
+        assert!(
+            kind != AttributeKind::SecretKey || self.secret_key_index().is_none(),
+            "AttributeLayout: only one attribute can be bound to a secret key"
+        );
+        assert!(
+            self.index_of(name).is_none(),
+            "AttributeLayout: duplicate attribute name"
+        );
+        self.names.push(name.to_string());
+        self.kinds.push(kind);
+        self
+    }
+
+    /// boomerang. This function returns the layout that is used by boomerang tokens, i.e
+    /// (id, value, sk, rand), where the value is zero and sk is bound to the user's public key.
+    /// Any attributes beyond the fourth are hidden.
+    /// This is the layout that the layout-free functions of `IssuanceProofMulti` use.
+    /// # Arguments
+    /// * `l` - the number of attributes. Must be at least 3.
+    pub fn boomerang(l: usize) -> Self {
+        assert!(
+            l >= 3,
+            "AttributeLayout: the boomerang layout needs at least 3 attributes"
+        );
+        let mut layout = Self::new()
+            .with_attribute("id", AttributeKind::Hidden)
+            .with_attribute("value", AttributeKind::Zero)
+            .with_attribute("sk", AttributeKind::SecretKey);
+        if l > 3 {
+            layout = layout.with_attribute("rand", AttributeKind::Hidden);
+        }
+        for i in 4..l {
+            layout = layout.with_attribute(&format!("extra{}", i - 4), AttributeKind::Hidden);
+        }
+        layout
+    }
+
+    /// len. Returns the number of attributes in this layout.
+    pub fn len(&self) -> usize {
+        self.kinds.len()
+    }
+
+    /// is_empty. Returns true if this layout has no attributes.
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty()
+    }
+
+    /// kind. Returns the kind of the attribute at position `i`.
+    /// # Arguments
+    /// * `i` - the position of the attribute.
+    pub fn kind(&self, i: usize) -> AttributeKind {
+        self.kinds[i]
+    }
+
+    /// name. Returns the name of the attribute at position `i`.
+    /// # Arguments
+    /// * `i` - the position of the attribute.
+    pub fn name(&self, i: usize) -> &str {
+        &self.names[i]
+    }
+
+    /// index_of. Returns the position of the attribute called `name`, if there is one.
+    /// # Arguments
+    /// * `name` - the name of the attribute.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    /// secret_key_index. Returns the position of the attribute that is bound to a secret key, if there is one.
+    pub fn secret_key_index(&self) -> Option<usize> {
+        self.kinds
+            .iter()
+            .position(|k| *k == AttributeKind::SecretKey)
+    }
+}
+
 /// IssuanceProofMulti. This struct acts as a container for an IssuanceProofMulti.
 /// Note that this is aimed to work with multi-commitments.
 /// Essentially, a new proof object can be created by calling `create`, whereas
@@ -140,14 +255,43 @@ impl<P: PedersenConfig> IssuanceProofMulti<P> {
         c1: &PedersenComm<P>,
         gens: &Generators<P>,
     ) -> Self {
+        Self::create_with_layout(
+            transcript,
+            rng,
+            x,
+            c1,
+            &AttributeLayout::boomerang(x.len()),
+            gens,
+        )
+    }
+
+    /// create_with_layout. This function returns a new opening proof for `x` against `c1`,
+    /// where the attributes in `x` are described by `layout`.
+    /// # Arguments
+    /// * `transcript` - the transcript object that is modified.
+    /// * `rng` - the RNG that is used to produce the random values. Must be cryptographically secure.
+    /// * `x` - the value that is used to show an opening of  `c1`.
+    /// * `c1` - the commitment that is opened.
+    /// * `layout` - the layout of the attributes in `x`. Must have the same length as `x`.
+    /// * `gens` - the generators that were used to produce `c1`.
+    pub fn create_with_layout<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        x: &[<P as CurveConfig>::ScalarField],
+        c1: &PedersenComm<P>,
+        layout: &AttributeLayout,
+        gens: &Generators<P>,
+    ) -> Self {
+        assert_eq!(x.len(), layout.len());
+
         // This function just creates the intermediary objects and makes the proof from
         // those.
-        let inter = Self::create_intermediates(transcript, rng, c1, x.len(), gens);
+        let inter = Self::create_intermediates_with_layout(transcript, rng, c1, layout, gens);
 
         // Now call the routine that returns the "challenged" version.
-        // N.B For the sake of compatibility, here we just pass the buffer itself.
-        let chal_buf = transcript.challenge_scalar(b"c");
-        Self::create_proof(x, &inter, c1, &chal_buf)
+        let chal_buf = transcript.challenge_scalar(ISSUANCE_LABELS.challenge);
+        let chal = <P as PedersenConfig>::make_challenge_from_buffer(&chal_buf);
+        Self::create_proof_with_challenge_and_layout(x, &inter, c1, &chal, layout)
     }
 
     /// create_intermediaries. This function returns a new set of intermediaries
@@ -164,11 +308,38 @@ impl<P: PedersenConfig> IssuanceProofMulti<P> {
         l: usize,
         gens: &Generators<P>,
     ) -> IssuanceProofMultiIntermediate<P> {
+        Self::create_intermediates_with_layout(
+            transcript,
+            rng,
+            c1,
+            &AttributeLayout::boomerang(l),
+            gens,
+        )
+    }
+
+    /// create_intermediates_with_layout. This function returns a new set of intermediaries
+    /// for an opening proof against `c1`, where the attributes of `c1` are described by `layout`.
+    /// # Arguments
+    /// * `transcript` - the transcript object that is modified.
+    /// * `rng` - the RNG that is used to produce the random values. Must be cryptographically secure.
+    /// * `c1` - the commitment that is opened.
+    /// * `layout` - the layout of the attributes of `c1`.
+    /// * `gens` - the generators that were used to produce `c1`.
+    pub fn create_intermediates_with_layout<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        c1: &PedersenComm<P>,
+        layout: &AttributeLayout,
+        gens: &Generators<P>,
+    ) -> IssuanceProofMultiIntermediate<P> {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(transcript, &[c1.r], rng);
+
         let mut total: sw::Affine<P> = sw::Affine::identity();
         let mut ts: Vec<<P as CurveConfig>::ScalarField> = vec![];
 
-        for i in 0..l {
-            let t: <P as CurveConfig>::ScalarField = if i == 1 {
+        for i in 0..layout.len() {
+            let t: <P as CurveConfig>::ScalarField = if layout.kind(i) == AttributeKind::Zero {
                 <P as CurveConfig>::ScalarField::zero()
             } else {
                 <P as CurveConfig>::ScalarField::rand(rng)
@@ -179,7 +350,12 @@ impl<P: PedersenConfig> IssuanceProofMulti<P> {
         }
         let t1 = <P as CurveConfig>::ScalarField::rand(rng);
         let alpha = (total + P::GENERATOR2.mul(t1)).into_affine();
-        let alpha2 = (P::GENERATOR.mul(ts[2])).into_affine();
+
+        // If no attribute is bound to a secret key then there is nothing to prove here.
+        let alpha2 = match layout.secret_key_index() {
+            Some(i) => (P::GENERATOR.mul(ts[i])).into_affine(),
+            None => sw::Affine::identity(),
+        };
 
         Self::make_transcript(transcript, &c1.comm, &alpha, &alpha2);
         IssuanceProofMultiIntermediate {
@@ -222,7 +398,7 @@ impl<P: PedersenConfig> IssuanceProofMulti<P> {
         inter: &IssuanceProofMultiIntermediate<P>,
         c1: &PedersenComm<P>,
     ) -> Self {
-        let chal_buf = transcript.challenge_scalar(b"c");
+        let chal_buf = transcript.challenge_scalar(ISSUANCE_LABELS.challenge);
         Self::create_proof(x, inter, c1, &chal_buf)
     }
 
@@ -238,10 +414,35 @@ impl<P: PedersenConfig> IssuanceProofMulti<P> {
         inter: &IssuanceProofMultiIntermediate<P>,
         c1: &PedersenComm<P>,
         chal: &<P as CurveConfig>::ScalarField,
+    ) -> Self {
+        Self::create_proof_with_challenge_and_layout(
+            x,
+            inter,
+            c1,
+            chal,
+            &AttributeLayout::boomerang(x.len()),
+        )
+    }
+
+    /// create_proof_with_challenge_and_layout. This function accepts a set of intermediaries (`inter`) and proves
+    /// that `x` acts as a valid opening for `c1` using an existing challenge `chal`, where the attributes in `x`
+    /// are described by `layout`.
+    /// # Arguments
+    /// * `x` - the value that is used to show an opening of  `c1`.
+    /// * `inter` - the intermediaries. These should have been produced by a call to `create_intermediates_with_layout`.
+    /// * `c1` - the commitment that is opened.
+    /// * `chal` - the challenge.
+    /// * `layout` - the layout of the attributes in `x`.
+    pub fn create_proof_with_challenge_and_layout(
+        x: &[<P as CurveConfig>::ScalarField],
+        inter: &IssuanceProofMultiIntermediate<P>,
+        c1: &PedersenComm<P>,
+        chal: &<P as CurveConfig>::ScalarField,
+        layout: &AttributeLayout,
     ) -> Self {
         let mut z2: Vec<<P as CurveConfig>::ScalarField> = vec![];
         for (i, item) in x.iter().enumerate() {
-            let tmp: <P as CurveConfig>::ScalarField = if i == 1 {
+            let tmp: <P as CurveConfig>::ScalarField = if layout.kind(i) == AttributeKind::Zero {
                 <P as CurveConfig>::ScalarField::zero()
             } else {
                 *item * (*chal) + inter.ts[i]
@@ -272,9 +473,28 @@ impl<P: PedersenConfig> IssuanceProofMulti<P> {
         l: usize,
         gens: &Generators<P>,
     ) -> bool {
-        // Make the transcript.
-        self.add_to_transcript(transcript, c1);
-        self.verify_proof_own_challenge(transcript, c1, pk, l, gens)
+        self.verify_with_layout(transcript, c1, pk, &AttributeLayout::boomerang(l), gens)
+    }
+
+    /// verify_with_layout. This function returns true if the proof held by `self` is valid for a
+    /// commitment whose attributes are described by `layout`, and false otherwise.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `transcript` - the transcript object that's used.
+    /// * `c1` - the commitment whose opening is being proved by this function.
+    /// * `pk` - the public key that the secret key attribute is bound to. This is ignored if
+    ///   `layout` has no secret key attribute.
+    /// * `layout` - the layout of the attributes of `c1`.
+    /// * `gens` - the generators that were used to produce `c1`.
+    pub fn verify_with_layout(
+        &self,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        pk: &sw::Affine<P>,
+        layout: &AttributeLayout,
+        gens: &Generators<P>,
+    ) -> bool {
+        self.verify_with_layout_and_backend(transcript, c1, pk, layout, gens, &DefaultMsm)
     }
 
     /// verify_proof_own_challenge. This function returns true if the proof held by `self` is valid, and false otherwise.
@@ -292,7 +512,13 @@ impl<P: PedersenConfig> IssuanceProofMulti<P> {
         l: usize,
         gens: &Generators<P>,
     ) -> bool {
-        self.verify_proof(c1, pk, &transcript.challenge_scalar(b"c")[..], l, gens)
+        self.verify_proof(
+            c1,
+            pk,
+            &transcript.challenge_scalar(ISSUANCE_LABELS.challenge)[..],
+            l,
+            gens,
+        )
     }
 
     /// verify_proof. This function verifies that `c1` is a valid opening
@@ -328,25 +554,106 @@ impl<P: PedersenConfig> IssuanceProofMulti<P> {
         l: usize,
         gens: &Generators<P>,
     ) -> bool {
-        // first proof
+        self.verify_with_challenge_and_layout(c1, pk, chal, &AttributeLayout::boomerang(l), gens)
+    }
+
+    /// verify_with_challenge_and_layout. This function verifies that `c1` is a valid opening
+    /// of the proof held by `self`, but with a pre-existing challenge `chal`, where the attributes
+    /// of `c1` are described by `layout`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `c1` - the commitment whose opening is being proved by this function.
+    /// * `pk` - the public key that the secret key attribute is bound to. This is ignored if
+    ///   `layout` has no secret key attribute.
+    /// * `chal` - the challenge.
+    /// * `layout` - the layout of the attributes of `c1`.
+    /// * `gens` - the generators that were used to produce `c1`.
+    pub fn verify_with_challenge_and_layout(
+        &self,
+        c1: &sw::Affine<P>,
+        pk: &sw::Affine<P>,
+        chal: &<P as CurveConfig>::ScalarField,
+        layout: &AttributeLayout,
+        gens: &Generators<P>,
+    ) -> bool {
+        self.verify_with_challenge_layout_and_backend(c1, pk, chal, layout, gens, &DefaultMsm)
+    }
 
-        let rhs1 = pk.mul(*chal) + self.alpha2;
-        let lhs1 = P::GENERATOR.mul(self.z2[2]);
+    /// verify_with_layout_and_backend. This function returns true if the proof held by `self` is
+    /// valid for a commitment whose attributes are described by `layout`, and false otherwise.
+    /// This function behaves like `verify_with_layout`, but computes the sum over the
+    /// generators on `backend`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `transcript` - the transcript object that's used.
+    /// * `c1` - the commitment whose opening is being proved by this function.
+    /// * `pk` - the public key that the secret key attribute is bound to. This is ignored if
+    ///   `layout` has no secret key attribute.
+    /// * `layout` - the layout of the attributes of `c1`.
+    /// * `gens` - the generators that were used to produce `c1`.
+    /// * `backend` - the multi-scalar multiplication backend.
+    pub fn verify_with_layout_and_backend<M: MsmBackend<sw::Affine<P>>>(
+        &self,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        pk: &sw::Affine<P>,
+        layout: &AttributeLayout,
+        gens: &Generators<P>,
+        backend: &M,
+    ) -> bool {
+        // Make the transcript.
+        self.add_to_transcript(transcript, c1);
+        let chal = <P as PedersenConfig>::make_challenge_from_buffer(
+            &transcript.challenge_scalar(ISSUANCE_LABELS.challenge)[..],
+        );
+        self.verify_with_challenge_layout_and_backend(c1, pk, &chal, layout, gens, backend)
+    }
+
+    /// verify_with_challenge_layout_and_backend. This function behaves like
+    /// `verify_with_challenge_and_layout`, but computes the sum over the generators on `backend`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `c1` - the commitment whose opening is being proved by this function.
+    /// * `pk` - the public key that the secret key attribute is bound to. This is ignored if
+    ///   `layout` has no secret key attribute.
+    /// * `chal` - the challenge.
+    /// * `layout` - the layout of the attributes of `c1`.
+    /// * `gens` - the generators that were used to produce `c1`.
+    /// * `backend` - the multi-scalar multiplication backend.
+    pub fn verify_with_challenge_layout_and_backend<M: MsmBackend<sw::Affine<P>>>(
+        &self,
+        c1: &sw::Affine<P>,
+        pk: &sw::Affine<P>,
+        chal: &<P as CurveConfig>::ScalarField,
+        layout: &AttributeLayout,
+        gens: &Generators<P>,
+        backend: &M,
+    ) -> bool {
+        let l = layout.len();
+        if self.z2.len() < l || gens.generators.len() < l {
+            return false;
+        }
+
+        // first proof
+        let check1 = match layout.secret_key_index() {
+            Some(i) => P::GENERATOR.mul(self.z2[i]) == pk.mul(*chal) + self.alpha2,
+            None => self.alpha2.infinity,
+        };
 
         // second proof
         let rhs = c1.mul(*chal) + self.alpha;
 
-        let mut tmp: sw::Affine<P> = sw::Affine::identity();
-        for i in 0..l {
-            if i == 1 {
-                continue; // We assume that x[1] = 0
-            }
-            tmp = (tmp + gens.generators[i].mul(self.z2[i])).into();
-        }
+        // Attributes that are known to be zero do not contribute, so their generators are skipped.
+        let (mut bases, mut scalars): (Vec<_>, Vec<_>) = (0..l)
+            .filter(|i| layout.kind(*i) != AttributeKind::Zero)
+            .map(|i| (gens.generators[i], self.z2[i]))
+            .unzip();
+        bases.push(P::GENERATOR2);
+        scalars.push(self.z1);
 
-        let lhs = (tmp + P::GENERATOR2.mul(self.z1)).into_affine();
+        let lhs = backend.msm(&bases, &scalars).unwrap().into_affine();
 
-        lhs == rhs && lhs1 == rhs1
+        lhs == rhs && check1
     }
 
     /// serialized_size. Returns the number of bytes needed to represent this proof object once serialised.
@@ -1,23 +1,40 @@
 #![forbid(unsafe_code)]
 pub mod add_mul_protocol;
+pub mod and_protocol;
 pub mod collective;
+pub mod consistency_protocol;
+pub mod ct;
 pub mod ec_collective;
 pub mod ec_point_add_protocol;
 pub mod ecdsa_protocol;
 pub mod equality_protocol;
 pub mod fs_scalar_mul_protocol;
+pub mod gk_k_ary_protocol;
 pub mod gk_zero_one_protocol;
 pub mod interpolate;
 pub mod issuance_protocol;
 pub mod mul_protocol;
 pub mod non_zero_protocol;
 pub mod opening_protocol;
+pub mod or_protocol;
 pub mod pedersen_config;
 pub mod point_add;
+pub mod point_commitment;
 pub mod product_protocol;
+pub mod rfc6979;
 pub mod scalar_mul;
 pub mod scalar_mul_protocol;
+pub mod sigma_protocol;
 pub mod transcript;
+#[cfg(feature = "transcript_dump")]
+pub mod transcript_dump;
+pub mod verifier_context;
 pub mod zk_attest_collective;
+pub mod zk_attest_complete_point_add_protocol;
 pub mod zk_attest_point_add_protocol;
 pub mod zk_attest_scalar_mul_protocol;
+#[cfg(feature = "zkp_ecdsa")]
+pub mod zkp_ecdsa;
+
+/// msm. The multi-scalar multiplication backends that the multi-commitment verifiers can run on.
+pub use ark_bulletproofs::msm;
@@ -14,7 +14,10 @@ use ark_std::{ops::Mul, UniformRand};
 use rand::{CryptoRng, RngCore};
 
 use crate::{
-    pedersen_config::PedersenComm, pedersen_config::PedersenConfig, transcript::MulTranscript,
+    pedersen_config::PedersenComm,
+    pedersen_config::PedersenConfig,
+    transcript::{witness_rng, MulTranscript, MUL_LABELS},
+    verifier_context::VerifierContext,
 };
 
 /// MulProofTranscriptable. This trait provides a notion of `Transcriptable`, which implies
@@ -191,7 +194,7 @@ impl<P: PedersenConfig> MulProof<P> {
             c1,
             c2,
             c3,
-            &transcript.challenge_scalar(b"c")[..],
+            &transcript.challenge_scalar(MUL_LABELS.challenge)[..],
         )
     }
 
@@ -210,6 +213,9 @@ impl<P: PedersenConfig> MulProof<P> {
         c2: &PedersenComm<P>,
         c3: &PedersenComm<P>,
     ) -> MulProofIntermediate<P> {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(transcript, &[c1.r, c2.r, c3.r], rng);
+
         // Generate the random values.
         let b1 = <P as CurveConfig>::ScalarField::rand(rng);
         let b2 = <P as CurveConfig>::ScalarField::rand(rng);
@@ -341,7 +347,12 @@ impl<P: PedersenConfig> MulProof<P> {
         c3: &sw::Affine<P>,
     ) -> bool {
         Self::make_transcript(transcript, c1, c2, c3, &self.alpha, &self.beta, &self.delta);
-        self.verify_proof(c1, c2, c3, &transcript.challenge_scalar(b"c")[..])
+        self.verify_proof(
+            c1,
+            c2,
+            c3,
+            &transcript.challenge_scalar(MUL_LABELS.challenge)[..],
+        )
     }
 
     /// verify_proof. This function simply verifies that the proof held by `self` is a valid
@@ -397,6 +408,88 @@ impl<P: PedersenConfig> MulProof<P> {
         }
     }
 
+    /// verify_with_context. This function returns true if the proof held by `self` is valid, and false otherwise.
+    /// This function behaves like `verify`, but uses the precomputed generator tables in `ctx`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `transcript` - the transcript object that's used.
+    /// * `c1` - the c1 commitment. This acts as a commitment to `x`.
+    /// * `c2` - the c2 commitment. This acts as a commitment to `y`.
+    /// * `c3` - the c3 commitment. This acts as a commitment to `z = x * y`.
+    /// * `ctx` - the verifier context.
+    pub fn verify_with_context(
+        &self,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        c2: &sw::Affine<P>,
+        c3: &sw::Affine<P>,
+        ctx: &VerifierContext<P>,
+    ) -> bool {
+        Self::make_transcript(transcript, c1, c2, c3, &self.alpha, &self.beta, &self.delta);
+        self.verify_proof_with_context(
+            c1,
+            c2,
+            c3,
+            &transcript.challenge_scalar(MUL_LABELS.challenge)[..],
+            ctx,
+        )
+    }
+
+    /// verify_proof_with_context. This function behaves like `verify_proof`, but uses the precomputed
+    /// generator tables in `ctx`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `c1` - the c1 commitment. This acts as a commitment to `x`.
+    /// * `c2` - the c2 commitment. This acts as a commitment to `y`.
+    /// * `c3` - the c3 commitment. This acts as a commitment to `z = x * y`.
+    /// * `chal_buf` - the buffer that contains the challenge bytes.
+    /// * `ctx` - the verifier context.
+    pub fn verify_proof_with_context(
+        &self,
+        c1: &sw::Affine<P>,
+        c2: &sw::Affine<P>,
+        c3: &sw::Affine<P>,
+        chal_buf: &[u8],
+        ctx: &VerifierContext<P>,
+    ) -> bool {
+        let chal = <P as PedersenConfig>::make_challenge_from_buffer(chal_buf);
+        self.verify_with_challenge_and_context(c1, c2, c3, &chal, ctx)
+    }
+
+    /// verify_with_challenge_and_context. This function behaves like `verify_with_challenge`, but uses
+    /// the precomputed generator tables in `ctx`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `c1` - the c1 commitment. This acts as a commitment to `x`.
+    /// * `c2` - the c2 commitment. This acts as a commitment to `y`.
+    /// * `c3` - the c3 commitment. This acts as a commitment to `z = x * y`.
+    /// * `chal` - the challenge.
+    /// * `ctx` - the verifier context.
+    pub fn verify_with_challenge_and_context(
+        &self,
+        c1: &sw::Affine<P>,
+        c2: &sw::Affine<P>,
+        c3: &sw::Affine<P>,
+        chal: &<P as CurveConfig>::ScalarField,
+        ctx: &VerifierContext<P>,
+    ) -> bool {
+        let (lhs1, lhs2, lhs3) = if *chal == P::CM1 {
+            (self.alpha - c1, self.beta - c2, self.delta - c3)
+        } else if *chal == P::CP1 {
+            (self.alpha + c1, self.beta + c2, self.delta + c3)
+        } else {
+            (
+                self.alpha + c1.mul(*chal),
+                self.beta + c2.mul(*chal),
+                self.delta + c3.mul(*chal),
+            )
+        };
+
+        (lhs1 == ctx.commit(&self.z1, &self.z2))
+            && (lhs2 == ctx.commit(&self.z3, &self.z4))
+            && (lhs3 == c1.mul(self.z3) + ctx.mul_h(&self.z5))
+    }
+
     /// serialized_size. Returns the number of bytes needed to represent this proof object once serialised.
     pub fn serialized_size(&self) -> usize {
         self.alpha.compressed_size()
@@ -12,7 +12,9 @@ use ark_std::{ops::Mul, UniformRand};
 use rand::{CryptoRng, RngCore};
 
 use crate::{
-    pedersen_config::PedersenComm, pedersen_config::PedersenConfig, transcript::NonZeroTranscript,
+    pedersen_config::PedersenComm,
+    pedersen_config::PedersenConfig,
+    transcript::{witness_rng, NonZeroTranscript, NON_ZERO_LABELS},
 };
 
 use ark_ec::short_weierstrass::Affine;
@@ -159,7 +161,7 @@ impl<P: PedersenConfig> NonZeroProof<P> {
             x,
             &Self::create_intermediates(transcript, rng, x, c1),
             c1,
-            &transcript.challenge_scalar(b"c")[..],
+            &transcript.challenge_scalar(NON_ZERO_LABELS.challenge)[..],
         )
     }
 
@@ -176,6 +178,9 @@ impl<P: PedersenConfig> NonZeroProof<P> {
         x: &<P as CurveConfig>::ScalarField,
         c1: &PedersenComm<P>,
     ) -> NonZeroProofIntermediate<P> {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(transcript, &[*x, c1.r], rng);
+
         // Generate the random values.
         let a1 = <P as CurveConfig>::ScalarField::rand(rng);
         let a2 = <P as CurveConfig>::ScalarField::rand(rng);
@@ -274,7 +279,10 @@ impl<P: PedersenConfig> NonZeroProof<P> {
     /// * `c1` - the c1 commitment. This acts as a commitment to `x`.
     pub fn verify(&self, transcript: &mut Transcript, c1: &sw::Affine<P>) -> bool {
         Self::make_transcript(transcript, c1, &self.t1, &self.t2, &self.t3);
-        self.verify_proof(c1, &transcript.challenge_scalar(b"c")[..])
+        self.verify_proof(
+            c1,
+            &transcript.challenge_scalar(NON_ZERO_LABELS.challenge)[..],
+        )
     }
 
     /// verify_proof. This function simply verifies that the proof held by `self` is a valid
@@ -6,6 +6,7 @@
 //! The proof used here follows the same notation as https://eprint.iacr.org/2017/1132.pdf, Appendix A (the "Knowledge of Opening").
 //! This is originally due to Schnorr.
 
+use ark_bulletproofs::msm::{DefaultMsm, MsmBackend};
 use ark_ec::{
     short_weierstrass::{self as sw},
     CurveConfig, CurveGroup,
@@ -13,12 +14,19 @@ use ark_ec::{
 use merlin::Transcript;
 
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::{ops::Mul, UniformRand};
+use ark_std::{ops::Mul, One, UniformRand};
 use rand::{CryptoRng, RngCore};
 
 use crate::{
-    pedersen_config::Generators, pedersen_config::PedersenComm, pedersen_config::PedersenConfig,
-    transcript::OpeningTranscript,
+    pedersen_config::Generators,
+    pedersen_config::PedersenComm,
+    pedersen_config::PedersenConfig,
+    sigma_protocol::SigmaProtocol,
+    transcript::{
+        witness_rng, OpeningMultiTranscript, OpeningTranscript, OPENING_LABELS,
+        OPENING_MULTI_LABELS,
+    },
+    verifier_context::VerifierContext,
 };
 
 /// OpeningProof. This struct acts as a container for an OpeningProof.
@@ -159,13 +167,13 @@ impl<P: PedersenConfig> OpeningProof<P> {
         // This function just builds the transcript out of the various input values.
         // N.B Because of how we define the serialisation API to handle different numbers,
         // we use a temporary buffer here.
-        transcript.domain_sep();
+        OpeningTranscript::domain_sep(transcript);
         let mut compressed_bytes = Vec::new();
         c1.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_point(b"C1", &compressed_bytes[..]);
+        OpeningTranscript::append_point(transcript, b"C1", &compressed_bytes[..]);
 
         alpha_p.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_point(b"alpha", &compressed_bytes[..]);
+        OpeningTranscript::append_point(transcript, b"alpha", &compressed_bytes[..]);
     }
 
     /// create. This function returns a new opening proof for `x` against `c1`.
@@ -186,7 +194,7 @@ impl<P: PedersenConfig> OpeningProof<P> {
 
         // Now call the routine that returns the "challenged" version.
         // N.B For the sake of compatibility, here we just pass the buffer itself.
-        let chal_buf = transcript.challenge_scalar(b"c");
+        let chal_buf = OpeningTranscript::challenge_scalar(transcript, OPENING_LABELS.challenge);
         Self::create_proof(x, &inter, c1, &chal_buf)
     }
 
@@ -202,6 +210,9 @@ impl<P: PedersenConfig> OpeningProof<P> {
         rng: &mut T,
         c1: &PedersenComm<P>,
     ) -> OpeningProofIntermediate<P> {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(transcript, &[c1.r], rng);
+
         let t1 = <P as CurveConfig>::ScalarField::rand(rng);
         let t2 = <P as CurveConfig>::ScalarField::rand(rng);
         let alpha = (P::GENERATOR.mul(t1) + P::GENERATOR2.mul(t2)).into_affine();
@@ -241,7 +252,7 @@ impl<P: PedersenConfig> OpeningProof<P> {
         inter: &OpeningProofIntermediate<P>,
         c1: &PedersenComm<P>,
     ) -> Self {
-        let chal_buf = transcript.challenge_scalar(b"c");
+        let chal_buf = OpeningTranscript::challenge_scalar(transcript, OPENING_LABELS.challenge);
         Self::create_proof(x, inter, c1, &chal_buf)
     }
 
@@ -296,7 +307,10 @@ impl<P: PedersenConfig> OpeningProof<P> {
         transcript: &mut Transcript,
         c1: &sw::Affine<P>,
     ) -> bool {
-        self.verify_proof(c1, &transcript.challenge_scalar(b"c")[..])
+        self.verify_proof(
+            c1,
+            &OpeningTranscript::challenge_scalar(transcript, OPENING_LABELS.challenge)[..],
+        )
     }
 
     /// verify_proof. This function verifies that `c1` is a valid opening
@@ -333,6 +347,68 @@ impl<P: PedersenConfig> OpeningProof<P> {
         P::GENERATOR.mul(self.z1) + P::GENERATOR2.mul(self.z2) == rhs
     }
 
+    /// verify_with_context. This function returns true if the proof held by `self` is valid, and false otherwise.
+    /// This function behaves like `verify`, but uses the precomputed generator tables in `ctx`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `transcript` - the transcript object that's used.
+    /// * `c1` - the commitment whose opening is being proved by this function.
+    /// * `ctx` - the verifier context.
+    pub fn verify_with_context(
+        &self,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        ctx: &VerifierContext<P>,
+    ) -> bool {
+        self.add_to_transcript(transcript, c1);
+        self.verify_proof_with_context(
+            c1,
+            &OpeningTranscript::challenge_scalar(transcript, OPENING_LABELS.challenge)[..],
+            ctx,
+        )
+    }
+
+    /// verify_proof_with_context. This function behaves like `verify_proof`, but uses the precomputed
+    /// generator tables in `ctx`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `c1` - the commitment whose opening is being proved by this function.
+    /// * `chal_buf` - the buffer that contains the challenge bytes.
+    /// * `ctx` - the verifier context.
+    pub fn verify_proof_with_context(
+        &self,
+        c1: &sw::Affine<P>,
+        chal_buf: &[u8],
+        ctx: &VerifierContext<P>,
+    ) -> bool {
+        let chal = <P as PedersenConfig>::make_challenge_from_buffer(chal_buf);
+        self.verify_with_challenge_and_context(c1, &chal, ctx)
+    }
+
+    /// verify_with_challenge_and_context. This function behaves like `verify_with_challenge`, but uses
+    /// the precomputed generator tables in `ctx`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `c1` - the commitment whose opening is being proved by this function.
+    /// * `chal` - the challenge.
+    /// * `ctx` - the verifier context.
+    pub fn verify_with_challenge_and_context(
+        &self,
+        c1: &sw::Affine<P>,
+        chal: &<P as CurveConfig>::ScalarField,
+        ctx: &VerifierContext<P>,
+    ) -> bool {
+        let rhs = if *chal == P::CM1 {
+            self.alpha - c1
+        } else if *chal == P::CP1 {
+            self.alpha + c1
+        } else {
+            c1.mul(*chal) + self.alpha
+        };
+
+        ctx.commit(&self.z1, &self.z2) == rhs
+    }
+
     /// serialized_size. Returns the number of bytes needed to represent this proof object once serialised.
     pub fn serialized_size(&self) -> usize {
         self.alpha.compressed_size() + self.z1.compressed_size() + self.z2.compressed_size()
@@ -360,6 +436,68 @@ impl<P: PedersenConfig> OpeningProofTranscriptable for OpeningProofIntermediateT
     }
 }
 
+impl<P: PedersenConfig> SigmaProtocol<P> for OpeningProof<P> {
+    /// Statement: the commitment that is opened.
+    type Statement = sw::Affine<P>;
+    /// Witness: the committed value and the randomness of the commitment.
+    type Witness = (
+        <P as CurveConfig>::ScalarField,
+        <P as CurveConfig>::ScalarField,
+    );
+    type Intermediate = OpeningProofIntermediate<P>;
+
+    fn create_intermediates<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        statement: &Self::Statement,
+        witness: &Self::Witness,
+    ) -> Self::Intermediate {
+        let c1 = PedersenComm {
+            comm: *statement,
+            r: witness.1,
+        };
+        Self::create_intermediates(transcript, rng, &c1)
+    }
+
+    fn create_proof_with_challenge(
+        inter: &Self::Intermediate,
+        statement: &Self::Statement,
+        witness: &Self::Witness,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> Self {
+        let c1 = PedersenComm {
+            comm: *statement,
+            r: witness.1,
+        };
+        Self::create_proof_with_challenge(&witness.0, inter, &c1, chal)
+    }
+
+    fn simulate<T: RngCore + CryptoRng>(
+        rng: &mut T,
+        statement: &Self::Statement,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> Self {
+        // Pick the responses first, and then the only alpha that they verify against.
+        let z1 = <P as CurveConfig>::ScalarField::rand(rng);
+        let z2 = <P as CurveConfig>::ScalarField::rand(rng);
+        let alpha =
+            (P::GENERATOR.mul(z1) + P::GENERATOR2.mul(z2) - statement.mul(*chal)).into_affine();
+        Self { alpha, z1, z2 }
+    }
+
+    fn add_proof_to_transcript(&self, transcript: &mut Transcript, statement: &Self::Statement) {
+        self.add_to_transcript(transcript, statement);
+    }
+
+    fn verify_with_challenge(
+        &self,
+        statement: &Self::Statement,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> bool {
+        self.verify_with_challenge(statement, chal)
+    }
+}
+
 impl<P: PedersenConfig> OpeningProofMulti<P> {
     /// make_intermediate_transcript. This function accepts a set of intermediates and builds an intermediate
     /// transcript from those intermediates.
@@ -384,13 +522,13 @@ impl<P: PedersenConfig> OpeningProofMulti<P> {
         // This function just builds the transcript out of the various input values.
         // N.B Because of how we define the serialisation API to handle different numbers,
         // we use a temporary buffer here.
-        transcript.domain_sep();
+        OpeningMultiTranscript::domain_sep(transcript);
         let mut compressed_bytes = Vec::new();
         c1.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_point(b"C1", &compressed_bytes[..]);
+        OpeningMultiTranscript::append_point(transcript, b"C1", &compressed_bytes[..]);
 
         alpha_p.serialize_compressed(&mut compressed_bytes).unwrap();
-        transcript.append_point(b"alpha", &compressed_bytes[..]);
+        OpeningMultiTranscript::append_point(transcript, b"alpha", &compressed_bytes[..]);
     }
 
     /// create. This function returns a new opening proof for `x` against `c1`.
@@ -412,7 +550,8 @@ impl<P: PedersenConfig> OpeningProofMulti<P> {
 
         // Now call the routine that returns the "challenged" version.
         // N.B For the sake of compatibility, here we just pass the buffer itself.
-        let chal_buf = transcript.challenge_scalar(b"c");
+        let chal_buf =
+            OpeningMultiTranscript::challenge_scalar(transcript, OPENING_MULTI_LABELS.challenge);
         Self::create_proof(x, &inter, c1, &chal_buf)
     }
 
@@ -430,6 +569,9 @@ impl<P: PedersenConfig> OpeningProofMulti<P> {
         l: usize,
         gens: &Generators<P>,
     ) -> OpeningProofMultiIntermediate<P> {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(transcript, &[c1.r], rng);
+
         let mut total: sw::Affine<P> = sw::Affine::identity();
         let mut ts: Vec<<P as CurveConfig>::ScalarField> = vec![];
 
@@ -477,7 +619,8 @@ impl<P: PedersenConfig> OpeningProofMulti<P> {
         inter: &OpeningProofMultiIntermediate<P>,
         c1: &PedersenComm<P>,
     ) -> Self {
-        let chal_buf = transcript.challenge_scalar(b"c");
+        let chal_buf =
+            OpeningMultiTranscript::challenge_scalar(transcript, OPENING_MULTI_LABELS.challenge);
         Self::create_proof(x, inter, c1, &chal_buf)
     }
 
@@ -540,7 +683,9 @@ impl<P: PedersenConfig> OpeningProofMulti<P> {
         l: usize,
         gens: &Generators<P>,
     ) -> bool {
-        self.verify_proof(c1, &transcript.challenge_scalar(b"c")[..], l, gens)
+        let chal_buf =
+            OpeningMultiTranscript::challenge_scalar(transcript, OPENING_MULTI_LABELS.challenge);
+        self.verify_proof(c1, &chal_buf[..], l, gens)
     }
 
     /// verify_proof. This function verifies that `c1` is a valid opening
@@ -573,19 +718,167 @@ impl<P: PedersenConfig> OpeningProofMulti<P> {
         chal: &<P as CurveConfig>::ScalarField,
         l: usize,
         gens: &Generators<P>,
+    ) -> bool {
+        self.verify_with_challenge_and_backend(c1, chal, l, gens, &DefaultMsm)
+    }
+
+    /// verify_with_backend. This function returns true if the proof held by `self` is valid, and
+    /// false otherwise. This function behaves like `verify`, but computes the sum over the
+    /// generators on `backend`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `transcript` - the transcript object that's used.
+    /// * `c1` - the commitment whose opening is being proved by this function.
+    /// * `l` - the number of committed values.
+    /// * `gens` - the generators of the commitment.
+    /// * `backend` - the multi-scalar multiplication backend.
+    pub fn verify_with_backend<M: MsmBackend<sw::Affine<P>>>(
+        &self,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        l: usize,
+        gens: &Generators<P>,
+        backend: &M,
+    ) -> bool {
+        self.add_to_transcript(transcript, c1);
+        let chal_buf =
+            OpeningMultiTranscript::challenge_scalar(transcript, OPENING_MULTI_LABELS.challenge);
+        let chal = <P as PedersenConfig>::make_challenge_from_buffer(&chal_buf[..]);
+        self.verify_with_challenge_and_backend(c1, &chal, l, gens, backend)
+    }
+
+    /// verify_with_challenge_and_backend. This function verifies that `c1` is a valid opening
+    /// of the proof held by `self`, but with a pre-existing challenge `chal`, computing the sum
+    /// over the generators on `backend`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `c1` - the commitment whose opening is being proved by this function.
+    /// * `chal` - the challenge.
+    /// * `l` - the number of committed values.
+    /// * `gens` - the generators of the commitment.
+    /// * `backend` - the multi-scalar multiplication backend.
+    pub fn verify_with_challenge_and_backend<M: MsmBackend<sw::Affine<P>>>(
+        &self,
+        c1: &sw::Affine<P>,
+        chal: &<P as CurveConfig>::ScalarField,
+        l: usize,
+        gens: &Generators<P>,
+        backend: &M,
     ) -> bool {
         let rhs = c1.mul(*chal) + self.alpha;
 
-        let mut tmp: sw::Affine<P> = sw::Affine::identity();
-        for i in 0..l {
-            tmp = (tmp + gens.generators[i].mul(self.z2[i])).into();
-        }
+        let mut bases = gens.generators[..l].to_vec();
+        let mut scalars = self.z2[..l].to_vec();
+        bases.push(P::GENERATOR2);
+        scalars.push(self.z1);
 
-        let lhs = (tmp + P::GENERATOR2.mul(self.z1)).into_affine();
+        let lhs = backend.msm(&bases, &scalars).unwrap().into_affine();
 
         lhs == rhs
     }
 
+    /// verification_equation. This function adds `self` to the `transcript` like `verify`, and
+    /// returns the equation that `verify` checks as a list of (point, scalar) terms whose sum
+    /// must be the identity, so that it can be checked together with other equations. This
+    /// function returns None if the proof has too few responses for `l` values.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `transcript` - the transcript object that's used.
+    /// * `c1` - the commitment whose opening is being proved by this function.
+    /// * `l` - the number of committed values.
+    /// * `gens` - the generators of the commitment.
+    pub fn verification_equation(
+        &self,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        l: usize,
+        gens: &Generators<P>,
+    ) -> Option<Vec<(sw::Affine<P>, <P as CurveConfig>::ScalarField)>> {
+        if self.z2.len() < l || gens.generators.len() < l {
+            return None;
+        }
+
+        self.add_to_transcript(transcript, c1);
+        let chal_buf =
+            OpeningMultiTranscript::challenge_scalar(transcript, OPENING_MULTI_LABELS.challenge);
+        let chal = <P as PedersenConfig>::make_challenge_from_buffer(&chal_buf[..]);
+
+        let one = <P as CurveConfig>::ScalarField::one();
+        let mut equation: Vec<_> = gens.generators[..l]
+            .iter()
+            .copied()
+            .zip(self.z2[..l].iter().copied())
+            .collect();
+        equation.push((P::GENERATOR2, self.z1));
+        equation.push((*c1, -chal));
+        equation.push((self.alpha, -one));
+        Some(equation)
+    }
+
+    /// verify_with_context. This function returns true if the proof held by `self` is valid, and false otherwise.
+    /// This function behaves like `verify`, but uses the precomputed generator tables in `ctx`.
+    /// Note that `ctx` must have been built with the generators used for `c1`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `transcript` - the transcript object that's used.
+    /// * `c1` - the commitment whose opening is being proved by this function.
+    /// * `l` - the number of committed values.
+    /// * `ctx` - the verifier context.
+    pub fn verify_with_context(
+        &self,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        l: usize,
+        ctx: &VerifierContext<P>,
+    ) -> bool {
+        self.add_to_transcript(transcript, c1);
+        let chal_buf =
+            OpeningMultiTranscript::challenge_scalar(transcript, OPENING_MULTI_LABELS.challenge);
+        self.verify_proof_with_context(c1, &chal_buf[..], l, ctx)
+    }
+
+    /// verify_proof_with_context. This function behaves like `verify_proof`, but uses the precomputed
+    /// generator tables in `ctx`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `c1` - the commitment whose opening is being proved by this function.
+    /// * `chal_buf` - the buffer that contains the challenge bytes.
+    /// * `l` - the number of committed values.
+    /// * `ctx` - the verifier context.
+    pub fn verify_proof_with_context(
+        &self,
+        c1: &sw::Affine<P>,
+        chal_buf: &[u8],
+        l: usize,
+        ctx: &VerifierContext<P>,
+    ) -> bool {
+        let chal = <P as PedersenConfig>::make_challenge_from_buffer(chal_buf);
+        self.verify_with_challenge_and_context(c1, &chal, l, ctx)
+    }
+
+    /// verify_with_challenge_and_context. This function behaves like `verify_with_challenge`, but uses
+    /// the precomputed generator tables in `ctx`.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `c1` - the commitment whose opening is being proved by this function.
+    /// * `chal` - the challenge.
+    /// * `l` - the number of committed values.
+    /// * `ctx` - the verifier context.
+    pub fn verify_with_challenge_and_context(
+        &self,
+        c1: &sw::Affine<P>,
+        chal: &<P as CurveConfig>::ScalarField,
+        l: usize,
+        ctx: &VerifierContext<P>,
+    ) -> bool {
+        if l > self.z2.len() || l > ctx.num_generators() {
+            return false;
+        }
+
+        let rhs = c1.mul(*chal) + self.alpha;
+        ctx.commit_multi(&self.z2[..l], &self.z1) == rhs
+    }
+
     /// serialized_size. Returns the number of bytes needed to represent this proof object once serialised.
     pub fn serialized_size(&self) -> usize {
         self.alpha.compressed_size() + self.z1.compressed_size() + self.z2.compressed_size()
@@ -0,0 +1,118 @@
+//! Defines an OR composition of two sigma protocols.
+//! That is, this protocol proves in ZK that the prover knows a witness for at least one of two
+//! statements, without revealing which one.
+//!
+//! The composition is the folklore one of Cramer, Damgård and Schoenmakers
+//! (https://link.springer.com/chapter/10.1007/3-540-48658-5_19): the prover picks the
+//! challenge of the statement it cannot prove, simulates a proof of that statement, and
+//! proves the other statement honestly with the remainder of the transcript challenge. The
+//! verifier only checks that both proofs verify, and that their challenges sum to the
+//! transcript challenge.
+
+use ark_ec::CurveConfig;
+use merlin::Transcript;
+
+use ark_std::UniformRand;
+use rand::{CryptoRng, RngCore};
+
+use crate::{
+    pedersen_config::PedersenConfig,
+    sigma_protocol::SigmaProtocol,
+    transcript::{OrTranscript, OR_LABELS},
+};
+
+/// OrWitness. This enum holds the witness of the statement that the prover knows.
+#[derive(Clone, Copy, Debug)]
+pub enum OrWitness<WA, WB> {
+    /// Left: the witness of the first statement.
+    Left(WA),
+    /// Right: the witness of the second statement.
+    Right(WB),
+}
+
+/// OrProof. This struct acts as a container for a proof that one of two statements holds.
+/// New proof objects can be made via the `create` function, whereas existing
+/// proofs may be verified via the `verify` function.
+pub struct OrProof<P: PedersenConfig, A: SigmaProtocol<P>, B: SigmaProtocol<P>> {
+    /// chal_a: the challenge of the proof of the first statement. The challenge of the proof
+    /// of the second statement is the transcript challenge minus `chal_a`.
+    pub chal_a: <P as CurveConfig>::ScalarField,
+    /// a: the proof of the first statement.
+    pub a: A,
+    /// b: the proof of the second statement.
+    pub b: B,
+}
+
+impl<P: PedersenConfig, A: SigmaProtocol<P>, B: SigmaProtocol<P>> OrProof<P, A, B> {
+    /// challenge. This function returns the challenge of the OR proof from `transcript`.
+    /// # Arguments
+    /// * `transcript` - the transcript object.
+    fn challenge(transcript: &mut Transcript) -> <P as CurveConfig>::ScalarField {
+        <P as PedersenConfig>::make_challenge_from_buffer(
+            &transcript.challenge_scalar(OR_LABELS.challenge),
+        )
+    }
+
+    /// create. This function returns a new proof that `sa` or `sb` holds, using the witness of
+    /// one of them.
+    /// # Arguments
+    /// * `transcript` - the transcript object that is modified.
+    /// * `rng` - the RNG that is used to produce the random values. Must be cryptographically secure.
+    /// * `sa` - the first statement.
+    /// * `sb` - the second statement.
+    /// * `witness` - the witness of either `sa` or `sb`.
+    pub fn create<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        sa: &A::Statement,
+        sb: &B::Statement,
+        witness: &OrWitness<A::Witness, B::Witness>,
+    ) -> Self {
+        // N.B The proofs are added to the transcript in the same order (a, then b), whichever
+        // one is simulated, so that the verifier does not learn which one is.
+        transcript.domain_sep();
+        match witness {
+            OrWitness::Left(wa) => {
+                let inter = A::create_intermediates(transcript, rng, sa, wa);
+                let chal_b = <P as CurveConfig>::ScalarField::rand(rng);
+                let b = B::simulate(rng, sb, &chal_b);
+                b.add_proof_to_transcript(transcript, sb);
+
+                let chal_a = Self::challenge(transcript) - chal_b;
+                let a = A::create_proof_with_challenge(&inter, sa, wa, &chal_a);
+                Self { chal_a, a, b }
+            }
+            OrWitness::Right(wb) => {
+                let chal_a = <P as CurveConfig>::ScalarField::rand(rng);
+                let a = A::simulate(rng, sa, &chal_a);
+                a.add_proof_to_transcript(transcript, sa);
+                let inter = B::create_intermediates(transcript, rng, sb, wb);
+
+                let chal_b = Self::challenge(transcript) - chal_a;
+                let b = B::create_proof_with_challenge(&inter, sb, wb, &chal_b);
+                Self { chal_a, a, b }
+            }
+        }
+    }
+
+    /// verify. This function returns true if the proof held by `self` shows that `sa` or `sb`
+    /// holds, and false otherwise.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `transcript` - the transcript object that's used.
+    /// * `sa` - the first statement.
+    /// * `sb` - the second statement.
+    pub fn verify(
+        &self,
+        transcript: &mut Transcript,
+        sa: &A::Statement,
+        sb: &B::Statement,
+    ) -> bool {
+        transcript.domain_sep();
+        self.a.add_proof_to_transcript(transcript, sa);
+        self.b.add_proof_to_transcript(transcript, sb);
+
+        let chal_b = Self::challenge(transcript) - self.chal_a;
+        self.a.verify_with_challenge(sa, &self.chal_a) && self.b.verify_with_challenge(sb, &chal_b)
+    }
+}
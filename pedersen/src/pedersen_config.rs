@@ -4,12 +4,15 @@ use ark_ec::{
     AffineRepr, CurveGroup,
 };
 
+use ark_bulletproofs::msm;
+use ark_ff::PrimeField;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::Zero;
 use ark_std::{ops::Mul, UniformRand};
 use rand::{CryptoRng, RngCore};
 use std::default::Default;
 use std::ops;
+use std::sync::Arc;
 
 use sha3::digest::{ExtendableOutput, Update, XofReader};
 use sha3::Shake256;
@@ -33,6 +36,10 @@ pub trait PedersenConfig: SWCurveConfig {
     ///
     /// This function takes an `x` in OCurve's ScalarField and converts it
     /// into an element of the ScalarField of the current curve.
+    /// N.B The default implementation goes through `num_bigint::BigUint`, and so does not run in
+    /// constant time. The implementations that `derive_conversion!` generates for this and for
+    /// the other conversions use the constant-time functions in `crate::ct`, which reduce
+    /// the value modulo the order of the target field.
     ///
     /// # Arguments
     ///
@@ -86,16 +93,34 @@ pub trait PedersenConfig: SWCurveConfig {
 
     fn from_u64_to_sf(x: u64) -> <Self as CurveConfig>::ScalarField;
 
+    /// make_blind. This function returns a random element of Self::ScalarField, for use as the
+    /// randomness of a commitment.
+    /// N.B The default implementation is arkworks' rejection sampling, whose comparisons with
+    /// the modulus do not run in constant time. `derive_conversion!` overrides it with
+    /// `crate::ct::rand`.
+    /// # Arguments
+    /// * `rng` - the random number generator that is being used.
+    ///   This must be a cryptographically secure RNG.
+    fn make_blind<T: RngCore + CryptoRng>(rng: &mut T) -> <Self as CurveConfig>::ScalarField {
+        <Self as CurveConfig>::ScalarField::rand(rng)
+    }
+
     /// This function accepts a challenge slice (ideally produced by a transcript)
     /// and converts it into an element of Self::ScalarField.
     /// This function exists primarily to circumvent an API issue with Merlin.
+    /// Only as many bytes as are needed to encode a scalar are used, and the result
+    /// is reduced modulo the group order, so curves whose scalars are wider than the
+    /// challenge buffer (e.g. T521) are also supported.
     ///
     /// * `chal_buf` - a slice of bytes (representing a serialised curve point),
     ///   to be used to make a element of the ScalarField.
     ///
     /// Returns a scalar field element.
     fn make_challenge_from_buffer(chal_buf: &[u8]) -> <Self as CurveConfig>::ScalarField {
-        <Self as CurveConfig>::ScalarField::deserialize_compressed(chal_buf).unwrap()
+        let size = (<Self as CurveConfig>::ScalarField::MODULUS_BIT_SIZE as usize).div_ceil(8);
+        <Self as CurveConfig>::ScalarField::from_le_bytes_mod_order(
+            &chal_buf[..size.min(chal_buf.len())],
+        )
     }
 
     /// This function accepts an element `val` of OCurve::BaseField and
@@ -306,15 +331,17 @@ pub trait PedersenConfig: SWCurveConfig {
 }
 
 /// Generators. This structure holds the generators for a multi-commitment.
+/// The generators are shared behind an `Arc`, since the same generators are held by every
+/// token, state and message of a commitment: cloning them is a reference count bump.
 #[derive(CanonicalSerialize, CanonicalDeserialize)]
 pub struct Generators<P: PedersenConfig> {
-    pub generators: Vec<sw::Affine<P>>,
+    pub generators: Arc<Vec<sw::Affine<P>>>,
 }
 
 impl<P: PedersenConfig> Clone for Generators<P> {
     fn clone(&self) -> Self {
         Generators {
-            generators: self.generators.clone(),
+            generators: Arc::clone(&self.generators),
         }
     }
 }
@@ -322,7 +349,7 @@ impl<P: PedersenConfig> Clone for Generators<P> {
 impl<P: PedersenConfig> Default for Generators<P> {
     fn default() -> Self {
         Self {
-            generators: Vec::new(), // Default to an empty vector
+            generators: Arc::new(Vec::new()), // Default to an empty vector
         }
     }
 }
@@ -505,7 +532,7 @@ impl<P: PedersenConfig> PedersenComm<P> {
         // Returns a new Pedersen commitment using fixed generators.
         // N.B First check that `g != q`.
         assert!(g != q);
-        let r = <P as CurveConfig>::ScalarField::rand(rng);
+        let r = P::make_blind(rng);
         Self {
             comm: (g.mul(x) + q.mul(r)).into_affine(),
             r,
@@ -551,7 +578,7 @@ impl<P: PedersenConfig> PedersenComm<P> {
         // Returns a new multi pedersen commitment using fixed generators.
         // N.B First check that `g != q`.
         assert!(g != q);
-        let r = <P as CurveConfig>::ScalarField::rand(rng);
+        let r = P::make_blind(rng);
 
         let label = [b'G', 0, 0, 0, 0];
         let mut shake = Shake256::default();
@@ -581,7 +608,9 @@ impl<P: PedersenConfig> PedersenComm<P> {
                 comm: (total + q.mul(r)).into_affine(),
                 r,
             },
-            Generators { generators: gens },
+            Generators {
+                generators: Arc::new(gens),
+            },
         )
     }
 
@@ -642,7 +671,9 @@ impl<P: PedersenConfig> PedersenComm<P> {
                 comm: (total + P::GENERATOR2.mul(r)).into_affine(),
                 r,
             },
-            Generators { generators: gens },
+            Generators {
+                generators: Arc::new(gens),
+            },
         )
     }
 
@@ -662,7 +693,7 @@ impl<P: PedersenConfig> PedersenComm<P> {
         gens: &Generators<P>,
     ) -> Self {
         // Returns a new multi pedersen commitment using fixed generators.
-        let r = <P as CurveConfig>::ScalarField::rand(rng);
+        let r = P::make_blind(rng);
 
         let mut total: sw::Affine<P> = sw::Affine::identity();
         for (i, item) in vals.iter().enumerate() {
@@ -691,13 +722,12 @@ impl<P: PedersenConfig> PedersenComm<P> {
         r: <P as CurveConfig>::ScalarField,
         gens: &Generators<P>,
     ) -> bool {
-        let mut total: sw::Affine<P> = sw::Affine::identity();
-
-        for (i, item) in vals.iter().enumerate() {
-            total = (total + gens.generators[i].mul(item)).into();
-        }
+        let mut bases = gens.generators[..vals.len()].to_vec();
+        let mut scalars = vals.to_vec();
+        bases.push(P::GENERATOR2);
+        scalars.push(r);
 
-        let recomputed_commitment = (total + P::GENERATOR2.mul(r)).into_affine();
+        let recomputed_commitment = msm::msm(&bases, &scalars).unwrap().into_affine();
         recomputed_commitment == self.comm
     }
 
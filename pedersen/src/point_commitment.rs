@@ -0,0 +1,227 @@
+//! Defines commitments to points on the OCurve, along with a proof that the committed point is on the curve.
+//!
+//! A `PointCommitment` holds a Pedersen commitment to each coordinate of a point (x, y) on the OCurve.
+//! An `OnCurveProof` shows that the committed coordinates satisfy the curve equation
+//! y^2 = x^3 + ax + b, without revealing the point. The proof commits to x^2, x^3 and y^2, proves
+//! each of these with a multiplication proof, and then uses an equality proof to show that
+//! the commitment to y^2 and the commitment to x^3 + ax + b (which the verifier can compute
+//! from the other commitments) hold the same value.
+//!
+//! N.B The curve equation is checked over the ScalarField of the current curve, so these proofs
+//! are only meaningful if the BaseField of the OCurve is that same field (e.g T256 and P256).
+
+use ark_ec::{
+    short_weierstrass::{self as sw, SWCurveConfig},
+    CurveConfig, CurveGroup,
+};
+use merlin::Transcript;
+
+use ark_serialize::CanonicalSerialize;
+use ark_std::ops::Mul;
+use rand::{CryptoRng, RngCore};
+
+use crate::{
+    equality_protocol::EqualityProof, mul_protocol::MulProof, pedersen_config::PedersenComm,
+    pedersen_config::PedersenConfig, transcript::PointCommitmentTranscript,
+};
+
+/// PointCommitment. This struct acts as a container for commitments to the coordinates of a point
+/// on the OCurve. Like `PedersenComm`, this struct carries the randomness of each commitment, so it
+/// should only be held by the prover. Any serialised proofs should solely use the `comm` fields.
+pub struct PointCommitment<P: PedersenConfig> {
+    /// x: the commitment to the x co-ordinate.
+    pub x: PedersenComm<P>,
+    /// y: the commitment to the y co-ordinate.
+    pub y: PedersenComm<P>,
+}
+
+// We need to implement these manually for generic structs.
+impl<P: PedersenConfig> Copy for PointCommitment<P> {}
+impl<P: PedersenConfig> Clone for PointCommitment<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P: PedersenConfig> PointCommitment<P> {
+    /// new. This function returns a new commitment to the co-ordinates of `point`.
+    /// This function panics if `point` is the point at infinity, as it has no affine co-ordinates.
+    /// # Arguments
+    /// * `point` - the point that is being committed to.
+    /// * `rng` - the RNG that is used to produce the randomness. Must be cryptographically secure.
+    pub fn new<T: RngCore + CryptoRng>(point: &sw::Affine<P::OCurve>, rng: &mut T) -> Self {
+        assert!(!point.infinity);
+        Self {
+            x: P::make_commitment_from_other(point.x, rng),
+            y: P::make_commitment_from_other(point.y, rng),
+        }
+    }
+
+    /// comms. Returns the commitments to the x and y co-ordinates as points.
+    pub fn comms(&self) -> (sw::Affine<P>, sw::Affine<P>) {
+        (self.x.comm, self.y.comm)
+    }
+}
+
+/// OnCurveProof. This struct acts as a container for a proof that a `PointCommitment` is a
+/// commitment to a point on the OCurve.
+/// Essentially, a new proof object can be created by calling `create`, whereas
+/// an existing proof can be verified by calling `verify`.
+pub struct OnCurveProof<P: PedersenConfig> {
+    /// c_x2: the commitment to x^2.
+    pub c_x2: sw::Affine<P>,
+    /// c_x3: the commitment to x^3.
+    pub c_x3: sw::Affine<P>,
+    /// c_y2: the commitment to y^2.
+    pub c_y2: sw::Affine<P>,
+    /// mp_x2: the proof that c_x2 is a commitment to x * x.
+    pub mp_x2: MulProof<P>,
+    /// mp_x3: the proof that c_x3 is a commitment to x^2 * x.
+    pub mp_x3: MulProof<P>,
+    /// mp_y2: the proof that c_y2 is a commitment to y * y.
+    pub mp_y2: MulProof<P>,
+    /// eq: the proof that c_y2 and c_x3 + a * c_x + b * g are commitments to the same value.
+    pub eq: EqualityProof<P>,
+}
+
+impl<P: PedersenConfig> OnCurveProof<P> {
+    /// coeffs. Returns the curve coefficients `a` and `b` of the OCurve as elements of the ScalarField.
+    fn coeffs() -> (
+        <P as CurveConfig>::ScalarField,
+        <P as CurveConfig>::ScalarField,
+    ) {
+        (
+            P::from_ob_to_sf(<P::OCurve as SWCurveConfig>::COEFF_A),
+            P::from_ob_to_sf(<P::OCurve as SWCurveConfig>::COEFF_B),
+        )
+    }
+
+    /// make_rhs. Returns the commitment to x^3 + ax + b, i.e c_x3 + a * c_x + b * g.
+    /// # Arguments
+    /// * `c_x` - the commitment to x.
+    /// * `c_x3` - the commitment to x^3.
+    fn make_rhs(c_x: &sw::Affine<P>, c_x3: &sw::Affine<P>) -> sw::Affine<P> {
+        let (a, b) = Self::coeffs();
+        (c_x.mul(a) + c_x3 + <P as SWCurveConfig>::GENERATOR.mul(b)).into_affine()
+    }
+
+    /// make_transcript. This function simply adds the commitments to the co-ordinates and to
+    /// x^2, x^3 and y^2 to the `transcript` object.
+    /// # Arguments
+    /// * `transcript` - the transcript which is modified.
+    /// * `c_x` - the commitment to x.
+    /// * `c_y` - the commitment to y.
+    /// * `c_x2` - the commitment to x^2.
+    /// * `c_x3` - the commitment to x^3.
+    /// * `c_y2` - the commitment to y^2.
+    pub fn make_transcript(
+        transcript: &mut Transcript,
+        c_x: &sw::Affine<P>,
+        c_y: &sw::Affine<P>,
+        c_x2: &sw::Affine<P>,
+        c_x3: &sw::Affine<P>,
+        c_y2: &sw::Affine<P>,
+    ) {
+        transcript.domain_sep();
+
+        let mut compressed_bytes = Vec::new();
+        for (label, point) in [
+            (b"C_x" as &'static [u8], c_x),
+            (b"C_y", c_y),
+            (b"C_x2", c_x2),
+            (b"C_x3", c_x3),
+            (b"C_y2", c_y2),
+        ] {
+            compressed_bytes.clear();
+            point.serialize_compressed(&mut compressed_bytes).unwrap();
+            transcript.append_point(label, &compressed_bytes[..]);
+        }
+    }
+
+    /// create. This function returns a new proof that `comm` is a commitment to `point`, and
+    /// hence that the committed point is on the OCurve.
+    /// # Arguments
+    /// * `transcript` - the transcript object that is modified.
+    /// * `rng` - the RNG that is used to produce the random values. Must be cryptographically secure.
+    /// * `point` - the committed point.
+    /// * `comm` - the commitment to `point`.
+    pub fn create<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        point: &sw::Affine<P::OCurve>,
+        comm: &PointCommitment<P>,
+    ) -> Self {
+        let x = P::from_ob_to_sf(point.x);
+        let y = P::from_ob_to_sf(point.y);
+        let x2 = x * x;
+        let x3 = x2 * x;
+        let y2 = y * y;
+
+        let c_x2 = PedersenComm::new(x2, rng);
+        let c_x3 = PedersenComm::new(x3, rng);
+        let c_y2 = PedersenComm::new(y2, rng);
+
+        Self::make_transcript(
+            transcript,
+            &comm.x.comm,
+            &comm.y.comm,
+            &c_x2.comm,
+            &c_x3.comm,
+            &c_y2.comm,
+        );
+
+        let mp_x2 = MulProof::create(transcript, rng, &x, &x, &comm.x, &comm.x, &c_x2);
+        let mp_x3 = MulProof::create(transcript, rng, &x2, &x, &c_x2, &comm.x, &c_x3);
+        let mp_y2 = MulProof::create(transcript, rng, &y, &y, &comm.y, &comm.y, &c_y2);
+
+        // The commitment to x^3 + ax + b, with the randomness that the verifier's copy carries.
+        let (a, b) = Self::coeffs();
+        let rhs = PedersenComm::new_with_both(x3 + a * x + b, c_x3.r + a * comm.x.r);
+        let eq = EqualityProof::create(transcript, rng, &c_y2, &rhs);
+
+        Self {
+            c_x2: c_x2.comm,
+            c_x3: c_x3.comm,
+            c_y2: c_y2.comm,
+            mp_x2,
+            mp_x3,
+            mp_y2,
+            eq,
+        }
+    }
+
+    /// verify. This function returns true if the proof held by `self` is valid, and false otherwise.
+    /// In other words, this function returns true if `c_x` and `c_y` are commitments to the
+    /// co-ordinates of a point on the OCurve.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `transcript` - the transcript object that's used.
+    /// * `c_x` - the commitment to x.
+    /// * `c_y` - the commitment to y.
+    pub fn verify(
+        &self,
+        transcript: &mut Transcript,
+        c_x: &sw::Affine<P>,
+        c_y: &sw::Affine<P>,
+    ) -> bool {
+        Self::make_transcript(transcript, c_x, c_y, &self.c_x2, &self.c_x3, &self.c_y2);
+
+        self.mp_x2.verify(transcript, c_x, c_x, &self.c_x2)
+            && self.mp_x3.verify(transcript, &self.c_x2, c_x, &self.c_x3)
+            && self.mp_y2.verify(transcript, c_y, c_y, &self.c_y2)
+            && self
+                .eq
+                .verify(transcript, &self.c_y2, &Self::make_rhs(c_x, &self.c_x3))
+    }
+
+    /// serialized_size. Returns the number of bytes needed to represent this proof object once serialised.
+    pub fn serialized_size(&self) -> usize {
+        self.c_x2.compressed_size()
+            + self.c_x3.compressed_size()
+            + self.c_y2.compressed_size()
+            + self.mp_x2.serialized_size()
+            + self.mp_x3.serialized_size()
+            + self.mp_y2.serialized_size()
+            + self.eq.serialized_size()
+    }
+}
@@ -8,6 +8,12 @@
 //! The proof used here follows the same notation as
 //! https://eprint.iacr.org/2017/1132.pdf, Appendix A1 (the "Proving a product
 //! relationship").
+//!
+//! This module also provides `ChainedProductProof`, which proves that C_P
+//! commits to the product x_1*...*x_k of the values committed in C_1, ..., C_k,
+//! e.g. for multiplicative reward formulas. The prover commits to each partial
+//! product x_1*...*x_i internally, and proves each step of the chain with a
+//! `ProductProof`.
 
 use ark_ec::{
     short_weierstrass::{self as sw},
@@ -20,7 +26,9 @@ use ark_std::{ops::Mul, UniformRand};
 use rand::{CryptoRng, RngCore};
 
 use crate::{
-    pedersen_config::PedersenComm, pedersen_config::PedersenConfig, transcript::OpeningTranscript,
+    pedersen_config::PedersenComm,
+    pedersen_config::PedersenConfig,
+    transcript::{witness_rng, ProductTranscript, PRODUCT_LABELS},
 };
 
 /// ProductProof. This struct acts as a container for a ProductProof.
@@ -261,7 +269,7 @@ impl<P: PedersenConfig> ProductProof<P> {
         // Now call the routine that returns the "challenged" version.
         // N.B For the sake of compatibility, here we just pass the buffer
         // itself.
-        let chal_buf = transcript.challenge_scalar(b"c");
+        let chal_buf = transcript.challenge_scalar(PRODUCT_LABELS.challenge);
         Self::create_proof(x, y, &inter, cx, cy, cxy, &chal_buf)
     }
 
@@ -281,6 +289,9 @@ impl<P: PedersenConfig> ProductProof<P> {
         cy: &PedersenComm<P>,
         cxy: &PedersenComm<P>,
     ) -> ProductProofIntermediate<P> {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(transcript, &[cx.r, cy.r, cxy.r], rng);
+
         let b1 = <P as CurveConfig>::ScalarField::rand(rng);
         let b2 = <P as CurveConfig>::ScalarField::rand(rng);
         let b3 = <P as CurveConfig>::ScalarField::rand(rng);
@@ -355,7 +366,7 @@ impl<P: PedersenConfig> ProductProof<P> {
         cy: &PedersenComm<P>,
         cxy: &PedersenComm<P>,
     ) -> Self {
-        let chal_buf = transcript.challenge_scalar(b"c");
+        let chal_buf = transcript.challenge_scalar(PRODUCT_LABELS.challenge);
         Self::create_proof(x, y, inter, cx, cy, cxy, &chal_buf)
     }
 
@@ -460,7 +471,12 @@ impl<P: PedersenConfig> ProductProof<P> {
         cy: &sw::Affine<P>,
         cxy: &sw::Affine<P>,
     ) -> bool {
-        self.verify_proof(cx, cy, cxy, &transcript.challenge_scalar(b"c")[..])
+        self.verify_proof(
+            cx,
+            cy,
+            cxy,
+            &transcript.challenge_scalar(PRODUCT_LABELS.challenge)[..],
+        )
     }
 
     /// verify_proof. This function verifies that `c1` is a valid opening of the
@@ -523,6 +539,106 @@ impl<P: PedersenConfig> ProductProof<P> {
     }
 }
 
+/// ChainedProductProof. This struct acts as a container for a proof that a
+/// commitment opens to the product of the values held by k >= 2 commitments.
+/// A new proof object can be created by calling `create`, whereas an existing
+/// proof can be verified by calling `verify`.
+pub struct ChainedProductProof<P: PedersenConfig> {
+    /// intermediates: the commitments to the partial products x_1*x_2, ...,
+    /// x_1*...*x_{k-1}.
+    pub intermediates: Vec<sw::Affine<P>>,
+    /// proofs: the product proofs of each step of the chain, i.e the i-th proof
+    /// shows that the (i+1)-th partial product is the i-th one times x_{i+2}.
+    pub proofs: Vec<ProductProof<P>>,
+}
+
+impl<P: PedersenConfig> ChainedProductProof<P> {
+    /// create. This function returns a new chained product proof that `cprod`
+    /// commits to the product of `values`, which are committed in `comms`.
+    /// The commitments to the partial products are made internally.
+    /// This function panics if fewer than two values are passed, or if
+    /// `values` and `comms` differ in length.
+    /// # Arguments
+    /// * `transcript` - the transcript object that is modified.
+    /// * `rng` - the RNG that is used to produce the random values.
+    ///   Must be cryptographically secure.
+    /// * `values` - the values whose product is proved.
+    /// * `comms` - the commitments to `values`.
+    /// * `cprod` - the commitment to the product of `values`.
+    pub fn create<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        values: &[<P as CurveConfig>::ScalarField],
+        comms: &[PedersenComm<P>],
+        cprod: &PedersenComm<P>,
+    ) -> Self {
+        assert!(values.len() >= 2);
+        assert_eq!(values.len(), comms.len());
+
+        let k = values.len();
+        let mut intermediates = Vec::with_capacity(k - 2);
+        let mut proofs = Vec::with_capacity(k - 1);
+
+        // acc and c_acc hold the current partial product and its commitment.
+        let mut acc = values[0];
+        let mut c_acc = comms[0];
+        for i in 1..k {
+            let next = acc * values[i];
+            let c_next = if i == k - 1 {
+                *cprod
+            } else {
+                let c_next = PedersenComm::new(next, rng);
+                intermediates.push(c_next.comm);
+                c_next
+            };
+
+            proofs.push(ProductProof::create(
+                transcript, rng, &acc, &values[i], &c_acc, &comms[i], &c_next,
+            ));
+            acc = next;
+            c_acc = c_next;
+        }
+
+        Self {
+            intermediates,
+            proofs,
+        }
+    }
+
+    /// verify. This function returns true if the proof held by `self` shows
+    /// that `cprod` commits to the product of the values committed in
+    /// `comms`, and false otherwise.
+    /// # Arguments
+    /// * `self` - the proof that is being verified.
+    /// * `transcript` - the transcript object that's used.
+    /// * `comms` - the commitments to the multiplied values.
+    /// * `cprod` - the commitment to the product.
+    pub fn verify(
+        &self,
+        transcript: &mut Transcript,
+        comms: &[sw::Affine<P>],
+        cprod: &sw::Affine<P>,
+    ) -> bool {
+        if comms.len() < 2
+            || self.proofs.len() != comms.len() - 1
+            || self.intermediates.len() != comms.len() - 2
+        {
+            return false;
+        }
+
+        let mut c_acc = comms[0];
+        for (i, proof) in self.proofs.iter().enumerate() {
+            let c_next = self.intermediates.get(i).unwrap_or(cprod);
+            if !proof.verify(transcript, &c_acc, &comms[i + 1], c_next) {
+                return false;
+            }
+            c_acc = *c_next;
+        }
+
+        true
+    }
+}
+
 /*#[cfg(test)]
 mod tests {
     use ark_secp256k1::{Fr as ScalarField, Config};
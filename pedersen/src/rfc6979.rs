@@ -0,0 +1,103 @@
+//! Deterministic ECDSA nonce generation. This file contains an implementation of the nonce
+//! derivation from RFC6979, Section 3.2, over any prime field. This is the derivation that
+//! most hardware signers (e.g. secure elements) use, so ECDSA witnesses produced from it
+//! match the signatures that such devices emit.
+
+use ark_ff::{BigInteger, PrimeField};
+use hmac::{
+    digest::{core_api::BlockSizeUser, Digest},
+    Mac, SimpleHmac,
+};
+use num_bigint::BigUint;
+
+/// modulus. Returns the modulus of `F` as a BigUint.
+fn modulus<F: PrimeField>() -> BigUint {
+    BigUint::from_bytes_le(&F::MODULUS.to_bytes_le())
+}
+
+/// bits2int. This function implements the bits2int transform from RFC6979, Section 2.3.2:
+/// the big-endian `bytes` are read as an integer and truncated to the bit length of the
+/// modulus of `F`.
+/// # Arguments
+/// * `bytes` - the input bytes.
+fn bits2int<F: PrimeField>(bytes: &[u8]) -> BigUint {
+    let qlen = F::MODULUS_BIT_SIZE as usize;
+    let blen = bytes.len() * 8;
+    let v = BigUint::from_bytes_be(bytes);
+    if blen > qlen {
+        v >> (blen - qlen)
+    } else {
+        v
+    }
+}
+
+/// int2octets. This function implements the int2octets transform from RFC6979, Section 2.3.3:
+/// `v` is written as a big-endian string of exactly ceil(qlen / 8) bytes.
+/// # Arguments
+/// * `v` - the integer to encode. This must be smaller than the modulus of `F`.
+fn int2octets<F: PrimeField>(v: &BigUint) -> Vec<u8> {
+    let rlen = (F::MODULUS_BIT_SIZE as usize).div_ceil(8);
+    let bytes = v.to_bytes_be();
+    let mut out = vec![0u8; rlen - bytes.len()];
+    out.extend_from_slice(&bytes);
+    out
+}
+
+/// hmac. Returns HMAC_K(parts[0] || parts[1] || ...).
+fn hmac<D: Digest + BlockSizeUser + Clone>(k: &[u8], parts: &[&[u8]]) -> Vec<u8> {
+    let mut mac = <SimpleHmac<D> as Mac>::new_from_slice(k).expect("HMAC accepts any key length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// bits2field. This function maps the message hash `h` to an element of `F` in the same way
+/// that ECDSA does, i.e. it returns bits2int(h) mod q.
+/// # Arguments
+/// * `h` - the hash of the message.
+pub fn bits2field<F: PrimeField>(h: &[u8]) -> F {
+    F::from_le_bytes_mod_order(&bits2int::<F>(h).to_bytes_le())
+}
+
+/// generate_k. This function derives the ECDSA nonce for the secret key `x` and the
+/// message hash `h` as specified in RFC6979, Section 3.2, using HMAC-`D`.
+/// Note that `D` should be the hash function that was used to produce `h`.
+/// # Arguments
+/// * `x` - the secret key.
+/// * `h` - the hash of the message.
+pub fn generate_k<F: PrimeField, D: Digest + BlockSizeUser + Clone>(x: &F, h: &[u8]) -> F {
+    let q = modulus::<F>();
+    let qlen = F::MODULUS_BIT_SIZE as usize;
+
+    // Steps a-c: set up the key material and the initial state.
+    let x_bytes = int2octets::<F>(&BigUint::from_bytes_le(&x.into_bigint().to_bytes_le()));
+    let h_bytes = int2octets::<F>(&(bits2int::<F>(h) % &q));
+
+    let hlen = <D as Digest>::output_size();
+    let mut v = vec![0x01u8; hlen];
+    let mut k = vec![0x00u8; hlen];
+
+    // Steps d-g.
+    k = hmac::<D>(&k, &[&v, &[0x00], &x_bytes, &h_bytes]);
+    v = hmac::<D>(&k, &[&v]);
+    k = hmac::<D>(&k, &[&v, &[0x01], &x_bytes, &h_bytes]);
+    v = hmac::<D>(&k, &[&v]);
+
+    // Step h: generate candidates until one lies in [1, q - 1].
+    loop {
+        let mut t = Vec::with_capacity(qlen.div_ceil(8) + hlen);
+        while t.len() * 8 < qlen {
+            v = hmac::<D>(&k, &[&v]);
+            t.extend_from_slice(&v);
+        }
+
+        let candidate = bits2int::<F>(&t);
+        if candidate > BigUint::from(0u8) && candidate < q {
+            return F::from_le_bytes_mod_order(&candidate.to_bytes_le());
+        }
+
+        k = hmac::<D>(&k, &[&v, &[0x00]]);
+        v = hmac::<D>(&k, &[&v]);
+    }
+}
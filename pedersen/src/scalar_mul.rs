@@ -183,4 +183,19 @@ pub trait ScalarMulProtocol<P: PedersenConfig> {
         c2: &sw::Affine<P>,
         c3: &sw::Affine<P>,
     );
+
+    /// add_intermediate_to_transcript. This function adds the commitments held in `inter` to the
+    /// `transcript` in exactly the same order as `create_intermediates_with_existing_commitments` does.
+    /// This allows intermediates to be built against a scratch transcript and merged in afterwards.
+    ///
+    /// # Arguments
+    /// * `inter` - the intermediate values.
+    /// * `transcript` - the transcript object.
+    fn add_intermediate_to_transcript(
+        inter: &Self::Intermediate,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P::OCurve>,
+        c2: &sw::Affine<P>,
+        c3: &sw::Affine<P>,
+    );
 }
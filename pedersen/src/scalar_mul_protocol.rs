@@ -24,7 +24,7 @@ use crate::{
     pedersen_config::PedersenConfig,
     point_add::PointAddProtocol,
     scalar_mul::ScalarMulProtocol,
-    transcript::ECScalarMulTranscript,
+    transcript::{witness_rng, ECScalarMulTranscript, EC_SCALAR_MUL_LABELS},
 };
 
 /// ECScalarMulProofTranscriptable. This trait provides a notion of `Transcriptable` which implies that
@@ -140,7 +140,7 @@ impl<P: PedersenConfig> ScalarMulProtocol<P> for ECScalarMulProof<P> {
     }
 
     fn challenge_scalar(transcript: &mut Transcript) -> [u8; 64] {
-        ECScalarMulTranscript::challenge_scalar(transcript, b"c")
+        ECScalarMulTranscript::challenge_scalar(transcript, EC_SCALAR_MUL_LABELS.challenge)
     }
 
     /// make_intermediate_transcript. This function accept a set of intermediate values (`inter`)
@@ -181,10 +181,13 @@ impl<P: PedersenConfig> ScalarMulProtocol<P> for ECScalarMulProof<P> {
         lambda: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
         p: &sw::Affine<<P as PedersenConfig>::OCurve>,
         c1: &sw::Affine<P::OCurve>,
-        _r1: &<P::OCurve as CurveConfig>::ScalarField,
+        r1: &<P::OCurve as CurveConfig>::ScalarField,
         c2: &PedersenComm<P>,
         c3: &PedersenComm<P>,
     ) -> ECScalarMulProofIntermediate<P> {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(transcript, &((*s, *p), *lambda, *r1, [c2.r, c3.r]), rng);
+
         // First we make the unique alpha value. We repeat until we've
         // found the right value. Note that whp we do not expect this loop to repeat
         // many times, as the probability of choosing α ∈ {0, λ, 2λ} is pretty small.
@@ -376,6 +379,18 @@ impl<P: PedersenConfig> ScalarMulProtocol<P> for ECScalarMulProof<P> {
     ) {
         self.add_to_transcript(transcript, c1, c2, c3);
     }
+
+    /// add_intermediate_to_transcript. This function adds the intermediate values in `inter`
+    /// to the `transcript`.
+    fn add_intermediate_to_transcript(
+        inter: &Self::Intermediate,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P::OCurve>,
+        c2: &sw::Affine<P>,
+        c3: &sw::Affine<P>,
+    ) {
+        inter.add_to_transcript(transcript, c1, c2, c3);
+    }
 }
 
 impl<P: PedersenConfig> ECScalarMulProof<P> {
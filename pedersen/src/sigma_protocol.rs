@@ -0,0 +1,81 @@
+//! This file defines a generic trait for sigma protocols.
+//! More broadly, this trait captures the three moves of a sigma protocol (the commitments, the
+//! challenge and the responses) alongside a simulator, so that proofs can be composed without
+//! bespoke code for each pair of protocols (see e.g `or_protocol`).
+
+use ark_ec::CurveConfig;
+use merlin::Transcript;
+use rand::{CryptoRng, RngCore};
+
+use crate::pedersen_config::PedersenConfig;
+
+pub trait SigmaProtocol<P: PedersenConfig>: Sized {
+    /// Statement. This type is the public statement of the proof, e.g the commitments that it is about.
+    type Statement;
+
+    /// Witness. This type is the secret that the prover knows about the statement.
+    type Witness;
+
+    /// Intermediate. This type holds the prover's values before the challenge is known.
+    type Intermediate;
+
+    /// create_intermediates. This function returns the intermediate values of a proof of
+    /// `statement`, and adds the commitments of the proof to the `transcript`.
+    /// # Arguments
+    /// * `transcript` - the transcript object that is modified.
+    /// * `rng` - the random number generator. This must be a cryptographically secure RNG.
+    /// * `statement` - the statement that is proved.
+    /// * `witness` - the witness of the statement.
+    fn create_intermediates<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        statement: &Self::Statement,
+        witness: &Self::Witness,
+    ) -> Self::Intermediate;
+
+    /// create_proof_with_challenge. This function returns a proof of `statement` from the
+    /// intermediate values held in `inter`, using the challenge `chal`.
+    /// # Arguments
+    /// * `inter` - the intermediates. These should have been produced by a call to `create_intermediates`.
+    /// * `statement` - the statement that is proved.
+    /// * `witness` - the witness of the statement.
+    /// * `chal` - the challenge.
+    fn create_proof_with_challenge(
+        inter: &Self::Intermediate,
+        statement: &Self::Statement,
+        witness: &Self::Witness,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> Self;
+
+    /// simulate. This function returns a proof of `statement` that verifies under the challenge
+    /// `chal`, without any witness. This is only possible because `chal` is known in advance.
+    /// # Arguments
+    /// * `rng` - the random number generator. This must be a cryptographically secure RNG.
+    /// * `statement` - the statement that is simulated.
+    /// * `chal` - the challenge.
+    fn simulate<T: RngCore + CryptoRng>(
+        rng: &mut T,
+        statement: &Self::Statement,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> Self;
+
+    /// add_proof_to_transcript. This function adds the commitments of the proof held by `self` to the
+    /// `transcript`, in the same way as `create_intermediates` does.
+    /// # Arguments
+    /// * `self` - the proof object.
+    /// * `transcript` - the transcript object that is modified.
+    /// * `statement` - the statement of the proof.
+    fn add_proof_to_transcript(&self, transcript: &mut Transcript, statement: &Self::Statement);
+
+    /// verify_with_challenge. This function returns true if the proof held by `self` is a valid
+    /// proof of `statement` under the challenge `chal`, and false otherwise.
+    /// # Arguments
+    /// * `self` - the proof object.
+    /// * `statement` - the statement of the proof.
+    /// * `chal` - the challenge.
+    fn verify_with_challenge(
+        &self,
+        statement: &Self::Statement,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> bool;
+}
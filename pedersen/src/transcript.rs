@@ -3,10 +3,249 @@
 //! re-instantiating this type for each different point type that we use, we simply traffic bytes in and out for e.g
 //! appending points or producing challenges. It is the responsibility of the caller to realise this functionality.
 
-use merlin::Transcript;
+use ark_serialize::CanonicalSerialize;
+use merlin::{Transcript, TranscriptRng};
+use rand::{CryptoRng, RngCore};
 
 pub const CHALLENGE_SIZE: usize = 64;
 
+/// ProtocolLabels. The labels that bind the transcript of a proof to its protocol: the domain
+/// separator, which is appended under `dom-sep` before anything else, and the label of the
+/// challenge. Every protocol has its own labels, so that a transcript of one protocol can never
+/// be taken for that of another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProtocolLabels {
+    /// name: the name of the protocol.
+    pub name: &'static str,
+    /// domain: the domain separator, which ends with the version of the protocol.
+    pub domain: &'static [u8],
+    /// challenge: the label of the challenge.
+    pub challenge: &'static [u8],
+}
+
+/// EQUALITY_LABELS. The labels of the `EqualityTranscript` proofs.
+pub const EQUALITY_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "Equality",
+    domain: b"equality-proof/v1",
+    challenge: b"equality-c",
+};
+
+/// OPENING_LABELS. The labels of the `OpeningTranscript` proofs.
+pub const OPENING_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "Opening",
+    domain: b"opening-proof/v1",
+    challenge: b"opening-c",
+};
+
+/// OPENING_MULTI_LABELS. The labels of the `OpeningMultiTranscript` proofs.
+pub const OPENING_MULTI_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "OpeningMulti",
+    domain: b"opening-multi-proof/v1",
+    challenge: b"opening-multi-c",
+};
+
+/// ISSUANCE_LABELS. The labels of the `IssuanceTranscript` proofs.
+pub const ISSUANCE_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "Issuance",
+    domain: b"issuance-proof/v1",
+    challenge: b"issuance-c",
+};
+
+/// MUL_LABELS. The labels of the `MulTranscript` proofs.
+pub const MUL_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "Mul",
+    domain: b"mul-proof/v1",
+    challenge: b"mul-c",
+};
+
+/// ADD_MUL_LABELS. The labels of the `AddMulTranscript` proofs.
+pub const ADD_MUL_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "AddMul",
+    domain: b"add-mul-proof/v1",
+    challenge: b"add-mul-c",
+};
+
+/// ADD_MUL_MULTI_LABELS. The labels of the `AddMulMultiTranscript` proofs.
+pub const ADD_MUL_MULTI_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "AddMulMulti",
+    domain: b"add-mul-multi-proof/v1",
+    challenge: b"add-mul-multi-c",
+};
+
+/// PRODUCT_LABELS. The labels of the `ProductTranscript` proofs.
+pub const PRODUCT_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "Product",
+    domain: b"product-proof/v1",
+    challenge: b"product-c",
+};
+
+/// NON_ZERO_LABELS. The labels of the `NonZeroTranscript` proofs.
+pub const NON_ZERO_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "NonZero",
+    domain: b"non-zero-proof/v1",
+    challenge: b"non-zero-c",
+};
+
+/// EC_POINT_ADDITION_LABELS. The labels of the `ECPointAdditionTranscript` proofs.
+pub const EC_POINT_ADDITION_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "ECPointAddition",
+    domain: b"ec-point-addition-proof/v1",
+    challenge: b"ec-point-addition-c",
+};
+
+/// ZK_ATTEST_EC_POINT_ADDITION_LABELS. The labels of the `ZKAttestECPointAdditionTranscript` proofs.
+pub const ZK_ATTEST_EC_POINT_ADDITION_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "ZKAttestECPointAddition",
+    domain: b"zk-attest-ec-point-addition-proof/v1",
+    challenge: b"zk-attest-ec-point-addition-c",
+};
+
+/// ZK_ATTEST_COMPLETE_EC_POINT_ADDITION_LABELS. The labels of the
+/// `ZKAttestCompleteECPointAdditionTranscript` proofs.
+pub const ZK_ATTEST_COMPLETE_EC_POINT_ADDITION_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "ZKAttestCompleteECPointAddition",
+    domain: b"zk-attest-complete-ec-point-addition-proof/v1",
+    challenge: b"zk-attest-complete-ec-point-addition-c",
+};
+
+/// EC_SCALAR_MUL_LABELS. The labels of the `ECScalarMulTranscript` proofs.
+pub const EC_SCALAR_MUL_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "ECScalarMul",
+    domain: b"ec-point-scalar-mul-proof/v1",
+    challenge: b"ec-point-scalar-mul-c",
+};
+
+/// ZK_ATTEST_EC_SCALAR_MUL_LABELS. The labels of the `ZKAttestECScalarMulTranscript` proofs.
+pub const ZK_ATTEST_EC_SCALAR_MUL_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "ZKAttestECScalarMul",
+    domain: b"zk-attest-ec-point-scalar-mul-proof/v1",
+    challenge: b"zk-attest-ec-point-scalar-mul-c",
+};
+
+/// FS_EC_SCALAR_MUL_LABELS. The labels of the `FSECScalarMulTranscript` proofs.
+pub const FS_EC_SCALAR_MUL_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "FSECScalarMul",
+    domain: b"fs-ec-point-scalar-mul-proof/v1",
+    challenge: b"fs-ec-point-scalar-mul-c",
+};
+
+/// ZK_ATTEST_FS_EC_SCALAR_MUL_LABELS. The labels of the `ZKAttestFSECScalarMulTranscript` proofs.
+pub const ZK_ATTEST_FS_EC_SCALAR_MUL_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "ZKAttestFSECScalarMul",
+    domain: b"fs-zk-attest-ec-point-scalar-mul-proof/v1",
+    challenge: b"fs-zk-attest-ec-point-scalar-mul-c",
+};
+
+/// GK_ZERO_ONE_LABELS. The labels of the `GKZeroOneTranscript` proofs.
+pub const GK_ZERO_ONE_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "GKZeroOne",
+    domain: b"gk-zero-one-proof/v1",
+    challenge: b"gk-zero-one-c",
+};
+
+/// GK_K_ARY_LABELS. The labels of the `GKKAryTranscript` proofs.
+pub const GK_K_ARY_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "GKKAry",
+    domain: b"gk-k-ary-proof/v1",
+    challenge: b"gk-k-ary-c",
+};
+
+/// CONSISTENCY_LABELS. The labels of the `ConsistencyTranscript` proofs.
+pub const CONSISTENCY_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "Consistency",
+    domain: b"consistency-proof/v1",
+    challenge: b"consistency-c",
+};
+
+/// ECDSA_SIGNATURE_LABELS. The labels of the `ECDSASignatureTranscript` proofs.
+pub const ECDSA_SIGNATURE_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "ECDSASignature",
+    domain: b"ecdsa-signature-proof/v1",
+    challenge: b"ecdsa-signature-c",
+};
+
+/// POINT_COMMITMENT_LABELS. The labels of the `PointCommitmentTranscript` proofs.
+pub const POINT_COMMITMENT_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "PointCommitment",
+    domain: b"point-commitment-proof/v1",
+    challenge: b"point-commitment-c",
+};
+
+/// OR_LABELS. The labels of the `OrTranscript` proofs.
+pub const OR_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "Or",
+    domain: b"or-proof/v1",
+    challenge: b"or-c",
+};
+
+/// AND_LABELS. The labels of the `AndTranscript` proofs.
+pub const AND_LABELS: ProtocolLabels = ProtocolLabels {
+    name: "And",
+    domain: b"and-proof/v1",
+    challenge: b"and-c",
+};
+
+/// PROTOCOL_LABELS. The labels of every protocol of this crate.
+pub const PROTOCOL_LABELS: &[ProtocolLabels] = &[
+    EQUALITY_LABELS,
+    OPENING_LABELS,
+    OPENING_MULTI_LABELS,
+    ISSUANCE_LABELS,
+    MUL_LABELS,
+    ADD_MUL_LABELS,
+    ADD_MUL_MULTI_LABELS,
+    PRODUCT_LABELS,
+    NON_ZERO_LABELS,
+    EC_POINT_ADDITION_LABELS,
+    ZK_ATTEST_EC_POINT_ADDITION_LABELS,
+    ZK_ATTEST_COMPLETE_EC_POINT_ADDITION_LABELS,
+    EC_SCALAR_MUL_LABELS,
+    ZK_ATTEST_EC_SCALAR_MUL_LABELS,
+    FS_EC_SCALAR_MUL_LABELS,
+    ZK_ATTEST_FS_EC_SCALAR_MUL_LABELS,
+    GK_ZERO_ONE_LABELS,
+    GK_K_ARY_LABELS,
+    CONSISTENCY_LABELS,
+    ECDSA_SIGNATURE_LABELS,
+    POINT_COMMITMENT_LABELS,
+    OR_LABELS,
+    AND_LABELS,
+];
+
+/// append_message. This function appends `message` to `transcript` under `label`. With the
+/// `transcript_dump` feature, the operation is also recorded (see the `transcript_dump` module).
+/// # Arguments
+/// * `transcript` - the transcript object.
+/// * `label` - the label of the message.
+/// * `message` - the message.
+pub fn append_message(transcript: &mut Transcript, label: &'static [u8], message: &[u8]) {
+    #[cfg(feature = "transcript_dump")]
+    crate::transcript_dump::log_append(label, message);
+    transcript.append_message(label, message);
+}
+
+/// append_u64. This function appends `x` to `transcript` under `label`, as Merlin's
+/// `append_u64` does.
+/// # Arguments
+/// * `transcript` - the transcript object.
+/// * `label` - the label of the value.
+/// * `x` - the value.
+pub fn append_u64(transcript: &mut Transcript, label: &'static [u8], x: u64) {
+    append_message(transcript, label, &x.to_le_bytes());
+}
+
+/// challenge_bytes. This function fills `dest` with challenge bytes from `transcript` under
+/// `label`. With the `transcript_dump` feature, the operation is also recorded.
+/// # Arguments
+/// * `transcript` - the transcript object.
+/// * `label` - the label of the challenge.
+/// * `dest` - the buffer to fill.
+pub fn challenge_bytes(transcript: &mut Transcript, label: &'static [u8], dest: &mut [u8]) {
+    transcript.challenge_bytes(label, dest);
+    #[cfg(feature = "transcript_dump")]
+    crate::transcript_dump::log_challenge(label, dest);
+}
+
 pub trait EqualityTranscript {
     /// Append a domain separator.
     fn domain_sep(&mut self);
@@ -20,16 +259,16 @@ pub trait EqualityTranscript {
 
 impl EqualityTranscript for Transcript {
     fn domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"equality-proof")
+        append_message(self, b"dom-sep", EQUALITY_LABELS.domain)
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
-        self.append_message(label, point);
+        append_message(self, label, point);
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
         let mut buf = [0u8; CHALLENGE_SIZE];
-        self.challenge_bytes(label, &mut buf);
+        challenge_bytes(self, label, &mut buf);
         buf
     }
 }
@@ -47,16 +286,43 @@ pub trait OpeningTranscript {
 
 impl OpeningTranscript for Transcript {
     fn domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"open-proof")
+        append_message(self, b"dom-sep", OPENING_LABELS.domain)
+    }
+
+    fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
+        append_message(self, label, point);
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
+        let mut buf = [0u8; CHALLENGE_SIZE];
+        challenge_bytes(self, label, &mut buf);
+        buf
+    }
+}
+
+pub trait OpeningMultiTranscript {
+    /// Append a domain separator.
+    fn domain_sep(&mut self);
+
+    /// Append a point.
+    fn append_point(&mut self, label: &'static [u8], point: &[u8]);
+
+    /// Produce the challenge.
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE];
+}
+
+impl OpeningMultiTranscript for Transcript {
+    fn domain_sep(&mut self) {
+        append_message(self, b"dom-sep", OPENING_MULTI_LABELS.domain)
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
-        self.append_message(label, point);
+        append_message(self, label, point);
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
         let mut buf = [0u8; CHALLENGE_SIZE];
-        self.challenge_bytes(label, &mut buf);
+        challenge_bytes(self, label, &mut buf);
         buf
     }
 }
@@ -74,16 +340,16 @@ pub trait IssuanceTranscript {
 
 impl IssuanceTranscript for Transcript {
     fn domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"issuance-proof")
+        append_message(self, b"dom-sep", ISSUANCE_LABELS.domain)
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
-        self.append_message(label, point);
+        append_message(self, label, point);
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
         let mut buf = [0u8; CHALLENGE_SIZE];
-        self.challenge_bytes(label, &mut buf);
+        challenge_bytes(self, label, &mut buf);
         buf
     }
 }
@@ -101,16 +367,16 @@ pub trait MulTranscript {
 
 impl MulTranscript for Transcript {
     fn domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"mul-proof")
+        append_message(self, b"dom-sep", MUL_LABELS.domain)
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
-        self.append_message(label, point);
+        append_message(self, label, point);
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
         let mut buf = [0u8; CHALLENGE_SIZE];
-        self.challenge_bytes(label, &mut buf);
+        challenge_bytes(self, label, &mut buf);
         buf
     }
 }
@@ -128,16 +394,70 @@ pub trait AddMulTranscript {
 
 impl AddMulTranscript for Transcript {
     fn domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"add-mul-proof")
+        append_message(self, b"dom-sep", ADD_MUL_LABELS.domain)
+    }
+
+    fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
+        append_message(self, label, point);
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
+        let mut buf = [0u8; CHALLENGE_SIZE];
+        challenge_bytes(self, label, &mut buf);
+        buf
+    }
+}
+
+pub trait AddMulMultiTranscript {
+    /// Append a domain separator.
+    fn domain_sep(&mut self);
+
+    /// Append a point.
+    fn append_point(&mut self, label: &'static [u8], point: &[u8]);
+
+    /// Produce the challenge.
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE];
+}
+
+impl AddMulMultiTranscript for Transcript {
+    fn domain_sep(&mut self) {
+        append_message(self, b"dom-sep", ADD_MUL_MULTI_LABELS.domain)
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
-        self.append_message(label, point);
+        append_message(self, label, point);
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
         let mut buf = [0u8; CHALLENGE_SIZE];
-        self.challenge_bytes(label, &mut buf);
+        challenge_bytes(self, label, &mut buf);
+        buf
+    }
+}
+
+pub trait ProductTranscript {
+    /// Append a domain separator.
+    fn domain_sep(&mut self);
+
+    /// Append a point.
+    fn append_point(&mut self, label: &'static [u8], point: &[u8]);
+
+    /// Produce the challenge.
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE];
+}
+
+impl ProductTranscript for Transcript {
+    fn domain_sep(&mut self) {
+        append_message(self, b"dom-sep", PRODUCT_LABELS.domain)
+    }
+
+    fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
+        append_message(self, label, point);
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
+        let mut buf = [0u8; CHALLENGE_SIZE];
+        challenge_bytes(self, label, &mut buf);
         buf
     }
 }
@@ -155,16 +475,16 @@ pub trait NonZeroTranscript {
 
 impl NonZeroTranscript for Transcript {
     fn domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"non-zero-proof")
+        append_message(self, b"dom-sep", NON_ZERO_LABELS.domain)
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
-        self.append_message(label, point);
+        append_message(self, label, point);
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
         let mut buf = [0u8; CHALLENGE_SIZE];
-        self.challenge_bytes(label, &mut buf);
+        challenge_bytes(self, label, &mut buf);
         buf
     }
 }
@@ -182,16 +502,16 @@ pub trait ECPointAdditionTranscript {
 
 impl ECPointAdditionTranscript for Transcript {
     fn domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"ec-point-addition-proof");
+        append_message(self, b"dom-sep", EC_POINT_ADDITION_LABELS.domain);
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
-        self.append_message(label, point);
+        append_message(self, label, point);
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
         let mut buf = [0u8; CHALLENGE_SIZE];
-        self.challenge_bytes(label, &mut buf);
+        challenge_bytes(self, label, &mut buf);
         buf
     }
 }
@@ -209,16 +529,47 @@ pub trait ZKAttestECPointAdditionTranscript {
 
 impl ZKAttestECPointAdditionTranscript for Transcript {
     fn domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"zk-attest-ec-point-addition-proof");
+        append_message(self, b"dom-sep", ZK_ATTEST_EC_POINT_ADDITION_LABELS.domain);
+    }
+
+    fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
+        append_message(self, label, point);
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
+        let mut buf = [0u8; 64];
+        challenge_bytes(self, label, &mut buf);
+        buf
+    }
+}
+
+pub trait ZKAttestCompleteECPointAdditionTranscript {
+    /// Append a domain separator.
+    fn domain_sep(&mut self);
+
+    /// Append a point.
+    fn append_point(&mut self, label: &'static [u8], point: &[u8]);
+
+    /// Produce the challenge.
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE];
+}
+
+impl ZKAttestCompleteECPointAdditionTranscript for Transcript {
+    fn domain_sep(&mut self) {
+        append_message(
+            self,
+            b"dom-sep",
+            ZK_ATTEST_COMPLETE_EC_POINT_ADDITION_LABELS.domain,
+        );
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
-        self.append_message(label, point);
+        append_message(self, label, point);
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
         let mut buf = [0u8; 64];
-        self.challenge_bytes(label, &mut buf);
+        challenge_bytes(self, label, &mut buf);
         buf
     }
 }
@@ -235,16 +586,16 @@ pub trait ECScalarMulTranscript {
 
 impl ECScalarMulTranscript for Transcript {
     fn domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"ec-point-scalar-mul-proof");
+        append_message(self, b"dom-sep", EC_SCALAR_MUL_LABELS.domain);
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
-        self.append_message(label, point);
+        append_message(self, label, point);
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
         let mut buf = [0u8; CHALLENGE_SIZE];
-        self.challenge_bytes(label, &mut buf);
+        challenge_bytes(self, label, &mut buf);
         buf
     }
 }
@@ -262,16 +613,16 @@ pub trait ZKAttestECScalarMulTranscript {
 
 impl ZKAttestECScalarMulTranscript for Transcript {
     fn domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"zk-attest-ec-point-scalar-mul-proof");
+        append_message(self, b"dom-sep", ZK_ATTEST_EC_SCALAR_MUL_LABELS.domain);
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
-        self.append_message(label, point);
+        append_message(self, label, point);
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
         let mut buf = [0u8; CHALLENGE_SIZE];
-        self.challenge_bytes(label, &mut buf);
+        challenge_bytes(self, label, &mut buf);
         buf
     }
 }
@@ -291,16 +642,16 @@ pub trait FSECScalarMulTranscript {
 
 impl FSECScalarMulTranscript for Transcript {
     fn domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"fs-ec-point-scalar-addition-proof");
+        append_message(self, b"dom-sep", FS_EC_SCALAR_MUL_LABELS.domain);
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
-        self.append_message(label, point);
+        append_message(self, label, point);
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; 64] {
         let mut buf = [0u8; 64];
-        self.challenge_bytes(label, &mut buf);
+        challenge_bytes(self, label, &mut buf);
         buf
     }
 }
@@ -319,16 +670,16 @@ pub trait ZKAttestFSECScalarMulTranscript {
 
 impl ZKAttestFSECScalarMulTranscript for Transcript {
     fn domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"fs-zk-attest-ec-point-scalar-mul-proof");
+        append_message(self, b"dom-sep", ZK_ATTEST_FS_EC_SCALAR_MUL_LABELS.domain);
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
-        self.append_message(label, point);
+        append_message(self, label, point);
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; 64] {
         let mut buf = [0u8; 64];
-        self.challenge_bytes(label, &mut buf);
+        challenge_bytes(self, label, &mut buf);
         buf
     }
 }
@@ -346,16 +697,70 @@ pub trait GKZeroOneTranscript {
 
 impl GKZeroOneTranscript for Transcript {
     fn domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"gk-zero-one-proof");
+        append_message(self, b"dom-sep", GK_ZERO_ONE_LABELS.domain);
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
-        self.append_message(label, point);
+        append_message(self, label, point);
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
         let mut buf = [0u8; CHALLENGE_SIZE];
-        self.challenge_bytes(label, &mut buf);
+        challenge_bytes(self, label, &mut buf);
+        buf
+    }
+}
+
+pub trait GKKAryTranscript {
+    /// Append a domain separator.
+    fn domain_sep(&mut self);
+
+    /// Append a point.
+    fn append_point(&mut self, label: &'static [u8], point: &[u8]);
+
+    /// Produce the challenge.
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE];
+}
+
+impl GKKAryTranscript for Transcript {
+    fn domain_sep(&mut self) {
+        append_message(self, b"dom-sep", GK_K_ARY_LABELS.domain);
+    }
+
+    fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
+        append_message(self, label, point);
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
+        let mut buf = [0u8; CHALLENGE_SIZE];
+        challenge_bytes(self, label, &mut buf);
+        buf
+    }
+}
+
+pub trait ConsistencyTranscript {
+    /// Append a domain separator.
+    fn domain_sep(&mut self);
+
+    /// Append a point.
+    fn append_point(&mut self, label: &'static [u8], point: &[u8]);
+
+    /// Produce the challenge.
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE];
+}
+
+impl ConsistencyTranscript for Transcript {
+    fn domain_sep(&mut self) {
+        append_message(self, b"dom-sep", CONSISTENCY_LABELS.domain);
+    }
+
+    fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
+        append_message(self, label, point);
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
+        let mut buf = [0u8; CHALLENGE_SIZE];
+        challenge_bytes(self, label, &mut buf);
         buf
     }
 }
@@ -373,16 +778,206 @@ pub trait ECDSASignatureTranscript {
 
 impl ECDSASignatureTranscript for Transcript {
     fn domain_sep(&mut self) {
-        self.append_message(b"dom-sep", b"ecdsa-signature-proof");
+        append_message(self, b"dom-sep", ECDSA_SIGNATURE_LABELS.domain);
     }
 
     fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
-        self.append_message(label, point);
+        append_message(self, label, point);
     }
 
     fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
         let mut buf = [0u8; CHALLENGE_SIZE];
-        self.challenge_bytes(label, &mut buf);
+        challenge_bytes(self, label, &mut buf);
         buf
     }
 }
+
+pub trait PointCommitmentTranscript {
+    /// Append a domain separator.
+    fn domain_sep(&mut self);
+
+    /// Append a point.
+    fn append_point(&mut self, label: &'static [u8], point: &[u8]);
+
+    /// Produce the challenge.
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE];
+}
+
+impl PointCommitmentTranscript for Transcript {
+    fn domain_sep(&mut self) {
+        append_message(self, b"dom-sep", POINT_COMMITMENT_LABELS.domain);
+    }
+
+    fn append_point(&mut self, label: &'static [u8], point: &[u8]) {
+        append_message(self, label, point);
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
+        let mut buf = [0u8; CHALLENGE_SIZE];
+        challenge_bytes(self, label, &mut buf);
+        buf
+    }
+}
+
+pub trait OrTranscript {
+    /// Append a domain separator.
+    fn domain_sep(&mut self);
+
+    /// Produce the challenge.
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE];
+}
+
+impl OrTranscript for Transcript {
+    fn domain_sep(&mut self) {
+        append_message(self, b"dom-sep", OR_LABELS.domain);
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
+        let mut buf = [0u8; CHALLENGE_SIZE];
+        challenge_bytes(self, label, &mut buf);
+        buf
+    }
+}
+
+pub trait AndTranscript {
+    /// Append a domain separator.
+    fn domain_sep(&mut self);
+
+    /// Produce the challenge.
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE];
+}
+
+impl AndTranscript for Transcript {
+    fn domain_sep(&mut self) {
+        append_message(self, b"dom-sep", AND_LABELS.domain);
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> [u8; CHALLENGE_SIZE] {
+        let mut buf = [0u8; CHALLENGE_SIZE];
+        challenge_bytes(self, label, &mut buf);
+        buf
+    }
+}
+
+/// witness_rng. This function returns an RNG for the blinding values of a prover. As in the
+/// Bulletproofs prover, the RNG is seeded from the current state of `transcript`, rekeyed with
+/// the prover's `witness`, and finally mixed with fresh randomness from `rng`. The blinding
+/// values therefore stay unpredictable as long as either the witness is secret or `rng` is
+/// sound, which protects provers on devices with a weak RNG.
+/// # Arguments
+/// * `transcript` - the transcript of the proof. It is not modified.
+/// * `witness` - the secret values of the prover, e.g the openings of the commitments.
+/// * `rng` - the caller's RNG.
+pub fn witness_rng<W: CanonicalSerialize, T: RngCore + CryptoRng>(
+    transcript: &Transcript,
+    witness: &W,
+    rng: &mut T,
+) -> TranscriptRng {
+    let mut bytes = Vec::new();
+    witness.serialize_compressed(&mut bytes).unwrap();
+    let rng_out = transcript
+        .build_rng()
+        .rekey_with_witness_bytes(b"witness", &bytes)
+        .finalize(rng);
+    bytes.iter_mut().for_each(|b| *b = 0);
+    rng_out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// literals. Returns every byte string literal that follows `prefix` in `source`.
+    fn literals<'a>(source: &'a str, prefix: &str) -> Vec<&'a str> {
+        source
+            .match_indices(prefix)
+            .filter_map(|(i, _)| {
+                let rest = &source[i + prefix.len()..];
+                rest.strip_prefix("b\"")
+                    .and_then(|rest| rest.split('"').next())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn protocol_labels_are_unique_and_versioned() {
+        let mut names = HashSet::new();
+        let mut labels = HashSet::new();
+        for protocol in PROTOCOL_LABELS {
+            assert!(
+                names.insert(protocol.name),
+                "{} is listed twice",
+                protocol.name
+            );
+            let domain = std::str::from_utf8(protocol.domain).unwrap();
+            let version = domain.rsplit_once("/v").map(|(_, version)| version);
+            assert!(
+                version.is_some_and(|v| !v.is_empty() && v.bytes().all(|b| b.is_ascii_digit())),
+                "the domain of {} is not versioned",
+                protocol.name
+            );
+            // The domains and the challenges share a namespace, so that a label can never
+            // stand for both.
+            assert!(
+                labels.insert(protocol.domain),
+                "{} reuses a label",
+                protocol.name
+            );
+            assert!(
+                labels.insert(protocol.challenge),
+                "{} reuses a label",
+                protocol.name
+            );
+        }
+    }
+
+    #[test]
+    fn sources_do_not_duplicate_domain_labels() {
+        // Every transcript of the crate must take its domain separator and its challenge label
+        // from `PROTOCOL_LABELS`, and every domain separator must be appended by one transcript
+        // only.
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/src");
+        let this = include_str!("transcript.rs");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|e| e.to_str()) != Some("rs") {
+                continue;
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            assert_eq!(
+                literals(&source, "b\"dom-sep\", "),
+                Vec::<&str>::new(),
+                "{} appends a domain separator that is not in PROTOCOL_LABELS",
+                path.display()
+            );
+            assert_eq!(
+                literals(&source, "challenge_scalar("),
+                Vec::<&str>::new(),
+                "{} uses a challenge label that is not in PROTOCOL_LABELS",
+                path.display()
+            );
+            assert_eq!(
+                literals(&source, "challenge_scalar(transcript, "),
+                Vec::<&str>::new(),
+                "{} uses a challenge label that is not in PROTOCOL_LABELS",
+                path.display()
+            );
+        }
+
+        let consts: Vec<_> = this
+            .match_indices("pub const ")
+            .filter_map(|(i, _)| this[i + 10..].split(':').next())
+            .filter(|name| name.ends_with("_LABELS") && *name != "PROTOCOL_LABELS")
+            .collect();
+        assert_eq!(consts.len(), PROTOCOL_LABELS.len());
+        // rustfmt may wrap the call, so the source is compared without whitespace.
+        let flat: String = this.split_whitespace().collect();
+        for name in consts {
+            let uses = flat
+                .matches(&format!("append_message(self,b\"dom-sep\",{}.domain", name))
+                .count();
+            assert_eq!(uses, 1, "{} is the domain of {} transcripts", name, uses);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Records the operations on Merlin transcripts, for debugging.
+//!
+//! A failed verification of a composed proof only says that some challenge did not match. To
+//! find out which sub-proof went wrong, run the prover and the verifier inside `record`, and
+//! compare both dumps with `TranscriptDump::diff`: the first operation at which they differ
+//! names the label, and hence the sub-proof, whose inputs disagree.
+//!
+//! Every operation that goes through the helpers of the `transcript` module (and the transcript
+//! traits, which use them) is recorded, in the order in which it happens on the current thread,
+//! whichever transcript it is on. Operations that call Merlin directly, e.g. inside the
+//! bulletproofs crate, are not recorded. Nor is the label that a transcript is created with: two
+//! transcripts that only differ in it first show up as differing challenges.
+//!
+//! N.B The dumps hold the raw transcript inputs, which may include secret values. They must only
+//! be used for debugging.
+
+use std::cell::RefCell;
+use std::fmt;
+
+thread_local! {
+    /// RECORDING: the operations recorded so far on this thread, if a recording is running.
+    static RECORDING: RefCell<Option<Vec<TranscriptOp>>> = const { RefCell::new(None) };
+}
+
+/// TranscriptOp. A single operation on a transcript.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TranscriptOp {
+    /// Append: a message was appended under `label`.
+    Append { label: Vec<u8>, message: Vec<u8> },
+    /// Challenge: the challenge `bytes` was produced under `label`.
+    Challenge { label: Vec<u8>, bytes: Vec<u8> },
+}
+
+impl TranscriptOp {
+    /// label. Returns the label of the operation.
+    pub fn label(&self) -> &[u8] {
+        match self {
+            Self::Append { label, .. } | Self::Challenge { label, .. } => label,
+        }
+    }
+}
+
+impl fmt::Display for TranscriptOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (op, label, bytes) = match self {
+            Self::Append { label, message } => ("append", label, message),
+            Self::Challenge { label, bytes } => ("challenge", label, bytes),
+        };
+        write!(f, "{} {} ", op, String::from_utf8_lossy(label))?;
+        bytes.iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+/// TranscriptDump. The operations recorded by `record`, in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TranscriptDump {
+    /// ops: the recorded operations.
+    pub ops: Vec<TranscriptOp>,
+}
+
+/// TranscriptDiff. The first operation at which two dumps differ. Either side is `None` if that
+/// dump ends before the other.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TranscriptDiff {
+    /// index: the position of the operation in both dumps.
+    pub index: usize,
+    /// left: the operation of the first dump.
+    pub left: Option<TranscriptOp>,
+    /// right: the operation of the second dump.
+    pub right: Option<TranscriptOp>,
+}
+
+impl TranscriptDump {
+    /// diff. This function returns the first operation at which `self` and `other` differ, or
+    /// `None` if they are equal.
+    /// # Arguments
+    /// * `other` - the dump to compare against, e.g. the verifier's.
+    pub fn diff(&self, other: &Self) -> Option<TranscriptDiff> {
+        let index = self
+            .ops
+            .iter()
+            .zip(&other.ops)
+            .position(|(a, b)| a != b)
+            .unwrap_or(self.ops.len().min(other.ops.len()));
+        if index == self.ops.len() && index == other.ops.len() {
+            return None;
+        }
+        Some(TranscriptDiff {
+            index,
+            left: self.ops.get(index).cloned(),
+            right: other.ops.get(index).cloned(),
+        })
+    }
+}
+
+impl fmt::Display for TranscriptDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.ops
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, op)| writeln!(f, "{:>5} {}", i, op))
+    }
+}
+
+impl fmt::Display for TranscriptDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "transcripts differ at operation {}:", self.index)?;
+        for (side, op) in [("<", &self.left), (">", &self.right)] {
+            match op {
+                Some(op) => writeln!(f, "{} {}", side, op)?,
+                None => writeln!(f, "{} (end of transcript)", side)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// record. This function runs `f`, and returns its result alongside the transcript operations
+/// that it performed on this thread. Recordings can be nested: the operations of an inner
+/// recording are also part of the outer one.
+/// # Arguments
+/// * `f` - the function to run, e.g. a prover or a verifier.
+pub fn record<R>(f: impl FnOnce() -> R) -> (R, TranscriptDump) {
+    let outer = RECORDING.with(|r| r.borrow_mut().replace(Vec::new()));
+    let out = f();
+    let ops = RECORDING.with(|r| {
+        let mut r = r.borrow_mut();
+        let ops = r.take().unwrap_or_default();
+        *r = outer.map(|mut outer| {
+            outer.extend_from_slice(&ops);
+            outer
+        });
+        ops
+    });
+    (out, TranscriptDump { ops })
+}
+
+/// log_append. This function records that `message` was appended under `label`, if a recording
+/// is running.
+pub fn log_append(label: &[u8], message: &[u8]) {
+    log(|| TranscriptOp::Append {
+        label: label.to_vec(),
+        message: message.to_vec(),
+    });
+}
+
+/// log_challenge. This function records that the challenge `bytes` was produced under `label`,
+/// if a recording is running.
+pub fn log_challenge(label: &[u8], bytes: &[u8]) {
+    log(|| TranscriptOp::Challenge {
+        label: label.to_vec(),
+        bytes: bytes.to_vec(),
+    });
+}
+
+/// log. This function records the operation made by `op`, if a recording is running.
+fn log(op: impl FnOnce() -> TranscriptOp) {
+    RECORDING.with(|r| {
+        if let Some(ops) = r.borrow_mut().as_mut() {
+            ops.push(op());
+        }
+    });
+}
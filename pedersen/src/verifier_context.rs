@@ -0,0 +1,166 @@
+//! Defines a verifier-side cache of fixed-base tables for the Pedersen generators.
+//!
+//! Every opening, equality and multiplication proof checks an equation that multiplies the
+//! fixed generators G and H (and, for multi-commitments, the multi-generators) by a response
+//! scalar. A plain scalar multiplication costs roughly one doubling per bit plus an addition for
+//! every other bit. A `VerifierContext` precomputes windowed tables for each generator once, so
+//! that each of these multiplications costs only one mixed addition per window.
+//!
+//! On T256 (release build) this takes an opening proof verification from ~500us to ~275us,
+//! an equality proof from ~320us to ~195us and a multiplication proof from ~1.5ms to ~0.9ms.
+//! The context itself takes ~7ms to build, so it pays for itself after a few dozen proofs.
+//! See the "(verifier context)" benchmarks in `bench_tcurve` for the exact numbers.
+
+use ark_ec::{
+    models::CurveConfig,
+    scalar_mul::fixed_base::FixedBase,
+    short_weierstrass::{self as sw, SWCurveConfig},
+};
+use ark_ff::PrimeField;
+
+use crate::pedersen_config::{Generators, PedersenConfig};
+
+/// TABLE_WINDOW. The number of scalar bits that are handled by each row of a fixed-base table.
+const TABLE_WINDOW: usize = 6;
+
+/// FixedBaseTable. This struct holds the windowed multiples of a single base point.
+struct FixedBaseTable<P: SWCurveConfig> {
+    table: Vec<Vec<sw::Affine<P>>>,
+}
+
+impl<P: SWCurveConfig> Clone for FixedBaseTable<P> {
+    fn clone(&self) -> Self {
+        Self {
+            table: self.table.clone(),
+        }
+    }
+}
+
+impl<P: SWCurveConfig> FixedBaseTable<P> {
+    /// num_bits. Returns the number of bits in a scalar.
+    fn num_bits() -> usize {
+        <P as CurveConfig>::ScalarField::MODULUS_BIT_SIZE as usize
+    }
+
+    /// new. This function builds a new table for `base`.
+    /// # Arguments
+    /// * `base` - the base point.
+    fn new(base: &sw::Affine<P>) -> Self {
+        Self {
+            table: FixedBase::get_window_table::<sw::Projective<P>>(
+                Self::num_bits(),
+                TABLE_WINDOW,
+                (*base).into(),
+            ),
+        }
+    }
+
+    /// mul. This function returns `scalar` times the base point of this table.
+    /// # Arguments
+    /// * `scalar` - the scalar.
+    fn mul(&self, scalar: &<P as CurveConfig>::ScalarField) -> sw::Projective<P> {
+        FixedBase::windowed_mul(
+            Self::num_bits().div_ceil(TABLE_WINDOW),
+            TABLE_WINDOW,
+            &self.table,
+            scalar,
+        )
+    }
+}
+
+/// VerifierContext. This struct caches precomputed tables for the Pedersen generators, so that
+/// many proofs that share the same generators can be verified without redoing the same work.
+/// A single context can be shared across any number of `verify_*_with_context` calls.
+pub struct VerifierContext<P: PedersenConfig> {
+    /// g: the table for the main generator G.
+    g: FixedBaseTable<P>,
+    /// h: the table for the second generator H (i.e GENERATOR2).
+    h: FixedBaseTable<P>,
+    /// gens: the tables for the multi-commitment generators.
+    gens: Vec<FixedBaseTable<P>>,
+}
+
+impl<P: PedersenConfig> Clone for VerifierContext<P> {
+    fn clone(&self) -> Self {
+        Self {
+            g: self.g.clone(),
+            h: self.h.clone(),
+            gens: self.gens.clone(),
+        }
+    }
+}
+
+impl<P: PedersenConfig> Default for VerifierContext<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: PedersenConfig> VerifierContext<P> {
+    /// new. This function builds a new verifier context for the generators G and H.
+    pub fn new() -> Self {
+        Self {
+            g: FixedBaseTable::new(&<P as SWCurveConfig>::GENERATOR),
+            h: FixedBaseTable::new(&P::GENERATOR2),
+            gens: Vec::new(),
+        }
+    }
+
+    /// new_with_generators. This function builds a new verifier context for the generators G and H,
+    /// as well as for every multi-commitment generator in `gens`.
+    /// # Arguments
+    /// * `gens` - the multi-commitment generators.
+    pub fn new_with_generators(gens: &Generators<P>) -> Self {
+        let mut ctx = Self::new();
+        ctx.gens = gens.generators.iter().map(FixedBaseTable::new).collect();
+        ctx
+    }
+
+    /// num_generators. Returns the number of multi-commitment generators held by this context.
+    pub fn num_generators(&self) -> usize {
+        self.gens.len()
+    }
+
+    /// mul_g. Returns `x` times the generator G.
+    /// # Arguments
+    /// * `x` - the scalar.
+    pub fn mul_g(&self, x: &<P as CurveConfig>::ScalarField) -> sw::Projective<P> {
+        self.g.mul(x)
+    }
+
+    /// mul_h. Returns `r` times the generator H.
+    /// # Arguments
+    /// * `r` - the scalar.
+    pub fn mul_h(&self, r: &<P as CurveConfig>::ScalarField) -> sw::Projective<P> {
+        self.h.mul(r)
+    }
+
+    /// commit. Returns the Pedersen commitment xG + rH.
+    /// # Arguments
+    /// * `x` - the committed value.
+    /// * `r` - the randomness.
+    pub fn commit(
+        &self,
+        x: &<P as CurveConfig>::ScalarField,
+        r: &<P as CurveConfig>::ScalarField,
+    ) -> sw::Projective<P> {
+        self.mul_g(x) + self.mul_h(r)
+    }
+
+    /// commit_multi. Returns the multi-commitment Σ vals[i]G_i + rH, where the G_i are the
+    /// multi-commitment generators held by this context.
+    /// This function panics if the context holds fewer than `vals.len()` generators.
+    /// # Arguments
+    /// * `vals` - the committed values.
+    /// * `r` - the randomness.
+    pub fn commit_multi(
+        &self,
+        vals: &[<P as CurveConfig>::ScalarField],
+        r: &<P as CurveConfig>::ScalarField,
+    ) -> sw::Projective<P> {
+        assert!(vals.len() <= self.gens.len());
+        vals.iter()
+            .zip(self.gens.iter())
+            .fold(self.mul_h(r), |acc, (v, table)| acc + table.mul(v))
+    }
+}
@@ -0,0 +1,832 @@
+//! Defines a complete variant of the ZKAttest point addition protocol.
+//! Namely, this protocol proves that `t = a + b` for two elliptic curve points `a`, `b`, including
+//! when `a == b`.
+//!
+//! `ZKAttestPointAddProof` proves the chord rule, i.e it uses the slope (b.y - a.y) / (b.x - a.x),
+//! and so it cannot prove a doubling. This protocol instead uses the unified slope
+//! (a.x^2 + a.x * b.x + b.x^2 + A) / (a.y + b.y), where `A` is the `a` coefficient of the curve:
+//! this is the slope of the chord when a.x != b.x, and the slope of the tangent when `a == b`.
+//! The only sums that this protocol cannot prove are those where a.y == -b.y, which includes
+//! `b == -a`, and those that involve the point at infinity, as it has no affine co-ordinates.
+//! For those inputs the prover does not panic: it produces a proof that is rejected.
+
+use ark_ec::{
+    short_weierstrass::{self as sw, SWCurveConfig},
+    AffineRepr, CurveConfig, CurveGroup,
+};
+use merlin::Transcript;
+
+use ark_ff::fields::Field;
+use ark_serialize::CanonicalSerialize;
+use ark_std::ops::Mul;
+use rand::{CryptoRng, RngCore};
+
+use crate::{
+    equality_protocol::{
+        EqualityProof, EqualityProofIntermediate, EqualityProofIntermediateTranscript,
+        EqualityProofTranscriptable,
+    },
+    mul_protocol::{
+        MulProof, MulProofIntermediate, MulProofIntermediateTranscript, MulProofTranscriptable,
+    },
+    pedersen_config::{PedersenComm, PedersenConfig},
+    point_add::PointAddProtocol,
+    transcript::{
+        witness_rng, ZKAttestCompleteECPointAdditionTranscript,
+        ZK_ATTEST_COMPLETE_EC_POINT_ADDITION_LABELS,
+    },
+};
+
+/// ZKAttestCompletePointAddProofTranscriptable. This trait provides a notion of `Transcriptable`,
+/// which implies that the particular struct can be, in some sense, added to the transcript for a
+/// complete ZK-Attest style point addition proof.
+pub trait ZKAttestCompletePointAddProofTranscriptable<P: PedersenConfig> {
+    /// Affine: the type of affine point used inside these proofs. This is defined to
+    /// ensure consistency for higher-level protocols.
+    type Affine;
+    /// add_to_transcript. This function adds all of the underlying information for this proof to the
+    /// transcript. This is carried out to allow a proper challenge to be computed later on.
+    /// # Arguments
+    /// *`self` - the proof object.
+    /// *`transcript` - the transcript object that is used.
+    /// * `ci` - the commitments to the co-ordinates.
+    #[allow(clippy::too_many_arguments)]
+    fn add_to_transcript(
+        &self,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        c2: &sw::Affine<P>,
+        c3: &sw::Affine<P>,
+        c4: &sw::Affine<P>,
+        c5: &sw::Affine<P>,
+        c6: &sw::Affine<P>,
+    );
+}
+
+/// ZKAttestCompletePointAddProof. This struct acts as a container for the complete point addition
+/// proof. New proof objects can be made via the `create` function, whereas existing
+/// proofs may be verified via the `verify` function.
+/// Note that this struct's documentation uses the convention that we are proving `t = a + b`, and
+/// that c1, ..., c6 are the commitments to a.x, a.y, b.x, b.y, t.x and t.y.
+pub struct ZKAttestCompletePointAddProof<P: PedersenConfig> {
+    // c7: the commitment to a.y + b.y. We compute this as c2 + c4.
+    // pub c7: sw::Affine<P>,
+    /// c8: the commitment to (a.y + b.y)^-1.
+    pub c8: sw::Affine<P>,
+    /// c9: the commitment to a.x^2.
+    pub c9: sw::Affine<P>,
+    /// c10: the commitment to a.x * b.x.
+    pub c10: sw::Affine<P>,
+    /// c11: the commitment to b.x^2.
+    pub c11: sw::Affine<P>,
+
+    // c12: the commitment to a.x^2 + a.x * b.x + b.x^2 + A. We compute this as c9 + c10 + c11 + A*g.
+    // pub c12: sw::Affine<P>,
+    /// c13: the commitment to the slope, i.e (a.x^2 + a.x * b.x + b.x^2 + A) / (a.y + b.y).
+    pub c13: sw::Affine<P>,
+    /// c14: the commitment to the square of the slope.
+    pub c14: sw::Affine<P>,
+    /// c15: the commitment to the slope times (a.x - t.x).
+    pub c15: sw::Affine<P>,
+
+    /// mp1: the multiplication proof for showing that c7*c8 = Com(1).
+    /// Alternatively, mp1 shows that c7 has an inverse.
+    pub mp1: MulProof<P>,
+    /// mp2: the multiplication proof for showing that c9 is a commitment to c1*c1.
+    pub mp2: MulProof<P>,
+    /// mp3: the multiplication proof for showing that c10 is a commitment to c1*c3.
+    pub mp3: MulProof<P>,
+    /// mp4: the multiplication proof for showing that c11 is a commitment to c3*c3.
+    pub mp4: MulProof<P>,
+    /// mp5: the multiplication proof for showing that c13 is a commitment to c12*c8.
+    pub mp5: MulProof<P>,
+    /// mp6: the multiplication proof for showing that c14 is a commitment to c13*c13.
+    pub mp6: MulProof<P>,
+    /// mp7: the multiplication proof for showing that c15 is a commitment to c13*(c1 - c5).
+    pub mp7: MulProof<P>,
+
+    /// e1: the equality proof for showing that c5 + c1 + c3 and c14 are commitments to the same value.
+    pub e1: EqualityProof<P>,
+    /// e2: the equality proof for showing that c15 and c6 + c2 are commitments to the same value.
+    pub e2: EqualityProof<P>,
+}
+
+/// ZKAttestCompletePointAddProofIntermediate. This struct acts as a temporary container for the
+/// intermediate values produced during setup. This struct should only be used when there are
+/// potentially future changes to the transcript object before the challenge can be generated.
+pub struct ZKAttestCompletePointAddProofIntermediate<P: PedersenConfig> {
+    /// c8: the commitment to (a.y + b.y)^-1.
+    pub c8: PedersenComm<P>,
+    /// c9: the commitment to a.x^2.
+    pub c9: PedersenComm<P>,
+    /// c10: the commitment to a.x * b.x.
+    pub c10: PedersenComm<P>,
+    /// c11: the commitment to b.x^2.
+    pub c11: PedersenComm<P>,
+    /// c13: the commitment to the slope.
+    pub c13: PedersenComm<P>,
+    /// c14: the commitment to the square of the slope.
+    pub c14: PedersenComm<P>,
+    /// c15: the commitment to the slope times (a.x - t.x).
+    pub c15: PedersenComm<P>,
+
+    /// mpi1: the multiplication proof's intermediates for showing that c7*c8 = Com(1).
+    pub mpi1: MulProofIntermediate<P>,
+    /// mpi2: the multiplication proof's intermediates for showing that c9 is a commitment to c1*c1.
+    pub mpi2: MulProofIntermediate<P>,
+    /// mpi3: the multiplication proof's intermediates for showing that c10 is a commitment to c1*c3.
+    pub mpi3: MulProofIntermediate<P>,
+    /// mpi4: the multiplication proof's intermediates for showing that c11 is a commitment to c3*c3.
+    pub mpi4: MulProofIntermediate<P>,
+    /// mpi5: the multiplication proof's intermediates for showing that c13 is a commitment to c12*c8.
+    pub mpi5: MulProofIntermediate<P>,
+    /// mpi6: the multiplication proof's intermediates for showing that c14 is a commitment to c13*c13.
+    pub mpi6: MulProofIntermediate<P>,
+    /// mpi7: the multiplication proof's intermediates for showing that c15 is a commitment to c13*(c1 - c5).
+    pub mpi7: MulProofIntermediate<P>,
+
+    /// ei1: the equality proof's intermediates for showing that c5 + c1 + c3 and c14 are commitments to the same value.
+    pub ei1: EqualityProofIntermediate<P>,
+    /// ei2: the equality proof's intermediates for showing that c15 and c6 + c2 are commitments to the same value.
+    pub ei2: EqualityProofIntermediate<P>,
+}
+
+impl<P: PedersenConfig> Copy for ZKAttestCompletePointAddProofIntermediate<P> {}
+impl<P: PedersenConfig> Clone for ZKAttestCompletePointAddProofIntermediate<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+/// ZKAttestCompletePointAddProofIntermediateTranscript. This struct provides a wrapper for every
+/// input into the transcript i.e everything that's in `ZKAttestCompletePointAddProofIntermediate`
+/// except from the randomness values.
+pub struct ZKAttestCompletePointAddProofIntermediateTranscript<P: PedersenConfig> {
+    /// c8: the commitment to (a.y + b.y)^-1.
+    pub c8: sw::Affine<P>,
+    /// c9: the commitment to a.x^2.
+    pub c9: sw::Affine<P>,
+    /// c10: the commitment to a.x * b.x.
+    pub c10: sw::Affine<P>,
+    /// c11: the commitment to b.x^2.
+    pub c11: sw::Affine<P>,
+    /// c13: the commitment to the slope.
+    pub c13: sw::Affine<P>,
+    /// c14: the commitment to the square of the slope.
+    pub c14: sw::Affine<P>,
+    /// c15: the commitment to the slope times (a.x - t.x).
+    pub c15: sw::Affine<P>,
+
+    /// mp1: the values produced during the multiplication proof for showing that c7*c8 = Com(1).
+    pub mp1: MulProofIntermediateTranscript<P>,
+    /// mp2: the values produced during the multiplication proof for showing that c9 is a commitment to c1*c1.
+    pub mp2: MulProofIntermediateTranscript<P>,
+    /// mp3: the values produced during the multiplication proof for showing that c10 is a commitment to c1*c3.
+    pub mp3: MulProofIntermediateTranscript<P>,
+    /// mp4: the values produced during the multiplication proof for showing that c11 is a commitment to c3*c3.
+    pub mp4: MulProofIntermediateTranscript<P>,
+    /// mp5: the values produced during the multiplication proof for showing that c13 is a commitment to c12*c8.
+    pub mp5: MulProofIntermediateTranscript<P>,
+    /// mp6: the values produced during the multiplication proof for showing that c14 is a commitment to c13*c13.
+    pub mp6: MulProofIntermediateTranscript<P>,
+    /// mp7: the values produced during the multiplication proof for showing that c15 is a commitment to c13*(c1 - c5).
+    pub mp7: MulProofIntermediateTranscript<P>,
+
+    /// e1: the values produced during the equality proof for showing that c5 + c1 + c3 and c14 are commitments to the same value.
+    pub e1: EqualityProofIntermediateTranscript<P>,
+    /// e2: the values produced during the equality proof for showing that c15 and c6 + c2 are commitments to the same value.
+    pub e2: EqualityProofIntermediateTranscript<P>,
+}
+
+/// slope_numerator. Returns a.x^2 + a.x * b.x + b.x^2 + A, i.e the numerator of the unified
+/// slope.
+fn slope_numerator<P: PedersenConfig>(
+    ax: &<P as CurveConfig>::ScalarField,
+    bx: &<P as CurveConfig>::ScalarField,
+) -> <P as CurveConfig>::ScalarField {
+    let coeff_a = <P as PedersenConfig>::from_ob_to_sf(
+        <<P as PedersenConfig>::OCurve as SWCurveConfig>::COEFF_A,
+    );
+    *ax * ax + *ax * bx + *bx * bx + coeff_a
+}
+
+impl<P: PedersenConfig> ZKAttestCompletePointAddProof<P> {
+    /// commit_one. Returns the fixed commitment to 1, i.e the generator with no randomness.
+    fn commit_one() -> PedersenComm<P> {
+        PedersenComm {
+            comm: <P as SWCurveConfig>::GENERATOR,
+            r: <P as CurveConfig>::ScalarField::ZERO,
+        }
+    }
+
+    /// commit_coeff_a. Returns the fixed commitment to the `a` coefficient of the other curve,
+    /// with no randomness.
+    fn commit_coeff_a() -> PedersenComm<P> {
+        let a = <P as PedersenConfig>::from_ob_to_sf(
+            <<P as PedersenConfig>::OCurve as SWCurveConfig>::COEFF_A,
+        );
+        PedersenComm {
+            comm: <P as SWCurveConfig>::GENERATOR.mul(a).into_affine(),
+            r: <P as CurveConfig>::ScalarField::ZERO,
+        }
+    }
+
+    /// witnesses. Returns the committed values of the proof for `t = a + b`, i.e
+    /// ((a.y + b.y)^-1, a.x^2, a.x * b.x, b.x^2, the slope, and a.x - t.x). If a.y + b.y has no
+    /// inverse, then it is replaced by zero, so that the proof is rejected rather than the prover
+    /// panicking.
+    #[allow(clippy::type_complexity)]
+    fn witnesses(
+        a: &sw::Affine<<P as PedersenConfig>::OCurve>,
+        b: &sw::Affine<<P as PedersenConfig>::OCurve>,
+        t: &sw::Affine<<P as PedersenConfig>::OCurve>,
+    ) -> (
+        <P as CurveConfig>::ScalarField,
+        <P as CurveConfig>::ScalarField,
+        <P as CurveConfig>::ScalarField,
+        <P as CurveConfig>::ScalarField,
+        <P as CurveConfig>::ScalarField,
+        <P as CurveConfig>::ScalarField,
+    ) {
+        let inv = <P as PedersenConfig>::from_ob_to_sf((a.y + b.y).inverse().unwrap_or_default());
+        let ax = <P as PedersenConfig>::from_ob_to_sf(a.x);
+        let bx = <P as PedersenConfig>::from_ob_to_sf(b.x);
+        let slope = slope_numerator::<P>(&ax, &bx) * inv;
+        let diff = ax - <P as PedersenConfig>::from_ob_to_sf(t.x);
+        (inv, ax * ax, ax * bx, bx * bx, slope, diff)
+    }
+
+    /// make_transcript. This function simply adds all of the commitments to the `transcript`.
+    /// # Arguments
+    /// * `transcript` - the transcript object to which the commitments are added.
+    /// * `c1` - the commitment to a.x
+    /// * `c2` - the commitment to a.y
+    /// * `c3` - the commitment to b.x
+    /// * `c4` - the commitment to b.y
+    /// * `c5` - the commitment to t.x
+    /// * `c6` - the commitment to t.y
+    pub fn make_transcript(
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        c2: &sw::Affine<P>,
+        c3: &sw::Affine<P>,
+        c4: &sw::Affine<P>,
+        c5: &sw::Affine<P>,
+        c6: &sw::Affine<P>,
+    ) {
+        ZKAttestCompleteECPointAdditionTranscript::domain_sep(transcript);
+
+        let mut compressed_bytes = Vec::new();
+        c1.serialize_compressed(&mut compressed_bytes).unwrap();
+        ZKAttestCompleteECPointAdditionTranscript::append_point(
+            transcript,
+            b"C1",
+            &compressed_bytes[..],
+        );
+
+        c2.serialize_compressed(&mut compressed_bytes).unwrap();
+        ZKAttestCompleteECPointAdditionTranscript::append_point(
+            transcript,
+            b"C2",
+            &compressed_bytes[..],
+        );
+
+        c3.serialize_compressed(&mut compressed_bytes).unwrap();
+        ZKAttestCompleteECPointAdditionTranscript::append_point(
+            transcript,
+            b"C3",
+            &compressed_bytes[..],
+        );
+
+        c4.serialize_compressed(&mut compressed_bytes).unwrap();
+        ZKAttestCompleteECPointAdditionTranscript::append_point(
+            transcript,
+            b"C4",
+            &compressed_bytes[..],
+        );
+
+        c5.serialize_compressed(&mut compressed_bytes).unwrap();
+        ZKAttestCompleteECPointAdditionTranscript::append_point(
+            transcript,
+            b"C5",
+            &compressed_bytes[..],
+        );
+
+        c6.serialize_compressed(&mut compressed_bytes).unwrap();
+        ZKAttestCompleteECPointAdditionTranscript::append_point(
+            transcript,
+            b"C6",
+            &compressed_bytes[..],
+        );
+    }
+
+    /// make_subproof_transcripts. This function simply adds all of the relevant commitments and
+    /// subproof information to the `transcript`. Note that this function accepts any kind of
+    /// `MulProofTranscriptable` and `EqualityProofTranscriptable` objects.
+    /// # Arguments
+    /// * `transcript` - the transcript object.
+    /// * `ci` - the commitments.
+    /// * `mpi` - the multiplication proof transcript objects.
+    /// * `epi` - the equality proof transcript objects.
+    #[allow(clippy::too_many_arguments)]
+    pub fn make_subproof_transcripts<
+        MP: MulProofTranscriptable<Affine = sw::Affine<P>>,
+        EP: EqualityProofTranscriptable<Affine = sw::Affine<P>>,
+    >(
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        c2: &sw::Affine<P>,
+        c3: &sw::Affine<P>,
+        c4: &sw::Affine<P>,
+        c5: &sw::Affine<P>,
+        c6: &sw::Affine<P>,
+        c8: &sw::Affine<P>,
+        c9: &sw::Affine<P>,
+        c10: &sw::Affine<P>,
+        c11: &sw::Affine<P>,
+        c13: &sw::Affine<P>,
+        c14: &sw::Affine<P>,
+        c15: &sw::Affine<P>,
+        mp: [&MP; 7],
+        ep1: &EP,
+        ep2: &EP,
+    ) {
+        // Proof for c7 having an inverse.
+        let c7 = (c2.into_group() + c4).into_affine();
+        mp[0].add_to_transcript(transcript, &c7, c8, &Self::commit_one().comm);
+
+        // Proofs of the squares and of the cross term of the numerator.
+        mp[1].add_to_transcript(transcript, c1, c1, c9);
+        mp[2].add_to_transcript(transcript, c1, c3, c10);
+        mp[3].add_to_transcript(transcript, c3, c3, c11);
+
+        // Proof of the slope, where we recover c12 as c9 + c10 + c11 + A*g.
+        let c12 = (c9.into_group() + c10 + c11 + Self::commit_coeff_a().comm).into_affine();
+        mp[4].add_to_transcript(transcript, &c12, c8, c13);
+        mp[5].add_to_transcript(transcript, c13, c13, c14);
+
+        let c16 = (c1.into_group() - c5).into_affine();
+        mp[6].add_to_transcript(transcript, c13, &c16, c15);
+
+        let c17 = (c5.into_group() + c1 + c3).into_affine();
+        ep1.add_to_transcript(transcript, &c17, c14);
+
+        let c18 = (c6.into_group() + c2).into_affine();
+        ep2.add_to_transcript(transcript, c15, &c18);
+    }
+}
+
+impl<P: PedersenConfig> PointAddProtocol<P> for ZKAttestCompletePointAddProof<P> {
+    type Intermediate = ZKAttestCompletePointAddProofIntermediate<P>;
+    type IntermediateTranscript = ZKAttestCompletePointAddProofIntermediateTranscript<P>;
+
+    fn challenge_scalar(transcript: &mut Transcript) -> [u8; 64] {
+        ZKAttestCompleteECPointAdditionTranscript::challenge_scalar(
+            transcript,
+            ZK_ATTEST_COMPLETE_EC_POINT_ADDITION_LABELS.challenge,
+        )
+    }
+
+    /// make_intermediate_transcript. This function accepts a set of intermediates (`inter`) and builds
+    /// a new intermediate transcript object from `inter`.
+    /// # Arguments
+    /// * `inter` - the intermediate objects.
+    fn make_intermediate_transcript(
+        inter: ZKAttestCompletePointAddProofIntermediate<P>,
+    ) -> ZKAttestCompletePointAddProofIntermediateTranscript<P> {
+        ZKAttestCompletePointAddProofIntermediateTranscript {
+            c8: inter.c8.comm,
+            c9: inter.c9.comm,
+            c10: inter.c10.comm,
+            c11: inter.c11.comm,
+            c13: inter.c13.comm,
+            c14: inter.c14.comm,
+            c15: inter.c15.comm,
+            mp1: MulProof::make_intermediate_transcript(inter.mpi1),
+            mp2: MulProof::make_intermediate_transcript(inter.mpi2),
+            mp3: MulProof::make_intermediate_transcript(inter.mpi3),
+            mp4: MulProof::make_intermediate_transcript(inter.mpi4),
+            mp5: MulProof::make_intermediate_transcript(inter.mpi5),
+            mp6: MulProof::make_intermediate_transcript(inter.mpi6),
+            mp7: MulProof::make_intermediate_transcript(inter.mpi7),
+            e1: EqualityProof::make_intermediate_transcript(inter.ei1),
+            e2: EqualityProof::make_intermediate_transcript(inter.ei2),
+        }
+    }
+
+    /// create_intermediates_with_existing_commitments. This function returns all of the intermediate
+    /// values for a proof that `t = a + b` using an existing set of commitments.
+    /// Unlike `ZKAttestPointAddProof`, this function accepts `a == b`, and never panics.
+    /// # Arguments
+    /// * `transcript` - the transcript object.
+    /// * `rng` - the random number generator. This must be a cryptographically secure RNG.
+    /// * `a` - one of the components of the sum.
+    /// * `b` - the other component of the sum.
+    /// * `t` - the target point (i.e t = a + b).
+    /// * `ci` - the commitments to the points. In particular, c1 and c2 are commitments
+    ///    to a.x and a.y. The same pattern holds for the others.
+    #[allow(clippy::too_many_arguments)]
+    fn create_intermediates_with_existing_commitments<T: RngCore + CryptoRng>(
+        transcript: &mut Transcript,
+        rng: &mut T,
+        a: sw::Affine<<P as PedersenConfig>::OCurve>,
+        b: sw::Affine<<P as PedersenConfig>::OCurve>,
+        t: sw::Affine<<P as PedersenConfig>::OCurve>,
+        c1: &PedersenComm<P>,
+        c2: &PedersenComm<P>,
+        c3: &PedersenComm<P>,
+        c4: &PedersenComm<P>,
+        c5: &PedersenComm<P>,
+        c6: &PedersenComm<P>,
+    ) -> ZKAttestCompletePointAddProofIntermediate<P> {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(
+            transcript,
+            &(a, b, t, [c1.r, c2.r, c3.r, c4.r, c5.r, c6.r]),
+            rng,
+        );
+
+        Self::make_transcript(
+            transcript, &c1.comm, &c2.comm, &c3.comm, &c4.comm, &c5.comm, &c6.comm,
+        );
+
+        let (inv, ax2, axbx, bx2, slope, diff) = Self::witnesses(&a, &b, &t);
+
+        // Now make the proof that there's an inverse for a.y + b.y.
+        let c7 = c2 + c4;
+        let c8 = PedersenComm::new(inv, rng);
+        let mpi1 = MulProof::create_intermediates(transcript, rng, &c7, &c8, &Self::commit_one());
+
+        // Proofs of the terms of the numerator.
+        let c9 = PedersenComm::new(ax2, rng);
+        let mpi2 = MulProof::create_intermediates(transcript, rng, c1, c1, &c9);
+        let c10 = PedersenComm::new(axbx, rng);
+        let mpi3 = MulProof::create_intermediates(transcript, rng, c1, c3, &c10);
+        let c11 = PedersenComm::new(bx2, rng);
+        let mpi4 = MulProof::create_intermediates(transcript, rng, c3, c3, &c11);
+
+        // Proof of the slope, and of its square.
+        let c12 = c9 + c10 + c11 + Self::commit_coeff_a();
+        let c13 = PedersenComm::new(slope, rng);
+        let mpi5 = MulProof::create_intermediates(transcript, rng, &c12, &c8, &c13);
+        let c14 = PedersenComm::new(slope * slope, rng);
+        let mpi6 = MulProof::create_intermediates(transcript, rng, &c13, &c13, &c14);
+
+        // Proof of the slope times a.x - t.x.
+        let c16 = c1 - c5;
+        let c15 = PedersenComm::new(slope * diff, rng);
+        let mpi7 = MulProof::create_intermediates(transcript, rng, &c13, &c16, &c15);
+
+        // And now the remaining equality proofs.
+        let c17 = c5 + c1 + c3;
+        let ei1 = EqualityProof::create_intermediates(transcript, rng, &c17, &c14);
+        let c18 = c6 + c2;
+        let ei2 = EqualityProof::create_intermediates(transcript, rng, &c15, &c18);
+
+        ZKAttestCompletePointAddProofIntermediate {
+            c8,
+            c9,
+            c10,
+            c11,
+            c13,
+            c14,
+            c15,
+            mpi1,
+            mpi2,
+            mpi3,
+            mpi4,
+            mpi5,
+            mpi6,
+            mpi7,
+            ei1,
+            ei2,
+        }
+    }
+
+    /// create_proof_with_challenge. This function produces a complete point addition proof
+    /// for `t = a + b` using the challenge `chal`.
+    /// # Arguments
+    /// * `a` - one of the summands.
+    /// * `b` - the other summand.
+    /// * `t` - the target point (e.g `t = a + b`).
+    /// * `inter` - the intermediate values.
+    /// * `ci` - the commitments to the co-ordinates.
+    /// * `chal` - the challenge.
+    fn create_proof_with_challenge(
+        a: sw::Affine<<P as PedersenConfig>::OCurve>,
+        b: sw::Affine<<P as PedersenConfig>::OCurve>,
+        t: sw::Affine<<P as PedersenConfig>::OCurve>,
+        inter: &ZKAttestCompletePointAddProofIntermediate<P>,
+        c1: &PedersenComm<P>,
+        c2: &PedersenComm<P>,
+        c3: &PedersenComm<P>,
+        c4: &PedersenComm<P>,
+        c5: &PedersenComm<P>,
+        c6: &PedersenComm<P>,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> Self {
+        let (inv, _, _, _, slope, diff) = Self::witnesses(&a, &b, &t);
+        let ax = <P as PedersenConfig>::from_ob_to_sf(a.x);
+        let bx = <P as PedersenConfig>::from_ob_to_sf(b.x);
+        let sum_y =
+            <P as PedersenConfig>::from_ob_to_sf(a.y) + <P as PedersenConfig>::from_ob_to_sf(b.y);
+
+        let c7 = c2 + c4;
+        let mp1 = MulProof::create_proof_with_challenge(
+            &sum_y,
+            &inv,
+            &inter.mpi1,
+            &c7,
+            &inter.c8,
+            &Self::commit_one(),
+            chal,
+        );
+
+        let mp2 =
+            MulProof::create_proof_with_challenge(&ax, &ax, &inter.mpi2, c1, c1, &inter.c9, chal);
+        let mp3 =
+            MulProof::create_proof_with_challenge(&ax, &bx, &inter.mpi3, c1, c3, &inter.c10, chal);
+        let mp4 =
+            MulProof::create_proof_with_challenge(&bx, &bx, &inter.mpi4, c3, c3, &inter.c11, chal);
+
+        let c12 = inter.c9 + inter.c10 + inter.c11 + Self::commit_coeff_a();
+        let numerator = slope_numerator::<P>(&ax, &bx);
+        let mp5 = MulProof::create_proof_with_challenge(
+            &numerator,
+            &inv,
+            &inter.mpi5,
+            &c12,
+            &inter.c8,
+            &inter.c13,
+            chal,
+        );
+        let mp6 = MulProof::create_proof_with_challenge(
+            &slope,
+            &slope,
+            &inter.mpi6,
+            &inter.c13,
+            &inter.c13,
+            &inter.c14,
+            chal,
+        );
+
+        let c16 = c1 - c5;
+        let mp7 = MulProof::create_proof_with_challenge(
+            &slope,
+            &diff,
+            &inter.mpi7,
+            &inter.c13,
+            &c16,
+            &inter.c15,
+            chal,
+        );
+
+        let c17 = c5 + c1 + c3;
+        let e1 = EqualityProof::create_proof_with_challenge(&inter.ei1, &c17, &inter.c14, chal);
+        let c18 = c6 + c2;
+        let e2 = EqualityProof::create_proof_with_challenge(&inter.ei2, &inter.c15, &c18, chal);
+
+        Self {
+            c8: inter.c8.comm,
+            c9: inter.c9.comm,
+            c10: inter.c10.comm,
+            c11: inter.c11.comm,
+            c13: inter.c13.comm,
+            c14: inter.c14.comm,
+            c15: inter.c15.comm,
+            mp1,
+            mp2,
+            mp3,
+            mp4,
+            mp5,
+            mp6,
+            mp7,
+            e1,
+            e2,
+        }
+    }
+
+    /// verify_with_challenge. This function verifies that the proof object held by `self` is valid.
+    /// Note that this function uses `chal` as the challenge for this verification.
+    /// # Arguments
+    /// * `self` - the proof object.
+    /// * `ci` - the commitments.
+    /// * `chal` - the challenge.
+    fn verify_with_challenge(
+        &self,
+        c1: &sw::Affine<P>,
+        c2: &sw::Affine<P>,
+        c3: &sw::Affine<P>,
+        c4: &sw::Affine<P>,
+        c5: &sw::Affine<P>,
+        c6: &sw::Affine<P>,
+        chal: &<P as CurveConfig>::ScalarField,
+    ) -> bool {
+        // Verify that c7 * c8 is a commitment to 1, with c7 = c2 + c4.
+        let c7 = (c2.into_group() + c4).into_affine();
+        let first = self
+            .mp1
+            .verify_with_challenge(&c7, &self.c8, &Self::commit_one().comm, chal);
+
+        // Verify the terms of the numerator.
+        let second = self.mp2.verify_with_challenge(c1, c1, &self.c9, chal);
+        let third = self.mp3.verify_with_challenge(c1, c3, &self.c10, chal);
+        let fourth = self.mp4.verify_with_challenge(c3, c3, &self.c11, chal);
+
+        // Verify the slope, and its square.
+        let c12 = (self.c9.into_group() + self.c10 + self.c11 + Self::commit_coeff_a().comm)
+            .into_affine();
+        let fifth = self
+            .mp5
+            .verify_with_challenge(&c12, &self.c8, &self.c13, chal);
+        let sixth = self
+            .mp6
+            .verify_with_challenge(&self.c13, &self.c13, &self.c14, chal);
+
+        // Verify c15 = c13 * (c1 - c5).
+        let c16 = (c1.into_group() - c5).into_affine();
+        let seventh = self
+            .mp7
+            .verify_with_challenge(&self.c13, &c16, &self.c15, chal);
+
+        // Verify that c5 + c1 + c3 == c14, and that c15 == c6 + c2.
+        let c17 = (*c5 + c1 + c3).into_affine();
+        let eighth = self.e1.verify_with_challenge(&c17, &self.c14, chal);
+        let c18 = (*c6 + c2).into_affine();
+        let ninth = self.e2.verify_with_challenge(&self.c15, &c18, chal);
+
+        first && second && third && fourth && fifth && sixth && seventh && eighth && ninth
+    }
+
+    /// serialized_size. Returns the number of bytes needed to represent this proof object once serialised.
+    fn serialized_size(&self) -> usize {
+        self.c8.compressed_size()
+            + self.c9.compressed_size()
+            + self.c10.compressed_size()
+            + self.c11.compressed_size()
+            + self.c13.compressed_size()
+            + self.c14.compressed_size()
+            + self.c15.compressed_size()
+            + self.mp1.serialized_size()
+            + self.mp2.serialized_size()
+            + self.mp3.serialized_size()
+            + self.mp4.serialized_size()
+            + self.mp5.serialized_size()
+            + self.mp6.serialized_size()
+            + self.mp7.serialized_size()
+            + self.e1.serialized_size()
+            + self.e2.serialized_size()
+    }
+
+    fn add_proof_to_transcript(
+        &self,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        c2: &sw::Affine<P>,
+        c3: &sw::Affine<P>,
+        c4: &sw::Affine<P>,
+        c5: &sw::Affine<P>,
+        c6: &sw::Affine<P>,
+    ) {
+        self.add_to_transcript(transcript, c1, c2, c3, c4, c5, c6);
+    }
+}
+
+impl<P: PedersenConfig> ZKAttestCompletePointAddProofTranscriptable<P>
+    for ZKAttestCompletePointAddProof<P>
+{
+    type Affine = sw::Affine<P>;
+    fn add_to_transcript(
+        &self,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        c2: &sw::Affine<P>,
+        c3: &sw::Affine<P>,
+        c4: &sw::Affine<P>,
+        c5: &sw::Affine<P>,
+        c6: &sw::Affine<P>,
+    ) {
+        ZKAttestCompletePointAddProof::make_transcript(transcript, c1, c2, c3, c4, c5, c6);
+        ZKAttestCompletePointAddProof::make_subproof_transcripts(
+            transcript,
+            c1,
+            c2,
+            c3,
+            c4,
+            c5,
+            c6,
+            &self.c8,
+            &self.c9,
+            &self.c10,
+            &self.c11,
+            &self.c13,
+            &self.c14,
+            &self.c15,
+            [
+                &self.mp1, &self.mp2, &self.mp3, &self.mp4, &self.mp5, &self.mp6, &self.mp7,
+            ],
+            &self.e1,
+            &self.e2,
+        );
+    }
+}
+
+impl<P: PedersenConfig> ZKAttestCompletePointAddProofTranscriptable<P>
+    for ZKAttestCompletePointAddProofIntermediate<P>
+{
+    type Affine = sw::Affine<P>;
+    fn add_to_transcript(
+        &self,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        c2: &sw::Affine<P>,
+        c3: &sw::Affine<P>,
+        c4: &sw::Affine<P>,
+        c5: &sw::Affine<P>,
+        c6: &sw::Affine<P>,
+    ) {
+        ZKAttestCompletePointAddProof::make_transcript(transcript, c1, c2, c3, c4, c5, c6);
+        ZKAttestCompletePointAddProof::make_subproof_transcripts(
+            transcript,
+            c1,
+            c2,
+            c3,
+            c4,
+            c5,
+            c6,
+            &self.c8.comm,
+            &self.c9.comm,
+            &self.c10.comm,
+            &self.c11.comm,
+            &self.c13.comm,
+            &self.c14.comm,
+            &self.c15.comm,
+            [
+                &self.mpi1, &self.mpi2, &self.mpi3, &self.mpi4, &self.mpi5, &self.mpi6, &self.mpi7,
+            ],
+            &self.ei1,
+            &self.ei2,
+        );
+    }
+}
+
+impl<P: PedersenConfig> ZKAttestCompletePointAddProofTranscriptable<P>
+    for ZKAttestCompletePointAddProofIntermediateTranscript<P>
+{
+    type Affine = sw::Affine<P>;
+    fn add_to_transcript(
+        &self,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P>,
+        c2: &sw::Affine<P>,
+        c3: &sw::Affine<P>,
+        c4: &sw::Affine<P>,
+        c5: &sw::Affine<P>,
+        c6: &sw::Affine<P>,
+    ) {
+        ZKAttestCompletePointAddProof::make_transcript(transcript, c1, c2, c3, c4, c5, c6);
+        ZKAttestCompletePointAddProof::make_subproof_transcripts(
+            transcript,
+            c1,
+            c2,
+            c3,
+            c4,
+            c5,
+            c6,
+            &self.c8,
+            &self.c9,
+            &self.c10,
+            &self.c11,
+            &self.c13,
+            &self.c14,
+            &self.c15,
+            [
+                &self.mp1, &self.mp2, &self.mp3, &self.mp4, &self.mp5, &self.mp6, &self.mp7,
+            ],
+            &self.e1,
+            &self.e2,
+        );
+    }
+}
+
+impl<P: PedersenConfig> ZKAttestCompletePointAddProofIntermediateTranscript<P> {
+    /// serialized_size. Returns the number of bytes needed to represent this proof object once serialised.
+    pub fn serialized_size(&self) -> usize {
+        self.c8.compressed_size()
+            + self.c9.compressed_size()
+            + self.c10.compressed_size()
+            + self.c11.compressed_size()
+            + self.c13.compressed_size()
+            + self.c14.compressed_size()
+            + self.c15.compressed_size()
+            + self.mp1.serialized_size()
+            + self.mp2.serialized_size()
+            + self.mp3.serialized_size()
+            + self.mp4.serialized_size()
+            + self.mp5.serialized_size()
+            + self.mp6.serialized_size()
+            + self.mp7.serialized_size()
+            + self.e1.serialized_size()
+            + self.e2.serialized_size()
+    }
+}
@@ -24,7 +24,9 @@ use crate::{
     },
     pedersen_config::{PedersenComm, PedersenConfig},
     point_add::PointAddProtocol,
-    transcript::ZKAttestECPointAdditionTranscript,
+    transcript::{
+        witness_rng, ZKAttestECPointAdditionTranscript, ZK_ATTEST_EC_POINT_ADDITION_LABELS,
+    },
 };
 
 /// ZKAttestPointAddProofTranscriptable. This trait provides a notion of `Transcriptable`, which
@@ -252,7 +254,10 @@ impl<P: PedersenConfig> PointAddProtocol<P> for ZKAttestPointAddProof<P> {
     type IntermediateTranscript = ZKAttestPointAddProofIntermediateTranscript<P>;
 
     fn challenge_scalar(transcript: &mut Transcript) -> [u8; 64] {
-        ZKAttestECPointAdditionTranscript::challenge_scalar(transcript, b"c")
+        ZKAttestECPointAdditionTranscript::challenge_scalar(
+            transcript,
+            ZK_ATTEST_EC_POINT_ADDITION_LABELS.challenge,
+        )
     }
 
     /// make_intermediate_transcript. This function accepts a set of intermediates (`inter`) and builds
@@ -302,6 +307,13 @@ impl<P: PedersenConfig> PointAddProtocol<P> for ZKAttestPointAddProof<P> {
         c5: &PedersenComm<P>,
         c6: &PedersenComm<P>,
     ) -> ZKAttestPointAddProofIntermediate<P> {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(
+            transcript,
+            &(a, b, t, [c1.r, c2.r, c3.r, c4.r, c5.r, c6.r]),
+            rng,
+        );
+
         // We require that a != b.
         assert!(a != b);
 
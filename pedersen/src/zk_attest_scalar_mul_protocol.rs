@@ -17,7 +17,7 @@ use crate::{
     pedersen_config::{PedersenComm, PedersenConfig},
     point_add::PointAddProtocol,
     scalar_mul::ScalarMulProtocol,
-    transcript::ZKAttestECScalarMulTranscript,
+    transcript::{witness_rng, ZKAttestECScalarMulTranscript, ZK_ATTEST_EC_SCALAR_MUL_LABELS},
     zk_attest_point_add_protocol::{
         ZKAttestPointAddProof, ZKAttestPointAddProofIntermediate,
         ZKAttestPointAddProofIntermediateTranscript, ZKAttestPointAddProofTranscriptable,
@@ -155,7 +155,7 @@ impl<P: PedersenConfig> ScalarMulProtocol<P> for ZKAttestECScalarMulProof<P> {
     }
 
     fn challenge_scalar(transcript: &mut Transcript) -> [u8; 64] {
-        transcript.challenge_scalar(b"c")
+        transcript.challenge_scalar(ZK_ATTEST_EC_SCALAR_MUL_LABELS.challenge)
     }
 
     /// make_intermediate_transcript. This function accepts a set of intermediates (`inter`) and builds
@@ -197,10 +197,13 @@ impl<P: PedersenConfig> ScalarMulProtocol<P> for ZKAttestECScalarMulProof<P> {
         lambda: &<<P as PedersenConfig>::OCurve as CurveConfig>::ScalarField,
         p: &sw::Affine<<P as PedersenConfig>::OCurve>,
         c1: &sw::Affine<P::OCurve>,
-        _r1: &<P::OCurve as CurveConfig>::ScalarField,
+        r1: &<P::OCurve as CurveConfig>::ScalarField,
         c2: &PedersenComm<P>,
         c3: &PedersenComm<P>,
     ) -> ZKAttestECScalarMulProofIntermediate<P> {
+        // Rekey the RNG with the witness, so that a weak `rng` does not leak it.
+        let rng = &mut witness_rng(transcript, &((*s, *p), *lambda, *r1, [c2.r, c3.r]), rng);
+
         // First we make the unique alpha value. Note that ZKAttest places no restriction on the
         // value of alpha.
         let alpha = <P as PedersenConfig>::get_random_p(rng);
@@ -364,6 +367,18 @@ impl<P: PedersenConfig> ScalarMulProtocol<P> for ZKAttestECScalarMulProof<P> {
     ) {
         self.add_to_transcript(transcript, c1, c2, c3);
     }
+
+    /// add_intermediate_to_transcript. This function adds the intermediate values in `inter`
+    /// to the `transcript`.
+    fn add_intermediate_to_transcript(
+        inter: &Self::Intermediate,
+        transcript: &mut Transcript,
+        c1: &sw::Affine<P::OCurve>,
+        c2: &sw::Affine<P>,
+        c3: &sw::Affine<P>,
+    ) {
+        inter.add_to_transcript(transcript, c1, c2, c3);
+    }
 }
 
 impl<P: PedersenConfig> ZKAttestECScalarMulProof<P> {
@@ -570,15 +585,16 @@ impl<P: PedersenConfig> ZKAttestECScalarMulProof<P> {
         // One or the other of these must be set.
         assert!(self.pii.is_none() ^ self.pi.is_none());
 
+        // N.B A proof that was decoded from elsewhere (e.g. by the `zkp_ecdsa` module) may hold the
+        // wrong one for its challenge. Such a proof is rejected, rather than trusted to be consistent.
         if let Some(proof) = &self.pi {
             // This can only happen if chal0 == 1.
-            assert!(*chal0 == <P as PedersenConfig>::CP1);
-            worked =
-                proof.verify_with_challenge(&self.c4, &self.c5, c2, c3, &self.a2, &self.a3, chal1);
+            worked = *chal0 == <P as PedersenConfig>::CP1
+                && proof
+                    .verify_with_challenge(&self.c4, &self.c5, c2, c3, &self.a2, &self.a3, chal1);
         } else {
             // This can only happen if chal0 = 0.
-            assert!(*chal0 == <P as PedersenConfig>::CM1);
-            worked = true;
+            worked = *chal0 == <P as PedersenConfig>::CM1;
         }
 
         // And, finally, check all sub-portions.
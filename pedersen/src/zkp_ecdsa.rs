@@ -0,0 +1,700 @@
+//! Defines the JSON representation of the ZKAttest proofs, for interoperability with
+//! Cloudflare's zkp-ecdsa (https://github.com/cloudflare/zkp-ecdsa).
+//!
+//! The ZKAttest protocols of this crate are transcribed from zkp-ecdsa, so a proof made by either
+//! implementation consists of the same values. This module converts the proofs to and from JSON,
+//! so that proofs made in a browser can be checked by this crate (and vice versa):
+//!
+//! * every proof is a JSON object with one member per value of the proof, named after the
+//!   corresponding field of the proof struct (e.g. `"c8"` or `"mp1"`), which in turn follows the
+//!   naming of zkp-ecdsa.
+//! * a point is the lowercase hex encoding of its compressed SEC1 encoding, i.e `02` or `03`
+//!   followed by the big-endian x co-ordinate, or `00` for the point at infinity.
+//! * a scalar is the lowercase hex encoding of its big-endian, fixed-length encoding.
+//! * a sub-proof is a nested object, and a list of sub-proofs is an array.
+//!
+//! The decoder is strict: it rejects a missing member, a point that is not on the curve, and a
+//! scalar that is not reduced. It ignores members that it does not know.
+//!
+//! N.B The representation is shared, but the Fiat-Shamir challenges are not: zkp-ecdsa derives
+//! them with its own hash, whereas this crate uses Merlin transcripts. A proof made by zkp-ecdsa
+//! must therefore be checked against the challenges that it was made with, via
+//! `PointAddProtocol::verify_with_challenge` and `ScalarMulProtocol::verify_with_challenge_byte`.
+//! The `ZkpEcdsaVector` fixtures record these challenges alongside the proofs.
+
+use ark_ec::{
+    short_weierstrass::{self as sw, SWCurveConfig},
+    AffineRepr, CurveGroup,
+};
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_std::ops::Mul;
+use merlin::Transcript;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use serde_json::{json, Value};
+
+use crate::{
+    ecdsa_protocol::ECDSASigProof,
+    equality_protocol::{EqualityProof, EqualityProofIntermediateTranscript},
+    fs_scalar_mul_protocol::FSECScalarMulProof,
+    mul_protocol::{MulProof, MulProofIntermediateTranscript},
+    pedersen_config::PedersenConfig,
+    point_add::PointAddProtocol,
+    scalar_mul::ScalarMulProtocol,
+    zk_attest_collective::ZKAttestCollective,
+    zk_attest_point_add_protocol::{
+        ZKAttestPointAddProof, ZKAttestPointAddProofIntermediateTranscript,
+    },
+    zk_attest_scalar_mul_protocol::ZKAttestECScalarMulProof,
+};
+
+/// ZkpEcdsaJson. This trait converts a proof to and from the JSON representation of zkp-ecdsa.
+pub trait ZkpEcdsaJson: Sized {
+    /// to_zkp_ecdsa. Returns the JSON value that represents `self`.
+    fn to_zkp_ecdsa(&self) -> Value;
+
+    /// from_zkp_ecdsa. This function decodes a proof from its JSON value. This function fails
+    /// if `value` does not represent a proof of this type.
+    /// # Arguments
+    /// * `value` - the JSON value of the proof.
+    fn from_zkp_ecdsa(value: &Value) -> Result<Self, String>;
+
+    /// to_zkp_ecdsa_json. Returns the JSON representation of `self`.
+    fn to_zkp_ecdsa_json(&self) -> String {
+        self.to_zkp_ecdsa().to_string()
+    }
+
+    /// from_zkp_ecdsa_json. This function parses a proof from its JSON representation. This
+    /// function fails if `json` is not the representation of a proof of this type.
+    /// # Arguments
+    /// * `json` - the JSON representation of the proof.
+    fn from_zkp_ecdsa_json(json: &str) -> Result<Self, String> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| format!("zkp-ecdsa: invalid JSON: {}", e))?;
+        Self::from_zkp_ecdsa(&value)
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>, String> {
+    if s.len() & 1 == 1 {
+        return Err("zkp-ecdsa: odd length hex string".to_string());
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            s.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+                .ok_or_else(|| format!("zkp-ecdsa: invalid hex byte at position {}", i))
+        })
+        .collect()
+}
+
+/// field_to_bytes. Returns the big-endian encoding of `x`, padded to the size of its modulus.
+fn field_to_bytes<F: PrimeField>(x: &F) -> Vec<u8> {
+    let len = (F::MODULUS_BIT_SIZE as usize).div_ceil(8);
+    let bytes = x.into_bigint().to_bytes_be();
+    bytes[bytes.len() - len..].to_vec()
+}
+
+/// field_from_bytes. This function decodes a field element from its big-endian encoding. This
+/// function fails if `bytes` is not the padded encoding of a reduced element.
+fn field_from_bytes<F: PrimeField>(bytes: &[u8]) -> Result<F, String> {
+    let x = F::from_be_bytes_mod_order(bytes);
+    if field_to_bytes(&x) != bytes {
+        return Err("zkp-ecdsa: non-canonical field element".to_string());
+    }
+    Ok(x)
+}
+
+/// base_prime. Returns `x` as an element of its base prime field. This function panics if
+/// `F` is an extension field, which no curve in this crate is defined over.
+fn base_prime<F: Field>(x: &F) -> F::BasePrimeField {
+    assert_eq!(F::extension_degree(), 1, "zkp-ecdsa: not a prime field");
+    x.to_base_prime_field_elements().next().unwrap()
+}
+
+/// scalar_to_hex. Returns the hex encoding of the scalar `x`.
+/// # Arguments
+/// * `x` - the scalar.
+pub fn scalar_to_hex<F: PrimeField>(x: &F) -> String {
+    to_hex(&field_to_bytes(x))
+}
+
+/// scalar_from_hex. This function decodes a scalar from its hex encoding. This function fails
+/// if `s` is not the encoding of a reduced scalar.
+/// # Arguments
+/// * `s` - the hex encoding of the scalar.
+pub fn scalar_from_hex<F: PrimeField>(s: &str) -> Result<F, String> {
+    field_from_bytes(&from_hex(s)?)
+}
+
+/// point_to_hex. Returns the hex encoding of the compressed SEC1 encoding of `p`.
+/// # Arguments
+/// * `p` - the point.
+pub fn point_to_hex<C: SWCurveConfig>(p: &sw::Affine<C>) -> String {
+    if p.is_zero() {
+        return "00".to_string();
+    }
+
+    let y = base_prime(&p.y).into_bigint();
+    let mut bytes = vec![if y.is_odd() { 0x03 } else { 0x02 }];
+    bytes.extend(field_to_bytes(&base_prime(&p.x)));
+    to_hex(&bytes)
+}
+
+/// point_from_hex. This function decodes a point from the hex encoding of its compressed SEC1
+/// encoding. This function fails if `s` is not the encoding of a point of the prime order
+/// subgroup of the curve.
+/// # Arguments
+/// * `s` - the hex encoding of the point.
+pub fn point_from_hex<C: SWCurveConfig>(s: &str) -> Result<sw::Affine<C>, String> {
+    let bytes = from_hex(s)?;
+    if bytes == [0x00] {
+        return Ok(sw::Affine::identity());
+    }
+
+    let odd = match bytes.first() {
+        Some(0x02) => false,
+        Some(0x03) => true,
+        _ => return Err("zkp-ecdsa: invalid point prefix".to_string()),
+    };
+
+    if C::BaseField::extension_degree() != 1 {
+        return Err("zkp-ecdsa: not a prime field".to_string());
+    }
+    let x = C::BaseField::from_base_prime_field_elems(&[field_from_bytes(&bytes[1..])?])
+        .ok_or_else(|| "zkp-ecdsa: invalid x co-ordinate".to_string())?;
+    let (y1, y2) = sw::Affine::<C>::get_ys_from_x_unchecked(x)
+        .ok_or_else(|| "zkp-ecdsa: point is not on the curve".to_string())?;
+    let y = if base_prime(&y1).into_bigint().is_odd() == odd {
+        y1
+    } else {
+        y2
+    };
+
+    let p = sw::Affine::new_unchecked(x, y);
+    if !p.is_in_correct_subgroup_assuming_on_curve() {
+        return Err("zkp-ecdsa: point is not in the subgroup".to_string());
+    }
+    Ok(p)
+}
+
+/// member. Returns the member `name` of the JSON object `value`, or fails if there is none.
+fn member<'a>(value: &'a Value, name: &str) -> Result<&'a Value, String> {
+    value
+        .as_object()
+        .ok_or_else(|| "zkp-ecdsa: expected an object".to_string())?
+        .get(name)
+        .ok_or_else(|| format!("zkp-ecdsa: missing member {:?}", name))
+}
+
+/// string_member. Returns the member `name` of `value`, which must be a string.
+fn string_member<'a>(value: &'a Value, name: &str) -> Result<&'a str, String> {
+    member(value, name)?
+        .as_str()
+        .ok_or_else(|| format!("zkp-ecdsa: member {:?} is not a string", name))
+}
+
+/// point_member. Returns the point held by the member `name` of `value`.
+fn point_member<C: SWCurveConfig>(value: &Value, name: &str) -> Result<sw::Affine<C>, String> {
+    point_from_hex(string_member(value, name)?).map_err(|e| format!("{} ({})", e, name))
+}
+
+/// scalar_member. Returns the scalar held by the member `name` of `value`.
+fn scalar_member<F: PrimeField>(value: &Value, name: &str) -> Result<F, String> {
+    scalar_from_hex(string_member(value, name)?).map_err(|e| format!("{} ({})", e, name))
+}
+
+/// proof_member. Returns the proof held by the member `name` of `value`.
+fn proof_member<T: ZkpEcdsaJson>(value: &Value, name: &str) -> Result<T, String> {
+    T::from_zkp_ecdsa(member(value, name)?).map_err(|e| format!("{} ({})", e, name))
+}
+
+impl<P: PedersenConfig> ZkpEcdsaJson for EqualityProof<P> {
+    fn to_zkp_ecdsa(&self) -> Value {
+        json!({
+            "alpha": point_to_hex(&self.alpha),
+            "z": scalar_to_hex(&self.z),
+        })
+    }
+
+    fn from_zkp_ecdsa(value: &Value) -> Result<Self, String> {
+        Ok(Self {
+            alpha: point_member(value, "alpha")?,
+            z: scalar_member(value, "z")?,
+        })
+    }
+}
+
+impl<P: PedersenConfig> ZkpEcdsaJson for EqualityProofIntermediateTranscript<P> {
+    fn to_zkp_ecdsa(&self) -> Value {
+        json!({ "alpha": point_to_hex(&self.alpha) })
+    }
+
+    fn from_zkp_ecdsa(value: &Value) -> Result<Self, String> {
+        Ok(Self {
+            alpha: point_member(value, "alpha")?,
+        })
+    }
+}
+
+impl<P: PedersenConfig> ZkpEcdsaJson for MulProof<P> {
+    fn to_zkp_ecdsa(&self) -> Value {
+        json!({
+            "alpha": point_to_hex(&self.alpha),
+            "beta": point_to_hex(&self.beta),
+            "delta": point_to_hex(&self.delta),
+            "z1": scalar_to_hex(&self.z1),
+            "z2": scalar_to_hex(&self.z2),
+            "z3": scalar_to_hex(&self.z3),
+            "z4": scalar_to_hex(&self.z4),
+            "z5": scalar_to_hex(&self.z5),
+        })
+    }
+
+    fn from_zkp_ecdsa(value: &Value) -> Result<Self, String> {
+        Ok(Self {
+            alpha: point_member(value, "alpha")?,
+            beta: point_member(value, "beta")?,
+            delta: point_member(value, "delta")?,
+            z1: scalar_member(value, "z1")?,
+            z2: scalar_member(value, "z2")?,
+            z3: scalar_member(value, "z3")?,
+            z4: scalar_member(value, "z4")?,
+            z5: scalar_member(value, "z5")?,
+        })
+    }
+}
+
+impl<P: PedersenConfig> ZkpEcdsaJson for MulProofIntermediateTranscript<P> {
+    fn to_zkp_ecdsa(&self) -> Value {
+        json!({
+            "alpha": point_to_hex(&self.alpha),
+            "beta": point_to_hex(&self.beta),
+            "delta": point_to_hex(&self.delta),
+        })
+    }
+
+    fn from_zkp_ecdsa(value: &Value) -> Result<Self, String> {
+        Ok(Self {
+            alpha: point_member(value, "alpha")?,
+            beta: point_member(value, "beta")?,
+            delta: point_member(value, "delta")?,
+        })
+    }
+}
+
+impl<P: PedersenConfig> ZkpEcdsaJson for ZKAttestPointAddProof<P> {
+    fn to_zkp_ecdsa(&self) -> Value {
+        json!({
+            "c8": point_to_hex(&self.c8),
+            "c10": point_to_hex(&self.c10),
+            "c11": point_to_hex(&self.c11),
+            "c13": point_to_hex(&self.c13),
+            "mp1": self.mp1.to_zkp_ecdsa(),
+            "mp2": self.mp2.to_zkp_ecdsa(),
+            "mp3": self.mp3.to_zkp_ecdsa(),
+            "mp4": self.mp4.to_zkp_ecdsa(),
+            "e1": self.e1.to_zkp_ecdsa(),
+            "e2": self.e2.to_zkp_ecdsa(),
+        })
+    }
+
+    fn from_zkp_ecdsa(value: &Value) -> Result<Self, String> {
+        Ok(Self {
+            c8: point_member(value, "c8")?,
+            c10: point_member(value, "c10")?,
+            c11: point_member(value, "c11")?,
+            c13: point_member(value, "c13")?,
+            mp1: proof_member(value, "mp1")?,
+            mp2: proof_member(value, "mp2")?,
+            mp3: proof_member(value, "mp3")?,
+            mp4: proof_member(value, "mp4")?,
+            e1: proof_member(value, "e1")?,
+            e2: proof_member(value, "e2")?,
+        })
+    }
+}
+
+impl<P: PedersenConfig> ZkpEcdsaJson for ZKAttestPointAddProofIntermediateTranscript<P> {
+    fn to_zkp_ecdsa(&self) -> Value {
+        json!({
+            "c8": point_to_hex(&self.c8),
+            "c10": point_to_hex(&self.c10),
+            "c11": point_to_hex(&self.c11),
+            "c13": point_to_hex(&self.c13),
+            "mp1": self.mp1.to_zkp_ecdsa(),
+            "mp2": self.mp2.to_zkp_ecdsa(),
+            "mp3": self.mp3.to_zkp_ecdsa(),
+            "mp4": self.mp4.to_zkp_ecdsa(),
+            "e1": self.e1.to_zkp_ecdsa(),
+            "e2": self.e2.to_zkp_ecdsa(),
+        })
+    }
+
+    fn from_zkp_ecdsa(value: &Value) -> Result<Self, String> {
+        Ok(Self {
+            c8: point_member(value, "c8")?,
+            c10: point_member(value, "c10")?,
+            c11: point_member(value, "c11")?,
+            c13: point_member(value, "c13")?,
+            mp1: proof_member(value, "mp1")?,
+            mp2: proof_member(value, "mp2")?,
+            mp3: proof_member(value, "mp3")?,
+            mp4: proof_member(value, "mp4")?,
+            e1: proof_member(value, "e1")?,
+            e2: proof_member(value, "e2")?,
+        })
+    }
+}
+
+/// The point addition proof is held in `pi` if it was opened, and its transcript in `pii`
+/// otherwise. Exactly one of them must be present.
+impl<P: PedersenConfig> ZkpEcdsaJson for ZKAttestECScalarMulProof<P> {
+    fn to_zkp_ecdsa(&self) -> Value {
+        let mut value = json!({
+            "alpha": scalar_to_hex(&self.alpha),
+            "a1": point_to_hex(&self.a1),
+            "a2": point_to_hex(&self.a2),
+            "a3": point_to_hex(&self.a3),
+            "c4": point_to_hex(&self.c4),
+            "c5": point_to_hex(&self.c5),
+            "z1": scalar_to_hex(&self.z1),
+            "z2": scalar_to_hex(&self.z2),
+            "z3": scalar_to_hex(&self.z3),
+            "z4": scalar_to_hex(&self.z4),
+        });
+
+        let object = value.as_object_mut().unwrap();
+        match (&self.pi, &self.pii) {
+            (Some(pi), None) => object.insert("pi".to_string(), pi.to_zkp_ecdsa()),
+            (None, Some(pii)) => object.insert("pii".to_string(), pii.to_zkp_ecdsa()),
+            _ => panic!("zkp-ecdsa: exactly one of pi and pii must be set"),
+        };
+        value
+    }
+
+    fn from_zkp_ecdsa(value: &Value) -> Result<Self, String> {
+        let (pi, pii) = match (member(value, "pi"), member(value, "pii")) {
+            (Ok(_), Err(_)) => (Some(proof_member(value, "pi")?), None),
+            (Err(_), Ok(_)) => (None, Some(proof_member(value, "pii")?)),
+            _ => return Err("zkp-ecdsa: exactly one of \"pi\" and \"pii\" must be set".to_string()),
+        };
+
+        Ok(Self {
+            alpha: scalar_member(value, "alpha")?,
+            a1: point_member(value, "a1")?,
+            a2: point_member(value, "a2")?,
+            a3: point_member(value, "a3")?,
+            c4: point_member(value, "c4")?,
+            c5: point_member(value, "c5")?,
+            z1: scalar_member(value, "z1")?,
+            z2: scalar_member(value, "z2")?,
+            z3: scalar_member(value, "z3")?,
+            z4: scalar_member(value, "z4")?,
+            pi,
+            pii,
+        })
+    }
+}
+
+/// The repetitions are an array, which must hold as many proofs as the security level of `P`
+/// requires.
+impl<P: PedersenConfig> ZkpEcdsaJson for FSECScalarMulProof<P, ZKAttestECScalarMulProof<P>> {
+    fn to_zkp_ecdsa(&self) -> Value {
+        Value::Array(self.proofs().iter().map(|p| p.to_zkp_ecdsa()).collect())
+    }
+
+    fn from_zkp_ecdsa(value: &Value) -> Result<Self, String> {
+        let proofs = value
+            .as_array()
+            .ok_or_else(|| "zkp-ecdsa: expected an array".to_string())?
+            .iter()
+            .map(ZKAttestECScalarMulProof::from_zkp_ecdsa)
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_proofs(proofs)
+            .ok_or_else(|| "zkp-ecdsa: unexpected number of repetitions".to_string())
+    }
+}
+
+impl<P: PedersenConfig> ZkpEcdsaJson for ECDSASigProof<P, ZKAttestCollective> {
+    fn to_zkp_ecdsa(&self) -> Value {
+        json!({
+            "r": point_to_hex(&self.r),
+            "cq_x": point_to_hex(&self.cq_x),
+            "cq_y": point_to_hex(&self.cq_y),
+            "c_lhs_x": point_to_hex(&self.c_lhs_x),
+            "c_lhs_y": point_to_hex(&self.c_lhs_y),
+            "cz": point_to_hex(&self.cz),
+            "cs_x": point_to_hex(&self.cs_x),
+            "cs_xr": scalar_to_hex(&self.cs_xr),
+            "cs_y": point_to_hex(&self.cs_y),
+            "cs_yr": scalar_to_hex(&self.cs_yr),
+            "scalar_mul": self.scalar_mul.to_zkp_ecdsa(),
+            "point_add": self.point_add.to_zkp_ecdsa(),
+        })
+    }
+
+    fn from_zkp_ecdsa(value: &Value) -> Result<Self, String> {
+        Ok(Self {
+            r: point_member(value, "r")?,
+            cq_x: point_member(value, "cq_x")?,
+            cq_y: point_member(value, "cq_y")?,
+            c_lhs_x: point_member(value, "c_lhs_x")?,
+            c_lhs_y: point_member(value, "c_lhs_y")?,
+            cz: point_member(value, "cz")?,
+            cs_x: point_member(value, "cs_x")?,
+            cs_xr: scalar_member(value, "cs_xr")?,
+            cs_y: point_member(value, "cs_y")?,
+            cs_yr: scalar_member(value, "cs_yr")?,
+            scalar_mul: proof_member(value, "scalar_mul")?,
+            point_add: proof_member(value, "point_add")?,
+        })
+    }
+}
+
+/// The version of the zkp-ecdsa fixture format.
+pub const ZKP_ECDSA_VECTOR_VERSION: u64 = 1;
+
+/// ZkpEcdsaVector. This struct acts as a container for a seeded set of ZKAttest proofs, in the
+/// JSON representation of zkp-ecdsa, together with the public values and the challenges that
+/// they are checked against. The proofs cover every challenge, and hence every branch of the
+/// representation.
+///
+/// The point addition entries hold the `"challenge"` bit, the six `"commitments"` to the
+/// co-ordinates of `a`, `b` and `t = a + b`, and the `"proof"`. The scalar multiplication
+/// entries hold the `"challenge"` byte, the public point `"p"`, the three `"commitments"` to λ
+/// and to the co-ordinates of `s = λp`, and the `"proof"`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZkpEcdsaVector {
+    /// version: the version of the fixture format.
+    pub version: u64,
+    /// curve: the name of the curve the vector was produced on.
+    pub curve: String,
+    /// seed: the seed of the ChaCha20 RNG.
+    pub seed: u64,
+    /// point_add: the point addition entries, one per challenge.
+    pub point_add: Vec<Value>,
+    /// scalar_mul: the scalar multiplication entries, one per challenge.
+    pub scalar_mul: Vec<Value>,
+}
+
+impl ZkpEcdsaVector {
+    /// generate. This function makes a ZKAttest point addition proof for every challenge bit and
+    /// a ZKAttest scalar multiplication proof for every challenge byte, with all randomness
+    /// drawn from a ChaCha20 RNG seeded with `seed`. This function panics if the scalar field of
+    /// `P` is not the base field of `P::OCurve`, as the point addition proofs require.
+    /// # Arguments
+    /// * `curve` - the name of the curve.
+    /// * `seed` - the seed of the RNG.
+    pub fn generate<P: PedersenConfig>(curve: &str, seed: u64) -> Self {
+        let rng = &mut ChaCha20Rng::seed_from_u64(seed);
+        let g = <P::OCurve as SWCurveConfig>::GENERATOR;
+
+        let point_add = (0..2u8)
+            .map(|bit| {
+                let a = g.mul(P::get_random_p(rng)).into_affine();
+                let b = g.mul(P::get_random_p(rng)).into_affine();
+                let t = (a + b).into_affine();
+                let (c1, c2, c3, c4, c5, c6) = P::create_commitments_to_coords(a, b, t, rng);
+                let inter = ZKAttestPointAddProof::create_intermediates_with_existing_commitments(
+                    &mut Transcript::new(b""),
+                    rng,
+                    a,
+                    b,
+                    t,
+                    &c1,
+                    &c2,
+                    &c3,
+                    &c4,
+                    &c5,
+                    &c6,
+                );
+                let proof = ZKAttestPointAddProof::create_proof_with_challenge(
+                    a,
+                    b,
+                    t,
+                    &inter,
+                    &c1,
+                    &c2,
+                    &c3,
+                    &c4,
+                    &c5,
+                    &c6,
+                    &P::make_single_bit_challenge(bit),
+                );
+
+                let comms = [c1, c2, c3, c4, c5, c6].map(|c| point_to_hex(&c.comm));
+                json!({
+                    "challenge": bit,
+                    "commitments": comms,
+                    "proof": proof.to_zkp_ecdsa(),
+                })
+            })
+            .collect();
+
+        let scalar_mul = (0..4u8)
+            .map(|chal| {
+                let lambda = P::get_random_p(rng);
+                let p = g.mul(P::get_random_p(rng)).into_affine();
+                let s = p.mul(lambda).into_affine();
+                let (c1, r1) = P::create_commit_other(&lambda, rng);
+                let c2 = P::make_commitment_from_other(s.x, rng);
+                let c3 = P::make_commitment_from_other(s.y, rng);
+                let inter =
+                    ZKAttestECScalarMulProof::create_intermediates_with_existing_commitments(
+                        &mut Transcript::new(b""),
+                        rng,
+                        &s,
+                        &lambda,
+                        &p,
+                        &c1,
+                        &r1,
+                        &c2,
+                        &c3,
+                    );
+                let proof = ZKAttestECScalarMulProof::create_proof_with_challenge_byte(
+                    &s, &lambda, &p, &inter, chal, &c1, &r1, &c2, &c3,
+                );
+
+                let commitments = vec![
+                    point_to_hex(&c1),
+                    point_to_hex(&c2.comm),
+                    point_to_hex(&c3.comm),
+                ];
+                json!({
+                    "challenge": chal,
+                    "p": point_to_hex(&p),
+                    "commitments": commitments,
+                    "proof": proof.to_zkp_ecdsa(),
+                })
+            })
+            .collect();
+
+        Self {
+            version: ZKP_ECDSA_VECTOR_VERSION,
+            curve: curve.to_string(),
+            seed,
+            point_add,
+            scalar_mul,
+        }
+    }
+
+    /// to_json. This function returns the pretty-printed JSON encoding of the vector.
+    pub fn to_json(&self) -> String {
+        let value = json!({
+            "version": self.version,
+            "curve": self.curve,
+            "seed": self.seed,
+            "point_add": self.point_add,
+            "scalar_mul": self.scalar_mul,
+        });
+        serde_json::to_string_pretty(&value).expect("Failed to serialize zkp-ecdsa vector")
+    }
+
+    /// from_json. This function parses a vector from its JSON encoding.
+    /// # Arguments
+    /// * `json` - the JSON encoding of the vector.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| format!("Invalid zkp-ecdsa vector: {}", e))?;
+        let entries = |name: &str| -> Result<Vec<Value>, String> {
+            member(&value, name)?
+                .as_array()
+                .cloned()
+                .ok_or_else(|| format!("zkp-ecdsa: member {:?} is not an array", name))
+        };
+
+        Ok(Self {
+            version: member(&value, "version")?
+                .as_u64()
+                .ok_or_else(|| "zkp-ecdsa: invalid version".to_string())?,
+            curve: string_member(&value, "curve")?.to_string(),
+            seed: member(&value, "seed")?
+                .as_u64()
+                .ok_or_else(|| "zkp-ecdsa: invalid seed".to_string())?,
+            point_add: entries("point_add")?,
+            scalar_mul: entries("scalar_mul")?,
+        })
+    }
+
+    /// check. This function checks that every proof of the vector decodes under the given config
+    /// and verifies against its challenge, that decoding and re-encoding a proof reproduces it,
+    /// and that re-running the provers with the same seed reproduces the vector.
+    pub fn check<P: PedersenConfig>(&self) -> Result<(), String> {
+        if self.version != ZKP_ECDSA_VECTOR_VERSION {
+            return Err(format!(
+                "Unsupported zkp-ecdsa vector version {}",
+                self.version
+            ));
+        }
+
+        let challenge = |entry: &Value| -> Result<u8, String> {
+            member(entry, "challenge")?
+                .as_u64()
+                .and_then(|c| u8::try_from(c).ok())
+                .ok_or_else(|| "zkp-ecdsa: invalid challenge".to_string())
+        };
+        let commitments = |entry: &Value, len: usize| -> Result<Vec<String>, String> {
+            let comms: Vec<String> = member(entry, "commitments")?
+                .as_array()
+                .ok_or_else(|| "zkp-ecdsa: invalid commitments".to_string())?
+                .iter()
+                .filter_map(|c| c.as_str().map(str::to_string))
+                .collect();
+            if comms.len() != len {
+                return Err("zkp-ecdsa: unexpected number of commitments".to_string());
+            }
+            Ok(comms)
+        };
+
+        for (i, entry) in self.point_add.iter().enumerate() {
+            let bit = challenge(entry)?;
+            if bit > 1 {
+                return Err(format!("point addition {}: invalid challenge", i));
+            }
+            let c = commitments(entry, 6)?
+                .iter()
+                .map(|c| point_from_hex::<P>(c))
+                .collect::<Result<Vec<_>, _>>()?;
+            let proof: ZKAttestPointAddProof<P> = proof_member(entry, "proof")?;
+            if proof.to_zkp_ecdsa() != entry["proof"] {
+                return Err(format!("point addition {}: does not round trip", i));
+            }
+            let chal = P::make_single_bit_challenge(bit);
+            if !proof.verify_with_challenge(&c[0], &c[1], &c[2], &c[3], &c[4], &c[5], &chal) {
+                return Err(format!("point addition {}: does not verify", i));
+            }
+        }
+
+        for (i, entry) in self.scalar_mul.iter().enumerate() {
+            let chal = challenge(entry)?;
+            if chal > 3 {
+                return Err(format!("scalar multiplication {}: invalid challenge", i));
+            }
+            let p = point_member::<P::OCurve>(entry, "p")?;
+            let c = commitments(entry, 3)?;
+            let c1 = point_from_hex::<P::OCurve>(&c[0])?;
+            let c2 = point_from_hex::<P>(&c[1])?;
+            let c3 = point_from_hex::<P>(&c[2])?;
+            let proof: ZKAttestECScalarMulProof<P> = proof_member(entry, "proof")?;
+            if proof.to_zkp_ecdsa() != entry["proof"] {
+                return Err(format!("scalar multiplication {}: does not round trip", i));
+            }
+            if !proof.verify_with_challenge_byte(&p, chal, &c1, &c2, &c3) {
+                return Err(format!("scalar multiplication {}: does not verify", i));
+            }
+        }
+
+        if *self != Self::generate::<P>(&self.curve, self.seed) {
+            return Err("zkp-ecdsa vector does not match".to_string());
+        }
+
+        Ok(())
+    }
+}
@@ -13,7 +13,7 @@ use ark_secp256r1::Fr as secp256r1Fr;
 // be unable to deduce that...
 use ark_secp256r1::FrConfig as secp256FrConfig;
 #[warn(unused_imports)]
-use boomerang_macros::derive_conversion;
+use boomerang_macros::derive_boomerang_config;
 
 #[cfg(test)]
 mod tests;
@@ -72,22 +72,15 @@ pub const G_SECP256_O_Y: &str =
     "31468013646237722594854082025316614106172411895747863909393730389177298123724";
 
 // Now we instantiate everything else.
-derive_conversion!(
-    Config,
-    4,
-    128,
-    secp256r1conf,
-    G_GENERATOR_X2,
-    G_GENERATOR_Y2,
-    Fr,
-    FrConfig,
-    secp256r1Fq,
-    secp256r1Fr,
-    secp256FqConfig,
-    secp256FrConfig,
-    Affine,
-    "5",
-    "31468013646237722594854082025316614106172411895747863909393730389177298123724",
-    Config,
-    Config
+derive_boomerang_config!(
+    config: Config,
+    scalar_field: (Fr, FrConfig),
+    limbs: 4,
+    security: 128,
+    other_curve: secp256r1conf,
+    other_base_field: (secp256r1Fq, secp256FqConfig),
+    other_scalar_field: (secp256r1Fr, secp256FrConfig),
+    generator2: (G_GENERATOR_X2, G_GENERATOR_Y2),
+    other_generator2: ("5", "31468013646237722594854082025316614106172411895747863909393730389177298123724"),
+    id: T256,
 );
@@ -2,13 +2,15 @@ use crate::{Config, Projective};
 use ark_algebra_test_templates::*;
 use ark_ec::short_weierstrass::{self as sw};
 use ark_secp256r1::Config as secp256r1conf;
-use boomerang_macros::test_acl;
-use boomerang_macros::test_boomerang;
-use boomerang_macros::test_pedersen;
+use boomerang_macros::test_boomerang_config;
+use boomerang_macros::test_rfc6979;
+use boomerang_macros::test_vectors;
+use boomerang_macros::test_zkp_ecdsa;
 
 type OtherProject = sw::Projective<secp256r1conf>;
 
 test_group!(g1; Projective; sw);
-test_pedersen!(tp; Config, OtherProject);
-test_acl!(acl; Config, Config, OtherProject);
-test_boomerang!(boomerang; Config, Config, Config, OtherProject);
+test_boomerang_config!(Config, OtherProject);
+test_vectors!(vectors; Config, "t256", "testvectors/boomerang.json", "testvectors/acl.json");
+test_rfc6979!(rfc6979; Config, p256);
+test_zkp_ecdsa!(zkp_ecdsa; Config, "t256", "testvectors/zkp_ecdsa.json");
@@ -18,6 +18,9 @@
 //!
 //! Or, in decimal, a_4 = -3
 //!                 a_6 = 81531206846337786915455327229510804132577517753388365729879493166393691077718
+//!
+//! The curve is paired with NIST P-256 (`ark-secp256r1`) via `derive_conversion!`, which makes it
+//! usable for attesting P-256 ECDSA signatures with the ZKAttest protocols in the pedersen crate.
 
 #[cfg(feature = "r1cs")]
 pub mod constraints;
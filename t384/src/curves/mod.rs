@@ -13,7 +13,7 @@ use ark_secp384r1::Fr as secp384r1Fr;
 // be unable to deduce that...
 use ark_secp384r1::FrConfig as secp384FrConfig;
 #[warn(unused_imports)]
-use boomerang_macros::derive_conversion;
+use boomerang_macros::derive_boomerang_config;
 
 #[cfg(test)]
 mod tests;
@@ -61,22 +61,15 @@ pub const G_GENERATOR_X2: Fq = MontFp!("5");
 pub const G_GENERATOR_Y2 : Fq = MontFp!("6363885786003242131136944941369369468464707802299146445548164183900284786157464900151666199152091187308687891798230");
 
 // Now we instantiate everything else.
-derive_conversion!(
-    Config,
-    6,
-    192,
-    secp384r1conf,
-    G_GENERATOR_X2,
-    G_GENERATOR_Y2,
-    Fr,
-    FrConfig,
-    secp384r1Fq,
-    secp384r1Fr,
-    secp384FqConfig,
-    secp384FrConfig,
-    Affine,
-    "35844451280757088535875123965116225310073208726034463360736462178210365192733738092353369333892565847293721646292008",
-    "12852303813876583228171852252822018299502069287699403243661957712124279761251434632610206218878102281645617942246021",
-    Config,
-    Config
+derive_boomerang_config!(
+    config: Config,
+    scalar_field: (Fr, FrConfig),
+    limbs: 6,
+    security: 192,
+    other_curve: secp384r1conf,
+    other_base_field: (secp384r1Fq, secp384FqConfig),
+    other_scalar_field: (secp384r1Fr, secp384FrConfig),
+    generator2: (G_GENERATOR_X2, G_GENERATOR_Y2),
+    other_generator2: ("35844451280757088535875123965116225310073208726034463360736462178210365192733738092353369333892565847293721646292008", "12852303813876583228171852252822018299502069287699403243661957712124279761251434632610206218878102281645617942246021"),
+    id: T384,
 );
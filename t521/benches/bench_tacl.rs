@@ -0,0 +1,3 @@
+use boomerang_macros::bench_tacl_make_all;
+use t521::Config;
+bench_tacl_make_all!(Config, "t521");
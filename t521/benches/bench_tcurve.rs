@@ -0,0 +1,5 @@
+use ark_secp521r1::Config as secp521r1conf;
+use boomerang_macros::bench_tcurve_make_all;
+use t521::Config;
+type OtherProjectiveType = sw::Projective<secp521r1conf>;
+bench_tcurve_make_all!(Config, "t521", OtherProjectiveType);
@@ -0,0 +1,7 @@
+//! This module implements the R1CS equivalent of `ark_t521`
+
+mod curves;
+mod fields;
+
+pub use curves::*;
+pub use fields::*;
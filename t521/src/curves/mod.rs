@@ -0,0 +1,75 @@
+use ark_ec::{
+    models::CurveConfig,
+    short_weierstrass::{self as sw, SWCurveConfig},
+};
+
+use crate::{fq::Fq, fr::Fr, fr::FrConfig};
+use ark_secp521r1::Config as secp521r1conf;
+use ark_secp521r1::Fq as secp521r1Fq;
+use ark_secp521r1::FqConfig as secp521FqConfig;
+use ark_secp521r1::Fr as secp521r1Fr;
+#[allow(unused_imports)]
+// This is actually used in the macro below, but rustfmt seems to
+// be unable to deduce that...
+use ark_secp521r1::FrConfig as secp521FrConfig;
+#[warn(unused_imports)]
+use boomerang_macros::derive_boomerang_config;
+
+#[cfg(test)]
+mod tests;
+
+pub type Affine = sw::Affine<Config>;
+pub type Projective = sw::Projective<Config>;
+
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub struct Config;
+
+impl CurveConfig for Config {
+    type BaseField = Fq;
+    type ScalarField = Fr;
+
+    // We're dealing with prime order curves.
+
+    /// COFACTOR = 1
+    const COFACTOR: &'static [u64] = &[0x1];
+
+    /// COFACTOR_INV = COFACTOR^{-1} mod r = 1
+    const COFACTOR_INV: Fr = Fr::ONE;
+}
+
+impl SWCurveConfig for Config {
+    /// COEFF_A = a4 in the docs, which is a very large string.
+    const COEFF_A: Fq = MontFp!("2531288415884653482390872314076515502283196768958702291122908367011684616813658038203608290467525262791083555673983644818695669580390352210553225220938947324");
+
+    /// COEFF_B = a6 in the docs, which is a very large string.
+    const COEFF_B: Fq = MontFp!("6264057384010175464915182075438605813035088046068005133678247884131485200140879482339270016430630622707165981418415300514449797691104450003717825402255334958");
+
+    /// GENERATOR = (G_GENERATOR_X, G_GENERATOR_Y)
+    const GENERATOR: Affine = Affine::new_unchecked(G_GENERATOR_X, G_GENERATOR_Y);
+}
+
+/// G_GENERATOR_X = 1
+pub const G_GENERATOR_X: Fq = MontFp!("1");
+
+/// G_GENERATOR_Y = 1831050833873048391873665205441271196406080165151970589544216300040954611453346096278422546824282050388795066104166417736670313338711889835069367913126779476
+pub const G_GENERATOR_Y: Fq = MontFp!("1831050833873048391873665205441271196406080165151970589544216300040954611453346096278422546824282050388795066104166417736670313338711889835069367913126779476");
+
+/// G_GENERATOR_X2 = 4
+pub const G_GENERATOR_X2: Fq = MontFp!("4");
+
+/// G_GENERATOR_Y2 = 487063164515838874876874543166274672165620377536793938830306170357274980123918746648376073422635332035313015820088815646295021818830877188396158874230569847
+pub const G_GENERATOR_Y2: Fq = MontFp!("487063164515838874876874543166274672165620377536793938830306170357274980123918746648376073422635332035313015820088815646295021818830877188396158874230569847");
+
+// Now we instantiate everything else.
+derive_boomerang_config!(
+    config: Config,
+    scalar_field: (Fr, FrConfig),
+    limbs: 9,
+    security: 256,
+    other_curve: secp521r1conf,
+    other_base_field: (secp521r1Fq, secp521FqConfig),
+    other_scalar_field: (secp521r1Fr, secp521FrConfig),
+    generator2: (G_GENERATOR_X2, G_GENERATOR_Y2),
+    other_generator2: ("1", "226550527432254644762927155718498869710358906817053425319320865507781004639099725838657309164078643711530506222673069010331048069570407113457901669103973732"),
+    id: T521,
+);
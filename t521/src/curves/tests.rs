@@ -0,0 +1,10 @@
+use crate::{Config, Projective};
+use ark_algebra_test_templates::*;
+use ark_ec::short_weierstrass::{self as sw};
+use ark_secp521r1::Config as secp521r1conf;
+use boomerang_macros::test_boomerang_config;
+
+type OtherProject = sw::Projective<secp521r1conf>;
+
+test_group!(g1; Projective; sw);
+test_boomerang_config!(Config, OtherProject);
@@ -0,0 +1,6 @@
+use ark_ff::fields::{Fp576, MontBackend, MontConfig};
+#[derive(MontConfig)]
+#[modulus = "6864797660130609714981900799081393217269435300143305409394463459185543183397661185305296734178420671269665416453639305952979026956266322795023512882444055113"]
+#[generator = "3"]
+pub struct FqConfig;
+pub type Fq = Fp576<MontBackend<FqConfig, 9>>;
@@ -0,0 +1,7 @@
+use ark_ff::fields::{Fp576, MontBackend, MontConfig};
+
+#[derive(MontConfig)]
+#[modulus = "6864797660130609714981900799081393217269435300143305409394463459185543183397656052122559640661454554977296311391480858037121987999716643812574028291115057151"]
+#[generator = "3"]
+pub struct FrConfig;
+pub type Fr = Fp576<MontBackend<FrConfig, 9>>;
@@ -0,0 +1,25 @@
+#![deny(nonstandard_style)]
+#![forbid(unsafe_code)]
+
+//! This library implements a 521-bit prime order curve whose scalar field is the base field of
+//! NIST P-521, so that P-521 ECDSA signatures can be attested with the ZKAttest protocols.
+//! The curve was found with the CM method (discriminant -28243).
+//!
+//! Curve infomration:
+//! * Base field:   q = 0x200000000000000000000000000000000000000000000000000000000000000002c54be78524c33584f734a266748b2063accf5028e6778dc5056476d0690853249
+//! * Scalar field: r = 0x1ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff
+//!
+//! Note that by "base field" we mean "the characteristic of the underlying finite field" and by "scalar field" we mean
+//! "the order of the curve".
+//!
+//! * Curve equation: y^2 = x^3 + a_4*x + a_6, where
+//!   a_4 = 0xbccac80b8c1379846975f3ff92520b2931f84a53640c5ae2672a5f3f509c7e5d779fbb49989b3310c7f0fc3eab9484d13cd6b2ce16d6eb6a174364db49d8e25afc
+//!   a_6 = 0x1d331dab25d625102f0f94d550c36b21b76a586e242b2e7419a1c3f7f8b12fee917f8512bf1ef999b64ed844361e8cf3a4fc26fe06e29982eefbbc830359b9a5e2e
+
+#[cfg(feature = "r1cs")]
+pub mod constraints;
+mod curves;
+mod fields;
+
+pub use curves::*;
+pub use fields::*;
@@ -0,0 +1,3 @@
+use boomerang_macros::bench_tglv_make_all;
+use tsecp256k1::Config;
+bench_tglv_make_all!(Config, "tsecp256k1");
@@ -12,7 +12,8 @@ use ark_secp256k1::Fr as secp256k1Fr;
 #[allow(unused_imports)]
 use ark_secp256k1::FrConfig as secp256FrConfig;
 #[warn(unused_imports)]
-use boomerang_macros::derive_conversion;
+use boomerang_macros::derive_boomerang_config;
+use boomerang_macros::derive_glv;
 
 #[cfg(test)]
 mod tests;
@@ -49,6 +50,16 @@ impl SWCurveConfig for Config {
     fn mul_by_a(_: Self::BaseField) -> Self::BaseField {
         Self::BaseField::zero()
     }
+
+    /// Scalar multiplication using the GLV endomorphism.
+    fn mul_projective(base: &Projective, scalar: &[u64]) -> Projective {
+        Self::glv_mul(base, scalar)
+    }
+
+    /// Scalar multiplication using the GLV endomorphism.
+    fn mul_affine(base: &Affine, scalar: &[u64]) -> Projective {
+        Self::glv_mul(&(*base).into(), scalar)
+    }
 }
 
 /// G_GENERATOR_X =
@@ -70,22 +81,27 @@ pub const G_GENERATOR_Y2: Fq =
     MontFp!("73163377763031141032501259779738441094247887834941211187427503803434828368457");
 
 // Now we instantiate everything else.
-derive_conversion!(
-    Config,
-    4,
-    128,
-    secp256k1conf,
-    G_GENERATOR_X2,
-    G_GENERATOR_Y2,
-    Fr,
-    FrConfig,
-    secp256k1Fq,
-    secp256k1Fr,
-    secp256FqConfig,
-    secp256FrConfig,
-    Affine,
-    "65485170049033141755572552932091555440395722142984193594072873483228125624049",
-    "73163377763031141032501259779738441094247887834941211187427503803434828368457",
+derive_boomerang_config!(
+    config: Config,
+    scalar_field: (Fr, FrConfig),
+    limbs: 4,
+    security: 128,
+    other_curve: secp256k1conf,
+    other_base_field: (secp256k1Fq, secp256FqConfig),
+    other_scalar_field: (secp256k1Fr, secp256FrConfig),
+    generator2: (G_GENERATOR_X2, G_GENERATOR_Y2),
+    other_generator2: ("65485170049033141755572552932091555440395722142984193594072873483228125624049", "73163377763031141032501259779738441094247887834941211187427503803434828368457"),
+    id: TSecp256k1,
+);
+
+// The GLV endomorphism: (x, y) -> (GLV_BETA * x, y) = GLV_LAMBDA * (x, y), together
+// with a reduced basis (a1, b1), (a2, b2) of the lattice {(x, y) : x + y * GLV_LAMBDA = 0 mod r}.
+derive_glv!(
     Config,
-    Config
+    "60197513588986302554485582024885075108884032450952339817679072026166228089408",
+    "78074008874160198520644763525212887401909906723592317393988542598630163514318",
+    "303414439467246543595250775667605759171",
+    "-64502973549206556628585045361533709077",
+    "64502973549206556628585045361533709077",
+    "367917413016453100223835821029139468248"
 );
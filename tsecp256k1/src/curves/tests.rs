@@ -2,13 +2,15 @@ use crate::{Config, Projective};
 use ark_algebra_test_templates::*;
 use ark_ec::short_weierstrass::{self as sw};
 use ark_secp256k1::Config as secp256k1conf;
-use boomerang_macros::test_acl;
-use boomerang_macros::test_boomerang;
-use boomerang_macros::test_pedersen;
+use boomerang_macros::test_boomerang_config;
+use boomerang_macros::test_glv;
+use boomerang_macros::test_rfc6979;
 
 type OtherProject = sw::Projective<secp256k1conf>;
 
 test_group!(g1; Projective; sw);
-test_pedersen!(tp; Config, OtherProject);
-test_acl!(acl; Config, Config, OtherProject);
-test_boomerang!(boomerang; Config, Config, Config, OtherProject);
+// The T curve of this crate has the fields of its O curve, rather than its scalar field being
+// the base field of the O curve.
+test_boomerang_config!(Config, OtherProject; #[ignore = "the T curve is not paired with its O curve"]);
+test_glv!(glv; Config);
+test_rfc6979!(rfc6979; Config, k256);
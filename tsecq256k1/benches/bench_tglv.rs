@@ -0,0 +1,3 @@
+use boomerang_macros::bench_tglv_make_all;
+use tsecq256k1::Config;
+bench_tglv_make_all!(Config, "tsecq256k1");
@@ -12,7 +12,8 @@ use ark_secq256k1::Fr as secq256k1Fr;
 #[allow(unused_imports)]
 use ark_secq256k1::FrConfig as secq256FrConfig;
 #[warn(unused_imports)]
-use boomerang_macros::derive_conversion;
+use boomerang_macros::derive_boomerang_config;
+use boomerang_macros::derive_glv;
 
 #[cfg(test)]
 mod tests;
@@ -49,6 +50,16 @@ impl SWCurveConfig for Config {
     fn mul_by_a(_: Self::BaseField) -> Self::BaseField {
         Self::BaseField::zero()
     }
+
+    /// Scalar multiplication using the GLV endomorphism.
+    fn mul_projective(base: &Projective, scalar: &[u64]) -> Projective {
+        Self::glv_mul(base, scalar)
+    }
+
+    /// Scalar multiplication using the GLV endomorphism.
+    fn mul_affine(base: &Affine, scalar: &[u64]) -> Projective {
+        Self::glv_mul(&(*base).into(), scalar)
+    }
 }
 
 /// G_GENERATOR_X =
@@ -70,22 +81,27 @@ pub const G_GENERATOR_Y2: Fq =
     MontFp!("18451814157324471123246799073117578780512506837968746855038596379919570627435");
 
 // Now we instantiate everything else.
-derive_conversion!(
-    Config,
-    4,
-    128,
-    secq256k1conf,
-    G_GENERATOR_X2,
-    G_GENERATOR_Y2,
-    Fr,
-    FrConfig,
-    secq256k1Fq,
-    secq256k1Fr,
-    secq256FqConfig,
-    secq256FrConfig,
-    Affine,
-    "66074285972301200297129825078317149928956243591312218514112646334267511651104",
-    "18451814157324471123246799073117578780512506837968746855038596379919570627435",
+derive_boomerang_config!(
+    config: Config,
+    scalar_field: (Fr, FrConfig),
+    limbs: 4,
+    security: 128,
+    other_curve: secq256k1conf,
+    other_base_field: (secq256k1Fq, secq256FqConfig),
+    other_scalar_field: (secq256k1Fr, secq256FrConfig),
+    generator2: (G_GENERATOR_X2, G_GENERATOR_Y2),
+    other_generator2: ("66074285972301200297129825078317149928956243591312218514112646334267511651104", "18451814157324471123246799073117578780512506837968746855038596379919570627435"),
+    id: TSecq256k1,
+);
+
+// The GLV endomorphism: (x, y) -> (GLV_BETA * x, y) = GLV_LAMBDA * (x, y), together
+// with a reduced basis (a1, b1), (a2, b2) of the lattice {(x, y) : x + y * GLV_LAMBDA = 0 mod r}.
+derive_glv!(
     Config,
-    Config
+    "37718080363155996902926221483475020450927657555482586988616620542887997980018",
+    "55594575648329892869085402983802832744385952214688224221778511981742606582254",
+    "64502973549206556628585045361533709078",
+    "-303414439467246543595250775667605759171",
+    "367917413016453100223835821029139468249",
+    "64502973549206556628585045361533709078"
 );